@@ -0,0 +1,29 @@
+resource "kubernetes_manifest" "worker" {
+  manifest = {
+    apiVersion = "apps/v1"
+    kind       = "Deployment"
+    metadata = {
+      name = "worker"
+    }
+    spec = {
+      template = {
+        spec = {
+          containers = [
+            {
+              name  = "worker"
+              image = "example/worker:2.0"
+              # Limits are left to the LimitRange
+              resources = {
+                requests = { cpu = "750m", memory = "768Mi" }
+                limits = {
+                  cpu = "1500m"
+                  memory = "1Gi"
+                }
+              }
+            },
+          ]
+        }
+      }
+    }
+  }
+}
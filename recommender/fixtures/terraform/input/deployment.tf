@@ -0,0 +1,64 @@
+# Storefront, sized by hand until the recommender takes over
+resource "kubernetes_deployment_v1" "web" {
+  metadata {
+    name      = "web"
+    namespace = "shop" // keep in sync with the namespace module
+  }
+
+  spec {
+    replicas = 3
+
+    template {
+      metadata {
+        labels = { app = "web" }
+      }
+
+      spec {
+        container {
+          name  = "nginx"
+          image = "nginx:1.27"
+
+          resources {
+            /* Requests were raised for the Black Friday sale */
+            requests = {
+              cpu    = "500m" # was 250m
+              memory = "256Mi"
+            }
+            limits = { cpu = "1", memory = "512Mi" }
+          }
+        }
+
+        container {
+          name  = "log-shipper"
+          image = "fluent/fluent-bit:3.1"
+
+          resources {
+            limits = {
+              cpu    = var.shipper_cpu
+              memory = "128Mi"
+            }
+          }
+        }
+
+        container {
+          name  = "exporter"
+          image = "nginx/nginx-prometheus-exporter:1.3"
+        }
+      }
+    }
+  }
+}
+
+resource "kubernetes_config_map_v1" "web" {
+  metadata {
+    name      = "web-config"
+    namespace = "shop"
+  }
+
+  data = {
+    "nginx.conf" = <<-EOT
+      # resources { requests = { cpu = "500m" } }
+      worker_processes auto;
+    EOT
+  }
+}
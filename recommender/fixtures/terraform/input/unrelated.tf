@@ -0,0 +1,32 @@
+variable "shipper_cpu" {
+  type    = string
+  default = "100m"
+}
+
+resource "kubernetes_deployment" "api" {
+  metadata {
+    name = "api"
+  }
+
+  spec {
+    template {
+      spec {
+        container {
+          name = "nginx"
+
+          resources {
+            requests = { cpu = "250m", memory = "256Mi" }
+          }
+        }
+      }
+    }
+  }
+}
+
+output "banner" {
+  value = <<EOT
+resources {
+  limits = { cpu = "1" }
+}
+EOT
+}
@@ -0,0 +1,704 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use kube::Client;
+use kube::api::{Api, DynamicObject, Patch, PatchParams};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+#[cfg(feature = "git")]
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "git")]
+use crate::clock::Timezone;
+use crate::clock::{SharedClock, SystemClock};
+#[cfg(feature = "git")]
+use crate::config::UpdaterConfig;
+use crate::error::{KubernetesError, RecommenderError, Result};
+use crate::helm::HelmValues;
+use crate::hpa::HpaTarget;
+use crate::manifest::{ManifestEditor, ManifestWorkload};
+use crate::overlays::EnvironmentOverlays;
+use crate::provenance::{PROVENANCE_ANNOTATION, Provenance};
+use crate::recommender::ResourceRecommendation;
+use crate::sops::SopsConfig;
+#[cfg(feature = "git")]
+use crate::updater::ManifestUpdater;
+use crate::vpa::vpa_resource;
+
+/// Result of applying a set of recommendations
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApplyOutcome {
+    /// Updated container count keyed by `namespace/deployment`
+    pub updated: HashMap<String, usize>,
+    pub branch: Option<String>,
+    pub commit_sha: Option<String>,
+    pub pr_url: Option<String>,
+}
+
+impl ApplyOutcome {
+    /// One-line summary for logs and the TUI result dialog
+    pub fn summary(&self) -> String {
+        match &self.branch {
+            Some(branch) => format!(
+                "Successfully applied recommendations to {} deployment(s) on branch '{}'",
+                self.updated.len(),
+                branch
+            ),
+            None => format!(
+                "Successfully applied recommendations to {} deployment(s)",
+                self.updated.len()
+            ),
+        }
+    }
+}
+
+/// A backend that applies recommendations somewhere
+///
+/// Futures are not required to be `Send` because libgit2 handles are not `Sync`;
+/// run targets on the current thread or a dedicated runtime.
+#[async_trait(?Send)]
+pub trait ApplyTarget: Send {
+    /// Human-readable description used in logs
+    fn describe(&self) -> String;
+
+    /// Apply the recommendations
+    async fn apply(&mut self, recommendations: &[ResourceRecommendation]) -> Result<ApplyOutcome>;
+
+    /// Deployments this target is able to update, None if any workload is
+    ///
+    /// Lets callers skip analyzing workloads whose recommendations would be dropped.
+    async fn known_workloads(&mut self) -> Result<Option<Vec<ManifestWorkload>>> {
+        Ok(None)
+    }
+}
+
+/// Commit updated manifests to a new branch and open a pull request
+#[cfg(feature = "git")]
+pub struct GitPrTarget {
+    updater: ManifestUpdater,
+    base_branch: String,
+}
+
+#[cfg(feature = "git")]
+impl GitPrTarget {
+    pub fn new(config: UpdaterConfig, base_branch: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            updater: ManifestUpdater::new(config)?,
+            base_branch: base_branch.into(),
+        })
+    }
+
+    /// See [`ManifestUpdater::with_cancellation`]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.updater = self.updater.with_cancellation(token);
+        self
+    }
+
+    /// See [`ManifestUpdater::with_clock`]
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.updater = self.updater.with_clock(clock);
+        self
+    }
+
+    /// See [`ManifestUpdater::with_timezone`]
+    pub fn with_timezone(mut self, timezone: Timezone) -> Self {
+        self.updater = self.updater.with_timezone(timezone);
+        self
+    }
+}
+
+#[cfg(feature = "git")]
+#[async_trait(?Send)]
+impl ApplyTarget for GitPrTarget {
+    fn describe(&self) -> String {
+        format!("pull request against branch '{}'", self.base_branch)
+    }
+
+    async fn apply(&mut self, recommendations: &[ResourceRecommendation]) -> Result<ApplyOutcome> {
+        let (branch, commit_sha, pr_url, updated) = self
+            .updater
+            .run_pr_workflow(&self.base_branch, recommendations)
+            .await?;
+
+        Ok(ApplyOutcome {
+            updated,
+            branch: Some(branch),
+            commit_sha: Some(commit_sha),
+            pr_url,
+        })
+    }
+
+    async fn known_workloads(&mut self) -> Result<Option<Vec<ManifestWorkload>>> {
+        self.updater.list_deployments(&self.base_branch).map(Some)
+    }
+}
+
+/// Edit manifests in place in a local checkout
+pub struct LocalDirTarget {
+    editor: ManifestEditor,
+}
+
+impl LocalDirTarget {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        if !root.is_dir() {
+            return Err(RecommenderError::InvalidInput(format!(
+                "Manifest directory does not exist: {}",
+                root.display()
+            )));
+        }
+
+        Ok(Self {
+            editor: ManifestEditor::new(root),
+        })
+    }
+
+    /// Decrypt SOPS-encrypted manifests and re-encrypt the edited values
+    pub fn with_sops(mut self, sops: SopsConfig) -> Self {
+        self.editor = self.editor.with_sops(sops);
+        self
+    }
+
+    /// Only write to the overlays of `overlays`, scaled per environment
+    pub fn with_overlays(mut self, overlays: EnvironmentOverlays) -> Self {
+        self.editor = self.editor.with_overlays(overlays);
+        self
+    }
+
+    /// Write the resources of Deployments mapped in `helm` to their values files
+    pub fn with_helm_values(mut self, helm: HelmValues) -> Self {
+        self.editor = self.editor.with_helm_values(helm);
+        self
+    }
+
+    /// Also set the suggested CPU utilization target on HorizontalPodAutoscalers
+    pub fn with_hpa_targets(mut self) -> Self {
+        self.editor = self.editor.with_hpa_targets();
+        self
+    }
+}
+
+#[async_trait(?Send)]
+impl ApplyTarget for LocalDirTarget {
+    fn describe(&self) -> String {
+        format!("local directory {}", self.editor.root().display())
+    }
+
+    async fn apply(&mut self, recommendations: &[ResourceRecommendation]) -> Result<ApplyOutcome> {
+        let updated = self.editor.apply_recommendations(recommendations)?;
+
+        if updated.is_empty() {
+            return Err(RecommenderError::ApplyError(
+                "No matching deployments found in directory".to_string(),
+            ));
+        }
+
+        Ok(ApplyOutcome {
+            updated,
+            ..Default::default()
+        })
+    }
+
+    async fn known_workloads(&mut self) -> Result<Option<Vec<ManifestWorkload>>> {
+        self.editor.list_deployments().map(Some)
+    }
+}
+
+/// How long to wait for a rollout before patching a workload sharing its PDB
+const ROLLOUT_TIMEOUT: Duration = Duration::from_secs(600);
+
+const ROLLOUT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default field manager of the server-side applied Deployment resources
+pub const FIELD_MANAGER: &str = "k8s-autorightsizing";
+
+/// Patch Deployment resources directly through the Kubernetes API
+///
+/// Resources are set with server-side apply, forcing ownership of the container
+/// resources to the field manager. Containers it set before keep their values
+/// when they have no recommendation this time. Workloads sharing a PodDisruptionBudget are
+/// patched one rollout at a time. Each patched Deployment records the applied
+/// values in its [`PROVENANCE_ANNOTATION`].
+pub struct ClusterTarget {
+    client: Client,
+    hpa_targets: bool,
+    clock: SharedClock,
+    field_manager: String,
+}
+
+impl ClusterTarget {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            hpa_targets: false,
+            clock: SystemClock::shared(),
+            field_manager: FIELD_MANAGER.to_string(),
+        }
+    }
+
+    /// Apply as `field_manager` instead of [`FIELD_MANAGER`]
+    pub fn with_field_manager(mut self, field_manager: impl Into<String>) -> Self {
+        self.field_manager = field_manager.into();
+        self
+    }
+
+    /// Timestamp the recorded provenance with `clock` instead of the system time
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Also set the suggested CPU utilization target on HorizontalPodAutoscalers
+    pub fn with_hpa_targets(mut self) -> Self {
+        self.hpa_targets = true;
+        self
+    }
+
+    /// Wait until every pod of the Deployment runs the latest template and is available
+    async fn wait_for_rollout(&self, namespace: &str, name: &str) -> Result<()> {
+        let api: Api<Deployment> = Api::namespaced(self.client.clone(), namespace);
+        let deadline = tokio::time::Instant::now() + ROLLOUT_TIMEOUT;
+        info!("Waiting for the rollout of {}/{}", namespace, name);
+
+        loop {
+            let deployment = api.get(name).await.map_err(KubernetesError::ApiError)?;
+            if rolled_out(&deployment) {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(RecommenderError::ApplyError(format!(
+                    "Rollout of deployment {}/{} did not finish within {}s, \
+                     not patching the workloads sharing its PodDisruptionBudget",
+                    namespace,
+                    name,
+                    ROLLOUT_TIMEOUT.as_secs()
+                )));
+            }
+            tokio::time::sleep(ROLLOUT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Set the CPU utilization target of the HPA, adding a CPU metric if it
+    /// scales on other metrics only
+    async fn retarget_hpa(&self, namespace: &str, target: &HpaTarget) -> Result<()> {
+        let api: Api<HorizontalPodAutoscaler> = Api::namespaced(self.client.clone(), namespace);
+        let hpa = api
+            .get(&target.name)
+            .await
+            .map_err(KubernetesError::ApiError)?;
+
+        let mut metrics: Vec<serde_json::Value> = hpa
+            .spec
+            .and_then(|spec| spec.metrics)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|metric| serde_json::to_value(metric).unwrap_or_default())
+            .collect();
+        let cpu = json!({
+            "type": "Resource",
+            "resource": {
+                "name": "cpu",
+                "target": {
+                    "type": "Utilization",
+                    "averageUtilization": target.recommended_cpu_utilization,
+                },
+            },
+        });
+        match metrics.iter().position(|m| {
+            m.get("type").and_then(|t| t.as_str()) == Some("Resource")
+                && m.pointer("/resource/name").and_then(|n| n.as_str()) == Some("cpu")
+        }) {
+            Some(index) => metrics[index] = cpu,
+            None => metrics.push(cpu),
+        }
+
+        // Merge patches replace lists whole, hence the full list of metrics
+        let patch = json!({ "spec": { "metrics": metrics } });
+        api.patch(&target.name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+            .map_err(KubernetesError::ApiError)?;
+        Ok(())
+    }
+
+    /// Set `minAllowed` and `maxAllowed` of the VPA's container policies to the
+    /// recommended requests and limits, keeping its other policies
+    async fn bound_vpa(
+        &self,
+        namespace: &str,
+        name: &str,
+        recommendations: &[&ResourceRecommendation],
+    ) -> Result<()> {
+        let api: Api<DynamicObject> =
+            Api::namespaced_with(self.client.clone(), namespace, &vpa_resource());
+        let vpa = api.get(name).await.map_err(KubernetesError::ApiError)?;
+
+        let mut policies: Vec<serde_json::Value> = vpa
+            .data
+            .pointer("/spec/resourcePolicy/containerPolicies")
+            .and_then(|p| p.as_array())
+            .cloned()
+            .unwrap_or_default();
+        for rec in recommendations {
+            let index = match policies.iter().position(|p| {
+                p.get("containerName").and_then(|n| n.as_str()) == Some(rec.container.as_str())
+            }) {
+                Some(index) => index,
+                None => {
+                    policies.push(json!({ "containerName": rec.container }));
+                    policies.len() - 1
+                }
+            };
+            policies[index]["minAllowed"] = json!({
+                "cpu": rec.recommended_cpu_request,
+                "memory": rec.recommended_memory_request,
+            });
+            policies[index]["maxAllowed"] = json!({
+                "cpu": rec.recommended_cpu_limit,
+                "memory": rec.recommended_memory_limit,
+            });
+        }
+
+        // Merge patches replace lists whole, hence the full list of policies
+        let patch = json!({ "spec": { "resourcePolicy": { "containerPolicies": policies } } });
+        api.patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+            .map_err(KubernetesError::ApiError)?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl ApplyTarget for ClusterTarget {
+    fn describe(&self) -> String {
+        "live cluster".to_string()
+    }
+
+    async fn apply(&mut self, recommendations: &[ResourceRecommendation]) -> Result<ApplyOutcome> {
+        // One patch per deployment or VPA covering all of its containers
+        let mut by_deployment: BTreeMap<(&str, &str), Vec<&ResourceRecommendation>> =
+            BTreeMap::new();
+        let mut by_vpa: BTreeMap<(&str, &str), Vec<&ResourceRecommendation>> = BTreeMap::new();
+        for rec in recommendations {
+            match rec.vpa.as_ref().filter(|vpa| vpa.manages()) {
+                Some(vpa) => by_vpa
+                    .entry((rec.namespace.as_str(), vpa.name.as_str()))
+                    .or_default()
+                    .push(rec),
+                None => by_deployment
+                    .entry((rec.namespace.as_str(), rec.deployment.as_str()))
+                    .or_default()
+                    .push(rec),
+            }
+        }
+
+        let mut outcome = ApplyOutcome::default();
+        let applied_at = self.clock.now_utc();
+
+        for ((namespace, name), recs) in by_vpa {
+            self.bound_vpa(namespace, name, &recs).await?;
+            info!("Patched VerticalPodAutoscaler {}/{}", namespace, name);
+            for rec in recs {
+                *outcome
+                    .updated
+                    .entry(format!("{}/{}", namespace, rec.deployment))
+                    .or_insert(0) += 1;
+            }
+        }
+
+        // Unprotected workloads first, then those sharing a PDB next to each other
+        let mut by_deployment: Vec<_> = by_deployment.into_iter().collect();
+        by_deployment.sort_by_key(|(_, recs)| recs[0].pdbs.first().cloned());
+
+        for (i, ((namespace, name), recs)) in by_deployment.iter().enumerate() {
+            let (namespace, name) = (*namespace, *name);
+            if let Some(risk) = &recs[0].disruption_risk {
+                warn!("Patching deployment {}/{}: {}", namespace, name, risk);
+            }
+            let api: Api<Deployment> = Api::namespaced(self.client.clone(), namespace);
+            let live = api.get(name).await.map_err(KubernetesError::ApiError)?;
+            let containers = container_patches(recs, &live, &self.field_manager);
+
+            // Only the fields listed here are owned, containers merge by name
+            let provenance = Provenance::new(applied_at, recs);
+            let patch = json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": {
+                    "name": name,
+                    "namespace": namespace,
+                    "annotations": { PROVENANCE_ANNOTATION: provenance.annotation_value() }
+                },
+                "spec": { "template": { "spec": { "containers": containers } } }
+            });
+
+            let params = PatchParams::apply(&self.field_manager).force();
+            api.patch(name, &params, &Patch::Apply(&patch))
+                .await
+                .map_err(KubernetesError::ApiError)?;
+
+            info!("Patched deployment {}/{}", namespace, name);
+            outcome
+                .updated
+                .insert(format!("{}/{}", namespace, name), recs.len());
+
+            let shares_pdb = by_deployment[i + 1..].iter().any(|((ns, _), next)| {
+                *ns == namespace && next[0].pdbs.iter().any(|pdb| recs[0].pdbs.contains(pdb))
+            });
+            if shares_pdb {
+                self.wait_for_rollout(namespace, name).await?;
+            }
+        }
+
+        if self.hpa_targets {
+            // Containers of a workload share its HPA's target
+            let hpas: BTreeMap<(&str, &str), &HpaTarget> = recommendations
+                .iter()
+                .filter_map(|rec| {
+                    let target = rec.hpa.as_ref().filter(|t| t.changes())?;
+                    Some(((rec.namespace.as_str(), target.name.as_str()), target))
+                })
+                .collect();
+            for ((namespace, name), target) in hpas {
+                self.retarget_hpa(namespace, target).await?;
+                info!(
+                    "Patched HorizontalPodAutoscaler {}/{} to target {}% CPU utilization",
+                    namespace, name, target.recommended_cpu_utilization
+                );
+            }
+        }
+
+        Ok(outcome)
+    }
+}
+
+/// Containers of the server-side apply patch of `live`: the recommended ones, and
+/// every other container whose resources `field_manager` already owns
+///
+/// Applying releases the fields the manager owned but left out, so a container
+/// patched before would lose its resources. Those are sent again as they are.
+fn container_patches(
+    recs: &[&ResourceRecommendation],
+    live: &Deployment,
+    field_manager: &str,
+) -> Vec<serde_json::Value> {
+    let mut containers: Vec<_> = recs
+        .iter()
+        .map(|rec| {
+            json!({
+                "name": rec.container,
+                "resources": {
+                    "requests": {
+                        "cpu": rec.recommended_cpu_request,
+                        "memory": rec.recommended_memory_request,
+                    },
+                    "limits": {
+                        "cpu": rec.recommended_cpu_limit,
+                        "memory": rec.recommended_memory_limit,
+                    },
+                },
+            })
+        })
+        .collect();
+
+    let owned = owned_resources(live, field_manager);
+    let current = live
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.spec.as_ref())
+        .map(|spec| spec.containers.as_slice())
+        .unwrap_or_default();
+    for container in current {
+        if recs.iter().any(|rec| rec.container == container.name) {
+            continue;
+        }
+        let (Some(fields), Some(resources)) = (owned.get(&container.name), &container.resources)
+        else {
+            continue;
+        };
+        let mut patch = serde_json::Map::new();
+        for (section, quantities) in [
+            ("requests", &resources.requests),
+            ("limits", &resources.limits),
+        ] {
+            let values: serde_json::Map<_, _> = quantities
+                .iter()
+                .flatten()
+                .filter(|(resource, _)| {
+                    fields
+                        .pointer(&format!("/f:{}/f:{}", section, resource))
+                        .is_some()
+                })
+                .map(|(resource, quantity)| (resource.clone(), json!(quantity)))
+                .collect();
+            if !values.is_empty() {
+                patch.insert(section.to_string(), values.into());
+            }
+        }
+        if !patch.is_empty() {
+            containers.push(json!({ "name": container.name, "resources": patch }));
+        }
+    }
+    containers
+}
+
+/// `f:resources` fields `field_manager` applied to each container of `deployment`
+fn owned_resources(
+    deployment: &Deployment,
+    field_manager: &str,
+) -> BTreeMap<String, serde_json::Value> {
+    let mut owned = BTreeMap::new();
+    for entry in deployment.metadata.managed_fields.iter().flatten() {
+        if entry.manager.as_deref() != Some(field_manager)
+            || entry.operation.as_deref() != Some("Apply")
+        {
+            continue;
+        }
+        let Some(containers) = entry
+            .fields_v1
+            .as_ref()
+            .and_then(|fields| fields.0.pointer("/f:spec/f:template/f:spec/f:containers"))
+            .and_then(|containers| containers.as_object())
+        else {
+            continue;
+        };
+        // Containers are keyed by their name, e.g. `k:{"name":"app"}`
+        for (key, fields) in containers {
+            let name = key
+                .strip_prefix("k:")
+                .and_then(|key| serde_json::from_str::<serde_json::Value>(key).ok())
+                .and_then(|key| Some(key.get("name")?.as_str()?.to_string()));
+            if let (Some(name), Some(resources)) = (name, fields.get("f:resources")) {
+                owned.insert(name, resources.clone());
+            }
+        }
+    }
+    owned
+}
+
+/// Whether all pods of the Deployment run its latest template and are available
+fn rolled_out(deployment: &Deployment) -> bool {
+    let (Some(spec), Some(status)) = (&deployment.spec, &deployment.status) else {
+        return false;
+    };
+    let desired = spec.replicas.unwrap_or(1);
+    status.observed_generation >= deployment.metadata.generation
+        && status.updated_replicas.unwrap_or(0) == desired
+        && status.replicas.unwrap_or(0) == desired
+        && status.available_replicas.unwrap_or(0) == desired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::UsageStats;
+
+    fn recommendation(container: &str) -> ResourceRecommendation {
+        serde_json::from_value(json!({
+            "namespace": "shop",
+            "deployment": "web",
+            "container": container,
+            "current_cpu_request": null,
+            "current_cpu_limit": null,
+            "current_memory_request": null,
+            "current_memory_limit": null,
+            "recommended_cpu_request": "250m",
+            "recommended_cpu_limit": "500m",
+            "recommended_memory_request": "256Mi",
+            "recommended_memory_limit": "512Mi",
+            "cpu_usage_stats": UsageStats::default(),
+            "memory_usage_stats": UsageStats::default(),
+            "recommendation_reason": "test",
+        }))
+        .unwrap()
+    }
+
+    /// shop/web with `app`, `sidecar` and `istio-proxy` containers, the resources
+    /// of the first two applied by [`FIELD_MANAGER`] before
+    fn live_deployment() -> Deployment {
+        let owned = json!({
+            "f:resources": {
+                "f:requests": { "f:cpu": {}, "f:memory": {} },
+                "f:limits": { "f:cpu": {}, "f:memory": {} },
+            },
+        });
+        let resources = json!({
+            "requests": { "cpu": "100m", "memory": "64Mi", "ephemeral-storage": "1Gi" },
+            "limits": { "cpu": "200m", "memory": "128Mi" },
+        });
+        serde_json::from_value(json!({
+            "metadata": {
+                "name": "web",
+                "namespace": "shop",
+                "managedFields": [
+                    {
+                        "manager": FIELD_MANAGER,
+                        "operation": "Apply",
+                        "fieldsType": "FieldsV1",
+                        "fieldsV1": { "f:spec": { "f:template": { "f:spec": { "f:containers": {
+                            "k:{\"name\":\"app\"}": owned,
+                            "k:{\"name\":\"sidecar\"}": owned,
+                        } } } } },
+                    },
+                    {
+                        "manager": "istio",
+                        "operation": "Apply",
+                        "fieldsType": "FieldsV1",
+                        "fieldsV1": { "f:spec": { "f:template": { "f:spec": { "f:containers": {
+                            "k:{\"name\":\"istio-proxy\"}": owned,
+                        } } } } },
+                    },
+                ],
+            },
+            "spec": {
+                "selector": {},
+                "template": { "spec": { "containers": [
+                    { "name": "app", "resources": resources },
+                    { "name": "sidecar", "resources": resources },
+                    { "name": "istio-proxy", "resources": resources },
+                ] } },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn resends_owned_resources_of_containers_without_recommendation() {
+        let app = recommendation("app");
+        let containers = container_patches(&[&app], &live_deployment(), FIELD_MANAGER);
+
+        assert_eq!(
+            containers,
+            vec![
+                json!({
+                    "name": "app",
+                    "resources": {
+                        "requests": { "cpu": "250m", "memory": "256Mi" },
+                        "limits": { "cpu": "500m", "memory": "512Mi" },
+                    },
+                }),
+                // Only the fields applied before, not the ephemeral storage request
+                json!({
+                    "name": "sidecar",
+                    "resources": {
+                        "requests": { "cpu": "100m", "memory": "64Mi" },
+                        "limits": { "cpu": "200m", "memory": "128Mi" },
+                    },
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_field_managers_do_not_own_resources() {
+        let owned = owned_resources(&live_deployment(), "kubectl");
+        assert!(owned.is_empty());
+
+        let owned = owned_resources(&live_deployment(), FIELD_MANAGER);
+        assert_eq!(owned.keys().collect::<Vec<_>>(), ["app", "sidecar"]);
+    }
+}
@@ -0,0 +1,452 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use kube::api::{ApiResource, DynamicObject, GroupVersionKind, ListParams};
+use kube::{Api, Client};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::cost::HOURS_PER_MONTH;
+use crate::nodes::{MAX_NODE_UTILIZATION, NodeGroupEstimate, NodePoolReport, nodes_to_fit};
+use crate::quantity::Quantity;
+use crate::{KubernetesError::ApiError, RecommenderError, Result};
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Built-in instance types: name, vCPUs, memory in GiB, us-east-1 on-demand $/hour
+const DEFAULT_CATALOG: &[(&str, f64, f64, f64)] = &[
+    ("t3.large", 2.0, 8.0, 0.0832),
+    ("t3.xlarge", 4.0, 16.0, 0.1664),
+    ("t3.2xlarge", 8.0, 32.0, 0.3328),
+    ("m5.large", 2.0, 8.0, 0.096),
+    ("m5.xlarge", 4.0, 16.0, 0.192),
+    ("m5.2xlarge", 8.0, 32.0, 0.384),
+    ("m5.4xlarge", 16.0, 64.0, 0.768),
+    ("m6i.large", 2.0, 8.0, 0.096),
+    ("m6i.xlarge", 4.0, 16.0, 0.192),
+    ("m6i.2xlarge", 8.0, 32.0, 0.384),
+    ("m6i.4xlarge", 16.0, 64.0, 0.768),
+    ("c5.large", 2.0, 4.0, 0.085),
+    ("c5.xlarge", 4.0, 8.0, 0.17),
+    ("c5.2xlarge", 8.0, 16.0, 0.34),
+    ("c5.4xlarge", 16.0, 32.0, 0.68),
+    ("c6i.large", 2.0, 4.0, 0.085),
+    ("c6i.xlarge", 4.0, 8.0, 0.17),
+    ("c6i.2xlarge", 8.0, 16.0, 0.34),
+    ("c6i.4xlarge", 16.0, 32.0, 0.68),
+    ("r5.large", 2.0, 16.0, 0.126),
+    ("r5.xlarge", 4.0, 32.0, 0.252),
+    ("r5.2xlarge", 8.0, 64.0, 0.504),
+    ("r5.4xlarge", 16.0, 128.0, 1.008),
+    ("r6i.large", 2.0, 16.0, 0.126),
+    ("r6i.xlarge", 4.0, 32.0, 0.252),
+    ("r6i.2xlarge", 8.0, 64.0, 0.504),
+    ("r6i.4xlarge", 16.0, 128.0, 1.008),
+];
+
+/// An instance type with its size and price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceType {
+    pub name: String,
+    pub cpu: f64,
+    pub memory_gib: f64,
+    /// On-demand price in dollars per hour
+    pub hourly_price: f64,
+}
+
+impl InstanceType {
+    /// Family of `m6i.large`, i.e. `m6i`
+    pub fn family(&self) -> &str {
+        self.name
+            .split_once('.')
+            .map_or(&self.name, |(family, _)| family)
+    }
+
+    /// Size of `m6i.large`, i.e. `large`
+    pub fn size(&self) -> &str {
+        self.name.split_once('.').map_or("", |(_, size)| size)
+    }
+
+    /// Category of `m6i.large`, i.e. `m`
+    pub fn category(&self) -> &str {
+        let family = self.family();
+        let end = family
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(family.len());
+        &family[..end]
+    }
+
+    fn node_cpu(&self) -> Quantity {
+        Quantity::from_cores(self.cpu)
+    }
+
+    fn node_memory(&self) -> Quantity {
+        Quantity::from_bytes(self.memory_gib * GIB)
+    }
+}
+
+/// Instance types available to the estimate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceCatalog {
+    pub instance_types: Vec<InstanceType>,
+}
+
+impl Default for InstanceCatalog {
+    /// Common general purpose, compute and memory optimized types at us-east-1 prices
+    fn default() -> Self {
+        Self {
+            instance_types: DEFAULT_CATALOG
+                .iter()
+                .map(|&(name, cpu, memory_gib, hourly_price)| InstanceType {
+                    name: name.to_string(),
+                    cpu,
+                    memory_gib,
+                    hourly_price,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl InstanceCatalog {
+    /// Read a YAML catalog, e.g. with the prices of another region
+    ///
+    /// ```yaml
+    /// instance_types:
+    ///   - name: m6g.large
+    ///     cpu: 2
+    ///     memory_gib: 8
+    ///     hourly_price: 0.077
+    /// ```
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            RecommenderError::InvalidInput(format!(
+                "Failed to read instance catalog {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InstanceType> {
+        self.instance_types.iter().find(|t| t.name == name)
+    }
+}
+
+/// A Karpenter NodePool requirement, like a node selector term
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Requirement {
+    pub key: String,
+    pub operator: String,
+    #[serde(default)]
+    pub values: Vec<String>,
+}
+
+impl Requirement {
+    /// Whether `instance` satisfies the requirement
+    ///
+    /// Only instance type, family, category, size and CPU keys are known; other
+    /// keys such as zones or capacity type don't narrow the instance types.
+    fn allows(&self, instance: &InstanceType) -> bool {
+        let cpu = instance.cpu.to_string();
+        let value = match self.key.as_str() {
+            "node.kubernetes.io/instance-type" => instance.name.as_str(),
+            "karpenter.k8s.aws/instance-family" => instance.family(),
+            "karpenter.k8s.aws/instance-category" => instance.category(),
+            "karpenter.k8s.aws/instance-size" => instance.size(),
+            "karpenter.k8s.aws/instance-cpu" => cpu.as_str(),
+            _ => return true,
+        };
+        let number = |v: &String| v.parse::<f64>().ok();
+        match self.operator.as_str() {
+            "In" => self.values.iter().any(|v| v == value),
+            "NotIn" => !self.values.iter().any(|v| v == value),
+            "Gt" => self
+                .values
+                .first()
+                .and_then(number)
+                .is_some_and(|n| instance.cpu > n),
+            "Lt" => self
+                .values
+                .first()
+                .and_then(number)
+                .is_some_and(|n| instance.cpu < n),
+            _ => true,
+        }
+    }
+}
+
+/// Instance constraints and resource limits of a Karpenter NodePool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KarpenterNodePool {
+    pub name: String,
+    pub requirements: Vec<Requirement>,
+    /// Total CPU the pool may provision, unlimited if None
+    pub cpu_limit: Option<Quantity>,
+    /// Total memory the pool may provision, unlimited if None
+    pub memory_limit: Option<Quantity>,
+}
+
+impl KarpenterNodePool {
+    pub fn allows(&self, instance: &InstanceType) -> bool {
+        self.requirements.iter().all(|r| r.allows(instance))
+    }
+
+    /// List the `karpenter.sh/v1` NodePools, empty when Karpenter isn't installed
+    pub async fn list(client: Client) -> Result<Vec<Self>> {
+        let resource =
+            ApiResource::from_gvk(&GroupVersionKind::gvk("karpenter.sh", "v1", "NodePool"));
+        let api: Api<DynamicObject> = Api::all_with(client, &resource);
+        let pools = match api.list(&ListParams::default()).await {
+            Ok(pools) => pools,
+            Err(kube::Error::Api(response)) if response.code == 404 => {
+                debug!("No Karpenter NodePool resource, assuming cluster-autoscaler");
+                return Ok(Vec::new());
+            }
+            Err(e) => return Err(ApiError(e).into()),
+        };
+
+        let pools: Vec<Self> = pools
+            .items
+            .into_iter()
+            .filter_map(|pool| {
+                let name = pool.metadata.name?;
+                let spec = pool.data.get("spec")?;
+                let requirements = spec
+                    .pointer("/template/spec/requirements")
+                    .cloned()
+                    .and_then(|value| {
+                        serde_json::from_value(value)
+                            .inspect_err(|e| {
+                                warn!("Ignoring requirements of NodePool {}: {}", name, e)
+                            })
+                            .ok()
+                    })
+                    .unwrap_or_default();
+                let limit = |key: &str| {
+                    spec.pointer(&format!("/limits/{}", key))
+                        .and_then(|v| v.as_str())
+                        .and_then(|v| Quantity::parse(v).ok())
+                };
+                Some(Self {
+                    cpu_limit: limit("cpu"),
+                    memory_limit: limit("memory"),
+                    name,
+                    requirements,
+                })
+            })
+            .collect();
+        info!("Found {} Karpenter NodePools", pools.len());
+        Ok(pools)
+    }
+}
+
+/// Who provisions the nodes of a group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provisioner {
+    /// Karpenter, which consolidates onto the cheapest fitting instance types
+    Karpenter,
+    /// A fixed-type node group scaled by cluster-autoscaler
+    NodeGroup,
+}
+
+/// Nodes and cost of a node group before and after rightsizing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeGroupImpact {
+    pub node_group: String,
+    pub provisioner: Provisioner,
+    /// Current node count per instance type
+    pub current_nodes: BTreeMap<String, usize>,
+    /// Node count per instance type after rightsizing and consolidation
+    pub nodes_after: BTreeMap<String, usize>,
+    /// None when an instance type is missing from the catalog
+    pub current_hourly_cost: Option<f64>,
+    pub hourly_cost_after: Option<f64>,
+    /// Caveats of the estimate, e.g. an exceeded NodePool limit
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// Predicted effect of applying the recommendations on nodes and cost
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AutoscalingImpact {
+    pub node_groups: Vec<NodeGroupImpact>,
+    /// Sum over the groups with known prices
+    pub current_monthly_cost: f64,
+    pub monthly_cost_after: f64,
+    pub monthly_savings: f64,
+}
+
+/// Estimate the instance mix and cost once the recommendations are merged
+///
+/// Groups matching a Karpenter NodePool are consolidated onto the single cheapest
+/// allowed instance type that fits the largest pod, approximating Karpenter's
+/// consolidation. Other groups keep their instance type and shrink to the node
+/// count of the [`NodePoolReport`], as cluster-autoscaler would scale them down.
+pub fn autoscaling_impact(
+    report: &NodePoolReport,
+    node_pools: &[KarpenterNodePool],
+    catalog: &InstanceCatalog,
+) -> AutoscalingImpact {
+    let mut groups: BTreeMap<&str, Vec<&NodeGroupEstimate>> = BTreeMap::new();
+    for estimate in &report.node_groups {
+        groups
+            .entry(estimate.node_group.as_str())
+            .or_default()
+            .push(estimate);
+    }
+
+    let node_groups: Vec<NodeGroupImpact> = groups
+        .into_iter()
+        .map(
+            |(name, estimates)| match node_pools.iter().find(|p| p.name == name) {
+                Some(pool) => karpenter_impact(pool, &estimates, catalog),
+                None => node_group_impact(name, &estimates, catalog),
+            },
+        )
+        .collect();
+
+    let monthly = |cost: Option<f64>| cost.unwrap_or_default() * HOURS_PER_MONTH;
+    let priced = || {
+        node_groups
+            .iter()
+            .filter(|g| g.current_hourly_cost.is_some() && g.hourly_cost_after.is_some())
+    };
+    let current_monthly_cost: f64 = priced().map(|g| monthly(g.current_hourly_cost)).sum();
+    let monthly_cost_after: f64 = priced().map(|g| monthly(g.hourly_cost_after)).sum();
+
+    AutoscalingImpact {
+        current_monthly_cost,
+        monthly_cost_after,
+        monthly_savings: current_monthly_cost - monthly_cost_after,
+        node_groups,
+    }
+}
+
+/// Sum of `nodes` priced from `catalog`, None if any type is unknown
+fn hourly_cost(nodes: &BTreeMap<String, usize>, catalog: &InstanceCatalog) -> Option<f64> {
+    nodes
+        .iter()
+        .map(|(name, count)| catalog.get(name).map(|t| t.hourly_price * *count as f64))
+        .sum()
+}
+
+fn current_nodes(estimates: &[&NodeGroupEstimate]) -> BTreeMap<String, usize> {
+    let mut nodes = BTreeMap::new();
+    for estimate in estimates {
+        *nodes.entry(estimate.instance_type.clone()).or_default() += estimate.current_nodes;
+    }
+    nodes
+}
+
+/// A cluster-autoscaler group keeps its instance types with fewer nodes
+fn node_group_impact(
+    name: &str,
+    estimates: &[&NodeGroupEstimate],
+    catalog: &InstanceCatalog,
+) -> NodeGroupImpact {
+    let current_nodes = current_nodes(estimates);
+    let mut nodes_after = BTreeMap::new();
+    for estimate in estimates {
+        *nodes_after
+            .entry(estimate.instance_type.clone())
+            .or_default() += estimate.nodes_needed_after.min(estimate.current_nodes);
+    }
+
+    let mut notes = Vec::new();
+    for instance_type in current_nodes.keys() {
+        if catalog.get(instance_type).is_none() {
+            notes.push(format!("{} is not in the instance catalog", instance_type));
+        }
+    }
+
+    NodeGroupImpact {
+        node_group: name.to_string(),
+        provisioner: Provisioner::NodeGroup,
+        current_hourly_cost: hourly_cost(&current_nodes, catalog),
+        hourly_cost_after: hourly_cost(&nodes_after, catalog),
+        current_nodes,
+        nodes_after,
+        notes,
+    }
+}
+
+/// A Karpenter pool consolidates onto the cheapest allowed type fitting its pods
+fn karpenter_impact(
+    pool: &KarpenterNodePool,
+    estimates: &[&NodeGroupEstimate],
+    catalog: &InstanceCatalog,
+) -> NodeGroupImpact {
+    let sum = |f: fn(&NodeGroupEstimate) -> Quantity| {
+        estimates
+            .iter()
+            .fold(Quantity::default(), |total, e| total + f(e))
+    };
+    let max = |f: fn(&NodeGroupEstimate) -> Quantity| {
+        estimates
+            .iter()
+            .map(|e| f(e))
+            .fold(Quantity::default(), |a, b| if b > a { b } else { a })
+    };
+    let cpu = sum(|e| e.recommended_cpu);
+    let memory = sum(|e| e.recommended_memory);
+    let largest_cpu = max(|e| e.largest_pod_cpu);
+    let largest_memory = max(|e| e.largest_pod_memory);
+
+    let current_nodes = current_nodes(estimates);
+    let mut notes = Vec::new();
+
+    let cheapest = catalog
+        .instance_types
+        .iter()
+        .filter(|t| pool.allows(t))
+        .filter(|t| {
+            largest_cpu.value() <= t.cpu * MAX_NODE_UTILIZATION
+                && largest_memory.value() <= t.memory_gib * GIB * MAX_NODE_UTILIZATION
+        })
+        .map(|t| {
+            let nodes = nodes_to_fit(cpu, memory, t.node_cpu(), t.node_memory());
+            (t, nodes, t.hourly_price * nodes as f64)
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2));
+
+    let mut nodes_after = BTreeMap::new();
+    match cheapest {
+        Some((instance_type, nodes, _)) => {
+            nodes_after.insert(instance_type.name.clone(), nodes);
+            let provisioned_cpu = instance_type.cpu * nodes as f64;
+            let provisioned_memory = instance_type.memory_gib * GIB * nodes as f64;
+            if pool.cpu_limit.is_some_and(|l| provisioned_cpu > l.value())
+                || pool
+                    .memory_limit
+                    .is_some_and(|l| provisioned_memory > l.value())
+            {
+                notes.push(format!(
+                    "{} x {} exceeds the NodePool limits",
+                    nodes, instance_type.name
+                ));
+            }
+        }
+        None => {
+            notes.push(
+                "No catalog instance type satisfies the NodePool requirements and fits the largest pod"
+                    .to_string(),
+            );
+        }
+    }
+
+    NodeGroupImpact {
+        node_group: pool.name.clone(),
+        provisioner: Provisioner::Karpenter,
+        current_hourly_cost: hourly_cost(&current_nodes, catalog),
+        hourly_cost_after: (!nodes_after.is_empty())
+            .then(|| hourly_cost(&nodes_after, catalog))
+            .flatten(),
+        current_nodes,
+        nodes_after,
+        notes,
+    }
+}
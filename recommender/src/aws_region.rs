@@ -1,18 +1,22 @@
 use std::fmt;
 use std::str::FromStr;
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 /// AWS Regions as documented in https://docs.aws.amazon.com/general/latest/gr/rande.html
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Regions launched after this list was written parse into [`AwsRegion::Other`].
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AwsRegion {
     // US Regions
-    UsEast1,      // US East (N. Virginia)
-    UsEast2,      // US East (Ohio)
-    UsWest1,      // US West (N. California)
-    UsWest2,      // US West (Oregon)
-    
+    UsEast1, // US East (N. Virginia)
+    UsEast2, // US East (Ohio)
+    UsWest1, // US West (N. California)
+    UsWest2, // US West (Oregon)
+
     // Africa
-    AfSouth1,     // Africa (Cape Town)
-    
+    AfSouth1, // Africa (Cape Town)
+
     // Asia Pacific
     ApEast1,      // Asia Pacific (Hong Kong)
     ApEast2,      // Asia Pacific (Taipei)
@@ -28,40 +32,55 @@ pub enum AwsRegion {
     ApSoutheast5, // Asia Pacific (Malaysia)
     ApSoutheast6, // Asia Pacific (New Zealand)
     ApSoutheast7, // Asia Pacific (Thailand)
-    
+
     // Canada
-    CaCentral1,   // Canada (Central)
-    CaWest1,      // Canada West (Calgary)
-    
+    CaCentral1, // Canada (Central)
+    CaWest1,    // Canada West (Calgary)
+
     // Europe
-    EuCentral1,   // Europe (Frankfurt)
-    EuCentral2,   // Europe (Zurich)
-    EuWest1,      // Europe (Ireland)
-    EuWest2,      // Europe (London)
-    EuWest3,      // Europe (Paris)
-    EuNorth1,     // Europe (Stockholm)
-    EuSouth1,     // Europe (Milan)
-    EuSouth2,     // Europe (Spain)
-    
+    EuCentral1, // Europe (Frankfurt)
+    EuCentral2, // Europe (Zurich)
+    EuWest1,    // Europe (Ireland)
+    EuWest2,    // Europe (London)
+    EuWest3,    // Europe (Paris)
+    EuNorth1,   // Europe (Stockholm)
+    EuSouth1,   // Europe (Milan)
+    EuSouth2,   // Europe (Spain)
+
     // Israel
-    IlCentral1,   // Israel (Tel Aviv)
-    
+    IlCentral1, // Israel (Tel Aviv)
+
     // Mexico
-    MxCentral1,   // Mexico (Central)
-    
+    MxCentral1, // Mexico (Central)
+
     // Middle East
-    MeSouth1,     // Middle East (Bahrain)
-    MeCentral1,   // Middle East (UAE)
-    
+    MeSouth1,   // Middle East (Bahrain)
+    MeCentral1, // Middle East (UAE)
+
     // South America
-    SaEast1,      // South America (São Paulo)
-    
+    SaEast1, // South America (São Paulo)
+
     // AWS GovCloud
-    UsGovEast1,   // AWS GovCloud (US-East)
-    UsGovWest1,   // AWS GovCloud (US-West)
+    UsGovEast1, // AWS GovCloud (US-East)
+    UsGovWest1, // AWS GovCloud (US-West)
+
+    // Any other well-formed region name (new regions, other partitions)
+    Other(String),
 }
 
 impl AwsRegion {
+    /// Accept a region that is not (yet) listed, e.g. `eu-west-9` or `cn-north-1`
+    pub fn custom(name: &str) -> Result<Self, String> {
+        if is_valid_region_name(name) {
+            Ok(AwsRegion::Other(name.to_string()))
+        } else {
+            Err(format!(
+                "Invalid AWS region: '{}'. Expected a name like 'us-east-1', see https://docs.aws.amazon.com/general/latest/gr/rande.html",
+                name
+            ))
+        }
+    }
+
     pub fn as_str(&self) -> &str {
         match self {
             AwsRegion::UsEast1 => "us-east-1",
@@ -100,6 +119,7 @@ impl AwsRegion {
             AwsRegion::SaEast1 => "sa-east-1",
             AwsRegion::UsGovEast1 => "us-gov-east-1",
             AwsRegion::UsGovWest1 => "us-gov-west-1",
+            AwsRegion::Other(name) => name,
         }
     }
 }
@@ -151,10 +171,37 @@ impl FromStr for AwsRegion {
             "sa-east-1" => Ok(AwsRegion::SaEast1),
             "us-gov-east-1" => Ok(AwsRegion::UsGovEast1),
             "us-gov-west-1" => Ok(AwsRegion::UsGovWest1),
-            _ => Err(format!(
-                "Invalid AWS region: '{}'. See https://docs.aws.amazon.com/general/latest/gr/rande.html for valid regions",
-                s
-            )),
+            _ => AwsRegion::custom(s),
         }
     }
 }
+
+impl Serialize for AwsRegion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for AwsRegion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Region names look like `<area>[-<partition>]-<direction>-<number>`, e.g. `us-gov-west-1`
+fn is_valid_region_name(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('-').collect();
+    let (Some(area), Some(number)) = (parts.first(), parts.last()) else {
+        return false;
+    };
+
+    parts.len() >= 3
+        && area.len() == 2
+        && area.chars().all(|c| c.is_ascii_lowercase())
+        && !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit())
+        && parts[1..parts.len() - 1]
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_lowercase()))
+}
@@ -0,0 +1,436 @@
+//! Replay history to compare recommendation strategies
+//!
+//! Recommendations are sized from a training window that ends `holdout` before
+//! the end of the replay, then checked against the usage of the holdout window:
+//! how often the recommended limits would have been exceeded (simulated OOM kills
+//! and CPU throttling) versus how much of the requests would have gone unused.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::clock::FixedClock;
+use crate::config::{MemoryMetric, RecommenderConfig};
+use crate::error::Result;
+use crate::kubernetes::DeploymentResources;
+use crate::lookback::LookbackOverrides;
+use crate::metric_variants::MetricVariants;
+use crate::metrics::MetricsProvider;
+use crate::output::PercentileConfig;
+use crate::recommender::{Recommender, ResourceRecommendation, RetainedUsage};
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Percentiles and margin to backtest, unset fields keep the configured ones
+///
+/// Parsed from comma-separated `key=value` pairs, e.g.
+/// `cpu=90/99,memory=95/99.9,margin=1.1`, where `cpu` and `memory` take the
+/// request and limit percentile separated by a slash.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    /// How the candidate was given, used as its name in the report
+    pub spec: String,
+    pub cpu_request: Option<f64>,
+    pub cpu_limit: Option<f64>,
+    pub memory_request: Option<f64>,
+    pub memory_limit: Option<f64>,
+    pub safety_margin: Option<f64>,
+}
+
+impl Candidate {
+    /// Percentiles of `base` overridden by the ones the candidate sets
+    pub fn resolve(&self, base: &PercentileConfig) -> PercentileConfig {
+        PercentileConfig {
+            cpu_request: self.cpu_request.unwrap_or(base.cpu_request),
+            cpu_limit: self.cpu_limit.unwrap_or(base.cpu_limit),
+            memory_request: self.memory_request.unwrap_or(base.memory_request),
+            memory_limit: self.memory_limit.unwrap_or(base.memory_limit),
+            safety_margin: self.safety_margin.unwrap_or(base.safety_margin),
+        }
+    }
+}
+
+impl FromStr for Candidate {
+    type Err = String;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let percentile = |value: &str| -> std::result::Result<f64, String> {
+            let percentile: f64 = value
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid percentile {:?}: {}", value, e))?;
+            if (0.0..=100.0).contains(&percentile) {
+                Ok(percentile)
+            } else {
+                Err(format!(
+                    "percentile must be between 0 and 100, got {}",
+                    value
+                ))
+            }
+        };
+        let pair = |value: &str| -> std::result::Result<(f64, f64), String> {
+            let (request, limit) = value
+                .split_once('/')
+                .ok_or_else(|| format!("expected REQUEST/LIMIT percentiles, got {}", value))?;
+            Ok((percentile(request)?, percentile(limit)?))
+        };
+
+        let mut candidate = Candidate {
+            spec: spec.trim().to_string(),
+            ..Candidate::default()
+        };
+        for field in spec.split(',').filter(|field| !field.trim().is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("expected KEY=VALUE, got {}", field.trim()))?;
+            match key.trim() {
+                "cpu" => {
+                    let (request, limit) = pair(value)?;
+                    candidate.cpu_request = Some(request);
+                    candidate.cpu_limit = Some(limit);
+                }
+                "memory" => {
+                    let (request, limit) = pair(value)?;
+                    candidate.memory_request = Some(request);
+                    candidate.memory_limit = Some(limit);
+                }
+                "margin" => {
+                    let margin: f64 = value
+                        .trim()
+                        .parse()
+                        .map_err(|e| format!("invalid margin {:?}: {}", value, e))?;
+                    if !margin.is_finite() || margin <= 0.0 {
+                        return Err(format!("margin must be greater than 0, got {}", value));
+                    }
+                    candidate.safety_margin = Some(margin);
+                }
+                other => {
+                    return Err(format!(
+                        "unknown key {:?}, expected cpu, memory or margin",
+                        other
+                    ));
+                }
+            }
+        }
+        if candidate.spec.is_empty() {
+            return Err("empty candidate".to_string());
+        }
+        Ok(candidate)
+    }
+}
+
+/// How one candidate would have fared over the holdout window
+///
+/// Rates are shares of the holdout samples above the recommended limit, over all
+/// evaluated containers. CPU is in cores and memory in bytes; waste is the
+/// recommended request above the average holdout usage, summed over containers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateResult {
+    pub candidate: String,
+    pub percentiles: PercentileConfig,
+    /// Share of memory samples above the limit, i.e. simulated OOM kills
+    pub oom_rate: f64,
+    /// Share of CPU samples above the limit, i.e. simulated throttling
+    pub throttle_rate: f64,
+    /// Containers with at least one memory sample above the limit
+    pub oom_containers: usize,
+    /// Containers with at least one CPU sample above the limit
+    pub throttled_containers: usize,
+    pub recommended_cpu: f64,
+    pub recommended_memory: f64,
+    pub wasted_cpu: f64,
+    pub wasted_memory: f64,
+}
+
+impl CandidateResult {
+    /// Share of the recommended CPU requests left unused on average
+    pub fn cpu_headroom(&self) -> f64 {
+        share(self.wasted_cpu, self.recommended_cpu)
+    }
+
+    /// Share of the recommended memory requests left unused on average
+    pub fn memory_headroom(&self) -> f64 {
+        share(self.wasted_memory, self.recommended_memory)
+    }
+}
+
+fn share(part: f64, total: f64) -> f64 {
+    if total > 0.0 { part / total } else { 0.0 }
+}
+
+/// Outcome of a backtest, one result per candidate in the order given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BacktestReport {
+    /// End of the training window and start of the holdout
+    pub trained_until: DateTime<Utc>,
+    pub holdout_hours: f64,
+    pub lookback_hours: f64,
+    /// Containers with usage in both windows
+    pub containers: usize,
+    /// Containers left out for lack of usage in either window
+    pub skipped_containers: usize,
+    pub candidates: Vec<CandidateResult>,
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Backtest of {} containers: trained on {}h until {}, evaluated on the following {}h",
+            self.containers,
+            self.lookback_hours,
+            self.trained_until.to_rfc3339(),
+            self.holdout_hours
+        )?;
+        if self.skipped_containers > 0 {
+            writeln!(
+                f,
+                "{} containers skipped for lack of usage in either window",
+                self.skipped_containers
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(
+            f,
+            "| Candidate | Percentiles | OOM rate | OOM containers | Throttle rate | Throttled containers | CPU headroom | Memory headroom | Wasted CPU | Wasted memory |"
+        )?;
+        write!(f, "|---|---|---:|---:|---:|---:|---:|---:|---:|---:|")?;
+        for result in &self.candidates {
+            let p = &result.percentiles;
+            write!(
+                f,
+                "\n| {} | p{}/p{} CPU, p{}/p{} memory, {:.2}x | {:.3}% | {} | {:.3}% | {} | {:.1}% | {:.1}% | {:.2} | {:.2}Gi |",
+                result.candidate,
+                p.cpu_request,
+                p.cpu_limit,
+                p.memory_request,
+                p.memory_limit,
+                p.safety_margin,
+                result.oom_rate * 100.0,
+                result.oom_containers,
+                result.throttle_rate * 100.0,
+                result.throttled_containers,
+                result.cpu_headroom() * 100.0,
+                result.memory_headroom() * 100.0,
+                result.wasted_cpu,
+                result.wasted_memory / GIB,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Replays the usage history of workloads against candidate percentiles
+///
+/// Training uses the configured lookback, outlier filter and queries. Forecasts
+/// and day splits are left out, as they can't be sized again per candidate.
+pub struct Backtest {
+    metrics: Arc<dyn MetricsProvider>,
+    config: RecommenderConfig,
+    holdout: Duration,
+    candidates: Vec<Candidate>,
+    /// Namespace the metric variants are detected in, None for all namespaces
+    namespace: Option<String>,
+    cancel: CancellationToken,
+}
+
+impl Backtest {
+    /// Evaluate recommendations against the `holdout` following their training window
+    pub fn new(
+        metrics: Arc<dyn MetricsProvider>,
+        config: RecommenderConfig,
+        holdout: Duration,
+    ) -> Self {
+        Self {
+            metrics,
+            config,
+            holdout,
+            candidates: Vec::new(),
+            namespace: None,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Candidates to compare, the configured percentiles alone when none are given
+    pub fn with_candidates(mut self, candidates: impl IntoIterator<Item = Candidate>) -> Self {
+        self.candidates.extend(candidates);
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// See [`Recommender::with_cancellation`]
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Train and evaluate over the `holdout` ending at `end`
+    pub async fn run(
+        &self,
+        deployments: Vec<DeploymentResources>,
+        end: DateTime<Utc>,
+    ) -> Result<BacktestReport> {
+        let trained_until = end
+            - chrono::Duration::from_std(self.holdout).unwrap_or_else(|_| chrono::Duration::zero());
+
+        let mut config = self.config.clone();
+        if config.memory_metric == MemoryMetric::Auto {
+            match MetricVariants::detect(
+                self.metrics.as_ref(),
+                self.namespace.as_deref(),
+                end.into(),
+            )
+            .await
+            {
+                Ok(variants) => variants.apply(&mut config),
+                Err(e) => warn!(
+                    "Failed to detect metric variants, using the defaults: {}",
+                    e
+                ),
+            }
+        }
+        if config.forecast.take().is_some() {
+            warn!("Forecasting is not backtested, sizing from the training usage");
+        }
+        if config.day_split.take().is_some() {
+            warn!("Day splits are not backtested, sizing from the whole week");
+        }
+        config.retain_usage = true;
+
+        // The holdout is measured as is, spikes included
+        let mut holdout_config = config.clone();
+        holdout_config.lookback_hours = self.holdout.as_secs_f64() / 3600.0;
+        holdout_config.lookback_overrides = LookbackOverrides::default();
+        holdout_config.outlier_filter = None;
+
+        info!(
+            "Training on {} hours until {}",
+            config.lookback_hours,
+            trained_until.to_rfc3339()
+        );
+        let (trained, _) = Recommender::with_provider(self.metrics.clone(), config.clone())
+            .with_clock(Arc::new(FixedClock::at(trained_until)))
+            .with_cancellation(self.cancel.clone())
+            .analyze(deployments.clone())
+            .await?;
+
+        info!(
+            "Evaluating on {} hours until {}",
+            holdout_config.lookback_hours,
+            end.to_rfc3339()
+        );
+        let (holdout, _) = Recommender::with_provider(self.metrics.clone(), holdout_config)
+            .with_clock(Arc::new(FixedClock::at(end)))
+            .with_cancellation(self.cancel.clone())
+            .analyze(deployments.clone())
+            .await?;
+        let observed: HashMap<(&str, &str, &str), &RetainedUsage> = holdout
+            .iter()
+            .filter_map(|rec| Some((container_key(rec), rec.usage.as_deref()?)))
+            .collect();
+
+        let pairs: Vec<(&ResourceRecommendation, &RetainedUsage)> = trained
+            .iter()
+            .filter(|rec| rec.usage.is_some())
+            .filter_map(|rec| Some((rec, *observed.get(&container_key(rec))?)))
+            .collect();
+        let total_containers: usize = deployments.iter().map(|d| d.containers.len()).sum();
+        info!(
+            "Backtesting {} of {} containers",
+            pairs.len(),
+            total_containers
+        );
+
+        let base = config.percentiles();
+        let candidates = if self.candidates.is_empty() {
+            vec![Candidate {
+                spec: "configured".to_string(),
+                ..Candidate::default()
+            }]
+        } else {
+            self.candidates.clone()
+        };
+
+        Ok(BacktestReport {
+            trained_until,
+            holdout_hours: self.holdout.as_secs_f64() / 3600.0,
+            lookback_hours: config.lookback_hours,
+            containers: pairs.len(),
+            skipped_containers: total_containers.saturating_sub(pairs.len()),
+            candidates: candidates
+                .iter()
+                .map(|candidate| evaluate(candidate, candidate.resolve(&base), &pairs))
+                .collect(),
+        })
+    }
+}
+
+fn container_key(rec: &ResourceRecommendation) -> (&str, &str, &str) {
+    (&rec.namespace, &rec.deployment, &rec.container)
+}
+
+/// Size each trained recommendation for `percentiles` and score it on its holdout
+fn evaluate(
+    candidate: &Candidate,
+    percentiles: PercentileConfig,
+    pairs: &[(&ResourceRecommendation, &RetainedUsage)],
+) -> CandidateResult {
+    let mut result = CandidateResult {
+        candidate: candidate.spec.clone(),
+        percentiles,
+        oom_rate: 0.0,
+        throttle_rate: 0.0,
+        oom_containers: 0,
+        throttled_containers: 0,
+        recommended_cpu: 0.0,
+        recommended_memory: 0.0,
+        wasted_cpu: 0.0,
+        wasted_memory: 0.0,
+    };
+    let (mut memory_samples, mut memory_over) = (0.0, 0.0);
+    let (mut cpu_samples, mut cpu_over) = (0.0, 0.0);
+
+    for (trained, holdout) in pairs {
+        let Some(rec) = trained.retune(&result.percentiles) else {
+            continue;
+        };
+
+        let memory = holdout.memory.count() as f64;
+        let over = holdout
+            .memory
+            .fraction_above(rec.recommended_memory_limit.value());
+        memory_samples += memory;
+        memory_over += over * memory;
+        result.oom_containers += usize::from(over > 0.0);
+
+        // Throttling shows at the resolution the limit was sized from
+        let cpu_usage = holdout.cpu_burst.as_ref().unwrap_or(&holdout.cpu);
+        let cpu = cpu_usage.count() as f64;
+        let over = cpu_usage.fraction_above(rec.recommended_cpu_limit.value());
+        cpu_samples += cpu;
+        cpu_over += over * cpu;
+        result.throttled_containers += usize::from(over > 0.0);
+
+        let cpu_request = rec.recommended_cpu_request.value();
+        let memory_request = rec.recommended_memory_request.value();
+        result.recommended_cpu += cpu_request;
+        result.recommended_memory += memory_request;
+        result.wasted_cpu += (cpu_request - holdout.cpu.finish().avg).max(0.0);
+        result.wasted_memory += (memory_request - holdout.memory.finish().avg).max(0.0);
+    }
+
+    result.oom_rate = share(memory_over, memory_samples);
+    result.throttle_rate = share(cpu_over, cpu_samples);
+    result
+}
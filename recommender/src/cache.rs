@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::to_unix_seconds;
+use crate::{MetricsProvider, RecommenderError, Result, Series};
+
+/// Series of one query with the time range they cover
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedQuery {
+    /// Unix timestamps in seconds
+    pub start: f64,
+    pub end: f64,
+    pub series: Vec<Series>,
+}
+
+/// Raw samples of previous runs, keyed by query and step
+///
+/// Percentiles can't be merged, so the samples themselves are kept and
+/// trimmed to the lookback window on every run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsCache {
+    pub queries: BTreeMap<String, CachedQuery>,
+}
+
+impl MetricsCache {
+    /// Read a cache file, starting empty if it is missing or unreadable
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Ignoring invalid metrics cache {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(|e| {
+            RecommenderError::Other(format!("Failed to serialize metrics cache: {}", e))
+        })?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+/// Metrics backend wrapper that only queries the part of a window not cached yet
+///
+/// When a cached entry covers the start of the requested window, only the time
+/// since its end is queried and merged; otherwise the full window is fetched.
+/// Only queries made during this run are persisted, so removed workloads age out.
+pub struct CachingMetricsProvider {
+    inner: Arc<dyn MetricsProvider>,
+    path: PathBuf,
+    previous: MetricsCache,
+    current: Mutex<MetricsCache>,
+}
+
+impl CachingMetricsProvider {
+    /// Wrap `inner`, loading previous results from `path`
+    pub fn new(inner: Arc<dyn MetricsProvider>, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let cache = MetricsCache::load(&path);
+        info!(
+            "Loaded {} cached queries from {}",
+            cache.queries.len(),
+            path.display()
+        );
+
+        Self {
+            inner,
+            path,
+            previous: cache,
+            current: Mutex::default(),
+        }
+    }
+
+    /// Write the merged results back for the next run
+    pub fn persist(&self) -> Result<()> {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .save(&self.path)
+    }
+}
+
+#[async_trait]
+impl MetricsProvider for CachingMetricsProvider {
+    async fn query_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+    ) -> Result<Vec<Series>> {
+        let key = format!("{}@{}s", query, step.as_secs());
+        let window_start = to_unix_seconds(start);
+        let window_end = to_unix_seconds(end);
+
+        let cached = self
+            .previous
+            .queries
+            .get(&key)
+            .filter(|c| c.start <= window_start && c.end >= window_start && c.end <= window_end)
+            .cloned();
+
+        let entry = match cached {
+            Some(mut cached) => {
+                debug!(
+                    "Querying {:.0}s not covered by the cache for: {}",
+                    window_end - cached.end,
+                    query
+                );
+                let fresh_start = SystemTime::UNIX_EPOCH + Duration::from_secs_f64(cached.end);
+                let fresh = self
+                    .inner
+                    .query_range(query, fresh_start, end, step)
+                    .await?;
+                merge_series(&mut cached.series, fresh, cached.end);
+                cached.end = window_end;
+                cached
+            }
+            None => CachedQuery {
+                start: window_start,
+                end: window_end,
+                series: self.inner.query_range(query, start, end, step).await?,
+            },
+        };
+
+        let entry = trim_to_window(entry, window_start);
+        let series = entry.series.clone();
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .queries
+            .insert(key, entry);
+        Ok(series)
+    }
+
+    /// Instant queries aren't cached, a single sample has nothing to merge
+    async fn query(&self, query: &str, time: SystemTime) -> Result<Vec<Series>> {
+        self.inner.query(query, time).await
+    }
+}
+
+/// Append samples after `since` to the series with the same labels
+fn merge_series(cached: &mut Vec<Series>, fresh: Vec<Series>, since: f64) {
+    for series in fresh {
+        let samples = series.samples.into_iter().filter(|s| s.timestamp > since);
+        match cached.iter_mut().find(|c| c.labels == series.labels) {
+            Some(existing) => existing.samples.extend(samples),
+            None => cached.push(Series {
+                labels: series.labels,
+                samples: samples.collect(),
+            }),
+        }
+    }
+}
+
+/// Drop samples older than the lookback window, and series left empty
+fn trim_to_window(mut entry: CachedQuery, start: f64) -> CachedQuery {
+    for series in &mut entry.series {
+        series.samples.retain(|s| s.timestamp >= start);
+    }
+    entry.series.retain(|s| !s.samples.is_empty());
+    entry.start = start;
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sample;
+    use std::collections::HashMap;
+
+    fn series(pod: &str, timestamps: &[f64]) -> Series {
+        Series {
+            labels: HashMap::from([("pod".to_string(), pod.to_string())]),
+            samples: timestamps
+                .iter()
+                .map(|&timestamp| Sample {
+                    timestamp,
+                    value: 1.0,
+                })
+                .collect(),
+        }
+    }
+
+    fn timestamps(series: &Series) -> Vec<f64> {
+        series.samples.iter().map(|s| s.timestamp).collect()
+    }
+
+    #[test]
+    fn merge_appends_new_samples_by_labels() {
+        let mut cached = vec![series("a", &[0.0, 60.0, 120.0])];
+        let fresh = vec![
+            // The boundary sample is already cached
+            series("a", &[120.0, 180.0]),
+            // A pod that started since the last run
+            series("b", &[120.0, 180.0]),
+        ];
+
+        merge_series(&mut cached, fresh, 120.0);
+
+        assert_eq!(cached.len(), 2);
+        assert_eq!(timestamps(&cached[0]), [0.0, 60.0, 120.0, 180.0]);
+        assert_eq!(cached[1].labels["pod"], "b");
+        assert_eq!(timestamps(&cached[1]), [180.0]);
+    }
+
+    #[test]
+    fn trim_drops_samples_and_series_before_window() {
+        let entry = CachedQuery {
+            start: 0.0,
+            end: 180.0,
+            series: vec![series("a", &[0.0, 60.0, 120.0]), series("b", &[0.0, 60.0])],
+        };
+
+        let entry = trim_to_window(entry, 120.0);
+
+        assert_eq!(entry.start, 120.0);
+        assert_eq!(entry.end, 180.0);
+        let [series] = entry.series.as_slice() else {
+            panic!("expected one series, got {:?}", entry.series);
+        };
+        assert_eq!(series.labels["pod"], "a");
+        assert_eq!(timestamps(series), [120.0]);
+    }
+
+    /// Backend answering instant queries natively, unlike its range queries
+    struct InstantOnly;
+
+    #[async_trait]
+    impl MetricsProvider for InstantOnly {
+        async fn query_range(
+            &self,
+            _query: &str,
+            _start: SystemTime,
+            _end: SystemTime,
+            _step: Duration,
+        ) -> Result<Vec<Series>> {
+            Ok(Vec::new())
+        }
+
+        async fn query(&self, _query: &str, time: SystemTime) -> Result<Vec<Series>> {
+            Ok(vec![series("a", &[to_unix_seconds(time)])])
+        }
+    }
+
+    #[tokio::test]
+    async fn forwards_instant_queries_to_the_backend() {
+        // Never persisted, a missing file starts an empty cache
+        let provider = CachingMetricsProvider::new(Arc::new(InstantOnly), "missing/cache.json");
+
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(600);
+        let result = provider.query("up", time).await.unwrap();
+
+        let [series] = result.as_slice() else {
+            panic!("expected one series, got {:?}", result);
+        };
+        assert_eq!(timestamps(series), [600.0]);
+    }
+}
@@ -0,0 +1,103 @@
+//! Staged apply, canary workloads first and the rest in a later run
+//!
+//! A canary run applies only the workloads its [`CanarySelector`] picks and
+//! records them in the run history. A later rollout run applies every other
+//! workload, once the canaries have proven themselves.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kubernetes::DeploymentResources;
+
+/// Deployment annotation putting it in the canary group, e.g. `"true"`
+pub const CANARY_ANNOTATION: &str = "autorightsizing.io/canary";
+
+/// Which part of a staged apply a run was
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyStage {
+    /// Every allowed recommendation, without staging
+    All,
+    /// The workloads picked by a [`CanarySelector`]
+    Canary,
+    /// The workloads left out by the preceding canary run
+    Rollout,
+}
+
+impl fmt::Display for ApplyStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyStage::All => write!(f, "all"),
+            ApplyStage::Canary => write!(f, "canary"),
+            ApplyStage::Rollout => write!(f, "rollout"),
+        }
+    }
+}
+
+/// Workloads applied in the canary stage
+///
+/// A workload is a canary when any of the criteria picks it. Percentage picks
+/// hash the workload's namespace and name, so reruns pick the same workloads
+/// and raising the percentage only adds to them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CanarySelector {
+    pub namespaces: Vec<String>,
+    /// Share of all workloads, 0 to 100
+    pub percent: Option<f64>,
+    /// Pick Deployments with the [`CANARY_ANNOTATION`]
+    pub annotated: bool,
+}
+
+impl CanarySelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_namespaces(mut self, namespaces: Vec<String>) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    pub fn with_percent(mut self, percent: f64) -> Self {
+        self.percent = Some(percent);
+        self
+    }
+
+    pub fn with_annotated(mut self, annotated: bool) -> Self {
+        self.annotated = annotated;
+        self
+    }
+
+    /// Whether no criterion is set, which picks nothing
+    pub fn is_empty(&self) -> bool {
+        self.namespaces.is_empty() && self.percent.is_none() && !self.annotated
+    }
+
+    /// Whether the workload `namespace/name` is a canary, `deployment` being its
+    /// Deployment when it was listed
+    pub fn selects(
+        &self,
+        namespace: &str,
+        name: &str,
+        deployment: Option<&DeploymentResources>,
+    ) -> bool {
+        self.namespaces.iter().any(|ns| ns == namespace)
+            || (self.annotated && deployment.is_some_and(|d| d.canary))
+            || self
+                .percent
+                .is_some_and(|percent| workload_bucket(namespace, name) < percent * 100.0)
+    }
+}
+
+/// Stable bucket from 0 to 9999 of a workload
+///
+/// FNV-1a, as the standard library hashers may change between releases.
+fn workload_bucket(namespace: &str, name: &str) -> f64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in namespace.bytes().chain([b'/']).chain(name.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash % 10_000) as f64
+}
@@ -0,0 +1,326 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+use crate::kubernetes::DeploymentResources;
+use crate::quantity::{Quantity, display_optional};
+use crate::recommender::ResourceRecommendation;
+
+/// Team of workloads without the chargeback label
+pub const UNLABELED: &str = "unlabeled";
+
+const MIB: f64 = 1024.0 * 1024.0;
+const GIB: f64 = 1024.0 * MIB;
+
+/// File format of a chargeback report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ChargebackFormat {
+    #[default]
+    Markdown,
+    Html,
+    Csv,
+}
+
+impl ChargebackFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ChargebackFormat::Markdown => "md",
+            ChargebackFormat::Html => "html",
+            ChargebackFormat::Csv => "csv",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ChargebackFormat::Markdown => "text/markdown; charset=utf-8",
+            ChargebackFormat::Html => "text/html; charset=utf-8",
+            ChargebackFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+}
+
+/// One container's requests now and after rightsizing, across all replicas
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChargebackLine {
+    pub namespace: String,
+    pub deployment: String,
+    pub container: String,
+    pub replicas: u64,
+    #[serde(with = "crate::quantity::optional")]
+    pub current_cpu_request: Option<Quantity>,
+    pub recommended_cpu_request: Quantity,
+    #[serde(with = "crate::quantity::optional")]
+    pub current_memory_request: Option<Quantity>,
+    pub recommended_memory_request: Quantity,
+    /// Cores freed over all replicas, negative when the container needs more
+    pub cpu_savings: f64,
+    /// Bytes freed over all replicas, negative when the container needs more
+    pub memory_savings: f64,
+}
+
+/// Recommendations of one team, the value of the chargeback label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeamReport {
+    pub team: String,
+    pub lines: Vec<ChargebackLine>,
+    /// Cores freed over all of the team's workloads
+    pub cpu_savings: f64,
+    /// Bytes freed over all of the team's workloads
+    pub memory_savings: f64,
+}
+
+/// Group recommendations by the value of `label` on their deployment
+///
+/// Workloads without the label are reported under [`UNLABELED`]. Savings count
+/// every replica and treat a container without a current request as unchanged.
+pub fn chargeback(
+    recommendations: &[ResourceRecommendation],
+    deployments: &[DeploymentResources],
+    label: &str,
+) -> Vec<TeamReport> {
+    let deployments: HashMap<(&str, &str), &DeploymentResources> = deployments
+        .iter()
+        .map(|d| ((d.namespace.as_str(), d.name.as_str()), d))
+        .collect();
+
+    let mut teams: BTreeMap<String, TeamReport> = BTreeMap::new();
+    for rec in recommendations {
+        let deployment = deployments.get(&(rec.namespace.as_str(), rec.deployment.as_str()));
+        let team = deployment
+            .and_then(|d| d.labels.get(label))
+            .map_or(UNLABELED, String::as_str);
+        let replicas = deployment
+            .and_then(|d| d.replicas)
+            .map_or(1, |r| r.max(0) as u64);
+
+        let savings = |current: Option<Quantity>, recommended: Quantity| {
+            current.map_or(0.0, |c| (c.value() - recommended.value()) * replicas as f64)
+        };
+        let line = ChargebackLine {
+            namespace: rec.namespace.clone(),
+            deployment: rec.deployment.clone(),
+            container: rec.container.clone(),
+            replicas,
+            current_cpu_request: rec.current_cpu_request,
+            recommended_cpu_request: rec.recommended_cpu_request,
+            current_memory_request: rec.current_memory_request,
+            recommended_memory_request: rec.recommended_memory_request,
+            cpu_savings: savings(rec.current_cpu_request, rec.recommended_cpu_request),
+            memory_savings: savings(rec.current_memory_request, rec.recommended_memory_request),
+        };
+
+        let report = teams.entry(team.to_string()).or_insert_with(|| TeamReport {
+            team: team.to_string(),
+            lines: Vec::new(),
+            cpu_savings: 0.0,
+            memory_savings: 0.0,
+        });
+        report.cpu_savings += line.cpu_savings;
+        report.memory_savings += line.memory_savings;
+        report.lines.push(line);
+    }
+
+    teams.into_values().collect()
+}
+
+/// Render the sections of `reports` as one document
+pub fn render_chargeback(reports: &[TeamReport], format: ChargebackFormat) -> String {
+    match format {
+        ChargebackFormat::Markdown => {
+            let mut out = String::from("# Rightsizing chargeback\n");
+            for report in reports {
+                out.push('\n');
+                out.push_str(&report.render(format));
+            }
+            out
+        }
+        ChargebackFormat::Html => {
+            let mut out = String::from(
+                "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Rightsizing chargeback</title></head>\n<body>\n<h1>Rightsizing chargeback</h1>\n",
+            );
+            for report in reports {
+                out.push_str(&report.render(format));
+            }
+            out.push_str("</body>\n</html>\n");
+            out
+        }
+        ChargebackFormat::Csv => {
+            let mut out = String::from(CSV_HEADER);
+            for report in reports {
+                out.push_str(&report.csv_rows());
+            }
+            out
+        }
+    }
+}
+
+/// File name and content of one file per team plus `all-teams.<ext>` with every section
+pub fn chargeback_files(reports: &[TeamReport], format: ChargebackFormat) -> Vec<(String, String)> {
+    reports
+        .iter()
+        .map(|report| {
+            (
+                format!("{}.{}", file_name(&report.team), format.extension()),
+                render_chargeback(std::slice::from_ref(report), format),
+            )
+        })
+        .chain(std::iter::once((
+            format!("all-teams.{}", format.extension()),
+            render_chargeback(reports, format),
+        )))
+        .collect()
+}
+
+/// Write the [`chargeback_files`] of `reports` into `dir`
+///
+/// Returns the number of team files written.
+pub fn write_chargeback(
+    reports: &[TeamReport],
+    format: ChargebackFormat,
+    dir: &Path,
+) -> Result<usize> {
+    std::fs::create_dir_all(dir)?;
+    for (name, content) in chargeback_files(reports, format) {
+        std::fs::write(dir.join(name), content)?;
+    }
+    Ok(reports.len())
+}
+
+const CSV_HEADER: &str = "team,namespace,deployment,container,replicas,current_cpu_request,recommended_cpu_request,cpu_savings_cores,current_memory_request,recommended_memory_request,memory_savings_bytes\n";
+
+impl TeamReport {
+    /// Section of this team, without a document header
+    pub fn render(&self, format: ChargebackFormat) -> String {
+        match format {
+            ChargebackFormat::Markdown => self.markdown(),
+            ChargebackFormat::Html => self.html(),
+            ChargebackFormat::Csv => format!("{}{}", CSV_HEADER, self.csv_rows()),
+        }
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "Over-provisioned by {:.2} CPU cores and {} memory across {} containers",
+            self.cpu_savings,
+            format_bytes(self.memory_savings),
+            self.lines.len()
+        )
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = format!("## Team `{}`\n\n{}\n\n", self.team, self.summary());
+        out.push_str("| Namespace | Deployment | Container | Replicas | CPU request | Recommended | Memory request | Recommended | CPU freed | Memory freed |\n");
+        out.push_str("|---|---|---|---:|---:|---:|---:|---:|---:|---:|\n");
+        for line in &self.lines {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} | {} | {:.3} | {} |",
+                line.namespace,
+                line.deployment,
+                line.container,
+                line.replicas,
+                display_optional(line.current_cpu_request),
+                line.recommended_cpu_request,
+                display_optional(line.current_memory_request),
+                line.recommended_memory_request,
+                line.cpu_savings,
+                format_bytes(line.memory_savings)
+            );
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let mut out = format!(
+            "<h2>Team {}</h2>\n<p>{}</p>\n<table>\n<tr><th>Namespace</th><th>Deployment</th><th>Container</th><th>Replicas</th><th>CPU request</th><th>Recommended</th><th>Memory request</th><th>Recommended</th><th>CPU freed</th><th>Memory freed</th></tr>\n",
+            escape_html(&self.team),
+            escape_html(&self.summary())
+        );
+        for line in &self.lines {
+            let cells = [
+                line.namespace.clone(),
+                line.deployment.clone(),
+                line.container.clone(),
+                line.replicas.to_string(),
+                display_optional(line.current_cpu_request),
+                line.recommended_cpu_request.to_string(),
+                display_optional(line.current_memory_request),
+                line.recommended_memory_request.to_string(),
+                format!("{:.3}", line.cpu_savings),
+                format_bytes(line.memory_savings),
+            ];
+            out.push_str("<tr>");
+            for cell in cells {
+                let _ = write!(out, "<td>{}</td>", escape_html(&cell));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+        out
+    }
+
+    fn csv_rows(&self) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            let fields = [
+                self.team.clone(),
+                line.namespace.clone(),
+                line.deployment.clone(),
+                line.container.clone(),
+                line.replicas.to_string(),
+                display_optional(line.current_cpu_request),
+                line.recommended_cpu_request.to_string(),
+                format!("{:.3}", line.cpu_savings),
+                display_optional(line.current_memory_request),
+                line.recommended_memory_request.to_string(),
+                format!("{:.0}", line.memory_savings),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| escape_csv(f)).collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Bytes as `Mi` or `Gi` with a sign, e.g. `-256Mi`
+pub(crate) fn format_bytes(bytes: f64) -> String {
+    if bytes.abs() >= GIB {
+        format!("{:.1}Gi", bytes / GIB)
+    } else {
+        format!("{:.0}Mi", bytes / MIB)
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub(crate) fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Team name usable as a file name
+fn file_name(team: &str) -> String {
+    team.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
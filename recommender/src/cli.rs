@@ -0,0 +1,1039 @@
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use clap::{Args, CommandFactory, Parser, Subcommand, error::ErrorKind};
+use url::Url;
+
+use crate::AwsRegion;
+use crate::apply::FIELD_MANAGER;
+use crate::backtest::Candidate;
+use crate::chargeback::ChargebackFormat;
+use crate::clock::Timezone;
+use crate::config::{
+    DEFAULT_MAX_CONCURRENT_QUERIES, DEFAULT_PUSH_JOB, DaySplit, ForecastMethod, GitProvider,
+    MemoryMetric, MergeMethod, NotifyFormat, OciReference, OutlierAction, PrometheusAuth,
+    PublishTarget, S3Location, VpaMode, VpaUpdateMode, WorkloadDiscovery,
+};
+use crate::config_file::{config_path, merge_config_file};
+use crate::filter::LabelSelector;
+use crate::quantity::Quantity;
+
+/// Kubernetes Resource Recommender
+///
+/// Analyzes pod resource usage from AWS Managed Prometheus and generates
+/// rightsizing recommendations.
+#[derive(Parser, Debug)]
+#[command(
+    name = "recommender",
+    author,
+    version,
+    about,
+    styles = get_styles(),
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// YAML or TOML file with values for the flags below, e.g. `lookback-hours: 336`
+    ///
+    /// Keys are long flag names; flags given on the command line win over the
+    /// file. Subcommand flags can't be set in it.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Amazon Managed Prometheus workspace endpoint
+    ///
+    /// Any Prometheus-compatible endpoint works with `--prometheus-auth`, e.g. the
+    /// query frontend of Thanos or Mimir.
+    #[arg(
+        long,
+        value_name = "URL",
+        visible_alias = "prometheus-url",
+        required_unless_present = "from_metrics"
+    )]
+    pub amp_url: Option<Url>,
+
+    /// AWS Region
+    #[arg(
+        short,
+        long,
+        required_unless_present_any = ["from_metrics", "prometheus_auth"]
+    )]
+    pub region: Option<AwsRegion>,
+
+    /// How to authenticate to the Prometheus endpoint
+    ///
+    /// `sigv4` signs requests for AMP with the default AWS credentials and needs
+    /// `--region`. `gcp` uses Workload Identity, the metadata server or the
+    /// application default credentials for Google Cloud Managed Service for
+    /// Prometheus. `azure` uses `AZURE_CLIENT_SECRET`, AKS workload identity or the
+    /// managed identity for Azure Monitor managed Prometheus. `basic` and `bearer`
+    /// suit self-hosted Prometheus, Thanos or Mimir.
+    #[arg(long, value_enum, value_name = "MODE", default_value = "sigv4")]
+    pub prometheus_auth: PrometheusAuth,
+
+    /// Username for `--prometheus-auth basic`
+    #[arg(long, value_name = "USERNAME")]
+    pub prometheus_username: Option<String>,
+
+    /// Password for `--prometheus-auth basic`
+    #[arg(long, value_name = "PASSWORD")]
+    pub prometheus_password: Option<String>,
+
+    /// Token for `--prometheus-auth bearer`
+    #[arg(long, value_name = "TOKEN")]
+    pub prometheus_token: Option<String>,
+
+    /// Enable verbose output
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Suppress log output to stdout/stderr (logs still written to file)
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Provide context name
+    ///
+    /// Use if you have multiple clusters in your kubeconfig
+    #[arg(long)]
+    pub context: Option<String>,
+
+    /// Namespace to scan workloads for rightsizing
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// Only analyze Deployments matching this label selector, e.g. `app=foo,tier!=cache`
+    ///
+    /// Supports `=`, `!=`, `in (...)`, `notin (...)`, `key` and `!key` like kubectl.
+    #[arg(long, value_name = "SELECTOR")]
+    pub selector: Option<LabelSelector>,
+
+    /// Only analyze Deployments whose name matches this glob, e.g. `api-*`
+    ///
+    /// Patterns with a `/` match `namespace/name`. Repeat to include several.
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Leave out Deployments whose name matches this glob, even if included
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// Also analyze CronJobs and Jobs not created by one, listed from the cluster
+    ///
+    /// They are sized for the peak of their completed runs rather than
+    /// percentiles of mostly idle samples. Their recommendations are reported
+    /// but not applied.
+    #[arg(long)]
+    pub include_jobs: bool,
+
+    /// Where workloads and their current requests and limits come from
+    ///
+    /// `kube-state-metrics` reads them from its series in Prometheus and needs no
+    /// kubeconfig; features that talk to the cluster are unavailable then.
+    #[arg(long, value_enum, value_name = "SOURCE", default_value = "cluster")]
+    pub discovery: WorkloadDiscovery,
+
+    /// Output format: table (default), json, csv, markdown or vpa
+    #[arg(long, value_name = "FORMAT", default_value = "table")]
+    pub output: OutputFormat,
+
+    /// Lookback period in hours for recommendations (default: 168 = 7 days, supports decimals)
+    #[arg(long, default_value = "168.0")]
+    pub lookback_hours: f64,
+
+    /// YAML file of lookbacks for specific namespaces or deployments
+    ///
+    /// Entries list a `namespace` and/or `deployment` with their `lookback_hours`;
+    /// the most specific match wins. A Deployment's
+    /// `autorightsizing.io/lookback-hours` annotation wins over both.
+    #[arg(long, value_name = "PATH")]
+    pub lookback_overrides: Option<PathBuf>,
+
+    /// End the lookback window at this RFC 3339 time instead of now
+    ///
+    /// Reproduces a past run, e.g. `--as-of 2025-01-31T00:00:00Z`.
+    #[arg(long, value_name = "TIME")]
+    pub as_of: Option<DateTime<Utc>>,
+
+    /// Zone of report timestamps, branch names and commit messages
+    ///
+    /// `utc`, `local` for the system zone, or a fixed offset like `+08:00`.
+    /// JSON timestamps stay RFC 3339 and carry the offset.
+    #[arg(long, value_name = "TZ", default_value = "utc")]
+    pub timezone: Timezone,
+
+    /// CPU percentile for request recommendations (default: 95)
+    #[arg(long, default_value = "95.0")]
+    pub cpu_request_percentile: f64,
+
+    /// CPU percentile for limit recommendations (default: 99)
+    #[arg(long, default_value = "99.0")]
+    pub cpu_limit_percentile: f64,
+
+    /// Memory percentile for request recommendations (default: 95)
+    #[arg(long, default_value = "95.0")]
+    pub memory_request_percentile: f64,
+
+    /// Memory percentile for limit recommendations (default: 99)
+    #[arg(long, default_value = "99.0")]
+    pub memory_limit_percentile: f64,
+
+    /// Safety margin multiplier for recommendations (default: 1.2 = 20% buffer)
+    #[arg(long, default_value = "1.2")]
+    pub safety_margin: f64,
+
+    /// Floor of recommended CPU requests, e.g. `10m`
+    ///
+    /// Bounds apply after the percentiles, margin, forecast and policies.
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub min_cpu_request: Option<Quantity>,
+
+    /// Cap of recommended CPU requests
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub max_cpu_request: Option<Quantity>,
+
+    /// Floor of recommended CPU limits
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub min_cpu_limit: Option<Quantity>,
+
+    /// Cap of recommended CPU limits, which also caps the requests
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub max_cpu_limit: Option<Quantity>,
+
+    /// Floor of recommended memory requests, e.g. `32Mi`
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub min_memory_request: Option<Quantity>,
+
+    /// Cap of recommended memory requests
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub max_memory_request: Option<Quantity>,
+
+    /// Floor of recommended memory limits
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub min_memory_limit: Option<Quantity>,
+
+    /// Cap of recommended memory limits, which also caps the requests
+    #[arg(long, value_name = "QUANTITY", value_parser = parse_quantity)]
+    pub max_memory_limit: Option<Quantity>,
+
+    /// Resolution of the usage queries in seconds, picked from the lookback if not set
+    ///
+    /// Defaults to 60 up to 6h, 300 up to 7 days, 900 up to 30 days and 3600 beyond.
+    #[arg(long, value_name = "SECONDS")]
+    pub query_step_seconds: Option<u64>,
+
+    /// Range of `rate()` in the CPU query in seconds
+    #[arg(long, value_name = "SECONDS", default_value = "300")]
+    pub rate_window_seconds: u64,
+
+    /// Wider rate window in seconds, retried when the first covers under half the lookback
+    ///
+    /// Scrape gaps and counter resets leave short windows with too few points. The
+    /// window used is reported per recommendation. 0 disables the retry.
+    #[arg(long, value_name = "SECONDS", default_value = "900")]
+    pub fallback_rate_window_seconds: u64,
+
+    /// Sample metrics-server when Prometheus has no usage for a container
+    ///
+    /// Also covers workloads created within the lookback window. Recommendations
+    /// from the live sample get a warning and a confidence near 0.
+    #[arg(long)]
+    pub metrics_server_fallback: bool,
+
+    /// How long to sample metrics-server for with `--metrics-server-fallback`
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "60",
+        requires = "metrics_server_fallback"
+    )]
+    pub metrics_server_window_seconds: u64,
+
+    /// Exclude or cap short usage spikes, e.g. retry storms during an incident
+    ///
+    /// A spike is a run of steps where the busiest pod's usage is far above the
+    /// median, lasting at most `--outlier-max-duration-seconds`. The spike windows
+    /// are listed in the recommendation reason. Spikes are kept if not set.
+    #[arg(long, value_enum, value_name = "ACTION")]
+    pub outliers: Option<OutlierAction>,
+
+    /// Robust standard deviations above the median usage has to reach to be a spike
+    #[arg(long, default_value = "10", requires = "outliers")]
+    pub outlier_threshold: f64,
+
+    /// Longest spike in seconds, longer runs of high usage are real load and kept
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "1800",
+        requires = "outliers"
+    )]
+    pub outlier_max_duration_seconds: u64,
+
+    /// Size for usage projected `--forecast-weeks` ahead instead of the lookback
+    ///
+    /// A trend is fitted through the per-step mean usage and requests and limits
+    /// are scaled by the projected growth; shrinking usage leaves them unchanged.
+    /// Holt-Winters keeps daily cycles out of the trend. Needs a lookback of at
+    /// least two days.
+    #[arg(long, value_enum, value_name = "METHOD")]
+    pub forecast: Option<ForecastMethod>,
+
+    /// Weeks ahead to project usage to
+    #[arg(long, value_name = "WEEKS", default_value = "4", requires = "forecast")]
+    pub forecast_weeks: f64,
+
+    /// Warn about workloads whose usage grows faster than this many percent per week
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value = "10",
+        requires = "forecast"
+    )]
+    pub growth_warning_percent: f64,
+
+    /// Handling of workloads whose VerticalPodAutoscaler changes their requests
+    ///
+    /// `skip` leaves them out, `compare` recommends next to the VPA's target but
+    /// never applies to them, and `update-vpa` applies by setting the VPA's
+    /// minAllowed to the recommended requests and maxAllowed to the limits.
+    /// VPAs in `Off` mode only recommend and never count as managing.
+    #[arg(long, value_enum, value_name = "MODE", default_value = "skip")]
+    pub vpa: VpaMode,
+
+    /// Update mode of the VPAs printed by `--output vpa`
+    ///
+    /// `off` only bounds the VPA's recommendation to ours, `auto` lets the VPA
+    /// evict pods to set it.
+    #[arg(long, value_enum, value_name = "MODE", default_value = "off")]
+    pub vpa_update_mode: VpaUpdateMode,
+
+    /// Publish recommendations on each analyzed Deployment, e.g. `annotations,events`
+    ///
+    /// Annotations go on the Deployment's own metadata, so no rollout is triggered.
+    /// Both show up in `kubectl describe deployment`.
+    #[arg(long, value_enum, value_name = "TARGET", value_delimiter = ',')]
+    pub publish: Vec<PublishTarget>,
+
+    /// Write the whole JSON output to this ConfigMap (namespace/name) for other controllers
+    ///
+    /// The document is stored under the `recommendations.json` key and replaced on
+    /// every run.
+    #[arg(long, value_name = "NAMESPACE/NAME", conflicts_with = "from_metrics")]
+    pub publish_configmap: Option<String>,
+
+    /// Push the whole JSON output as an OCI artifact, e.g. `ghcr.io/org/rightsizing:prod`
+    ///
+    /// Pull it with `oras pull`. Prefix the reference with `http://` for a registry
+    /// without TLS.
+    #[arg(long, value_name = "REFERENCE")]
+    pub publish_oci: Option<OciReference>,
+
+    /// Push recommendation metrics to this Prometheus Pushgateway, e.g. `http://pushgateway:9091`
+    ///
+    /// Each run replaces the group of `--push-job` and `--cluster-name`, with
+    /// recommended and requested resources, usage and waste per container.
+    #[arg(long, value_name = "URL")]
+    pub push_gateway: Option<url::Url>,
+
+    /// Job label of the metrics pushed to `--push-gateway`
+    #[arg(
+        long,
+        value_name = "NAME",
+        default_value = DEFAULT_PUSH_JOB,
+        requires = "push_gateway"
+    )]
+    pub push_job: String,
+
+    /// Username for `--publish-oci`, anonymous if not set
+    #[arg(long, value_name = "USERNAME", requires = "oci_password")]
+    pub oci_username: Option<String>,
+
+    /// Password or token for `--publish-oci`
+    #[arg(long, value_name = "PASSWORD", requires = "oci_username")]
+    pub oci_password: Option<String>,
+
+    /// Size weekdays and weekends separately, for workloads with different weekend profiles
+    ///
+    /// `both` emits a recommendation per part of the week, labeled in the output,
+    /// and can't be combined with `--apply`. `max` emits one with the higher
+    /// value of both for each request and limit. Weekends are Saturday and
+    /// Sunday in `--timezone`.
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub day_split: Option<DaySplit>,
+
+    /// Size workloads with strongly daily or weekly usage for their peak hours
+    ///
+    /// Usage is bucketed by hour of day and of week in `--timezone`. When the
+    /// pattern explains enough of the variance, percentiles are taken over the
+    /// busy hours only and the reason lists them.
+    #[arg(long, conflicts_with = "day_split")]
+    pub seasonal: bool,
+
+    /// Share of the usage variance the hour has to explain for `--seasonal` sizing
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value = "50",
+        value_parser = parse_percent,
+        requires = "seasonal"
+    )]
+    pub seasonal_strength_percent: f64,
+
+    /// PromQL for CPU usage in cores instead of the cAdvisor rate
+    ///
+    /// `$namespace`, `$deployment`, `$container`, `$pod` (regex of the
+    /// deployment's pods, of its ReplicaSets when listed from the cluster) and
+    /// `$rate_window` are filled in per container.
+    #[arg(long, value_name = "PROMQL")]
+    pub cpu_query: Option<String>,
+
+    /// `--cpu-query` returns usage summed over the deployment's pods
+    ///
+    /// Samples are divided by `kube_deployment_status_replicas` at the same step to
+    /// get per-pod usage, e.g. for `sum()` queries and recording rules.
+    #[arg(long, requires = "cpu_query")]
+    pub cpu_query_aggregated: bool,
+
+    /// PromQL for memory usage in bytes instead of the cAdvisor working set
+    ///
+    /// Takes the same placeholders as `--cpu-query`.
+    #[arg(long, value_name = "PROMQL")]
+    pub memory_query: Option<String>,
+
+    /// `--memory-query` returns usage summed over the deployment's pods
+    #[arg(long, requires = "memory_query")]
+    pub memory_query_aggregated: bool,
+
+    /// Memory series to size from
+    ///
+    /// `auto` detects pods without `container_memory_working_set_bytes` (some
+    /// runtimes on cgroup v2) and reads usage minus inactive file pages, then RSS,
+    /// for them. It also deduplicates series scraped from both the kubelet's
+    /// cAdvisor and resource metrics endpoints. Ignored with `--memory-query`.
+    #[arg(long, value_enum, value_name = "METRIC", default_value = "auto")]
+    pub memory_metric: MemoryMetric,
+
+    /// Size CPU limits from the peak of a rate over this many seconds in each step
+    ///
+    /// Requests keep using the smoothed `--rate-window-seconds` usage, so bursty
+    /// services get limits above their short bursts instead of being throttled.
+    /// Needs a scrape interval of at most half the window.
+    #[arg(long, value_name = "SECONDS")]
+    pub cpu_burst_window_seconds: Option<u64>,
+
+    /// PromQL for peak CPU cores per pod to size limits from, instead of the cAdvisor burst query
+    ///
+    /// Takes the placeholders of `--cpu-query` plus `$step`, the query step.
+    /// `$rate_window` is the burst window (default: 60s).
+    #[arg(long, value_name = "PROMQL")]
+    pub cpu_limit_query: Option<String>,
+
+    /// Timeout in seconds for each AMP query and git provider API call
+    #[arg(long, value_name = "SECONDS", default_value = "30")]
+    pub http_timeout_seconds: u64,
+
+    /// Maximum AMP queries per second, unlimited if not set
+    ///
+    /// Keeps large runs below the workspace query quota instead of getting throttled.
+    /// Throttled queries are retried and halve the rate for the rest of the run.
+    #[arg(long, value_name = "RPS", value_parser = parse_rate)]
+    pub amp_max_rps: Option<f64>,
+
+    /// Maximum AMP range queries in flight at once
+    ///
+    /// Containers are analyzed in parallel, each running its queries in turn.
+    /// Combine with `--amp-max-rps` to also bound the query rate.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_CONCURRENT_QUERIES)]
+    pub max_concurrent_queries: usize,
+
+    /// Retries of a query that was throttled, failed with HTTP 500/502/503/504 or timed out
+    ///
+    /// Each retry waits for the server's Retry-After, or an exponential backoff
+    /// of about 1s, 2s, 4s, ... with random jitter.
+    #[arg(long, value_name = "N", default_value = "5")]
+    pub query_retries: u32,
+
+    /// Timeout in seconds of each Prometheus request, `--http-timeout-seconds` if not set
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub query_timeout: Option<u64>,
+
+    /// Maximum git provider API calls per second, unlimited if not set
+    #[arg(long, value_name = "RPS", value_parser = parse_rate)]
+    pub git_api_max_rps: Option<f64>,
+
+    /// Cache queried samples in this file and only query new data on the next run
+    ///
+    /// Scheduled runs then fetch just the time since the previous run from AMP.
+    #[arg(long, value_name = "PATH", conflicts_with = "from_metrics")]
+    pub metrics_cache: Option<PathBuf>,
+
+    /// Record this run's requested and recommended totals in a JSON history file
+    ///
+    /// `recommender --history-file PATH trend` reports the waste month over month.
+    #[arg(long, value_name = "PATH")]
+    pub history_file: Option<PathBuf>,
+
+    /// Append this run's recommendations to a JSON-lines journal, one line per workload
+    ///
+    /// Lines are keyed by `--cluster-name`, namespace and deployment. Automatic
+    /// applies are journaled too, as the last applied values for `--show-drift`.
+    #[arg(long, value_name = "PATH")]
+    pub journal: Option<PathBuf>,
+
+    /// Report how far recommendations moved from the last applied values, then exit
+    ///
+    /// Compares with the applies in `--journal` and the provenance annotations of
+    /// the Deployments, and flags resources edited by hand since. Nothing is applied.
+    #[arg(long)]
+    pub show_drift: bool,
+
+    /// Leave out containers whose requests moved less than this from the applied ones
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = parse_percent,
+        default_value = "10",
+        requires = "show_drift"
+    )]
+    pub drift_threshold: f64,
+
+    /// Save the analyzed workloads and every queried series to a JSON snapshot
+    #[arg(long, value_name = "PATH", conflicts_with = "from_metrics")]
+    pub save_metrics: Option<PathBuf>,
+
+    /// Recompute recommendations offline from a snapshot written by `--save-metrics`
+    ///
+    /// Neither the cluster nor AMP are contacted, so percentiles and margins can be
+    /// tuned without re-querying. Cluster features like leader election are unavailable.
+    #[arg(long, value_name = "PATH", conflicts_with = "leader_election")]
+    pub from_metrics: Option<PathBuf>,
+
+    /// YAML file with governance policies evaluated before output and apply
+    #[arg(long, value_name = "PATH")]
+    pub policy_file: Option<PathBuf>,
+
+    /// ConfigMap (namespace/name) holding policies under the `policy.yaml` key
+    #[arg(long, value_name = "NAMESPACE/NAME")]
+    pub policy_configmap: Option<String>,
+
+    /// Add a suggested ResourceQuota per namespace to the output
+    ///
+    /// Sums the recommended requests and limits over each deployment's replicas.
+    #[arg(long)]
+    pub namespace_quotas: bool,
+
+    /// Multiplier on the namespace quota totals, room for rolling-update surge pods
+    #[arg(long, value_name = "FACTOR", default_value = "1.25", value_parser = parse_headroom)]
+    pub quota_headroom: f64,
+
+    /// Write the suggested ResourceQuotas as YAML to this file, implies `--namespace-quotas`
+    #[arg(long, value_name = "PATH")]
+    pub quota_yaml: Option<PathBuf>,
+
+    /// Write a strategic-merge patch per recommended Deployment to DIR and list it in
+    /// DIR's kustomization.yaml
+    ///
+    /// For overlays of GitOps repositories, the base manifests are left alone. An
+    /// existing kustomization.yaml keeps its resources and other patches.
+    #[arg(long, value_name = "DIR")]
+    pub output_kustomize: Option<PathBuf>,
+
+    /// Analyze each namespace in its own pipeline and write one JSON file per namespace to DIR
+    ///
+    /// Files are named `recommendations-<namespace>.json`, listed with their totals
+    /// in `index.json`. The rest of the run works on all namespaces together.
+    #[arg(long, value_name = "DIR")]
+    pub split_namespaces: Option<PathBuf>,
+
+    /// Namespaces analyzed at the same time with `--split-namespaces`
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "4",
+        requires = "split_namespaces"
+    )]
+    pub namespace_concurrency: usize,
+
+    /// Estimate node counts per node group and instance type after rightsizing
+    ///
+    /// Lists the cluster's nodes and running pods, so it needs cluster access.
+    #[arg(long, conflicts_with = "from_metrics")]
+    pub node_report: bool,
+
+    /// Estimate the instance mix and cost after rightsizing, implies `--node-report`
+    ///
+    /// Karpenter NodePools are consolidated onto the cheapest allowed instance type,
+    /// cluster-autoscaler node groups keep their type with fewer nodes.
+    #[arg(long, conflicts_with = "from_metrics")]
+    pub autoscaling_impact: bool,
+
+    /// YAML instance catalog with sizes and hourly prices for `--autoscaling-impact`
+    ///
+    /// Defaults to common m, c, r and t3 types at us-east-1 on-demand prices.
+    #[arg(long, value_name = "PATH", requires = "autoscaling_impact")]
+    pub instance_catalog: Option<PathBuf>,
+
+    /// Dollars per vCPU-hour the estimated savings are priced at
+    ///
+    /// Defaults to on-demand Fargate in us-east-1, like `--memory-hourly-price`.
+    #[arg(long, value_name = "USD", conflicts_with = "pricing_api")]
+    pub cpu_hourly_price: Option<f64>,
+
+    /// Dollars per GiB-hour of memory the estimated savings are priced at
+    #[arg(long, value_name = "USD", conflicts_with = "pricing_api")]
+    pub memory_hourly_price: Option<f64>,
+
+    /// Price the estimated savings at Fargate's rates for `--region` from the AWS Pricing API
+    ///
+    /// Needs `pricing:GetProducts`; the default prices are used when the lookup fails.
+    #[arg(long, requires = "region")]
+    pub pricing_api: bool,
+
+    /// Write per-team chargeback reports, grouping deployments by this label
+    ///
+    /// Workloads without the label are reported under `unlabeled`.
+    #[arg(long, value_name = "LABEL")]
+    pub chargeback_label: Option<String>,
+
+    /// File format of the chargeback reports
+    #[arg(long, value_enum, default_value = "markdown")]
+    pub chargeback_format: ChargebackFormat,
+
+    /// Directory the chargeback reports are written to, one file per team
+    #[arg(long, value_name = "DIR", default_value = "chargeback")]
+    pub chargeback_dir: PathBuf,
+
+    /// Upload the JSON output, chargeback reports and metrics snapshot to S3
+    ///
+    /// Objects are named `<prefix><cluster>/<timestamp>/<file>`, signed with the
+    /// same AWS credentials as the AMP queries.
+    #[arg(long, value_name = "s3://BUCKET/PREFIX/")]
+    pub upload_s3: Option<S3Location>,
+
+    /// Region of the `--upload-s3` bucket, defaults to `--region`
+    #[arg(long, value_name = "REGION", requires = "upload_s3")]
+    pub upload_s3_region: Option<AwsRegion>,
+
+    /// Cluster name in uploaded object keys and the journal, defaults to `--context` or
+    /// the AMP workspace ID
+    #[arg(long, value_name = "NAME")]
+    pub cluster_name: Option<String>,
+
+    /// Exit with status 2 when a container's requests are off by more than
+    /// `--fail-threshold-percent`, for CI gates
+    ///
+    /// Over- and under-provisioned containers both count, those without current
+    /// requests don't. Outputs are written first, nothing is applied.
+    #[arg(long, conflicts_with = "apply")]
+    pub fail_on_change: bool,
+
+    /// Change of the CPU or memory request, in percent of the current one, that fails the run
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value = "10",
+        requires = "fail_on_change"
+    )]
+    pub fail_threshold_percent: u32,
+
+    /// Make changes to the manifest files
+    #[arg(long)]
+    pub apply: bool,
+
+    /// Post a summary of each automatic apply and its pull request to this Slack or Teams webhook
+    ///
+    /// The URL holds the webhook's secret and is never logged, only its host. A
+    /// failed post is logged without failing the run.
+    #[arg(long, value_name = "URL", requires = "apply")]
+    pub notify_webhook: Option<url::Url>,
+
+    /// Payload format of `--notify-webhook`, told from its host if not set
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "notify_webhook")]
+    pub notify_format: Option<NotifyFormat>,
+
+    /// Only analyze workloads that have a manifest in the apply target
+    ///
+    /// Scans the manifest repository or directory before querying metrics.
+    #[arg(long, requires = "apply")]
+    pub only_manifest_workloads: bool,
+
+    /// Only apply to workloads in this namespace, repeatable
+    ///
+    /// Starts a staged apply: the applied workloads are recorded in `--history-file`
+    /// and `--canary-extend` applies the rest in a later run. Automatic apply only.
+    #[arg(
+        long = "canary-namespace",
+        value_name = "NAMESPACE",
+        requires_all = ["apply", "history_file"]
+    )]
+    pub canary_namespaces: Vec<String>,
+
+    /// Only apply to this percentage of the workloads, picked by a stable hash of their name
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        value_parser = parse_percent,
+        requires_all = ["apply", "history_file"]
+    )]
+    pub canary_percent: Option<f64>,
+
+    /// Only apply to Deployments annotated with `autorightsizing.io/canary: "true"`
+    #[arg(long, requires_all = ["apply", "history_file"])]
+    pub canary_annotated: bool,
+
+    /// Apply to the workloads the last canary run in `--history-file` left out
+    #[arg(
+        long,
+        requires_all = ["apply", "history_file"],
+        conflicts_with_all = ["canary_namespaces", "canary_percent", "canary_annotated"]
+    )]
+    pub canary_extend: bool,
+
+    /// Where to apply recommendations: git (pull request), local (directory) or cluster
+    #[arg(long, value_name = "MODE", default_value = "git")]
+    pub apply_mode: ApplyMode,
+
+    /// Field manager of the resources server-side applied by `--apply-mode cluster`
+    ///
+    /// Ownership of the container resources moves to it, so `kubectl diff` and
+    /// GitOps controllers show which values came from the recommender.
+    #[arg(long, value_name = "NAME", default_value = FIELD_MANAGER)]
+    pub field_manager: String,
+
+    /// Location of the manifest files
+    #[arg(long, value_name = "URL")]
+    pub manifest_url: Option<Url>,
+
+    /// Local directory with manifest files, used with `--apply-mode local`
+    #[arg(long, value_name = "PATH")]
+    pub manifest_dir: Option<PathBuf>,
+
+    /// YAML mapping environments to overlay directories, e.g. `overlays/prod`
+    ///
+    /// Recommendations go to the overlay of the `source` environment, and scaled
+    /// by their `scale` to other environments that set one, in one PR.
+    #[arg(long, value_name = "PATH")]
+    pub overlays: Option<PathBuf>,
+
+    /// YAML mapping Deployments of Helm charts to the values files setting their resources
+    ///
+    /// Their recommendations update `resources:` at the mapped key, by default
+    /// `resources`, instead of the chart templates.
+    #[arg(long, value_name = "PATH", conflicts_with = "overlays")]
+    pub helm_values: Option<PathBuf>,
+
+    /// Also set the suggested CPU utilization target on the workloads' HorizontalPodAutoscalers
+    ///
+    /// Targets are suggested for every workload with an HPA; without this flag
+    /// they are only reported.
+    #[arg(long, requires = "apply")]
+    pub apply_hpa_target: bool,
+
+    /// Decrypt SOPS-encrypted manifests and re-encrypt the edited values
+    ///
+    /// Encrypted manifests are skipped without it.
+    #[arg(long)]
+    pub sops: bool,
+
+    /// sops executable used with `--sops`
+    #[arg(long, value_name = "PATH", default_value = "sops", requires = "sops")]
+    pub sops_binary: PathBuf,
+
+    /// age identities for `--sops`, passed to sops as SOPS_AGE_KEY_FILE
+    #[arg(long, value_name = "PATH", requires = "sops")]
+    pub sops_age_key_file: Option<PathBuf>,
+
+    /// Git repository branch to use
+    /// If not specified, the default branch will be used
+    #[arg(long, default_value = "main")]
+    pub git_branch: String,
+
+    /// Git username for authentication
+    /// If not specified, defaults to "git"
+    #[arg(long)]
+    pub git_username: Option<String>,
+
+    /// Git token for private repositories
+    /// If not specified, assumes public repository access
+    #[arg(long)]
+    pub git_token: Option<String>,
+
+    /// Git hosting provider of --manifest-url
+    /// If not specified, it is detected from the URL
+    #[arg(long, value_enum)]
+    pub git_provider: Option<GitProvider>,
+
+    /// Enable auto-merge on created PRs (GitLab: merge when pipeline succeeds)
+    /// The PR merges itself once required checks and approvals pass
+    #[arg(long)]
+    pub auto_merge: bool,
+
+    /// Merge method used with --auto-merge
+    #[arg(long, value_enum, default_value = "merge")]
+    pub merge_method: MergeMethod,
+
+    /// Add a Signed-off-by trailer to commits, for repositories enforcing the DCO
+    #[arg(long)]
+    pub signoff: bool,
+
+    /// Extra trailer appended to commit messages, e.g. "Reviewed-by: Platform Team"
+    /// Can be repeated
+    #[arg(long = "commit-trailer", value_name = "KEY: VALUE", value_parser = parse_trailer)]
+    pub commit_trailers: Vec<(String, String)>,
+
+    /// Enable Lease-based leader election
+    ///
+    /// Use when running multiple replicas so only one instance analyzes and opens PRs
+    #[arg(long)]
+    pub leader_election: bool,
+
+    /// Name of the Lease object used for leader election
+    #[arg(long, default_value = "recommender-leader")]
+    pub lease_name: String,
+
+    /// Namespace of the Lease object used for leader election
+    #[arg(long, default_value = "default")]
+    pub lease_namespace: String,
+
+    /// Lease duration in seconds before another instance may take over
+    #[arg(long, default_value = "30")]
+    pub lease_duration_seconds: u64,
+}
+
+impl Cli {
+    /// Parse the command line over the settings of its `--config` file
+    ///
+    /// Exits with a usage error like [`Parser::parse`] when the file is invalid.
+    pub fn parse_with_config() -> Self {
+        let args: Vec<OsString> = std::env::args_os().collect();
+        let Some(path) = config_path(&args) else {
+            return Self::parse_from(args);
+        };
+        let mut command = Self::command();
+        match merge_config_file(&command, args, &path) {
+            Ok(args) => Self::parse_from(args),
+            Err(e) => command
+                .error(
+                    ErrorKind::InvalidValue,
+                    format!("--config {}: {}", path.display(), e),
+                )
+                .exit(),
+        }
+    }
+}
+
+/// Subcommands, without one recommendations are generated
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Explain why a deployment has no usage data
+    ///
+    /// Checks metric names, the namespace label, pod and container series and
+    /// label cardinality in AMP over the lookback window, e.g.
+    /// `recommender --amp-url URL --region REGION diagnose --deployment web -n shop`.
+    Diagnose(DiagnoseArgs),
+
+    /// Report requested vs recommended resources month over month
+    ///
+    /// Reads the runs recorded with `--history-file`, e.g.
+    /// `recommender --history-file history.json trend --by-label team`.
+    Trend(TrendArgs),
+
+    /// Re-analyze namespaces when deployment webhooks arrive
+    ///
+    /// Receives ArgoCD sync notifications and GitHub `deployment_status` events on
+    /// `POST /webhook` and analyzes the deployed namespace after the soak period,
+    /// applying the results like a regular run, e.g.
+    /// `recommender --amp-url URL --region REGION --output json --apply ... serve`.
+    Serve(ServeArgs),
+
+    /// Compare percentiles and margins against the usage that followed
+    ///
+    /// Sizes recommendations from the lookback window ending `--holdout-hours`
+    /// ago and reports how often the holdout usage would have exceeded their
+    /// limits versus the headroom left unused, e.g.
+    /// `recommender --from-metrics snapshot.json backtest --candidate margin=1.1
+    /// --candidate memory=99/99.9,margin=1.2`.
+    Backtest(BacktestArgs),
+}
+
+/// Arguments of `recommender diagnose`
+#[derive(Args, Debug, Clone)]
+pub struct DiagnoseArgs {
+    /// Deployment to diagnose
+    #[arg(long, value_name = "NAME")]
+    pub deployment: String,
+
+    /// Namespace of the deployment
+    #[arg(short = 'n', long, value_name = "NAMESPACE")]
+    pub namespace: String,
+}
+
+/// Arguments of `recommender trend`
+#[derive(Args, Debug, Clone)]
+pub struct TrendArgs {
+    /// Group by the value of this deployment label instead of the namespace
+    #[arg(long, value_name = "LABEL")]
+    pub by_label: Option<String>,
+
+    /// Only report the most recent months
+    #[arg(long, value_name = "N")]
+    pub months: Option<usize>,
+}
+
+/// Arguments of `recommender serve`
+#[derive(Args, Debug, Clone)]
+pub struct ServeArgs {
+    /// Address to receive webhooks on
+    #[arg(long, value_name = "ADDR", default_value = "0.0.0.0:8080")]
+    pub listen: SocketAddr,
+
+    /// GitHub webhook secret, other senders pass it as `Authorization: Bearer` token
+    #[arg(long, value_name = "SECRET")]
+    pub webhook_secret: Option<String>,
+
+    /// Seconds to wait after a deployment before analyzing its namespace
+    #[arg(long, value_name = "SECONDS", default_value = "600")]
+    pub soak_seconds: u64,
+}
+
+/// Arguments of `recommender backtest`
+#[derive(Args, Debug, Clone)]
+pub struct BacktestArgs {
+    /// Hours of usage after the training window the recommendations are checked against
+    #[arg(long, value_name = "HOURS", default_value = "168")]
+    pub holdout_hours: f64,
+
+    /// Percentiles to compare, e.g. `cpu=90/99,memory=95/99.9,margin=1.1`
+    ///
+    /// `cpu` and `memory` take the request and limit percentiles; anything left
+    /// out keeps the value of the regular flags. Repeat to compare several, the
+    /// configured percentiles alone are backtested when none are given.
+    #[arg(long = "candidate", value_name = "SPEC")]
+    pub candidates: Vec<Candidate>,
+}
+
+/// Output format for the recommender results
+#[derive(Debug, Clone, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Display results in an interactive table (TUI)
+    Table,
+    /// Output results as JSON
+    Json,
+    /// Print one CSV row per recommendation, for spreadsheets
+    Csv,
+    /// Print a Markdown table, for pull request descriptions
+    Markdown,
+    /// Print one VerticalPodAutoscaler per Deployment, for clusters running VPA
+    Vpa,
+}
+
+/// Target for applying recommendations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ApplyMode {
+    /// Commit to a git repository and open a pull request
+    Git,
+    /// Edit manifests in a local directory
+    Local,
+    /// Server-side apply to Deployments in the live cluster
+    Cluster,
+}
+
+/// Parse a quota headroom, which can't shrink the totals below the recommendations
+fn parse_headroom(value: &str) -> Result<f64, String> {
+    let headroom: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if headroom.is_finite() && headroom >= 1.0 {
+        Ok(headroom)
+    } else {
+        Err(format!("must be at least 1.0, got {}", value))
+    }
+}
+
+/// Parse a percentage from 0 to 100
+fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if (0.0..=100.0).contains(&percent) {
+        Ok(percent)
+    } else {
+        Err(format!("must be between 0 and 100, got {}", value))
+    }
+}
+
+/// Parse a resource quantity like `250m` or `512Mi`, which must be positive
+fn parse_quantity(value: &str) -> Result<Quantity, String> {
+    let quantity = Quantity::parse(value).map_err(|e| e.to_string())?;
+    if quantity.value() > 0.0 {
+        Ok(quantity)
+    } else {
+        Err(format!("must be greater than 0, got {}", value))
+    }
+}
+
+/// Parse a request rate, which must be a positive number
+fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if rate.is_finite() && rate > 0.0 {
+        Ok(rate)
+    } else {
+        Err(format!("must be greater than 0, got {}", value))
+    }
+}
+
+/// Parse a `key: value` commit trailer, the key being a single token
+fn parse_trailer(value: &str) -> Result<(String, String), String> {
+    let (key, trailer) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected KEY: VALUE, got {}", value))?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("invalid trailer key {:?}", key));
+    }
+    if trailer.trim().is_empty() || trailer.contains('\n') {
+        return Err(format!("trailer {} needs a single-line value", key));
+    }
+    Ok((key.to_string(), trailer.trim().to_string()))
+}
+
+/// Set color and variants for help description
+///
+/// Thanks to [Praveen Perera](https://stackoverflow.com/a/76916424)
+fn get_styles() -> clap::builder::Styles {
+    clap::builder::Styles::styled()
+        .usage(
+            anstyle::Style::new()
+                .bold()
+                .underline()
+                .fg_color(Some(anstyle::Color::Ansi(anstyle::AnsiColor::Yellow))),
+        )
+        .header(
+            anstyle::Style::new()
+                .bold()
+                .underline()
+                .fg_color(Some(anstyle::Color::Ansi(anstyle::AnsiColor::Yellow))),
+        )
+        .literal(
+            anstyle::Style::new().fg_color(Some(anstyle::Color::Ansi(anstyle::AnsiColor::Green))),
+        )
+        .invalid(
+            anstyle::Style::new()
+                .bold()
+                .fg_color(Some(anstyle::Color::Ansi(anstyle::AnsiColor::Red))),
+        )
+        .error(
+            anstyle::Style::new()
+                .bold()
+                .fg_color(Some(anstyle::Color::Ansi(anstyle::AnsiColor::Red))),
+        )
+        .valid(
+            anstyle::Style::new()
+                .bold()
+                .underline()
+                .fg_color(Some(anstyle::Color::Ansi(anstyle::AnsiColor::Green))),
+        )
+        .placeholder(
+            anstyle::Style::new().fg_color(Some(anstyle::Color::Ansi(anstyle::AnsiColor::White))),
+        )
+}
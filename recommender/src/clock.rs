@@ -0,0 +1,124 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, FixedOffset, Local, Utc};
+
+/// Source of the current time
+///
+/// Components that derive time windows or names from "now" take a clock, so
+/// runs can be anchored in the past and tests get deterministic results.
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> SystemTime;
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.now().into()
+    }
+
+    /// Current time in `timezone`, for anything shown to people
+    fn now_in(&self, timezone: Timezone) -> DateTime<FixedOffset> {
+        timezone.convert(self.now_utc())
+    }
+}
+
+/// Clock shared between components
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The operating system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// Shared handle to the system clock, the default everywhere
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock frozen at a given time, only moving when told to
+#[derive(Debug)]
+pub struct FixedClock {
+    now: Mutex<SystemTime>,
+}
+
+impl FixedClock {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn at(now: DateTime<Utc>) -> Self {
+        Self::new(now.into())
+    }
+
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Zone of the timestamps in reports, branch names and commit messages
+///
+/// Parsed from `utc`, `local` (the system zone) or a fixed offset like `+08:00`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Timezone {
+    #[default]
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    /// `time` with this zone's offset at that instant
+    pub fn convert(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Timezone::Utc => time.fixed_offset(),
+            Timezone::Local => time.with_timezone(&Local).fixed_offset(),
+            Timezone::Fixed(offset) => time.with_timezone(offset),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utc" | "z" => Ok(Timezone::Utc),
+            "local" => Ok(Timezone::Local),
+            _ => s.parse::<FixedOffset>().map(Timezone::Fixed).map_err(|_| {
+                format!(
+                    "expected 'utc', 'local' or an offset like '+08:00', got '{}'",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timezone::Utc => f.write_str("utc"),
+            Timezone::Local => f.write_str("local"),
+            Timezone::Fixed(offset) => write!(f, "{}", offset),
+        }
+    }
+}
@@ -0,0 +1,367 @@
+//! OAuth access tokens for Google Cloud and Azure managed Prometheus
+//!
+//! Google Cloud Managed Service for Prometheus takes tokens of the GKE or GCE
+//! metadata server, e.g. through Workload Identity, or of the application default
+//! credentials `gcloud auth application-default login` writes. Azure Monitor
+//! managed Prometheus takes Entra ID (AAD) tokens of a service principal, AKS
+//! workload identity or the VM's managed identity, found through the usual
+//! `AZURE_*` environment variables. Tokens are refreshed shortly before they
+//! expire and never logged.
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Deserializer};
+use url::Url;
+
+use crate::error::{ConfigError, RecommenderError, Result};
+use crate::http::shared_client;
+use crate::redact::register_secret;
+
+/// Scope of Google Cloud tokens, read access to Cloud Monitoring
+pub const GCP_SCOPE: &str = "https://www.googleapis.com/auth/monitoring.read";
+
+/// Resource of Azure Monitor managed Prometheus tokens
+pub const AZURE_RESOURCE: &str = "https://prometheus.monitor.azure.com";
+
+/// Tokens are refreshed when they expire within this margin
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+const GCP_METADATA_HOST: &str = "metadata.google.internal";
+const GCP_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const AZURE_IMDS_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+const AZURE_AUTHORITY: &str = "https://login.microsoftonline.com";
+
+/// Cloud whose identity the tokens come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudProvider {
+    Gcp,
+    Azure,
+}
+
+/// Where a provider's tokens are requested from, chosen from the environment
+enum Credential {
+    /// Metadata server of the GKE node or GCE VM
+    GcpMetadata { host: String },
+    /// Refresh token of `gcloud auth application-default login`
+    GcpAuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+    /// Service principal with a client secret
+    AzureClientSecret {
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+    /// AKS workload identity, a federated token exchanged for an Entra ID one
+    AzureWorkloadIdentity {
+        tenant_id: String,
+        client_id: String,
+        token_file: PathBuf,
+    },
+    /// Instance metadata service, optionally for a user-assigned identity
+    AzureManagedIdentity { client_id: Option<String> },
+}
+
+/// Access tokens of one cloud identity, cached until shortly before they expire
+pub struct TokenSource {
+    client: Client,
+    credential: Credential,
+    cached: Mutex<Option<(String, Instant)>>,
+}
+
+impl TokenSource {
+    /// Find the credentials of `provider` in the environment
+    ///
+    /// Google Cloud uses the application default credentials file when
+    /// `GOOGLE_APPLICATION_CREDENTIALS` or gcloud's default location has one,
+    /// the metadata server otherwise. Azure uses `AZURE_CLIENT_SECRET`, then
+    /// `AZURE_FEDERATED_TOKEN_FILE`, then the managed identity of the VM.
+    pub fn from_env(provider: CloudProvider) -> Result<Self> {
+        let credential = match provider {
+            CloudProvider::Gcp => gcp_credential()?,
+            CloudProvider::Azure => azure_credential(),
+        };
+        debug!(
+            "Requesting {:?} tokens from {}",
+            provider,
+            credential.describe()
+        );
+        Ok(Self {
+            client: shared_client(),
+            credential,
+            cached: Mutex::new(None),
+        })
+    }
+
+    /// Send requests through `client` instead of the shared one
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// A token valid for at least a few more minutes, requesting a new one when needed
+    pub async fn token(&self) -> Result<String> {
+        let cached = self
+            .cached
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if let Some((token, expires_at)) = cached
+            && expires_at.saturating_duration_since(Instant::now()) > REFRESH_MARGIN
+        {
+            return Ok(token);
+        }
+
+        let response = self.request().await?;
+        register_secret(&response.access_token);
+        let expires_at = Instant::now() + Duration::from_secs(response.expires_in);
+        info!(
+            "Got an access token from {}, valid for {}s",
+            self.credential.describe(),
+            response.expires_in
+        );
+        *self.cached.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((response.access_token.clone(), expires_at));
+        Ok(response.access_token)
+    }
+
+    /// Request a new token for the credential
+    async fn request(&self) -> Result<TokenResponse> {
+        let azure_scope = format!("{}/.default", AZURE_RESOURCE);
+        let request = match &self.credential {
+            Credential::GcpMetadata { host } => self
+                .client
+                .get(format!(
+                    "http://{}/computeMetadata/v1/instance/service-accounts/default/token",
+                    host
+                ))
+                .query(&[("scopes", GCP_SCOPE)])
+                .header("Metadata-Flavor", "Google"),
+            Credential::GcpAuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+            } => self.client.post(GCP_TOKEN_URL).form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("refresh_token", refresh_token.as_str()),
+            ]),
+            Credential::AzureClientSecret {
+                tenant_id,
+                client_id,
+                client_secret,
+            } => self.client.post(azure_token_url(tenant_id)?).form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("scope", azure_scope.as_str()),
+            ]),
+            Credential::AzureWorkloadIdentity {
+                tenant_id,
+                client_id,
+                token_file,
+            } => {
+                // Kubelet rotates the projected token, so it is read on every request
+                let assertion = std::fs::read_to_string(token_file)?;
+                self.client.post(azure_token_url(tenant_id)?).form(&[
+                    ("grant_type", "client_credentials"),
+                    ("client_id", client_id.as_str()),
+                    (
+                        "client_assertion_type",
+                        "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+                    ),
+                    ("client_assertion", assertion.trim()),
+                    ("scope", azure_scope.as_str()),
+                ])
+            }
+            Credential::AzureManagedIdentity { client_id } => {
+                let mut query = vec![("api-version", "2018-02-01"), ("resource", AZURE_RESOURCE)];
+                if let Some(client_id) = client_id {
+                    query.push(("client_id", client_id.as_str()));
+                }
+                self.client
+                    .get(AZURE_IMDS_URL)
+                    .query(&query)
+                    .header("Metadata", "true")
+            }
+        };
+        self.send(request).await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<TokenResponse> {
+        let source = self.credential.describe();
+        let response = request.send().await.map_err(|e| {
+            RecommenderError::Network(format!(
+                "Failed to request an access token from {}: {}",
+                source,
+                e.without_url()
+            ))
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(RecommenderError::Network(format!(
+                "{} refused the access token request with {}: {}",
+                source,
+                status,
+                body.trim()
+            )));
+        }
+        response.json().await.map_err(|e| {
+            RecommenderError::Network(format!(
+                "Invalid access token response from {}: {}",
+                source,
+                e.without_url()
+            ))
+        })
+    }
+}
+
+impl Credential {
+    /// Where tokens come from, safe to log
+    fn describe(&self) -> String {
+        match self {
+            Credential::GcpMetadata { host } => format!("the metadata server {}", host),
+            Credential::GcpAuthorizedUser { .. } => "the application default credentials".into(),
+            Credential::AzureClientSecret { client_id, .. } => {
+                format!("service principal {}", client_id)
+            }
+            Credential::AzureWorkloadIdentity { client_id, .. } => {
+                format!("workload identity {}", client_id)
+            }
+            Credential::AzureManagedIdentity { client_id } => match client_id {
+                Some(client_id) => format!("managed identity {}", client_id),
+                None => "the system-assigned managed identity".into(),
+            },
+        }
+    }
+}
+
+/// Token endpoint responses, `expires_in` is a string in the Azure IMDS one
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(deserialize_with = "seconds")]
+    expires_in: u64,
+}
+
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Seconds {
+        Number(u64),
+        Text(String),
+    }
+    match Seconds::deserialize(deserializer)? {
+        Seconds::Number(seconds) => Ok(seconds),
+        Seconds::Text(seconds) => seconds.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// Application default credentials file, or the metadata server without one
+fn gcp_credential() -> Result<Credential> {
+    let Some(path) = gcp_credentials_file() else {
+        return Ok(Credential::GcpMetadata {
+            host: std::env::var("GCE_METADATA_HOST").unwrap_or_else(|_| GCP_METADATA_HOST.into()),
+        });
+    };
+
+    #[derive(Deserialize)]
+    struct CredentialsFile {
+        #[serde(rename = "type")]
+        kind: String,
+        client_id: Option<String>,
+        client_secret: Option<String>,
+        refresh_token: Option<String>,
+    }
+    let file: CredentialsFile = serde_json::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(|e| ConfigError::InvalidValue(format!("{}: {}", path.display(), e)))?;
+    match (
+        file.kind.as_str(),
+        file.client_id,
+        file.client_secret,
+        file.refresh_token,
+    ) {
+        ("authorized_user", Some(client_id), Some(client_secret), Some(refresh_token)) => {
+            register_secret(&client_secret);
+            register_secret(&refresh_token);
+            Ok(Credential::GcpAuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+            })
+        }
+        (kind, ..) => Err(ConfigError::InvalidValue(format!(
+            "{} holds {} credentials, only authorized_user ones from `gcloud auth application-default login` \
+             are supported; use Workload Identity, or --prometheus-auth bearer with \
+             `gcloud auth print-access-token`",
+            path.display(),
+            kind
+        ))
+        .into()),
+    }
+}
+
+/// `GOOGLE_APPLICATION_CREDENTIALS`, or gcloud's file when it exists
+fn gcp_credentials_file() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = match std::env::var_os("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => PathBuf::from(std::env::var_os("APPDATA")?).join("gcloud"),
+        None => PathBuf::from(std::env::var_os("HOME")?)
+            .join(".config")
+            .join("gcloud"),
+    };
+    Some(config_dir.join("application_default_credentials.json")).filter(|path| path.is_file())
+}
+
+/// Service principal or workload identity from `AZURE_*`, the managed identity otherwise
+fn azure_credential() -> Credential {
+    let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    match (
+        var("AZURE_TENANT_ID"),
+        var("AZURE_CLIENT_ID"),
+        var("AZURE_CLIENT_SECRET"),
+        var("AZURE_FEDERATED_TOKEN_FILE"),
+    ) {
+        (Some(tenant_id), Some(client_id), Some(client_secret), _) => {
+            register_secret(&client_secret);
+            Credential::AzureClientSecret {
+                tenant_id,
+                client_id,
+                client_secret,
+            }
+        }
+        (Some(tenant_id), Some(client_id), None, Some(token_file)) => {
+            Credential::AzureWorkloadIdentity {
+                tenant_id,
+                client_id,
+                token_file: token_file.into(),
+            }
+        }
+        (_, client_id, ..) => Credential::AzureManagedIdentity { client_id },
+    }
+}
+
+/// Token endpoint of `tenant_id`, honoring `AZURE_AUTHORITY_HOST` for sovereign clouds
+fn azure_token_url(tenant_id: &str) -> Result<Url> {
+    let authority =
+        std::env::var("AZURE_AUTHORITY_HOST").unwrap_or_else(|_| AZURE_AUTHORITY.into());
+    let mut url = Url::parse(&authority)
+        .map_err(|e| ConfigError::InvalidValue(format!("AZURE_AUTHORITY_HOST: {}", e)))?;
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments
+            .pop_if_empty()
+            .extend([tenant_id, "oauth2", "v2.0", "token"]);
+    }
+    Ok(url)
+}
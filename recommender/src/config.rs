@@ -0,0 +1,1405 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::clock::Timezone;
+use crate::filter::WorkloadFilter;
+use crate::helm::HelmValues;
+use crate::kubernetes::DeploymentResources;
+use crate::lookback::LookbackOverrides;
+use crate::output::PercentileConfig;
+use crate::overlays::EnvironmentOverlays;
+use crate::quantity::Quantity;
+use crate::query::QueryTemplate;
+use crate::rate_limit::RateLimiter;
+use crate::recommender::ResourceRecommendation;
+use crate::redact::register_secret;
+use crate::sops::SopsConfig;
+use crate::{ConfigError, RecommenderError, Result};
+
+#[derive(Clone, Debug)]
+pub struct KubernetesConfig {
+    pub amp_url: String,
+    pub region: String,
+    pub context: Option<String>,
+    pub namespace: Option<String>,
+    /// Workloads to analyze within the namespace, all by default
+    pub workloads: WorkloadFilter,
+    /// List CronJobs and Jobs besides Deployments
+    pub include_jobs: bool,
+}
+
+impl KubernetesConfig {
+    pub fn new(
+        amp_url: String,
+        region: String,
+        context: Option<String>,
+        namespace: Option<String>,
+    ) -> Self {
+        Self {
+            amp_url,
+            region,
+            context,
+            namespace,
+            workloads: WorkloadFilter::default(),
+            include_jobs: false,
+        }
+    }
+
+    /// Only list the workloads `filter` keeps
+    pub fn with_workload_filter(mut self, filter: WorkloadFilter) -> Self {
+        self.workloads = filter;
+        self
+    }
+
+    /// Also list CronJobs and the Jobs they didn't create
+    pub fn with_jobs(mut self, include_jobs: bool) -> Self {
+        self.include_jobs = include_jobs;
+        self
+    }
+}
+
+/// Range of `rate()` in the default CPU query
+const DEFAULT_RATE_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Wider range retried when the default rate window returns too few points
+const DEFAULT_FALLBACK_RATE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Containers analyzed at the same time unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT_QUERIES: usize = 8;
+
+/// Job label of the metrics pushed to a Pushgateway unless configured otherwise
+pub const DEFAULT_PUSH_JOB: &str = "k8s-autorightsizing";
+
+/// How weekday and weekend usage are sized when analyzed separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DaySplit {
+    /// One recommendation per profile, labeled weekday or weekend
+    Both,
+    /// One recommendation with the higher of both profiles for each value
+    Max,
+}
+
+/// How workloads whose VerticalPodAutoscaler changes their requests are handled
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum VpaMode {
+    /// Skip them, the VPA owns their requests
+    #[default]
+    Skip,
+    /// Recommend next to the VPA's current target, without applying to them
+    Compare,
+    /// Apply by setting the VPA's minAllowed and maxAllowed instead of the Deployment
+    UpdateVpa,
+}
+
+/// `spec.updatePolicy.updateMode` of the VPAs rendered by `--output vpa`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum VpaUpdateMode {
+    /// The VPA only publishes its recommendation, bounded to ours
+    #[default]
+    Off,
+    /// The VPA evicts pods and sets the recommended requests itself
+    Auto,
+}
+
+impl VpaUpdateMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VpaUpdateMode::Off => crate::vpa::UPDATE_MODE_OFF,
+            VpaUpdateMode::Auto => "Auto",
+        }
+    }
+}
+
+/// Where recommendations are published on the analyzed Deployments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PublishTarget {
+    /// `rightsizing.k8s.io/recommended-*` annotations on the Deployment
+    Annotations,
+    /// A `RightsizingRecommendation` Event on the Deployment
+    Events,
+}
+
+/// Payload format of the chat webhook notified after an apply
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum NotifyFormat {
+    /// Slack incoming webhook, Block Kit message
+    Slack,
+    /// Microsoft Teams Workflows webhook, adaptive card
+    Teams,
+}
+
+impl NotifyFormat {
+    /// Format of the service hosting `url`, None for other hosts
+    pub fn for_url(url: &Url) -> Option<Self> {
+        let host = url.host_str()?;
+        if host == "slack.com" || host.ends_with(".slack.com") {
+            Some(NotifyFormat::Slack)
+        } else if [".office.com", ".logic.azure.com", ".powerplatform.com"]
+            .iter()
+            .any(|suffix| host.ends_with(suffix))
+        {
+            Some(NotifyFormat::Teams)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for NotifyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NotifyFormat::Slack => "Slack",
+            NotifyFormat::Teams => "Teams",
+        })
+    }
+}
+
+/// Where the workloads to analyze and their current resources come from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum WorkloadDiscovery {
+    /// The Kubernetes API, through the kubeconfig
+    #[default]
+    Cluster,
+    /// kube-state-metrics series in Prometheus, without any cluster access
+    KubeStateMetrics,
+}
+
+/// How requests to the Prometheus endpoint authenticate
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum PrometheusAuth {
+    /// No credentials
+    None,
+    /// HTTP basic auth
+    Basic,
+    /// `Authorization: Bearer` token
+    Bearer,
+    /// AWS SigV4 with the default AWS credentials, for AMP
+    #[default]
+    #[cfg_attr(feature = "cli", value(name = "sigv4"))]
+    SigV4,
+    /// OAuth tokens of the Google Cloud identity, for Managed Service for Prometheus
+    Gcp,
+    /// Entra ID tokens of the Azure identity, for Azure Monitor managed Prometheus
+    Azure,
+}
+
+/// Bucket and key prefix given as `s3://bucket/prefix/`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    /// Empty or ending with `/`
+    pub prefix: String,
+}
+
+impl FromStr for S3Location {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let rest = value
+            .strip_prefix("s3://")
+            .ok_or_else(|| format!("expected s3://bucket/prefix/, got {}", value))?;
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("missing bucket name in {}", value));
+        }
+
+        let prefix = prefix.trim_matches('/');
+        Ok(Self {
+            bucket: bucket.to_string(),
+            prefix: if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("{}/", prefix)
+            },
+        })
+    }
+}
+
+impl fmt::Display for S3Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.prefix)
+    }
+}
+
+/// Repository and tag given as `[http://]registry/repository[:tag]`
+///
+/// The registry is reached over HTTPS unless the reference starts with `http://`,
+/// e.g. for a local registry. The tag defaults to `latest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OciReference {
+    /// Scheme and host of the registry, e.g. `https://ghcr.io`
+    pub registry: Url,
+    pub repository: String,
+    pub tag: String,
+}
+
+impl FromStr for OciReference {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let (scheme, rest) = match value.strip_prefix("http://") {
+            Some(rest) => ("http", rest),
+            None => ("https", value.strip_prefix("https://").unwrap_or(value)),
+        };
+        let (registry, path) = rest
+            .split_once('/')
+            .ok_or_else(|| format!("expected registry/repository[:tag], got {}", value))?;
+
+        // A colon after the last slash separates the tag, one before is a port
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+            _ => (path, "latest"),
+        };
+        if repository.is_empty() || tag.is_empty() {
+            return Err(format!("expected registry/repository[:tag], got {}", value));
+        }
+        if repository
+            .chars()
+            .any(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || "._-/".contains(c)))
+        {
+            return Err(format!(
+                "repository names are lowercase letters, digits and ._-/ only, got {}",
+                repository
+            ));
+        }
+
+        let registry = Url::parse(&format!("{}://{}", scheme, registry))
+            .map_err(|e| format!("invalid registry {}: {}", registry, e))?;
+        Ok(Self {
+            registry,
+            repository: repository.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for OciReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}:{}",
+            self.registry.authority(),
+            self.repository,
+            self.tag
+        )
+    }
+}
+
+/// Which memory series usage is read from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum MemoryMetric {
+    /// Detect the variants the workspace has and take the best one per pod
+    #[default]
+    Auto,
+    /// cAdvisor's `container_memory_working_set_bytes` only
+    WorkingSet,
+}
+
+/// Weekly usage growth above which a workload is flagged
+const DEFAULT_GROWTH_WARNING: f64 = 0.1;
+
+/// How usage is projected ahead
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ForecastMethod {
+    /// Least-squares line through the lookback
+    #[default]
+    Linear,
+    /// Additive Holt-Winters with a daily season, which keeps daily cycles out of the trend
+    HoltWinters,
+}
+
+/// Projecting usage ahead so growing workloads are sized for the future
+///
+/// Requests and limits are scaled by the projected growth over `horizon`;
+/// shrinking usage leaves them as they are. Lookbacks under two days aren't
+/// projected, their trend is mostly intraday ramps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForecastConfig {
+    pub method: ForecastMethod,
+    pub horizon: Duration,
+    /// Weekly growth, as a fraction of the current usage, that adds a warning
+    pub growth_warning: f64,
+}
+
+impl ForecastConfig {
+    pub fn new(method: ForecastMethod, horizon: Duration) -> Self {
+        Self {
+            method,
+            horizon,
+            growth_warning: DEFAULT_GROWTH_WARNING,
+        }
+    }
+
+    /// Warn about workloads growing faster than `growth` (0.1 = 10%) per week
+    pub fn with_growth_warning(mut self, growth: f64) -> Self {
+        self.growth_warning = growth;
+        self
+    }
+}
+
+/// Share of the usage variance a pattern has to explain to size for its peaks
+const DEFAULT_SEASONAL_STRENGTH: f64 = 0.5;
+
+/// Sizing strongly periodic workloads for their busy hours
+///
+/// Usage is bucketed by hour of day and of week, see [`crate::seasonality`].
+/// When the pattern explains at least `min_strength` of the variance, the
+/// percentiles are taken over the peak hours only instead of the whole lookback.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeasonalityConfig {
+    pub min_strength: f64,
+}
+
+impl Default for SeasonalityConfig {
+    fn default() -> Self {
+        Self {
+            min_strength: DEFAULT_SEASONAL_STRENGTH,
+        }
+    }
+}
+
+impl SeasonalityConfig {
+    /// Only size for the peaks of patterns explaining this share (0.5 = 50%) of the variance
+    pub fn with_min_strength(mut self, strength: f64) -> Self {
+        self.min_strength = strength;
+        self
+    }
+}
+
+/// Range of `rate()` in the CPU limit query
+const DEFAULT_BURST_WINDOW: Duration = Duration::from_secs(60);
+
+/// Robust standard deviations above the median a spike has to reach
+const DEFAULT_OUTLIER_THRESHOLD: f64 = 10.0;
+
+/// Runs above the threshold lasting longer than this are real load
+const DEFAULT_OUTLIER_MAX_DURATION: Duration = Duration::from_secs(30 * 60);
+
+/// What happens to the usage samples of a detected spike
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OutlierAction {
+    /// Leave the spike out of the percentiles
+    Exclude,
+    /// Lower the spike to the threshold it crossed
+    Cap,
+}
+
+/// Short usage spikes, e.g. retry storms during an incident, to drop or cap before
+/// computing percentiles
+///
+/// A spike is a run of query steps whose peak usage over all pods is more than
+/// `threshold` robust standard deviations above the median, lasting no longer than
+/// `max_duration`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutlierFilter {
+    pub action: OutlierAction,
+    pub threshold: f64,
+    pub max_duration: Duration,
+}
+
+impl OutlierFilter {
+    pub fn new(action: OutlierAction) -> Self {
+        Self {
+            action,
+            threshold: DEFAULT_OUTLIER_THRESHOLD,
+            max_duration: DEFAULT_OUTLIER_MAX_DURATION,
+        }
+    }
+
+    /// Only treat usage this many robust standard deviations above the median as spikes
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Keep runs above the threshold that last longer than `max_duration`
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+}
+
+/// Floors and caps of the recommended values, e.g. a 10m / 32Mi floor
+///
+/// Applied last, after the percentiles, margin, forecast and policies. Requests
+/// are also capped by their limit's cap, so raising a limit to its request
+/// never lifts it above the cap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceBounds {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_cpu_request: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cpu_request: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_cpu_limit: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cpu_limit: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_memory_request: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_request: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_memory_limit: Option<Quantity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_limit: Option<Quantity>,
+}
+
+impl ResourceBounds {
+    pub fn with_cpu_request(mut self, min: Option<Quantity>, max: Option<Quantity>) -> Self {
+        self.min_cpu_request = min;
+        self.max_cpu_request = max;
+        self
+    }
+
+    pub fn with_cpu_limit(mut self, min: Option<Quantity>, max: Option<Quantity>) -> Self {
+        self.min_cpu_limit = min;
+        self.max_cpu_limit = max;
+        self
+    }
+
+    pub fn with_memory_request(mut self, min: Option<Quantity>, max: Option<Quantity>) -> Self {
+        self.min_memory_request = min;
+        self.max_memory_request = max;
+        self
+    }
+
+    pub fn with_memory_limit(mut self, min: Option<Quantity>, max: Option<Quantity>) -> Self {
+        self.min_memory_limit = min;
+        self.max_memory_limit = max;
+        self
+    }
+
+    /// Whether no value is bounded
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Bounds of each value as `(name, min, max, floor flag)`, requests capped by
+    /// their limit's cap
+    fn ranges(
+        &self,
+    ) -> [(
+        &'static str,
+        Option<Quantity>,
+        Option<Quantity>,
+        &'static str,
+    ); 4] {
+        let lower = |a: Option<Quantity>, b: Option<Quantity>| match (a, b) {
+            (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+            (a, b) => a.or(b),
+        };
+        [
+            (
+                "CPU request",
+                self.min_cpu_request,
+                lower(self.max_cpu_request, self.max_cpu_limit),
+                "min-cpu-request",
+            ),
+            (
+                "CPU limit",
+                self.min_cpu_limit,
+                self.max_cpu_limit,
+                "min-cpu-limit",
+            ),
+            (
+                "memory request",
+                self.min_memory_request,
+                lower(self.max_memory_request, self.max_memory_limit),
+                "min-memory-request",
+            ),
+            (
+                "memory limit",
+                self.min_memory_limit,
+                self.max_memory_limit,
+                "min-memory-limit",
+            ),
+        ]
+    }
+
+    /// Raise values below their floor and lower those above their cap
+    ///
+    /// Each change is noted in the recommendation's reason. Returns whether a
+    /// value changed.
+    pub fn clamp(&self, recommendation: &mut ResourceRecommendation) -> bool {
+        let values = [
+            &mut recommendation.recommended_cpu_request,
+            &mut recommendation.recommended_cpu_limit,
+            &mut recommendation.recommended_memory_request,
+            &mut recommendation.recommended_memory_limit,
+        ];
+        let mut notes = Vec::new();
+        for ((name, min, max, _), value) in self.ranges().into_iter().zip(values) {
+            if let Some(min) = min
+                && *value < min
+            {
+                notes.push(format!("{} raised to the {} floor", name, min));
+                *value = min;
+            } else if let Some(max) = max
+                && *value > max
+            {
+                notes.push(format!("{} lowered to the {} cap", name, max));
+                *value = max;
+            }
+        }
+        for note in &notes {
+            recommendation.recommendation_reason.push_str("; ");
+            recommendation.recommendation_reason.push_str(note);
+        }
+        !notes.is_empty()
+    }
+
+    /// Check that no floor is above its cap, naming the flag of the floor
+    pub fn validate(&self) -> std::result::Result<(), ConfigError> {
+        for (_, min, max, flag) in self.ranges() {
+            if let (Some(min), Some(max)) = (min, max)
+                && min > max
+            {
+                return Err(invalid_flag(
+                    flag,
+                    format!("{} is above the {} cap", min, max),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecommenderConfig {
+    pub lookback_hours: f64,
+    /// Lookbacks of specific namespaces or workloads, see [`RecommenderConfig::lookback_hours_for`]
+    pub lookback_overrides: LookbackOverrides,
+    pub cpu_request_percentile: f64,
+    pub cpu_limit_percentile: f64,
+    pub memory_request_percentile: f64,
+    pub memory_limit_percentile: f64,
+    pub safety_margin: f64,
+    /// Resolution of the range queries, picked from the lookback if None
+    pub query_step: Option<Duration>,
+    /// CPU usage in cores, per pod unless the template is aggregated
+    pub cpu_query: QueryTemplate,
+    /// Memory usage in bytes, per pod unless the template is aggregated
+    pub memory_query: QueryTemplate,
+    /// Whether the default queries are adapted to the metric variants the
+    /// workspace has, see [`crate::metric_variants`]
+    pub memory_metric: MemoryMetric,
+    /// Range of `rate()` in the CPU query, filled in for `$rate_window`
+    pub rate_window: Duration,
+    /// Wider range retried when the rate window covers too little of the lookback
+    ///
+    /// Scrape gaps and counter resets leave windows with fewer than two samples,
+    /// which `rate()` drops. None disables the retry.
+    pub fallback_rate_window: Option<Duration>,
+    /// Peak CPU per pod the limits are sized from, None to use `cpu_query`
+    ///
+    /// Limits tuned to the smoothed series of the requests throttle services
+    /// bursting for less than a rate window.
+    pub cpu_limit_query: Option<QueryTemplate>,
+    /// Range of `rate()` in the CPU limit query, filled in for its `$rate_window`
+    pub burst_window: Duration,
+    /// Spikes to drop or cap before computing percentiles, None keeps every sample
+    pub outlier_filter: Option<OutlierFilter>,
+    /// Usage projection the recommendations are sized for, None sizes for the lookback
+    pub forecast: Option<ForecastConfig>,
+    /// Size weekdays and weekends separately, None sizes for the whole week
+    pub day_split: Option<DaySplit>,
+    /// Size periodic workloads for their peak hours, None sizes for the whole lookback
+    pub seasonality: Option<SeasonalityConfig>,
+    /// Zone whose calendar tells weekdays from weekends
+    pub timezone: Timezone,
+    /// Handling of workloads managed by a VerticalPodAutoscaler
+    pub vpa_mode: VpaMode,
+    /// Floors and caps of the recommended values, none by default
+    pub bounds: ResourceBounds,
+    /// Keep usage sketches on the recommendations so they can be retuned, see
+    /// [`ResourceRecommendation::retune`](crate::recommender::ResourceRecommendation::retune)
+    pub retain_usage: bool,
+    /// Range queries in flight at once
+    ///
+    /// Containers are analyzed concurrently, each running its queries one after
+    /// another, so this is also the number of containers analyzed at a time.
+    pub max_concurrent_queries: usize,
+}
+
+impl RecommenderConfig {
+    pub fn new(
+        lookback_hours: f64,
+        cpu_request_percentile: f64,
+        cpu_limit_percentile: f64,
+        memory_request_percentile: f64,
+        memory_limit_percentile: f64,
+        safety_margin: f64,
+    ) -> Self {
+        Self {
+            lookback_hours,
+            lookback_overrides: LookbackOverrides::default(),
+            cpu_request_percentile,
+            cpu_limit_percentile,
+            memory_request_percentile,
+            memory_limit_percentile,
+            safety_margin,
+            query_step: None,
+            cpu_query: QueryTemplate::cpu_usage(),
+            memory_query: QueryTemplate::memory_usage(),
+            memory_metric: MemoryMetric::default(),
+            rate_window: DEFAULT_RATE_WINDOW,
+            fallback_rate_window: Some(DEFAULT_FALLBACK_RATE_WINDOW),
+            cpu_limit_query: None,
+            burst_window: DEFAULT_BURST_WINDOW,
+            outlier_filter: None,
+            forecast: None,
+            day_split: None,
+            seasonality: None,
+            timezone: Timezone::Utc,
+            vpa_mode: VpaMode::default(),
+            bounds: ResourceBounds::default(),
+            retain_usage: false,
+            max_concurrent_queries: DEFAULT_MAX_CONCURRENT_QUERIES,
+        }
+    }
+
+    /// Query step used for the run, the override or one fitting the lookback
+    ///
+    /// Longer lookbacks get coarser steps to keep samples per series and query
+    /// cost bounded: 1m up to 6h, 5m up to 7d, 15m up to 30d and 1h beyond.
+    pub fn effective_query_step(&self) -> Duration {
+        self.query_step_for(self.lookback_hours)
+    }
+
+    /// Query step for a workload with a lookback of `lookback_hours`
+    pub fn query_step_for(&self, lookback_hours: f64) -> Duration {
+        self.query_step
+            .unwrap_or_else(|| auto_query_step(lookback_hours))
+    }
+
+    /// Lookback of `deployment`: its annotation, else the most specific override,
+    /// else `lookback_hours`
+    pub fn lookback_hours_for(&self, deployment: &DeploymentResources) -> f64 {
+        deployment
+            .lookback_hours
+            .or_else(|| self.lookback_overrides.lookback_hours(deployment))
+            .unwrap_or(self.lookback_hours)
+    }
+
+    /// Percentiles and margin requests and limits are sized for
+    pub fn percentiles(&self) -> PercentileConfig {
+        PercentileConfig {
+            cpu_request: self.cpu_request_percentile,
+            cpu_limit: self.cpu_limit_percentile,
+            memory_request: self.memory_request_percentile,
+            memory_limit: self.memory_limit_percentile,
+            safety_margin: self.safety_margin,
+        }
+    }
+
+    /// Start building a config from the default settings
+    pub fn builder() -> RecommenderConfigBuilder {
+        RecommenderConfigBuilder::default()
+    }
+
+    /// Check that all settings are usable, naming the flag of the first invalid one
+    pub fn validate(&self) -> std::result::Result<(), ConfigError> {
+        if !(self.lookback_hours.is_finite() && self.lookback_hours > 0.0) {
+            return Err(invalid_flag(
+                "lookback-hours",
+                format!("must be greater than 0, got {}", self.lookback_hours),
+            ));
+        }
+
+        for lookback in &self.lookback_overrides.overrides {
+            if !(lookback.lookback_hours.is_finite() && lookback.lookback_hours > 0.0) {
+                return Err(invalid_flag(
+                    "lookback-overrides",
+                    format!(
+                        "lookback_hours must be greater than 0, got {}",
+                        lookback.lookback_hours
+                    ),
+                ));
+            }
+        }
+
+        if self.max_concurrent_queries == 0 {
+            return Err(invalid_flag(
+                "max-concurrent-queries",
+                "must be at least 1".to_string(),
+            ));
+        }
+
+        for (flag, percentile) in [
+            ("cpu-request-percentile", self.cpu_request_percentile),
+            ("cpu-limit-percentile", self.cpu_limit_percentile),
+            ("memory-request-percentile", self.memory_request_percentile),
+            ("memory-limit-percentile", self.memory_limit_percentile),
+        ] {
+            if !(0.0..=100.0).contains(&percentile) {
+                return Err(invalid_flag(
+                    flag,
+                    format!("must be between 0 and 100, got {}", percentile),
+                ));
+            }
+        }
+
+        if self.cpu_request_percentile > self.cpu_limit_percentile {
+            return Err(invalid_flag(
+                "cpu-request-percentile",
+                format!(
+                    "{} is above --cpu-limit-percentile {}, requests would exceed limits",
+                    self.cpu_request_percentile, self.cpu_limit_percentile
+                ),
+            ));
+        }
+        if self.memory_request_percentile > self.memory_limit_percentile {
+            return Err(invalid_flag(
+                "memory-request-percentile",
+                format!(
+                    "{} is above --memory-limit-percentile {}, requests would exceed limits",
+                    self.memory_request_percentile, self.memory_limit_percentile
+                ),
+            ));
+        }
+
+        if !(self.safety_margin.is_finite() && self.safety_margin > 0.0) {
+            return Err(invalid_flag(
+                "safety-margin",
+                format!(
+                    "must be greater than 0 (1.2 = 20% buffer), got {}",
+                    self.safety_margin
+                ),
+            ));
+        }
+
+        if let Some(step) = self.query_step {
+            if step.is_zero() {
+                return Err(invalid_flag(
+                    "query-step-seconds",
+                    "must be greater than 0".to_string(),
+                ));
+            }
+            if step.as_secs_f64() > self.lookback_hours * 3600.0 {
+                return Err(invalid_flag(
+                    "query-step-seconds",
+                    format!(
+                        "{}s is longer than the {}h lookback",
+                        step.as_secs(),
+                        self.lookback_hours
+                    ),
+                ));
+            }
+        }
+
+        if self.rate_window.is_zero() {
+            return Err(invalid_flag(
+                "rate-window-seconds",
+                "must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(fallback) = self.fallback_rate_window
+            && fallback <= self.rate_window
+        {
+            return Err(invalid_flag(
+                "fallback-rate-window-seconds",
+                format!(
+                    "{}s must be wider than the {}s rate window",
+                    fallback.as_secs(),
+                    self.rate_window.as_secs()
+                ),
+            ));
+        }
+
+        if self.burst_window.is_zero() {
+            return Err(invalid_flag(
+                "cpu-burst-window-seconds",
+                "must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(query) = &self.cpu_limit_query
+            && query.template.trim().is_empty()
+        {
+            return Err(invalid_flag(
+                "cpu-limit-query",
+                "must not be empty".to_string(),
+            ));
+        }
+
+        if let Some(filter) = &self.outlier_filter {
+            if !(filter.threshold.is_finite() && filter.threshold > 0.0) {
+                return Err(invalid_flag(
+                    "outlier-threshold",
+                    format!("must be greater than 0, got {}", filter.threshold),
+                ));
+            }
+            let step = self.effective_query_step();
+            if filter.max_duration < step {
+                return Err(invalid_flag(
+                    "outlier-max-duration-seconds",
+                    format!(
+                        "{}s is shorter than the {}s query step, no spike would fit",
+                        filter.max_duration.as_secs(),
+                        step.as_secs()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(forecast) = &self.forecast {
+            if self.lookback_hours < 48.0 {
+                return Err(invalid_flag(
+                    "forecast",
+                    format!(
+                        "needs a lookback of at least 48 hours, got {}",
+                        self.lookback_hours
+                    ),
+                ));
+            }
+            if forecast.horizon.is_zero() {
+                return Err(invalid_flag(
+                    "forecast-weeks",
+                    "must be greater than 0".to_string(),
+                ));
+            }
+            if !(forecast.growth_warning.is_finite() && forecast.growth_warning >= 0.0) {
+                return Err(invalid_flag(
+                    "growth-warning-percent",
+                    format!("must be 0 or more, got {}", forecast.growth_warning * 100.0),
+                ));
+            }
+        }
+
+        if let Some(seasonality) = &self.seasonality {
+            if !(0.0..=1.0).contains(&seasonality.min_strength) {
+                return Err(invalid_flag(
+                    "seasonal-strength-percent",
+                    format!(
+                        "must be between 0 and 100, got {}",
+                        seasonality.min_strength * 100.0
+                    ),
+                ));
+            }
+            if self.day_split.is_some() {
+                return Err(invalid_flag(
+                    "seasonal",
+                    "already splits the week, leave out --day-split".to_string(),
+                ));
+            }
+        }
+
+        self.bounds.validate()?;
+
+        for (flag, query) in [
+            ("cpu-query", &self.cpu_query),
+            ("memory-query", &self.memory_query),
+        ] {
+            if query.template.trim().is_empty() {
+                return Err(invalid_flag(flag, "must not be empty".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Step for a lookback of `lookback_hours` when none was configured
+pub(crate) fn auto_query_step(lookback_hours: f64) -> Duration {
+    let minutes = if lookback_hours <= 6.0 {
+        1
+    } else if lookback_hours <= 7.0 * 24.0 {
+        5
+    } else if lookback_hours <= 30.0 * 24.0 {
+        15
+    } else {
+        60
+    };
+    Duration::from_secs(minutes * 60)
+}
+
+fn invalid_flag(flag: &'static str, message: String) -> ConfigError {
+    ConfigError::InvalidFlag { flag, message }
+}
+
+impl Default for RecommenderConfig {
+    /// Same defaults as the CLI: 7 days lookback, p95 requests, p99 limits, 20% buffer
+    fn default() -> Self {
+        Self {
+            lookback_hours: 168.0,
+            lookback_overrides: LookbackOverrides::default(),
+            cpu_request_percentile: 95.0,
+            cpu_limit_percentile: 99.0,
+            memory_request_percentile: 95.0,
+            memory_limit_percentile: 99.0,
+            safety_margin: 1.2,
+            query_step: None,
+            cpu_query: QueryTemplate::cpu_usage(),
+            memory_query: QueryTemplate::memory_usage(),
+            memory_metric: MemoryMetric::default(),
+            rate_window: DEFAULT_RATE_WINDOW,
+            fallback_rate_window: Some(DEFAULT_FALLBACK_RATE_WINDOW),
+            cpu_limit_query: None,
+            burst_window: DEFAULT_BURST_WINDOW,
+            outlier_filter: None,
+            forecast: None,
+            day_split: None,
+            seasonality: None,
+            timezone: Timezone::Utc,
+            vpa_mode: VpaMode::default(),
+            bounds: ResourceBounds::default(),
+            retain_usage: false,
+            max_concurrent_queries: DEFAULT_MAX_CONCURRENT_QUERIES,
+        }
+    }
+}
+
+/// Builder for [`RecommenderConfig`] with named setters
+#[derive(Clone, Debug, Default)]
+pub struct RecommenderConfigBuilder {
+    config: RecommenderConfig,
+}
+
+impl RecommenderConfigBuilder {
+    pub fn lookback_hours(mut self, lookback_hours: f64) -> Self {
+        self.config.lookback_hours = lookback_hours;
+        self
+    }
+
+    /// Use other lookbacks for the namespaces or workloads `overrides` match
+    pub fn lookback_overrides(mut self, overrides: LookbackOverrides) -> Self {
+        self.config.lookback_overrides = overrides;
+        self
+    }
+
+    pub fn cpu_request_percentile(mut self, percentile: f64) -> Self {
+        self.config.cpu_request_percentile = percentile;
+        self
+    }
+
+    pub fn cpu_limit_percentile(mut self, percentile: f64) -> Self {
+        self.config.cpu_limit_percentile = percentile;
+        self
+    }
+
+    pub fn memory_request_percentile(mut self, percentile: f64) -> Self {
+        self.config.memory_request_percentile = percentile;
+        self
+    }
+
+    pub fn memory_limit_percentile(mut self, percentile: f64) -> Self {
+        self.config.memory_limit_percentile = percentile;
+        self
+    }
+
+    pub fn safety_margin(mut self, safety_margin: f64) -> Self {
+        self.config.safety_margin = safety_margin;
+        self
+    }
+
+    /// Query at this resolution instead of picking one from the lookback
+    pub fn query_step(mut self, step: Duration) -> Self {
+        self.config.query_step = Some(step);
+        self
+    }
+
+    /// Query CPU usage with `template` instead of the cAdvisor default
+    pub fn cpu_query(mut self, template: QueryTemplate) -> Self {
+        self.config.cpu_query = template;
+        self
+    }
+
+    /// Query memory usage with `template` instead of the cAdvisor default
+    pub fn memory_query(mut self, template: QueryTemplate) -> Self {
+        self.config.memory_query = template;
+        self
+    }
+
+    /// Detect memory metric variants with [`MemoryMetric::Auto`], the default
+    pub fn memory_metric(mut self, metric: MemoryMetric) -> Self {
+        self.config.memory_metric = metric;
+        self
+    }
+
+    /// Range of `rate()` in the CPU query, 5m by default
+    pub fn rate_window(mut self, window: Duration) -> Self {
+        self.config.rate_window = window;
+        self
+    }
+
+    /// Wider rate window to retry with, or None to never retry
+    pub fn fallback_rate_window(mut self, window: Option<Duration>) -> Self {
+        self.config.fallback_rate_window = window;
+        self
+    }
+
+    /// Size CPU limits from `template`, e.g. [`QueryTemplate::cpu_burst`], or None
+    /// to size them from the CPU query
+    pub fn cpu_limit_query(mut self, template: Option<QueryTemplate>) -> Self {
+        self.config.cpu_limit_query = template;
+        self
+    }
+
+    /// Range of `rate()` in the CPU limit query, 1m by default
+    pub fn burst_window(mut self, window: Duration) -> Self {
+        self.config.burst_window = window;
+        self
+    }
+
+    /// Drop or cap short usage spikes, or None to keep every sample
+    pub fn outlier_filter(mut self, filter: Option<OutlierFilter>) -> Self {
+        self.config.outlier_filter = filter;
+        self
+    }
+
+    /// Size for projected usage, or None to size for the lookback
+    pub fn forecast(mut self, forecast: Option<ForecastConfig>) -> Self {
+        self.config.forecast = forecast;
+        self
+    }
+
+    /// Size weekdays and weekends separately, or None to size for the whole week
+    pub fn day_split(mut self, split: Option<DaySplit>) -> Self {
+        self.config.day_split = split;
+        self
+    }
+
+    /// Size periodic workloads for their peak hours, or None for the whole lookback
+    pub fn seasonality(mut self, seasonality: Option<SeasonalityConfig>) -> Self {
+        self.config.seasonality = seasonality;
+        self
+    }
+
+    /// Tell weekends apart in `timezone` instead of UTC
+    pub fn timezone(mut self, timezone: Timezone) -> Self {
+        self.config.timezone = timezone;
+        self
+    }
+
+    /// Handle workloads managed by a VerticalPodAutoscaler with `mode`
+    pub fn vpa_mode(mut self, mode: VpaMode) -> Self {
+        self.config.vpa_mode = mode;
+        self
+    }
+
+    /// Keep recommended values within `bounds`
+    pub fn bounds(mut self, bounds: ResourceBounds) -> Self {
+        self.config.bounds = bounds;
+        self
+    }
+
+    /// Keep usage sketches in memory so recommendations can be retuned without
+    /// querying again
+    pub fn retain_usage(mut self, retain: bool) -> Self {
+        self.config.retain_usage = retain;
+        self
+    }
+
+    /// Run up to `max` range queries at the same time, 1 runs them one by one
+    pub fn max_concurrent_queries(mut self, max: usize) -> Self {
+        self.config.max_concurrent_queries = max;
+        self
+    }
+
+    pub fn build(self) -> RecommenderConfig {
+        self.config
+    }
+}
+
+/// Timeouts and connection reuse of the HTTP clients
+#[derive(Clone, Debug)]
+pub struct HttpConfig {
+    /// Whole-request timeout, including reading the body
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// How long idle pooled connections are kept open
+    pub pool_idle_timeout: Duration,
+    pub tcp_keepalive: Duration,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            pool_idle_timeout: Duration::from_secs(90),
+            tcp_keepalive: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LeaderElectionConfig {
+    pub lease_name: String,
+    pub lease_namespace: String,
+    pub identity: String,
+    pub lease_duration: Duration,
+}
+
+impl LeaderElectionConfig {
+    pub fn new(lease_name: String, lease_namespace: String, lease_duration: Duration) -> Self {
+        Self {
+            lease_name,
+            lease_namespace,
+            identity: Self::default_identity(),
+            lease_duration,
+        }
+    }
+
+    /// Identity of this instance, the pod name when running in-cluster
+    fn default_identity() -> String {
+        let host = std::env::var("POD_NAME")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| "recommender".to_string());
+        format!("{}-{}", host, std::process::id())
+    }
+
+    /// Renew well before the lease expires so a slow API call doesn't lose it
+    pub fn renew_interval(&self) -> Duration {
+        self.lease_duration / 3
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GitConnectionType {
+    Ssh,
+    Https,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum GitProvider {
+    #[cfg_attr(feature = "cli", value(name = "github"))]
+    GitHub,
+    #[cfg_attr(feature = "cli", value(name = "gitlab"))]
+    GitLab,
+    Bitbucket,
+    Gitea,
+    /// Changes are pushed to `refs/for/<branch>` for review instead of opening a PR
+    Gerrit,
+    Generic, // For any other Git provider
+}
+
+impl GitProvider {
+    /// Detect provider from URL
+    pub fn from_url(url: &Url) -> Self {
+        let url_str = url.as_str();
+
+        if url_str.contains("github.com") {
+            GitProvider::GitHub
+        } else if url_str.contains("gitlab.com") || url_str.contains("gitlab") {
+            GitProvider::GitLab
+        } else if url_str.contains("bitbucket.org") {
+            GitProvider::Bitbucket
+        } else if url_str.contains("gitea") {
+            GitProvider::Gitea
+        } else if url_str.contains("gerrit") {
+            GitProvider::Gerrit
+        } else {
+            GitProvider::Generic
+        }
+    }
+
+    /// Get the API base URL for the provider
+    pub fn api_base_url(&self, git_url: &Url) -> Option<String> {
+        match self {
+            GitProvider::GitHub => {
+                // Extract base domain (supports GitHub Enterprise)
+                let host = git_url.host_str()?;
+                if host.contains("github.com") {
+                    Some("https://api.github.com".to_string())
+                } else {
+                    // GitHub Enterprise
+                    Some(format!("https://{}/api/v3", host))
+                }
+            }
+            GitProvider::GitLab => {
+                let host = git_url.host_str()?;
+                if host.contains("gitlab.com") {
+                    Some("https://gitlab.com/api/v4".to_string())
+                } else {
+                    // Self-hosted GitLab
+                    Some(format!("https://{}/api/v4", host))
+                }
+            }
+            GitProvider::Bitbucket => Some("https://api.bitbucket.org/2.0".to_string()),
+            GitProvider::Gitea => {
+                let host = git_url.host_str()?;
+                Some(format!("https://{}/api/v1", host))
+            }
+            GitProvider::Gerrit | GitProvider::Generic => None,
+        }
+    }
+}
+
+/// How an auto-merged pull request is merged
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum MergeMethod {
+    #[default]
+    Merge,
+    Squash,
+    Rebase,
+}
+
+#[derive(Clone, Debug)]
+pub struct UpdaterConfig {
+    pub git_url: Url,
+    pub connection_type: GitConnectionType,
+    pub auth_token: Option<String>,
+    pub auth_username: Option<String>,
+    pub provider: GitProvider,
+    /// Throttles calls to the provider API, e.g. when opening many PRs
+    pub rate_limiter: Option<RateLimiter>,
+    /// Decrypts SOPS-encrypted manifests, which are skipped when unset
+    pub sops: Option<SopsConfig>,
+    /// Merge created PRs once checks and approvals pass, with this method
+    pub auto_merge: Option<MergeMethod>,
+    /// Add a `Signed-off-by` trailer for the commit author, for DCO checks
+    pub signoff: bool,
+    /// Extra `key: value` trailers appended to commit messages
+    pub trailers: Vec<(String, String)>,
+    /// Write to these environment overlays instead of every manifest in the repository
+    pub overlays: Option<EnvironmentOverlays>,
+    /// Write Deployments of Helm charts to their values files
+    pub helm_values: Option<HelmValues>,
+    /// Also set the suggested CPU utilization target on HorizontalPodAutoscalers
+    pub hpa_targets: bool,
+}
+
+impl UpdaterConfig {
+    pub fn new(
+        git_url: Url,
+        auth_token: Option<String>,
+        auth_username: Option<String>,
+    ) -> Result<Self> {
+        let connection_type = match git_url.scheme() {
+            "ssh" => Ok(GitConnectionType::Ssh),
+            "https" | "http" => Ok(GitConnectionType::Https),
+            scheme => Err(RecommenderError::Config(ConfigError::InvalidValue(
+                format!("Unsupported git URL scheme: {}", scheme),
+            ))),
+        }?;
+
+        let provider = GitProvider::from_url(&git_url);
+        if let Some(token) = &auth_token {
+            register_secret(token);
+        }
+
+        Ok(Self {
+            git_url,
+            connection_type,
+            auth_token,
+            auth_username,
+            provider,
+            rate_limiter: None,
+            sops: None,
+            auto_merge: None,
+            signoff: false,
+            trailers: Vec::new(),
+            overlays: None,
+            helm_values: None,
+            hpa_targets: false,
+        })
+    }
+
+    /// Create config with explicit provider override
+    pub fn with_provider(
+        git_url: Url,
+        auth_token: Option<String>,
+        auth_username: Option<String>,
+        provider: GitProvider,
+    ) -> Result<Self> {
+        let connection_type = match git_url.scheme() {
+            "ssh" => Ok(GitConnectionType::Ssh),
+            "https" | "http" => Ok(GitConnectionType::Https),
+            scheme => Err(RecommenderError::Config(ConfigError::InvalidValue(
+                format!("Unsupported git URL scheme: {}", scheme),
+            ))),
+        }?;
+
+        if let Some(token) = &auth_token {
+            register_secret(token);
+        }
+
+        Ok(Self {
+            git_url,
+            connection_type,
+            auth_token,
+            auth_username,
+            provider,
+            rate_limiter: None,
+            sops: None,
+            auto_merge: None,
+            signoff: false,
+            trailers: Vec::new(),
+            overlays: None,
+            helm_values: None,
+            hpa_targets: false,
+        })
+    }
+
+    /// Use `provider` instead of the one detected from the URL, when given
+    pub fn with_provider_override(mut self, provider: Option<GitProvider>) -> Self {
+        if let Some(provider) = provider {
+            self.provider = provider;
+        }
+        self
+    }
+
+    /// Throttle provider API calls through `limiter`
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Enable auto-merge (GitHub, Gitea) or merge when pipeline succeeds (GitLab) on created PRs
+    pub fn with_auto_merge(mut self, method: MergeMethod) -> Self {
+        self.auto_merge = Some(method);
+        self
+    }
+
+    /// Sign off commits with the author identity, like `git commit --signoff`
+    pub fn with_signoff(mut self) -> Self {
+        self.signoff = true;
+        self
+    }
+
+    /// Append a `key: value` trailer to commit messages
+    pub fn with_trailer(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.trailers.push((key.into(), value.into()));
+        self
+    }
+
+    /// Edit SOPS-encrypted manifests through `sops`
+    pub fn with_sops(mut self, sops: SopsConfig) -> Self {
+        self.sops = Some(sops);
+        self
+    }
+
+    /// Write recommendations to the overlays of `overlays`, scaled per environment
+    pub fn with_overlays(mut self, overlays: EnvironmentOverlays) -> Self {
+        self.overlays = Some(overlays);
+        self
+    }
+
+    /// Write the resources of Deployments mapped in `helm` to their values files
+    pub fn with_helm_values(mut self, helm: HelmValues) -> Self {
+        self.helm_values = Some(helm);
+        self
+    }
+
+    /// Also set the suggested CPU utilization target on HorizontalPodAutoscalers
+    pub fn with_hpa_targets(mut self) -> Self {
+        self.hpa_targets = true;
+        self
+    }
+}
@@ -0,0 +1,339 @@
+//! `--config` files holding command-line settings
+//!
+//! A YAML or TOML file maps long flag names, e.g. `lookback-hours` or
+//! `lookback_hours`, to their values. The settings become arguments in front of
+//! the command line's own, leaving out flags given there, so the command line
+//! always wins over the file.
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use clap::{Arg, Command};
+use serde_json::{Map, Value};
+
+use crate::error::{ConfigError, Result};
+
+/// Flag naming the file, it can't be set from the file itself
+pub const CONFIG_FLAG: &str = "config";
+
+/// Path of `--config PATH` or `--config=PATH` in `args`, the binary name first
+pub fn config_path(args: &[OsString]) -> Option<PathBuf> {
+    let flag = format!("--{}", CONFIG_FLAG);
+    let mut tokens = args.iter().skip(1);
+    while let Some(token) = tokens.next() {
+        let token = token.to_string_lossy();
+        if token == "--" {
+            break;
+        }
+        if token == flag {
+            return tokens.next().map(PathBuf::from);
+        }
+        if let Some(path) = token.strip_prefix(&format!("{}=", flag)) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Insert the settings of the file at `path` into `args` for `command`
+///
+/// Keys must be long flags of `command` itself, not of its subcommands. Lists
+/// repeat the flag, `true` sets a switch and `false` or null leave it out.
+pub fn merge_config_file(
+    command: &Command,
+    mut args: Vec<OsString>,
+    path: &Path,
+) -> Result<Vec<OsString>> {
+    let settings = read_settings(path)?;
+    let given = given_flags(command, &args);
+
+    let mut file_args = Vec::new();
+    for (key, value) in settings {
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .filter(|arg| !matches!(arg.get_id().as_str(), CONFIG_FLAG | "help" | "version"))
+            .ok_or_else(|| ConfigError::InvalidValue(format!("unknown setting `{}`", key)))?;
+        if given.contains(arg.get_id().as_str()) {
+            continue;
+        }
+
+        if !arg.get_action().takes_values() {
+            match value {
+                Value::Bool(true) => file_args.push(OsString::from(format!("--{}", long))),
+                Value::Bool(false) | Value::Null => {}
+                _ => {
+                    return Err(ConfigError::InvalidValue(format!(
+                        "`{}` is a switch, set it to true or false",
+                        key
+                    ))
+                    .into());
+                }
+            }
+            continue;
+        }
+
+        let values = match value {
+            Value::Array(values) => values,
+            Value::Null => Vec::new(),
+            value => vec![value],
+        };
+        for value in values {
+            let value = match value {
+                Value::String(value) => value,
+                Value::Number(value) => value.to_string(),
+                Value::Bool(value) => value.to_string(),
+                _ => {
+                    return Err(ConfigError::InvalidValue(format!(
+                        "`{}` takes a value or a list of values",
+                        key
+                    ))
+                    .into());
+                }
+            };
+            // Inline, so values starting with `-` aren't taken for flags
+            file_args.push(OsString::from(format!("--{}={}", long, value)));
+        }
+    }
+
+    // Top-level flags go before the subcommand
+    let at = args.len().min(1);
+    args.splice(at..at, file_args);
+    Ok(args)
+}
+
+/// Top-level settings of a `.toml` file, or of YAML for any other extension
+fn read_settings(path: &Path) -> Result<Map<String, Value>> {
+    let content = std::fs::read_to_string(path)?;
+    let is_toml = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+    let settings = if is_toml {
+        let table: toml::Table = toml::from_str(&content)
+            .map_err(|e| ConfigError::InvalidValue(format!("invalid TOML: {}", e)))?;
+        toml_to_json(toml::Value::Table(table))
+    } else {
+        serde_yaml::from_str(&content)?
+    };
+
+    match settings {
+        Value::Object(settings) => Ok(settings),
+        Value::Null => Ok(Map::new()),
+        _ => Err(ConfigError::InvalidValue(
+            "expected a mapping of flag names to values".to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Ids of the top-level flags in `args`, up to the subcommand
+fn given_flags(command: &Command, args: &[OsString]) -> HashSet<String> {
+    let takes_value = |arg: &Arg| arg.get_action().takes_values();
+    let mut given = HashSet::new();
+    let mut tokens = args.iter().skip(1).map(|token| token.to_string_lossy());
+    while let Some(token) = tokens.next() {
+        if token == "--" {
+            break;
+        }
+        if let Some(long) = token.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, _)) => (name, true),
+                None => (long, false),
+            };
+            if let Some(arg) = command
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(name))
+            {
+                given.insert(arg.get_id().to_string());
+                if takes_value(arg) && !inline_value {
+                    tokens.next();
+                }
+            }
+        } else if let Some(shorts) = token.strip_prefix('-').filter(|s| !s.is_empty()) {
+            // Clustered switches like `-vq`, a value may follow the last short
+            for (i, short) in shorts.char_indices() {
+                let Some(arg) = command
+                    .get_arguments()
+                    .find(|arg| arg.get_short() == Some(short))
+                else {
+                    break;
+                };
+                given.insert(arg.get_id().to_string());
+                if takes_value(arg) {
+                    if i + short.len_utf8() == shorts.len() {
+                        tokens.next();
+                    }
+                    break;
+                }
+            }
+        } else {
+            // Every top-level option takes a flag, so this is the subcommand
+            break;
+        }
+    }
+    given
+}
+
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(value) => Value::String(value),
+        toml::Value::Integer(value) => Value::from(value),
+        toml::Value::Float(value) => Value::from(value),
+        toml::Value::Boolean(value) => Value::Bool(value),
+        toml::Value::Datetime(value) => Value::String(value.to_string()),
+        toml::Value::Array(values) => Value::Array(values.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ArgAction;
+
+    fn command() -> Command {
+        Command::new("recommender")
+            .arg(Arg::new(CONFIG_FLAG).long(CONFIG_FLAG))
+            .arg(Arg::new("namespace").long("namespace").short('n'))
+            .arg(Arg::new("lookback_hours").long("lookback-hours").short('l'))
+            .arg(
+                Arg::new("verbose")
+                    .long("verbose")
+                    .short('v')
+                    .action(ArgAction::Count),
+            )
+            .arg(
+                Arg::new("quiet")
+                    .long("quiet")
+                    .short('q')
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("exclude")
+                    .long("exclude")
+                    .action(ArgAction::Append),
+            )
+            .subcommand(Command::new("trend"))
+    }
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn given(tokens: &[&str]) -> Vec<String> {
+        let mut given: Vec<String> = given_flags(&command(), &args(tokens)).into_iter().collect();
+        given.sort();
+        given
+    }
+
+    /// Write `content` to a file named `name` of its own temporary directory
+    fn settings_file(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "recommender-config-{}-{}",
+            std::process::id(),
+            name.replace('.', "-")
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn finds_config_path_in_both_forms() {
+        assert_eq!(
+            config_path(&args(&["recommender", "--config", "a.yaml"])),
+            Some(PathBuf::from("a.yaml"))
+        );
+        assert_eq!(
+            config_path(&args(&["recommender", "-v", "--config=b.toml"])),
+            Some(PathBuf::from("b.toml"))
+        );
+        assert_eq!(
+            config_path(&args(&["recommender", "--", "--config", "a.yaml"])),
+            None
+        );
+    }
+
+    #[test]
+    fn given_flags_reads_long_and_clustered_short_flags() {
+        assert_eq!(
+            given(&["recommender", "--namespace", "shop", "--lookback-hours=24"]),
+            ["lookback_hours", "namespace"]
+        );
+        // The value of the last short of a cluster is the next token
+        assert_eq!(
+            given(&["recommender", "-vqn", "shop", "--exclude", "a"]),
+            ["exclude", "namespace", "quiet", "verbose"]
+        );
+        // An attached value ends the cluster
+        assert_eq!(given(&["recommender", "-nvq"]), ["namespace"]);
+    }
+
+    #[test]
+    fn given_flags_stop_at_the_subcommand() {
+        assert_eq!(
+            given(&["recommender", "-v", "trend", "--namespace", "shop"]),
+            ["verbose"]
+        );
+        assert_eq!(
+            given(&["recommender", "--", "--namespace", "shop"]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn yaml_settings_become_flags_before_the_subcommand() {
+        let path = settings_file(
+            "settings.yaml",
+            "lookback_hours: 48\nquiet: true\nverbose: false\nexclude: [web, \"-db\"]\n",
+        );
+        let merged = merge_config_file(&command(), args(&["recommender", "trend"]), &path).unwrap();
+        assert_eq!(
+            merged,
+            args(&[
+                "recommender",
+                "--exclude=web",
+                "--exclude=-db",
+                "--lookback-hours=48",
+                "--quiet",
+                "trend",
+            ])
+        );
+    }
+
+    #[test]
+    fn command_line_wins_over_toml_settings() {
+        let path = settings_file(
+            "settings.toml",
+            "namespace = \"shop\"\nlookback-hours = 12\n",
+        );
+        let merged =
+            merge_config_file(&command(), args(&["recommender", "-n", "staging"]), &path).unwrap();
+        assert_eq!(
+            merged,
+            args(&["recommender", "--lookback-hours=12", "-n", "staging"])
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_settings_and_values_for_switches() {
+        let path = settings_file("unknown.yaml", "lookback: 12\n");
+        let error = merge_config_file(&command(), args(&["recommender"]), &path).unwrap_err();
+        assert!(error.to_string().contains("unknown setting `lookback`"));
+
+        let path = settings_file("switch.yaml", "quiet: yes please\n");
+        let error = merge_config_file(&command(), args(&["recommender"]), &path).unwrap_err();
+        assert!(error.to_string().contains("is a switch"));
+
+        let path = settings_file("config.yaml", "config: other.yaml\n");
+        assert!(merge_config_file(&command(), args(&["recommender"]), &path).is_err());
+    }
+}
@@ -0,0 +1,392 @@
+//! Estimated monthly savings of recommendations
+//!
+//! Requests are priced per vCPU-hour and GiB-hour, by default at the on-demand
+//! Linux/x86 Fargate rates of us-east-1, so the estimate is what the requested
+//! capacity would cost rather than what the cluster's nodes are billed.
+
+use serde::{Deserialize, Serialize};
+
+use crate::recommender::{DayProfile, ResourceRecommendation};
+
+/// Hours in an average month, for monthly cost figures
+pub const HOURS_PER_MONTH: f64 = 730.0;
+
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Hourly prices of requested CPU and memory in dollars
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ResourcePrices {
+    /// Dollars per vCPU-hour
+    pub cpu_hourly: f64,
+    /// Dollars per GiB-hour
+    pub memory_gib_hourly: f64,
+}
+
+impl Default for ResourcePrices {
+    fn default() -> Self {
+        Self {
+            cpu_hourly: 0.04048,
+            memory_gib_hourly: 0.004445,
+        }
+    }
+}
+
+impl ResourcePrices {
+    pub fn new(cpu_hourly: f64, memory_gib_hourly: f64) -> Self {
+        Self {
+            cpu_hourly,
+            memory_gib_hourly,
+        }
+    }
+
+    /// Monthly cost of `cores` vCPUs and `bytes` of memory
+    pub fn monthly_cost(&self, cores: f64, bytes: f64) -> f64 {
+        (cores * self.cpu_hourly + bytes / GIB * self.memory_gib_hourly) * HOURS_PER_MONTH
+    }
+
+    /// Monthly savings of the recommended requests across the workload's replicas
+    ///
+    /// Negative when the container needs more than it requests now. A resource
+    /// without a current request counts as unchanged; None without either, and
+    /// for CronJobs and Jobs, whose pods only run part of the month.
+    pub fn monthly_savings(&self, recommendation: &ResourceRecommendation) -> Option<f64> {
+        if recommendation.workload_kind.is_batch() {
+            return None;
+        }
+        let current_cpu = recommendation.current_cpu_request;
+        let current_memory = recommendation.current_memory_request;
+        if current_cpu.is_none() && current_memory.is_none() {
+            return None;
+        }
+
+        let cpu = current_cpu.map_or(0.0, |current| {
+            current.value() - recommendation.recommended_cpu_request.value()
+        });
+        let memory = current_memory.map_or(0.0, |current| {
+            current.value() - recommendation.recommended_memory_request.value()
+        });
+        let replicas = recommendation.replicas.unwrap_or(1) as f64;
+        Some(self.monthly_cost(cpu, memory) * replicas)
+    }
+
+    /// Set the estimated savings of every recommendation
+    pub fn estimate(&self, recommendations: &mut [ResourceRecommendation]) {
+        for recommendation in recommendations {
+            recommendation.estimated_monthly_savings = self.monthly_savings(recommendation);
+        }
+    }
+
+    /// Prices of Fargate in `region` from the AWS Pricing API
+    ///
+    /// Uses the on-demand Linux/x86 rates, which Fargate and EKS on EC2 capacity
+    /// roughly track. The Pricing API is only served from us-east-1.
+    #[cfg(feature = "aws")]
+    pub async fn from_pricing_api(
+        region: &crate::aws_region::AwsRegion,
+        credentials: &aws_credential_types::Credentials,
+    ) -> crate::error::Result<Self> {
+        pricing::fargate_prices(region, credentials).await
+    }
+}
+
+/// Total estimated savings, None when no recommendation has an estimate
+///
+/// With `--day-split both` a container has a weekday and a weekend entry, only
+/// the weekday one counts so the container isn't counted twice.
+pub fn total_monthly_savings(recommendations: &[ResourceRecommendation]) -> Option<f64> {
+    recommendations
+        .iter()
+        .filter(|recommendation| recommendation.day_profile != Some(DayProfile::Weekend))
+        .filter_map(|recommendation| recommendation.estimated_monthly_savings)
+        .reduce(|total, savings| total + savings)
+}
+
+/// `$1,234.56`, with a leading `-` for costs
+pub fn format_dollars(amount: f64) -> String {
+    let cents = (amount.abs() * 100.0).round() as u64;
+    let dollars = (cents / 100).to_string();
+    let mut grouped = String::new();
+    for (i, digit) in dollars.chars().enumerate() {
+        if i > 0 && (dollars.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    let sign = if amount < 0.0 && cents > 0 { "-" } else { "" };
+    format!("{}${}.{:02}", sign, grouped, cents % 100)
+}
+
+#[cfg(feature = "aws")]
+mod pricing {
+    use std::time::SystemTime;
+
+    use aws_credential_types::Credentials;
+    use aws_sigv4::http_request::{SignableBody, SignableRequest, SigningSettings};
+    use aws_sigv4::sign::v4;
+    use aws_smithy_runtime_api::client::identity::Identity;
+    use log::debug;
+    use reqwest::header::{CONTENT_TYPE, HeaderName, HeaderValue};
+    use reqwest::{Method, Request};
+    use serde::Deserialize;
+    use serde_json::{Value, json};
+
+    use super::ResourcePrices;
+    use crate::aws_region::AwsRegion;
+    use crate::error::{AwsError, Result};
+    use crate::http::shared_client;
+
+    const ENDPOINT: &str = "https://api.pricing.us-east-1.amazonaws.com/";
+    const SIGNING_REGION: &str = "us-east-1";
+    const TARGET: &str = "AWSPriceListService.GetProducts";
+    const JSON_CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct GetProductsResponse {
+        #[serde(default)]
+        price_list: Vec<String>,
+        next_token: Option<String>,
+    }
+
+    pub(super) async fn fargate_prices(
+        region: &AwsRegion,
+        credentials: &Credentials,
+    ) -> Result<ResourcePrices> {
+        let mut cpu_hourly = None;
+        let mut memory_gib_hourly = None;
+        let mut next_token: Option<String> = None;
+
+        loop {
+            let mut body = json!({
+                "ServiceCode": "AmazonECS",
+                "FormatVersion": "aws_v1",
+                "Filters": [
+                    { "Type": "TERM_MATCH", "Field": "regionCode", "Value": region.as_str() },
+                ],
+                "MaxResults": 100,
+            });
+            if let Some(token) = &next_token {
+                body["NextToken"] = Value::String(token.clone());
+            }
+            let body = serde_json::to_vec(&body)
+                .map_err(|e| AwsError::ServiceError(format!("GetProducts request: {}", e)))?;
+
+            let response = shared_client()
+                .execute(sign(body, credentials)?)
+                .await
+                .map_err(|e| AwsError::ServiceError(format!("GetProducts: {}", e)))?;
+            let status = response.status();
+            if !status.is_success() {
+                let message = format!(
+                    "GetProducts returned HTTP {}: {}",
+                    status.as_u16(),
+                    response.text().await.unwrap_or_default()
+                );
+                return Err(if status.as_u16() == 403 {
+                    AwsError::PermissionDenied(message)
+                } else {
+                    AwsError::ServiceError(message)
+                }
+                .into());
+            }
+            let page: GetProductsResponse = response.json().await.map_err(|e| {
+                AwsError::ServiceError(format!("Invalid GetProducts response: {}", e))
+            })?;
+
+            for product in &page.price_list {
+                let Ok(product) = serde_json::from_str::<Value>(product) else {
+                    continue;
+                };
+                let Some(price) = on_demand_usd(&product) else {
+                    continue;
+                };
+                match product["product"]["attributes"]["usagetype"].as_str() {
+                    Some(usage_type) if is_usage_type(usage_type, "Fargate-vCPU-Hours:perCPU") => {
+                        cpu_hourly.get_or_insert(price);
+                    }
+                    Some(usage_type) if is_usage_type(usage_type, "Fargate-GB-Hours") => {
+                        memory_gib_hourly.get_or_insert(price);
+                    }
+                    _ => {}
+                }
+            }
+
+            match page.next_token.filter(|token| !token.is_empty()) {
+                Some(token) if cpu_hourly.is_none() || memory_gib_hourly.is_none() => {
+                    next_token = Some(token);
+                }
+                _ => break,
+            }
+        }
+
+        match (cpu_hourly, memory_gib_hourly) {
+            (Some(cpu_hourly), Some(memory_gib_hourly)) => {
+                debug!(
+                    "Fargate in {}: ${}/vCPU-hour, ${}/GiB-hour",
+                    region.as_str(),
+                    cpu_hourly,
+                    memory_gib_hourly
+                );
+                Ok(ResourcePrices::new(cpu_hourly, memory_gib_hourly))
+            }
+            _ => Err(AwsError::ServiceError(format!(
+                "No Fargate prices for {} in the Pricing API",
+                region.as_str()
+            ))
+            .into()),
+        }
+    }
+
+    /// Whether `usage_type` is `name`, optionally prefixed by a region code like
+    /// `EUW2-`, leaving out Spot, ARM and Windows variants
+    fn is_usage_type(usage_type: &str, name: &str) -> bool {
+        usage_type == name
+            || usage_type
+                .split_once('-')
+                .is_some_and(|(_, rest)| rest == name)
+    }
+
+    /// First on-demand price per unit in USD
+    fn on_demand_usd(product: &Value) -> Option<f64> {
+        product["terms"]["OnDemand"]
+            .as_object()?
+            .values()
+            .filter_map(|term| term["priceDimensions"].as_object())
+            .flat_map(|dimensions| dimensions.values())
+            .find_map(|dimension| dimension["pricePerUnit"]["USD"].as_str()?.parse().ok())
+    }
+
+    /// Build a `GetProducts` request signed with AWS SigV4
+    fn sign(body: Vec<u8>, credentials: &Credentials) -> Result<Request> {
+        let headers = [
+            ("content-type", JSON_CONTENT_TYPE),
+            ("x-amz-target", TARGET),
+        ];
+        let signable_request = SignableRequest::new(
+            Method::POST.as_str(),
+            ENDPOINT,
+            headers.iter().copied(),
+            SignableBody::Bytes(&body),
+        )
+        .map_err(|e| AwsError::ServiceError(e.to_string()))?;
+
+        let identity: Identity = credentials.clone().into();
+        let signing_params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(SIGNING_REGION)
+            .name("pricing")
+            .time(SystemTime::now())
+            .settings(SigningSettings::default())
+            .build()
+            .map_err(|e| AwsError::ServiceError(e.to_string()))?
+            .into();
+
+        let (signing_instructions, _) =
+            aws_sigv4::http_request::sign(signable_request, &signing_params)
+                .map_err(|e| AwsError::ServiceError(e.to_string()))?
+                .into_parts();
+
+        let url = ENDPOINT
+            .parse::<url::Url>()
+            .map_err(|e| AwsError::ServiceError(e.to_string()))?;
+        let mut request = Request::new(Method::POST, url);
+        for (name, value) in signing_instructions.headers() {
+            let header_name: HeaderName = name.parse().unwrap();
+            let header_value: HeaderValue = value.parse().unwrap();
+            request.headers_mut().insert(header_name, header_value);
+        }
+        request
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(JSON_CONTENT_TYPE));
+        request.headers_mut().insert(
+            HeaderName::from_static("x-amz-target"),
+            HeaderValue::from_static(TARGET),
+        );
+        *request.body_mut() = Some(body.into());
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn recommendation(
+        current: [Value; 2],
+        recommended: [&str; 2],
+        replicas: Option<u32>,
+    ) -> ResourceRecommendation {
+        let stats = json!({
+            "samples": 0, "min": 0.0, "max": 0.0, "avg": 0.0, "p50": 0.0, "p95": 0.0, "p99": 0.0,
+        });
+        let [current_cpu, current_memory] = current;
+        serde_json::from_value(json!({
+            "deployment": "web",
+            "container": "app",
+            "namespace": "shop",
+            "current_cpu_request": current_cpu,
+            "current_memory_request": current_memory,
+            "current_cpu_limit": "not set",
+            "current_memory_limit": "not set",
+            "recommended_cpu_request": recommended[0],
+            "recommended_cpu_limit": recommended[0],
+            "recommended_memory_request": recommended[1],
+            "recommended_memory_limit": recommended[1],
+            "cpu_usage_stats": stats,
+            "memory_usage_stats": stats,
+            "recommendation_reason": "",
+            "confidence": 1.0,
+            "replicas": replicas,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn savings_cover_all_replicas() {
+        let prices = ResourcePrices::new(0.04, 0.005);
+        let recommendation = recommendation([json!("1"), json!("2Gi")], ["500m", "1Gi"], Some(2));
+        // (0.5 vCPU * $0.04 + 1 GiB * $0.005) * 730 hours * 2 replicas
+        let savings = prices.monthly_savings(&recommendation).unwrap();
+        assert!((savings - 36.5).abs() < 1e-9, "{}", savings);
+    }
+
+    #[test]
+    fn savings_are_negative_when_growing() {
+        let prices = ResourcePrices::new(0.04, 0.005);
+        let recommendation = recommendation([json!("250m"), json!("1Gi")], ["500m", "1Gi"], None);
+        let savings = prices.monthly_savings(&recommendation).unwrap();
+        assert!(
+            (savings + 0.25 * 0.04 * HOURS_PER_MONTH).abs() < 1e-9,
+            "{}",
+            savings
+        );
+    }
+
+    #[test]
+    fn missing_requests_count_as_unchanged() {
+        let prices = ResourcePrices::new(0.04, 0.005);
+        let memory_only = recommendation([json!("not set"), json!("2Gi")], ["500m", "1Gi"], None);
+        let savings = prices.monthly_savings(&memory_only).unwrap();
+        assert!(
+            (savings - 0.005 * HOURS_PER_MONTH).abs() < 1e-9,
+            "{}",
+            savings
+        );
+
+        let unset = recommendation([json!("not set"), json!("not set")], ["500m", "1Gi"], None);
+        assert_eq!(prices.monthly_savings(&unset), None);
+    }
+
+    #[test]
+    fn formats_dollars_with_thousands_separators() {
+        assert_eq!(format_dollars(0.0), "$0.00");
+        assert_eq!(format_dollars(12.3), "$12.30");
+        assert_eq!(format_dollars(999.995), "$1,000.00");
+        assert_eq!(format_dollars(1_234_567.891), "$1,234,567.89");
+        assert_eq!(format_dollars(-42.5), "-$42.50");
+        // Rounds to zero, so no sign
+        assert_eq!(format_dollars(-0.001), "$0.00");
+    }
+}
@@ -0,0 +1,62 @@
+//! Git tokens remembered in the platform keyring
+//!
+//! Tokens are stored per git host in the macOS Keychain, the Secret Service on
+//! Linux or the Windows Credential Manager. Without the `keyring` feature nothing
+//! is ever found or stored.
+
+use url::Url;
+
+use crate::error::Result;
+
+/// Keyring service the tokens are stored under
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "k8s-autorightsizing";
+
+/// Keyring account of a repository URL, its host and port
+pub fn account(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// Token saved for the host of `url`, None when there is none or no keyring
+#[cfg(feature = "keyring")]
+pub fn stored_token(url: &Url) -> Option<String> {
+    let account = account(url)?;
+    match keyring::Entry::new(SERVICE, &account).and_then(|entry| entry.get_password()) {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            log::debug!("No git token for {} in the keyring: {}", account, e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn stored_token(_url: &Url) -> Option<String> {
+    None
+}
+
+/// Save `token` for the host of `url`, replacing a previous one
+#[cfg(feature = "keyring")]
+pub fn store_token(url: &Url, token: &str) -> Result<()> {
+    let Some(account) = account(url) else {
+        return Ok(());
+    };
+    keyring::Entry::new(SERVICE, &account)
+        .and_then(|entry| entry.set_password(token))
+        .map_err(|e| {
+            crate::RecommenderError::Other(format!(
+                "Failed to save the git token for {} in the keyring: {}",
+                account, e
+            ))
+        })
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store_token(_url: &Url, _token: &str) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,424 @@
+//! Guided checks for workloads whose usage queries come back empty
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::SystemTime;
+
+use log::debug;
+
+use crate::error::Result;
+use crate::kubernetes::DeploymentResources;
+use crate::prometheus::PrometheusClient;
+use crate::query::{CPU_USAGE_METRIC, MEMORY_USAGE_METRIC, pod_pattern};
+
+/// Series per container above which the labels are likely exploding
+const HIGH_CARDINALITY_PER_CONTAINER: usize = 500;
+
+/// Workload to diagnose
+#[derive(Debug, Clone)]
+pub struct DiagnoseTarget {
+    pub namespace: String,
+    pub deployment: String,
+    /// Containers from the cluster, None when the cluster wasn't reachable
+    pub containers: Option<Vec<String>>,
+}
+
+impl DiagnoseTarget {
+    pub fn new(namespace: impl Into<String>, deployment: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            deployment: deployment.into(),
+            containers: None,
+        }
+    }
+
+    /// Compare the metrics against the containers of the live Deployment
+    pub fn with_workload(mut self, workload: &DeploymentResources) -> Self {
+        self.containers = Some(workload.containers.iter().map(|c| c.name.clone()).collect());
+        self
+    }
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// One step of a diagnosis, with what to do about it
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+/// Result of [`diagnose`], printed as a guided explanation
+#[derive(Debug, Clone)]
+pub struct Diagnosis {
+    pub target: DiagnoseTarget,
+    pub lookback_hours: f64,
+    pub checks: Vec<Check>,
+}
+
+impl Diagnosis {
+    /// Whether any check failed, i.e. recommendations will be missing
+    pub fn has_failures(&self) -> bool {
+        self.checks.iter().any(|c| c.status == CheckStatus::Fail)
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Diagnosing {}/{} over the last {} hours\n",
+            self.target.namespace, self.target.deployment, self.lookback_hours
+        )?;
+
+        for check in &self.checks {
+            let mark = match check.status {
+                CheckStatus::Pass => "✓",
+                CheckStatus::Warn => "!",
+                CheckStatus::Fail => "✗",
+            };
+            writeln!(f, "  {} {}: {}", mark, check.name, check.detail)?;
+            if let Some(hint) = &check.hint {
+                writeln!(f, "      → {}", hint)?;
+            }
+        }
+
+        writeln!(f)?;
+        match self.checks.iter().find(|c| c.status == CheckStatus::Fail) {
+            Some(first) => write!(
+                f,
+                "No usable data: start with the first failed check ({}), later checks depend on it.",
+                first.name
+            ),
+            None => write!(
+                f,
+                "Usage data looks complete, recommendations should be generated."
+            ),
+        }
+    }
+}
+
+/// Run the checks from broad to narrow, stopping once nothing further can be found
+///
+/// Goes from the metric names to the namespace, the workload's pods and finally
+/// its containers, mirroring the matchers of the recommendation queries.
+pub async fn diagnose(
+    client: &PrometheusClient,
+    target: DiagnoseTarget,
+    start: SystemTime,
+    end: SystemTime,
+    lookback_hours: f64,
+) -> Result<Diagnosis> {
+    let mut diagnosis = Diagnosis {
+        target,
+        lookback_hours,
+        checks: Vec::new(),
+    };
+    let target = diagnosis.target.clone();
+    let checks = &mut diagnosis.checks;
+
+    // 1. Are the cAdvisor metrics in the workspace at all?
+    let names_matcher = format!(
+        r#"{{__name__=~"{}|{}"}}"#,
+        CPU_USAGE_METRIC, MEMORY_USAGE_METRIC
+    );
+    let names: BTreeSet<String> = client
+        .label_values("__name__", &[&names_matcher], start, end)
+        .await?
+        .into_iter()
+        .collect();
+    let missing: Vec<&str> = [CPU_USAGE_METRIC, MEMORY_USAGE_METRIC]
+        .into_iter()
+        .filter(|name| !names.contains(*name))
+        .collect();
+    if missing.len() == 2 {
+        checks.push(Check::fail(
+            "Metric names",
+            format!("neither {} nor {} has any series", CPU_USAGE_METRIC, MEMORY_USAGE_METRIC),
+            "cAdvisor metrics don't reach the workspace; check that the Prometheus agent scrapes the kubelet's /metrics/cadvisor endpoint and remote-writes to this workspace",
+        ));
+        return Ok(diagnosis);
+    } else if !missing.is_empty() {
+        checks.push(Check::fail(
+            "Metric names",
+            format!("{} has no series", missing.join(", ")),
+            "a relabeling rule probably drops it; both metrics are needed for a recommendation",
+        ));
+    } else {
+        checks.push(Check::pass(
+            "Metric names",
+            "CPU and memory metrics are present",
+        ));
+    }
+
+    // 2. Does the namespace label exist?
+    let namespaces = client
+        .label_values("namespace", &[&names_matcher], start, end)
+        .await?;
+    if !namespaces.contains(&target.namespace) {
+        let similar: Vec<&str> = namespaces
+            .iter()
+            .map(String::as_str)
+            .filter(|ns| ns.contains(&target.namespace) || target.namespace.contains(ns))
+            .take(5)
+            .collect();
+        let hint = if similar.is_empty() {
+            format!(
+                "{} namespaces have data; check the spelling, or whether the agent's relabeling renames the `namespace` label",
+                namespaces.len()
+            )
+        } else {
+            format!("did you mean {}?", similar.join(", "))
+        };
+        checks.push(Check::fail(
+            "Namespace",
+            format!("no series with namespace=\"{}\"", target.namespace),
+            hint,
+        ));
+        return Ok(diagnosis);
+    }
+    checks.push(Check::pass(
+        "Namespace",
+        format!("namespace=\"{}\" has series", target.namespace),
+    ));
+
+    // 3. Do the workload's pods have series, and does the pod pattern match them?
+    let loose_matcher = format!(
+        r#"{}{{namespace="{}",pod=~"{}.*"}}"#,
+        CPU_USAGE_METRIC, target.namespace, target.deployment
+    );
+    let series = client.series(&[&loose_matcher], start, end).await?;
+    debug!("{} series match {}", series.len(), loose_matcher);
+
+    let pattern = pod_matcher(&target.deployment);
+    let mut matched_pods = BTreeSet::new();
+    let mut other_pods = BTreeSet::new();
+    for labels in &series {
+        if let Some(pod) = labels.get("pod") {
+            if pattern(pod) {
+                matched_pods.insert(pod.as_str());
+            } else {
+                other_pods.insert(pod.as_str());
+            }
+        }
+    }
+
+    if matched_pods.is_empty() {
+        let (detail, hint) = if other_pods.is_empty() {
+            (
+                format!("no pods named {}-* had CPU series", target.deployment),
+                "the deployment had no running pods in the window, or its pods aren't named after it (e.g. a StatefulSet or a bare pod)".to_string(),
+            )
+        } else {
+            (
+                format!(
+                    "{} pods start with {} but none look like <deployment>-<hash>-<suffix>",
+                    other_pods.len(),
+                    target.deployment
+                ),
+                format!(
+                    "found {}; only Deployment pods are analyzed",
+                    preview(&other_pods)
+                ),
+            )
+        };
+        checks.push(Check::fail("Pod series", detail, hint));
+        return Ok(diagnosis);
+    }
+    checks.push(Check::pass(
+        "Pod series",
+        format!(
+            "{} pods of the deployment have CPU series",
+            matched_pods.len()
+        ),
+    ));
+    if !other_pods.is_empty() {
+        checks.push(Check::warn(
+            "Similar pods",
+            format!(
+                "{} pods share the name prefix but belong to other workloads",
+                other_pods.len()
+            ),
+            format!("{} are excluded from the analysis", preview(&other_pods)),
+        ));
+    }
+
+    // 4. Which containers have series?
+    let mut per_container: BTreeMap<&str, usize> = BTreeMap::new();
+    for labels in series
+        .iter()
+        .filter(|l| l.get("pod").is_some_and(|p| pattern(p)))
+    {
+        let container = labels.get("container").map_or("", String::as_str);
+        *per_container.entry(container).or_default() += 1;
+    }
+    let containers: BTreeSet<&str> = per_container
+        .keys()
+        .copied()
+        .filter(|c| !c.is_empty() && *c != "POD")
+        .collect();
+
+    if containers.is_empty() {
+        let legacy = series.iter().any(|l| l.contains_key("container_name"));
+        let hint = if legacy {
+            "series use the pre-1.16 `container_name` label; relabel it to `container`"
+        } else {
+            "only pod-level cgroup series exist (container=\"\"); the agent probably drops per-container series"
+        };
+        checks.push(Check::fail(
+            "Containers",
+            "no series carry a container name",
+            hint,
+        ));
+        return Ok(diagnosis);
+    }
+
+    match &target.containers {
+        Some(expected) => {
+            let absent: Vec<&str> = expected
+                .iter()
+                .map(String::as_str)
+                .filter(|c| !containers.contains(c))
+                .collect();
+            if absent.is_empty() {
+                checks.push(Check::pass(
+                    "Containers",
+                    format!("all {} containers have series", expected.len()),
+                ));
+            } else {
+                checks.push(Check::fail(
+                    "Containers",
+                    format!("no series for {}", absent.join(", ")),
+                    format!(
+                        "metrics only have {}; init containers and containers renamed during the window have no usage",
+                        containers.iter().copied().collect::<Vec<_>>().join(", ")
+                    ),
+                ));
+            }
+        }
+        None => checks.push(Check::pass(
+            "Containers",
+            format!(
+                "series for {} (cluster not reachable, not compared with the spec)",
+                containers.iter().copied().collect::<Vec<_>>().join(", ")
+            ),
+        )),
+    }
+
+    // 5. Memory needs the same labels as CPU
+    let memory_matcher = format!(
+        r#"{}{{namespace="{}",pod=~"{}"}}"#,
+        MEMORY_USAGE_METRIC,
+        target.namespace,
+        pod_pattern(&target.deployment)
+    );
+    let memory_series = client.series(&[&memory_matcher], start, end).await?;
+    if memory_series.is_empty() {
+        checks.push(Check::fail(
+            "Memory series",
+            format!("CPU series exist but {} has none", MEMORY_USAGE_METRIC),
+            "the metric is dropped for this namespace, check the agent's metric_relabel_configs",
+        ));
+    } else {
+        checks.push(Check::pass(
+            "Memory series",
+            format!("{} series", memory_series.len()),
+        ));
+    }
+
+    // 6. Cardinality and duplicate scrapes
+    let jobs: BTreeSet<&str> = series
+        .iter()
+        .filter_map(|l| l.get("job").map(String::as_str))
+        .collect();
+    let busiest = per_container
+        .iter()
+        .filter(|(c, _)| containers.contains(*c))
+        .max_by_key(|(_, count)| **count);
+    if jobs.len() > 1 {
+        checks.push(Check::warn(
+            "Label cardinality",
+            format!("series come from {} scrape jobs", jobs.len()),
+            format!(
+                "{} scrape the same containers, so samples are counted more than once and skew percentiles",
+                jobs.iter().copied().collect::<Vec<_>>().join(", ")
+            ),
+        ));
+    } else if let Some((container, count)) =
+        busiest.filter(|(_, count)| **count > HIGH_CARDINALITY_PER_CONTAINER)
+    {
+        checks.push(Check::warn(
+            "Label cardinality",
+            format!("{} series for container {}", count, container),
+            "high pod churn or extra labels make queries slow; consider a shorter lookback",
+        ));
+    } else {
+        checks.push(Check::pass(
+            "Label cardinality",
+            format!("{} series across {} pods", series.len(), matched_pods.len()),
+        ));
+    }
+
+    Ok(diagnosis)
+}
+
+/// Rust equivalent of [`pod_pattern`], anchored like PromQL regexes
+fn pod_matcher(deployment: &str) -> impl Fn(&str) -> bool + use<> {
+    let prefix = format!("{}-", deployment);
+    let segment = |s: &str| {
+        !s.is_empty()
+            && s.bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+    };
+    move |pod: &str| {
+        pod.strip_prefix(prefix.as_str())
+            .and_then(|rest| rest.split_once('-'))
+            .is_some_and(|(hash, suffix)| segment(hash) && segment(suffix))
+    }
+}
+
+/// First few names of a set, for hints
+fn preview(names: &BTreeSet<&str>) -> String {
+    let shown: Vec<&str> = names.iter().copied().take(3).collect();
+    match names.len() - shown.len() {
+        0 => shown.join(", "),
+        more => format!("{} and {} more", shown.join(", "), more),
+    }
+}
@@ -0,0 +1,272 @@
+use thiserror::Error;
+
+/// Main error type for the recommender application
+#[derive(Error, Debug)]
+pub enum RecommenderError {
+    /// AWS-related errors
+    #[error("AWS error: {0}")]
+    Aws(#[from] AwsError),
+
+    /// Prometheus query errors
+    #[error("Prometheus error: {0}")]
+    Prometheus(#[from] PrometheusError),
+
+    /// Kubernetes API errors
+    #[error("Kubernetes error: {0}")]
+    Kubernetes(#[from] KubernetesError),
+
+    /// Configuration errors
+    #[error("Configuration error: {0}")]
+    Config(#[from] ConfigError),
+
+    /// I/O errors
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Parsing errors
+    #[error("Parse error: {0}")]
+    Parse(String),
+
+    /// Network/HTTP errors
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// Invalid input/arguments
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// Resource not found
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Insufficient data for analysis
+    #[error("Insufficient data: {0}")]
+    InsufficientData(String),
+
+    /// Generic error with message
+    #[error("{0}")]
+    Other(String),
+
+    /// Git operation errors
+    #[cfg(feature = "git")]
+    #[error("Git error: {0}")]
+    Git(#[from] git2::Error),
+
+    /// YAML parsing/serialization errors
+    #[error("YAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    /// HTTP request to a git provider API failed
+    #[cfg(feature = "git")]
+    #[error("{context}: {source}")]
+    Http {
+        context: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// Git provider API answered with an error status
+    #[error("API error ({status}): {message}")]
+    HttpStatus { status: u16, message: String },
+
+    /// Apply/update operation errors
+    #[error("Apply error: {0}")]
+    ApplyError(String),
+
+    /// The run was cancelled, e.g. by Ctrl-C
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    /// `--fail-on-change` found containers whose requests are off by more than the threshold
+    #[error("{0} containers need their requests changed")]
+    ChangesRequired(usize),
+}
+
+/// AWS-specific errors
+#[derive(Error, Debug)]
+pub enum AwsError {
+    /// Authentication failure
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+
+    /// Authorization/permission denied
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// AWS service error
+    #[error("AWS service error: {0}")]
+    ServiceError(String),
+
+    /// Invalid AWS region
+    #[error("Invalid region: {0}")]
+    InvalidRegion(String),
+
+    /// AWS resource not found
+    #[error("Resource not found: {0}")]
+    ResourceNotFound(String),
+
+    /// AWS rate limiting
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+}
+
+/// Prometheus-specific errors
+#[derive(Error, Debug)]
+pub enum PrometheusError {
+    /// Connection failed
+    #[error("Connection failed: {0}")]
+    ConnectionFailed(String),
+
+    /// Connection error (generic)
+    #[error("Connection error: {0}")]
+    ConnectionError(String),
+
+    /// Authentication failed
+    #[error("Authentication failed")]
+    AuthenticationFailed,
+
+    /// Query syntax error
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
+    /// Query execution error
+    #[error("Query failed: {0}")]
+    QueryFailed(String),
+
+    /// Query error (generic)
+    #[error("Query error: {0}")]
+    QueryError(String),
+
+    /// No data returned
+    #[error("No data: {0}")]
+    NoData(String),
+
+    /// Invalid response format
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+
+    /// Timeout
+    #[error("Timeout: {0}")]
+    Timeout(String),
+
+    /// HTTP request could not be sent or its body not decoded
+    #[cfg(feature = "aws")]
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    /// Server answered with an error status
+    #[error("HTTP {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+}
+
+/// Kubernetes-specific errors
+#[derive(Error, Debug)]
+pub enum KubernetesError {
+    /// API server connection failed
+    #[error("Connection failed: {0}")]
+    ConnectionFailed(#[source] kube::Error),
+
+    /// Kubeconfig could not be loaded
+    #[error("Invalid kubeconfig: {0}")]
+    Kubeconfig(#[from] kube::config::KubeconfigError),
+
+    /// Resource not found
+    #[error("Resource not found: {0}")]
+    ResourceNotFound(String),
+
+    /// Invalid resource specification
+    #[error("Invalid resource: {0}")]
+    InvalidResource(String),
+
+    /// API error
+    #[error("API error: {0}")]
+    ApiError(#[from] kube::Error),
+}
+
+/// Configuration-specific errors
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// Missing required configuration
+    #[error("Missing required: {0}")]
+    MissingRequired(String),
+
+    /// Invalid configuration value
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+
+    /// Invalid value of a command-line flag
+    #[error("Invalid --{flag}: {message}")]
+    InvalidFlag { flag: &'static str, message: String },
+
+    /// Configuration file error
+    #[error("File error: {0}")]
+    FileError(String),
+}
+
+/// Helper type alias for Results
+pub type Result<T> = std::result::Result<T, RecommenderError>;
+
+impl RecommenderError {
+    /// HTTP status code of the failed request, when the error came from one
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            RecommenderError::Prometheus(PrometheusError::HttpStatus { status, .. })
+            | RecommenderError::HttpStatus { status, .. } => Some(*status),
+            RecommenderError::Kubernetes(KubernetesError::ApiError(kube::Error::Api(resp))) => {
+                Some(resp.code)
+            }
+            #[cfg(any(feature = "aws", feature = "git"))]
+            _ => self
+                .request_error()
+                .and_then(|e| e.status())
+                .map(|s| s.as_u16()),
+            #[cfg(not(any(feature = "aws", feature = "git")))]
+            _ => None,
+        }
+    }
+
+    /// Whether the failed operation may succeed when tried again
+    ///
+    /// True for timeouts, connection problems, throttling (429) and server errors (5xx).
+    pub fn is_retryable(&self) -> bool {
+        if let Some(status) = self.status() {
+            return status == 429 || status >= 500;
+        }
+
+        #[cfg(any(feature = "aws", feature = "git"))]
+        if let Some(e) = self.request_error() {
+            return e.is_timeout() || e.is_connect();
+        }
+
+        match self {
+            RecommenderError::Network(_)
+            | RecommenderError::Prometheus(PrometheusError::Timeout(_))
+            | RecommenderError::Aws(AwsError::RateLimited(_))
+            | RecommenderError::Kubernetes(KubernetesError::ConnectionFailed(_)) => true,
+            RecommenderError::Kubernetes(KubernetesError::ApiError(e)) => matches!(
+                e,
+                kube::Error::HyperError(_) | kube::Error::Service(_) | kube::Error::ReadEvents(_)
+            ),
+            RecommenderError::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+
+    /// Underlying HTTP client error, if any
+    #[cfg(any(feature = "aws", feature = "git"))]
+    fn request_error(&self) -> Option<&reqwest::Error> {
+        match self {
+            #[cfg(feature = "aws")]
+            RecommenderError::Prometheus(PrometheusError::Request(e)) => Some(e),
+            #[cfg(feature = "git")]
+            RecommenderError::Http { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
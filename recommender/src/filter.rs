@@ -0,0 +1,428 @@
+//! Scoping the analysis to a subset of the workloads
+//!
+//! A label selector narrows the Deployments the way `kubectl get -l` does, name
+//! patterns include or exclude them by `name` or `namespace/name`. The cluster
+//! loader hands the selector to the API server, other sources match it against
+//! the labels they know.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use log::info;
+
+use crate::error::{ConfigError, Result};
+use crate::kubernetes::{DeploymentResources, WorkloadSource};
+
+/// Kubernetes label selector, e.g. `app=foo,tier!=cache,env in (prod,staging)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<Requirement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, Vec<String>),
+    NotIn(String, Vec<String>),
+    Exists(String),
+    DoesNotExist(String),
+}
+
+impl LabelSelector {
+    /// Whether `labels` satisfy every requirement
+    ///
+    /// Like the API server, `!=` and `notin` also match labels that aren't set.
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| match requirement {
+                Requirement::Equals(key, value) => labels.get(key) == Some(value),
+                Requirement::NotEquals(key, value) => labels.get(key) != Some(value),
+                Requirement::In(key, values) => labels.get(key).is_some_and(|v| values.contains(v)),
+                Requirement::NotIn(key, values) => {
+                    labels.get(key).is_none_or(|v| !values.contains(v))
+                }
+                Requirement::Exists(key) => labels.contains_key(key),
+                Requirement::DoesNotExist(key) => !labels.contains_key(key),
+            })
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = |term: &str, reason: &str| {
+            ConfigError::InvalidValue(format!("invalid selector `{}`: {}", term, reason))
+        };
+        let key = |key: &str, term: &str| {
+            let key = key.trim();
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                Err(invalid(term, "expected a label key"))
+            } else {
+                Ok(key.to_string())
+            }
+        };
+
+        let mut requirements = Vec::new();
+        for term in split_terms(s) {
+            let term = term.trim();
+            if term.is_empty() {
+                return Err(invalid(s, "empty requirement"));
+            }
+
+            let requirement = if let Some(open) = term.find('(') {
+                let values = term[open + 1..]
+                    .strip_suffix(')')
+                    .ok_or_else(|| invalid(term, "missing `)`"))?
+                    .split(',')
+                    .map(|value| value.trim().to_string())
+                    .collect();
+                match term[..open].split_whitespace().collect::<Vec<_>>()[..] {
+                    [name, "in"] => Requirement::In(key(name, term)?, values),
+                    [name, "notin"] => Requirement::NotIn(key(name, term)?, values),
+                    _ => {
+                        return Err(invalid(
+                            term,
+                            "expected `key in (...)` or `key notin (...)`",
+                        ));
+                    }
+                }
+            } else if let Some(name) = term.strip_prefix('!') {
+                Requirement::DoesNotExist(key(name, term)?)
+            } else if let Some((name, value)) = term.split_once("!=") {
+                Requirement::NotEquals(key(name, term)?, value.trim().to_string())
+            } else if let Some((name, value)) = term.split_once("==") {
+                Requirement::Equals(key(name, term)?, value.trim().to_string())
+            } else if let Some((name, value)) = term.split_once('=') {
+                Requirement::Equals(key(name, term)?, value.trim().to_string())
+            } else {
+                Requirement::Exists(key(term, term)?)
+            };
+            requirements.push(requirement);
+        }
+        Ok(Self { requirements })
+    }
+}
+
+/// The selector in the syntax of the API server's `labelSelector` parameter
+impl fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .requirements
+            .iter()
+            .map(|requirement| match requirement {
+                Requirement::Equals(key, value) => format!("{}={}", key, value),
+                Requirement::NotEquals(key, value) => format!("{}!={}", key, value),
+                Requirement::In(key, values) => format!("{} in ({})", key, values.join(",")),
+                Requirement::NotIn(key, values) => {
+                    format!("{} notin ({})", key, values.join(","))
+                }
+                Requirement::Exists(key) => key.clone(),
+                Requirement::DoesNotExist(key) => format!("!{}", key),
+            })
+            .collect();
+        f.write_str(&terms.join(","))
+    }
+}
+
+/// Comma-separated terms, leaving the commas of `in (...)` lists alone
+fn split_terms(selector: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                terms.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    terms.push(&selector[start..]);
+    terms
+}
+
+/// Which workloads to analyze, every one unless narrowed down
+///
+/// Patterns are globs with `*` and `?`. Those containing a `/` match
+/// `namespace/name`, the others just the name. A workload is analyzed when it
+/// matches the selector and any include pattern, and no exclude pattern.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkloadFilter {
+    pub selector: Option<LabelSelector>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl WorkloadFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_selector(mut self, selector: LabelSelector) -> Self {
+        self.selector = Some(selector);
+        self
+    }
+
+    /// Only analyze workloads matching one of `patterns`, all when empty
+    pub fn with_include(mut self, patterns: Vec<String>) -> Self {
+        self.include = patterns;
+        self
+    }
+
+    /// Leave out workloads matching one of `patterns`, even if included
+    pub fn with_exclude(mut self, patterns: Vec<String>) -> Self {
+        self.exclude = patterns;
+        self
+    }
+
+    /// Whether the filter keeps every workload
+    pub fn is_empty(&self) -> bool {
+        self.selector.is_none() && self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether the patterns keep the workload `name` in `namespace`
+    pub fn includes_name(&self, namespace: &str, name: &str) -> bool {
+        let qualified = format!("{}/{}", namespace, name);
+        let matches = |pattern: &String| {
+            if pattern.contains('/') {
+                glob_match(pattern, &qualified)
+            } else {
+                glob_match(pattern, name)
+            }
+        };
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    /// Whether `deployment` passes the selector and the patterns
+    pub fn includes(&self, deployment: &DeploymentResources) -> bool {
+        self.selector
+            .as_ref()
+            .is_none_or(|selector| selector.matches(&deployment.labels))
+            && self.includes_name(&deployment.namespace, &deployment.name)
+    }
+}
+
+/// Workloads of another source that pass a [`WorkloadFilter`]
+///
+/// For sources without a server-side selector, e.g. snapshots and
+/// kube-state-metrics, which match it against the labels they recorded.
+pub struct FilteredWorkloadSource {
+    inner: Arc<dyn WorkloadSource>,
+    filter: WorkloadFilter,
+}
+
+impl FilteredWorkloadSource {
+    pub fn new(inner: Arc<dyn WorkloadSource>, filter: WorkloadFilter) -> Self {
+        Self { inner, filter }
+    }
+}
+
+#[async_trait]
+impl WorkloadSource for FilteredWorkloadSource {
+    async fn list_workloads(&self) -> Result<Vec<DeploymentResources>> {
+        let all = self.inner.list_workloads().await?;
+        let total = all.len();
+        let kept: Vec<_> = all
+            .into_iter()
+            .filter(|deployment| self.filter.includes(deployment))
+            .collect();
+        info!(
+            "Analyzing {} of {} workloads matching the filters",
+            kept.len(),
+            total
+        );
+        Ok(kept)
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` is any run of characters and `?`
+/// a single one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text it was tried against, to backtrack to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn selector(s: &str) -> LabelSelector {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_equality_requirements() {
+        let selector = selector("app=web, tier == frontend,env!=dev");
+        assert_eq!(
+            selector.requirements,
+            [
+                Requirement::Equals("app".into(), "web".into()),
+                Requirement::Equals("tier".into(), "frontend".into()),
+                Requirement::NotEquals("env".into(), "dev".into()),
+            ]
+        );
+        assert!(selector.matches(&labels(&[("app", "web"), ("tier", "frontend")])));
+        assert!(!selector.matches(&labels(&[
+            ("app", "web"),
+            ("tier", "frontend"),
+            ("env", "dev")
+        ])));
+        assert!(!selector.matches(&labels(&[("app", "api"), ("tier", "frontend")])));
+    }
+
+    #[test]
+    fn parses_set_requirements() {
+        let selector = selector("env in (prod, staging),tier notin (cache)");
+        assert_eq!(
+            selector.requirements,
+            [
+                Requirement::In("env".into(), vec!["prod".into(), "staging".into()]),
+                Requirement::NotIn("tier".into(), vec!["cache".into()]),
+            ]
+        );
+        assert!(selector.matches(&labels(&[("env", "staging")])));
+        assert!(selector.matches(&labels(&[("env", "prod"), ("tier", "web")])));
+        assert!(!selector.matches(&labels(&[("env", "prod"), ("tier", "cache")])));
+        // `in` needs the label, `notin` doesn't
+        assert!(!selector.matches(&labels(&[("tier", "web")])));
+    }
+
+    #[test]
+    fn parses_existence_requirements() {
+        let selector = selector("app,!canary");
+        assert_eq!(
+            selector.requirements,
+            [
+                Requirement::Exists("app".into()),
+                Requirement::DoesNotExist("canary".into()),
+            ]
+        );
+        assert!(selector.matches(&labels(&[("app", "")])));
+        assert!(!selector.matches(&labels(&[("app", "web"), ("canary", "true")])));
+        assert!(!selector.matches(&labels(&[])));
+    }
+
+    #[test]
+    fn displays_in_api_server_syntax() {
+        let input = "app=web,env!=dev,env in (prod,staging),tier notin (cache),app,!canary";
+        assert_eq!(selector(input).to_string(), input);
+        assert_eq!(selector("app == web").to_string(), "app=web");
+    }
+
+    #[test]
+    fn rejects_malformed_selectors() {
+        for malformed in [
+            "",
+            "app=web,",
+            "app=web,,tier=db",
+            "=web",
+            "!",
+            "my app",
+            "env in (prod",
+            "env in prod)",
+            "env has (prod)",
+            "in (prod)",
+            "env in (prod) x",
+        ] {
+            assert!(
+                malformed.parse::<LabelSelector>().is_err(),
+                "{:?} should be rejected",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn glob_matches_literals_and_wildcards() {
+        assert!(glob_match("web", "web"));
+        assert!(!glob_match("web", "webapp"));
+        assert!(glob_match("w?b", "web"));
+        assert!(!glob_match("w?b", "wb"));
+    }
+
+    #[test]
+    fn glob_star_at_either_end() {
+        assert!(glob_match("*-api", "billing-api"));
+        assert!(glob_match("*-api", "-api"));
+        assert!(!glob_match("*-api", "billing-api-v2"));
+        assert!(glob_match("billing-*", "billing-"));
+        assert!(glob_match("billing-*", "billing-api"));
+        assert!(!glob_match("billing-*", "billing"));
+        assert!(glob_match("*api*", "api"));
+        assert!(glob_match("*api*", "my-api-v2"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+    }
+
+    #[test]
+    fn glob_consecutive_stars_match_like_one() {
+        assert!(glob_match("a**b", "ab"));
+        assert!(glob_match("a**b", "axyzb"));
+        assert!(glob_match("**", ""));
+        assert!(glob_match("shop/**", "shop/web"));
+        assert!(!glob_match("a**b", "axyzc"));
+        // Backtracking past an early partial match
+        assert!(glob_match("*ab*ab", "aabxabab"));
+    }
+
+    #[test]
+    fn glob_empty_pattern_only_matches_empty_text() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "web"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn includes_by_name_or_qualified_name() {
+        let filter = WorkloadFilter::new()
+            .with_include(vec!["shop/*".into(), "api-*".into()])
+            .with_exclude(vec!["*-canary".into()]);
+        assert!(filter.includes_name("shop", "web"));
+        assert!(filter.includes_name("billing", "api-gateway"));
+        assert!(!filter.includes_name("billing", "web"));
+        assert!(!filter.includes_name("shop", "web-canary"));
+        assert!(WorkloadFilter::new().includes_name("any", "thing"));
+    }
+}
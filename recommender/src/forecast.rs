@@ -0,0 +1,166 @@
+//! Projecting usage past the end of the lookback
+//!
+//! Usage is reduced to one value per query step, the mean over all pods, and a
+//! trend is fitted through it. Growth is measured against the usage level at the
+//! end of the lookback; with Holt-Winters that level is deseasonalized, so a
+//! workload ending the lookback at its daily peak doesn't look like it's growing.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::config::ForecastMethod;
+
+const DAY_SECONDS: f64 = 24.0 * 3600.0;
+const WEEK_SECONDS: f64 = 7.0 * DAY_SECONDS;
+
+/// Profiles with fewer steps are too short to fit a trend through
+const MIN_STEPS: usize = 10;
+
+/// Shorter profiles mostly show intraday ramps, which projected for weeks explode
+const MIN_SPAN: Duration = Duration::from_secs(2 * 24 * 3600);
+
+/// Time over which Holt-Winters forgets old usage levels
+const LEVEL_MEMORY: Duration = Duration::from_secs(3600);
+
+/// Seasons over which Holt-Winters forgets old daily patterns
+const SEASON_MEMORY: f64 = 3.0;
+
+/// Projected usage of a container
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Forecast {
+    /// Usage level at the end of the lookback
+    pub current: f64,
+    /// Usage level at the end of the horizon
+    pub projected: f64,
+    /// Trend per week as a fraction of `current`
+    pub weekly_growth: f64,
+}
+
+impl Forecast {
+    /// Factor to scale recommendations by, 1.0 for flat or shrinking usage
+    pub fn growth_factor(&self) -> f64 {
+        (self.projected / self.current).max(1.0)
+    }
+}
+
+/// Project `profile`, usage per index of `step`-long query steps, `horizon` past
+/// its last step
+///
+/// Returns None when the profile covers less than two days, has too few steps or
+/// there is no usage to grow from.
+pub fn forecast(
+    profile: &BTreeMap<i64, f64>,
+    step: Duration,
+    method: ForecastMethod,
+    horizon: Duration,
+) -> Option<Forecast> {
+    let (Some(&first), Some(&last)) = (profile.keys().next(), profile.keys().next_back()) else {
+        return None;
+    };
+    let step_seconds = step.as_secs_f64();
+    if profile.len() < MIN_STEPS
+        || ((last - first + 1) as f64) * step_seconds < MIN_SPAN.as_secs_f64()
+    {
+        return None;
+    }
+
+    let (current, trend) = match method {
+        ForecastMethod::Linear => linear_trend(profile)?,
+        ForecastMethod::HoltWinters => {
+            let season = (DAY_SECONDS / step_seconds).round() as i64;
+            holt_winters(profile, step, season)?
+        }
+    };
+    if current.is_nan() || current <= 0.0 {
+        return None;
+    }
+
+    let horizon_steps = horizon.as_secs_f64() / step_seconds;
+    Some(Forecast {
+        current,
+        projected: (current + trend * horizon_steps).max(0.0),
+        weekly_growth: trend * (WEEK_SECONDS / step_seconds) / current,
+    })
+}
+
+/// Fitted value at the last step and slope per step of a least-squares line
+fn linear_trend(profile: &BTreeMap<i64, f64>) -> Option<(f64, f64)> {
+    let n = profile.len() as f64;
+    let mean_x = profile.keys().map(|&x| x as f64).sum::<f64>() / n;
+    let mean_y = profile.values().sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (&x, &y) in profile {
+        let dx = x as f64 - mean_x;
+        covariance += dx * (y - mean_y);
+        variance += dx * dx;
+    }
+    if variance == 0.0 {
+        return None;
+    }
+    let slope = covariance / variance;
+    let last = *profile.keys().next_back()? as f64;
+    Some((mean_y + slope * (last - mean_x), slope))
+}
+
+/// Final level and trend per step of additive Holt-Winters with a `season` steps
+/// long season, or of Holt's linear trend when the profile spans under two seasons,
+/// e.g. with query steps of a day
+///
+/// Steps missing from the profile are skipped over, advancing the level by the
+/// trend for each of them.
+fn holt_winters(profile: &BTreeMap<i64, f64>, step: Duration, season: i64) -> Option<(f64, f64)> {
+    let first = *profile.keys().next()?;
+    let last = *profile.keys().next_back()?;
+    let span = (last - first + 1) as f64;
+
+    // Smoothing factors from the time constant each component should have, the
+    // trend being fitted over the whole profile
+    let smoothing = |steps: f64| 1.0 - (-1.0 / steps.max(1.0)).exp();
+    let alpha = smoothing(LEVEL_MEMORY.as_secs_f64() / step.as_secs_f64());
+    let beta = smoothing(span / 4.0);
+    let gamma = smoothing(SEASON_MEMORY);
+
+    let seasonal = season >= 2 && span >= 2.0 * season as f64;
+    let mean_between = |from: i64, to: i64| {
+        let values: Vec<f64> = profile.range(from..to).map(|(_, &y)| y).collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+
+    let (mut level, mut trend, mut seasons, mut previous) = if seasonal {
+        let first_mean = mean_between(first, first + season)?;
+        let second_mean = mean_between(first + season, first + 2 * season)?;
+        let trend = (second_mean - first_mean) / season as f64;
+        // The mean is the level halfway through the first season
+        let midpoint = first as f64 + (season - 1) as f64 / 2.0;
+        let mut seasons = vec![0.0; season as usize];
+        for (&x, &y) in profile.range(first..first + season) {
+            let level = first_mean + trend * (x as f64 - midpoint);
+            seasons[x.rem_euclid(season) as usize] = y - level;
+        }
+        let level = first_mean + trend * (season - 1) as f64 / 2.0;
+        (level, trend, seasons, first + season - 1)
+    } else {
+        (profile[&first], 0.0, Vec::new(), first)
+    };
+
+    for (&x, &y) in profile.range(previous + 1..) {
+        let gap = (x - previous) as f64;
+        let season_index = if seasonal {
+            Some(x.rem_euclid(season) as usize)
+        } else {
+            None
+        };
+        let season_value = season_index.map_or(0.0, |i| seasons[i]);
+
+        let predicted = level + gap * trend;
+        let next_level = alpha * (y - season_value) + (1.0 - alpha) * predicted;
+        trend = beta * (next_level - level) / gap + (1.0 - beta) * trend;
+        if let Some(i) = season_index {
+            seasons[i] = gamma * (y - next_level) + (1.0 - gamma) * season_value;
+        }
+        level = next_level;
+        previous = x;
+    }
+
+    Some((level, trend))
+}
@@ -0,0 +1,158 @@
+//! Helm charts, whose container resources live in values files
+//!
+//! Chart templates aren't valid YAML until rendered, so Deployments of a chart
+//! are mapped to the key of their `resources:` in a values file instead. Their
+//! recommendations update that key, all others go to the plain manifests.
+
+use std::path::{Component, Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::ManifestWorkload;
+use crate::recommender::ResourceRecommendation;
+use crate::{ConfigError, Result};
+
+/// Values key holding the resources of a Deployment's containers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HelmValuesEntry {
+    pub deployment: String,
+    /// Unset to match the Deployment in any namespace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Unset to match the containers no other entry of the Deployment names
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    /// Values file relative to the manifest root
+    pub file: PathBuf,
+    /// Dotted path of the mapping holding `requests` and `limits`
+    #[serde(default = "default_key")]
+    pub key: String,
+}
+
+fn default_key() -> String {
+    "resources".to_string()
+}
+
+/// Deployments deployed from Helm charts and where their resources are set
+///
+/// Example values mapping file:
+///
+/// ```yaml
+/// values:
+///   - deployment: checkout
+///     namespace: shop
+///     file: charts/checkout/values-prod.yaml
+///   - deployment: checkout
+///     container: istio-proxy
+///     file: charts/checkout/values-prod.yaml
+///     key: sidecar.resources
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HelmValues {
+    pub values: Vec<HelmValuesEntry>,
+}
+
+impl HelmValues {
+    /// Load the mapping from a YAML file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::FileError(format!(
+                "Failed to read Helm values mapping {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let helm: HelmValues = serde_yaml::from_str(&content)?;
+        helm.validate()?;
+
+        info!(
+            "Loaded {} Helm values mapping(s) from {}",
+            helm.values.len(),
+            path.display()
+        );
+        Ok(helm)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (i, entry) in self.values.iter().enumerate() {
+            let workload = entry_label(entry);
+            if self.values[..i].iter().any(|e| {
+                e.deployment == entry.deployment
+                    && e.namespace == entry.namespace
+                    && e.container == entry.container
+            }) {
+                return Err(invalid(format!("{} is listed twice", workload)));
+            }
+            if !entry
+                .file
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(invalid(format!(
+                    "file {} of {} must stay inside the manifest root",
+                    entry.file.display(),
+                    workload
+                )));
+            }
+            if entry.key.split('.').any(str::is_empty) {
+                return Err(invalid(format!(
+                    "key `{}` of {} must be a dotted path like `app.resources`",
+                    entry.key, workload
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Entry of the recommendation's container, the one naming it before a catch-all
+    pub fn entry_for(&self, recommendation: &ResourceRecommendation) -> Option<&HelmValuesEntry> {
+        let entries: Vec<&HelmValuesEntry> = self
+            .values
+            .iter()
+            .filter(|entry| {
+                entry.deployment == recommendation.deployment
+                    && entry
+                        .namespace
+                        .as_deref()
+                        .is_none_or(|ns| ns == recommendation.namespace)
+            })
+            .collect();
+        entries
+            .iter()
+            .find(|entry| entry.container.as_deref() == Some(recommendation.container.as_str()))
+            .or_else(|| entries.iter().find(|entry| entry.container.is_none()))
+            .copied()
+    }
+
+    /// Deployments with a values mapping, once each
+    pub fn workloads(&self) -> Vec<ManifestWorkload> {
+        let mut workloads: Vec<ManifestWorkload> = Vec::new();
+        for entry in &self.values {
+            let workload = ManifestWorkload {
+                namespace: entry.namespace.clone(),
+                name: entry.deployment.clone(),
+            };
+            if !workloads.contains(&workload) {
+                workloads.push(workload);
+            }
+        }
+        workloads
+    }
+}
+
+/// `namespace/deployment` of an entry, with the container when it names one
+fn entry_label(entry: &HelmValuesEntry) -> String {
+    let mut label = match &entry.namespace {
+        Some(namespace) => format!("{}/{}", namespace, entry.deployment),
+        None => entry.deployment.clone(),
+    };
+    if let Some(container) = &entry.container {
+        label.push_str(&format!(" container {}", container));
+    }
+    label
+}
+
+fn invalid(message: String) -> crate::RecommenderError {
+    ConfigError::InvalidValue(format!("Invalid Helm values mapping: {}", message)).into()
+}
@@ -0,0 +1,381 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::apply::ApplyOutcome;
+use crate::canary::ApplyStage;
+use crate::kubernetes::DeploymentResources;
+use crate::provenance::Provenance;
+use crate::recommender::ResourceRecommendation;
+use crate::{RecommenderError, Result};
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Requests of one deployment in one run, summed over its containers and replicas
+///
+/// CPU is in cores and memory in bytes. Containers without a current request
+/// count as 0 requested and no waste.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeploymentTotals {
+    pub namespace: String,
+    pub deployment: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    pub requested_cpu: f64,
+    pub recommended_cpu: f64,
+    /// Requested above the recommendation, over-provisioned containers only
+    pub wasted_cpu: f64,
+    pub requested_memory: f64,
+    pub recommended_memory: f64,
+    pub wasted_memory: f64,
+}
+
+/// Totals of every deployment analyzed in one run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub timestamp: DateTime<Utc>,
+    pub deployments: Vec<DeploymentTotals>,
+}
+
+impl RunRecord {
+    /// Sum `recommendations` per deployment, with replicas and labels from `deployments`
+    pub fn new(
+        timestamp: DateTime<Utc>,
+        recommendations: &[ResourceRecommendation],
+        deployments: &[DeploymentResources],
+    ) -> Self {
+        let deployments: HashMap<(&str, &str), &DeploymentResources> = deployments
+            .iter()
+            .map(|d| ((d.namespace.as_str(), d.name.as_str()), d))
+            .collect();
+
+        let mut totals: BTreeMap<(&str, &str), DeploymentTotals> = BTreeMap::new();
+        for rec in recommendations {
+            let key = (rec.namespace.as_str(), rec.deployment.as_str());
+            let deployment = deployments.get(&key);
+            let replicas = deployment
+                .and_then(|d| d.replicas)
+                .map_or(1.0, |r| r.max(0) as f64);
+            let entry = totals.entry(key).or_insert_with(|| DeploymentTotals {
+                namespace: rec.namespace.clone(),
+                deployment: rec.deployment.clone(),
+                labels: deployment.map(|d| d.labels.clone()).unwrap_or_default(),
+                requested_cpu: 0.0,
+                recommended_cpu: 0.0,
+                wasted_cpu: 0.0,
+                requested_memory: 0.0,
+                recommended_memory: 0.0,
+                wasted_memory: 0.0,
+            });
+
+            let requested_cpu = rec.current_cpu_request.map_or(0.0, |q| q.value());
+            let recommended_cpu = rec.recommended_cpu_request.value();
+            entry.requested_cpu += requested_cpu * replicas;
+            entry.recommended_cpu += recommended_cpu * replicas;
+            if rec.current_cpu_request.is_some() {
+                entry.wasted_cpu += (requested_cpu - recommended_cpu).max(0.0) * replicas;
+            }
+
+            let requested_memory = rec.current_memory_request.map_or(0.0, |q| q.value());
+            let recommended_memory = rec.recommended_memory_request.value();
+            entry.requested_memory += requested_memory * replicas;
+            entry.recommended_memory += recommended_memory * replicas;
+            if rec.current_memory_request.is_some() {
+                entry.wasted_memory += (requested_memory - recommended_memory).max(0.0) * replicas;
+            }
+        }
+
+        Self {
+            timestamp,
+            deployments: totals.into_values().collect(),
+        }
+    }
+}
+
+/// Workloads one run applied recommendations to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedSet {
+    pub timestamp: DateTime<Utc>,
+    pub stage: ApplyStage,
+    /// `namespace/deployment` of each updated workload, sorted
+    pub workloads: Vec<String>,
+    /// Values of a pull request keyed by `namespace/deployment`, recorded on the
+    /// workloads once it is merged and rolled out
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub provenance: BTreeMap<String, Provenance>,
+}
+
+impl AppliedSet {
+    pub fn new(timestamp: DateTime<Utc>, stage: ApplyStage, outcome: &ApplyOutcome) -> Self {
+        let mut workloads: Vec<String> = outcome.updated.keys().cloned().collect();
+        workloads.sort();
+        Self {
+            timestamp,
+            stage,
+            workloads,
+            provenance: BTreeMap::new(),
+        }
+    }
+
+    /// Keep the applied values of the updated workloads until the pull request
+    /// at `pr_url` is merged
+    pub fn awaiting_merge(
+        mut self,
+        recommendations: &[ResourceRecommendation],
+        pr_url: Option<String>,
+    ) -> Self {
+        let mut by_workload: BTreeMap<String, Vec<&ResourceRecommendation>> = BTreeMap::new();
+        for rec in recommendations {
+            let workload = format!("{}/{}", rec.namespace, rec.deployment);
+            if self.workloads.contains(&workload) {
+                by_workload.entry(workload).or_default().push(rec);
+            }
+        }
+        self.provenance = by_workload
+            .into_iter()
+            .map(|(workload, recs)| {
+                let provenance = Provenance::new(self.timestamp, &recs).with_pr_url(pr_url.clone());
+                (workload, provenance)
+            })
+            .collect();
+        self
+    }
+
+    pub fn contains(&self, namespace: &str, deployment: &str) -> bool {
+        self.workloads
+            .iter()
+            .any(|w| w.split_once('/') == Some((namespace, deployment)))
+    }
+}
+
+/// Totals of previous runs, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunHistory {
+    pub runs: Vec<RunRecord>,
+    /// Workloads applied by previous runs, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied: Vec<AppliedSet>,
+}
+
+impl RunHistory {
+    /// Read a history file, starting empty if it is missing or unreadable
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            warn!("Ignoring invalid run history {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(|e| {
+            RecommenderError::Other(format!("Failed to serialize run history: {}", e))
+        })?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Add a run, keeping the runs sorted and replacing one with the same timestamp
+    pub fn record(&mut self, run: RunRecord) {
+        self.runs.retain(|r| r.timestamp != run.timestamp);
+        let index = self.runs.partition_point(|r| r.timestamp < run.timestamp);
+        self.runs.insert(index, run);
+    }
+
+    /// Add the workloads a run applied, keeping the sets sorted
+    pub fn record_applied(&mut self, applied: AppliedSet) {
+        let index = self
+            .applied
+            .partition_point(|a| a.timestamp <= applied.timestamp);
+        self.applied.insert(index, applied);
+    }
+
+    /// Values of the latest pull request of each workload, keyed by `namespace/deployment`
+    pub fn awaiting_merge(&self) -> BTreeMap<&str, &Provenance> {
+        self.applied
+            .iter()
+            .flat_map(|applied| &applied.provenance)
+            .map(|(workload, provenance)| (workload.as_str(), provenance))
+            .collect()
+    }
+
+    /// Canary set of the last staged apply, unless a rollout or full apply followed it
+    pub fn pending_canary(&self) -> Option<&AppliedSet> {
+        self.applied
+            .last()
+            .filter(|applied| applied.stage == ApplyStage::Canary)
+    }
+}
+
+/// How [`savings_trend`] groups deployments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrendGrouping {
+    Namespace,
+    /// Value of a deployment label, e.g. `team`, `unlabeled` when missing
+    Label(String),
+}
+
+impl fmt::Display for TrendGrouping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrendGrouping::Namespace => write!(f, "namespace"),
+            TrendGrouping::Label(label) => write!(f, "label {}", label),
+        }
+    }
+}
+
+/// Totals of a group in the last run of a month
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonthTotals {
+    /// `YYYY-MM` in UTC
+    pub month: String,
+    pub runs: usize,
+    pub deployments: usize,
+    pub requested_cpu: f64,
+    pub recommended_cpu: f64,
+    pub wasted_cpu: f64,
+    pub requested_memory: f64,
+    pub recommended_memory: f64,
+    pub wasted_memory: f64,
+    /// Change of the wasted cores since the previous month, negative when waste shrank
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasted_cpu_change: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasted_memory_change: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupTrend {
+    pub group: String,
+    pub months: Vec<MonthTotals>,
+}
+
+/// Month-over-month requested, recommended and wasted resources per group
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavingsTrend {
+    pub grouped_by: String,
+    pub groups: Vec<GroupTrend>,
+}
+
+/// Requested vs recommended resources per group and month
+///
+/// Each month reports the last run recorded in it, so the totals show where
+/// things stood at the end of the month rather than an average over reruns.
+/// `months` keeps only the most recent months.
+pub fn savings_trend(
+    history: &RunHistory,
+    grouping: &TrendGrouping,
+    months: Option<usize>,
+) -> SavingsTrend {
+    let mut last_runs: BTreeMap<String, (&RunRecord, usize)> = BTreeMap::new();
+    for run in &history.runs {
+        let month = run.timestamp.format("%Y-%m").to_string();
+        let entry = last_runs.entry(month).or_insert((run, 0));
+        if run.timestamp >= entry.0.timestamp {
+            entry.0 = run;
+        }
+        entry.1 += 1;
+    }
+    let skip = months.map_or(0, |m| last_runs.len().saturating_sub(m));
+
+    let mut groups: BTreeMap<String, Vec<MonthTotals>> = BTreeMap::new();
+    for (month, (run, runs)) in last_runs.into_iter().skip(skip) {
+        let mut totals: BTreeMap<String, MonthTotals> = BTreeMap::new();
+        for deployment in &run.deployments {
+            let group = match grouping {
+                TrendGrouping::Namespace => deployment.namespace.clone(),
+                TrendGrouping::Label(label) => deployment
+                    .labels
+                    .get(label)
+                    .cloned()
+                    .unwrap_or_else(|| crate::chargeback::UNLABELED.to_string()),
+            };
+            let entry = totals.entry(group).or_insert_with(|| MonthTotals {
+                month: month.clone(),
+                runs,
+                deployments: 0,
+                requested_cpu: 0.0,
+                recommended_cpu: 0.0,
+                wasted_cpu: 0.0,
+                requested_memory: 0.0,
+                recommended_memory: 0.0,
+                wasted_memory: 0.0,
+                wasted_cpu_change: None,
+                wasted_memory_change: None,
+            });
+            entry.deployments += 1;
+            entry.requested_cpu += deployment.requested_cpu;
+            entry.recommended_cpu += deployment.recommended_cpu;
+            entry.wasted_cpu += deployment.wasted_cpu;
+            entry.requested_memory += deployment.requested_memory;
+            entry.recommended_memory += deployment.recommended_memory;
+            entry.wasted_memory += deployment.wasted_memory;
+        }
+
+        for (group, mut month_totals) in totals {
+            let trend = groups.entry(group).or_default();
+            if let Some(previous) = trend.last() {
+                month_totals.wasted_cpu_change =
+                    Some(month_totals.wasted_cpu - previous.wasted_cpu);
+                month_totals.wasted_memory_change =
+                    Some(month_totals.wasted_memory - previous.wasted_memory);
+            }
+            trend.push(month_totals);
+        }
+    }
+
+    SavingsTrend {
+        grouped_by: grouping.to_string(),
+        groups: groups
+            .into_iter()
+            .map(|(group, months)| GroupTrend { group, months })
+            .collect(),
+    }
+}
+
+impl fmt::Display for SavingsTrend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.groups.is_empty() {
+            return write!(f, "No runs recorded in the history yet.");
+        }
+
+        write!(f, "Savings trend by {}", self.grouped_by)?;
+        for group in &self.groups {
+            writeln!(f, "\n\n## {}\n", group.group)?;
+            writeln!(
+                f,
+                "| Month | Deployments | CPU requested | Recommended | Wasted | Change | Memory requested | Recommended | Wasted | Change |"
+            )?;
+            write!(f, "|---|---:|---:|---:|---:|---:|---:|---:|---:|---:|")?;
+            for month in &group.months {
+                write!(
+                    f,
+                    "\n| {} | {} | {:.2} | {:.2} | {:.2} | {} | {:.0}Mi | {:.0}Mi | {:.0}Mi | {} |",
+                    month.month,
+                    month.deployments,
+                    month.requested_cpu,
+                    month.recommended_cpu,
+                    month.wasted_cpu,
+                    month
+                        .wasted_cpu_change
+                        .map_or_else(|| "-".to_string(), |c| format!("{:+.2}", c)),
+                    month.requested_memory / MIB,
+                    month.recommended_memory / MIB,
+                    month.wasted_memory / MIB,
+                    month
+                        .wasted_memory_change
+                        .map_or_else(|| "-".to_string(), |c| format!("{:+.0}Mi", c / MIB)),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+//! CPU utilization targets of HorizontalPodAutoscalers
+//!
+//! An HPA keeps the average CPU usage of its pods at a share of their requests.
+//! Changing the requests moves that point, so a suggested target is computed
+//! alongside them: a burst from p95 to peak usage should still fit in the new
+//! request while the HPA adds pods.
+
+use std::collections::HashMap;
+
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use kube::Client;
+use kube::api::{Api, ListParams};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::KubernetesError::ApiError;
+use crate::Result;
+
+/// Lowest suggested target, below it most of every request sits idle
+pub const MIN_CPU_UTILIZATION: i32 = 30;
+
+/// Highest suggested target, above it the HPA reacts too late to any burst
+pub const MAX_CPU_UTILIZATION: i32 = 90;
+
+/// HPA scaling a Deployment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hpa {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_replicas: Option<i32>,
+    pub max_replicas: i32,
+    /// Average CPU utilization target in percent, None without a CPU metric
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_utilization: Option<i32>,
+}
+
+/// Current and suggested CPU utilization target of a recommendation's HPA
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HpaTarget {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_cpu_utilization: Option<i32>,
+    pub recommended_cpu_utilization: i32,
+}
+
+impl HpaTarget {
+    /// Whether the suggested target differs from the HPA's current one
+    pub fn changes(&self) -> bool {
+        self.current_cpu_utilization != Some(self.recommended_cpu_utilization)
+    }
+}
+
+impl Hpa {
+    /// The HPA with `recommended` as the suggested CPU utilization target
+    pub fn target(&self, recommended: i32) -> HpaTarget {
+        HpaTarget {
+            name: self.name.clone(),
+            current_cpu_utilization: self.cpu_utilization,
+            recommended_cpu_utilization: recommended,
+        }
+    }
+
+    /// Namespace, target Deployment and HPA of an `autoscaling/v2` object
+    fn from_object(hpa: HorizontalPodAutoscaler) -> Option<((String, String), Self)> {
+        let spec = hpa.spec?;
+        if spec.scale_target_ref.kind != "Deployment" {
+            return None;
+        }
+        let cpu_utilization = spec
+            .metrics
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|metric| {
+                let resource = metric.resource?;
+                if resource.name != "cpu" || resource.target.type_ != "Utilization" {
+                    return None;
+                }
+                resource.target.average_utilization
+            });
+
+        Some((
+            (hpa.metadata.namespace?, spec.scale_target_ref.name),
+            Self {
+                name: hpa.metadata.name?,
+                min_replicas: spec.min_replicas,
+                max_replicas: spec.max_replicas,
+                cpu_utilization,
+            },
+        ))
+    }
+}
+
+/// HPAs keyed by the namespace and name of the Deployment they scale
+pub async fn list_hpas(
+    client: Client,
+    namespace: Option<&str>,
+) -> Result<HashMap<(String, String), Hpa>> {
+    let api: Api<HorizontalPodAutoscaler> = match namespace {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    };
+    let hpas = api.list(&ListParams::default()).await.map_err(ApiError)?;
+
+    let hpas: HashMap<_, _> = hpas
+        .items
+        .into_iter()
+        .filter_map(Hpa::from_object)
+        .collect();
+    info!(
+        "Found {} HorizontalPodAutoscalers scaling deployments",
+        hpas.len()
+    );
+    Ok(hpas)
+}
+
+/// CPU utilization target in percent for pods using `p95` cores most of the time
+/// and `max` at peak, None without usage
+///
+/// With the HPA holding usage at the target, a burst scales usage by `max / p95`
+/// before new pods are ready, so the target leaves that much room below the request.
+pub fn suggest_cpu_utilization(p95: f64, max: f64) -> Option<i32> {
+    if !(p95 > 0.0 && max > 0.0) {
+        return None;
+    }
+    let target = (100.0 * p95 / max.max(p95)).floor() as i32;
+    Some(target.clamp(MIN_CPU_UTILIZATION, MAX_CPU_UTILIZATION))
+}
@@ -0,0 +1,39 @@
+use std::sync::OnceLock;
+
+use reqwest::Client;
+
+use crate::config::HttpConfig;
+use crate::error::{RecommenderError, Result};
+
+static SHARED_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Build an HTTP client with pooled keep-alive connections
+pub fn build_client(config: &HttpConfig) -> Result<Client> {
+    Client::builder()
+        .timeout(config.timeout)
+        .connect_timeout(config.connect_timeout)
+        .pool_idle_timeout(config.pool_idle_timeout)
+        .tcp_keepalive(config.tcp_keepalive)
+        .build()
+        .map_err(|e| RecommenderError::Network(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// Configure the client returned by [`shared_client`]
+///
+/// Must run before the first request; fails if the shared client already exists.
+pub fn init_shared_client(config: &HttpConfig) -> Result<()> {
+    let client = build_client(config)?;
+    SHARED_CLIENT.set(client).map_err(|_| {
+        RecommenderError::Other("Shared HTTP client is already initialized".to_string())
+    })
+}
+
+/// Client shared by AMP queries and git provider API calls
+///
+/// Reusing it keeps TLS sessions and connections alive across requests. Uses
+/// [`HttpConfig::default`] unless [`init_shared_client`] ran first.
+pub fn shared_client() -> Client {
+    SHARED_CLIENT
+        .get_or_init(|| build_client(&HttpConfig::default()).unwrap_or_default())
+        .clone()
+}
@@ -0,0 +1,314 @@
+//! Journal of every run's recommendations, one JSON line per workload
+//!
+//! Lines are keyed by cluster, namespace and deployment and only ever appended,
+//! so the file can be tailed or shipped like a log. Applied values are journaled
+//! too, which is what [`drift_report`] compares new recommendations with.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::kubernetes::DeploymentResources;
+use crate::provenance::{AppliedResources, Provenance};
+use crate::quantity::Quantity;
+use crate::recommender::{DayProfile, ResourceRecommendation};
+use crate::{RecommenderError, Result};
+
+/// Recommendations of one workload in one run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub cluster: String,
+    pub namespace: String,
+    pub deployment: String,
+    /// Whether the values were applied, or proposed in a pull request, rather
+    /// than just recommended
+    #[serde(default)]
+    pub applied: bool,
+    pub recommendations: Vec<ResourceRecommendation>,
+}
+
+/// JSON-lines file of [`JournalEntry`], shared by the clusters writing to it
+pub struct RecommendationJournal {
+    path: PathBuf,
+    cluster: String,
+}
+
+impl RecommendationJournal {
+    pub fn new(path: impl Into<PathBuf>, cluster: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            cluster: cluster.into(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the recommendations of a run, one line per workload
+    ///
+    /// Returns the number of lines written.
+    pub fn append(
+        &self,
+        timestamp: DateTime<Utc>,
+        recommendations: &[ResourceRecommendation],
+        applied: bool,
+    ) -> Result<usize> {
+        let mut workloads: BTreeMap<(&str, &str), Vec<ResourceRecommendation>> = BTreeMap::new();
+        for rec in recommendations {
+            workloads
+                .entry((rec.namespace.as_str(), rec.deployment.as_str()))
+                .or_default()
+                .push(rec.clone());
+        }
+
+        let mut lines = String::new();
+        for ((namespace, deployment), recommendations) in &workloads {
+            let entry = JournalEntry {
+                timestamp,
+                cluster: self.cluster.clone(),
+                namespace: namespace.to_string(),
+                deployment: deployment.to_string(),
+                applied,
+                recommendations: recommendations.clone(),
+            };
+            let line = serde_json::to_string(&entry).map_err(|e| {
+                RecommenderError::Other(format!("Failed to serialize journal entry: {}", e))
+            })?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())?;
+        Ok(workloads.len())
+    }
+
+    /// Entries of this cluster, oldest first, starting empty if the file is missing
+    ///
+    /// Invalid lines, e.g. one cut short by a crash, are skipped with a warning.
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<JournalEntry>(line) {
+                Ok(entry) if entry.cluster == self.cluster => entries.push(entry),
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Ignoring invalid line {} of {}: {}",
+                    number + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+        entries.sort_by_key(|entry| entry.timestamp);
+        Ok(entries)
+    }
+
+    /// Values of the last apply journaled for each workload, keyed by namespace and deployment
+    pub fn last_applied(&self) -> Result<HashMap<(String, String), Provenance>> {
+        let mut applied = HashMap::new();
+        for entry in self.entries()? {
+            if !entry.applied {
+                continue;
+            }
+            let recommendations: Vec<&ResourceRecommendation> = entry
+                .recommendations
+                .iter()
+                .filter(|rec| rec.day_profile != Some(DayProfile::Weekend))
+                .collect();
+            applied.insert(
+                (entry.namespace, entry.deployment),
+                Provenance::new(entry.timestamp, &recommendations),
+            );
+        }
+        Ok(applied)
+    }
+}
+
+/// How far a container's recommendation and running values moved from the last apply
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerDrift {
+    pub namespace: String,
+    pub deployment: String,
+    pub container: String,
+    pub applied_at: DateTime<Utc>,
+    pub applied: AppliedResources,
+    pub recommended_cpu_request: Quantity,
+    pub recommended_memory_request: Quantity,
+    /// Change of the recommended CPU request in percent of the applied one
+    pub cpu_change_percent: f64,
+    pub memory_change_percent: f64,
+    /// Running requests or limits differ from the applied ones, edited by hand
+    pub edited: bool,
+}
+
+/// Containers whose recommendation moved past the threshold or whose resources were edited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DriftReport {
+    pub threshold_percent: f64,
+    /// Containers compared with an earlier apply
+    pub compared: usize,
+    pub containers: Vec<ContainerDrift>,
+}
+
+/// Compare `recommendations` with the values last applied to their workloads
+///
+/// The applied values come from the journal and the provenance annotations of
+/// `deployments`, the more recent one per workload. Containers without an
+/// earlier apply are left out. The largest moves come first.
+pub fn drift_report(
+    recommendations: &[ResourceRecommendation],
+    journaled: &HashMap<(String, String), Provenance>,
+    deployments: &[DeploymentResources],
+    threshold_percent: f64,
+) -> DriftReport {
+    let annotated: HashMap<(&str, &str), &Provenance> = deployments
+        .iter()
+        .filter_map(|d| {
+            let provenance = d.provenance.as_ref()?;
+            Some(((d.namespace.as_str(), d.name.as_str()), provenance))
+        })
+        .collect();
+
+    let mut report = DriftReport {
+        threshold_percent,
+        ..DriftReport::default()
+    };
+    for rec in recommendations {
+        if rec.day_profile == Some(DayProfile::Weekend) {
+            continue;
+        }
+        let key = (rec.namespace.as_str(), rec.deployment.as_str());
+        let journaled = journaled.get(&(rec.namespace.clone(), rec.deployment.clone()));
+        let provenance = match (journaled, annotated.get(&key).copied()) {
+            (Some(journaled), Some(annotated)) if annotated.applied_at > journaled.applied_at => {
+                annotated
+            }
+            (Some(journaled), _) => journaled,
+            (None, Some(annotated)) => annotated,
+            (None, None) => continue,
+        };
+        let Some(applied) = provenance.containers.get(&rec.container) else {
+            continue;
+        };
+        report.compared += 1;
+
+        let cpu_change_percent = change_percent(applied.cpu_request, rec.recommended_cpu_request);
+        let memory_change_percent =
+            change_percent(applied.memory_request, rec.recommended_memory_request);
+        let edited = [
+            rec.current_cpu_request,
+            rec.current_cpu_limit,
+            rec.current_memory_request,
+            rec.current_memory_limit,
+        ] != [
+            Some(applied.cpu_request),
+            Some(applied.cpu_limit),
+            Some(applied.memory_request),
+            Some(applied.memory_limit),
+        ];
+        if !edited
+            && cpu_change_percent.abs() < threshold_percent
+            && memory_change_percent.abs() < threshold_percent
+        {
+            continue;
+        }
+
+        report.containers.push(ContainerDrift {
+            namespace: rec.namespace.clone(),
+            deployment: rec.deployment.clone(),
+            container: rec.container.clone(),
+            applied_at: provenance.applied_at,
+            applied: *applied,
+            recommended_cpu_request: rec.recommended_cpu_request,
+            recommended_memory_request: rec.recommended_memory_request,
+            cpu_change_percent,
+            memory_change_percent,
+            edited,
+        });
+    }
+
+    report.containers.sort_by(|a, b| {
+        let largest = |d: &ContainerDrift| {
+            d.cpu_change_percent
+                .abs()
+                .max(d.memory_change_percent.abs())
+        };
+        largest(b).total_cmp(&largest(a))
+    });
+    report
+}
+
+/// Change from `applied` to `recommended` in percent, 0 when nothing was applied
+fn change_percent(applied: Quantity, recommended: Quantity) -> f64 {
+    if applied.value() > 0.0 {
+        (recommended.value() - applied.value()) / applied.value() * 100.0
+    } else {
+        0.0
+    }
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.compared == 0 {
+            return write!(f, "No workload has applied values to compare with yet.");
+        }
+        if self.containers.is_empty() {
+            return write!(
+                f,
+                "No drift: {} containers are within {}% of their applied values.",
+                self.compared, self.threshold_percent
+            );
+        }
+
+        writeln!(
+            f,
+            "Drift of {} of {} containers since their last apply (threshold {}%)\n",
+            self.containers.len(),
+            self.compared,
+            self.threshold_percent
+        )?;
+        writeln!(
+            f,
+            "| Workload | Container | Applied | CPU applied → now | Change | Memory applied → now | Change | Edited |"
+        )?;
+        write!(f, "|---|---|---|---:|---:|---:|---:|---|")?;
+        for drift in &self.containers {
+            write!(
+                f,
+                "\n| {}/{} | {} | {} | {} → {} | {:+.0}% | {} → {} | {:+.0}% | {} |",
+                drift.namespace,
+                drift.deployment,
+                drift.container,
+                drift.applied_at.format("%Y-%m-%d"),
+                drift.applied.cpu_request,
+                drift.recommended_cpu_request,
+                drift.cpu_change_percent,
+                drift.applied.memory_request,
+                drift.recommended_memory_request,
+                drift.memory_change_percent,
+                if drift.edited { "yes" } else { "" },
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,255 @@
+//! Workload discovery from kube-state-metrics series, for AMP access without a kubeconfig
+//!
+//! Deployments, their ReplicaSets and pods and the pods' requests and limits are
+//! all exported by kube-state-metrics. Only series sampled within the last few
+//! minutes count, so deleted objects drop out like they would from the API.
+//! VPAs, HPAs, PDBs and rollout strategies aren't known in this mode.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use log::{debug, info};
+
+use crate::Result;
+use crate::clock::{SharedClock, SystemClock};
+use crate::kubernetes::{ContainerResources, DeploymentResources, WorkloadKind, WorkloadSource};
+use crate::metrics::MetricsProvider;
+use crate::quantity::Quantity;
+
+/// Labels and latest value of one series
+type Latest = Vec<(HashMap<String, String>, f64)>;
+
+/// Discovers Deployments from kube-state-metrics through a [`MetricsProvider`]
+pub struct KubeStateMetricsSource {
+    metrics: Arc<dyn MetricsProvider>,
+    namespace: Option<String>,
+    clock: SharedClock,
+}
+
+impl KubeStateMetricsSource {
+    /// Deployments in `namespace`, or in all namespaces when None
+    pub fn new(metrics: Arc<dyn MetricsProvider>, namespace: Option<String>) -> Self {
+        Self {
+            metrics,
+            namespace,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Discover the objects that existed at `clock`'s time instead of now
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Latest value of each series of `metric`, restricted to the namespace
+    async fn latest(&self, metric: &str, matchers: &[&str]) -> Result<Latest> {
+        let mut matchers: Vec<String> = matchers.iter().map(|m| m.to_string()).collect();
+        if let Some(namespace) = &self.namespace {
+            matchers.push(format!(r#"namespace="{}""#, namespace));
+        }
+        let query = format!("{}{{{}}}", metric, matchers.join(","));
+
+        // Objects count as existing while their series have a sample within the lookback
+        let series = self.metrics.query(&query, self.clock.now()).await?;
+        debug!("{} returned {} series", query, series.len());
+
+        Ok(series
+            .into_iter()
+            .filter_map(|s| Some((s.labels, s.samples.last()?.value)))
+            .collect())
+    }
+}
+
+/// Value of `name` in `labels`, empty when missing
+fn label<'a>(labels: &'a HashMap<String, String>, name: &str) -> &'a str {
+    labels.get(name).map_or("", String::as_str)
+}
+
+#[async_trait]
+impl WorkloadSource for KubeStateMetricsSource {
+    async fn list_workloads(&self) -> Result<Vec<DeploymentResources>> {
+        info!("Discovering deployments from kube-state-metrics...");
+
+        let replicas = self.latest("kube_deployment_spec_replicas", &[]).await?;
+        let created = self.latest("kube_deployment_created", &[]).await?;
+        let deployment_labels = self.latest("kube_deployment_labels", &[]).await?;
+        let replica_set_owners = self
+            .latest("kube_replicaset_owner", &[r#"owner_kind="Deployment""#])
+            .await?;
+        let replica_set_created = self.latest("kube_replicaset_created", &[]).await?;
+        let pod_owners = self
+            .latest("kube_pod_owner", &[r#"owner_kind="ReplicaSet""#])
+            .await?;
+        let containers = self.latest("kube_pod_container_info", &[]).await?;
+        let requests = self
+            .latest(
+                "kube_pod_container_resource_requests",
+                &[r#"resource=~"cpu|memory""#],
+            )
+            .await?;
+        let limits = self
+            .latest(
+                "kube_pod_container_resource_limits",
+                &[r#"resource=~"cpu|memory""#],
+            )
+            .await?;
+
+        // (namespace, deployment) -> ReplicaSets, (namespace, replica set) -> pods
+        let mut replica_sets: HashMap<(&str, &str), Vec<&str>> = HashMap::new();
+        for (labels, _) in &replica_set_owners {
+            replica_sets
+                .entry((label(labels, "namespace"), label(labels, "owner_name")))
+                .or_default()
+                .push(label(labels, "replicaset"));
+        }
+        let replica_set_created: HashMap<(&str, &str), f64> = replica_set_created
+            .iter()
+            .map(|(labels, value)| {
+                (
+                    (label(labels, "namespace"), label(labels, "replicaset")),
+                    *value,
+                )
+            })
+            .collect();
+        let mut pods: HashMap<(&str, &str), Vec<&str>> = HashMap::new();
+        for (labels, _) in &pod_owners {
+            pods.entry((label(labels, "namespace"), label(labels, "owner_name")))
+                .or_default()
+                .push(label(labels, "pod"));
+        }
+
+        // (namespace, pod) -> containers in the order listed
+        let mut pod_containers: HashMap<(&str, &str), Vec<&str>> = HashMap::new();
+        for (labels, _) in &containers {
+            let names = pod_containers
+                .entry((label(labels, "namespace"), label(labels, "pod")))
+                .or_default();
+            let name = label(labels, "container");
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        // (namespace, pod, container, resource) -> cores or bytes
+        let resources = |series: &Latest| -> HashMap<(String, String, String, String), f64> {
+            series
+                .iter()
+                .map(|(labels, value)| {
+                    (
+                        (
+                            label(labels, "namespace").to_string(),
+                            label(labels, "pod").to_string(),
+                            label(labels, "container").to_string(),
+                            label(labels, "resource").to_string(),
+                        ),
+                        *value,
+                    )
+                })
+                .collect()
+        };
+        let requests = resources(&requests);
+        let limits = resources(&limits);
+
+        let created: HashMap<(&str, &str), f64> = created
+            .iter()
+            .map(|(labels, value)| {
+                (
+                    (label(labels, "namespace"), label(labels, "deployment")),
+                    *value,
+                )
+            })
+            .collect();
+        let deployment_labels: HashMap<(&str, &str), BTreeMap<String, String>> = deployment_labels
+            .iter()
+            .map(|(labels, _)| {
+                let exported = labels
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        Some((key.strip_prefix("label_")?.to_string(), value.clone()))
+                    })
+                    .collect();
+                (
+                    (label(labels, "namespace"), label(labels, "deployment")),
+                    exported,
+                )
+            })
+            .collect();
+
+        let mut deployments = Vec::new();
+        for (labels, value) in &replicas {
+            let key = (label(labels, "namespace"), label(labels, "deployment"));
+            let owned = replica_sets.get(&key).cloned().unwrap_or_default();
+
+            // Specs come from the newest ReplicaSet that still has pods
+            let newest = owned
+                .iter()
+                .filter(|rs| pods.contains_key(&(key.0, **rs)))
+                .max_by(|a, b| {
+                    let created = |rs: &&&str| replica_set_created.get(&(key.0, **rs)).copied();
+                    created(a)
+                        .partial_cmp(&created(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+            let pod = newest
+                .and_then(|rs| pods.get(&(key.0, *rs)))
+                .and_then(|pods| pods.first().copied());
+
+            let quantity = |series: &HashMap<(String, String, String, String), f64>,
+                            container: &str,
+                            resource: &str| {
+                let pod = pod?;
+                let value = *series.get(&(
+                    key.0.to_string(),
+                    pod.to_string(),
+                    container.to_string(),
+                    resource.to_string(),
+                ))?;
+                Some(match resource {
+                    "memory" => Quantity::from_bytes(value),
+                    _ => Quantity::from_cores(value),
+                })
+            };
+            let containers = pod
+                .and_then(|pod| pod_containers.get(&(key.0, pod)))
+                .into_iter()
+                .flatten()
+                .map(|name| ContainerResources {
+                    name: name.to_string(),
+                    cpu_request: quantity(&requests, name, "cpu"),
+                    cpu_limit: quantity(&limits, name, "cpu"),
+                    memory_request: quantity(&requests, name, "memory"),
+                    memory_limit: quantity(&limits, name, "memory"),
+                })
+                .collect();
+
+            deployments.push(DeploymentResources {
+                name: key.1.to_string(),
+                namespace: key.0.to_string(),
+                kind: WorkloadKind::Deployment,
+                containers,
+                replicas: Some(*value as i32),
+                created_at: created
+                    .get(&key)
+                    .and_then(|seconds| DateTime::from_timestamp(*seconds as i64, 0)),
+                replica_sets: owned.iter().map(|rs| rs.to_string()).collect(),
+                labels: deployment_labels.get(&key).cloned().unwrap_or_default(),
+                lookback_hours: None,
+                canary: false,
+                vpa: None,
+                hpa: None,
+                max_unavailable: None,
+                pdbs: Vec::new(),
+                provenance: None,
+            });
+        }
+        deployments.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+
+        info!(
+            "Discovered {} deployments from kube-state-metrics",
+            deployments.len()
+        );
+        Ok(deployments)
+    }
+}
@@ -0,0 +1,683 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::apps::v1::{Deployment, ReplicaSet};
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::PodTemplateSpec;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::{Client, Config, config::KubeConfigOptions};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::canary::CANARY_ANNOTATION;
+use crate::hpa::{Hpa, list_hpas};
+use crate::lookback::LOOKBACK_ANNOTATION;
+use crate::output::SkipReason;
+use crate::pdb::{Pdb, list_pdbs};
+use crate::provenance::Provenance;
+use crate::quantity::Quantity;
+use crate::vpa::{Vpa, list_vpas};
+use crate::{
+    KubernetesConfig as RecommenderConfig, KubernetesError::ApiError,
+    KubernetesError::ConnectionFailed, KubernetesError::Kubeconfig, RecommenderError, Result,
+};
+
+/// Source of the workloads to analyze
+///
+/// Implemented by [`KubernetesLoader`] for live clusters and by
+/// [`StaticWorkloadSource`] for fixtures.
+#[async_trait]
+pub trait WorkloadSource: Send + Sync {
+    /// List workloads with the resource specs of their containers
+    async fn list_workloads(&self) -> Result<Vec<DeploymentResources>>;
+}
+
+/// Kind of the workload a [`DeploymentResources`] was read from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WorkloadKind {
+    #[default]
+    Deployment,
+    CronJob,
+    /// A Job not created by a CronJob
+    Job,
+}
+
+impl WorkloadKind {
+    /// Whether pods run to completion, sized on the peaks of their runs
+    pub fn is_batch(self) -> bool {
+        matches!(self, WorkloadKind::CronJob | WorkloadKind::Job)
+    }
+
+    pub fn is_deployment(&self) -> bool {
+        *self == WorkloadKind::Deployment
+    }
+
+    /// `apiVersion` of manifests of this kind
+    pub fn api_version(self) -> &'static str {
+        match self {
+            WorkloadKind::Deployment => "apps/v1",
+            WorkloadKind::CronJob | WorkloadKind::Job => "batch/v1",
+        }
+    }
+}
+
+impl fmt::Display for WorkloadKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WorkloadKind::Deployment => "Deployment",
+            WorkloadKind::CronJob => "CronJob",
+            WorkloadKind::Job => "Job",
+        })
+    }
+}
+
+/// A workload and the resource specs of its containers, a Deployment unless `kind` says otherwise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeploymentResources {
+    pub name: String,
+    pub namespace: String,
+    #[serde(default, skip_serializing_if = "WorkloadKind::is_deployment")]
+    pub kind: WorkloadKind,
+    pub containers: Vec<ContainerResources>,
+    /// Desired replicas, None when the spec leaves it to the default of 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// ReplicaSets owned by the Deployment, empty when unknown
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replica_sets: Vec<String>,
+    /// Labels of the Deployment, with pod template labels it doesn't set itself
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Lookback from the Deployment's [`LOOKBACK_ANNOTATION`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lookback_hours: Option<f64>,
+    /// Whether the Deployment has the [`CANARY_ANNOTATION`] set to `"true"`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub canary: bool,
+    /// VerticalPodAutoscaler targeting the Deployment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vpa: Option<Vpa>,
+    /// HorizontalPodAutoscaler scaling the Deployment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hpa: Option<Hpa>,
+    /// Pods a rolling update may take down at once, all of them with `Recreate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_unavailable: Option<i32>,
+    /// PodDisruptionBudgets covering the Deployment's pods
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pdbs: Vec<Pdb>,
+    /// Values last applied to the Deployment, from its [`crate::provenance::PROVENANCE_ANNOTATION`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+impl DeploymentResources {
+    /// Why usage over a window starting at `window_start` can't be trusted, if at all
+    pub fn skip_reason(&self, window_start: DateTime<Utc>) -> Option<SkipReason> {
+        if self.replicas == Some(0) {
+            return Some(SkipReason::ScaledToZero);
+        }
+        match self.created_at {
+            Some(created_at) if created_at > window_start => {
+                Some(SkipReason::CreatedWithinWindow { created_at })
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a pod seen in the metrics belongs to this Deployment
+    ///
+    /// Deployment pods are named `<deployment>-<pod-template-hash>-<suffix>`. When the
+    /// owning ReplicaSets are known the pod's ReplicaSet must be one of them, which
+    /// also rejects Job or StatefulSet pods that happen to share the naming scheme.
+    /// CronJob pods follow the same scheme with the scheduled time instead of the
+    /// hash, Job pods are named `<job>-<suffix>`.
+    pub fn owns_pod(&self, pod: &str) -> bool {
+        let Some((replica_set, _)) = pod.rsplit_once('-') else {
+            return false;
+        };
+        if self.kind == WorkloadKind::Job {
+            return replica_set == self.name;
+        }
+        if !self.replica_sets.is_empty() {
+            return self.replica_sets.iter().any(|rs| rs == replica_set);
+        }
+        replica_set
+            .rsplit_once('-')
+            .is_some_and(|(deployment, _)| deployment == self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContainerResources {
+    pub name: String,
+    #[serde(with = "crate::quantity::optional")]
+    pub cpu_request: Option<Quantity>,
+    #[serde(with = "crate::quantity::optional")]
+    pub cpu_limit: Option<Quantity>,
+    #[serde(with = "crate::quantity::optional")]
+    pub memory_request: Option<Quantity>,
+    #[serde(with = "crate::quantity::optional")]
+    pub memory_limit: Option<Quantity>,
+}
+
+pub struct KubernetesLoader {
+    client: Client,
+    config: RecommenderConfig,
+}
+
+impl KubernetesLoader {
+    pub async fn new(config: RecommenderConfig) -> Result<Self> {
+        let client = if let Some(ref context) = config.context {
+            debug!("Using custom context for Kubeconfig");
+            let custom_config = Config::from_kubeconfig(&KubeConfigOptions {
+                context: Some(context.clone()),
+                ..Default::default()
+            })
+            .await
+            .map_err(Kubeconfig)?;
+
+            debug!("Creating a Kubernetes client using custom Kubeconfig");
+            Client::try_from(custom_config).map_err(ConnectionFailed)?
+        } else {
+            debug!("Creating a Kubernetes client using default Kubeconfig");
+            Client::try_default().await.map_err(ConnectionFailed)?
+        };
+
+        info!("Successfully created Kubernetes client");
+        Ok(Self { client, config })
+    }
+
+    /// Get a handle to the underlying Kubernetes client
+    pub fn client(&self) -> Client {
+        self.client.clone()
+    }
+
+    /// Names of the deployments the config's namespace and workload filter keep
+    pub async fn get_deployments(&self) -> Result<Vec<String>> {
+        let lp = self.deployment_list_params();
+        let deployments = if let Some(namespace) = self.config.namespace.as_deref() {
+            debug!("Listing all deployments in {namespace} namespace");
+            let api: kube::Api<k8s_openapi::api::apps::v1::Deployment> =
+                kube::Api::namespaced(self.client.clone(), namespace);
+            api.list(&lp).await.map_err(ApiError)?
+        } else {
+            debug!("Listing all deployments in all namespaces");
+            let api: kube::Api<k8s_openapi::api::apps::v1::Deployment> =
+                kube::Api::all(self.client.clone());
+            api.list(&lp).await.map_err(ApiError)?
+        };
+
+        info!("Retrieved all deployments");
+        Ok(deployments
+            .items
+            .into_iter()
+            .filter(|d| {
+                self.config.workloads.includes_name(
+                    d.metadata.namespace.as_deref().unwrap_or_default(),
+                    d.metadata.name.as_deref().unwrap_or_default(),
+                )
+            })
+            .filter_map(|d| d.metadata.name)
+            .collect())
+    }
+
+    /// Deployments with the resource specs of their containers
+    ///
+    /// The label selector of the workload filter is evaluated by the API server,
+    /// its name patterns once the Deployments are listed.
+    pub async fn get_deployment_resources(&self) -> Result<Vec<DeploymentResources>> {
+        let lp = self.deployment_list_params();
+        let deployments = if let Some(namespace) = self.config.namespace.as_deref() {
+            debug!("Listing all deployments with resources in {namespace} namespace");
+            let api: kube::Api<Deployment> = kube::Api::namespaced(self.client.clone(), namespace);
+            api.list(&lp).await.map_err(ApiError)?
+        } else {
+            debug!("Listing all deployments with resources in all namespaces");
+            let api: kube::Api<Deployment> = kube::Api::all(self.client.clone());
+            api.list(&lp).await.map_err(ApiError)?
+        };
+
+        let mut deployment_resources: Vec<DeploymentResources> = deployments
+            .items
+            .iter()
+            .filter_map(deployment_resources)
+            .filter(|d| self.config.workloads.includes_name(&d.namespace, &d.name))
+            .collect();
+
+        // Without ReplicaSets pod ownership falls back to the naming scheme
+        match self.get_replica_set_owners().await {
+            Ok(mut owners) => {
+                for deployment in &mut deployment_resources {
+                    let key = (deployment.namespace.clone(), deployment.name.clone());
+                    deployment.replica_sets = owners.remove(&key).unwrap_or_default();
+                }
+            }
+            Err(e) => warn!(
+                "Failed to list ReplicaSets, matching pods by name only: {}",
+                e
+            ),
+        }
+
+        // Without VPAs every workload counts as unmanaged
+        match list_vpas(self.client.clone(), self.config.namespace.as_deref()).await {
+            Ok(mut vpas) => {
+                for deployment in &mut deployment_resources {
+                    let key = (deployment.namespace.clone(), deployment.name.clone());
+                    deployment.vpa = vpas.remove(&key);
+                }
+            }
+            Err(e) => warn!("Failed to list VerticalPodAutoscalers: {}", e),
+        }
+
+        // Without HPAs no utilization target is suggested
+        match list_hpas(self.client.clone(), self.config.namespace.as_deref()).await {
+            Ok(mut hpas) => {
+                for deployment in &mut deployment_resources {
+                    let key = (deployment.namespace.clone(), deployment.name.clone());
+                    deployment.hpa = hpas.remove(&key);
+                }
+            }
+            Err(e) => warn!("Failed to list HorizontalPodAutoscalers: {}", e),
+        }
+
+        // Without PDBs rollouts aren't checked for disruption
+        match list_pdbs(self.client.clone(), self.config.namespace.as_deref()).await {
+            Ok(pdbs) => {
+                for deployment in &mut deployment_resources {
+                    deployment.pdbs = pdbs
+                        .get(&deployment.namespace)
+                        .into_iter()
+                        .flatten()
+                        .filter(|pdb| pdb.covers(&deployment.labels))
+                        .cloned()
+                        .collect();
+                }
+            }
+            Err(e) => warn!("Failed to list PodDisruptionBudgets: {}", e),
+        }
+
+        info!(
+            "Retrieved {} deployments with resource specs",
+            deployment_resources.len()
+        );
+        Ok(deployment_resources)
+    }
+
+    /// CronJobs, and Jobs not created by one, with the resource specs of their containers
+    ///
+    /// Filtered like [`KubernetesLoader::get_deployment_resources`].
+    pub async fn get_batch_resources(&self) -> Result<Vec<DeploymentResources>> {
+        let lp = self.deployment_list_params();
+        let (cronjobs, jobs): (kube::Api<CronJob>, kube::Api<Job>) =
+            match self.config.namespace.as_deref() {
+                Some(namespace) => (
+                    kube::Api::namespaced(self.client.clone(), namespace),
+                    kube::Api::namespaced(self.client.clone(), namespace),
+                ),
+                None => (
+                    kube::Api::all(self.client.clone()),
+                    kube::Api::all(self.client.clone()),
+                ),
+            };
+        debug!("Listing CronJobs and Jobs with resources");
+        let cronjobs = cronjobs.list(&lp).await.map_err(ApiError)?;
+        let jobs = jobs.list(&lp).await.map_err(ApiError)?;
+
+        let batch: Vec<DeploymentResources> = cronjobs
+            .items
+            .iter()
+            .filter_map(cronjob_resources)
+            .chain(jobs.items.iter().filter_map(job_resources))
+            .filter(|w| self.config.workloads.includes_name(&w.namespace, &w.name))
+            .collect();
+
+        info!(
+            "Retrieved {} CronJobs and Jobs with resource specs",
+            batch.len()
+        );
+        Ok(batch)
+    }
+
+    /// Listing Deployments matching the workload filter's label selector
+    fn deployment_list_params(&self) -> kube::api::ListParams {
+        let lp = kube::api::ListParams::default();
+        match &self.config.workloads.selector {
+            Some(selector) => lp.labels(&selector.to_string()),
+            None => lp,
+        }
+    }
+
+    /// ReplicaSet names keyed by the namespace and name of their owning Deployment
+    async fn get_replica_set_owners(&self) -> Result<HashMap<(String, String), Vec<String>>> {
+        let lp = kube::api::ListParams::default();
+        let api: kube::Api<ReplicaSet> = match self.config.namespace.as_deref() {
+            Some(namespace) => kube::Api::namespaced(self.client.clone(), namespace),
+            None => kube::Api::all(self.client.clone()),
+        };
+        let replica_sets = api.list(&lp).await.map_err(ApiError)?;
+
+        let mut owners: HashMap<(String, String), Vec<String>> = HashMap::new();
+        for rs in replica_sets.items {
+            let (Some(name), Some(namespace)) = (rs.metadata.name, rs.metadata.namespace) else {
+                continue;
+            };
+            let owner = rs
+                .metadata
+                .owner_references
+                .unwrap_or_default()
+                .into_iter()
+                .find(|owner| owner.kind == "Deployment");
+            if let Some(owner) = owner {
+                owners
+                    .entry((namespace, owner.name))
+                    .or_default()
+                    .push(name);
+            }
+        }
+
+        debug!("Found ReplicaSets of {} deployments", owners.len());
+        Ok(owners)
+    }
+}
+
+#[async_trait]
+impl WorkloadSource for KubernetesLoader {
+    async fn list_workloads(&self) -> Result<Vec<DeploymentResources>> {
+        let mut workloads = self.get_deployment_resources().await?;
+        if self.config.include_jobs {
+            workloads.extend(self.get_batch_resources().await?);
+        }
+        Ok(workloads)
+    }
+}
+
+/// In-memory workload source backed by a fixed list of deployments
+#[derive(Debug, Clone, Default)]
+pub struct StaticWorkloadSource {
+    workloads: Vec<DeploymentResources>,
+}
+
+impl StaticWorkloadSource {
+    pub fn new(workloads: Vec<DeploymentResources>) -> Self {
+        Self { workloads }
+    }
+
+    /// Parse Deployment, CronJob and Job manifests, e.g. `fixtures/deployments.yaml`
+    ///
+    /// Multi-document YAML is supported; documents of other kinds are ignored.
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let mut workloads = Vec::new();
+
+        for doc in serde_yaml::Deserializer::from_str(content) {
+            let value = serde_yaml::Value::deserialize(doc)?;
+            let kind = value
+                .get("kind")
+                .and_then(|k| k.as_str())
+                .map(str::to_string);
+            let workload = match kind.as_deref() {
+                Some("Deployment") => {
+                    deployment_resources(&serde_yaml::from_value::<Deployment>(value)?)
+                }
+                Some("CronJob") => cronjob_resources(&serde_yaml::from_value::<CronJob>(value)?),
+                Some("Job") => job_resources(&serde_yaml::from_value::<Job>(value)?),
+                _ => continue,
+            };
+            workloads.extend(workload.map(|mut workload| {
+                // Manifests usually leave the namespace to kubectl
+                if workload.namespace.is_empty() {
+                    workload.namespace = "default".to_string();
+                }
+                workload
+            }));
+        }
+
+        Ok(Self { workloads })
+    }
+
+    /// Read and parse a manifest file, see [`StaticWorkloadSource::from_yaml`]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            RecommenderError::InvalidInput(format!(
+                "Failed to read manifest file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Self::from_yaml(&content)
+    }
+}
+
+#[async_trait]
+impl WorkloadSource for StaticWorkloadSource {
+    async fn list_workloads(&self) -> Result<Vec<DeploymentResources>> {
+        Ok(self.workloads.clone())
+    }
+}
+
+/// Extract per-container resource specs from a Deployment
+///
+/// Returns None when the Deployment has no pod template spec.
+pub fn deployment_resources(deployment: &Deployment) -> Option<DeploymentResources> {
+    let spec = deployment.spec.as_ref()?;
+    let mut resources = workload_resources(
+        WorkloadKind::Deployment,
+        &deployment.metadata,
+        &spec.template,
+    )?;
+    resources.replicas = spec.replicas;
+    resources.max_unavailable = Some(max_unavailable(
+        spec.strategy.as_ref(),
+        spec.replicas.unwrap_or(1),
+    ));
+    Some(resources)
+}
+
+/// Extract per-container resource specs from a CronJob's job template
+///
+/// A suspended CronJob counts as scaled to zero.
+pub fn cronjob_resources(cronjob: &CronJob) -> Option<DeploymentResources> {
+    let spec = cronjob.spec.as_ref()?;
+    let template = &spec.job_template.spec.as_ref()?.template;
+    let mut resources = workload_resources(WorkloadKind::CronJob, &cronjob.metadata, template)?;
+    if spec.suspend == Some(true) {
+        resources.replicas = Some(0);
+    }
+    Some(resources)
+}
+
+/// Extract per-container resource specs from a Job not created by a CronJob
+///
+/// Jobs usually run within the lookback of their creation, so it isn't kept.
+pub fn job_resources(job: &Job) -> Option<DeploymentResources> {
+    let owned_by_cronjob = job
+        .metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .any(|owner| owner.kind == "CronJob");
+    if owned_by_cronjob {
+        return None;
+    }
+    let mut resources = workload_resources(
+        WorkloadKind::Job,
+        &job.metadata,
+        &job.spec.as_ref()?.template,
+    )?;
+    resources.created_at = None;
+    Some(resources)
+}
+
+/// Resource specs, labels and annotations shared by every kind of workload
+fn workload_resources(
+    kind: WorkloadKind,
+    metadata: &ObjectMeta,
+    template: &PodTemplateSpec,
+) -> Option<DeploymentResources> {
+    let pod_spec = template.spec.as_ref()?;
+
+    let containers = pod_spec
+        .containers
+        .iter()
+        .map(|container| {
+            let resources = container.resources.as_ref();
+            let requests = resources.and_then(|r| r.requests.as_ref());
+            let limits = resources.and_then(|r| r.limits.as_ref());
+            let quantity = |q: &k8s_openapi::apimachinery::pkg::api::resource::Quantity| {
+                Quantity::parse(&q.0)
+                    .inspect_err(|e| {
+                        warn!("Ignoring resource of container {}: {}", container.name, e)
+                    })
+                    .ok()
+            };
+
+            ContainerResources {
+                name: container.name.clone(),
+                cpu_request: requests.and_then(|req| req.get("cpu")).and_then(quantity),
+                cpu_limit: limits.and_then(|lim| lim.get("cpu")).and_then(quantity),
+                memory_request: requests
+                    .and_then(|req| req.get("memory"))
+                    .and_then(quantity),
+                memory_limit: limits.and_then(|lim| lim.get("memory")).and_then(quantity),
+            }
+        })
+        .collect();
+
+    let mut labels: BTreeMap<String, String> = template
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.labels.clone())
+        .unwrap_or_default();
+    labels.extend(metadata.labels.clone().unwrap_or_default());
+
+    let name = metadata.name.clone().unwrap_or_default();
+    let annotations = metadata.annotations.as_ref();
+    let lookback_hours = annotations
+        .and_then(|annotations| annotations.get(LOOKBACK_ANNOTATION))
+        .and_then(|value| match value.trim().parse::<f64>() {
+            Ok(hours) if hours.is_finite() && hours > 0.0 => Some(hours),
+            _ => {
+                warn!(
+                    "Ignoring {} {:?} of {} {}, expected hours greater than 0",
+                    LOOKBACK_ANNOTATION, value, kind, name
+                );
+                None
+            }
+        });
+
+    let canary = annotations
+        .and_then(|annotations| annotations.get(CANARY_ANNOTATION))
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"));
+    let provenance = annotations.and_then(Provenance::from_annotations);
+
+    Some(DeploymentResources {
+        name,
+        namespace: metadata.namespace.clone().unwrap_or_default(),
+        kind,
+        containers,
+        replicas: None,
+        created_at: metadata.creation_timestamp.as_ref().map(|time| time.0),
+        replica_sets: Vec::new(),
+        labels,
+        lookback_hours,
+        canary,
+        vpa: None,
+        hpa: None,
+        max_unavailable: None,
+        pdbs: Vec::new(),
+        provenance,
+    })
+}
+
+/// Pods a rollout of `replicas` with `strategy` may take down at once
+///
+/// Percentages round down like in the Deployment controller, the default
+/// rolling update allows 25% unavailable.
+fn max_unavailable(
+    strategy: Option<&k8s_openapi::api::apps::v1::DeploymentStrategy>,
+    replicas: i32,
+) -> i32 {
+    if strategy.and_then(|s| s.type_.as_deref()) == Some("Recreate") {
+        return replicas;
+    }
+    let value = strategy
+        .and_then(|s| s.rolling_update.as_ref())
+        .and_then(|r| r.max_unavailable.clone())
+        .unwrap_or_else(|| IntOrString::String("25%".to_string()));
+    match value {
+        IntOrString::Int(pods) => pods,
+        IntOrString::String(value) => value
+            .strip_suffix('%')
+            .and_then(|percent| percent.trim().parse::<i32>().ok())
+            .map_or(0, |percent| replicas * percent / 100),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantity(value: &str) -> Option<Quantity> {
+        Some(Quantity::parse(value).unwrap())
+    }
+
+    #[tokio::test]
+    async fn loads_fixture_deployments() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/deployments.yaml");
+        let workloads = StaticWorkloadSource::from_file(path)
+            .unwrap()
+            .list_workloads()
+            .await
+            .unwrap();
+
+        // The Service is skipped
+        assert_eq!(workloads.len(), 2);
+        let [web, worker] = workloads.as_slice() else {
+            unreachable!();
+        };
+
+        assert_eq!((web.namespace.as_str(), web.name.as_str()), ("shop", "web"));
+        assert_eq!(web.kind, WorkloadKind::Deployment);
+        let names: Vec<&str> = web.containers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["nginx", "log-shipper"]);
+
+        let nginx = &web.containers[0];
+        assert_eq!(nginx.cpu_request, quantity("250m"));
+        assert_eq!(nginx.memory_request, quantity("256Mi"));
+        assert_eq!(nginx.cpu_limit, quantity("1"));
+        assert_eq!(nginx.memory_limit, quantity("512Mi"));
+
+        let shipper = &web.containers[1];
+        assert_eq!(shipper.cpu_request, quantity("50m"));
+        assert_eq!(shipper.memory_request, quantity("64Mi"));
+        assert_eq!(shipper.cpu_limit, None);
+        assert_eq!(shipper.memory_limit, None);
+
+        // Without a namespace in the manifest, like `kubectl apply` without `-n`
+        assert_eq!(
+            (worker.namespace.as_str(), worker.name.as_str()),
+            ("default", "worker")
+        );
+        let [container] = worker.containers.as_slice() else {
+            panic!("expected one container, got {:?}", worker.containers);
+        };
+        assert_eq!(container.name, "worker");
+        assert_eq!(container.cpu_request, None);
+        assert_eq!(container.memory_request, None);
+        assert_eq!(container.cpu_limit, None);
+        assert_eq!(container.memory_limit, None);
+    }
+
+    #[test]
+    fn missing_manifest_file_is_invalid_input() {
+        let error = StaticWorkloadSource::from_file("does/not/exist.yaml").unwrap_err();
+        assert!(matches!(error, RecommenderError::InvalidInput(_)));
+    }
+}
@@ -0,0 +1,143 @@
+//! Kustomize patches of the recommendations, for overlays left to GitOps
+//!
+//! Each recommended Deployment gets a strategic-merge patch setting its
+//! containers' resources, listed in the directory's `kustomization.yaml`. The
+//! base manifests are never touched.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde_json::json;
+use serde_yaml::{Mapping, Value};
+
+use crate::kubernetes::WorkloadKind;
+use crate::recommender::{DayProfile, ResourceRecommendation};
+use crate::{ConfigError, Result};
+
+/// Kustomization listing the patches, extended in place when the directory has one
+pub const KUSTOMIZATION_FILE: &str = "kustomization.yaml";
+
+/// Patch files keyed by file name, `<namespace>.<deployment>.yaml`
+///
+/// CronJobs and Jobs are patched under their own kind.
+///
+/// With `--day-split both` only the weekday recommendations are patched in, like
+/// for the savings totals.
+pub fn kustomize_patches(
+    recommendations: &[ResourceRecommendation],
+) -> Result<Vec<(String, String)>> {
+    let mut deployments: BTreeMap<(&str, &str), Vec<&ResourceRecommendation>> = BTreeMap::new();
+    for recommendation in recommendations {
+        if recommendation.day_profile == Some(DayProfile::Weekend) {
+            continue;
+        }
+        deployments
+            .entry((
+                recommendation.namespace.as_str(),
+                recommendation.deployment.as_str(),
+            ))
+            .or_default()
+            .push(recommendation);
+    }
+
+    deployments
+        .into_iter()
+        .map(|((namespace, deployment), containers)| {
+            let kind = containers[0].workload_kind;
+            let pod_spec = json!({ "template": { "spec": {
+                "containers": containers.into_iter().map(container_patch).collect::<Vec<_>>(),
+            } } });
+            // A CronJob's pod template sits in its job template
+            let spec = match kind {
+                WorkloadKind::CronJob => json!({ "jobTemplate": { "spec": pod_spec } }),
+                _ => pod_spec,
+            };
+            let patch = json!({
+                "apiVersion": kind.api_version(),
+                "kind": kind.to_string(),
+                "metadata": { "name": deployment, "namespace": namespace },
+                "spec": spec,
+            });
+            Ok((
+                format!("{}.{}.yaml", namespace, deployment),
+                serde_yaml::to_string(&patch)?,
+            ))
+        })
+        .collect()
+}
+
+/// Write the [`kustomize_patches`] of `recommendations` into `dir` and list them
+/// in its kustomization
+///
+/// An existing `kustomization.yaml` keeps its resources and other patches.
+/// Returns the number of patch files written.
+pub fn write_kustomize_patches(
+    recommendations: &[ResourceRecommendation],
+    dir: &Path,
+) -> Result<usize> {
+    let patches = kustomize_patches(recommendations)?;
+    std::fs::create_dir_all(dir)?;
+    for (name, content) in &patches {
+        std::fs::write(dir.join(name), content)?;
+    }
+
+    let path = dir.join(KUSTOMIZATION_FILE);
+    let mut kustomization = if path.is_file() {
+        serde_yaml::from_str(&std::fs::read_to_string(&path)?)?
+    } else {
+        let mut kustomization = Mapping::new();
+        kustomization.insert(
+            "apiVersion".into(),
+            "kustomize.config.k8s.io/v1beta1".into(),
+        );
+        kustomization.insert("kind".into(), "Kustomization".into());
+        Value::Mapping(kustomization)
+    };
+    let Some(kustomization_map) = kustomization.as_mapping_mut() else {
+        return Err(
+            ConfigError::InvalidValue(format!("{} is not a mapping", path.display())).into(),
+        );
+    };
+
+    let key = Value::from("patches");
+    if !kustomization_map.get(&key).is_some_and(Value::is_sequence) {
+        kustomization_map.insert(key.clone(), Value::Sequence(Vec::new()));
+    }
+    let listed = kustomization_map
+        .get_mut(&key)
+        .and_then(Value::as_sequence_mut)
+        .expect("patches is a sequence");
+    for (name, _) in &patches {
+        let already_listed = listed
+            .iter()
+            .any(|patch| patch.get("path").and_then(Value::as_str) == Some(name.as_str()));
+        if !already_listed {
+            let mut patch = Mapping::new();
+            patch.insert("path".into(), name.as_str().into());
+            listed.push(Value::Mapping(patch));
+        }
+    }
+
+    std::fs::write(&path, serde_yaml::to_string(&kustomization)?)?;
+    Ok(patches.len())
+}
+
+/// Container entry of a patch, merged into the base by the container's name
+fn container_patch(recommendation: &ResourceRecommendation) -> serde_json::Value {
+    let mut recommendation = recommendation.clone();
+    // Never write a limit below its request
+    recommendation.enforce_limits_above_requests();
+    json!({
+        "name": recommendation.container,
+        "resources": {
+            "requests": {
+                "cpu": recommendation.recommended_cpu_request.to_string(),
+                "memory": recommendation.recommended_memory_request.to_string(),
+            },
+            "limits": {
+                "cpu": recommendation.recommended_cpu_limit.to_string(),
+                "memory": recommendation.recommended_memory_limit.to_string(),
+            },
+        },
+    })
+}
@@ -0,0 +1,246 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{MicroTime, ObjectMeta};
+use kube::Client;
+use kube::api::{Api, PostParams};
+use log::{debug, info, warn};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::LeaderElectionConfig;
+use crate::{KubernetesError::ApiError, Result};
+
+/// Kubernetes Lease-based leader election
+///
+/// Only the instance holding the lease runs analyses and opens PRs. The lease
+/// is taken over once the current holder stops renewing it for longer than
+/// the configured lease duration.
+pub struct LeaderElector {
+    api: Api<Lease>,
+    config: LeaderElectionConfig,
+    is_leader: Arc<AtomicBool>,
+}
+
+impl LeaderElector {
+    pub fn new(client: Client, config: LeaderElectionConfig) -> Self {
+        let api = Api::namespaced(client, &config.lease_namespace);
+
+        Self {
+            api,
+            config,
+            is_leader: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Whether this instance currently holds the lease
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+
+    /// Try to acquire (or renew) the lease, returns true if we are the leader
+    pub async fn try_acquire(&self) -> Result<bool> {
+        let acquired = try_acquire_lease(&self.api, &self.config).await?;
+        self.is_leader.store(acquired, Ordering::SeqCst);
+
+        if acquired {
+            info!(
+                "Acquired leader lease {}/{} as {}",
+                self.config.lease_namespace, self.config.lease_name, self.config.identity
+            );
+        }
+
+        Ok(acquired)
+    }
+
+    /// Spawn a background task that keeps renewing the lease until the returned
+    /// [`Renewal`] is stopped or dropped
+    ///
+    /// If a renewal fails or another instance took over, `is_leader` flips to
+    /// false so callers can stop before doing any further work.
+    pub fn spawn_renewal(&self) -> Renewal {
+        let api = self.api.clone();
+        let config = self.config.clone();
+        let is_leader = self.is_leader.clone();
+        let interval = self.config.renew_interval();
+        let cancel = CancellationToken::new();
+        let stopped = cancel.clone();
+
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = stopped.cancelled() => return,
+                    _ = tokio::time::sleep(interval) => {}
+                }
+
+                match try_acquire_lease(&api, &config).await {
+                    Ok(true) => debug!("Renewed leader lease {}", config.lease_name),
+                    Ok(false) => {
+                        warn!(
+                            "Lost leader lease {} to another instance",
+                            config.lease_name
+                        );
+                        is_leader.store(false, Ordering::SeqCst);
+                        return;
+                    }
+                    Err(e) => {
+                        warn!("Failed to renew leader lease {}: {}", config.lease_name, e);
+                        is_leader.store(false, Ordering::SeqCst);
+                        return;
+                    }
+                }
+            }
+        });
+
+        Renewal {
+            cancel,
+            task: Some(task),
+        }
+    }
+
+    /// Stop `renewal` and release the lease so another instance can take over immediately
+    ///
+    /// The renewal is stopped first, otherwise its next tick would take the
+    /// released lease right back.
+    pub async fn release(&self, renewal: Renewal) -> Result<()> {
+        renewal.stop().await;
+        if !self.is_leader() {
+            return Ok(());
+        }
+
+        let Some(mut lease) = self
+            .api
+            .get_opt(&self.config.lease_name)
+            .await
+            .map_err(ApiError)?
+        else {
+            return Ok(());
+        };
+
+        let spec = lease.spec.get_or_insert_with(LeaseSpec::default);
+        if spec.holder_identity.as_deref() != Some(self.config.identity.as_str()) {
+            return Ok(());
+        }
+
+        spec.holder_identity = None;
+        spec.acquire_time = None;
+        spec.renew_time = None;
+
+        self.api
+            .replace(&self.config.lease_name, &PostParams::default(), &lease)
+            .await
+            .map_err(ApiError)?;
+
+        self.is_leader.store(false, Ordering::SeqCst);
+        info!("Released leader lease {}", self.config.lease_name);
+        Ok(())
+    }
+}
+
+/// Background renewal of a held lease, stopped when dropped
+pub struct Renewal {
+    cancel: CancellationToken,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Renewal {
+    /// Stop renewing, waiting for a renewal in flight to finish
+    pub async fn stop(mut self) {
+        self.cancel.cancel();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for Renewal {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+/// Create, renew or take over the lease depending on its current state
+async fn try_acquire_lease(api: &Api<Lease>, config: &LeaderElectionConfig) -> Result<bool> {
+    let now = Utc::now();
+    let lease_duration = config.lease_duration.as_secs() as i32;
+
+    let existing = api.get_opt(&config.lease_name).await.map_err(ApiError)?;
+
+    let Some(mut lease) = existing else {
+        debug!("Creating leader lease {}", config.lease_name);
+        let lease = Lease {
+            metadata: ObjectMeta {
+                name: Some(config.lease_name.clone()),
+                namespace: Some(config.lease_namespace.clone()),
+                ..Default::default()
+            },
+            spec: Some(LeaseSpec {
+                holder_identity: Some(config.identity.clone()),
+                lease_duration_seconds: Some(lease_duration),
+                acquire_time: Some(MicroTime(now)),
+                renew_time: Some(MicroTime(now)),
+                lease_transitions: Some(0),
+                ..Default::default()
+            }),
+        };
+
+        return match api.create(&PostParams::default(), &lease).await {
+            Ok(_) => Ok(true),
+            // Another instance created it first
+            Err(kube::Error::Api(resp)) if resp.code == 409 => Ok(false),
+            Err(e) => Err(ApiError(e).into()),
+        };
+    };
+
+    let spec = lease.spec.get_or_insert_with(LeaseSpec::default);
+    let held_by_us = spec.holder_identity.as_deref() == Some(config.identity.as_str());
+
+    if !held_by_us && !lease_expired(spec, now) {
+        debug!(
+            "Leader lease {} is held by {:?}",
+            config.lease_name, spec.holder_identity
+        );
+        return Ok(false);
+    }
+
+    if !held_by_us {
+        spec.holder_identity = Some(config.identity.clone());
+        spec.acquire_time = Some(MicroTime(now));
+        spec.lease_transitions = Some(spec.lease_transitions.unwrap_or(0) + 1);
+    }
+    spec.lease_duration_seconds = Some(lease_duration);
+    spec.renew_time = Some(MicroTime(now));
+
+    // The resourceVersion carried over from the GET makes this a compare-and-swap
+    match api
+        .replace(&config.lease_name, &PostParams::default(), &lease)
+        .await
+    {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(resp)) if resp.code == 409 => Ok(false),
+        Err(e) => Err(ApiError(e).into()),
+    }
+}
+
+/// A lease without a holder, or one not renewed within its duration, is up for grabs
+fn lease_expired(spec: &LeaseSpec, now: chrono::DateTime<Utc>) -> bool {
+    if spec
+        .holder_identity
+        .as_deref()
+        .unwrap_or_default()
+        .is_empty()
+    {
+        return true;
+    }
+
+    let Some(MicroTime(renew_time)) = spec.renew_time.as_ref() else {
+        return true;
+    };
+
+    let duration = Duration::from_secs(spec.lease_duration_seconds.unwrap_or(0).max(0) as u64);
+    let expires_at = *renew_time + chrono::Duration::from_std(duration).unwrap_or_default();
+    expires_at < now
+}
@@ -2,32 +2,213 @@
 //!
 //! This library provides functionality to analyze Kubernetes pod resource usage
 //! from AWS Managed Prometheus and generate rightsizing recommendations.
+//!
+//! Most consumers only need `use recommender::prelude::*;`.
 
-pub mod lib {
-    pub mod aws_region;
-    pub mod cli;
-    pub mod config;
-    pub mod error;
-    pub mod kubernetes;
-    pub mod logger;
-    pub mod output;
-    pub mod prometheus;
-    pub mod recommender;
-    pub mod tui;
-    pub mod updater;
-}
+pub mod apply;
+pub mod autoscaling;
+pub mod aws_region;
+pub mod backtest;
+pub mod cache;
+pub mod canary;
+pub mod chargeback;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod clock;
+#[cfg(any(feature = "aws", feature = "git", feature = "oci"))]
+pub mod cloud_auth;
+pub mod config;
+#[cfg(feature = "cli")]
+pub mod config_file;
+pub mod cost;
+pub mod credentials;
+#[cfg(feature = "aws")]
+pub mod diagnose;
+pub mod error;
+pub mod filter;
+pub mod forecast;
+pub mod helm;
+pub mod history;
+pub mod hpa;
+#[cfg(any(feature = "aws", feature = "git", feature = "oci"))]
+pub mod http;
+pub mod journal;
+pub mod kube_state_metrics;
+pub mod kubernetes;
+pub mod kustomize;
+pub mod leader;
+#[cfg(feature = "cli")]
+pub mod logger;
+pub mod lookback;
+pub mod manifest;
+pub mod metric_variants;
+pub mod metrics;
+pub mod metrics_server;
+pub mod nodes;
+#[cfg(any(feature = "aws", feature = "git", feature = "oci"))]
+pub mod notifications;
+#[cfg(feature = "oci")]
+pub mod oci;
+pub mod outliers;
+pub mod output;
+pub mod overlays;
+pub mod pdb;
+pub mod pipeline;
+pub mod policy;
+#[cfg(feature = "git")]
+pub mod pr_template;
+pub mod prelude;
+#[cfg(feature = "aws")]
+pub mod prometheus;
+pub mod provenance;
+pub mod publish;
+#[cfg(any(feature = "aws", feature = "git", feature = "oci"))]
+pub mod pushgateway;
+pub mod quantity;
+pub mod query;
+pub mod quota;
+pub mod rate_limit;
+pub mod recommender;
+pub mod redact;
+#[cfg(feature = "aws")]
+pub mod s3;
+pub mod seasonality;
+pub mod snapshot;
+pub mod sops;
+pub mod stats;
+pub mod terraform;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "git")]
+pub mod updater;
+pub mod vpa;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
 // Re-export commonly used types at the root level for convenience
-pub use lib::aws_region::AwsRegion;
-pub use lib::cli::{Cli, OutputFormat};
-pub use lib::config::{GitProvider, KubernetesConfig, RecommenderConfig, UpdaterConfig};
-pub use lib::error::{
+#[cfg(feature = "git")]
+pub use apply::GitPrTarget;
+pub use apply::{ApplyOutcome, ApplyTarget, ClusterTarget, FIELD_MANAGER, LocalDirTarget};
+pub use autoscaling::{
+    AutoscalingImpact, InstanceCatalog, InstanceType, KarpenterNodePool, NodeGroupImpact,
+    Provisioner, Requirement, autoscaling_impact,
+};
+pub use aws_region::AwsRegion;
+pub use backtest::{Backtest, BacktestReport, Candidate, CandidateResult};
+pub use cache::{CachedQuery, CachingMetricsProvider, MetricsCache};
+pub use canary::{ApplyStage, CANARY_ANNOTATION, CanarySelector};
+pub use chargeback::{
+    ChargebackFormat, ChargebackLine, TeamReport, chargeback, chargeback_files, render_chargeback,
+    write_chargeback,
+};
+#[cfg(feature = "cli")]
+pub use cli::{
+    ApplyMode, BacktestArgs, Cli, Command, DiagnoseArgs, OutputFormat, ServeArgs, TrendArgs,
+};
+pub use clock::{Clock, FixedClock, SharedClock, SystemClock, Timezone};
+#[cfg(any(feature = "aws", feature = "git", feature = "oci"))]
+pub use cloud_auth::{AZURE_RESOURCE, CloudProvider, GCP_SCOPE, TokenSource};
+pub use config::{
+    DaySplit, ForecastConfig, ForecastMethod, GitProvider, HttpConfig, KubernetesConfig,
+    LeaderElectionConfig, MemoryMetric, MergeMethod, NotifyFormat, OciReference, OutlierAction,
+    OutlierFilter, PrometheusAuth, PublishTarget, RecommenderConfig, RecommenderConfigBuilder,
+    ResourceBounds, S3Location, SeasonalityConfig, UpdaterConfig, VpaMode, VpaUpdateMode,
+    WorkloadDiscovery,
+};
+#[cfg(feature = "cli")]
+pub use config_file::{CONFIG_FLAG, config_path, merge_config_file};
+pub use cost::{HOURS_PER_MONTH, ResourcePrices, format_dollars, total_monthly_savings};
+#[cfg(feature = "aws")]
+pub use diagnose::{Check, CheckStatus, DiagnoseTarget, Diagnosis, diagnose};
+pub use error::{
     AwsError, ConfigError, KubernetesError, PrometheusError, RecommenderError, Result,
 };
-pub use lib::kubernetes::{ContainerResources, DeploymentResources, KubernetesLoader};
-pub use lib::logger::init_logger;
-pub use lib::output::{OutputMetadata, PercentileConfig, RecommenderOutput};
-pub use lib::prometheus::{PrometheusClient, PrometheusData, PrometheusResponse, PrometheusResult};
-pub use lib::recommender::{Recommender, ResourceRecommendation, UsageStats};
-pub use lib::tui::display_recommendations_table;
-pub use lib::updater::ManifestUpdater;
+pub use filter::{FilteredWorkloadSource, LabelSelector, WorkloadFilter, glob_match};
+pub use forecast::{Forecast, forecast};
+pub use helm::{HelmValues, HelmValuesEntry};
+pub use history::{
+    AppliedSet, DeploymentTotals, GroupTrend, MonthTotals, RunHistory, RunRecord, SavingsTrend,
+    TrendGrouping, savings_trend,
+};
+pub use hpa::{
+    Hpa, HpaTarget, MAX_CPU_UTILIZATION, MIN_CPU_UTILIZATION, list_hpas, suggest_cpu_utilization,
+};
+pub use journal::{ContainerDrift, DriftReport, JournalEntry, RecommendationJournal, drift_report};
+pub use kube_state_metrics::KubeStateMetricsSource;
+pub use kubernetes::{
+    ContainerResources, DeploymentResources, KubernetesLoader, StaticWorkloadSource, WorkloadKind,
+    WorkloadSource, cronjob_resources, deployment_resources, job_resources,
+};
+pub use kustomize::{KUSTOMIZATION_FILE, kustomize_patches, write_kustomize_patches};
+pub use leader::{LeaderElector, Renewal};
+#[cfg(feature = "cli")]
+pub use logger::init_logger;
+pub use lookback::{LOOKBACK_ANNOTATION, LookbackOverride, LookbackOverrides};
+pub use manifest::{ManifestEditor, ManifestWorkload};
+pub use metric_variants::{MemoryVariant, MetricVariants};
+pub use metrics::{INSTANT_LOOKBACK, MetricsProvider, Sample, Series, StaticMetricsProvider};
+pub use metrics_server::{
+    DEFAULT_SAMPLE_INTERVAL, LiveUsage, MetricsServerSampler, pod_metrics_resource,
+};
+pub use nodes::{
+    InstanceTypeSavings, NodeGroupEstimate, NodeInfo, NodeInventory, NodePoolReport, PodPlacement,
+    node_pool_report,
+};
+#[cfg(any(feature = "aws", feature = "git", feature = "oci"))]
+pub use notifications::{Notification, WebhookNotifier};
+#[cfg(feature = "oci")]
+pub use oci::{ARTIFACT_TYPE, OciPusher};
+pub use outliers::{Spike, SpikeScan, find_spikes};
+pub use output::{
+    NAMESPACE_INDEX_FILE, NamespaceIndex, NamespaceIndexEntry, OutputMetadata, PercentileConfig,
+    RecommenderOutput, RecommenderOutputBuilder, SkipReason, SkippedWorkload,
+    write_namespace_outputs,
+};
+pub use overlays::{
+    EnvironmentChanges, EnvironmentOverlay, EnvironmentOverlays, merge_updates,
+    scale_recommendation,
+};
+pub use pdb::{DisruptionRisk, Pdb, disruption_risk, list_pdbs};
+pub use pipeline::{
+    AnalysisRequest, NamespacedAnalysis, RecommenderBuilder, run_analysis,
+    run_analysis_per_namespace,
+};
+pub use policy::{PolicyRule, PolicySet, PolicyViolation};
+#[cfg(feature = "aws")]
+pub use prometheus::{PrometheusClient, PrometheusData, PrometheusResponse, PrometheusResult};
+pub use provenance::{
+    AppliedResources, Drift, PROVENANCE_ANNOTATION, Provenance, VERSION, annotate_deployment,
+    record_merged_applies,
+};
+pub use publish::{
+    ANNOTATION_PREFIX, CONFIGMAP_OUTPUT_KEY, EVENT_REASON, RecommendationPublisher, event_message,
+    publish_configmap, recommendation_annotations,
+};
+#[cfg(any(feature = "aws", feature = "git", feature = "oci"))]
+pub use pushgateway::{PushgatewayExporter, exposition};
+pub use quantity::{Quantity, QuantityFormat};
+pub use query::{Aggregation, QueryTemplate};
+pub use quota::{
+    NamespaceQuota, QuotaTotals, namespace_quotas, quota_manifests, write_quota_manifests,
+};
+pub use rate_limit::{RateLimiter, RateLimiterStats};
+pub use recommender::{
+    DayProfile, Progress, ProgressCallback, Recommender, ResourceRecommendation, clamp_to_bounds,
+    enforce_limits_above_requests, recommend_from_samples,
+};
+#[cfg(feature = "aws")]
+pub use s3::S3Uploader;
+pub use seasonality::{SeasonalPeriod, Seasonality};
+pub use snapshot::{MetricsSnapshot, RecordingMetricsProvider};
+pub use sops::SopsConfig;
+pub use stats::{Interpolation, StreamingStats, UsageStats};
+pub use terraform::TerraformEditor;
+#[cfg(feature = "tui")]
+pub use tui::display_recommendations_table;
+#[cfg(feature = "git")]
+pub use updater::ManifestUpdater;
+pub use vpa::{
+    UPDATE_MODE_OFF, Vpa, VpaContainerTarget, VpaTarget, list_vpas, vpa_manifests, vpa_resource,
+};
+#[cfg(feature = "webhook")]
+pub use webhook::{DeploymentEvent, WebhookConfig, WebhookServer, parse_event};
@@ -5,6 +5,7 @@ use std::fs;
 use std::io::Write;
 
 use crate::Result;
+use crate::redact::redact;
 
 /// Initialize the logger with file and console output
 ///
@@ -77,9 +78,9 @@ pub fn init_logger(verbose: bool, quiet: bool) -> Result<()> {
                 .fg_color(Some(Color::Ansi(AnsiColor::BrightBlack)))
                 .bold();
 
-            write!(
+            writeln!(
                 buf,
-                "{}[{} {}{:5}{} {}]{} {}\n",
+                "{}[{} {}{:5}{} {}]{} {}",
                 bracket_style.render(),
                 buf.timestamp(),
                 level_style.render(),
@@ -87,7 +88,7 @@ pub fn init_logger(verbose: bool, quiet: bool) -> Result<()> {
                 bracket_style.render(),
                 record.module_path().unwrap_or("unknown"),
                 bracket_style.render_reset(),
-                record.args()
+                redact(&record.args().to_string())
             )
         })
         .write_style(env_logger::WriteStyle::Always);
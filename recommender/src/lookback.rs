@@ -0,0 +1,91 @@
+//! Lookback windows per namespace or workload
+//!
+//! Batch pipelines need a month of history to catch their monthly jobs, while
+//! stateless APIs are fine with a week. A Deployment's own annotation wins over
+//! the overrides file, which wins over `--lookback-hours`.
+
+use std::path::Path;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::kubernetes::DeploymentResources;
+use crate::{ConfigError, Result};
+
+/// Deployment annotation holding its lookback in hours, e.g. `"720"`
+pub const LOOKBACK_ANNOTATION: &str = "autorightsizing.io/lookback-hours";
+
+/// Lookback of the workloads matching a namespace and/or deployment name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LookbackOverride {
+    pub namespace: Option<String>,
+    pub deployment: Option<String>,
+    pub lookback_hours: f64,
+}
+
+impl LookbackOverride {
+    fn matches(&self, deployment: &DeploymentResources) -> bool {
+        self.namespace
+            .as_ref()
+            .is_none_or(|ns| *ns == deployment.namespace)
+            && self
+                .deployment
+                .as_ref()
+                .is_none_or(|name| *name == deployment.name)
+    }
+
+    /// Overrides naming a deployment beat namespace-wide ones
+    fn specificity(&self) -> u8 {
+        u8::from(self.deployment.is_some()) * 2 + u8::from(self.namespace.is_some())
+    }
+}
+
+/// Lookback overrides, the most specific match applies
+///
+/// Example overrides file:
+///
+/// ```yaml
+/// overrides:
+///   - namespace: batch
+///     lookback_hours: 720
+///   - namespace: shop
+///     deployment: checkout
+///     lookback_hours: 336
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LookbackOverrides {
+    #[serde(default)]
+    pub overrides: Vec<LookbackOverride>,
+}
+
+impl LookbackOverrides {
+    /// Load overrides from a YAML file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::FileError(format!(
+                "Failed to read lookback overrides {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let overrides: LookbackOverrides = serde_yaml::from_str(&content)?;
+
+        info!(
+            "Loaded {} lookback override(s) from {}",
+            overrides.overrides.len(),
+            path.display()
+        );
+        Ok(overrides)
+    }
+
+    /// Lookback of the most specific override matching `deployment`, the first
+    /// listed among equally specific ones
+    pub fn lookback_hours(&self, deployment: &DeploymentResources) -> Option<f64> {
+        self.overrides
+            .iter()
+            .filter(|o| o.matches(deployment))
+            .rev()
+            .max_by_key(|o| o.specificity())
+            .map(|o| o.lookback_hours)
+    }
+}
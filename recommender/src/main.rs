@@ -1,11 +1,37 @@
-use clap::Parser;
-use log::{debug, info, warn};
+use chrono::{DateTime, Utc};
+use clap::{CommandFactory, error::ErrorKind};
+use log::{debug, error, info, warn};
+use recommender::credentials;
 use recommender::{
-    Cli, KubernetesConfig, KubernetesLoader, ManifestUpdater, OutputFormat, PrometheusClient,
-    Recommender, RecommenderConfig, RecommenderOutput, ResourceRecommendation, Result,
-    UpdaterConfig, display_recommendations_table, init_logger,
+    AnalysisRequest, AppliedSet, ApplyMode, ApplyOutcome, ApplyStage, ApplyTarget, AwsRegion,
+    Backtest, BacktestArgs, CachingMetricsProvider, CanarySelector, Cli, CloudProvider,
+    ClusterTarget, Command, DaySplit, DeploymentResources, DiagnoseArgs, DiagnoseTarget,
+    EnvironmentOverlays, FilteredWorkloadSource, FixedClock, ForecastConfig, GitPrTarget,
+    HelmValues, HttpConfig, InstanceCatalog, KarpenterNodePool, KubeStateMetricsSource,
+    KubernetesConfig, KubernetesLoader, LeaderElectionConfig, LeaderElector, LocalDirTarget,
+    LookbackOverrides, MetricsProvider, MetricsServerSampler, MetricsSnapshot, NodeInventory,
+    Notification, OciPusher, OutlierFilter, OutputFormat, PolicySet, Progress, PrometheusAuth,
+    PrometheusClient, PushgatewayExporter, QueryTemplate, RateLimiter, RecommendationJournal,
+    RecommendationPublisher, RecommenderConfig, RecordingMetricsProvider, Renewal, ResourceBounds,
+    ResourcePrices, ResourceRecommendation, Result, RunHistory, RunRecord, S3Location, S3Uploader,
+    SeasonalityConfig, ServeArgs, SopsConfig, StaticWorkloadSource, TeamReport, Timezone,
+    TokenSource, TrendArgs, TrendGrouping, UpdaterConfig, VpaMode, WebhookConfig, WebhookNotifier,
+    WebhookServer, WorkloadDiscovery, WorkloadFilter, WorkloadSource, autoscaling_impact,
+    chargeback, chargeback_files, diagnose, display_recommendations_table, drift_report,
+    format_dollars, init_logger, node_pool_report, publish_configmap, record_merged_applies,
+    run_analysis, run_analysis_per_namespace, savings_trend, vpa_manifests, write_chargeback,
+    write_kustomize_patches, write_namespace_outputs, write_quota_manifests,
 };
-use std::io::{self, Write};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Exit status of `--fail-on-change` runs that found changes, 1 is left to errors
+const FAIL_ON_CHANGE_EXIT_CODE: i32 = 2;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -15,46 +41,328 @@ async fn main() -> Result<()> {
     // when run without this line :P
     let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
 
-    let cli = Cli::parse();
+    let cli = Cli::parse_with_config();
 
     init_logger(cli.verbose, cli.quiet)?;
 
     info!("Starting Kubernetes Resource Recommender");
-    debug!("AWS Managed Prometheus URL: {}", cli.amp_url);
-    debug!("AWS Region: {}", cli.region);
 
+    // Ctrl-C aborts outstanding queries and stops the apply before pushing
+    let cancel = CancellationToken::new();
+    tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                warn!("Received Ctrl-C, shutting down (press again to force)...");
+                cancel.cancel();
+            }
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+    let mut config_builder = RecommenderConfig::builder()
+        .lookback_hours(cli.lookback_hours)
+        .cpu_request_percentile(cli.cpu_request_percentile)
+        .cpu_limit_percentile(cli.cpu_limit_percentile)
+        .memory_request_percentile(cli.memory_request_percentile)
+        .memory_limit_percentile(cli.memory_limit_percentile)
+        .safety_margin(cli.safety_margin)
+        .rate_window(Duration::from_secs(cli.rate_window_seconds))
+        .fallback_rate_window(
+            (cli.fallback_rate_window_seconds > 0)
+                .then(|| Duration::from_secs(cli.fallback_rate_window_seconds)),
+        )
+        .outlier_filter(cli.outliers.map(|action| {
+            OutlierFilter::new(action)
+                .with_threshold(cli.outlier_threshold)
+                .with_max_duration(Duration::from_secs(cli.outlier_max_duration_seconds))
+        }))
+        .forecast(cli.forecast.map(|method| {
+            let horizon =
+                Duration::from_secs_f64(cli.forecast_weeks.max(0.0) * 7.0 * 24.0 * 3600.0);
+            ForecastConfig::new(method, horizon)
+                .with_growth_warning(cli.growth_warning_percent / 100.0)
+        }))
+        .day_split(cli.day_split)
+        .seasonality(cli.seasonal.then(|| {
+            SeasonalityConfig::default().with_min_strength(cli.seasonal_strength_percent / 100.0)
+        }))
+        .vpa_mode(cli.vpa)
+        .bounds(
+            ResourceBounds::default()
+                .with_cpu_request(cli.min_cpu_request, cli.max_cpu_request)
+                .with_cpu_limit(cli.min_cpu_limit, cli.max_cpu_limit)
+                .with_memory_request(cli.min_memory_request, cli.max_memory_request)
+                .with_memory_limit(cli.min_memory_limit, cli.max_memory_limit),
+        )
+        .memory_metric(cli.memory_metric)
+        .timezone(cli.timezone)
+        // Only the TUI's what-if panel sizes recommendations again
+        .retain_usage(cli.output == OutputFormat::Table && cli.command.is_none())
+        .max_concurrent_queries(cli.max_concurrent_queries);
+    if let Some(path) = &cli.lookback_overrides {
+        config_builder = config_builder.lookback_overrides(LookbackOverrides::from_file(path)?);
+    }
+    if let Some(seconds) = cli.query_step_seconds {
+        config_builder = config_builder.query_step(Duration::from_secs(seconds));
+    }
+    if let Some(query) = &cli.cpu_query {
+        config_builder = config_builder.cpu_query(query_template(query, cli.cpu_query_aggregated));
+    }
+    if let Some(query) = &cli.memory_query {
+        config_builder =
+            config_builder.memory_query(query_template(query, cli.memory_query_aggregated));
+    }
+    if cli.cpu_burst_window_seconds.is_some() || cli.cpu_limit_query.is_some() {
+        let template = cli
+            .cpu_limit_query
+            .as_deref()
+            .map_or_else(QueryTemplate::cpu_burst, QueryTemplate::new);
+        config_builder = config_builder.cpu_limit_query(Some(template));
+    }
+    if let Some(seconds) = cli.cpu_burst_window_seconds {
+        config_builder = config_builder.burst_window(Duration::from_secs(seconds));
+    }
+    let recommender_config = config_builder.build();
+    if let Err(e) = recommender_config.validate() {
+        Cli::command().error(ErrorKind::ValueValidation, e).exit();
+    }
+    if cli.apply && cli.day_split == Some(DaySplit::Both) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--day-split both gives two recommendations per container, use --day-split max with --apply",
+            )
+            .exit();
+    }
+
+    recommender::http::init_shared_client(&HttpConfig {
+        timeout: Duration::from_secs(cli.http_timeout_seconds),
+        ..HttpConfig::default()
+    })?;
+
+    match &cli.command {
+        Some(Command::Diagnose(args)) => {
+            return run_diagnose(&cli, args, recommender_config.lookback_hours).await;
+        }
+        Some(Command::Trend(args)) => return run_trend(&cli, args),
+        Some(Command::Serve(args)) => {
+            return run_serve(&cli, args, &recommender_config, &cancel).await;
+        }
+        Some(Command::Backtest(args)) => {
+            return run_backtest(&cli, args, &recommender_config, &cancel).await;
+        }
+        None => {}
+    }
+
+    match run_recommender(&cli, &recommender_config, None, true, &cancel).await {
+        Err(recommender::RecommenderError::ChangesRequired(changes)) => {
+            error!(
+                "{} containers are off by more than {}% of their requests",
+                changes, cli.fail_threshold_percent
+            );
+            std::process::exit(FAIL_ON_CHANGE_EXIT_CODE);
+        }
+        result => result,
+    }
+}
+
+/// Analyze the workloads, then report or apply the recommendations
+///
+/// `namespace` overrides `--namespace`. Unless `interactive`, nothing is displayed
+/// or prompted for: recommendations are applied automatically or only logged.
+async fn run_recommender(
+    cli: &Cli,
+    recommender_config: &RecommenderConfig,
+    namespace: Option<&str>,
+    interactive: bool,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let mut leader = None;
+    let result = analyze_and_apply(
+        cli,
+        recommender_config,
+        namespace,
+        interactive,
+        cancel,
+        &mut leader,
+    )
+    .await;
+    // However the run ended, a standby replica can take over right away
+    if let Some((elector, renewal)) = leader {
+        release_leadership(&elector, renewal).await;
+    }
+    result
+}
+
+/// Body of [`run_recommender`], setting `leader` once it holds the leader lease
+async fn analyze_and_apply(
+    cli: &Cli,
+    recommender_config: &RecommenderConfig,
+    namespace: Option<&str>,
+    interactive: bool,
+    cancel: &CancellationToken,
+    leader: &mut Option<(LeaderElector, Renewal)>,
+) -> Result<()> {
     // Create unified config with all settings
     let k8s_config = KubernetesConfig::new(
-        String::from(cli.amp_url.clone()),
-        cli.region.to_string(),
-        cli.context,
-        cli.namespace,
-    );
-    let recommender_config = RecommenderConfig::new(
-        cli.lookback_hours,
-        cli.cpu_request_percentile,
-        cli.cpu_limit_percentile,
-        cli.memory_request_percentile,
-        cli.memory_limit_percentile,
-        cli.safety_margin,
-    );
+        cli.amp_url.as_ref().map(Url::to_string).unwrap_or_default(),
+        cli.region
+            .as_ref()
+            .map(AwsRegion::to_string)
+            .unwrap_or_default(),
+        cli.context.clone(),
+        namespace
+            .map(str::to_string)
+            .or_else(|| cli.namespace.clone()),
+    )
+    .with_workload_filter(workload_filter(cli))
+    .with_jobs(cli.include_jobs);
+    let mut k8s_loader = None;
+    let mut recording = None;
+    let mut caching = None;
+    let mut aws_credentials = None;
+    let amp_limiter = cli.amp_max_rps.map(RateLimiter::per_second);
+    let (workloads, metrics): (Arc<dyn WorkloadSource>, Arc<dyn MetricsProvider>) =
+        if let Some(path) = &cli.from_metrics {
+            info!("Loading metrics snapshot from {}", path.display());
+            let snapshot = Arc::new(MetricsSnapshot::load(path)?);
+            if recommender_config.lookback_hours > snapshot.lookback_hours {
+                warn!(
+                    "Lookback of {} hours exceeds the {} hours captured in the snapshot",
+                    recommender_config.lookback_hours, snapshot.lookback_hours
+                );
+            }
+            (snapshot.clone(), snapshot)
+        } else {
+            let Some(amp_url) = &cli.amp_url else {
+                return Err(recommender::ConfigError::MissingRequired(
+                    "--amp-url is required unless --from-metrics is given".to_string(),
+                )
+                .into());
+            };
+            debug!("Prometheus URL: {}", amp_url);
+
+            let loader = match cli.discovery {
+                WorkloadDiscovery::Cluster => {
+                    info!("Connecting to Kubernetes cluster...");
+                    Some(Arc::new(KubernetesLoader::new(k8s_config.clone()).await?))
+                }
+                WorkloadDiscovery::KubeStateMetrics => None,
+            };
+
+            // Only the replica holding the lease analyzes and opens PRs
+            if cli.leader_election {
+                let loader = loader.as_ref().ok_or_else(|| {
+                    recommender::ConfigError::InvalidValue(
+                        "--leader-election needs cluster access, not --discovery kube-state-metrics"
+                            .to_string(),
+                    )
+                })?;
+                let elector = LeaderElector::new(
+                    loader.client(),
+                    LeaderElectionConfig::new(
+                        cli.lease_name.clone(),
+                        cli.lease_namespace.clone(),
+                        Duration::from_secs(cli.lease_duration_seconds),
+                    ),
+                );
 
-    // Initialize Kubernetes client
-    info!("Connecting to Kubernetes cluster...");
-    let k8s_loader = KubernetesLoader::new(k8s_config.clone()).await?;
+                if !elector.try_acquire().await? {
+                    info!("Another instance holds the leader lease, nothing to do");
+                    return Ok(());
+                }
+
+                let renewal = elector.spawn_renewal();
+                *leader = Some((elector, renewal));
+            }
+            k8s_loader = loader.clone();
+
+            debug!("Connecting to Prometheus...");
+
+            // Initialize Prometheus client
+            let mut prom_client = prometheus_client(cli, amp_url).await?;
+            if let Some(limiter) = &amp_limiter {
+                prom_client = prom_client.with_rate_limiter(limiter.clone());
+            }
 
-    // Get all deployments with their resource specifications
-    info!("Scanning deployments for resource requests and limits...");
-    let deployments = k8s_loader.get_deployment_resources().await?;
+            info!("Successfully connected to Prometheus");
+            aws_credentials = prom_client.credentials().cloned();
 
-    info!("Found {} deployments", deployments.len());
+            let mut prom_client: Arc<dyn MetricsProvider> = Arc::new(prom_client);
+            let source: Arc<dyn WorkloadSource> = match loader {
+                Some(loader) => loader,
+                None => {
+                    let mut source = KubeStateMetricsSource::new(
+                        prom_client.clone(),
+                        k8s_config.namespace.clone(),
+                    );
+                    if let Some(as_of) = cli.as_of {
+                        source = source.with_clock(Arc::new(FixedClock::at(as_of)));
+                    }
+                    Arc::new(source)
+                }
+            };
+            if let Some(path) = &cli.metrics_cache {
+                let cache = Arc::new(CachingMetricsProvider::new(prom_client, path));
+                caching = Some(cache.clone());
+                prom_client = cache;
+            }
+            if cli.save_metrics.is_some() {
+                // The snapshot needs the exact workloads that were analyzed
+                let deployments = source.list_workloads().await?;
+                let recorder = Arc::new(RecordingMetricsProvider::new(prom_client));
+                recording = Some((recorder.clone(), deployments.clone()));
+                (Arc::new(StaticWorkloadSource::new(deployments)), recorder)
+            } else {
+                (source, prom_client)
+            }
+        };
 
-    debug!("Connecting to AWS Managed Prometheus...");
+    // The cluster loader filters itself, with the selector evaluated server-side
+    let workloads: Arc<dyn WorkloadSource> =
+        if k8s_loader.is_none() && !k8s_config.workloads.is_empty() {
+            Arc::new(FilteredWorkloadSource::new(
+                workloads,
+                k8s_config.workloads.clone(),
+            ))
+        } else {
+            workloads
+        };
 
-    // Initialize Prometheus client
-    let prom_client = PrometheusClient::new(cli.amp_url.clone(), cli.region).await?;
+    // Built up front so it can narrow down the workloads to analyze
+    let mut apply_target = if cli.apply {
+        build_apply_target(cli, k8s_loader.as_deref(), cancel)?
+    } else {
+        None
+    };
+    let notifier = cli
+        .notify_webhook
+        .as_ref()
+        .map(|url| WebhookNotifier::new(url.clone(), cli.notify_format))
+        .transpose()?;
+    let workloads = match (&mut apply_target, cli.only_manifest_workloads) {
+        (Some(target), true) => restrict_to_manifests(workloads, target.as_mut()).await?,
+        (None, true) => {
+            warn!(
+                "--only-manifest-workloads needs --manifest-url or --manifest-dir, analyzing all workloads"
+            );
+            workloads
+        }
+        (_, false) => workloads,
+    };
 
-    info!("Successfully connected to Prometheus");
+    // Load governance policies from file and/or ConfigMap
+    let mut policies = PolicySet::default();
+    if let Some(path) = &cli.policy_file {
+        policies.extend(PolicySet::from_file(path)?);
+    }
+    if let Some(reference) = &cli.policy_configmap {
+        let client = cluster_client(k8s_loader.as_deref(), "--policy-configmap")?;
+        policies.extend(PolicySet::from_configmap(client, reference).await?);
+    }
+    let policies = Arc::new(policies);
 
     // Generate recommendations
     debug!(
@@ -62,73 +370,507 @@ async fn main() -> Result<()> {
         recommender_config.lookback_hours
     );
 
-    let recommender = Recommender::new(prom_client, recommender_config.clone());
-    let recommendations = recommender
-        .generate_recommendations(deployments.clone())
-        .await?;
+    // Listed again after the analysis to map pods and labels to deployments
+    let report_workloads = (cli.node_report
+        || cli.autoscaling_impact
+        || cli.chargeback_label.is_some()
+        || cli.history_file.is_some()
+        || cli.show_drift)
+        .then(|| workloads.clone());
+    let mut request = AnalysisRequest::new(workloads, metrics)
+        .config(recommender_config.clone())
+        .namespace(k8s_config.namespace.clone())
+        .policies(policies.clone())
+        .cancellation(cancel.clone())
+        .timezone(cli.timezone)
+        .prices(resource_prices(cli, aws_credentials.as_ref()).await);
+    if cli.namespace_quotas || cli.quota_yaml.is_some() {
+        request = request.namespace_quotas(cli.quota_headroom);
+    }
+    if cli.metrics_server_fallback {
+        let client = cluster_client(k8s_loader.as_deref(), "--metrics-server-fallback")?;
+        request = request.metrics_server_fallback(MetricsServerSampler::new(
+            client,
+            Duration::from_secs(cli.metrics_server_window_seconds),
+        ));
+    }
+    if let Some(as_of) = cli.as_of {
+        info!(
+            "Analyzing the lookback window ending at {}",
+            cli.timezone.convert(as_of).to_rfc3339()
+        );
+        request = request.clock(Arc::new(FixedClock::at(as_of)));
+    }
+    // Logs already report each deployment, so only draw a bar when they are hidden
+    if cli.quiet && io::stderr().is_terminal() {
+        request = request.on_progress(render_progress_bar);
+    }
+    let analysis = match &cli.split_namespaces {
+        Some(dir) => run_analysis_per_namespace(request, cli.namespace_concurrency)
+            .await
+            .and_then(|analysis| {
+                let index = write_namespace_outputs(
+                    &analysis.namespaces,
+                    &analysis.combined.metadata,
+                    dir,
+                )?;
+                info!(
+                    "Wrote recommendations for {} namespaces to {}",
+                    index.namespaces.len(),
+                    dir.display()
+                );
+                Ok(analysis.combined)
+            }),
+        None => run_analysis(request).await,
+    };
+    let mut output = match analysis {
+        Err(recommender::RecommenderError::Cancelled) => {
+            return Ok(());
+        }
+        result => result?,
+    };
+    if let Some(savings) = output.metadata.estimated_monthly_savings {
+        info!("Estimated savings: {}/month", format_dollars(savings));
+    }
 
-    info!("Generated {} recommendations", recommendations.len());
-
-    // Build unified output structure
-    let output = RecommenderOutput::new(
-        k8s_config.namespace.clone(),
-        recommender_config.lookback_hours,
-        deployments.len(),
-        recommender_config.cpu_request_percentile,
-        recommender_config.cpu_limit_percentile,
-        recommender_config.memory_request_percentile,
-        recommender_config.memory_limit_percentile,
-        recommender_config.safety_margin,
-        recommendations,
-    );
+    if let Some(limiter) = &amp_limiter {
+        info!("AMP rate limiter: {}", limiter.stats());
+    }
+
+    if let Some(cache) = &caching {
+        cache.persist()?;
+    }
+
+    if let (Some(path), Some((recorder, deployments))) = (&cli.save_metrics, recording) {
+        recorder
+            .snapshot(deployments, recommender_config.lookback_hours)
+            .save(path)?;
+        info!("Saved metrics snapshot to {}", path.display());
+    }
+
+    let deployments = match &report_workloads {
+        Some(workloads) => workloads.list_workloads().await?,
+        None => Vec::new(),
+    };
+
+    if cli.node_report || cli.autoscaling_impact {
+        let client = cluster_client(k8s_loader.as_deref(), "--node-report")?;
+        let inventory = NodeInventory::load(client.clone()).await?;
+        let report = node_pool_report(&inventory, &deployments, &output.recommendations);
+        for group in &report.node_groups {
+            info!(
+                "Node group {} ({}): {} nodes, {} needed now, {} after rightsizing",
+                group.node_group,
+                group.instance_type,
+                group.current_nodes,
+                group.nodes_needed_now,
+                group.nodes_needed_after
+            );
+        }
+        for savings in &report.instance_types {
+            info!(
+                "{}: {} of {} nodes could be removed",
+                savings.instance_type, savings.reducible_nodes, savings.current_nodes
+            );
+        }
+
+        if cli.autoscaling_impact {
+            let catalog = match &cli.instance_catalog {
+                Some(path) => InstanceCatalog::from_file(path)?,
+                None => InstanceCatalog::default(),
+            };
+            let node_pools = KarpenterNodePool::list(client).await?;
+            let impact = autoscaling_impact(&report, &node_pools, &catalog);
+            for group in &impact.node_groups {
+                for note in &group.notes {
+                    warn!("Node group {}: {}", group.node_group, note);
+                }
+            }
+            info!(
+                "Estimated node cost ${:.0}/month now, ${:.0}/month after rightsizing (saves ${:.0})",
+                impact.current_monthly_cost, impact.monthly_cost_after, impact.monthly_savings
+            );
+            output.autoscaling = Some(impact);
+        }
+        output.node_pools = Some(report);
+    }
+
+    let mut chargeback_reports = Vec::new();
+    if let Some(label) = &cli.chargeback_label {
+        chargeback_reports = chargeback(&output.recommendations, &deployments, label);
+        let teams = write_chargeback(
+            &chargeback_reports,
+            cli.chargeback_format,
+            &cli.chargeback_dir,
+        )?;
+        info!(
+            "Wrote chargeback reports for {} teams to {}",
+            teams,
+            cli.chargeback_dir.display()
+        );
+    }
+
+    if !cli.publish.is_empty() {
+        let client = cluster_client(k8s_loader.as_deref(), "--publish")?;
+        let mut publisher = RecommendationPublisher::new(client, cli.publish.clone());
+        if let Some(as_of) = cli.as_of {
+            publisher = publisher.with_clock(Arc::new(FixedClock::at(as_of)));
+        }
+        publisher.publish(&output.recommendations).await;
+    }
+
+    if let Some(path) = &cli.history_file {
+        let timestamp = DateTime::parse_from_rfc3339(&output.metadata.timestamp)
+            .map_err(|e| recommender::RecommenderError::Parse(e.to_string()))?;
+        let mut history = RunHistory::load(path);
+        history.record(RunRecord::new(
+            timestamp.with_timezone(&Utc),
+            &output.recommendations,
+            &deployments,
+        ));
+        history.save(path)?;
+        info!(
+            "Recorded run in {} ({} runs)",
+            path.display(),
+            history.runs.len()
+        );
+
+        // Pull requests of earlier runs show up as merged once their values run
+        if let Some(loader) = &k8s_loader
+            && !history.awaiting_merge().is_empty()
+        {
+            record_merged_applies(loader.client(), &history, &deployments).await;
+        }
+    }
+
+    for quota in &output.namespace_quotas {
+        info!(
+            "Namespace {} quota for {} pods: requests {} CPU / {} memory (now {} / {}), limits {} CPU / {} memory (now {} / {})",
+            quota.namespace,
+            quota.pods,
+            quota.recommended.requests_cpu,
+            quota.recommended.requests_memory,
+            quota.current.requests_cpu,
+            quota.current.requests_memory,
+            quota.recommended.limits_cpu,
+            quota.recommended.limits_memory,
+            quota.current.limits_cpu,
+            quota.current.limits_memory
+        );
+    }
+    if let Some(path) = &cli.quota_yaml {
+        write_quota_manifests(&output.namespace_quotas, path)?;
+        info!(
+            "Wrote {} ResourceQuotas to {}",
+            output.namespace_quotas.len(),
+            path.display()
+        );
+    }
+    if let Some(dir) = &cli.output_kustomize {
+        let patches = write_kustomize_patches(&output.recommendations, dir)?;
+        info!("Wrote {} Kustomize patches to {}", patches, dir.display());
+    }
+
+    // Always output JSON for logging purposes
+    let json = serde_json::to_string_pretty(&output).map_err(|e| {
+        recommender::RecommenderError::Config(recommender::ConfigError::InvalidValue(format!(
+            "Failed to serialize JSON: {}",
+            e
+        )))
+    })?;
+
+    let created = DateTime::parse_from_rfc3339(&output.metadata.timestamp)
+        .map_err(|e| recommender::RecommenderError::Parse(e.to_string()))?
+        .with_timezone(&Utc);
+    let journal = cli
+        .journal
+        .as_ref()
+        .map(|path| RecommendationJournal::new(path, cluster_name(cli)));
+    let journaled = match &journal {
+        Some(journal) if cli.show_drift => journal.last_applied()?,
+        _ => HashMap::new(),
+    };
+    if let Some(journal) = &journal {
+        let workloads = journal.append(created, &output.recommendations, false)?;
+        info!(
+            "Journaled {} workloads in {}",
+            workloads,
+            journal.path().display()
+        );
+    }
+
+    if let Some(location) = &cli.upload_s3 {
+        let result = upload_artifacts(
+            cli,
+            location,
+            aws_credentials,
+            created,
+            &json,
+            &chargeback_reports,
+        )
+        .await;
+        // Reporting is secondary, a failed upload doesn't stop the apply
+        if let Err(e) = result {
+            warn!("Failed to upload run artifacts to {}: {}", location, e);
+        }
+    }
+
+    if let Some(reference) = &cli.publish_configmap {
+        let client = cluster_client(k8s_loader.as_deref(), "--publish-configmap")?;
+        if let Err(e) = publish_configmap(client, reference, &output).await {
+            warn!(
+                "Failed to publish the output to ConfigMap {}: {}",
+                reference, e
+            );
+        }
+    }
+
+    if let Some(reference) = &cli.publish_oci {
+        let mut pusher = OciPusher::new(reference.clone());
+        if let (Some(username), Some(password)) = (&cli.oci_username, &cli.oci_password) {
+            pusher = pusher.with_credentials(username.clone(), password.clone());
+        }
+        if let Err(e) = pusher
+            .push(json.clone().into_bytes(), "recommendations.json", created)
+            .await
+        {
+            warn!("Failed to push the output to {}: {}", reference, e);
+        }
+    }
+
+    if let Some(url) = &cli.push_gateway {
+        let exporter = PushgatewayExporter::new(url.clone(), cli.push_job.clone())
+            .with_grouping("cluster", cluster_name(cli));
+        if let Err(e) = exporter.push(&output.recommendations, created).await {
+            warn!("{}", e);
+        }
+    }
+
+    for skipped in &output.skipped {
+        warn!("Skipped {}", skipped);
+    }
+
+    if cli.show_drift {
+        let report = drift_report(
+            &output.recommendations,
+            &journaled,
+            &deployments,
+            cli.drift_threshold,
+        );
+        match cli.output {
+            OutputFormat::Json => {
+                let json = serde_json::to_string_pretty(&report).map_err(|e| {
+                    recommender::RecommenderError::Other(format!(
+                        "Failed to serialize drift report: {}",
+                        e
+                    ))
+                })?;
+                println!("{}", json);
+            }
+            OutputFormat::Table
+            | OutputFormat::Csv
+            | OutputFormat::Markdown
+            | OutputFormat::Vpa => {
+                println!("{}", report)
+            }
+        }
+        return Ok(());
+    }
+
+    // With --quiet the logs only go to the log file, leaving stdout to the table
+    match cli.output {
+        OutputFormat::Csv => print!("{}", output.to_csv()),
+        OutputFormat::Markdown => print!("{}", output.to_markdown()),
+        OutputFormat::Vpa => print!(
+            "{}",
+            vpa_manifests(&output.recommendations, cli.vpa_update_mode)?
+        ),
+        OutputFormat::Table | OutputFormat::Json => {}
+    }
 
     // Display output based on format
     if !output.recommendations.is_empty() {
-        // Always output JSON for logging purposes
-        let json = serde_json::to_string_pretty(&output).map_err(|e| {
-            recommender::RecommenderError::Config(recommender::ConfigError::InvalidValue(format!(
-                "Failed to serialize JSON: {}",
-                e
-            )))
-        })?;
-
         info!("Recommendations JSON: {}", json);
 
+        // A CI gate, neither the TUI nor prompts come up
+        if cli.fail_on_change {
+            let threshold = f64::from(cli.fail_threshold_percent);
+            let mut changes = 0;
+            for rec in &output.recommendations {
+                let Some(change) = rec.request_change_percent() else {
+                    continue;
+                };
+                if change.abs() > threshold {
+                    warn!(
+                        "{}/{}/{} is {} by {:.0}%",
+                        rec.namespace,
+                        rec.deployment,
+                        rec.container,
+                        if change > 0.0 {
+                            "under-provisioned"
+                        } else {
+                            "over-provisioned"
+                        },
+                        change.abs()
+                    );
+                    changes += 1;
+                }
+            }
+            return if changes > 0 {
+                Err(recommender::RecommenderError::ChangesRequired(changes))
+            } else {
+                info!(
+                    "All requests are within {}% of the recommendations",
+                    cli.fail_threshold_percent
+                );
+                Ok(())
+            };
+        }
+
         // Phase 1: Automatic apply mode (only for non-table output)
-        if cli.apply && cli.manifest_url.is_some() && cli.output != OutputFormat::Table {
+        if (cli.output != OutputFormat::Table || !interactive)
+            && let Some(target) = apply_target
+        {
             info!("Automatic apply mode enabled");
-            apply_recommendations_automatic(
-                cli.manifest_url.unwrap(),
-                cli.git_branch,
-                cli.git_username,
-                cli.git_token,
-                &output.recommendations,
-            )
-            .await?;
+
+            if let Some((elector, _)) = leader
+                && !elector.is_leader()
+            {
+                return Err(recommender::RecommenderError::Other(
+                    "Lost leader lease before applying recommendations".to_string(),
+                ));
+            }
+
+            let mut allowed = output.recommendations;
+            allowed.retain(|rec| policies.allows_auto_apply(rec));
+            let batch = allowed
+                .iter()
+                .filter(|rec| rec.workload_kind.is_batch())
+                .count();
+            if batch > 0 {
+                warn!(
+                    "Not applying {} CronJob and Job recommendations, only Deployments are applied",
+                    batch
+                );
+                allowed.retain(|rec| !rec.workload_kind.is_batch());
+            }
+            if cli.vpa != VpaMode::UpdateVpa {
+                allowed.retain(|rec| !rec.vpa.as_ref().is_some_and(|vpa| vpa.manages()));
+            }
+
+            if allowed.is_empty() {
+                warn!("No recommendations passed the auto-apply policies");
+                return Ok(());
+            }
+
+            let stage = match cli.history_file.as_deref() {
+                Some(path) => stage_apply(cli, path, &mut allowed, &deployments)?,
+                None => ApplyStage::All,
+            };
+            if allowed.is_empty() {
+                warn!("No recommendations left to apply in the {} stage", stage);
+                return Ok(());
+            }
+
+            match apply_recommendations_automatic(target, &allowed).await {
+                Err(recommender::RecommenderError::Cancelled) => {
+                    warn!("Apply cancelled, nothing was pushed")
+                }
+                Ok(outcome) => {
+                    if let Some(journal) = &journal {
+                        let applied: Vec<ResourceRecommendation> = allowed
+                            .iter()
+                            .filter(|rec| {
+                                outcome
+                                    .updated
+                                    .contains_key(&format!("{}/{}", rec.namespace, rec.deployment))
+                            })
+                            .cloned()
+                            .collect();
+                        journal.append(created, &applied, true)?;
+                    }
+                    if let Some(path) = &cli.history_file {
+                        let timestamp = DateTime::parse_from_rfc3339(&output.metadata.timestamp)
+                            .map_err(|e| recommender::RecommenderError::Parse(e.to_string()))?;
+                        let mut applied =
+                            AppliedSet::new(timestamp.with_timezone(&Utc), stage, &outcome);
+                        if outcome.branch.is_some() {
+                            applied = applied.awaiting_merge(&allowed, outcome.pr_url.clone());
+                        }
+                        let workloads = applied.workloads.len();
+                        let mut history = RunHistory::load(path);
+                        history.record_applied(applied);
+                        history.save(path)?;
+                        info!(
+                            "Recorded {} workloads applied in the {} stage in {}",
+                            workloads,
+                            stage,
+                            path.display()
+                        );
+                    }
+                    if let Some(notifier) = &notifier {
+                        let notification =
+                            Notification::applied(&cluster_name(cli), &allowed, &outcome);
+                        if let Err(e) = notifier.send(&notification).await {
+                            warn!("Failed to notify the {}: {}", notifier.describe(), e);
+                        }
+                    }
+                }
+                Err(e) => return Err(e),
+            }
             return Ok(());
         }
 
+        // Nobody is around to look at a table or answer prompts
+        if !interactive {
+            return Ok(());
+        }
+
+        if cli.canary_extend
+            || !cli.canary_namespaces.is_empty()
+            || cli.canary_percent.is_some()
+            || cli.canary_annotated
+        {
+            warn!("Staged apply only covers automatic apply, recommendations are picked by hand");
+        }
+
         // Display based on output format
         match cli.output {
             OutputFormat::Table => {
+                // The TUI prompts for git settings itself, other targets are ready to use
+                let tui_target = match (cli.apply_mode, apply_target) {
+                    (ApplyMode::Git, _) => None,
+                    (_, Some(target)) => Some(target),
+                    (_, None) => build_apply_target(cli, k8s_loader.as_deref(), cancel)?,
+                };
+
                 display_recommendations_table(
                     output,
-                    cli.manifest_url,
-                    cli.git_branch,
-                    cli.git_username,
-                    cli.git_token,
+                    policies.clone(),
+                    cli.manifest_url.clone(),
+                    cli.git_branch.clone(),
+                    cli.git_username.clone(),
+                    cli.git_token.clone(),
+                    cli.git_provider,
+                    tui_target,
+                    cancel.clone(),
                 )?;
             }
-            OutputFormat::Json => {
-                info!("{}", json);
+            OutputFormat::Json | OutputFormat::Csv | OutputFormat::Markdown | OutputFormat::Vpa => {
+                if cli.output == OutputFormat::Json {
+                    info!("{}", json);
+                }
 
-                // Phase 3: Interactive CLI mode for JSON output
+                // Phase 3: Interactive CLI mode for non-table output
                 if cli.apply {
                     apply_recommendations_interactive_cli(
-                        cli.manifest_url,
-                        cli.git_branch,
-                        cli.git_token,
+                        cli.manifest_url.clone(),
+                        cli.git_branch.clone(),
+                        cli.git_token.clone(),
                         &output.recommendations,
+                        |config| configure_updater(cli, config),
+                        cli.timezone,
+                        cancel.clone(),
                     )
                     .await?;
                 }
@@ -136,40 +878,622 @@ async fn main() -> Result<()> {
         }
     } else {
         info!("No recommendations generated");
+        // Still report what was skipped and why
+        if cli.output == OutputFormat::Json && !output.skipped.is_empty() {
+            info!("{}", json);
+        }
     }
 
     Ok(())
 }
 
-/// Apply recommendations automatically (non-interactive mode)
-async fn apply_recommendations_automatic(
-    manifest_url: url::Url,
-    git_branch: String,
-    git_username: Option<String>,
-    git_token: Option<String>,
-    recommendations: &[ResourceRecommendation],
-) -> Result<()> {
-    info!("Creating updater configuration...");
+/// Prices to estimate the savings at, from the Pricing API with `--pricing-api`
+///
+/// A failed lookup only warns, the savings are then priced like without it.
+async fn resource_prices(
+    cli: &Cli,
+    credentials: Option<&aws_credential_types::Credentials>,
+) -> ResourcePrices {
+    if let (true, Some(region)) = (cli.pricing_api, &cli.region) {
+        let credentials = match credentials {
+            Some(credentials) => Ok(credentials.clone()),
+            None => S3Uploader::load_credentials().await,
+        };
+        let prices = match credentials {
+            Ok(credentials) => ResourcePrices::from_pricing_api(region, &credentials).await,
+            Err(e) => Err(e),
+        };
+        match prices {
+            Ok(prices) => {
+                info!(
+                    "Pricing savings at ${}/vCPU-hour and ${}/GiB-hour in {}",
+                    prices.cpu_hourly, prices.memory_gib_hourly, region
+                );
+                return prices;
+            }
+            Err(e) => warn!("Failed to look up prices, using the defaults: {}", e),
+        }
+    }
 
-    let updater_config = UpdaterConfig::new(manifest_url.clone(), git_token, git_username)?;
-    let mut updater = ManifestUpdater::new(updater_config)?;
+    let default = ResourcePrices::default();
+    ResourcePrices::new(
+        cli.cpu_hourly_price.unwrap_or(default.cpu_hourly),
+        cli.memory_hourly_price.unwrap_or(default.memory_gib_hourly),
+    )
+}
 
-    info!("Applying recommendations and creating PR...");
-    let (branch_name, _commit_sha, pr_url) = updater
-        .apply_and_create_pr(&git_branch, recommendations)
+/// Upload the JSON output, chargeback reports and metrics snapshot of a run
+///
+/// Reuses the credentials of the AMP queries, only loading them when replaying
+/// a snapshot or querying Prometheus without SigV4.
+async fn upload_artifacts(
+    cli: &Cli,
+    location: &S3Location,
+    credentials: Option<aws_credential_types::Credentials>,
+    timestamp: DateTime<Utc>,
+    json: &str,
+    chargeback_reports: &[TeamReport],
+) -> Result<()> {
+    let Some(region) = cli.upload_s3_region.as_ref().or(cli.region.as_ref()) else {
+        return Err(recommender::ConfigError::MissingRequired(
+            "--upload-s3 needs --upload-s3-region or --region".to_string(),
+        )
+        .into());
+    };
+    let credentials = match credentials {
+        Some(credentials) => credentials,
+        None => S3Uploader::load_credentials().await?,
+    };
+    // Object keys group runs by cluster for the reporting jobs
+    let uploader = S3Uploader::new(
+        location.clone(),
+        region.clone(),
+        credentials,
+        &cluster_name(cli),
+        timestamp,
+    );
+    info!("Uploading run artifacts to {}", location);
+    uploader
+        .upload(
+            "recommendations.json",
+            json.as_bytes().to_vec(),
+            "application/json",
+        )
         .await?;
+    if !chargeback_reports.is_empty() {
+        for (name, content) in chargeback_files(chargeback_reports, cli.chargeback_format) {
+            uploader
+                .upload(
+                    &format!("chargeback/{}", name),
+                    content.into_bytes(),
+                    cli.chargeback_format.content_type(),
+                )
+                .await?;
+        }
+    }
+    if let Some(path) = &cli.save_metrics {
+        uploader
+            .upload("metrics.json", std::fs::read(path)?, "application/json")
+            .await?;
+    }
+    Ok(())
+}
 
-    info!("Successfully created branch: {}", branch_name);
-    if let Some(url) = pr_url {
-        info!("Pull Request created: {}", url);
+/// `--cluster-name`, else the kubeconfig context or the AMP workspace ID
+fn cluster_name(cli: &Cli) -> String {
+    cli.cluster_name
+        .clone()
+        .or_else(|| cli.context.clone())
+        .or_else(|| {
+            let amp_url = cli.amp_url.as_ref()?;
+            let segments = amp_url.path_segments()?;
+            segments
+                .skip_while(|segment| *segment != "workspaces")
+                .nth(1)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Client for `--amp-url`, authenticating the way `--prometheus-auth` asks
+async fn prometheus_client(cli: &Cli, amp_url: &Url) -> Result<PrometheusClient> {
+    let client = PrometheusClient::unauthenticated(amp_url.clone());
+    let mut client = match cli.prometheus_auth {
+        PrometheusAuth::SigV4 => {
+            let Some(region) = &cli.region else {
+                return Err(recommender::ConfigError::MissingRequired(
+                    "--prometheus-auth sigv4 needs --region".to_string(),
+                )
+                .into());
+            };
+            PrometheusClient::new(amp_url.clone(), region.clone()).await
+        }
+        PrometheusAuth::None => Ok(client),
+        PrometheusAuth::Gcp => {
+            TokenSource::from_env(CloudProvider::Gcp).map(|source| client.with_token_source(source))
+        }
+        PrometheusAuth::Azure => TokenSource::from_env(CloudProvider::Azure)
+            .map(|source| client.with_token_source(source)),
+        PrometheusAuth::Basic => match (&cli.prometheus_username, &cli.prometheus_password) {
+            (Some(username), Some(password)) => {
+                Ok(client.with_basic_auth(username.clone(), password.clone()))
+            }
+            _ => Err(recommender::ConfigError::MissingRequired(
+                "--prometheus-auth basic needs --prometheus-username and --prometheus-password"
+                    .to_string(),
+            )
+            .into()),
+        },
+        PrometheusAuth::Bearer => match &cli.prometheus_token {
+            Some(token) => Ok(client.with_bearer_token(token.clone())),
+            None => Err(recommender::ConfigError::MissingRequired(
+                "--prometheus-auth bearer needs --prometheus-token".to_string(),
+            )
+            .into()),
+        },
+    }?
+    .with_max_retries(cli.query_retries);
+    if let Some(seconds) = cli.query_timeout {
+        client = client.with_query_timeout(Duration::from_secs(seconds));
+    }
+    Ok(client)
+}
+
+/// Run `recommender diagnose` and print the guided explanation
+async fn run_diagnose(cli: &Cli, args: &DiagnoseArgs, lookback_hours: f64) -> Result<()> {
+    let Some(amp_url) = &cli.amp_url else {
+        return Err(recommender::ConfigError::MissingRequired(
+            "diagnose queries Prometheus, --amp-url is required".to_string(),
+        )
+        .into());
+    };
+
+    let mut prom_client = prometheus_client(cli, amp_url).await?;
+    if let Some(rps) = cli.amp_max_rps {
+        prom_client = prom_client.with_rate_limiter(RateLimiter::per_second(rps));
+    }
+
+    // The cluster is optional, it only adds the expected container names
+    let mut target = DiagnoseTarget::new(&args.namespace, &args.deployment);
+    let k8s_config = KubernetesConfig::new(
+        amp_url.to_string(),
+        cli.region
+            .as_ref()
+            .map(AwsRegion::to_string)
+            .unwrap_or_default(),
+        cli.context.clone(),
+        Some(args.namespace.clone()),
+    );
+    match KubernetesLoader::new(k8s_config).await {
+        Ok(loader) => match loader.list_workloads().await {
+            Ok(workloads) => match workloads.iter().find(|w| w.name == args.deployment) {
+                Some(workload) => target = target.with_workload(workload),
+                None => warn!(
+                    "Deployment {}/{} not found in the cluster",
+                    args.namespace, args.deployment
+                ),
+            },
+            Err(e) => warn!(
+                "Could not list deployments, skipping the spec comparison: {}",
+                e
+            ),
+        },
+        Err(e) => warn!("Cluster not reachable, skipping the spec comparison: {}", e),
+    }
+
+    let end: std::time::SystemTime = cli
+        .as_of
+        .map_or_else(std::time::SystemTime::now, Into::into);
+    let start = end - Duration::from_secs_f64(lookback_hours * 3600.0);
+    let diagnosis = diagnose(&prom_client, target, start, end, lookback_hours).await?;
+
+    println!("{}", diagnosis);
+    Ok(())
+}
+
+fn run_trend(cli: &Cli, args: &TrendArgs) -> Result<()> {
+    let Some(path) = &cli.history_file else {
+        return Err(recommender::ConfigError::MissingRequired(
+            "trend reads the recorded runs, --history-file is required".to_string(),
+        )
+        .into());
+    };
+
+    let grouping = match &args.by_label {
+        Some(label) => TrendGrouping::Label(label.clone()),
+        None => TrendGrouping::Namespace,
+    };
+    let trend = savings_trend(&RunHistory::load(path), &grouping, args.months);
+    match cli.output {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&trend).map_err(|e| {
+                recommender::RecommenderError::Other(format!("Failed to serialize trend: {}", e))
+            })?;
+            println!("{}", json);
+        }
+        OutputFormat::Table | OutputFormat::Csv | OutputFormat::Markdown | OutputFormat::Vpa => {
+            println!("{}", trend)
+        }
+    }
+    Ok(())
+}
+
+/// Re-run the analysis for namespaces reported by deployment webhooks
+async fn run_serve(
+    cli: &Cli,
+    args: &ServeArgs,
+    recommender_config: &RecommenderConfig,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let mut config =
+        WebhookConfig::new(args.listen).with_soak(Duration::from_secs(args.soak_seconds));
+    if let Some(secret) = &args.webhook_secret {
+        recommender::redact::register_secret(secret);
+        config = config.with_secret(secret);
+    }
+    WebhookServer::new(config)
+        .run(cancel.clone(), |namespace| async move {
+            run_recommender(cli, recommender_config, Some(&namespace), false, cancel).await
+        })
+        .await
+}
+
+/// Run `recommender backtest` over a metrics snapshot or AMP and print the report
+async fn run_backtest(
+    cli: &Cli,
+    args: &BacktestArgs,
+    recommender_config: &RecommenderConfig,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    if !(args.holdout_hours.is_finite() && args.holdout_hours > 0.0) {
+        return Err(recommender::ConfigError::InvalidValue(format!(
+            "--holdout-hours must be greater than 0, got {}",
+            args.holdout_hours
+        ))
+        .into());
+    }
+
+    let snapshot = match &cli.from_metrics {
+        Some(path) => {
+            info!("Loading metrics snapshot from {}", path.display());
+            Some(Arc::new(MetricsSnapshot::load(path)?))
+        }
+        None => None,
+    };
+    let (deployments, metrics, end): (_, Arc<dyn MetricsProvider>, _) =
+        if let Some(snapshot) = snapshot {
+            if recommender_config.lookback_hours + args.holdout_hours > snapshot.lookback_hours {
+                warn!(
+                    "Lookback and holdout of {} hours exceed the {} hours captured in the snapshot",
+                    recommender_config.lookback_hours + args.holdout_hours,
+                    snapshot.lookback_hours
+                );
+            }
+            let captured_at =
+                DateTime::from_timestamp(snapshot.captured_at as i64, 0).unwrap_or_else(Utc::now);
+            (
+                snapshot.workloads.clone(),
+                snapshot,
+                cli.as_of.unwrap_or(captured_at),
+            )
+        } else {
+            let Some(amp_url) = &cli.amp_url else {
+                return Err(recommender::ConfigError::MissingRequired(
+                    "backtest needs --from-metrics or --amp-url".to_string(),
+                )
+                .into());
+            };
+            let end = cli.as_of.unwrap_or_else(Utc::now);
+            let mut prom_client = prometheus_client(cli, amp_url).await?;
+            if let Some(rps) = cli.amp_max_rps {
+                prom_client = prom_client.with_rate_limiter(RateLimiter::per_second(rps));
+            }
+            let prom_client: Arc<dyn MetricsProvider> = Arc::new(prom_client);
+            let deployments = match cli.discovery {
+                WorkloadDiscovery::Cluster => {
+                    let k8s_config = KubernetesConfig::new(
+                        amp_url.to_string(),
+                        cli.region
+                            .as_ref()
+                            .map(AwsRegion::to_string)
+                            .unwrap_or_default(),
+                        cli.context.clone(),
+                        cli.namespace.clone(),
+                    );
+                    KubernetesLoader::new(k8s_config)
+                        .await?
+                        .list_workloads()
+                        .await?
+                }
+                WorkloadDiscovery::KubeStateMetrics => {
+                    KubeStateMetricsSource::new(prom_client.clone(), cli.namespace.clone())
+                        .with_clock(Arc::new(FixedClock::at(end)))
+                        .list_workloads()
+                        .await?
+                }
+            };
+            (deployments, prom_client, end)
+        };
+
+    let backtest = Backtest::new(
+        metrics,
+        recommender_config.clone(),
+        Duration::from_secs_f64(args.holdout_hours * 3600.0),
+    )
+    .with_candidates(args.candidates.iter().cloned())
+    .with_namespace(cli.namespace.clone())
+    .with_cancellation(cancel.clone());
+    let report = match backtest.run(deployments, end).await {
+        Err(recommender::RecommenderError::Cancelled) => return Ok(()),
+        result => result?,
+    };
+
+    match cli.output {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&report).map_err(|e| {
+                recommender::RecommenderError::Other(format!("Failed to serialize backtest: {}", e))
+            })?;
+            println!("{}", json);
+        }
+        OutputFormat::Table | OutputFormat::Csv | OutputFormat::Markdown | OutputFormat::Vpa => {
+            println!("{}", report)
+        }
+    }
+    Ok(())
+}
+
+/// Stop renewing and give up the leader lease so a standby replica can take over right away
+async fn release_leadership(elector: &LeaderElector, renewal: Renewal) {
+    if let Err(e) = elector.release(renewal).await {
+        warn!("Failed to release leader lease: {}", e);
+    }
+}
+
+/// Draw a single-line progress bar on stderr
+fn render_progress_bar(progress: &Progress) {
+    const WIDTH: usize = 30;
+
+    let filled = (progress.completed * WIDTH)
+        .checked_div(progress.total)
+        .unwrap_or(WIDTH);
+    eprint!(
+        "\r\x1b[2K[{}{}] {}/{} {}",
+        "#".repeat(filled),
+        "-".repeat(WIDTH - filled),
+        progress.completed,
+        progress.total,
+        progress.workload
+    );
+
+    if progress.completed >= progress.total {
+        eprintln!();
+    }
+    let _ = io::stderr().flush();
+}
+
+/// Build the configured apply target
+///
+/// Returns None in git mode when no manifest URL was given, so the caller can prompt for it.
+fn build_apply_target(
+    cli: &Cli,
+    k8s_loader: Option<&KubernetesLoader>,
+    cancel: &CancellationToken,
+) -> Result<Option<Box<dyn ApplyTarget>>> {
+    match cli.apply_mode {
+        ApplyMode::Git => {
+            let Some(url) = &cli.manifest_url else {
+                return Ok(None);
+            };
+            let config = configure_updater(
+                cli,
+                UpdaterConfig::new(url.clone(), cli.git_token.clone(), cli.git_username.clone())?,
+            )?;
+            let target = GitPrTarget::new(config, cli.git_branch.clone())?
+                .with_cancellation(cancel.clone())
+                .with_timezone(cli.timezone);
+            Ok(Some(Box::new(target)))
+        }
+        ApplyMode::Local => {
+            let dir = cli.manifest_dir.clone().ok_or_else(|| {
+                recommender::ConfigError::MissingRequired(
+                    "--manifest-dir is required with --apply-mode local".to_string(),
+                )
+            })?;
+            let mut target = LocalDirTarget::new(dir)?;
+            if let Some(sops) = sops_config(cli) {
+                target = target.with_sops(sops);
+            }
+            if let Some(path) = &cli.overlays {
+                target = target.with_overlays(EnvironmentOverlays::from_file(path)?);
+            }
+            if let Some(path) = &cli.helm_values {
+                target = target.with_helm_values(HelmValues::from_file(path)?);
+            }
+            if cli.apply_hpa_target {
+                target = target.with_hpa_targets();
+            }
+            Ok(Some(Box::new(target)))
+        }
+        ApplyMode::Cluster => {
+            let client = cluster_client(k8s_loader, "--apply-mode cluster")?;
+            let mut target = ClusterTarget::new(client).with_field_manager(&cli.field_manager);
+            if cli.apply_hpa_target {
+                target = target.with_hpa_targets();
+            }
+            Ok(Some(Box::new(target)))
+        }
+    }
+}
+
+/// SOPS settings from the command line, None unless `--sops` is given
+fn sops_config(cli: &Cli) -> Option<SopsConfig> {
+    if !cli.sops {
+        return None;
+    }
+    let config = SopsConfig::default().with_binary(&cli.sops_binary);
+    Some(match &cli.sops_age_key_file {
+        Some(path) => config.with_age_key_file(path),
+        None => config,
+    })
+}
+
+/// Drop workloads the apply target has no manifest for
+async fn restrict_to_manifests(
+    workloads: Arc<dyn WorkloadSource>,
+    target: &mut dyn ApplyTarget,
+) -> Result<Arc<dyn WorkloadSource>> {
+    info!("Scanning {} for manifests...", target.describe());
+    let Some(manifests) = target.known_workloads().await? else {
+        return Ok(workloads);
+    };
+
+    let all = workloads.list_workloads().await?;
+    let total = all.len();
+    let kept: Vec<_> = all
+        .into_iter()
+        .filter(|d| manifests.iter().any(|m| m.matches(&d.namespace, &d.name)))
+        .collect();
+    info!(
+        "Analyzing {} of {} workloads that have a manifest",
+        kept.len(),
+        total
+    );
+
+    Ok(Arc::new(StaticWorkloadSource::new(kept)))
+}
+
+/// Workloads `--selector`, `--include` and `--exclude` keep
+fn workload_filter(cli: &Cli) -> WorkloadFilter {
+    let mut filter = WorkloadFilter::new()
+        .with_include(cli.include.clone())
+        .with_exclude(cli.exclude.clone());
+    if let Some(selector) = &cli.selector {
+        filter = filter.with_selector(selector.clone());
+    }
+    filter
+}
+
+/// Usage query from a `--cpu-query`/`--memory-query` flag
+fn query_template(query: &str, aggregated: bool) -> QueryTemplate {
+    if aggregated {
+        QueryTemplate::aggregated(query)
     } else {
+        QueryTemplate::new(query)
+    }
+}
+
+/// Apply the git flags other than URL and credentials to `config`
+fn configure_updater(cli: &Cli, config: UpdaterConfig) -> Result<UpdaterConfig> {
+    let mut config = config.with_provider_override(cli.git_provider);
+    // Throttle provider API calls when `--git-api-max-rps` is set
+    if let Some(rps) = cli.git_api_max_rps {
+        config = config.with_rate_limiter(RateLimiter::per_second(rps));
+    }
+    if let Some(sops) = sops_config(cli) {
+        config = config.with_sops(sops);
+    }
+    if cli.auto_merge {
+        config = config.with_auto_merge(cli.merge_method);
+    }
+    if cli.signoff {
+        config = config.with_signoff();
+    }
+    for (key, value) in &cli.commit_trailers {
+        config = config.with_trailer(key, value);
+    }
+    if let Some(path) = &cli.overlays {
+        config = config.with_overlays(EnvironmentOverlays::from_file(path)?);
+    }
+    if let Some(path) = &cli.helm_values {
+        config = config.with_helm_values(HelmValues::from_file(path)?);
+    }
+    if cli.apply_hpa_target {
+        config = config.with_hpa_targets();
+    }
+    Ok(config)
+}
+
+/// Kubernetes client for features that need the live cluster
+///
+/// Fails when replaying a metrics snapshot, as no cluster connection exists then.
+fn cluster_client(k8s_loader: Option<&KubernetesLoader>, feature: &str) -> Result<kube::Client> {
+    k8s_loader.map(KubernetesLoader::client).ok_or_else(|| {
+        recommender::ConfigError::InvalidValue(format!(
+            "{} needs cluster access and cannot be used with --from-metrics or --discovery kube-state-metrics",
+            feature
+        ))
+        .into()
+    })
+}
+
+/// Narrow `allowed` down to the stage of a staged apply
+///
+/// Canary runs keep the workloads the `--canary-*` flags pick, `--canary-extend`
+/// keeps the ones the last canary run in the history left out.
+fn stage_apply(
+    cli: &Cli,
+    history_file: &Path,
+    allowed: &mut Vec<ResourceRecommendation>,
+    deployments: &[DeploymentResources],
+) -> Result<ApplyStage> {
+    if cli.canary_extend {
+        let history = RunHistory::load(history_file);
+        let Some(canary) = history.pending_canary() else {
+            return Err(recommender::RecommenderError::Other(format!(
+                "No canary apply to extend in {}",
+                history_file.display()
+            )));
+        };
+        info!(
+            "Extending the canary apply of {} to the remaining workloads",
+            canary.timestamp.to_rfc3339()
+        );
+        allowed.retain(|rec| !canary.contains(&rec.namespace, &rec.deployment));
+        return Ok(ApplyStage::Rollout);
+    }
+
+    let mut selector = CanarySelector::new()
+        .with_namespaces(cli.canary_namespaces.clone())
+        .with_annotated(cli.canary_annotated);
+    if let Some(percent) = cli.canary_percent {
+        selector = selector.with_percent(percent);
+    }
+    if selector.is_empty() {
+        return Ok(ApplyStage::All);
+    }
+
+    allowed.retain(|rec| {
+        let deployment = deployments
+            .iter()
+            .find(|d| d.namespace == rec.namespace && d.name == rec.deployment);
+        selector.selects(&rec.namespace, &rec.deployment, deployment)
+    });
+    info!(
+        "Canary apply to {} recommendations, run with --canary-extend to apply the rest",
+        allowed.len()
+    );
+    Ok(ApplyStage::Canary)
+}
+
+/// Apply recommendations automatically (non-interactive mode)
+async fn apply_recommendations_automatic(
+    mut target: Box<dyn ApplyTarget>,
+    recommendations: &[ResourceRecommendation],
+) -> Result<ApplyOutcome> {
+    info!("Applying recommendations to {}...", target.describe());
+    let outcome = target.apply(recommendations).await?;
+
+    info!("{}", outcome.summary());
+    if let Some(url) = &outcome.pr_url {
+        info!("Pull Request created: {}", url);
+    } else if let Some(branch) = &outcome.branch {
         warn!(
             "Changes committed to branch '{}' but PR creation was not available",
-            branch_name
+            branch
         );
     }
 
-    Ok(())
+    Ok(outcome)
 }
 
 /// Apply recommendations with interactive CLI prompts (for JSON mode)
@@ -178,6 +1502,9 @@ async fn apply_recommendations_interactive_cli(
     git_branch: String,
     git_token: Option<String>,
     recommendations: &[ResourceRecommendation],
+    configure: impl FnOnce(UpdaterConfig) -> Result<UpdaterConfig>,
+    timezone: Timezone,
+    cancel: CancellationToken,
 ) -> Result<()> {
     // Prompt 1: Confirm apply
     print!(
@@ -216,11 +1543,17 @@ async fn apply_recommendations_interactive_cli(
         })?
     };
 
-    // Prompt 3: Get token if not provided
+    // Prompt 3: Get token if not provided, offering the one saved in the keyring
+    let mut token_to_store = None;
     let token = if let Some(token) = git_token {
         Some(token)
     } else {
-        print!("Enter Git token (optional, press Enter for public repo): ");
+        let saved = credentials::stored_token(&url);
+        if saved.is_some() {
+            print!("Enter Git token (press Enter to use the one saved in the keyring): ");
+        } else {
+            print!("Enter Git token (optional, press Enter for public repo): ");
+        }
         io::stdout().flush().unwrap();
 
         let mut token_input = String::new();
@@ -230,8 +1563,9 @@ async fn apply_recommendations_interactive_cli(
 
         let trimmed = token_input.trim();
         if trimmed.is_empty() {
-            None
+            saved
         } else {
+            token_to_store = Some(trimmed.to_string());
             Some(trimmed.to_string())
         }
     };
@@ -253,18 +1587,26 @@ async fn apply_recommendations_interactive_cli(
 
     // Execute apply
     info!("Creating updater configuration...");
-    let updater_config = UpdaterConfig::new(url.clone(), token, None)?;
-    let mut updater = ManifestUpdater::new(updater_config)?;
+    let updater_config = configure(UpdaterConfig::new(url.clone(), token, None)?)?;
+    let mut target = GitPrTarget::new(updater_config, branch)?
+        .with_cancellation(cancel)
+        .with_timezone(timezone);
 
-    let (branch_name, _commit_sha, pr_url) = updater
-        .apply_and_create_pr(&branch, recommendations)
-        .await?;
+    let outcome = target.apply(recommendations).await?;
+
+    // Only remember a typed token once it has worked
+    if let Some(token) = token_to_store {
+        match credentials::store_token(&url, &token) {
+            Ok(()) => info!("Saved the git token in the keyring"),
+            Err(e) => warn!("{}", e),
+        }
+    }
 
     // Output result as JSON
     let result = serde_json::json!({
         "status": "success",
-        "branch": branch_name,
-        "pr_url": pr_url,
+        "branch": outcome.branch,
+        "pr_url": outcome.pr_url,
     });
 
     info!("\n{}", serde_json::to_string_pretty(&result).unwrap());
@@ -0,0 +1,797 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::error::{RecommenderError, Result};
+use crate::helm::HelmValues;
+use crate::hpa::HpaTarget;
+use crate::overlays::{
+    EnvironmentChanges, EnvironmentOverlays, merge_updates, scale_recommendation,
+};
+use crate::recommender::ResourceRecommendation;
+use crate::sops::{self, SopsConfig};
+use crate::terraform::TerraformEditor;
+use crate::vpa::VpaTarget;
+
+/// A Deployment declared in a manifest
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ManifestWorkload {
+    /// None when the manifest leaves the namespace to kubectl
+    pub namespace: Option<String>,
+    pub name: String,
+}
+
+impl ManifestWorkload {
+    /// Whether recommendations for `namespace/name` would update this manifest
+    pub fn matches(&self, namespace: &str, name: &str) -> bool {
+        self.name == name && self.namespace.as_deref().is_none_or(|ns| ns == namespace)
+    }
+}
+
+/// Edits Deployment manifests found under a directory tree
+///
+/// Deployments declared in Terraform files are handled by [`TerraformEditor`],
+/// those of Helm charts through [`ManifestEditor::with_helm_values`].
+/// SOPS-encrypted files are skipped unless [`ManifestEditor::with_sops`] is set.
+pub struct ManifestEditor {
+    root: PathBuf,
+    sops: Option<SopsConfig>,
+    overlays: Option<EnvironmentOverlays>,
+    helm: Option<HelmValues>,
+    hpa_targets: bool,
+}
+
+impl ManifestEditor {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            sops: None,
+            overlays: None,
+            helm: None,
+            hpa_targets: false,
+        }
+    }
+
+    /// Decrypt SOPS-encrypted manifests with `sops` and re-encrypt the edited values
+    pub fn with_sops(mut self, sops: SopsConfig) -> Self {
+        self.sops = Some(sops);
+        self
+    }
+
+    /// Only write to the overlays of `overlays`, scaled per environment
+    pub fn with_overlays(mut self, overlays: EnvironmentOverlays) -> Self {
+        self.overlays = Some(overlays);
+        self
+    }
+
+    /// Write the resources of Deployments mapped in `helm` to their values files
+    pub fn with_helm_values(mut self, helm: HelmValues) -> Self {
+        self.helm = Some(helm);
+        self
+    }
+
+    /// Also set the suggested CPU utilization target on HorizontalPodAutoscalers
+    pub fn with_hpa_targets(mut self) -> Self {
+        self.hpa_targets = true;
+        self
+    }
+
+    /// Root directory scanned for manifests
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Find all deployment YAML files below the root
+    pub fn find_deployment_files(&self) -> Result<Vec<PathBuf>> {
+        let mut deployment_files = Vec::new();
+
+        find_yaml_files_recursive(&self.root, &mut deployment_files)?;
+
+        info!("Found {} YAML files to scan", deployment_files.len());
+        Ok(deployment_files)
+    }
+
+    /// List the Deployments declared below the root
+    pub fn list_deployments(&self) -> Result<Vec<ManifestWorkload>> {
+        let manifests = parse_manifests(self.find_deployment_files()?, self.sops.as_ref())?;
+
+        let mut workloads: Vec<ManifestWorkload> = manifests
+            .iter()
+            .flat_map(|manifest| &manifest.docs)
+            .filter(|doc| doc.get("kind").and_then(|v| v.as_str()) == Some("Deployment"))
+            .filter_map(|doc| {
+                let metadata = doc.get("metadata")?;
+                Some(ManifestWorkload {
+                    namespace: metadata
+                        .get("namespace")
+                        .and_then(|n| n.as_str())
+                        .map(str::to_string),
+                    name: metadata.get("name")?.as_str()?.to_string(),
+                })
+            })
+            .collect();
+        workloads.extend(TerraformEditor::new(&self.root).list_deployments()?);
+        if let Some(helm) = &self.helm {
+            workloads.extend(helm.workloads());
+        }
+        Ok(workloads)
+    }
+
+    /// Apply all recommendations
+    ///
+    /// Every file is parsed once into an index of Deployments, then all
+    /// recommendations are applied against it and only changed files are written.
+    /// Returns the number of updated containers keyed by `namespace/deployment`
+    pub fn apply_recommendations(
+        &self,
+        recommendations: &[ResourceRecommendation],
+    ) -> Result<HashMap<String, usize>> {
+        if self.overlays.is_some() {
+            return Ok(merge_updates(&self.apply_to_overlays(recommendations)?));
+        }
+
+        let deployment_files = self.find_deployment_files()?;
+        let mut manifests = parse_manifests(deployment_files, self.sops.as_ref())?;
+        let index = index_documents(&manifests, deployment_name);
+        let vpa_index = index_documents(&manifests, vpa_target_name);
+        let mut updates = self.apply_to_helm_values(recommendations)?;
+
+        for recommendation in recommendations {
+            if self.in_helm_values(recommendation) {
+                continue;
+            }
+
+            // Never write a limit below its request, whatever the caller passed in
+            let mut recommendation = recommendation.clone();
+            recommendation.enforce_limits_above_requests();
+            let recommendation = &recommendation;
+            let mut updated = 0;
+
+            // A VPA managing the requests is bounded instead of fighting it
+            let (index, update): (_, fn(&mut Value, &ResourceRecommendation) -> bool) =
+                if managed_by_vpa(recommendation) {
+                    (&vpa_index, update_vpa_bounds)
+                } else {
+                    (&index, update_container_resources)
+                };
+
+            for &(file, doc) in index
+                .get(recommendation.deployment.as_str())
+                .into_iter()
+                .flatten()
+            {
+                let manifest = &mut manifests[file];
+                if !namespace_matches(&manifest.docs[doc], recommendation) {
+                    continue;
+                }
+
+                debug!("Found matching manifest in: {}", manifest.path.display());
+                if update(&mut manifest.docs[doc], recommendation) {
+                    manifest.modified = true;
+                    updated += 1;
+                }
+            }
+
+            if updated > 0 {
+                let key = format!("{}/{}", recommendation.namespace, recommendation.deployment);
+                *updates.entry(key).or_insert(0) += updated;
+            }
+        }
+
+        if self.hpa_targets {
+            let hpa_index = index_documents(&manifests, hpa_target_name);
+            for recommendation in recommendations {
+                let Some(target) = recommendation.hpa.as_ref().filter(|t| t.changes()) else {
+                    continue;
+                };
+                for &(file, doc) in hpa_index
+                    .get(recommendation.deployment.as_str())
+                    .into_iter()
+                    .flatten()
+                {
+                    let manifest = &mut manifests[file];
+                    if namespace_matches(&manifest.docs[doc], recommendation)
+                        && update_hpa_target(&mut manifest.docs[doc], target)
+                    {
+                        manifest.modified = true;
+                    }
+                }
+            }
+        }
+
+        for manifest in manifests.iter().filter(|m| m.modified) {
+            manifest.write(self.sops.as_ref())?;
+            info!("Updated file: {}", manifest.path.display());
+        }
+
+        let unmanaged: Vec<ResourceRecommendation> = recommendations
+            .iter()
+            .filter(|rec| !managed_by_vpa(rec) && !self.in_helm_values(rec))
+            .cloned()
+            .collect();
+        for (key, count) in TerraformEditor::new(&self.root).apply_recommendations(&unmanaged)? {
+            *updates.entry(key).or_insert(0) += count;
+        }
+
+        Ok(updates)
+    }
+
+    /// Apply recommendations to each environment overlay, empty without overlays
+    pub fn apply_to_overlays(
+        &self,
+        recommendations: &[ResourceRecommendation],
+    ) -> Result<Vec<EnvironmentChanges>> {
+        let Some(overlays) = &self.overlays else {
+            return Ok(Vec::new());
+        };
+
+        let mut changes = Vec::new();
+        for (environment, scale) in overlays.targets() {
+            let dir = self.root.join(&environment.path);
+            if !dir.is_dir() {
+                warn!(
+                    "Skipping environment {}, {} does not exist",
+                    environment.name,
+                    dir.display()
+                );
+                continue;
+            }
+
+            info!(
+                "Applying recommendations to environment {} in {}",
+                environment.name,
+                dir.display()
+            );
+            let scaled: Vec<ResourceRecommendation> = recommendations
+                .iter()
+                .map(|rec| scale_recommendation(rec, scale))
+                .collect();
+            let editor = ManifestEditor {
+                root: dir,
+                sops: self.sops.clone(),
+                overlays: None,
+                helm: None,
+                hpa_targets: self.hpa_targets,
+            };
+            changes.push(EnvironmentChanges {
+                environment: environment.name.clone(),
+                scale,
+                updated: editor.apply_recommendations(&scaled)?,
+            });
+        }
+        Ok(changes)
+    }
+
+    /// Whether the recommendation goes to a Helm values file
+    ///
+    /// A VPA managing the workload is bounded instead, like for plain manifests.
+    fn in_helm_values(&self, recommendation: &ResourceRecommendation) -> bool {
+        !managed_by_vpa(recommendation)
+            && self
+                .helm
+                .as_ref()
+                .is_some_and(|helm| helm.entry_for(recommendation).is_some())
+    }
+
+    /// Set the resources of Deployments mapped to Helm values files
+    ///
+    /// Returns the number of updated containers keyed by `namespace/deployment`
+    fn apply_to_helm_values(
+        &self,
+        recommendations: &[ResourceRecommendation],
+    ) -> Result<HashMap<String, usize>> {
+        let mut updates = HashMap::new();
+        let Some(helm) = &self.helm else {
+            return Ok(updates);
+        };
+
+        let mut values_files: Vec<ManifestFile> = Vec::new();
+        for recommendation in recommendations {
+            if !self.in_helm_values(recommendation) {
+                continue;
+            }
+            let Some(entry) = helm.entry_for(recommendation) else {
+                continue;
+            };
+
+            let path = self.root.join(&entry.file);
+            let file = match values_files.iter().position(|file| file.path == path) {
+                Some(file) => file,
+                None if path.is_file() => {
+                    values_files.push(ManifestFile::parse(path, self.sops.as_ref())?);
+                    values_files.len() - 1
+                }
+                None => {
+                    warn!(
+                        "Skipping {}/{}, values file {} does not exist",
+                        recommendation.namespace,
+                        recommendation.deployment,
+                        path.display()
+                    );
+                    continue;
+                }
+            };
+
+            // Never write a limit below its request, whatever the caller passed in
+            let mut recommendation = recommendation.clone();
+            recommendation.enforce_limits_above_requests();
+            let values = &mut values_files[file];
+            let Some(doc) = values.docs.first_mut() else {
+                continue;
+            };
+            if update_values_resources(doc, &entry.key, &recommendation) {
+                values.modified = true;
+                let key = format!("{}/{}", recommendation.namespace, recommendation.deployment);
+                *updates.entry(key).or_insert(0) += 1;
+            }
+        }
+
+        for values in values_files.iter().filter(|v| v.modified) {
+            values.write(self.sops.as_ref())?;
+            info!("Updated values file: {}", values.path.display());
+        }
+        Ok(updates)
+    }
+}
+
+/// Parsed documents of one YAML file
+struct ManifestFile {
+    path: PathBuf,
+    docs: Vec<Value>,
+    modified: bool,
+    /// Decrypted documents as read, set for SOPS-encrypted files
+    decrypted: Option<Vec<Value>>,
+}
+
+impl ManifestFile {
+    fn parse(path: PathBuf, sops: Option<&SopsConfig>) -> Result<Self> {
+        let content = fs::read_to_string(&path)?;
+        let docs = parse_documents(&content)?;
+
+        let mut manifest = Self {
+            path,
+            docs,
+            modified: false,
+            decrypted: None,
+        };
+        if !manifest.docs.iter().any(sops::is_encrypted) {
+            return Ok(manifest);
+        }
+
+        // Encrypted files are never rewritten as a whole, that would drop their MAC
+        let docs = std::mem::take(&mut manifest.docs);
+        let Some(sops) = sops else {
+            warn!(
+                "Skipping SOPS-encrypted {}, pass --sops to decrypt it",
+                manifest.path.display()
+            );
+            return Ok(manifest);
+        };
+        if docs.len() > 1 {
+            warn!(
+                "Skipping SOPS-encrypted {}, multi-document files are not supported",
+                manifest.path.display()
+            );
+            return Ok(manifest);
+        }
+        match sops
+            .decrypt(&manifest.path)
+            .and_then(|plain| parse_documents(&plain))
+        {
+            Ok(plain) => {
+                manifest.decrypted = Some(plain.clone());
+                manifest.docs = plain;
+            }
+            Err(e) => warn!("Skipping {}: {}", manifest.path.display(), e),
+        }
+        Ok(manifest)
+    }
+
+    fn write(&self, sops: Option<&SopsConfig>) -> Result<()> {
+        if let (Some(decrypted), Some(sops)) = (&self.decrypted, sops) {
+            for (original, doc) in decrypted.iter().zip(&self.docs) {
+                for (key, value) in sops::changed_values(original, doc) {
+                    sops.set(&self.path, &key, &value)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let mut output = String::new();
+        for (i, doc) in self.docs.iter().enumerate() {
+            if i > 0 {
+                output.push_str("\n---\n");
+            }
+            output.push_str(&serde_yaml::to_string(doc)?);
+        }
+
+        fs::write(&self.path, output)?;
+        Ok(())
+    }
+}
+
+/// Documents of a YAML stream
+fn parse_documents(content: &str) -> Result<Vec<Value>> {
+    serde_yaml::Deserializer::from_str(content)
+        .map(|doc| serde_yaml::Value::deserialize(doc).map_err(|e| e.into()))
+        .collect()
+}
+
+/// Read and parse files on all available cores, keeping their order
+fn parse_manifests(files: Vec<PathBuf>, sops: Option<&SopsConfig>) -> Result<Vec<ManifestFile>> {
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = files.len().div_ceil(workers).max(1);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| ManifestFile::parse(path.clone(), sops))
+                        .collect::<Result<Vec<_>>>()
+                })
+            })
+            .collect();
+
+        let mut manifests = Vec::with_capacity(files.len());
+        for handle in handles {
+            let parsed = handle
+                .join()
+                .map_err(|_| RecommenderError::Other("Manifest parser panicked".to_string()))?;
+            manifests.extend(parsed?);
+        }
+        Ok(manifests)
+    })
+}
+
+/// Map Deployment names to the (file, document) positions `key` finds them at
+fn index_documents(
+    manifests: &[ManifestFile],
+    key: fn(&Value) -> Option<&str>,
+) -> HashMap<String, Vec<(usize, usize)>> {
+    let mut index: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+
+    for (file, manifest) in manifests.iter().enumerate() {
+        for (doc, value) in manifest.docs.iter().enumerate() {
+            if let Some(name) = key(value) {
+                index.entry(name.to_string()).or_default().push((file, doc));
+            }
+        }
+    }
+
+    index
+}
+
+/// Name of a Deployment document
+fn deployment_name(doc: &Value) -> Option<&str> {
+    if doc.get("kind").and_then(|v| v.as_str()) != Some("Deployment") {
+        return None;
+    }
+    doc.get("metadata")?.get("name")?.as_str()
+}
+
+/// Name of the Deployment a VerticalPodAutoscaler document targets
+fn vpa_target_name(doc: &Value) -> Option<&str> {
+    if doc.get("kind").and_then(|v| v.as_str()) != Some("VerticalPodAutoscaler") {
+        return None;
+    }
+    let target = doc.get("spec")?.get("targetRef")?;
+    if target.get("kind").and_then(|v| v.as_str()) != Some("Deployment") {
+        return None;
+    }
+    target.get("name")?.as_str()
+}
+
+/// Name of the Deployment a HorizontalPodAutoscaler document scales
+fn hpa_target_name(doc: &Value) -> Option<&str> {
+    if doc.get("kind").and_then(|v| v.as_str()) != Some("HorizontalPodAutoscaler") {
+        return None;
+    }
+    let target = doc.get("spec")?.get("scaleTargetRef")?;
+    if target.get("kind").and_then(|v| v.as_str()) != Some("Deployment") {
+        return None;
+    }
+    target.get("name")?.as_str()
+}
+
+/// Whether a VPA changes the requests of the recommendation's workload
+fn managed_by_vpa(recommendation: &ResourceRecommendation) -> bool {
+    recommendation.vpa.as_ref().is_some_and(VpaTarget::manages)
+}
+
+/// Recursively find YAML files
+fn find_yaml_files_recursive(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        // Skip .git directory
+        if path.file_name().and_then(|n| n.to_str()) == Some(".git") {
+            continue;
+        }
+
+        // Chart templates aren't YAML until Helm renders them
+        if path.file_name().and_then(|n| n.to_str()) == Some("templates")
+            && dir.join("Chart.yaml").is_file()
+        {
+            continue;
+        }
+
+        if path.is_dir() {
+            find_yaml_files_recursive(&path, files)?;
+        } else if let Some(ext) = path.extension()
+            && (ext == "yaml" || ext == "yml")
+        {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Manifests without a namespace match any, leaving it to kubectl
+fn namespace_matches(doc: &Value, recommendation: &ResourceRecommendation) -> bool {
+    doc.get("metadata")
+        .and_then(|m| m.get("namespace"))
+        .and_then(|n| n.as_str())
+        .is_none_or(|namespace| namespace == recommendation.namespace)
+}
+
+/// Update container resources in deployment YAML
+fn update_container_resources(doc: &mut Value, recommendation: &ResourceRecommendation) -> bool {
+    let mut updated = false;
+
+    // Navigate to spec.template.spec.containers
+    let Some(containers) = doc
+        .get_mut("spec")
+        .and_then(|s| s.get_mut("template"))
+        .and_then(|t| t.get_mut("spec"))
+        .and_then(|s| s.get_mut("containers"))
+        .and_then(|c| c.as_sequence_mut())
+    else {
+        return false;
+    };
+
+    for container in containers {
+        // Check if this is the container we're looking for
+        if container.get("name").and_then(|n| n.as_str()) != Some(recommendation.container.as_str())
+        {
+            continue;
+        }
+
+        let Some(container) = container.as_mapping_mut() else {
+            continue;
+        };
+
+        set_resources(mapping_entry(container, "resources"), recommendation);
+
+        updated = true;
+        debug!(
+            "Updated resources for container: {}",
+            recommendation.container
+        );
+    }
+
+    updated
+}
+
+/// Update the resources at the dotted `key` of a Helm values document
+///
+/// Missing mappings along the path are created, values files may leave them out.
+fn update_values_resources(
+    doc: &mut Value,
+    key: &str,
+    recommendation: &ResourceRecommendation,
+) -> bool {
+    if doc.is_null() {
+        *doc = Value::Mapping(Default::default());
+    }
+    let Some(mut resources) = doc.as_mapping_mut() else {
+        return false;
+    };
+    for segment in key.split('.') {
+        resources = mapping_entry(resources, segment);
+    }
+    set_resources(resources, recommendation);
+
+    debug!(
+        "Updated values {} for container: {}",
+        key, recommendation.container
+    );
+    true
+}
+
+/// Set the recommended requests and limits on a `resources` mapping
+fn set_resources(resources: &mut serde_yaml::Mapping, recommendation: &ResourceRecommendation) {
+    let requests = mapping_entry(resources, "requests");
+    requests.insert(
+        Value::String("cpu".to_string()),
+        Value::String(recommendation.recommended_cpu_request.to_string()),
+    );
+    requests.insert(
+        Value::String("memory".to_string()),
+        Value::String(recommendation.recommended_memory_request.to_string()),
+    );
+
+    let limits = mapping_entry(resources, "limits");
+    limits.insert(
+        Value::String("cpu".to_string()),
+        Value::String(recommendation.recommended_cpu_limit.to_string()),
+    );
+    limits.insert(
+        Value::String("memory".to_string()),
+        Value::String(recommendation.recommended_memory_limit.to_string()),
+    );
+}
+
+/// Bound a VerticalPodAutoscaler to the recommendation
+///
+/// The container's policy gets the recommended requests as `minAllowed` and the
+/// recommended limits as `maxAllowed`, adding the policy if the VPA has none.
+fn update_vpa_bounds(doc: &mut Value, recommendation: &ResourceRecommendation) -> bool {
+    let Some(spec) = doc.get_mut("spec").and_then(|s| s.as_mapping_mut()) else {
+        return false;
+    };
+    let policy = mapping_entry(spec, "resourcePolicy");
+    let key = Value::String("containerPolicies".to_string());
+    if !policy.get(&key).is_some_and(|v| v.is_sequence()) {
+        policy.insert(key.clone(), Value::Sequence(Vec::new()));
+    }
+    let Some(policies) = policy.get_mut(&key).and_then(|v| v.as_sequence_mut()) else {
+        return false;
+    };
+
+    let name = Value::String(recommendation.container.clone());
+    let index = match policies
+        .iter()
+        .position(|p| p.get("containerName") == Some(&name))
+    {
+        Some(index) => index,
+        None => {
+            let mut container = serde_yaml::Mapping::new();
+            container.insert(Value::String("containerName".to_string()), name);
+            policies.push(Value::Mapping(container));
+            policies.len() - 1
+        }
+    };
+    let Some(container) = policies[index].as_mapping_mut() else {
+        return false;
+    };
+
+    let bounds = [
+        (
+            "minAllowed",
+            recommendation.recommended_cpu_request,
+            recommendation.recommended_memory_request,
+        ),
+        (
+            "maxAllowed",
+            recommendation.recommended_cpu_limit,
+            recommendation.recommended_memory_limit,
+        ),
+    ];
+    for (key, cpu, memory) in bounds {
+        let bound = mapping_entry(container, key);
+        bound.insert(
+            Value::String("cpu".to_string()),
+            Value::String(cpu.to_string()),
+        );
+        bound.insert(
+            Value::String("memory".to_string()),
+            Value::String(memory.to_string()),
+        );
+    }
+
+    debug!(
+        "Updated VPA bounds for container: {}",
+        recommendation.container
+    );
+    true
+}
+
+/// Get a nested mapping by key, creating (or replacing a non-mapping value) when needed
+/// Set the CPU utilization target of a HorizontalPodAutoscaler
+///
+/// `autoscaling/v1` documents get `targetCPUUtilizationPercentage`, later versions
+/// the CPU resource metric, which is added if the HPA scales on other metrics only.
+/// Returns whether the document changed.
+fn update_hpa_target(doc: &mut Value, target: &HpaTarget) -> bool {
+    let utilization = Value::Number(target.recommended_cpu_utilization.into());
+    let v1 = doc.get("apiVersion").and_then(|v| v.as_str()) == Some("autoscaling/v1");
+    let Some(spec) = doc.get_mut("spec").and_then(|s| s.as_mapping_mut()) else {
+        return false;
+    };
+
+    if v1 {
+        let key = Value::String("targetCPUUtilizationPercentage".to_string());
+        if spec.get(&key) == Some(&utilization) {
+            return false;
+        }
+        spec.insert(key, utilization);
+    } else {
+        let key = Value::String("metrics".to_string());
+        if !spec.get(&key).is_some_and(|v| v.is_sequence()) {
+            spec.insert(key.clone(), Value::Sequence(Vec::new()));
+        }
+        let Some(metrics) = spec.get_mut(&key).and_then(|v| v.as_sequence_mut()) else {
+            return false;
+        };
+
+        let cpu = metrics.iter().position(|m| {
+            m.get("type").and_then(|t| t.as_str()) == Some("Resource")
+                && m.get("resource")
+                    .and_then(|r| r.get("name"))
+                    .and_then(|n| n.as_str())
+                    == Some("cpu")
+        });
+        let index = match cpu {
+            Some(index) => index,
+            None => {
+                let mut metric = serde_yaml::Mapping::new();
+                metric.insert(
+                    Value::String("type".to_string()),
+                    Value::String("Resource".to_string()),
+                );
+                let mut resource = serde_yaml::Mapping::new();
+                resource.insert(
+                    Value::String("name".to_string()),
+                    Value::String("cpu".to_string()),
+                );
+                metric.insert(
+                    Value::String("resource".to_string()),
+                    Value::Mapping(resource),
+                );
+                metrics.push(Value::Mapping(metric));
+                metrics.len() - 1
+            }
+        };
+        let Some(metric) = metrics[index].as_mapping_mut() else {
+            return false;
+        };
+
+        let resource_target = mapping_entry(mapping_entry(metric, "resource"), "target");
+        let kind = Value::String("Utilization".to_string());
+        let type_key = Value::String("type".to_string());
+        let utilization_key = Value::String("averageUtilization".to_string());
+        if resource_target.get(&type_key) == Some(&kind)
+            && resource_target.get(&utilization_key) == Some(&utilization)
+        {
+            return false;
+        }
+        // Any other target type is replaced, an averageValue would outlive the new requests
+        resource_target.clear();
+        resource_target.insert(type_key, kind);
+        resource_target.insert(utilization_key, utilization);
+    }
+
+    debug!(
+        "Updated CPU utilization target of HPA {} to {}%",
+        target.name, target.recommended_cpu_utilization
+    );
+    true
+}
+
+fn mapping_entry<'a>(
+    mapping: &'a mut serde_yaml::Mapping,
+    key: &str,
+) -> &'a mut serde_yaml::Mapping {
+    let key = Value::String(key.to_string());
+    if !mapping.get(&key).is_some_and(|v| v.is_mapping()) {
+        mapping.insert(key.clone(), Value::Mapping(Default::default()));
+    }
+
+    mapping
+        .get_mut(&key)
+        .and_then(|v| v.as_mapping_mut())
+        .expect("entry was just inserted as a mapping")
+}
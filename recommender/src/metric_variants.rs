@@ -0,0 +1,229 @@
+//! Memory series variants across cgroup versions, runtimes and scrape setups
+//!
+//! cAdvisor on cgroup v2 nodes leaves `container_memory_working_set_bytes` out for
+//! some runtimes, while still exporting the usage and inactive file pages it is
+//! computed from. Scraping the kubelet's `/metrics/resource` endpoint next to
+//! `/metrics/cadvisor` exports the working set twice per container. The variants
+//! a workspace has are detected once per run, and the default usage queries are
+//! rewritten to take the best one per pod and to drop duplicate series, so
+//! mixed node pools are sized from the same quantity.
+
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+use log::{debug, info, warn};
+
+use crate::Result;
+use crate::config::RecommenderConfig;
+use crate::metrics::MetricsProvider;
+use crate::query::{MEMORY_USAGE_METRIC, QueryTemplate};
+
+/// How far back detection looks for series
+const DETECTION_WINDOW: Duration = Duration::from_secs(600);
+
+const DETECTION_STEP: Duration = Duration::from_secs(300);
+
+/// Labels the rewritten queries keep, one series per container of a pod
+const SERIES_LABELS: &str = "namespace, pod, container";
+
+/// Matchers of the default usage queries
+const SELECTOR: &str = r#"{namespace="$namespace",pod=~"$pod",container="$container"}"#;
+
+/// A memory series usage can be read from, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryVariant {
+    /// cAdvisor's working set, what OOM kills act on
+    WorkingSet,
+    /// Usage minus inactive file pages, the working set computed by hand
+    UsageMinusInactiveFile,
+    /// Resident set size, leaves out active page cache and so reads low
+    Rss,
+}
+
+impl MemoryVariant {
+    const ALL: [MemoryVariant; 3] = [
+        MemoryVariant::WorkingSet,
+        MemoryVariant::UsageMinusInactiveFile,
+        MemoryVariant::Rss,
+    ];
+
+    /// Metrics the variant needs
+    fn metrics(self) -> &'static [&'static str] {
+        match self {
+            MemoryVariant::WorkingSet => &[MEMORY_USAGE_METRIC],
+            MemoryVariant::UsageMinusInactiveFile => &[
+                "container_memory_usage_bytes",
+                "container_memory_total_inactive_file_bytes",
+            ],
+            MemoryVariant::Rss => &["container_memory_rss"],
+        }
+    }
+
+    /// PromQL of the variant for the series matching `selector`
+    fn expression(self, selector: &str) -> String {
+        match self.metrics() {
+            [metric] => format!("{}{}", metric, selector),
+            [usage, inactive] => format!("({}{} - {}{})", usage, selector, inactive, selector),
+            _ => unreachable!("memory variants use one or two metrics"),
+        }
+    }
+}
+
+impl fmt::Display for MemoryVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MemoryVariant::WorkingSet => "working set",
+            MemoryVariant::UsageMinusInactiveFile => "usage minus inactive file",
+            MemoryVariant::Rss => "RSS",
+        })
+    }
+}
+
+/// Metric variants found in the workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricVariants {
+    /// Memory variants with series, in order of preference
+    pub memory: Vec<MemoryVariant>,
+    /// Pods with memory usage series but no working set
+    pub pods_without_working_set: usize,
+    /// Whether containers have their working set scraped more than once, e.g. from
+    /// both the cAdvisor and resource metrics endpoints of the kubelet
+    pub duplicate_series: bool,
+}
+
+impl MetricVariants {
+    /// Probe which variants have series in `namespace` (all when None) as of `end`
+    pub async fn detect(
+        metrics: &dyn MetricsProvider,
+        namespace: Option<&str>,
+        end: SystemTime,
+    ) -> Result<Self> {
+        let matchers = match namespace {
+            Some(namespace) => format!(r#"{{container!="",namespace="{}"}}"#, namespace),
+            None => r#"{container!=""}"#.to_string(),
+        };
+        let count = |query: String| async move {
+            let series = metrics
+                .query_range(&query, end - DETECTION_WINDOW, end, DETECTION_STEP)
+                .await?;
+            debug!("{} returned {} series", query, series.len());
+            Ok::<f64, crate::RecommenderError>(
+                series
+                    .iter()
+                    .filter_map(|s| s.samples.last())
+                    .map(|sample| sample.value)
+                    .sum(),
+            )
+        };
+
+        let mut variants = Self::default();
+        for variant in MemoryVariant::ALL {
+            let mut available = true;
+            for metric in variant.metrics() {
+                available &= count(format!("count({}{})", metric, matchers)).await? > 0.0;
+            }
+            if available {
+                variants.memory.push(variant);
+            }
+        }
+
+        let working_set = format!("{}{}", MEMORY_USAGE_METRIC, matchers);
+        if variants.memory.contains(&MemoryVariant::WorkingSet) {
+            let by_pod = |series: &str| format!("count by (namespace, pod) ({})", series);
+            variants.pods_without_working_set = count(format!(
+                "count({} unless {})",
+                by_pod(&format!("container_memory_usage_bytes{}", matchers)),
+                by_pod(&working_set)
+            ))
+            .await? as usize;
+            variants.duplicate_series = count(format!(
+                "count(count by ({}) ({}) > 1)",
+                SERIES_LABELS, working_set
+            ))
+            .await?
+                > 0.0;
+        }
+        Ok(variants)
+    }
+
+    /// Memory query taking the preferred variant each pod has, None when the
+    /// default working set query already covers every pod once
+    pub fn memory_query(&self) -> Option<QueryTemplate> {
+        let complete =
+            self.memory.contains(&MemoryVariant::WorkingSet) && self.pods_without_working_set == 0;
+        if complete && !self.duplicate_series {
+            return None;
+        }
+
+        let variants = if complete {
+            &[MemoryVariant::WorkingSet][..]
+        } else {
+            &self.memory[..]
+        };
+        if variants.is_empty() {
+            return None;
+        }
+        let expression = variants
+            .iter()
+            .map(|variant| variant.expression(SELECTOR))
+            .collect::<Vec<_>>()
+            .join(" or ");
+        Some(QueryTemplate::new(deduplicated(&expression)))
+    }
+
+    /// Memory variants in order of preference, e.g. `working set, then RSS`
+    fn describe_memory(&self) -> String {
+        self.memory
+            .iter()
+            .map(MemoryVariant::to_string)
+            .collect::<Vec<_>>()
+            .join(", then ")
+    }
+
+    /// Point the default usage queries of `config` at the detected variants
+    ///
+    /// Queries set explicitly are left alone.
+    pub fn apply(&self, config: &mut RecommenderConfig) {
+        if config.memory_query == QueryTemplate::memory_usage()
+            && let Some(query) = self.memory_query()
+        {
+            if !self.memory.contains(&MemoryVariant::WorkingSet) {
+                info!(
+                    "No {} series, reading memory from {}",
+                    MEMORY_USAGE_METRIC,
+                    self.describe_memory()
+                );
+            } else if self.pods_without_working_set > 0 {
+                info!(
+                    "{} pods have no {}, reading memory from {}",
+                    self.pods_without_working_set,
+                    MEMORY_USAGE_METRIC,
+                    self.describe_memory()
+                );
+            }
+            if self.memory == [MemoryVariant::Rss] {
+                warn!(
+                    "Only container_memory_rss is available, memory recommendations leave out active page cache"
+                );
+            }
+            config.memory_query = query;
+        }
+
+        if self.duplicate_series {
+            info!("Usage series are scraped more than once per container, deduplicating them");
+            if config.cpu_query == QueryTemplate::cpu_usage() {
+                config.cpu_query = QueryTemplate::new(deduplicated(&config.cpu_query.template));
+            }
+            if let Some(query) = &mut config.cpu_limit_query
+                && *query == QueryTemplate::cpu_burst()
+            {
+                *query = QueryTemplate::new(deduplicated(&query.template));
+            }
+        }
+    }
+}
+
+/// `expression` with one series per container of a pod, the highest when several
+fn deduplicated(expression: &str) -> String {
+    format!("max by ({}) ({})", SERIES_LABELS, expression)
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+
+/// How far back an instant query looks for a series' latest sample, like
+/// Prometheus' default lookback delta
+pub const INSTANT_LOOKBACK: Duration = Duration::from_secs(300);
+
+const INSTANT_STEP: Duration = Duration::from_secs(60);
+
+/// A single data point of a time series
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    /// Unix timestamp in seconds
+    pub timestamp: f64,
+    pub value: f64,
+}
+
+/// A labelled time series returned by a range query
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Series {
+    pub labels: HashMap<String, String>,
+    pub samples: Vec<Sample>,
+}
+
+/// Backend able to answer PromQL range and instant queries
+///
+/// The Recommender only depends on this trait, so alternative backends or
+/// in-memory fakes can be plugged in instead of a live AMP workspace.
+/// `PrometheusClient` is the AMP implementation.
+#[async_trait]
+pub trait MetricsProvider: Send + Sync {
+    /// Execute a range query and return typed samples
+    async fn query_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+    ) -> Result<Vec<Series>>;
+
+    /// Execute a range query, handing each series to `on_series` as it is decoded
+    ///
+    /// A series may be handed over several times, in order, when the backend
+    /// splits long windows. Defaults to [`MetricsProvider::query_range`]; backends
+    /// able to decode responses incrementally override it so a long window is
+    /// never held in memory at once.
+    async fn stream_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+        on_series: &mut (dyn FnMut(Series) + Send),
+    ) -> Result<()> {
+        for series in self.query_range(query, start, end, step).await? {
+            on_series(series);
+        }
+        Ok(())
+    }
+
+    /// Execute an instant query at `time`, one sample per series
+    ///
+    /// Defaults to the latest sample of each series within [`INSTANT_LOOKBACK`]
+    /// of `time`, so backends only answering range queries, and snapshots of
+    /// them, work too.
+    async fn query(&self, query: &str, time: SystemTime) -> Result<Vec<Series>> {
+        let start = time
+            .checked_sub(INSTANT_LOOKBACK)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut series = self.query_range(query, start, time, INSTANT_STEP).await?;
+        for s in &mut series {
+            s.samples = s.samples.last().copied().into_iter().collect();
+        }
+        series.retain(|s| !s.samples.is_empty());
+        Ok(series)
+    }
+}
+
+/// In-memory metrics backend returning pre-registered series per query
+///
+/// Queries without registered series return an empty result, like a
+/// Prometheus query that matched nothing.
+#[derive(Debug, Clone, Default)]
+pub struct StaticMetricsProvider {
+    series: HashMap<String, Vec<Series>>,
+}
+
+impl StaticMetricsProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the series returned for an exact query string
+    pub fn insert(&mut self, query: impl Into<String>, series: Vec<Series>) {
+        self.series.insert(query.into(), series);
+    }
+
+    /// Builder-style variant of [`StaticMetricsProvider::insert`]
+    pub fn with_series(mut self, query: impl Into<String>, series: Vec<Series>) -> Self {
+        self.insert(query, series);
+        self
+    }
+}
+
+#[async_trait]
+impl MetricsProvider for StaticMetricsProvider {
+    async fn query_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        _step: Duration,
+    ) -> Result<Vec<Series>> {
+        let start = to_unix_seconds(start);
+        let end = to_unix_seconds(end);
+
+        Ok(self
+            .series
+            .get(query)
+            .map(|series| {
+                series
+                    .iter()
+                    .map(|s| Series {
+                        labels: s.labels.clone(),
+                        samples: s
+                            .samples
+                            .iter()
+                            .filter(|sample| sample.timestamp >= start && sample.timestamp <= end)
+                            .copied()
+                            .collect(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+/// Convert a SystemTime to fractional Unix seconds
+pub(crate) fn to_unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn series(pod: &str, timestamps: &[u64]) -> Series {
+        Series {
+            labels: HashMap::from([("pod".to_string(), pod.to_string())]),
+            samples: timestamps
+                .iter()
+                .map(|&timestamp| Sample {
+                    timestamp: timestamp as f64,
+                    value: timestamp as f64,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn instant_query_keeps_latest_sample_within_lookback() {
+        let now = 10_000;
+        let lookback = INSTANT_LOOKBACK.as_secs();
+        let metrics = StaticMetricsProvider::new().with_series(
+            "up",
+            vec![
+                series("recent", &[now - 200, now - 100, now + 60]),
+                series("edge", &[now - lookback - 60, now - lookback]),
+                series("stale", &[now - lookback - 1]),
+            ],
+        );
+
+        let mut series = metrics.query("up", at(now)).await.unwrap();
+        series.sort_by(|a, b| a.labels["pod"].cmp(&b.labels["pod"]));
+
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].labels["pod"], "edge");
+        assert_eq!(
+            series[0].samples,
+            vec![Sample {
+                timestamp: (now - lookback) as f64,
+                value: (now - lookback) as f64,
+            }]
+        );
+        // Samples after the query time are never returned
+        assert_eq!(series[1].labels["pod"], "recent");
+        assert_eq!(series[1].samples.len(), 1);
+        assert_eq!(series[1].samples[0].timestamp, (now - 100) as f64);
+    }
+
+    #[tokio::test]
+    async fn instant_query_without_match_is_empty() {
+        let metrics = StaticMetricsProvider::new();
+        assert!(metrics.query("up", at(10_000)).await.unwrap().is_empty());
+    }
+}
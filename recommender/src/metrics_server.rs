@@ -0,0 +1,174 @@
+//! Live usage from the `metrics.k8s.io` API, for containers Prometheus has no data for
+//!
+//! metrics-server only keeps the latest sample per pod, so usage is polled for a
+//! short window instead of queried over the lookback. Recommendations sized from
+//! it are rough and flagged as such.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use kube::Client;
+use kube::api::{Api, ApiResource, DynamicObject, GroupVersionKind, ListParams};
+use log::{debug, info};
+use tokio_util::sync::CancellationToken;
+
+use crate::KubernetesError::ApiError;
+use crate::kubernetes::DeploymentResources;
+use crate::quantity::Quantity;
+use crate::{RecommenderError, Result};
+
+/// metrics-server scrapes the kubelets every 15s by default
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// CPU usage in cores and memory usage in bytes of one container, one value per
+/// pod and metrics-server sample
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LiveUsage {
+    pub cpu: Vec<f64>,
+    pub memory: Vec<f64>,
+}
+
+/// Polls pod usage from metrics-server
+#[derive(Clone)]
+pub struct MetricsServerSampler {
+    client: Client,
+    window: Duration,
+    interval: Duration,
+}
+
+impl MetricsServerSampler {
+    /// Sample every [`DEFAULT_SAMPLE_INTERVAL`] for `window`
+    pub fn new(client: Client, window: Duration) -> Self {
+        Self {
+            client,
+            window,
+            interval: DEFAULT_SAMPLE_INTERVAL,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// How long usage is polled for
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Usage of the containers of `deployments`, keyed by namespace, deployment
+    /// and container
+    ///
+    /// Fails with [`RecommenderError::Cancelled`] when `cancel` fires mid-window.
+    pub async fn sample(
+        &self,
+        deployments: &[&DeploymentResources],
+        cancel: &CancellationToken,
+    ) -> Result<HashMap<(String, String, String), LiveUsage>> {
+        let mut namespaces: Vec<&str> = deployments.iter().map(|d| d.namespace.as_str()).collect();
+        namespaces.sort();
+        namespaces.dedup();
+
+        let mut usage: HashMap<(String, String, String), LiveUsage> = HashMap::new();
+        // Sample timestamp of each pod last recorded, metrics-server repeats it until the next scrape
+        let mut seen: HashMap<(String, String), String> = HashMap::new();
+        let deadline = tokio::time::Instant::now() + self.window;
+
+        loop {
+            for namespace in &namespaces {
+                let api: Api<DynamicObject> =
+                    Api::namespaced_with(self.client.clone(), namespace, &pod_metrics_resource());
+                let pods = api.list(&ListParams::default()).await.map_err(ApiError)?;
+
+                for pod in pods.items {
+                    let Some(name) = pod.metadata.name.clone() else {
+                        continue;
+                    };
+                    let Some(deployment) = deployments
+                        .iter()
+                        .find(|d| d.namespace == *namespace && d.owns_pod(&name))
+                    else {
+                        continue;
+                    };
+                    let timestamp = pod
+                        .data
+                        .get("timestamp")
+                        .and_then(|t| t.as_str())
+                        .unwrap_or_default()
+                        .to_string();
+                    let key = (namespace.to_string(), name);
+                    if seen.get(&key) == Some(&timestamp) {
+                        continue;
+                    }
+                    seen.insert(key, timestamp);
+
+                    for (container, cpu, memory) in container_usage(&pod) {
+                        let entry = usage
+                            .entry((
+                                deployment.namespace.clone(),
+                                deployment.name.clone(),
+                                container,
+                            ))
+                            .or_default();
+                        entry.cpu.push(cpu);
+                        entry.memory.push(memory);
+                    }
+                }
+            }
+
+            let now = tokio::time::Instant::now();
+            if now >= deadline {
+                break;
+            }
+            debug!(
+                "Collected metrics-server samples for {} containers",
+                usage.len()
+            );
+            tokio::select! {
+                _ = cancel.cancelled() => return Err(RecommenderError::Cancelled),
+                _ = tokio::time::sleep(self.interval.min(deadline - now)) => {}
+            }
+        }
+
+        info!(
+            "Sampled {} containers from metrics-server over {}s",
+            usage.len(),
+            self.window.as_secs()
+        );
+        Ok(usage)
+    }
+}
+
+/// API resource of `metrics.k8s.io/v1beta1` PodMetrics, served as `pods`
+pub fn pod_metrics_resource() -> ApiResource {
+    ApiResource::from_gvk_with_plural(
+        &GroupVersionKind::gvk("metrics.k8s.io", "v1beta1", "PodMetrics"),
+        "pods",
+    )
+}
+
+/// Container name, CPU cores and memory bytes of each container of a PodMetrics object
+fn container_usage(pod: &DynamicObject) -> Vec<(String, f64, f64)> {
+    let quantity = |usage: &serde_json::Value, key: &str| {
+        usage
+            .get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|v| Quantity::parse(v).ok())
+            .map(|q| q.value())
+    };
+
+    pod.data
+        .get("containers")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|container| {
+            let usage = container.get("usage")?;
+            Some((
+                container.get("name")?.as_str()?.to_string(),
+                quantity(usage, "cpu")?,
+                quantity(usage, "memory")?,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashMap};
+
+use k8s_openapi::api::core::v1::{Node, Pod};
+use kube::api::ListParams;
+use kube::{Api, Client};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::kubernetes::{ContainerResources, DeploymentResources};
+use crate::quantity::Quantity;
+use crate::recommender::ResourceRecommendation;
+use crate::{KubernetesError::ApiError, Result};
+
+/// Node labels naming the node group, the first one present wins
+const NODE_GROUP_LABELS: [&str; 3] = [
+    "eks.amazonaws.com/nodegroup",
+    "karpenter.sh/nodepool",
+    "alpha.eksctl.io/nodegroup-name",
+];
+
+const INSTANCE_TYPE_LABEL: &str = "node.kubernetes.io/instance-type";
+
+/// Reported for nodes without a node group or instance type label
+const UNKNOWN: &str = "unknown";
+
+/// Share of a node's allocatable resources pods are expected to fill
+///
+/// Requests never pack perfectly, so the estimate leaves room for fragmentation
+/// and DaemonSets scheduled onto new nodes.
+pub(crate) const MAX_NODE_UTILIZATION: f64 = 0.85;
+
+/// A schedulable node and what pods may request on it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeInfo {
+    pub name: String,
+    pub node_group: String,
+    pub instance_type: String,
+    pub allocatable_cpu: Quantity,
+    pub allocatable_memory: Quantity,
+}
+
+/// A running pod and the requests of its containers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PodPlacement {
+    pub namespace: String,
+    pub name: String,
+    pub node: String,
+    pub containers: Vec<ContainerResources>,
+}
+
+/// Nodes and running pods of a cluster, the input of [`node_pool_report`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeInventory {
+    pub nodes: Vec<NodeInfo>,
+    pub pods: Vec<PodPlacement>,
+}
+
+impl NodeInventory {
+    /// List schedulable nodes and the running pods of all namespaces
+    pub async fn load(client: Client) -> Result<Self> {
+        let nodes: Api<Node> = Api::all(client.clone());
+        let nodes = nodes.list(&ListParams::default()).await.map_err(ApiError)?;
+        let pods: Api<Pod> = Api::all(client);
+        let pods = pods
+            .list(&ListParams::default().fields("status.phase=Running"))
+            .await
+            .map_err(ApiError)?;
+
+        let inventory = Self {
+            nodes: nodes.items.iter().filter_map(node_info).collect(),
+            pods: pods.items.iter().filter_map(pod_placement).collect(),
+        };
+        info!(
+            "Found {} schedulable nodes running {} pods",
+            inventory.nodes.len(),
+            inventory.pods.len()
+        );
+        Ok(inventory)
+    }
+}
+
+/// Node count estimate of one node group and instance type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeGroupEstimate {
+    pub node_group: String,
+    pub instance_type: String,
+    pub current_nodes: usize,
+    /// Average allocatable resources of a node
+    pub node_cpu: Quantity,
+    pub node_memory: Quantity,
+    /// Requests of the pods running on the group today
+    pub requested_cpu: Quantity,
+    pub requested_memory: Quantity,
+    /// The same requests with the recommendations applied
+    pub recommended_cpu: Quantity,
+    pub recommended_memory: Quantity,
+    /// Highest recommended pod requests, which any replacement node must fit
+    #[serde(default)]
+    pub largest_pod_cpu: Quantity,
+    #[serde(default)]
+    pub largest_pod_memory: Quantity,
+    /// Nodes the current requests need at the target utilization
+    pub nodes_needed_now: usize,
+    /// Nodes the recommended requests need at the target utilization
+    pub nodes_needed_after: usize,
+    /// Nodes that could be removed after rightsizing
+    pub reducible_nodes: usize,
+}
+
+/// Potential node reductions of one instance type across node groups
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstanceTypeSavings {
+    pub instance_type: String,
+    pub current_nodes: usize,
+    pub reducible_nodes: usize,
+}
+
+/// How rightsizing translates into node counts, see [`node_pool_report`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodePoolReport {
+    /// Target share of allocatable resources the estimates pack pods to
+    pub max_utilization: f64,
+    pub node_groups: Vec<NodeGroupEstimate>,
+    pub instance_types: Vec<InstanceTypeSavings>,
+}
+
+/// Estimate the nodes each node group needs once the recommendations are applied
+///
+/// Pods of analyzed deployments count with their recommended requests, all other
+/// pods (DaemonSets, system workloads, skipped containers) with their current
+/// ones. Node counts assume pods fill nodes up to 85% of their allocatable CPU and
+/// memory, whichever runs out first, so they are an estimate, not a scheduling plan.
+pub fn node_pool_report(
+    inventory: &NodeInventory,
+    deployments: &[DeploymentResources],
+    recommendations: &[ResourceRecommendation],
+) -> NodePoolReport {
+    let recommended: HashMap<(&str, &str, &str), &ResourceRecommendation> = recommendations
+        .iter()
+        .map(|rec| {
+            (
+                (
+                    rec.namespace.as_str(),
+                    rec.deployment.as_str(),
+                    rec.container.as_str(),
+                ),
+                rec,
+            )
+        })
+        .collect();
+    let node_groups: HashMap<&str, (&str, &str)> = inventory
+        .nodes
+        .iter()
+        .map(|node| {
+            (
+                node.name.as_str(),
+                (node.node_group.as_str(), node.instance_type.as_str()),
+            )
+        })
+        .collect();
+
+    let mut groups: BTreeMap<(&str, &str), NodeGroupEstimate> = BTreeMap::new();
+    for node in &inventory.nodes {
+        let group = groups
+            .entry((node.node_group.as_str(), node.instance_type.as_str()))
+            .or_insert_with(|| NodeGroupEstimate {
+                node_group: node.node_group.clone(),
+                instance_type: node.instance_type.clone(),
+                current_nodes: 0,
+                node_cpu: Quantity::from_cores(0.0),
+                node_memory: Quantity::from_bytes(0.0),
+                requested_cpu: Quantity::from_cores(0.0),
+                requested_memory: Quantity::from_bytes(0.0),
+                recommended_cpu: Quantity::from_cores(0.0),
+                recommended_memory: Quantity::from_bytes(0.0),
+                largest_pod_cpu: Quantity::from_cores(0.0),
+                largest_pod_memory: Quantity::from_bytes(0.0),
+                nodes_needed_now: 0,
+                nodes_needed_after: 0,
+                reducible_nodes: 0,
+            });
+        group.current_nodes += 1;
+        group.node_cpu = group.node_cpu + node.allocatable_cpu;
+        group.node_memory = group.node_memory + node.allocatable_memory;
+    }
+
+    for pod in &inventory.pods {
+        let Some(group) = node_groups
+            .get(pod.node.as_str())
+            .and_then(|key| groups.get_mut(key))
+        else {
+            debug!(
+                "Pod {}/{} runs on unlisted node {}",
+                pod.namespace, pod.name, pod.node
+            );
+            continue;
+        };
+        let owner = deployments
+            .iter()
+            .find(|d| d.namespace == pod.namespace && d.owns_pod(&pod.name));
+
+        let (mut pod_cpu, mut pod_memory) = (Quantity::from_cores(0.0), Quantity::from_bytes(0.0));
+        for container in &pod.containers {
+            let cpu = container.cpu_request.unwrap_or_default().value();
+            let memory = container.memory_request.unwrap_or_default().value();
+            let rec = owner.and_then(|deployment| {
+                recommended.get(&(
+                    pod.namespace.as_str(),
+                    deployment.name.as_str(),
+                    container.name.as_str(),
+                ))
+            });
+
+            group.requested_cpu = group.requested_cpu + Quantity::from_cores(cpu);
+            group.requested_memory = group.requested_memory + Quantity::from_bytes(memory);
+            pod_cpu =
+                pod_cpu + rec.map_or(Quantity::from_cores(cpu), |r| r.recommended_cpu_request);
+            pod_memory = pod_memory
+                + rec.map_or(Quantity::from_bytes(memory), |r| {
+                    r.recommended_memory_request
+                });
+        }
+        group.recommended_cpu = group.recommended_cpu + pod_cpu;
+        group.recommended_memory = group.recommended_memory + pod_memory;
+        if pod_cpu > group.largest_pod_cpu {
+            group.largest_pod_cpu = pod_cpu;
+        }
+        if pod_memory > group.largest_pod_memory {
+            group.largest_pod_memory = pod_memory;
+        }
+    }
+
+    let mut instance_types: BTreeMap<String, InstanceTypeSavings> = BTreeMap::new();
+    let node_groups: Vec<NodeGroupEstimate> = groups
+        .into_values()
+        .map(|mut group| {
+            let nodes = group.current_nodes as f64;
+            group.node_cpu = group.node_cpu * (1.0 / nodes);
+            group.node_memory = group.node_memory * (1.0 / nodes);
+            group.nodes_needed_now = nodes_to_fit(
+                group.requested_cpu,
+                group.requested_memory,
+                group.node_cpu,
+                group.node_memory,
+            );
+            group.nodes_needed_after = nodes_to_fit(
+                group.recommended_cpu,
+                group.recommended_memory,
+                group.node_cpu,
+                group.node_memory,
+            );
+            group.reducible_nodes = group.current_nodes.saturating_sub(group.nodes_needed_after);
+
+            let savings = instance_types
+                .entry(group.instance_type.clone())
+                .or_insert_with(|| InstanceTypeSavings {
+                    instance_type: group.instance_type.clone(),
+                    current_nodes: 0,
+                    reducible_nodes: 0,
+                });
+            savings.current_nodes += group.current_nodes;
+            savings.reducible_nodes += group.reducible_nodes;
+            group
+        })
+        .collect();
+
+    NodePoolReport {
+        max_utilization: MAX_NODE_UTILIZATION,
+        node_groups,
+        instance_types: instance_types.into_values().collect(),
+    }
+}
+
+/// Nodes of `node_cpu` and `node_memory` fitting `cpu` and `memory` at the target utilization
+///
+/// At least one node is needed while any pod requests resources.
+pub(crate) fn nodes_to_fit(
+    cpu: Quantity,
+    memory: Quantity,
+    node_cpu: Quantity,
+    node_memory: Quantity,
+) -> usize {
+    let fit = |requested: Quantity, allocatable: Quantity| {
+        if allocatable.value() > 0.0 {
+            (requested.value() / (allocatable.value() * MAX_NODE_UTILIZATION)).ceil()
+        } else {
+            0.0
+        }
+    };
+    let nodes = fit(cpu, node_cpu).max(fit(memory, node_memory)) as usize;
+    if cpu.value() > 0.0 || memory.value() > 0.0 {
+        nodes.max(1)
+    } else {
+        nodes
+    }
+}
+
+/// Node group, instance type and allocatable resources of a schedulable node
+fn node_info(node: &Node) -> Option<NodeInfo> {
+    if node
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.unschedulable)
+        .unwrap_or(false)
+    {
+        return None;
+    }
+    let labels = node.metadata.labels.as_ref();
+    let label = |key: &str| labels.and_then(|labels| labels.get(key)).cloned();
+    let allocatable = node.status.as_ref()?.allocatable.as_ref()?;
+    let quantity = |key: &str| {
+        allocatable
+            .get(key)
+            .and_then(|q| Quantity::parse(&q.0).ok())
+    };
+
+    Some(NodeInfo {
+        name: node.metadata.name.clone()?,
+        node_group: NODE_GROUP_LABELS
+            .iter()
+            .find_map(|key| label(key))
+            .unwrap_or_else(|| UNKNOWN.to_string()),
+        instance_type: label(INSTANCE_TYPE_LABEL).unwrap_or_else(|| UNKNOWN.to_string()),
+        allocatable_cpu: quantity("cpu")?,
+        allocatable_memory: quantity("memory")?,
+    })
+}
+
+/// Node and container requests of a scheduled pod
+fn pod_placement(pod: &Pod) -> Option<PodPlacement> {
+    let spec = pod.spec.as_ref()?;
+    let containers = spec
+        .containers
+        .iter()
+        .map(|container| {
+            let requests = container
+                .resources
+                .as_ref()
+                .and_then(|r| r.requests.as_ref());
+            let quantity = |key: &str| {
+                requests
+                    .and_then(|req| req.get(key))
+                    .and_then(|q| Quantity::parse(&q.0).ok())
+            };
+            ContainerResources {
+                name: container.name.clone(),
+                cpu_request: quantity("cpu"),
+                cpu_limit: None,
+                memory_request: quantity("memory"),
+                memory_limit: None,
+            }
+        })
+        .collect();
+
+    Some(PodPlacement {
+        namespace: pod.metadata.namespace.clone()?,
+        name: pod.metadata.name.clone()?,
+        node: spec.node_name.clone()?,
+        containers,
+    })
+}
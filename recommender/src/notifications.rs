@@ -0,0 +1,247 @@
+//! Chat notifications after an apply, posted to Slack or Microsoft Teams webhooks
+//!
+//! The message lists the changed containers, the estimated savings and the pull
+//! request to review, so nobody has to read the job's logs. Webhook URLs carry
+//! their secret in the path and are never logged, only their host.
+
+use log::info;
+use reqwest::Client;
+use serde_json::{Value, json};
+use url::Url;
+
+use crate::apply::ApplyOutcome;
+use crate::config::NotifyFormat;
+use crate::cost::{format_dollars, total_monthly_savings};
+use crate::error::{ConfigError, RecommenderError, Result};
+use crate::http::shared_client;
+use crate::quantity::Quantity;
+use crate::recommender::{DayProfile, ResourceRecommendation};
+use crate::redact::register_secret;
+
+/// Changed containers listed in a message, the rest are counted
+const MAX_LISTED_CONTAINERS: usize = 10;
+
+/// Summary of one apply, rendered as a Slack or Teams message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    /// One line per changed container, then the savings
+    pub lines: Vec<String>,
+    pub pr_url: Option<String>,
+}
+
+impl Notification {
+    /// Summary of the `recommendations` applied by `outcome` in `cluster`
+    ///
+    /// Recommendations of workloads the apply didn't update are left out.
+    pub fn applied(
+        cluster: &str,
+        recommendations: &[ResourceRecommendation],
+        outcome: &ApplyOutcome,
+    ) -> Self {
+        let applied: Vec<ResourceRecommendation> = recommendations
+            .iter()
+            .filter(|rec| {
+                rec.day_profile != Some(DayProfile::Weekend)
+                    && outcome
+                        .updated
+                        .contains_key(&format!("{}/{}", rec.namespace, rec.deployment))
+            })
+            .cloned()
+            .collect();
+
+        let title = match &outcome.pr_url {
+            Some(_) => format!(
+                "Rightsizing pull request for {} workload(s) in {}",
+                outcome.updated.len(),
+                cluster
+            ),
+            None => format!(
+                "Rightsizing applied to {} workload(s) in {}",
+                outcome.updated.len(),
+                cluster
+            ),
+        };
+
+        let mut lines: Vec<String> = applied
+            .iter()
+            .take(MAX_LISTED_CONTAINERS)
+            .map(|rec| {
+                format!(
+                    "{}/{}/{}: CPU {} → {}, memory {} → {}",
+                    rec.namespace,
+                    rec.deployment,
+                    rec.container,
+                    current(rec.current_cpu_request),
+                    rec.recommended_cpu_request,
+                    current(rec.current_memory_request),
+                    rec.recommended_memory_request
+                )
+            })
+            .collect();
+        if applied.len() > MAX_LISTED_CONTAINERS {
+            lines.push(format!(
+                "and {} more containers",
+                applied.len() - MAX_LISTED_CONTAINERS
+            ));
+        }
+        if let Some(savings) = total_monthly_savings(&applied) {
+            lines.push(format!(
+                "Estimated savings: {}/month",
+                format_dollars(savings)
+            ));
+        }
+        if outcome.pr_url.is_none()
+            && let Some(branch) = &outcome.branch
+        {
+            lines.push(format!("Committed to branch {}", branch));
+        }
+
+        Self {
+            title,
+            lines,
+            pr_url: outcome.pr_url.clone(),
+        }
+    }
+
+    /// Incoming-webhook payload with a header, the lines and a link to the PR
+    pub fn slack_payload(&self) -> Value {
+        let mut text = self
+            .lines
+            .iter()
+            .map(|line| format!("• {}", slack_escape(line)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Some(url) = &self.pr_url {
+            text.push_str(&format!("\n<{}|Review the pull request>", url));
+        }
+        json!({
+            "text": self.title,
+            "blocks": [
+                { "type": "header", "text": { "type": "plain_text", "text": self.title } },
+                { "type": "section", "text": { "type": "mrkdwn", "text": text } },
+            ],
+        })
+    }
+
+    /// Adaptive card for Teams Workflows webhooks, the PR as an action button
+    pub fn teams_payload(&self) -> Value {
+        let mut body = vec![json!({
+            "type": "TextBlock",
+            "size": "Medium",
+            "weight": "Bolder",
+            "text": self.title,
+            "wrap": true,
+        })];
+        body.extend(self.lines.iter().map(
+            |line| json!({ "type": "TextBlock", "text": line, "wrap": true, "spacing": "None" }),
+        ));
+        let actions: Vec<Value> = self
+            .pr_url
+            .iter()
+            .map(|url| {
+                json!({
+                    "type": "Action.OpenUrl",
+                    "title": "Review the pull request",
+                    "url": url,
+                })
+            })
+            .collect();
+        json!({
+            "type": "message",
+            "attachments": [{
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": body,
+                    "actions": actions,
+                },
+            }],
+        })
+    }
+}
+
+/// Posts [`Notification`]s to a chat webhook
+pub struct WebhookNotifier {
+    client: Client,
+    url: Url,
+    format: NotifyFormat,
+}
+
+impl WebhookNotifier {
+    /// Post to `url` in `format`, or the format its host belongs to
+    pub fn new(url: Url, format: Option<NotifyFormat>) -> Result<Self> {
+        let Some(format) = format.or_else(|| NotifyFormat::for_url(&url)) else {
+            return Err(ConfigError::InvalidValue(format!(
+                "Can't tell the payload format of webhooks on {}, set --notify-format",
+                url.host_str().unwrap_or_default()
+            ))
+            .into());
+        };
+        // The path is the webhook's credential
+        register_secret(url.path());
+        Ok(Self {
+            client: shared_client(),
+            url,
+            format,
+        })
+    }
+
+    /// Send requests through `client` instead of the shared one
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Host and format, safe to log unlike the URL
+    pub fn describe(&self) -> String {
+        format!(
+            "{} webhook on {}",
+            self.format,
+            self.url.host_str().unwrap_or_default()
+        )
+    }
+
+    pub async fn send(&self, notification: &Notification) -> Result<()> {
+        let payload = match self.format {
+            NotifyFormat::Slack => notification.slack_payload(),
+            NotifyFormat::Teams => notification.teams_payload(),
+        };
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| {
+                RecommenderError::Network(format!(
+                    "Failed to post to the {}: {}",
+                    self.describe(),
+                    e.without_url()
+                ))
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(RecommenderError::Network(format!(
+                "The {} returned {}",
+                self.describe(),
+                status
+            )));
+        }
+        info!("Posted the apply summary to the {}", self.describe());
+        Ok(())
+    }
+}
+
+fn current(quantity: Option<Quantity>) -> String {
+    quantity.map_or_else(|| "unset".to_string(), |q| q.to_string())
+}
+
+/// `text` with the characters Slack's mrkdwn treats as control sequences escaped
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,306 @@
+//! Pushing the recommender output to an OCI registry as an artifact
+//!
+//! The output JSON is the single layer of an image manifest with an empty config,
+//! so it can be pulled with `oras pull` or any client of the distribution API.
+
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use reqwest::header::{CONTENT_TYPE, LOCATION, WWW_AUTHENTICATE};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::config::OciReference;
+use crate::error::{RecommenderError, Result};
+use crate::http::shared_client;
+
+/// Type of the pushed artifact and media type of its layer
+pub const ARTIFACT_TYPE: &str = "application/vnd.k8s-autorightsizing.recommendations.v1+json";
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// Config of artifacts that don't need one, the two bytes `{}`
+const EMPTY_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+
+/// Pushes artifacts to one repository, authenticating when the registry asks to
+///
+/// Registries answering with a `Bearer` challenge get a token from their realm
+/// for `pull,push` on the repository, `Basic` ones get the credentials directly.
+pub struct OciPusher {
+    client: Client,
+    reference: OciReference,
+    credentials: Option<(String, String)>,
+    /// How requests authenticate once the registry challenged one
+    authorization: Mutex<Option<Authorization>>,
+}
+
+#[derive(Clone)]
+enum Authorization {
+    Basic,
+    Bearer(String),
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+impl OciPusher {
+    pub fn new(reference: OciReference) -> Self {
+        Self {
+            client: shared_client(),
+            reference,
+            credentials: None,
+            authorization: Mutex::new(None),
+        }
+    }
+
+    /// Username and password or token for the registry, anonymous without
+    pub fn with_credentials(mut self, username: String, password: String) -> Self {
+        crate::redact::register_secret(&password);
+        self.credentials = Some((username, password));
+        self
+    }
+
+    /// Send requests through `client` instead of the shared one
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Push `content` as the artifact's single layer, returning the manifest digest
+    ///
+    /// The layer is titled `file_name` for clients extracting it, e.g. `oras pull`.
+    pub async fn push(
+        &self,
+        content: Vec<u8>,
+        file_name: &str,
+        created: DateTime<Utc>,
+    ) -> Result<String> {
+        let config = b"{}".to_vec();
+        let config_digest = digest(&config);
+        let config_size = config.len();
+        let layer_digest = digest(&content);
+        let layer_size = content.len();
+        self.upload_blob(config, &config_digest).await?;
+        self.upload_blob(content, &layer_digest).await?;
+
+        let manifest = json!({
+            "schemaVersion": 2,
+            "mediaType": MANIFEST_MEDIA_TYPE,
+            "artifactType": ARTIFACT_TYPE,
+            "config": {
+                "mediaType": EMPTY_MEDIA_TYPE,
+                "digest": config_digest,
+                "size": config_size,
+            },
+            "layers": [{
+                "mediaType": ARTIFACT_TYPE,
+                "digest": layer_digest,
+                "size": layer_size,
+                "annotations": { "org.opencontainers.image.title": file_name },
+            }],
+            "annotations": { "org.opencontainers.image.created": created.to_rfc3339() },
+        });
+        let manifest = serde_json::to_vec(&manifest).map_err(|e| {
+            RecommenderError::Other(format!("Failed to serialize OCI manifest: {}", e))
+        })?;
+        let manifest_digest = digest(&manifest);
+
+        let url = self.url(&format!("manifests/{}", self.reference.tag))?;
+        self.send(|| {
+            self.client
+                .put(url.clone())
+                .header(CONTENT_TYPE, MANIFEST_MEDIA_TYPE)
+                .body(manifest.clone())
+        })
+        .await?;
+
+        info!("Pushed {}@{}", self.reference, manifest_digest);
+        Ok(manifest_digest)
+    }
+
+    /// Upload a blob unless the repository has it already
+    async fn upload_blob(&self, content: Vec<u8>, digest: &str) -> Result<()> {
+        let blob = self.url(&format!("blobs/{}", digest))?;
+        let response = self
+            .send_unchecked(|| self.client.request(Method::HEAD, blob.clone()))
+            .await?;
+        if response.status().is_success() {
+            debug!("Blob {} already in {}", digest, self.reference);
+            return Ok(());
+        }
+
+        // Monolithic upload: open a session, then put the whole blob to its location
+        let uploads = self.url("blobs/uploads/")?;
+        let response = self.send(|| self.client.post(uploads.clone())).await?;
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| {
+                RecommenderError::Network("Registry returned no upload location".to_string())
+            })?;
+        let mut location = uploads
+            .join(location)
+            .map_err(|e| RecommenderError::Network(format!("Invalid upload location: {}", e)))?;
+        location.query_pairs_mut().append_pair("digest", digest);
+
+        self.send(|| {
+            self.client
+                .put(location.clone())
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(content.clone())
+        })
+        .await?;
+        debug!("Uploaded blob {} to {}", digest, self.reference);
+        Ok(())
+    }
+
+    /// `/v2/<repository>/<path>` on the registry
+    fn url(&self, path: &str) -> Result<Url> {
+        self.reference
+            .registry
+            .join(&format!("/v2/{}/{}", self.reference.repository, path))
+            .map_err(|e| RecommenderError::InvalidInput(format!("Invalid registry URL: {}", e)))
+    }
+
+    /// Send a request, failing on any status but success
+    async fn send(&self, request: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let response = self.send_unchecked(request).await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        Err(RecommenderError::HttpStatus {
+            status: status.as_u16(),
+            message: response.text().await.unwrap_or_default(),
+        })
+    }
+
+    /// Send a request, answering an authentication challenge once
+    async fn send_unchecked(&self, request: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let response = self.execute(request()).await?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let challenge = response
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        self.authenticate(&challenge).await?;
+        self.execute(request()).await
+    }
+
+    async fn execute(&self, mut request: RequestBuilder) -> Result<Response> {
+        let authorization = self
+            .authorization
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        match (authorization, &self.credentials) {
+            (Some(Authorization::Bearer(token)), _) => request = request.bearer_auth(token),
+            (Some(Authorization::Basic), Some((username, password))) => {
+                request = request.basic_auth(username, Some(password));
+            }
+            _ => {}
+        }
+        request
+            .send()
+            .await
+            .map_err(|e| RecommenderError::Network(format!("Registry request failed: {}", e)))
+    }
+
+    /// Authenticate the way the registry's challenge asks for
+    async fn authenticate(&self, challenge: &str) -> Result<()> {
+        let authorization = match challenge.split_once(' ') {
+            Some((scheme, params)) if scheme.eq_ignore_ascii_case("bearer") => {
+                let params = challenge_params(params);
+                let realm = params
+                    .iter()
+                    .find(|(key, _)| key == "realm")
+                    .map(|(_, value)| value.as_str())
+                    .ok_or_else(|| {
+                        RecommenderError::Network(format!("No realm in challenge {}", challenge))
+                    })?;
+                let mut realm = Url::parse(realm)
+                    .map_err(|e| RecommenderError::Network(format!("Invalid realm: {}", e)))?;
+                {
+                    let mut query = realm.query_pairs_mut();
+                    if let Some((_, service)) = params.iter().find(|(key, _)| key == "service") {
+                        query.append_pair("service", service);
+                    }
+                    query.append_pair(
+                        "scope",
+                        &format!("repository:{}:pull,push", self.reference.repository),
+                    );
+                }
+
+                let mut request = self.client.get(realm);
+                if let Some((username, password)) = &self.credentials {
+                    request = request.basic_auth(username, Some(password));
+                }
+                let response = request.send().await.map_err(|e| {
+                    RecommenderError::Network(format!("Registry token request failed: {}", e))
+                })?;
+                let status = response.status();
+                if !status.is_success() {
+                    return Err(RecommenderError::HttpStatus {
+                        status: status.as_u16(),
+                        message: response.text().await.unwrap_or_default(),
+                    });
+                }
+                let token: TokenResponse = response.json().await.map_err(|e| {
+                    RecommenderError::Parse(format!("Invalid registry token response: {}", e))
+                })?;
+                let token = token.token.or(token.access_token).ok_or_else(|| {
+                    RecommenderError::Parse("Registry token response has no token".to_string())
+                })?;
+                crate::redact::register_secret(&token);
+                Authorization::Bearer(token)
+            }
+            _ => {
+                if self.credentials.is_none() {
+                    return Err(RecommenderError::InvalidInput(format!(
+                        "{} requires credentials, set --oci-username and --oci-password",
+                        self.reference.registry
+                    )));
+                }
+                Authorization::Basic
+            }
+        };
+
+        *self.authorization.lock().unwrap_or_else(|e| e.into_inner()) = Some(authorization);
+        Ok(())
+    }
+}
+
+/// `sha256:<hex>` digest of `content`
+fn digest(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+/// `key="value"` pairs of an authentication challenge
+fn challenge_params(params: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    let mut rest = params.trim();
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_string();
+        let value = value.trim_start();
+        let (value, remainder) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        pairs.push((key, value.to_string()));
+        rest = remainder;
+    }
+    pairs
+}
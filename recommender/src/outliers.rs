@@ -0,0 +1,115 @@
+//! Detecting short usage spikes, e.g. a retry storm during an incident
+//!
+//! Spikes are found in a usage profile holding one value per query step, the peak
+//! over all pods. Steps more than `threshold` robust standard deviations above the
+//! median are high, and a run of consecutive high steps no longer than the maximum
+//! duration is a spike. The standard deviation is estimated from the median
+//! absolute deviation and kept at a tenth of the median or more, so bumps in flat
+//! usage don't count. Runs touching either end of the profile are kept, as their
+//! true length is unknown.
+
+use std::collections::BTreeMap;
+
+use crate::stats::{Interpolation, quantile};
+
+/// Scales the median absolute deviation to a standard deviation for normal data
+const MAD_TO_STDDEV: f64 = 1.4826;
+
+/// Smallest standard deviation used, as a share of the median
+const MIN_RELATIVE_STDDEV: f64 = 0.1;
+
+/// Profiles with fewer steps are too short to tell spikes from load
+const MIN_PROFILE_STEPS: usize = 30;
+
+/// A run of consecutive query steps with unusually high usage
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spike {
+    pub first_step: i64,
+    pub last_step: i64,
+    /// Highest usage during the run
+    pub peak: f64,
+}
+
+/// Spikes in a usage profile and the levels they were detected against
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpikeScan {
+    pub median: f64,
+    /// Usage above this counts as high
+    pub threshold: f64,
+    pub spikes: Vec<Spike>,
+}
+
+impl SpikeScan {
+    /// The spike containing `step`, if any
+    pub fn spike_at(&self, step: i64) -> Option<&Spike> {
+        let index = self.spikes.partition_point(|spike| spike.last_step < step);
+        self.spikes
+            .get(index)
+            .filter(|spike| spike.first_step <= step)
+    }
+}
+
+/// Find runs of at most `max_steps` steps more than `threshold` robust standard
+/// deviations above the median of `profile`, which maps step indices to usage
+pub fn find_spikes(profile: &BTreeMap<i64, f64>, threshold: f64, max_steps: usize) -> SpikeScan {
+    if profile.len() < MIN_PROFILE_STEPS {
+        return SpikeScan::default();
+    }
+
+    let mut values: Vec<f64> = profile.values().copied().collect();
+    values.sort_by(f64::total_cmp);
+    let median = quantile(&values, 0.5, Interpolation::Linear);
+    let mut deviations: Vec<f64> = values.iter().map(|v| (v - median).abs()).collect();
+    deviations.sort_by(f64::total_cmp);
+    let stddev = (quantile(&deviations, 0.5, Interpolation::Linear) * MAD_TO_STDDEV)
+        .max(median * MIN_RELATIVE_STDDEV);
+    let threshold = median + threshold * stddev;
+    if stddev <= 0.0 {
+        // Idle containers have nothing to compare against
+        return SpikeScan {
+            median,
+            threshold,
+            spikes: Vec::new(),
+        };
+    }
+
+    let (Some(&first), Some(&last)) = (profile.keys().next(), profile.keys().next_back()) else {
+        return SpikeScan::default();
+    };
+
+    let mut spikes = Vec::new();
+    let mut run: Option<Spike> = None;
+    for (&step, &value) in profile {
+        let high = value > threshold;
+        match &mut run {
+            Some(current) if high && step == current.last_step + 1 => {
+                current.last_step = step;
+                current.peak = current.peak.max(value);
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(done) = run.take() {
+            spikes.push(done);
+        }
+        if high {
+            run = Some(Spike {
+                first_step: step,
+                last_step: step,
+                peak: value,
+            });
+        }
+    }
+    spikes.extend(run);
+
+    spikes.retain(|spike| {
+        let steps = (spike.last_step - spike.first_step + 1) as usize;
+        steps <= max_steps && spike.first_step != first && spike.last_step != last
+    });
+
+    SpikeScan {
+        median,
+        threshold,
+        spikes,
+    }
+}
@@ -0,0 +1,493 @@
+use std::fmt::{self, Write as _};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::autoscaling::AutoscalingImpact;
+use crate::chargeback::{escape_csv, format_bytes};
+use crate::clock::Timezone;
+use crate::config::{RecommenderConfig, ResourceBounds, auto_query_step};
+use crate::cost::{ResourcePrices, format_dollars, total_monthly_savings};
+use crate::error::{PrometheusError, RecommenderError, Result};
+use crate::nodes::NodePoolReport;
+use crate::policy::PolicyViolation;
+use crate::quantity::{Quantity, display_optional};
+use crate::quota::NamespaceQuota;
+use crate::recommender::ResourceRecommendation;
+
+/// Top-level output structure containing metadata and recommendations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecommenderOutput {
+    pub metadata: OutputMetadata,
+    pub recommendations: Vec<ResourceRecommendation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy_violations: Vec<PolicyViolation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedWorkload>,
+    /// Suggested ResourceQuota per namespace, only when requested
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespace_quotas: Vec<NamespaceQuota>,
+    /// Node counts after rightsizing, only when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_pools: Option<NodePoolReport>,
+    /// Instance mix and cost after rightsizing, only when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub autoscaling: Option<AutoscalingImpact>,
+}
+
+/// Workload or container left out of the recommendations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkippedWorkload {
+    pub namespace: String,
+    pub deployment: String,
+    /// None when the whole workload was skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+    pub reason: SkipReason,
+}
+
+impl fmt::Display for SkippedWorkload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.namespace, self.deployment)?;
+        if let Some(container) = &self.container {
+            write!(f, "/{}", container)?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+/// Why a workload got no recommendation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// No pods are running, so the window holds no usage to size from
+    ScaledToZero,
+    /// Created after the lookback window started, so only part of it has data
+    CreatedWithinWindow { created_at: DateTime<Utc> },
+    /// The metrics backend returned no samples
+    NoData,
+    /// Samples or specs could not be parsed
+    InvalidData { error: String },
+    /// Querying the metrics backend failed
+    QueryFailed { error: String },
+    /// A VerticalPodAutoscaler changes the requests, see `--vpa`
+    ManagedByVpa { vpa: String, update_mode: String },
+}
+
+impl SkipReason {
+    /// Classify the error that made a container's analysis fail
+    pub fn from_error(error: &RecommenderError) -> Self {
+        match error {
+            RecommenderError::InsufficientData(_)
+            | RecommenderError::Prometheus(PrometheusError::NoData(_)) => SkipReason::NoData,
+            RecommenderError::Parse(_)
+            | RecommenderError::Yaml(_)
+            | RecommenderError::Prometheus(PrometheusError::InvalidResponse(_)) => {
+                SkipReason::InvalidData {
+                    error: error.to_string(),
+                }
+            }
+            _ => SkipReason::QueryFailed {
+                error: error.to_string(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::ScaledToZero => f.write_str("scaled to zero replicas"),
+            SkipReason::CreatedWithinWindow { created_at } => write!(
+                f,
+                "created at {}, within the lookback window",
+                created_at.to_rfc3339()
+            ),
+            SkipReason::NoData => f.write_str("no usage data in the lookback window"),
+            SkipReason::InvalidData { error } => write!(f, "invalid data: {}", error),
+            SkipReason::QueryFailed { error } => write!(f, "query failed: {}", error),
+            SkipReason::ManagedByVpa { vpa, update_mode } => write!(
+                f,
+                "managed by VerticalPodAutoscaler {} in {} mode",
+                vpa, update_mode
+            ),
+        }
+    }
+}
+
+/// Metadata about the recommendation generation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutputMetadata {
+    pub timestamp: String,
+    pub namespace: Option<String>,
+    pub lookback_hours: f64,
+    pub total_deployments: usize,
+    pub total_containers: usize,
+    pub percentiles_used: PercentileConfig,
+    /// Resolution of the usage queries, 0 in outputs written before it was recorded
+    #[serde(default)]
+    pub query_step_seconds: u64,
+    /// Workloads and containers in `skipped`
+    #[serde(default)]
+    pub total_skipped: usize,
+    /// Prices the savings were estimated at, None when they weren't estimated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prices: Option<ResourcePrices>,
+    /// Dollars a month saved by all recommendations together
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_monthly_savings: Option<f64>,
+    /// Floors and caps the recommendations were clamped to
+    #[serde(default, skip_serializing_if = "ResourceBounds::is_empty")]
+    pub bounds: ResourceBounds,
+}
+
+/// Configuration for percentiles used in recommendations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PercentileConfig {
+    pub cpu_request: f64,
+    pub cpu_limit: f64,
+    pub memory_request: f64,
+    pub memory_limit: f64,
+    pub safety_margin: f64,
+}
+
+impl RecommenderOutput {
+    /// Create a new RecommenderOutput
+    ///
+    /// Prefer [`RecommenderOutput::builder`], this is kept for compatibility.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        namespace: Option<String>,
+        lookback_hours: f64,
+        total_deployments: usize,
+        cpu_request_percentile: f64,
+        cpu_limit_percentile: f64,
+        memory_request_percentile: f64,
+        memory_limit_percentile: f64,
+        safety_margin: f64,
+        recommendations: Vec<ResourceRecommendation>,
+    ) -> Self {
+        let total_containers = recommendations.len();
+
+        Self {
+            metadata: OutputMetadata {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                namespace,
+                lookback_hours,
+                total_deployments,
+                total_containers,
+                percentiles_used: PercentileConfig {
+                    cpu_request: cpu_request_percentile,
+                    cpu_limit: cpu_limit_percentile,
+                    memory_request: memory_request_percentile,
+                    memory_limit: memory_limit_percentile,
+                    safety_margin,
+                },
+                query_step_seconds: auto_query_step(lookback_hours).as_secs(),
+                total_skipped: 0,
+                prices: None,
+                estimated_monthly_savings: None,
+                bounds: ResourceBounds::default(),
+            },
+            recommendations,
+            policy_violations: Vec::new(),
+            skipped: Vec::new(),
+            namespace_quotas: Vec::new(),
+            node_pools: None,
+            autoscaling: None,
+        }
+    }
+
+    /// Start building an output with named setters
+    pub fn builder() -> RecommenderOutputBuilder {
+        RecommenderOutputBuilder::default()
+    }
+
+    /// One row per recommendation with the TUI table's columns and the usage stats
+    ///
+    /// Quantities are written like in manifests, e.g. `250m` and `512Mi`, usage in
+    /// cores and bytes.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(CSV_HEADER);
+        for rec in &self.recommendations {
+            let fields = [
+                rec.namespace.clone(),
+                rec.deployment.clone(),
+                rec.container.clone(),
+                rec.day_profile
+                    .map(|days| days.to_string())
+                    .unwrap_or_default(),
+                optional(rec.current_cpu_request),
+                rec.recommended_cpu_request.to_string(),
+                optional(rec.current_cpu_limit),
+                rec.recommended_cpu_limit.to_string(),
+                optional(rec.current_memory_request),
+                rec.recommended_memory_request.to_string(),
+                optional(rec.current_memory_limit),
+                rec.recommended_memory_limit.to_string(),
+                rec.estimated_monthly_savings
+                    .map(|savings| format!("{:.2}", savings))
+                    .unwrap_or_default(),
+                format!("{:.4}", rec.cpu_usage_stats.avg),
+                format!("{:.4}", rec.cpu_usage_stats.p50),
+                format!("{:.4}", rec.cpu_usage_stats.p95),
+                format!("{:.4}", rec.cpu_usage_stats.p99),
+                format!("{:.4}", rec.cpu_usage_stats.max),
+                format!("{:.0}", rec.memory_usage_stats.avg),
+                format!("{:.0}", rec.memory_usage_stats.p50),
+                format!("{:.0}", rec.memory_usage_stats.p95),
+                format!("{:.0}", rec.memory_usage_stats.p99),
+                format!("{:.0}", rec.memory_usage_stats.max),
+                format!("{:.2}", rec.confidence),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| escape_csv(f)).collect();
+            out.push_str(&row.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Summary and table for PR descriptions, usage as p50 / p95 / p99
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "## Resource recommendations\n\n{} containers in {} deployments, {}h lookback, generated {}\n",
+            self.metadata.total_containers,
+            self.metadata.total_deployments,
+            self.metadata.lookback_hours,
+            self.metadata.timestamp
+        );
+        if let Some(savings) = self.metadata.estimated_monthly_savings {
+            let _ = writeln!(
+                out,
+                "\nEstimated savings: **{}/month**",
+                format_dollars(savings)
+            );
+        }
+        out.push_str("\n| Namespace | Deployment | Container | CPU request | CPU limit | Memory request | Memory limit | Savings/mo | CPU usage | Memory usage | Confidence |\n");
+        out.push_str("|---|---|---|---|---|---|---|---:|---|---|---:|\n");
+        for rec in &self.recommendations {
+            let container = match rec.day_profile {
+                Some(days) => format!("{} ({})", rec.container, days),
+                None => rec.container.clone(),
+            };
+            let change = |current: Option<Quantity>, recommended: Quantity| {
+                format!("{} → {}", display_optional(current), recommended)
+            };
+            let cpu = &rec.cpu_usage_stats;
+            let memory = &rec.memory_usage_stats;
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} | {} | {:.3} / {:.3} / {:.3} | {} / {} / {} | {:.0}% |",
+                rec.namespace,
+                rec.deployment,
+                container,
+                change(rec.current_cpu_request, rec.recommended_cpu_request),
+                change(rec.current_cpu_limit, rec.recommended_cpu_limit),
+                change(rec.current_memory_request, rec.recommended_memory_request),
+                change(rec.current_memory_limit, rec.recommended_memory_limit),
+                rec.estimated_monthly_savings
+                    .map(format_dollars)
+                    .unwrap_or_else(|| "-".to_string()),
+                cpu.p50,
+                cpu.p95,
+                cpu.p99,
+                format_bytes(memory.p50),
+                format_bytes(memory.p95),
+                format_bytes(memory.p99),
+                rec.confidence * 100.0
+            );
+        }
+        if let Some(savings) = self.metadata.estimated_monthly_savings {
+            let _ = writeln!(
+                out,
+                "| **Total** | | | | | | | **{}** | | | |",
+                format_dollars(savings)
+            );
+        }
+
+        if !self.skipped.is_empty() {
+            let _ = writeln!(out, "\n### Skipped\n");
+            for skipped in &self.skipped {
+                let _ = writeln!(out, "- {}", skipped);
+            }
+        }
+        out
+    }
+}
+
+const CSV_HEADER: &str = "namespace,deployment,container,day_profile,current_cpu_request,recommended_cpu_request,current_cpu_limit,recommended_cpu_limit,current_memory_request,recommended_memory_request,current_memory_limit,recommended_memory_limit,estimated_monthly_savings,cpu_avg,cpu_p50,cpu_p95,cpu_p99,cpu_max,memory_avg,memory_p50,memory_p95,memory_p99,memory_max,confidence\n";
+
+/// CSV cell of an optional quantity, empty when unset
+fn optional(quantity: Option<Quantity>) -> String {
+    quantity.map(|q| q.to_string()).unwrap_or_default()
+}
+
+/// Builder for [`RecommenderOutput`]
+#[derive(Debug, Clone, Default)]
+pub struct RecommenderOutputBuilder {
+    namespace: Option<String>,
+    config: RecommenderConfig,
+    total_deployments: usize,
+    recommendations: Vec<ResourceRecommendation>,
+    policy_violations: Vec<PolicyViolation>,
+    skipped: Vec<SkippedWorkload>,
+    namespace_quotas: Vec<NamespaceQuota>,
+    generated_at: Option<DateTime<Utc>>,
+    timezone: Timezone,
+    prices: Option<ResourcePrices>,
+}
+
+impl RecommenderOutputBuilder {
+    pub fn namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Take lookback, percentiles and safety margin from the config used for the run
+    pub fn config(mut self, config: &RecommenderConfig) -> Self {
+        self.config = config.clone();
+        self
+    }
+
+    pub fn total_deployments(mut self, total_deployments: usize) -> Self {
+        self.total_deployments = total_deployments;
+        self
+    }
+
+    pub fn recommendations(mut self, recommendations: Vec<ResourceRecommendation>) -> Self {
+        self.recommendations = recommendations;
+        self
+    }
+
+    pub fn policy_violations(mut self, policy_violations: Vec<PolicyViolation>) -> Self {
+        self.policy_violations = policy_violations;
+        self
+    }
+
+    /// Workloads that were not analyzed, see [`SkippedWorkload`]
+    pub fn skipped(mut self, skipped: Vec<SkippedWorkload>) -> Self {
+        self.skipped = skipped;
+        self
+    }
+
+    /// Namespace rollups, see [`crate::quota::namespace_quotas`]
+    pub fn namespace_quotas(mut self, namespace_quotas: Vec<NamespaceQuota>) -> Self {
+        self.namespace_quotas = namespace_quotas;
+        self
+    }
+
+    /// Time recorded in the metadata, the current time if not set
+    pub fn generated_at(mut self, generated_at: DateTime<Utc>) -> Self {
+        self.generated_at = Some(generated_at);
+        self
+    }
+
+    /// Zone of the metadata timestamp, written as RFC 3339 with its offset
+    pub fn timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Estimate the monthly savings of every recommendation at `prices`
+    pub fn prices(mut self, prices: Option<ResourcePrices>) -> Self {
+        self.prices = prices;
+        self
+    }
+
+    pub fn build(mut self) -> RecommenderOutput {
+        if let Some(prices) = &self.prices {
+            prices.estimate(&mut self.recommendations);
+        }
+        let mut output = RecommenderOutput::new(
+            self.namespace,
+            self.config.lookback_hours,
+            self.total_deployments,
+            self.config.cpu_request_percentile,
+            self.config.cpu_limit_percentile,
+            self.config.memory_request_percentile,
+            self.config.memory_limit_percentile,
+            self.config.safety_margin,
+            self.recommendations,
+        );
+        output.policy_violations = self.policy_violations;
+        output.metadata.total_skipped = self.skipped.len();
+        output.skipped = self.skipped;
+        output.namespace_quotas = self.namespace_quotas;
+        output.metadata.query_step_seconds = self.config.effective_query_step().as_secs();
+        output.metadata.bounds = self.config.bounds;
+        if self.prices.is_some() {
+            output.metadata.prices = self.prices;
+            output.metadata.estimated_monthly_savings =
+                total_monthly_savings(&output.recommendations);
+        }
+        let generated_at = self.generated_at.unwrap_or_else(Utc::now);
+        output.metadata.timestamp = self.timezone.convert(generated_at).to_rfc3339();
+        output
+    }
+}
+
+/// Index of the per-namespace files written by [`write_namespace_outputs`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceIndex {
+    pub timestamp: String,
+    pub lookback_hours: f64,
+    pub namespaces: Vec<NamespaceIndexEntry>,
+}
+
+/// One namespace's file and totals in the [`NamespaceIndex`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceIndexEntry {
+    pub namespace: String,
+    /// File name relative to the index
+    pub file: String,
+    pub total_deployments: usize,
+    pub total_containers: usize,
+    pub total_skipped: usize,
+    pub policy_violations: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_monthly_savings: Option<f64>,
+}
+
+/// File name of the index written next to the per-namespace outputs
+pub const NAMESPACE_INDEX_FILE: &str = "index.json";
+
+/// Write each output as `recommendations-<namespace>.json` and their index into `dir`
+///
+/// `timestamp` and `lookback_hours` of the index are the combined run's.
+pub fn write_namespace_outputs(
+    outputs: &[RecommenderOutput],
+    combined: &OutputMetadata,
+    dir: &Path,
+) -> Result<NamespaceIndex> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut namespaces = Vec::with_capacity(outputs.len());
+    for output in outputs {
+        let namespace = output.metadata.namespace.clone().unwrap_or_default();
+        let file = format!("recommendations-{}.json", namespace);
+        std::fs::write(dir.join(&file), to_json(output)?)?;
+        namespaces.push(NamespaceIndexEntry {
+            namespace,
+            file,
+            total_deployments: output.metadata.total_deployments,
+            total_containers: output.metadata.total_containers,
+            total_skipped: output.metadata.total_skipped,
+            policy_violations: output.policy_violations.len(),
+            estimated_monthly_savings: output.metadata.estimated_monthly_savings,
+        });
+    }
+
+    let index = NamespaceIndex {
+        timestamp: combined.timestamp.clone(),
+        lookback_hours: combined.lookback_hours,
+        namespaces,
+    };
+    std::fs::write(dir.join(NAMESPACE_INDEX_FILE), to_json(&index)?)?;
+    Ok(index)
+}
+
+fn to_json(value: &impl Serialize) -> Result<String> {
+    serde_json::to_string_pretty(value)
+        .map_err(|e| RecommenderError::Other(format!("Failed to serialize JSON: {}", e)))
+}
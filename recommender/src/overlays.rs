@@ -0,0 +1,192 @@
+//! Environment overlays, e.g. kustomize `overlays/dev`, `overlays/staging` and `overlays/prod`
+//!
+//! Recommendations come from the cluster of one environment, the source. They are
+//! written to its overlay as is, and to the overlays of environments with a
+//! `scale` multiplied by it. Other environments and the shared base are left alone.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::quantity::{round_cpu, round_memory};
+use crate::recommender::ResourceRecommendation;
+use crate::{ConfigError, Result};
+
+/// Overlay directory of one environment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentOverlay {
+    pub name: String,
+    /// Directory relative to the manifest root
+    pub path: PathBuf,
+    /// Multiplier on the source's recommendations, unset to leave the overlay alone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+}
+
+/// Environments of a manifest repository and the one recommendations come from
+///
+/// Example overlays file:
+///
+/// ```yaml
+/// source: prod
+/// environments:
+///   - name: prod
+///     path: overlays/prod
+///   - name: staging
+///     path: overlays/staging
+///     scale: 0.5
+///   - name: dev
+///     path: overlays/dev
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentOverlays {
+    /// Environment of the analyzed cluster
+    pub source: String,
+    pub environments: Vec<EnvironmentOverlay>,
+}
+
+/// Workloads updated in one environment's overlay
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentChanges {
+    pub environment: String,
+    /// 1.0 for the source environment
+    pub scale: f64,
+    /// Updated container count keyed by `namespace/deployment`
+    pub updated: HashMap<String, usize>,
+}
+
+impl EnvironmentChanges {
+    /// Environment name, with the scale for lower environments
+    pub fn label(&self) -> String {
+        if self.scale == 1.0 {
+            self.environment.clone()
+        } else {
+            format!(
+                "{} (scaled to {:.0}%)",
+                self.environment,
+                self.scale * 100.0
+            )
+        }
+    }
+
+    /// Updated `namespace/deployment` keys, sorted
+    pub fn workloads(&self) -> Vec<&str> {
+        let mut workloads: Vec<&str> = self.updated.keys().map(String::as_str).collect();
+        workloads.sort();
+        workloads
+    }
+}
+
+impl EnvironmentOverlays {
+    /// Load overlays from a YAML file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::FileError(format!(
+                "Failed to read environment overlays {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let overlays: EnvironmentOverlays = serde_yaml::from_str(&content)?;
+        overlays.validate()?;
+
+        info!(
+            "Loaded {} environment overlay(s) from {}, recommendations come from {}",
+            overlays.environments.len(),
+            path.display(),
+            overlays.source
+        );
+        Ok(overlays)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for (i, environment) in self.environments.iter().enumerate() {
+            if self.environments[..i]
+                .iter()
+                .any(|e| e.name == environment.name)
+            {
+                return Err(invalid(format!(
+                    "environment {} is listed twice",
+                    environment.name
+                )));
+            }
+            if !environment
+                .path
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(invalid(format!(
+                    "path {} of environment {} must stay inside the manifest root",
+                    environment.path.display(),
+                    environment.name
+                )));
+            }
+            if let Some(scale) = environment.scale
+                && !(scale.is_finite() && scale > 0.0)
+            {
+                return Err(invalid(format!(
+                    "scale of environment {} must be greater than 0, got {}",
+                    environment.name, scale
+                )));
+            }
+        }
+        if !self.environments.iter().any(|e| e.name == self.source) {
+            return Err(invalid(format!(
+                "source environment {} is not listed",
+                self.source
+            )));
+        }
+        Ok(())
+    }
+
+    /// Overlays to write and the multiplier for each, the source first
+    pub fn targets(&self) -> Vec<(&EnvironmentOverlay, f64)> {
+        let source = self.environments.iter().find(|e| e.name == self.source);
+        source
+            .map(|e| (e, 1.0))
+            .into_iter()
+            .chain(
+                self.environments
+                    .iter()
+                    .filter(|e| e.name != self.source)
+                    .filter_map(|e| e.scale.map(|scale| (e, scale))),
+            )
+            .collect()
+    }
+}
+
+fn invalid(message: String) -> crate::RecommenderError {
+    ConfigError::InvalidValue(format!("Invalid environment overlays: {}", message)).into()
+}
+
+/// `recommendation` with requests and limits multiplied by `scale`
+pub fn scale_recommendation(
+    recommendation: &ResourceRecommendation,
+    scale: f64,
+) -> ResourceRecommendation {
+    let mut scaled = recommendation.clone();
+    if scale != 1.0 {
+        scaled.recommended_cpu_request =
+            round_cpu(recommendation.recommended_cpu_request.value() * scale);
+        scaled.recommended_cpu_limit =
+            round_cpu(recommendation.recommended_cpu_limit.value() * scale);
+        scaled.recommended_memory_request =
+            round_memory(recommendation.recommended_memory_request.value() * scale);
+        scaled.recommended_memory_limit =
+            round_memory(recommendation.recommended_memory_limit.value() * scale);
+    }
+    scaled
+}
+
+/// Updated container counts summed over all environments
+pub fn merge_updates(changes: &[EnvironmentChanges]) -> HashMap<String, usize> {
+    let mut updates = HashMap::new();
+    for environment in changes {
+        for (key, count) in &environment.updated {
+            *updates.entry(key.clone()).or_insert(0) += count;
+        }
+    }
+    updates
+}
@@ -0,0 +1,128 @@
+//! PodDisruptionBudgets covering the analyzed Deployments
+//!
+//! A resource change rolls out new pods, taking up to the Deployment's
+//! `maxUnavailable` down at once. Rollouts don't go through the eviction API, so a
+//! PDB never blocks them, but one that has no room for that many disruptions is
+//! breached. Such rollouts are flagged, and workloads sharing a PDB are applied
+//! one after another.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
+use kube::Client;
+use kube::api::{Api, ListParams};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::KubernetesError::ApiError;
+use crate::Result;
+use crate::kubernetes::DeploymentResources;
+
+/// PDB and the disruptions it allows right now
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pdb {
+    pub name: String,
+    /// Pods the PDB applies to, None to match none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<LabelSelector>,
+    /// `status.disruptionsAllowed`, healthy pods beyond the budget's minimum
+    pub disruptions_allowed: i32,
+}
+
+/// A rollout taking down more pods at once than a PDB allows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisruptionRisk {
+    pub pdb: String,
+    pub disruptions_allowed: i32,
+    /// Pods the rollout may take down at once
+    pub rollout_unavailable: i32,
+}
+
+impl fmt::Display for DisruptionRisk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rolling out may take {} pod(s) down at once, PodDisruptionBudget {} allows {} disruption(s) now",
+            self.rollout_unavailable, self.pdb, self.disruptions_allowed
+        )
+    }
+}
+
+impl Pdb {
+    /// Whether the PDB applies to pods with `labels`
+    ///
+    /// An empty selector matches every pod in the namespace.
+    pub fn covers(&self, labels: &BTreeMap<String, String>) -> bool {
+        let Some(selector) = &self.selector else {
+            return false;
+        };
+        let match_labels = selector.match_labels.iter().flatten();
+        let expressions = selector.match_expressions.iter().flatten();
+
+        match_labels
+            .into_iter()
+            .all(|(key, value)| labels.get(key) == Some(value))
+            && expressions.into_iter().all(|expression| {
+                let value = labels.get(&expression.key);
+                let values = expression.values.as_deref().unwrap_or_default();
+                match expression.operator.as_str() {
+                    "In" => value.is_some_and(|v| values.contains(v)),
+                    "NotIn" => value.is_none_or(|v| !values.contains(v)),
+                    "Exists" => value.is_some(),
+                    "DoesNotExist" => value.is_none(),
+                    _ => false,
+                }
+            })
+    }
+
+    /// Namespace and PDB of a `policy/v1` object
+    fn from_object(pdb: PodDisruptionBudget) -> Option<(String, Self)> {
+        Some((
+            pdb.metadata.namespace?,
+            Self {
+                name: pdb.metadata.name?,
+                selector: pdb.spec.and_then(|spec| spec.selector),
+                disruptions_allowed: pdb.status.map_or(0, |status| status.disruptions_allowed),
+            },
+        ))
+    }
+}
+
+/// PDBs keyed by namespace
+pub async fn list_pdbs(
+    client: Client,
+    namespace: Option<&str>,
+) -> Result<HashMap<String, Vec<Pdb>>> {
+    let api: Api<PodDisruptionBudget> = match namespace {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    };
+    let pdbs = api.list(&ListParams::default()).await.map_err(ApiError)?;
+
+    let mut by_namespace: HashMap<String, Vec<Pdb>> = HashMap::new();
+    for (namespace, pdb) in pdbs.items.into_iter().filter_map(Pdb::from_object) {
+        by_namespace.entry(namespace).or_default().push(pdb);
+    }
+    info!(
+        "Found {} PodDisruptionBudgets",
+        by_namespace.values().map(Vec::len).sum::<usize>()
+    );
+    Ok(by_namespace)
+}
+
+/// The tightest PDB a rollout of `deployment` would breach, if any
+pub fn disruption_risk(deployment: &DeploymentResources) -> Option<DisruptionRisk> {
+    let rollout_unavailable = deployment.max_unavailable?;
+    deployment
+        .pdbs
+        .iter()
+        .filter(|pdb| pdb.disruptions_allowed < rollout_unavailable)
+        .min_by_key(|pdb| pdb.disruptions_allowed)
+        .map(|pdb| DisruptionRisk {
+            pdb: pdb.name.clone(),
+            disruptions_allowed: pdb.disruptions_allowed,
+            rollout_unavailable,
+        })
+}
@@ -0,0 +1,849 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use log::{info, warn};
+use tokio_util::sync::CancellationToken;
+
+use crate::clock::{SharedClock, SystemClock, Timezone};
+use crate::config::{MemoryMetric, RecommenderConfig, VpaMode};
+use crate::cost::ResourcePrices;
+use crate::error::{ConfigError, RecommenderError, Result};
+use crate::hpa::{Hpa, suggest_cpu_utilization};
+use crate::kubernetes::{DeploymentResources, StaticWorkloadSource, WorkloadKind, WorkloadSource};
+use crate::metric_variants::MetricVariants;
+use crate::metrics::MetricsProvider;
+use crate::metrics_server::MetricsServerSampler;
+use crate::output::{RecommenderOutput, SkipReason, SkippedWorkload};
+use crate::pdb::{DisruptionRisk, disruption_risk};
+use crate::policy::PolicySet;
+use crate::provenance::{Drift, Provenance};
+use crate::quantity::display_optional;
+use crate::quota::namespace_quotas;
+use crate::recommender::{
+    DayProfile, Progress, ProgressCallback, Recommender, ResourceRecommendation, clamp_to_bounds,
+    enforce_limits_above_requests, recommend_from_samples,
+};
+use crate::vpa::Vpa;
+
+/// Everything needed for one analysis run, see [`run_analysis`]
+pub struct AnalysisRequest {
+    pub workloads: Arc<dyn WorkloadSource>,
+    pub metrics: Arc<dyn MetricsProvider>,
+    pub config: RecommenderConfig,
+    /// Namespace recorded in the output metadata, None for all namespaces
+    pub namespace: Option<String>,
+    pub policies: Arc<PolicySet>,
+    pub cancel: CancellationToken,
+    pub clock: SharedClock,
+    /// Zone of the output timestamp
+    pub timezone: Timezone,
+    /// Headroom of the namespace quota rollup, None to leave it out
+    pub quota_headroom: Option<f64>,
+    pub on_progress: Option<ProgressCallback>,
+    /// Samples containers Prometheus has no usage for, None to skip them
+    pub metrics_server: Option<MetricsServerSampler>,
+    /// Prices to estimate the savings at, None to leave them out
+    pub prices: Option<ResourcePrices>,
+}
+
+impl AnalysisRequest {
+    /// Analyze `workloads` with the default config, no policies and the system clock
+    pub fn new(workloads: Arc<dyn WorkloadSource>, metrics: Arc<dyn MetricsProvider>) -> Self {
+        Self {
+            workloads,
+            metrics,
+            config: RecommenderConfig::default(),
+            namespace: None,
+            policies: Arc::default(),
+            cancel: CancellationToken::new(),
+            clock: SystemClock::shared(),
+            timezone: Timezone::Utc,
+            quota_headroom: None,
+            on_progress: None,
+            metrics_server: None,
+            prices: None,
+        }
+    }
+
+    pub fn config(mut self, config: RecommenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Policies enforced on the recommendations before they are returned
+    pub fn policies(mut self, policies: impl Into<Arc<PolicySet>>) -> Self {
+        self.policies = policies.into();
+        self
+    }
+
+    /// See [`Recommender::with_cancellation`]
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// See [`Recommender::with_clock`]
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Roll recommendations up into namespace quotas, see [`namespace_quotas`]
+    pub fn namespace_quotas(mut self, headroom: f64) -> Self {
+        self.quota_headroom = Some(headroom);
+        self
+    }
+
+    /// Recommend from a live metrics-server sample for containers without
+    /// Prometheus usage and workloads created within the lookback window
+    pub fn metrics_server_fallback(mut self, sampler: MetricsServerSampler) -> Self {
+        self.metrics_server = Some(sampler);
+        self
+    }
+
+    /// Estimate the monthly savings of the recommendations, see [`ResourcePrices`]
+    pub fn prices(mut self, prices: ResourcePrices) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+
+    /// See [`Recommender::on_progress`]
+    pub fn on_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+}
+
+/// Fluent entry point for embedding the analysis in another program
+///
+/// Needs neither the `cli` feature nor a logger: log records go to whatever
+/// `log` implementation the embedding program installed, if any.
+///
+/// ```no_run
+/// # async fn example(metrics: recommender::StaticMetricsProvider) -> recommender::Result<()> {
+/// use recommender::prelude::*;
+///
+/// let config = RecommenderConfig::builder().lookback_hours(72.0).build();
+/// let cluster = KubernetesConfig::new(String::new(), "us-east-1".into(), None, None);
+/// let loader = KubernetesLoader::new(cluster).await?;
+/// let output = RecommenderBuilder::new()
+///     .metrics(metrics)
+///     .workloads(loader)
+///     .config(config)
+///     .run()
+///     .await?;
+/// println!("{} recommendations", output.recommendations.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct RecommenderBuilder {
+    workloads: Option<Arc<dyn WorkloadSource>>,
+    metrics: Option<Arc<dyn MetricsProvider>>,
+    config: RecommenderConfig,
+    namespace: Option<String>,
+    policies: Arc<PolicySet>,
+    cancel: Option<CancellationToken>,
+    clock: Option<SharedClock>,
+    timezone: Timezone,
+    prices: Option<ResourcePrices>,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl RecommenderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where usage is queried from, e.g. a `PrometheusClient`
+    pub fn metrics(self, metrics: impl MetricsProvider + 'static) -> Self {
+        self.metrics_provider(Arc::new(metrics))
+    }
+
+    pub fn metrics_provider(mut self, metrics: Arc<dyn MetricsProvider>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Where workloads are listed from, e.g. a [`KubernetesLoader`](crate::KubernetesLoader)
+    pub fn workloads(self, workloads: impl WorkloadSource + 'static) -> Self {
+        self.workload_source(Arc::new(workloads))
+    }
+
+    pub fn workload_source(mut self, workloads: Arc<dyn WorkloadSource>) -> Self {
+        self.workloads = Some(workloads);
+        self
+    }
+
+    /// Settings of the analysis, checked by [`RecommenderBuilder::build`]
+    pub fn config(mut self, config: RecommenderConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Namespace recorded in the output metadata
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn policies(mut self, policies: impl Into<Arc<PolicySet>>) -> Self {
+        self.policies = policies.into();
+        self
+    }
+
+    /// Stop the run when `token` is cancelled, e.g. on controller shutdown
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn prices(mut self, prices: ResourcePrices) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+
+    pub fn on_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// The [`AnalysisRequest`] to run, failing when the metrics or workloads are
+    /// missing or the config is invalid
+    pub fn build(self) -> Result<AnalysisRequest> {
+        let metrics = self.metrics.ok_or_else(|| {
+            ConfigError::MissingRequired(
+                "a metrics provider, see RecommenderBuilder::metrics".into(),
+            )
+        })?;
+        let workloads = self.workloads.ok_or_else(|| {
+            ConfigError::MissingRequired(
+                "a workload source, see RecommenderBuilder::workloads".into(),
+            )
+        })?;
+        self.config.validate()?;
+
+        let mut request = AnalysisRequest::new(workloads, metrics)
+            .config(self.config)
+            .namespace(self.namespace)
+            .policies(self.policies)
+            .timezone(self.timezone);
+        if let Some(token) = self.cancel {
+            request = request.cancellation(token);
+        }
+        if let Some(clock) = self.clock {
+            request = request.clock(clock);
+        }
+        if let Some(prices) = self.prices {
+            request = request.prices(prices);
+        }
+        request.on_progress = self.on_progress;
+        Ok(request)
+    }
+
+    /// Build the request and [`run_analysis`] it
+    pub async fn run(self) -> Result<RecommenderOutput> {
+        run_analysis(self.build()?).await
+    }
+}
+
+/// Load workloads, query their usage, recommend and enforce policies
+///
+/// Fails with [`ConfigError`] for an invalid config, before anything is queried,
+/// and with [`crate::RecommenderError::Cancelled`] when the request's token is
+/// cancelled before all workloads were analyzed.
+pub async fn run_analysis(request: AnalysisRequest) -> Result<RecommenderOutput> {
+    request.config.validate()?;
+    info!("Scanning deployments for resource requests and limits...");
+    let deployments = request.workloads.list_workloads().await?;
+    info!("Found {} deployments", deployments.len());
+    let total_deployments = deployments.len();
+
+    // Skipped workloads may still be sampled from metrics-server
+    let listed = request.metrics_server.as_ref().map(|_| deployments.clone());
+    let (deployments, mut skipped) =
+        partition_skipped(deployments, request.clock.now_utc(), &request.config);
+    // Replica counts for the quota rollup, the deployments move into the recommender
+    let mut analyzed = request.quota_headroom.map(|_| deployments.clone());
+    let vpas: HashMap<WorkloadKey, Vpa> = listed
+        .as_deref()
+        .unwrap_or(&deployments)
+        .iter()
+        .filter_map(|d| Some((workload_key(d), d.vpa.clone()?)))
+        .collect();
+    let hpas: HashMap<WorkloadKey, Hpa> = listed
+        .as_deref()
+        .unwrap_or(&deployments)
+        .iter()
+        .filter_map(|d| Some((workload_key(d), d.hpa.clone()?)))
+        .collect();
+    let provenance: HashMap<WorkloadKey, Provenance> = listed
+        .as_deref()
+        .unwrap_or(&deployments)
+        .iter()
+        .filter_map(|d| {
+            let provenance = d.provenance.clone()?;
+            Some((workload_key(d), provenance))
+        })
+        .collect();
+    let budgets: DisruptionBudgets = listed
+        .as_deref()
+        .unwrap_or(&deployments)
+        .iter()
+        .filter(|d| !d.pdbs.is_empty())
+        .map(|d| {
+            let names = d.pdbs.iter().map(|pdb| pdb.name.clone()).collect();
+            (workload_key(d), (names, disruption_risk(d)))
+        })
+        .collect();
+
+    let mut config = request.config.clone();
+    if config.memory_metric == MemoryMetric::Auto {
+        match MetricVariants::detect(
+            request.metrics.as_ref(),
+            request.namespace.as_deref(),
+            request.clock.now(),
+        )
+        .await
+        {
+            Ok(variants) => variants.apply(&mut config),
+            Err(e) => warn!(
+                "Failed to detect metric variants, using the defaults: {}",
+                e
+            ),
+        }
+    }
+
+    let mut recommender = Recommender::with_provider(request.metrics, config)
+        .with_cancellation(request.cancel.clone())
+        .with_clock(request.clock.clone());
+    if let Some(callback) = request.on_progress {
+        recommender = recommender.on_progress(move |progress| callback(progress));
+    }
+
+    let (mut recommendations, failed) = recommender.analyze(deployments).await?;
+    info!("Generated {} recommendations", recommendations.len());
+    if !failed.is_empty() {
+        warn!("{} containers got no recommendation", failed.len());
+    }
+    skipped.extend(failed);
+
+    if let (Some(sampler), Some(listed)) = (&request.metrics_server, &listed) {
+        let (live, sampled) = recommend_from_live_usage(
+            sampler,
+            listed,
+            &mut skipped,
+            &request.config,
+            &request.cancel,
+        )
+        .await?;
+        recommendations.extend(live);
+        if let Some(analyzed) = &mut analyzed {
+            analyzed.extend(sampled);
+        }
+    }
+    compare_with_vpas(&mut recommendations, &vpas, request.config.vpa_mode);
+    suggest_hpa_targets(&mut recommendations, &hpas);
+    check_disruption_budgets(&mut recommendations, &budgets);
+    check_drift(&mut recommendations, &provenance);
+
+    let policy_violations = request.policies.enforce(&mut recommendations);
+    clamp_to_bounds(&mut recommendations, &request.config.bounds);
+    // Policies and bounds adjust requests and limits separately
+    enforce_limits_above_requests(&mut recommendations);
+
+    let quotas = match (request.quota_headroom, &analyzed) {
+        (Some(headroom), Some(deployments)) => {
+            namespace_quotas(&recommendations, deployments, headroom)
+        }
+        _ => Vec::new(),
+    };
+
+    Ok(RecommenderOutput::builder()
+        .namespace(request.namespace)
+        .config(&request.config)
+        .total_deployments(total_deployments)
+        .recommendations(recommendations)
+        .policy_violations(policy_violations)
+        .skipped(skipped)
+        .namespace_quotas(quotas)
+        .prices(request.prices)
+        .generated_at(request.clock.now_utc())
+        .timezone(request.timezone)
+        .build())
+}
+
+/// Outputs of [`run_analysis_per_namespace`]
+pub struct NamespacedAnalysis {
+    /// All namespaces together, as [`run_analysis`] returns them
+    pub combined: RecommenderOutput,
+    /// One output per namespace, sorted by namespace
+    pub namespaces: Vec<RecommenderOutput>,
+}
+
+/// Run [`run_analysis`] for each namespace of the request's workloads concurrently
+///
+/// Workloads are listed once, then up to `concurrency` namespaces are analyzed at
+/// a time, each with its own metric variant detection, policies and quota rollup.
+/// The request's progress callback is dropped, the counts of concurrent runs
+/// would interleave.
+pub async fn run_analysis_per_namespace(
+    request: AnalysisRequest,
+    concurrency: usize,
+) -> Result<NamespacedAnalysis> {
+    info!("Scanning deployments for resource requests and limits...");
+    let deployments = request.workloads.list_workloads().await?;
+    let mut by_namespace: BTreeMap<String, Vec<DeploymentResources>> = BTreeMap::new();
+    for deployment in deployments {
+        by_namespace
+            .entry(deployment.namespace.clone())
+            .or_default()
+            .push(deployment);
+    }
+    info!(
+        "Analyzing {} namespaces, {} at a time",
+        by_namespace.len(),
+        concurrency.max(1)
+    );
+
+    let requests = by_namespace
+        .into_iter()
+        .map(|(namespace, deployments)| AnalysisRequest {
+            workloads: Arc::new(StaticWorkloadSource::new(deployments)),
+            metrics: request.metrics.clone(),
+            config: request.config.clone(),
+            namespace: Some(namespace),
+            policies: request.policies.clone(),
+            cancel: request.cancel.clone(),
+            clock: request.clock.clone(),
+            timezone: request.timezone,
+            quota_headroom: request.quota_headroom,
+            on_progress: None,
+            metrics_server: request.metrics_server.clone(),
+            prices: request.prices,
+        });
+    let mut namespaces = futures::stream::iter(requests)
+        .map(run_analysis)
+        .buffer_unordered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    namespaces.sort_by(|a, b| a.metadata.namespace.cmp(&b.metadata.namespace));
+
+    let combined = RecommenderOutput::builder()
+        .namespace(request.namespace)
+        .config(&request.config)
+        .total_deployments(
+            namespaces
+                .iter()
+                .map(|o| o.metadata.total_deployments)
+                .sum(),
+        )
+        .recommendations(
+            namespaces
+                .iter()
+                .flat_map(|o| o.recommendations.clone())
+                .collect(),
+        )
+        .policy_violations(
+            namespaces
+                .iter()
+                .flat_map(|o| o.policy_violations.clone())
+                .collect(),
+        )
+        .skipped(namespaces.iter().flat_map(|o| o.skipped.clone()).collect())
+        .namespace_quotas(
+            namespaces
+                .iter()
+                .flat_map(|o| o.namespace_quotas.clone())
+                .collect(),
+        )
+        .prices(request.prices)
+        .generated_at(request.clock.now_utc())
+        .timezone(request.timezone)
+        .build();
+
+    Ok(NamespacedAnalysis {
+        combined,
+        namespaces,
+    })
+}
+
+/// Recommend from metrics-server samples for containers skipped for lack of usage
+///
+/// Covers containers Prometheus returned no data for and workloads created within
+/// the lookback window. The recommendations keep their sample-based confidence,
+/// which a few minutes of samples leave close to 0, and carry a warning. Returns
+/// them with the deployments they are for.
+async fn recommend_from_live_usage(
+    sampler: &MetricsServerSampler,
+    listed: &[DeploymentResources],
+    skipped: &mut Vec<SkippedWorkload>,
+    config: &RecommenderConfig,
+    cancel: &CancellationToken,
+) -> Result<(Vec<ResourceRecommendation>, Vec<DeploymentResources>)> {
+    let without_usage =
+        |s: &SkippedWorkload, namespace: &str, deployment: &str, container: &str| {
+            matches!(
+                s.reason,
+                SkipReason::NoData | SkipReason::CreatedWithinWindow { .. }
+            ) && s.namespace == namespace
+                && s.deployment == deployment
+                && s.container.as_deref().is_none_or(|c| c == container)
+        };
+    let candidates: Vec<&DeploymentResources> = listed
+        .iter()
+        .filter(|d| {
+            d.containers.iter().any(|c| {
+                skipped
+                    .iter()
+                    .any(|s| without_usage(s, &d.namespace, &d.name, &c.name))
+            })
+        })
+        .collect();
+    if candidates.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    info!(
+        "Sampling {} deployments without usage from metrics-server for {}s",
+        candidates.len(),
+        sampler.window().as_secs()
+    );
+    let usage = match sampler.sample(&candidates, cancel).await {
+        Ok(usage) => usage,
+        Err(RecommenderError::Cancelled) => return Err(RecommenderError::Cancelled),
+        Err(e) => {
+            warn!(
+                "Failed to sample metrics-server, leaving the workloads skipped: {}",
+                e
+            );
+            return Ok((Vec::new(), Vec::new()));
+        }
+    };
+
+    let mut recommendations = Vec::new();
+    let mut sampled = Vec::new();
+    for deployment in candidates {
+        let before = recommendations.len();
+        for container in &deployment.containers {
+            if !skipped
+                .iter()
+                .any(|s| without_usage(s, &deployment.namespace, &deployment.name, &container.name))
+            {
+                continue;
+            }
+            let key = (
+                deployment.namespace.clone(),
+                deployment.name.clone(),
+                container.name.clone(),
+            );
+            let Some(live) = usage.get(&key).filter(|u| !u.cpu.is_empty()) else {
+                continue;
+            };
+
+            let mut rec =
+                recommend_from_samples(deployment, container, &live.cpu, &live.memory, config);
+            rec.warnings.push(format!(
+                "Low confidence: Prometheus had no usage, sized from {} metrics-server sample(s) over {}s",
+                live.cpu.len(),
+                sampler.window().as_secs()
+            ));
+            rec.from_metrics_server = true;
+            recommendations.push(rec);
+        }
+        if recommendations.len() > before {
+            sampled.push(deployment.clone());
+        }
+    }
+
+    // Deployment-wide skips go once any container got a recommendation
+    skipped.retain(|s| {
+        !recommendations
+            .iter()
+            .any(|rec| without_usage(s, &rec.namespace, &rec.deployment, &rec.container))
+    });
+    info!(
+        "Recommended {} containers from metrics-server samples",
+        recommendations.len()
+    );
+    Ok((recommendations, sampled))
+}
+
+/// Split off workloads whose usage in the window would be misleading
+fn partition_skipped(
+    deployments: Vec<DeploymentResources>,
+    now: DateTime<Utc>,
+    config: &RecommenderConfig,
+) -> (Vec<DeploymentResources>, Vec<SkippedWorkload>) {
+    let mut analyzed = Vec::with_capacity(deployments.len());
+    let mut skipped = Vec::new();
+
+    for deployment in deployments {
+        let lookback_hours = config.lookback_hours_for(&deployment);
+        let window_start =
+            now - chrono::Duration::milliseconds((lookback_hours * 3_600_000.0) as i64);
+        let reason = deployment.skip_reason(window_start).or_else(|| {
+            let vpa = deployment.vpa.as_ref()?;
+            (config.vpa_mode == VpaMode::Skip && vpa.manages()).then(|| SkipReason::ManagedByVpa {
+                vpa: vpa.name.clone(),
+                update_mode: vpa.update_mode.clone(),
+            })
+        });
+        match reason {
+            Some(reason) => {
+                info!(
+                    "Skipping deployment {}/{}: {}",
+                    deployment.namespace, deployment.name, reason
+                );
+                skipped.push(SkippedWorkload {
+                    namespace: deployment.namespace,
+                    deployment: deployment.name,
+                    container: None,
+                    reason,
+                });
+            }
+            None => analyzed.push(deployment),
+        }
+    }
+
+    (analyzed, skipped)
+}
+
+/// Attach the VPA target of each container to its recommendation
+///
+/// VPA-managed workloads only get here with [`VpaMode::Compare`] or
+/// [`VpaMode::UpdateVpa`]; compared ones are flagged as not applied.
+fn compare_with_vpas(
+    recommendations: &mut [ResourceRecommendation],
+    vpas: &HashMap<WorkloadKey, Vpa>,
+    mode: VpaMode,
+) {
+    for rec in recommendations {
+        let key = recommendation_key(rec);
+        let Some(vpa) = vpas.get(&key) else {
+            continue;
+        };
+        let target = vpa.target(&rec.container);
+        rec.recommendation_reason.push_str(&format!(
+            ". VerticalPodAutoscaler {} ({}) targets {} CPU, {} memory",
+            target.name,
+            target.update_mode,
+            display_optional(target.cpu),
+            display_optional(target.memory)
+        ));
+        if target.manages() && mode == VpaMode::Compare {
+            rec.warnings.push(format!(
+                "Not applied while VerticalPodAutoscaler {} manages the requests, see --vpa update-vpa",
+                target.name
+            ));
+        }
+        rec.vpa = Some(target);
+    }
+}
+
+/// Attach the suggested CPU utilization target of each workload's HPA
+///
+/// The HPA averages utilization over whole pods, so the target comes from the
+/// CPU usage summed over the workload's containers, per day profile.
+fn suggest_hpa_targets(
+    recommendations: &mut [ResourceRecommendation],
+    hpas: &HashMap<WorkloadKey, Hpa>,
+) {
+    type Key = (String, String, Option<DayProfile>);
+    let key = |rec: &ResourceRecommendation| -> Key {
+        (
+            rec.namespace.clone(),
+            rec.deployment.clone(),
+            rec.day_profile,
+        )
+    };
+
+    let mut usage: HashMap<Key, (f64, f64)> = HashMap::new();
+    for rec in recommendations.iter() {
+        let (p95, max) = usage.entry(key(rec)).or_default();
+        *p95 += rec.cpu_usage_stats.p95;
+        *max += rec.cpu_usage_stats.max;
+    }
+
+    for rec in recommendations {
+        let Some(hpa) = hpas.get(&recommendation_key(rec)) else {
+            continue;
+        };
+        let Some(utilization) = usage
+            .get(&key(rec))
+            .and_then(|&(p95, max)| suggest_cpu_utilization(p95, max))
+        else {
+            continue;
+        };
+
+        let target = hpa.target(utilization);
+        rec.recommendation_reason.push_str(&format!(
+            ". HorizontalPodAutoscaler {} should target {}% CPU utilization ({})",
+            target.name,
+            utilization,
+            target.current_cpu_utilization.map_or(
+                "no CPU target now".to_string(),
+                |current| format!("now {}%", current)
+            )
+        ));
+        rec.hpa = Some(target);
+    }
+}
+
+/// Names of the PDBs covering each workload and the one its rollout would breach
+type DisruptionBudgets = HashMap<WorkloadKey, (Vec<String>, Option<DisruptionRisk>)>;
+
+/// Namespace, name and kind of a workload, as a CronJob may share a Deployment's name
+type WorkloadKey = (String, String, WorkloadKind);
+
+fn workload_key(workload: &DeploymentResources) -> WorkloadKey {
+    (
+        workload.namespace.clone(),
+        workload.name.clone(),
+        workload.kind,
+    )
+}
+
+fn recommendation_key(rec: &ResourceRecommendation) -> WorkloadKey {
+    (
+        rec.namespace.clone(),
+        rec.deployment.clone(),
+        rec.workload_kind,
+    )
+}
+
+/// Attach the PDBs of each workload and flag rollouts that would breach one
+fn check_disruption_budgets(
+    recommendations: &mut [ResourceRecommendation],
+    budgets: &DisruptionBudgets,
+) {
+    for rec in recommendations {
+        let key = recommendation_key(rec);
+        let Some((pdbs, risk)) = budgets.get(&key) else {
+            continue;
+        };
+        rec.pdbs = pdbs.clone();
+        if let Some(risk) = risk {
+            rec.warnings.push(risk.to_string());
+            rec.disruption_risk = Some(risk.clone());
+        }
+    }
+}
+
+/// Tell resources edited by hand since the last apply from usage changes
+fn check_drift(
+    recommendations: &mut [ResourceRecommendation],
+    provenance: &HashMap<WorkloadKey, Provenance>,
+) {
+    for rec in recommendations {
+        let key = recommendation_key(rec);
+        let Some(provenance) = provenance.get(&key) else {
+            continue;
+        };
+        rec.drift = provenance.drift(rec);
+        if rec.drift == Some(Drift::Manual) {
+            rec.warnings.push(format!(
+                "Resources were changed by hand since version {} applied them on {}",
+                provenance.version,
+                provenance.applied_at.format("%Y-%m-%d")
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::StaticMetricsProvider;
+    use crate::stats::UsageStats;
+    use serde_json::json;
+
+    fn request(config: RecommenderConfig) -> AnalysisRequest {
+        AnalysisRequest::new(
+            Arc::new(StaticWorkloadSource::new(Vec::new())),
+            Arc::new(StaticMetricsProvider::new()),
+        )
+        .config(config)
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_config_before_analyzing() {
+        for lookback_hours in [f64::NAN, -1.0] {
+            let config = RecommenderConfig::builder()
+                .lookback_hours(lookback_hours)
+                .build();
+            let result = run_analysis(request(config)).await;
+            assert!(
+                matches!(result, Err(RecommenderError::Config(_))),
+                "lookback of {} hours was accepted",
+                lookback_hours
+            );
+        }
+    }
+
+    fn recommendation(kind: WorkloadKind) -> ResourceRecommendation {
+        serde_json::from_value(json!({
+            "namespace": "shop",
+            "deployment": "web",
+            "workload_kind": kind,
+            "container": "app",
+            "current_cpu_request": null,
+            "current_cpu_limit": null,
+            "current_memory_request": null,
+            "current_memory_limit": null,
+            "recommended_cpu_request": "250m",
+            "recommended_cpu_limit": "500m",
+            "recommended_memory_request": "256Mi",
+            "recommended_memory_limit": "512Mi",
+            "cpu_usage_stats": UsageStats::default(),
+            "memory_usage_stats": UsageStats::default(),
+            "recommendation_reason": "test",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cronjobs_do_not_share_the_budgets_of_a_deployment_of_the_same_name() {
+        let budgets = DisruptionBudgets::from([(
+            (
+                "shop".to_string(),
+                "web".to_string(),
+                WorkloadKind::Deployment,
+            ),
+            (vec!["web-pdb".to_string()], None),
+        )]);
+        let mut recommendations = vec![
+            recommendation(WorkloadKind::Deployment),
+            recommendation(WorkloadKind::CronJob),
+        ];
+
+        check_disruption_budgets(&mut recommendations, &budgets);
+
+        assert_eq!(recommendations[0].pdbs, ["web-pdb"]);
+        assert!(recommendations[1].pdbs.is_empty());
+    }
+}
@@ -0,0 +1,481 @@
+use std::path::Path;
+
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{Api, Client};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::quantity::{Quantity, round_cpu, round_memory};
+use crate::recommender::ResourceRecommendation;
+use crate::{ConfigError, KubernetesError, Result};
+
+/// Key looked up in the policy ConfigMap
+const CONFIGMAP_POLICY_KEY: &str = "policy.yaml";
+
+/// Set of governance rules evaluated before output and apply
+///
+/// Example policy file:
+///
+/// ```yaml
+/// rules:
+///   - type: never_reduce
+///     resource: memory
+///     namespace: prod-payments
+///   - type: max_reduction
+///     percent: 30
+///   - type: min_confidence_for_apply
+///     confidence: 0.8
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicySet {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// A single governance rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PolicyRule {
+    /// Never recommend a value below the current one
+    NeverReduce {
+        #[serde(default)]
+        resource: PolicyResource,
+        #[serde(flatten)]
+        scope: PolicyScope,
+    },
+    /// Cap how far a single change may reduce a value, in percent
+    MaxReduction {
+        percent: f64,
+        #[serde(default)]
+        resource: PolicyResource,
+        #[serde(flatten)]
+        scope: PolicyScope,
+    },
+    /// Exclude low-confidence recommendations from automatic apply
+    MinConfidenceForApply {
+        confidence: f64,
+        #[serde(flatten)]
+        scope: PolicyScope,
+    },
+}
+
+/// Resources a rule applies to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyResource {
+    Cpu,
+    Memory,
+    #[default]
+    All,
+}
+
+impl PolicyResource {
+    fn includes_cpu(&self) -> bool {
+        matches!(self, PolicyResource::Cpu | PolicyResource::All)
+    }
+
+    fn includes_memory(&self) -> bool {
+        matches!(self, PolicyResource::Memory | PolicyResource::All)
+    }
+}
+
+/// Optional namespace/deployment filter, an empty scope matches everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyScope {
+    pub namespace: Option<String>,
+    pub deployment: Option<String>,
+}
+
+impl PolicyScope {
+    fn matches(&self, recommendation: &ResourceRecommendation) -> bool {
+        self.namespace
+            .as_ref()
+            .is_none_or(|ns| *ns == recommendation.namespace)
+            && self
+                .deployment
+                .as_ref()
+                .is_none_or(|name| *name == recommendation.deployment)
+    }
+}
+
+/// Record of a rule changing or blocking a recommendation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    pub namespace: String,
+    pub deployment: String,
+    pub container: String,
+    pub rule: String,
+    pub message: String,
+}
+
+impl PolicySet {
+    /// Load policies from a YAML file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            ConfigError::FileError(format!(
+                "Failed to read policy file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let policies: PolicySet = serde_yaml::from_str(&content)?;
+
+        info!(
+            "Loaded {} policy rule(s) from {}",
+            policies.rules.len(),
+            path.display()
+        );
+        Ok(policies)
+    }
+
+    /// Load policies from a ConfigMap given as `namespace/name`
+    pub async fn from_configmap(client: Client, reference: &str) -> Result<Self> {
+        let (namespace, name) = reference.split_once('/').ok_or_else(|| {
+            ConfigError::InvalidValue(format!(
+                "Policy ConfigMap must be given as namespace/name, got '{}'",
+                reference
+            ))
+        })?;
+
+        let api: Api<ConfigMap> = Api::namespaced(client, namespace);
+        let configmap = api.get(name).await.map_err(KubernetesError::ApiError)?;
+
+        let content = configmap
+            .data
+            .as_ref()
+            .and_then(|data| data.get(CONFIGMAP_POLICY_KEY))
+            .ok_or_else(|| {
+                ConfigError::MissingRequired(format!(
+                    "ConfigMap {} has no '{}' key",
+                    reference, CONFIGMAP_POLICY_KEY
+                ))
+            })?;
+        let policies: PolicySet = serde_yaml::from_str(content)?;
+
+        info!(
+            "Loaded {} policy rule(s) from ConfigMap {}",
+            policies.rules.len(),
+            reference
+        );
+        Ok(policies)
+    }
+
+    /// Merge the rules of another policy set into this one
+    pub fn extend(&mut self, other: PolicySet) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Apply value-adjusting rules to the recommendations
+    ///
+    /// Returns every adjustment made so it can be surfaced in the output.
+    pub fn enforce(&self, recommendations: &mut [ResourceRecommendation]) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+
+        for recommendation in recommendations.iter_mut() {
+            for rule in &self.rules {
+                match rule {
+                    PolicyRule::NeverReduce { resource, scope }
+                        if scope.matches(recommendation) =>
+                    {
+                        adjust_values(
+                            recommendation,
+                            *resource,
+                            1.0,
+                            "never_reduce",
+                            &mut violations,
+                        );
+                    }
+                    PolicyRule::MaxReduction {
+                        percent,
+                        resource,
+                        scope,
+                    } if scope.matches(recommendation) => {
+                        let floor = (1.0 - percent / 100.0).clamp(0.0, 1.0);
+                        adjust_values(
+                            recommendation,
+                            *resource,
+                            floor,
+                            "max_reduction",
+                            &mut violations,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if !violations.is_empty() {
+            info!(
+                "Policies adjusted {} recommended value(s)",
+                violations.len()
+            );
+        }
+        violations
+    }
+
+    /// Check whether a recommendation may be applied without a human in the loop
+    pub fn allows_auto_apply(&self, recommendation: &ResourceRecommendation) -> bool {
+        self.rules.iter().all(|rule| match rule {
+            PolicyRule::MinConfidenceForApply { confidence, scope }
+                if scope.matches(recommendation) =>
+            {
+                let allowed = recommendation.confidence >= *confidence;
+                if !allowed {
+                    warn!(
+                        "Skipping auto-apply for {}/{}/{}: confidence {:.2} below {:.2}",
+                        recommendation.namespace,
+                        recommendation.deployment,
+                        recommendation.container,
+                        recommendation.confidence,
+                        confidence
+                    );
+                }
+                allowed
+            }
+            _ => true,
+        })
+    }
+}
+
+/// Raise recommended values so they are at least `floor` times the current value
+fn adjust_values(
+    recommendation: &mut ResourceRecommendation,
+    resource: PolicyResource,
+    floor: f64,
+    rule: &str,
+    violations: &mut Vec<PolicyViolation>,
+) {
+    let mut messages = Vec::new();
+
+    if resource.includes_cpu() {
+        messages.extend(raise_to_floor(
+            "cpu request",
+            recommendation.current_cpu_request,
+            &mut recommendation.recommended_cpu_request,
+            floor,
+            true,
+        ));
+        messages.extend(raise_to_floor(
+            "cpu limit",
+            recommendation.current_cpu_limit,
+            &mut recommendation.recommended_cpu_limit,
+            floor,
+            true,
+        ));
+    }
+    if resource.includes_memory() {
+        messages.extend(raise_to_floor(
+            "memory request",
+            recommendation.current_memory_request,
+            &mut recommendation.recommended_memory_request,
+            floor,
+            false,
+        ));
+        messages.extend(raise_to_floor(
+            "memory limit",
+            recommendation.current_memory_limit,
+            &mut recommendation.recommended_memory_limit,
+            floor,
+            false,
+        ));
+    }
+
+    for message in messages {
+        debug!(
+            "Policy {} adjusted {}/{}/{}: {}",
+            rule,
+            recommendation.namespace,
+            recommendation.deployment,
+            recommendation.container,
+            message
+        );
+        violations.push(PolicyViolation {
+            namespace: recommendation.namespace.clone(),
+            deployment: recommendation.deployment.clone(),
+            container: recommendation.container.clone(),
+            rule: rule.to_string(),
+            message,
+        });
+    }
+}
+
+/// Raise a single value, returns a description of the change if one was made
+fn raise_to_floor(
+    label: &str,
+    current: Option<Quantity>,
+    recommended: &mut Quantity,
+    floor: f64,
+    is_cpu: bool,
+) -> Option<String> {
+    let current = current?;
+    let minimum = current * floor;
+    if *recommended >= minimum {
+        return None;
+    }
+
+    // Keep the exact current value when reductions are forbidden outright
+    let adjusted = if floor >= 1.0 {
+        current
+    } else if is_cpu {
+        round_cpu(minimum.value())
+    } else {
+        round_memory(minimum.value())
+    };
+    let message = format!("{} raised from {} to {}", label, recommended, adjusted);
+    *recommended = adjusted;
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policies(yaml: &str) -> PolicySet {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    /// Recommendation for shop/web with `[cpu request, cpu limit, memory request,
+    /// memory limit]` as currently set and as recommended
+    fn recommendation(current: [&str; 4], recommended: [&str; 4]) -> ResourceRecommendation {
+        let stats = json!({
+            "samples": 0, "min": 0.0, "max": 0.0, "avg": 0.0, "p50": 0.0, "p95": 0.0, "p99": 0.0,
+        });
+        serde_json::from_value(json!({
+            "deployment": "web",
+            "container": "app",
+            "namespace": "shop",
+            "current_cpu_request": current[0],
+            "current_cpu_limit": current[1],
+            "current_memory_request": current[2],
+            "current_memory_limit": current[3],
+            "recommended_cpu_request": recommended[0],
+            "recommended_cpu_limit": recommended[1],
+            "recommended_memory_request": recommended[2],
+            "recommended_memory_limit": recommended[3],
+            "cpu_usage_stats": stats,
+            "memory_usage_stats": stats,
+            "recommendation_reason": "",
+            "confidence": 1.0,
+        }))
+        .unwrap()
+    }
+
+    fn recommended(recommendation: &ResourceRecommendation) -> [String; 4] {
+        [
+            recommendation.recommended_cpu_request.to_string(),
+            recommendation.recommended_cpu_limit.to_string(),
+            recommendation.recommended_memory_request.to_string(),
+            recommendation.recommended_memory_limit.to_string(),
+        ]
+    }
+
+    #[test]
+    fn never_reduce_keeps_current_values() {
+        let policies = policies("rules:\n  - type: never_reduce\n    resource: memory\n");
+        let mut recommendations = [recommendation(
+            ["500m", "1", "512Mi", "1Gi"],
+            ["200m", "400m", "256Mi", "2Gi"],
+        )];
+
+        let violations = policies.enforce(&mut recommendations);
+
+        // CPU isn't covered by the rule and the memory limit already grows
+        assert_eq!(
+            recommended(&recommendations[0]),
+            ["200m", "400m", "512Mi", "2Gi"]
+        );
+        let [violation] = violations.as_slice() else {
+            panic!("expected one violation, got {:?}", violations);
+        };
+        assert_eq!(violation.rule, "never_reduce");
+        assert_eq!(violation.container, "app");
+        assert_eq!(
+            violation.message,
+            "memory request raised from 256Mi to 512Mi"
+        );
+    }
+
+    #[test]
+    fn max_reduction_rounds_floor_up() {
+        let policies = policies("rules:\n  - type: max_reduction\n    percent: 50\n");
+        let mut recommendations = [recommendation(
+            ["333m", "1", "100Mi", "1Gi"],
+            ["100m", "800m", "10Mi", "256Mi"],
+        )];
+
+        let violations = policies.enforce(&mut recommendations);
+
+        // Half of 333m is 166.5m, rounded up to a whole millicore; the CPU limit
+        // is reduced by less than half and left alone
+        assert_eq!(
+            recommended(&recommendations[0]),
+            ["167m", "800m", "50Mi", "512Mi"]
+        );
+        assert_eq!(violations.len(), 3);
+        assert!(violations.iter().all(|v| v.rule == "max_reduction"));
+    }
+
+    #[test]
+    fn values_without_current_one_are_left_alone() {
+        let policies = policies("rules:\n  - type: never_reduce\n");
+        let mut recommendations = [recommendation(
+            ["not set", "not set", "not set", "not set"],
+            ["100m", "200m", "64Mi", "128Mi"],
+        )];
+
+        assert!(policies.enforce(&mut recommendations).is_empty());
+        assert_eq!(
+            recommended(&recommendations[0]),
+            ["100m", "200m", "64Mi", "128Mi"]
+        );
+    }
+
+    #[test]
+    fn rules_apply_within_their_scope() {
+        let policies =
+            policies("rules:\n  - type: never_reduce\n    namespace: prod\n    deployment: api\n");
+        let mut recommendations = [
+            recommendation(["1", "2", "1Gi", "2Gi"], ["500m", "1", "512Mi", "1Gi"]),
+            recommendation(["1", "2", "1Gi", "2Gi"], ["500m", "1", "512Mi", "1Gi"]),
+            recommendation(["1", "2", "1Gi", "2Gi"], ["500m", "1", "512Mi", "1Gi"]),
+        ];
+        recommendations[1].namespace = "prod".to_string();
+        recommendations[2].namespace = "prod".to_string();
+        recommendations[2].deployment = "api".to_string();
+
+        let violations = policies.enforce(&mut recommendations);
+
+        assert_eq!(violations.len(), 4);
+        assert!(violations.iter().all(|v| v.deployment == "api"));
+        for recommendation in &recommendations[..2] {
+            assert_eq!(recommended(recommendation), ["500m", "1", "512Mi", "1Gi"]);
+        }
+        assert_eq!(recommended(&recommendations[2]), ["1", "2", "1Gi", "2Gi"]);
+    }
+
+    #[test]
+    fn min_confidence_gates_auto_apply() {
+        let policies = policies(concat!(
+            "rules:\n",
+            "  - type: min_confidence_for_apply\n    confidence: 0.8\n",
+            "  - type: min_confidence_for_apply\n    confidence: 0.95\n    namespace: prod\n",
+        ));
+        let confident = |namespace: &str, confidence: f64| {
+            let mut recommendation =
+                recommendation(["1", "2", "1Gi", "2Gi"], ["500m", "1", "512Mi", "1Gi"]);
+            recommendation.namespace = namespace.to_string();
+            recommendation.confidence = confidence;
+            recommendation
+        };
+
+        assert!(policies.allows_auto_apply(&confident("shop", 0.8)));
+        assert!(!policies.allows_auto_apply(&confident("shop", 0.5)));
+        assert!(!policies.allows_auto_apply(&confident("prod", 0.9)));
+        assert!(policies.allows_auto_apply(&confident("prod", 0.95)));
+
+        // Confidence gates apply only, it never changes values
+        let mut recommendations = [confident("shop", 0.1)];
+        assert!(policies.enforce(&mut recommendations).is_empty());
+    }
+}
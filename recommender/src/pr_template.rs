@@ -0,0 +1,133 @@
+//! Filling a repository's PR/MR template with the generated description
+//!
+//! Repositories often enforce their template with bots checking its checklists,
+//! so the template is kept as is and the generated sections are written under the
+//! headings they fit. Sections no heading fits go in front of the template, or
+//! after it when an earlier section was placed, keeping their order.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::GitProvider;
+
+/// Template locations relative to the repository root, matched case-insensitively
+const GITHUB_TEMPLATES: [&str; 4] = [
+    ".github/pull_request_template.md",
+    "pull_request_template.md",
+    "docs/pull_request_template.md",
+    ".gitea/pull_request_template.md",
+];
+
+/// Template GitLab preselects for new merge requests
+const GITLAB_TEMPLATE: &str = ".gitlab/merge_request_templates/Default.md";
+
+/// Part of a generated description, written under the template heading it matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptionSection {
+    /// Heading used when no template heading matches
+    pub title: String,
+    pub body: String,
+    /// Lowercase words one of which a template heading has to contain
+    pub keywords: &'static [&'static str],
+}
+
+/// The PR/MR template of the repository checked out at `root`, if it has one
+pub fn find_template(root: &Path, provider: &GitProvider) -> Option<String> {
+    let mut candidates: Vec<&str> = GITHUB_TEMPLATES.to_vec();
+    if *provider == GitProvider::GitLab {
+        candidates.insert(0, GITLAB_TEMPLATE);
+    } else {
+        candidates.push(GITLAB_TEMPLATE);
+    }
+
+    candidates.into_iter().find_map(|relative| {
+        let path = find_case_insensitive(root, relative)?;
+        let template = fs::read_to_string(&path).ok()?;
+        (!template.trim().is_empty()).then_some(template)
+    })
+}
+
+/// `template` with every section placed under the first unused heading it matches
+///
+/// Headings inside code blocks are ignored. Each heading takes at most one section,
+/// so a template with one "Description" heading only receives the first match.
+pub fn fill_template(template: &str, sections: &[DescriptionSection]) -> String {
+    let mut placed = vec![false; sections.len()];
+    let mut filled = String::with_capacity(template.len());
+    let mut in_code_block = false;
+
+    for line in template.lines() {
+        filled.push_str(line);
+        filled.push('\n');
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        let Some(heading) = heading_text(line).filter(|_| !in_code_block) else {
+            continue;
+        };
+        let heading = heading.to_lowercase();
+        if let Some(index) = (0..sections.len())
+            .find(|&i| !placed[i] && sections[i].keywords.iter().any(|k| heading.contains(k)))
+        {
+            placed[index] = true;
+            filled.push('\n');
+            filled.push_str(sections[index].body.trim_end());
+            filled.push('\n');
+        }
+    }
+
+    let first_placed = placed.iter().position(|&p| p);
+    let mut front = String::new();
+    for (index, section) in sections.iter().enumerate().filter(|&(i, _)| !placed[i]) {
+        let text = format!("## {}\n\n{}\n", section.title, section.body.trim_end());
+        if first_placed.is_none_or(|first| index < first) {
+            front.push_str(&text);
+            front.push('\n');
+        } else {
+            filled.push('\n');
+            filled.push_str(&text);
+        }
+    }
+    front + &filled
+}
+
+/// Text of an ATX heading like `## Summary`
+fn heading_text(line: &str) -> Option<&str> {
+    // More than three spaces of indentation make it a code block
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+    let line = &line[indent..];
+    let level = line.len() - line.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let text = &line[level..];
+    (text.is_empty() || text.starts_with([' ', '\t']))
+        .then(|| text.trim().trim_end_matches('#').trim())
+}
+
+/// `relative` below `root`, with each component matched ignoring ASCII case
+fn find_case_insensitive(root: &Path, relative: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    for component in relative.split('/') {
+        let exact = path.join(component);
+        path = if exact.exists() {
+            exact
+        } else {
+            fs::read_dir(&path)
+                .ok()?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .find(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.eq_ignore_ascii_case(component))
+                })?
+        };
+    }
+    path.is_file().then_some(path)
+}
@@ -0,0 +1,29 @@
+//! Everything a typical consumer needs to generate and apply recommendations
+//!
+//! ```no_run
+//! use recommender::prelude::*;
+//! ```
+
+#[cfg(feature = "git")]
+pub use crate::apply::GitPrTarget;
+pub use crate::apply::{ApplyOutcome, ApplyTarget, ClusterTarget, LocalDirTarget};
+pub use crate::aws_region::AwsRegion;
+pub use crate::config::{KubernetesConfig, RecommenderConfig, UpdaterConfig};
+pub use crate::error::{RecommenderError, Result};
+pub use crate::kubernetes::{
+    ContainerResources, DeploymentResources, KubernetesLoader, StaticWorkloadSource, WorkloadSource,
+};
+pub use crate::metrics::{MetricsProvider, Sample, Series, StaticMetricsProvider};
+pub use crate::output::RecommenderOutput;
+pub use crate::pipeline::{AnalysisRequest, RecommenderBuilder, run_analysis};
+pub use crate::policy::PolicySet;
+#[cfg(feature = "aws")]
+pub use crate::prometheus::PrometheusClient;
+pub use crate::quantity::Quantity;
+pub use crate::query::QueryTemplate;
+pub use crate::recommender::{
+    Progress, Recommender, ResourceRecommendation, recommend_from_samples,
+};
+pub use crate::stats::{StreamingStats, UsageStats};
+#[cfg(feature = "git")]
+pub use crate::updater::ManifestUpdater;
@@ -0,0 +1,961 @@
+use crate::aws_region::AwsRegion;
+use crate::cloud_auth::TokenSource;
+use crate::error::{PrometheusError, Result};
+use crate::http::shared_client;
+use crate::metrics::{MetricsProvider, Sample, Series};
+use crate::rate_limit::RateLimiter;
+use async_trait::async_trait;
+use aws_credential_types::Credentials;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::sign::v4;
+use aws_smithy_runtime_api::client::identity::Identity;
+use bytes::{Buf, Bytes};
+use log::{info, warn};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Method, Request, Response, StatusCode};
+use serde::de::{DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use url::Url;
+
+/// Default steps per range request, well below Prometheus' 11,000 point limit
+const DEFAULT_MAX_POINTS_PER_QUERY: u32 = 2_000;
+
+/// Body chunks buffered for the decoder of a range query response
+const BODY_CHUNKS_IN_FLIGHT: usize = 8;
+
+/// Retries of a throttled, failing or timed out query before it fails, by default
+pub const DEFAULT_QUERY_RETRIES: u32 = 5;
+
+/// Starting rate when throttled without `--amp-max-rps`
+const THROTTLED_REQUESTS_PER_SECOND: f64 = 5.0;
+
+/// Prometheus client, authenticating with AWS SigV4 for AMP, OAuth tokens for Google
+/// Cloud or Azure managed Prometheus, or basic auth or bearer tokens
+pub struct PrometheusClient {
+    client: Client,
+    endpoint: Url,
+    authentication: Authentication,
+    /// Set on throttling when not configured up front
+    rate_limiter: OnceLock<RateLimiter>,
+    max_points_per_query: u32,
+    max_retries: u32,
+    /// Per-request timeout overriding the HTTP client's
+    query_timeout: Option<Duration>,
+}
+
+/// How requests to the endpoint authenticate
+enum Authentication {
+    None,
+    Basic {
+        username: String,
+        password: String,
+    },
+    Bearer(String),
+    /// Short-lived tokens of a cloud identity, refreshed as they expire
+    OAuth(TokenSource),
+    SigV4 {
+        region: AwsRegion,
+        credentials: Credentials,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrometheusResponse {
+    pub status: String,
+    pub data: PrometheusData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrometheusData {
+    #[serde(rename = "resultType")]
+    pub result_type: String,
+    pub result: Vec<PrometheusResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrometheusResult {
+    pub metric: HashMap<String, String>,
+    pub value: Option<(f64, String)>,
+    pub values: Option<Vec<(f64, String)>>,
+}
+
+impl PrometheusClient {
+    /// Create a new Prometheus client signing requests with AWS credentials, for AMP
+    pub async fn new(endpoint: Url, region: AwsRegion) -> Result<Self> {
+        // Load AWS credentials from environment
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let credentials = config
+            .credentials_provider()
+            .ok_or(PrometheusError::AuthenticationFailed)?
+            .provide_credentials()
+            .await
+            .map_err(|_| PrometheusError::AuthenticationFailed)?;
+
+        let mut client = Self::unauthenticated(endpoint);
+        client.authentication = Authentication::SigV4 {
+            region,
+            credentials,
+        };
+        Ok(client)
+    }
+
+    /// Create a client sending requests without credentials, e.g. for self-hosted Prometheus
+    pub fn unauthenticated(endpoint: Url) -> Self {
+        Self {
+            client: shared_client(),
+            endpoint,
+            authentication: Authentication::None,
+            rate_limiter: OnceLock::new(),
+            max_points_per_query: DEFAULT_MAX_POINTS_PER_QUERY,
+            max_retries: DEFAULT_QUERY_RETRIES,
+            query_timeout: None,
+        }
+    }
+
+    /// Authenticate with HTTP basic auth instead, e.g. for Mimir or Thanos behind a proxy
+    pub fn with_basic_auth(mut self, username: String, password: String) -> Self {
+        crate::redact::register_secret(&password);
+        self.authentication = Authentication::Basic { username, password };
+        self
+    }
+
+    /// Authenticate with a bearer token instead
+    pub fn with_bearer_token(mut self, token: String) -> Self {
+        crate::redact::register_secret(&token);
+        self.authentication = Authentication::Bearer(token);
+        self
+    }
+
+    /// Authenticate with tokens of `source` instead, e.g. for Google Cloud Managed
+    /// Service for Prometheus or Azure Monitor managed Prometheus
+    pub fn with_token_source(mut self, source: TokenSource) -> Self {
+        self.authentication = Authentication::OAuth(source);
+        self
+    }
+
+    /// Credentials the queries are signed with, e.g. to reuse for S3 uploads
+    ///
+    /// None unless the client signs with SigV4.
+    pub fn credentials(&self) -> Option<&Credentials> {
+        match &self.authentication {
+            Authentication::SigV4 { credentials, .. } => Some(credentials),
+            _ => None,
+        }
+    }
+
+    /// Send requests through `client` instead of the shared one
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Throttle all requests through `limiter` to stay below AMP quotas
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = OnceLock::from(limiter);
+        self
+    }
+
+    /// Split range queries into windows of at most `points` steps each
+    ///
+    /// Lower values reduce peak memory for wide matchers at the cost of more requests.
+    pub fn with_max_points_per_query(mut self, points: u32) -> Self {
+        self.max_points_per_query = points.max(1);
+        self
+    }
+
+    /// Retry queries that were throttled, failed with a 5xx or timed out up to `retries` times
+    pub fn with_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Give up on a single request after `timeout` instead of the HTTP client's timeout
+    ///
+    /// Timed out requests are retried like failed ones.
+    pub fn with_query_timeout(mut self, timeout: Duration) -> Self {
+        self.query_timeout = Some(timeout);
+        self
+    }
+
+    /// Execute a PromQL instant query, returning the response as sent
+    ///
+    /// [`MetricsProvider::query`] decodes the samples instead.
+    pub async fn query_raw(&self, query: &str) -> Result<PrometheusResponse> {
+        let mut url = self.endpoint.clone();
+        url.set_path(&format!(
+            "{}/api/v1/query",
+            url.path().trim_end_matches('/')
+        ));
+        url.query_pairs_mut().append_pair("query", query);
+
+        self.execute_request(Method::GET, url).await
+    }
+
+    /// Execute a PromQL range query, returning the response as sent
+    ///
+    /// [`MetricsProvider::query_range`] decodes the samples instead.
+    pub async fn query_range_raw(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+    ) -> Result<PrometheusResponse> {
+        let url = self.range_url(query, start, end, step);
+        self.execute_request(Method::GET, url).await
+    }
+
+    /// Label sets of all series matching any of `matchers` within the window
+    pub async fn series(
+        &self,
+        matchers: &[&str],
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Vec<HashMap<String, String>>> {
+        let url = self.metadata_url("series", matchers, start, end);
+        let response: DataResponse<Vec<HashMap<String, String>>> =
+            self.send(Method::GET, url).await?;
+        check_status(&response.status)?;
+        Ok(response.data)
+    }
+
+    /// Values of `label` on series matching any of `matchers` within the window
+    ///
+    /// Without matchers all series of the workspace are considered.
+    pub async fn label_values(
+        &self,
+        label: &str,
+        matchers: &[&str],
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Result<Vec<String>> {
+        let url = self.metadata_url(&format!("label/{}/values", label), matchers, start, end);
+        let response: DataResponse<Vec<String>> = self.send(Method::GET, url).await?;
+        check_status(&response.status)?;
+        Ok(response.data)
+    }
+
+    /// URL of a metadata endpoint like `series` under `/api/v1`
+    fn metadata_url(
+        &self,
+        endpoint: &str,
+        matchers: &[&str],
+        start: SystemTime,
+        end: SystemTime,
+    ) -> Url {
+        let mut url = self.endpoint.clone();
+        url.set_path(&format!(
+            "{}/api/v1/{}",
+            url.path().trim_end_matches('/'),
+            endpoint
+        ));
+
+        let mut pairs = url.query_pairs_mut();
+        for matcher in matchers {
+            pairs.append_pair("match[]", matcher);
+        }
+        pairs
+            .append_pair("start", &unix_seconds(start).to_string())
+            .append_pair("end", &unix_seconds(end).to_string());
+        drop(pairs);
+        url
+    }
+
+    /// URL of a range query against the workspace
+    fn range_url(&self, query: &str, start: SystemTime, end: SystemTime, step: Duration) -> Url {
+        let mut url = self.endpoint.clone();
+        url.set_path(&format!(
+            "{}/api/v1/query_range",
+            url.path().trim_end_matches('/')
+        ));
+
+        url.query_pairs_mut()
+            .append_pair("query", query)
+            .append_pair("start", &unix_seconds(start).to_string())
+            .append_pair("end", &unix_seconds(end).to_string())
+            .append_pair("step", &format!("{}s", step.as_secs()));
+        url
+    }
+
+    /// Execute an authenticated HTTP request
+    async fn execute_request(&self, method: Method, url: Url) -> Result<PrometheusResponse> {
+        let prom_response: PrometheusResponse = self.send(method, url).await?;
+        check_status(&prom_response.status)?;
+
+        Ok(prom_response)
+    }
+
+    /// Send an authenticated request and decode the body of a successful response
+    async fn send<T: DeserializeOwned>(&self, method: Method, url: Url) -> Result<T> {
+        let response = self.execute(method, url).await?;
+        // Parse from the raw bytes, skipping reqwest's intermediate copies
+        let body = response.bytes().await.map_err(PrometheusError::Request)?;
+        serde_json::from_slice(&body)
+            .map_err(|e| PrometheusError::InvalidResponse(e.to_string()).into())
+    }
+
+    /// Send an authenticated request, returning the response once it succeeded
+    ///
+    /// Throttled requests, transient server errors and timeouts are retried after
+    /// the server's `Retry-After` (or a jittered exponential backoff). Throttling
+    /// also lowers the query rate for the rest of the run.
+    async fn execute(&self, method: Method, url: Url) -> Result<Response> {
+        let mut retries = 0;
+        loop {
+            // Wait for the rate limit before signing so the signature stays fresh
+            if let Some(limiter) = self.rate_limiter.get() {
+                limiter.acquire().await;
+            }
+
+            let request = self.authenticate(method.clone(), &url).await?;
+            let response = match self.client.execute(request).await {
+                Ok(response) => response,
+                Err(e) if (e.is_timeout() || e.is_connect()) && retries < self.max_retries => {
+                    let delay = retry_backoff(retries);
+                    retries += 1;
+                    warn!(
+                        "Prometheus query failed ({}), retrying in {:.1}s ({}/{})",
+                        e,
+                        delay.as_secs_f64(),
+                        retries,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                Err(e) => return Err(PrometheusError::Request(e).into()),
+            };
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+
+            let retry_after = retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            if retries < self.max_retries {
+                let delay = retry_after.unwrap_or_else(|| retry_backoff(retries));
+                if is_throttled(status, &body) {
+                    retries += 1;
+                    warn!(
+                        "AMP throttled the query (HTTP {}), retrying in {:.1}s ({}/{})",
+                        status.as_u16(),
+                        delay.as_secs_f64(),
+                        retries,
+                        self.max_retries
+                    );
+                    self.back_off(delay);
+                    continue;
+                }
+                if is_transient(status) {
+                    retries += 1;
+                    warn!(
+                        "Prometheus returned HTTP {}, retrying in {:.1}s ({}/{})",
+                        status.as_u16(),
+                        delay.as_secs_f64(),
+                        retries,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+            }
+
+            return Err(PrometheusError::HttpStatus {
+                status: status.as_u16(),
+                body,
+            }
+            .into());
+        }
+    }
+
+    /// Slow down all further queries after being throttled
+    ///
+    /// Without a configured limiter one is started at [`THROTTLED_REQUESTS_PER_SECOND`].
+    fn back_off(&self, delay: Duration) {
+        let mut created = false;
+        let limiter = self.rate_limiter.get_or_init(|| {
+            created = true;
+            RateLimiter::per_second(THROTTLED_REQUESTS_PER_SECOND)
+        });
+        if !created {
+            limiter.slow_down(0.5);
+        }
+        limiter.pause(delay);
+        info!(
+            "Limiting AMP queries to {:.2} per second for the rest of the run",
+            limiter.requests_per_second()
+        );
+    }
+
+    /// Build a request carrying the client's credentials
+    async fn authenticate(&self, method: Method, url: &Url) -> Result<Request> {
+        let mut request = self.client.request(method, url.clone());
+        if let Some(timeout) = self.query_timeout {
+            request = request.timeout(timeout);
+        }
+        let request = match &self.authentication {
+            Authentication::None => request,
+            Authentication::Basic { username, password } => {
+                request.basic_auth(username, Some(password))
+            }
+            Authentication::Bearer(token) => request.bearer_auth(token),
+            Authentication::OAuth(source) => request.bearer_auth(source.token().await?),
+            Authentication::SigV4 {
+                region,
+                credentials,
+            } => {
+                let request = request.build().map_err(PrometheusError::Request)?;
+                return sign(request, region, credentials);
+            }
+        };
+        request
+            .build()
+            .map_err(|e| PrometheusError::Request(e).into())
+    }
+
+    /// Range query decoding samples straight into [`Series`]
+    ///
+    /// The series of each chunk are merged into those of the earlier ones by
+    /// their label set.
+    async fn query_series(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+    ) -> Result<Vec<Series>> {
+        let mut series: Vec<Series> = Vec::new();
+        let mut index: HashMap<BTreeMap<String, String>, usize> = HashMap::new();
+        self.stream_chunks(query, start, end, step, &mut |chunk: Series| {
+            let key: BTreeMap<String, String> = chunk.labels.clone().into_iter().collect();
+            match index.get(&key) {
+                Some(&i) => series[i].samples.extend(chunk.samples),
+                None => {
+                    index.insert(key, series.len());
+                    series.push(chunk);
+                }
+            }
+        })
+        .await?;
+        Ok(series)
+    }
+
+    /// Range query handing each series to `on_series` as it is decoded
+    ///
+    /// Long windows are split into chunks of at most `max_points_per_query` steps,
+    /// so a wide matcher never has to be held in one huge response. A series
+    /// spanning several chunks is handed over once per chunk, in order.
+    async fn stream_chunks(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+        on_series: &mut (dyn FnMut(Series) + Send),
+    ) -> Result<()> {
+        let step_size = step.max(Duration::from_secs(1));
+        let chunk = step_size * self.max_points_per_query;
+
+        let mut chunk_start = start;
+        while chunk_start <= end {
+            let chunk_end = (chunk_start + chunk).min(end);
+            let url = self.range_url(query, chunk_start, chunk_end, step);
+            let response = self.execute(Method::GET, url).await?;
+            decode_range_stream(response, on_series).await?;
+            // Steps are evaluated from the start, the next chunk begins at the one after its end
+            chunk_start = chunk_end + step_size;
+        }
+
+        Ok(())
+    }
+
+    /// Instant query at `time` decoding samples straight into [`Series`]
+    async fn instant_series(&self, query: &str, time: SystemTime) -> Result<Vec<Series>> {
+        let mut url = self.endpoint.clone();
+        url.set_path(&format!(
+            "{}/api/v1/query",
+            url.path().trim_end_matches('/')
+        ));
+        url.query_pairs_mut()
+            .append_pair("query", query)
+            .append_pair("time", &unix_seconds(time).to_string());
+
+        let response: InstantResponse = self.send(Method::GET, url).await?;
+        check_status(&response.status)?;
+        Ok(response
+            .data
+            .result
+            .into_iter()
+            .filter_map(|result| {
+                Some(Series {
+                    labels: result.metric,
+                    samples: vec![result.value?.0],
+                })
+            })
+            .collect())
+    }
+}
+
+/// Sign `request` for AMP with AWS SigV4
+fn sign(mut request: Request, region: &AwsRegion, credentials: &Credentials) -> Result<Request> {
+    let signable_request = SignableRequest::new(
+        request.method().as_str(),
+        request.url().as_str(),
+        std::iter::empty(),
+        SignableBody::Bytes(&[]),
+    )
+    .map_err(|e| PrometheusError::ConnectionError(e.to_string()))?;
+
+    let signing_settings = SigningSettings::default();
+    let identity: Identity = credentials.clone().into();
+    let signing_params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(region.as_str())
+        .name("aps")
+        .time(SystemTime::now())
+        .settings(signing_settings)
+        .build()
+        .map_err(|e| PrometheusError::ConnectionError(e.to_string()))?
+        .into();
+
+    let (signing_instructions, _) =
+        aws_sigv4::http_request::sign(signable_request, &signing_params)
+            .map_err(|e| PrometheusError::ConnectionError(e.to_string()))?
+            .into_parts();
+
+    // Apply signature headers
+    for (name, value) in signing_instructions.headers() {
+        let header_name: reqwest::header::HeaderName = name.parse().unwrap();
+        let header_value: reqwest::header::HeaderValue = value.parse().unwrap();
+        request.headers_mut().insert(header_name, header_value);
+    }
+
+    Ok(request)
+}
+
+/// Whether the response is AMP or an AWS front end asking us to slow down
+fn is_throttled(status: StatusCode, body: &str) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || body.contains("ThrottlingException")
+        || body.contains("TooManyRequestsException")
+}
+
+/// Server errors a retry may get past, e.g. an overloaded querier or a gateway timeout
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Delay requested by a `Retry-After` header, in seconds or as an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds));
+    }
+
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+/// Exponential backoff when the server gave no `Retry-After`: up to 1s, 2s, 4s, ...
+///
+/// Up to half of each delay is random, so containers analyzed in parallel don't
+/// retry in lockstep.
+fn retry_backoff(retries: u32) -> Duration {
+    Duration::from_secs(1 << retries.min(5)).mul_f64(1.0 - 0.5 * fastrand::f64())
+}
+
+/// Whole seconds since the epoch, as the HTTP API expects
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Fail unless Prometheus reported success
+fn check_status(status: &str) -> Result<()> {
+    if status != "success" {
+        return Err(
+            PrometheusError::QueryError(format!("Prometheus returned status: {}", status)).into(),
+        );
+    }
+    Ok(())
+}
+
+/// Response of the metadata endpoints, e.g. label values
+#[derive(Deserialize)]
+struct DataResponse<T> {
+    status: String,
+    data: T,
+}
+
+/// Series of a range query response and their samples
+type DecodedSeries = (HashMap<String, String>, Vec<Sample>);
+
+/// Decode a range query response as it arrives, handing each series to `on_series`
+///
+/// The body is parsed on a blocking thread fed chunk by chunk, so only a few
+/// chunks and the series being decoded are held at a time, however long the
+/// window is.
+async fn decode_range_stream(
+    mut response: Response,
+    on_series: &mut (dyn FnMut(Series) + Send),
+) -> Result<()> {
+    let (chunk_tx, chunk_rx) = tokio::sync::mpsc::channel::<Bytes>(BODY_CHUNKS_IN_FLIGHT);
+    let (series_tx, mut series_rx) = tokio::sync::mpsc::unbounded_channel::<DecodedSeries>();
+    let decoder = tokio::task::spawn_blocking(move || {
+        decode_range_body(
+            ChunkReader {
+                chunks: chunk_rx,
+                current: Bytes::new(),
+            },
+            &series_tx,
+        )
+    });
+
+    let mut body_error = None;
+    loop {
+        while let Ok((labels, samples)) = series_rx.try_recv() {
+            on_series(Series { labels, samples });
+        }
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                // The decoder stops early on invalid JSON, its error is reported below
+                if chunk_tx.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                body_error = Some(e);
+                break;
+            }
+        }
+    }
+    drop(chunk_tx);
+    while let Some((labels, samples)) = series_rx.recv().await {
+        on_series(Series { labels, samples });
+    }
+
+    let status = decoder
+        .await
+        .map_err(|e| PrometheusError::InvalidResponse(e.to_string()))?;
+    if let Some(e) = body_error {
+        return Err(PrometheusError::Request(e).into());
+    }
+    check_status(&status?)
+}
+
+/// Parse a range query response from `body`, sending each series on as soon as it
+/// is complete, and return the response status
+fn decode_range_body(
+    body: impl Read,
+    series: &tokio::sync::mpsc::UnboundedSender<DecodedSeries>,
+) -> Result<String> {
+    let mut deserializer = serde_json::Deserializer::from_reader(body);
+    let status = RangeResponseSeed { series }
+        .deserialize(&mut deserializer)
+        .and_then(|status| deserializer.end().map(|()| status))
+        .map_err(|e| PrometheusError::InvalidResponse(e.to_string()))?;
+    Ok(status)
+}
+
+/// Body of a response as it arrives, read by the blocking decoder
+struct ChunkReader {
+    chunks: tokio::sync::mpsc::Receiver<Bytes>,
+    current: Bytes,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.current.is_empty() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.current.len());
+        buf[..len].copy_from_slice(&self.current[..len]);
+        self.current.advance(len);
+        Ok(len)
+    }
+}
+
+/// `{"status": ..., "data": {"result": [...]}}` with the results sent on one by one
+struct RangeResponseSeed<'a> {
+    series: &'a tokio::sync::mpsc::UnboundedSender<DecodedSeries>,
+}
+
+impl<'de> DeserializeSeed<'de> for RangeResponseSeed<'_> {
+    /// Status of the response
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<String, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RangeResponseSeed<'_> {
+    type Value = String;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Prometheus range query response")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<String, A::Error> {
+        let mut status = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "status" => status = Some(map.next_value()?),
+                "data" => map.next_value_seed(RangeDataSeed {
+                    series: self.series,
+                })?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        status.ok_or_else(|| serde::de::Error::missing_field("status"))
+    }
+}
+
+/// `data` of a range query response, its `result` sent on one series at a time
+struct RangeDataSeed<'a> {
+    series: &'a tokio::sync::mpsc::UnboundedSender<DecodedSeries>,
+}
+
+impl<'de> DeserializeSeed<'de> for RangeDataSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RangeDataSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the data of a range query response")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "result" {
+                map.next_value_seed(RangeResultsSeed {
+                    series: self.series,
+                })?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct RangeResultsSeed<'a> {
+    series: &'a tokio::sync::mpsc::UnboundedSender<DecodedSeries>,
+}
+
+impl<'de> DeserializeSeed<'de> for RangeResultsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RangeResultsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of series")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        while let Some(result) = seq.next_element::<RangeResult>()? {
+            let samples = result.values.into_iter().map(|s| s.0).collect();
+            // A closed channel means the query was dropped, the rest is read to the end anyway
+            let _ = self.series.send((result.metric, samples));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct RangeResult {
+    metric: HashMap<String, String>,
+    #[serde(default)]
+    values: Vec<RangeSample>,
+}
+
+/// Instant query response, at most one sample per series
+#[derive(Deserialize)]
+struct InstantResponse {
+    status: String,
+    data: InstantData,
+}
+
+#[derive(Deserialize)]
+struct InstantData {
+    result: Vec<InstantResult>,
+}
+
+#[derive(Deserialize)]
+struct InstantResult {
+    metric: HashMap<String, String>,
+    #[serde(default)]
+    value: Option<RangeSample>,
+}
+
+/// `[timestamp, "value"]` pair parsed in place
+struct RangeSample(Sample);
+
+impl<'de> Deserialize<'de> for RangeSample {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Pair;
+
+        impl<'de> Visitor<'de> for Pair {
+            type Value = Sample;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a [timestamp, \"value\"] pair")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<Sample, A::Error> {
+                let timestamp = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+                let NumericString(value) = seq
+                    .next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+                Ok(Sample { timestamp, value })
+            }
+        }
+
+        deserializer.deserialize_seq(Pair).map(RangeSample)
+    }
+}
+
+/// Sample value, a number in a string, parsed without allocating
+struct NumericString(f64);
+
+impl<'de> Deserialize<'de> for NumericString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct Number;
+
+        impl Visitor<'_> for Number {
+            type Value = f64;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number in a string")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> std::result::Result<f64, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(Number).map(NumericString)
+    }
+}
+
+#[async_trait]
+impl MetricsProvider for PrometheusClient {
+    async fn query_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+    ) -> Result<Vec<Series>> {
+        self.query_series(query, start, end, step).await
+    }
+
+    async fn stream_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+        on_series: &mut (dyn FnMut(Series) + Send),
+    ) -> Result<()> {
+        self.stream_chunks(query, start, end, step, on_series).await
+    }
+
+    async fn query(&self, query: &str, time: SystemTime) -> Result<Vec<Series>> {
+        self.instant_series(query, time).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_range_body_one_series_at_a_time() {
+        let body = br#"{"status":"success","data":{"resultType":"matrix","result":[
+            {"metric":{"pod":"a"},"values":[[1,"0.5"],[2,"1e3"]]},
+            {"metric":{"pod":"b"},"values":[[1,"NaN"]]}]}}"#;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let status = decode_range_body(&body[..], &tx).unwrap();
+        assert_eq!(status, "success");
+
+        let (labels, samples) = rx.try_recv().unwrap();
+        assert_eq!(labels["pod"], "a");
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[1].timestamp, 2.0);
+        assert_eq!(samples[1].value, 1000.0);
+        let (labels, samples) = rx.try_recv().unwrap();
+        assert_eq!(labels["pod"], "b");
+        assert!(samples[0].value.is_nan());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn retry_backoff_doubles_with_up_to_half_jitter() {
+        for retries in 0..8 {
+            let full = Duration::from_secs(1 << retries.min(5));
+            let delays: Vec<Duration> = (0..50).map(|_| retry_backoff(retries)).collect();
+            assert!(
+                delays
+                    .iter()
+                    .all(|&delay| delay > full / 2 && delay <= full)
+            );
+            assert!(delays.iter().any(|&delay| delay != delays[0]));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_range_body() {
+        let body = br#"{"status":"success","data":{"result":[{"metric":{},"values":[[1,"#;
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        assert!(decode_range_body(&body[..], &tx).is_err());
+    }
+}
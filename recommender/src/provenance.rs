@@ -0,0 +1,223 @@
+//! Provenance of applied recommendations, recorded on the workloads themselves
+//!
+//! Once applied values are live, patched in cluster mode or from a pull request a
+//! later run finds merged and rolled out, the Deployment gets a
+//! [`PROVENANCE_ANNOTATION`] with the run's timestamp, the tool version and the
+//! values. The next run compares the running resources with it: a difference was
+//! made by hand, otherwise a new recommendation follows a change in usage.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::Client;
+use kube::api::{Api, Patch, PatchParams};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::KubernetesError::ApiError;
+use crate::Result;
+use crate::history::RunHistory;
+use crate::kubernetes::DeploymentResources;
+use crate::quantity::Quantity;
+use crate::recommender::ResourceRecommendation;
+
+/// Annotation holding the JSON-encoded [`Provenance`] of the last apply
+pub const PROVENANCE_ANNOTATION: &str = "rightsizing.k8s.io/last-applied";
+
+/// Version of this tool, recorded with each apply
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Requests and limits applied to one container
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AppliedResources {
+    pub cpu_request: Quantity,
+    pub cpu_limit: Quantity,
+    pub memory_request: Quantity,
+    pub memory_limit: Quantity,
+}
+
+impl AppliedResources {
+    /// Whether CPU and memory requests and limits are all the applied ones
+    fn matches(&self, current: [Option<Quantity>; 4]) -> bool {
+        current
+            == [
+                Some(self.cpu_request),
+                Some(self.cpu_limit),
+                Some(self.memory_request),
+                Some(self.memory_limit),
+            ]
+    }
+}
+
+/// When, by which version and from where values were applied to a workload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub applied_at: DateTime<Utc>,
+    pub version: String,
+    /// Pull request the values were merged from, None when patched in the cluster
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pr_url: Option<String>,
+    /// Applied values keyed by container
+    pub containers: BTreeMap<String, AppliedResources>,
+}
+
+/// Why a workload's resources or recommendation moved since the last apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Drift {
+    /// Still running the applied values, any new recommendation follows usage
+    Usage,
+    /// Resources were edited since they were applied
+    Manual,
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Drift::Usage => "usage",
+            Drift::Manual => "manual",
+        })
+    }
+}
+
+impl Provenance {
+    /// Values of `recommendations` applied at `applied_at` by this version
+    pub fn new(applied_at: DateTime<Utc>, recommendations: &[&ResourceRecommendation]) -> Self {
+        Self {
+            applied_at,
+            version: VERSION.to_string(),
+            pr_url: None,
+            containers: recommendations
+                .iter()
+                .map(|rec| {
+                    (
+                        rec.container.clone(),
+                        AppliedResources {
+                            cpu_request: rec.recommended_cpu_request,
+                            cpu_limit: rec.recommended_cpu_limit,
+                            memory_request: rec.recommended_memory_request,
+                            memory_limit: rec.recommended_memory_limit,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn with_pr_url(mut self, pr_url: Option<String>) -> Self {
+        self.pr_url = pr_url;
+        self
+    }
+
+    /// Provenance recorded in a Deployment's annotations, if any
+    pub fn from_annotations(annotations: &BTreeMap<String, String>) -> Option<Self> {
+        let value = annotations.get(PROVENANCE_ANNOTATION)?;
+        serde_json::from_str(value)
+            .inspect_err(|e| warn!("Ignoring invalid {}: {}", PROVENANCE_ANNOTATION, e))
+            .ok()
+    }
+
+    /// Value of the [`PROVENANCE_ANNOTATION`]
+    pub fn annotation_value(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Whether the workload runs exactly the applied values
+    pub fn is_running(&self, deployment: &DeploymentResources) -> bool {
+        self.containers.iter().all(|(name, applied)| {
+            deployment
+                .containers
+                .iter()
+                .find(|c| c.name == *name)
+                .is_some_and(|c| {
+                    applied.matches([c.cpu_request, c.cpu_limit, c.memory_request, c.memory_limit])
+                })
+        })
+    }
+
+    /// How the container of `rec` moved since the apply, None if it wasn't applied
+    pub fn drift(&self, rec: &ResourceRecommendation) -> Option<Drift> {
+        let applied = self.containers.get(&rec.container)?;
+        let current = [
+            rec.current_cpu_request,
+            rec.current_cpu_limit,
+            rec.current_memory_request,
+            rec.current_memory_limit,
+        ];
+        if applied.matches(current) {
+            Some(Drift::Usage)
+        } else {
+            Some(Drift::Manual)
+        }
+    }
+}
+
+/// Record `provenance` on the Deployment's metadata, leaving its pod template alone
+pub async fn annotate_deployment(
+    client: Client,
+    namespace: &str,
+    name: &str,
+    provenance: &Provenance,
+) -> Result<()> {
+    let api: Api<Deployment> = Api::namespaced(client, namespace);
+    let patch = json!({
+        "metadata": {
+            "annotations": { PROVENANCE_ANNOTATION: provenance.annotation_value() }
+        }
+    });
+    api.patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await
+        .map_err(ApiError)?;
+    Ok(())
+}
+
+/// Annotate workloads whose pull request from an earlier run is now merged and rolled out
+///
+/// A workload counts as merged once it runs the values recorded in `history` for
+/// it and carries no newer provenance. Returns how many workloads were annotated,
+/// failures are logged.
+pub async fn record_merged_applies(
+    client: Client,
+    history: &RunHistory,
+    deployments: &[DeploymentResources],
+) -> usize {
+    let mut recorded = 0;
+    for (workload, provenance) in history.awaiting_merge() {
+        let Some((namespace, name)) = workload.split_once('/') else {
+            continue;
+        };
+        let Some(deployment) = deployments
+            .iter()
+            .find(|d| d.namespace == namespace && d.name == name)
+        else {
+            continue;
+        };
+        if deployment
+            .provenance
+            .as_ref()
+            .is_some_and(|p| p.applied_at >= provenance.applied_at)
+            || !provenance.is_running(deployment)
+        {
+            continue;
+        }
+
+        match annotate_deployment(client.clone(), namespace, name, provenance).await {
+            Ok(()) => {
+                info!(
+                    "Deployment {} runs the values applied on {}, recorded their provenance",
+                    workload,
+                    provenance.applied_at.to_rfc3339()
+                );
+                recorded += 1;
+            }
+            Err(e) => warn!(
+                "Failed to record apply provenance on deployment {}: {}",
+                workload, e
+            ),
+        }
+    }
+    recorded
+}
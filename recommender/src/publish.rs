@@ -0,0 +1,293 @@
+//! Recommendations published on the analyzed Deployments themselves
+//!
+//! Annotations on the Deployment (not its pod template, so nothing rolls out) and
+//! Events on it make recommendations show up in `kubectl describe` and in any
+//! dashboard reading the API, without distributing reports. Controllers wanting
+//! the whole output read it from a ConfigMap instead.
+
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{ConfigMap, Event, EventSource, ObjectReference};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, Time};
+use kube::Client;
+use kube::api::{Api, Patch, PatchParams, PostParams};
+use log::{info, warn};
+use serde_json::json;
+
+use crate::KubernetesError::ApiError;
+use crate::clock::{SharedClock, SystemClock};
+use crate::config::PublishTarget;
+use crate::output::RecommenderOutput;
+use crate::quantity::{Quantity, display_optional};
+use crate::recommender::ResourceRecommendation;
+use crate::{ConfigError, Result};
+
+/// Prefix of the recommendation annotations
+pub const ANNOTATION_PREFIX: &str = "rightsizing.k8s.io/";
+
+/// Reason of the published Events
+pub const EVENT_REASON: &str = "RightsizingRecommendation";
+
+/// Reporting component of the published Events
+const EVENT_COMPONENT: &str = "k8s-autorightsizing";
+
+/// The API server rejects longer Event messages
+const EVENT_MESSAGE_MAX: usize = 1024;
+
+/// Key of the output JSON in the ConfigMap written by [`publish_configmap`]
+pub const CONFIGMAP_OUTPUT_KEY: &str = "recommendations.json";
+
+/// Largest ConfigMap the API server stores
+const CONFIGMAP_MAX_BYTES: usize = 1024 * 1024;
+
+/// Writes recommendations to Deployments as annotations and/or Events
+pub struct RecommendationPublisher {
+    client: Client,
+    targets: Vec<PublishTarget>,
+    clock: SharedClock,
+}
+
+impl RecommendationPublisher {
+    pub fn new(client: Client, targets: Vec<PublishTarget>) -> Self {
+        Self {
+            client,
+            targets,
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Timestamp annotations and Events with `clock` instead of the system time
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Publish the recommendations of each Deployment, returning how many were published
+    ///
+    /// Failures are logged per Deployment and don't stop the others. CronJobs and
+    /// Jobs are left out.
+    pub async fn publish(&self, recommendations: &[ResourceRecommendation]) -> usize {
+        let mut by_deployment: BTreeMap<(&str, &str), Vec<&ResourceRecommendation>> =
+            BTreeMap::new();
+        for rec in recommendations {
+            if !rec.workload_kind.is_deployment() {
+                continue;
+            }
+            by_deployment
+                .entry((rec.namespace.as_str(), rec.deployment.as_str()))
+                .or_default()
+                .push(rec);
+        }
+
+        let mut published = 0;
+        for ((namespace, name), recs) in by_deployment {
+            match self.publish_deployment(namespace, name, &recs).await {
+                Ok(()) => published += 1,
+                Err(e) => warn!(
+                    "Failed to publish recommendations on deployment {}/{}: {}",
+                    namespace, name, e
+                ),
+            }
+        }
+        info!("Published recommendations on {} deployments", published);
+        published
+    }
+
+    async fn publish_deployment(
+        &self,
+        namespace: &str,
+        name: &str,
+        recommendations: &[&ResourceRecommendation],
+    ) -> Result<()> {
+        let api: Api<Deployment> = Api::namespaced(self.client.clone(), namespace);
+        let now = self.clock.now_utc();
+
+        let deployment = if self.targets.contains(&PublishTarget::Annotations) {
+            let patch = json!({
+                "metadata": {
+                    "annotations": recommendation_annotations(recommendations, &now.to_rfc3339())
+                }
+            });
+            api.patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+                .await
+                .map_err(ApiError)?
+        } else {
+            api.get(name).await.map_err(ApiError)?
+        };
+
+        if self.targets.contains(&PublishTarget::Events) {
+            let event = Event {
+                metadata: ObjectMeta {
+                    generate_name: Some(format!("{}.", name)),
+                    namespace: Some(namespace.to_string()),
+                    ..Default::default()
+                },
+                involved_object: ObjectReference {
+                    api_version: Some("apps/v1".to_string()),
+                    kind: Some("Deployment".to_string()),
+                    name: Some(name.to_string()),
+                    namespace: Some(namespace.to_string()),
+                    uid: deployment.metadata.uid,
+                    resource_version: deployment.metadata.resource_version,
+                    ..Default::default()
+                },
+                reason: Some(EVENT_REASON.to_string()),
+                message: Some(event_message(recommendations)),
+                type_: Some("Normal".to_string()),
+                source: Some(EventSource {
+                    component: Some(EVENT_COMPONENT.to_string()),
+                    host: None,
+                }),
+                reporting_component: Some(EVENT_COMPONENT.to_string()),
+                first_timestamp: Some(Time(now)),
+                last_timestamp: Some(Time(now)),
+                count: Some(1),
+                ..Default::default()
+            };
+            Api::<Event>::namespaced(self.client.clone(), namespace)
+                .create(&PostParams::default(), &event)
+                .await
+                .map_err(ApiError)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the whole output as JSON to the ConfigMap `reference` (namespace/name)
+///
+/// The ConfigMap is created or replaced through server-side apply, its
+/// `recommendations.json` key holding the same document as `--output json`.
+pub async fn publish_configmap(
+    client: Client,
+    reference: &str,
+    output: &RecommenderOutput,
+) -> Result<()> {
+    let (namespace, name) = reference.split_once('/').ok_or_else(|| {
+        ConfigError::InvalidValue(format!(
+            "Output ConfigMap must be given as namespace/name, got '{}'",
+            reference
+        ))
+    })?;
+
+    let json = serde_json::to_string(output).map_err(|e| {
+        crate::RecommenderError::Other(format!("Failed to serialize output: {}", e))
+    })?;
+    if json.len() > CONFIGMAP_MAX_BYTES {
+        return Err(ConfigError::InvalidValue(format!(
+            "Output of {} bytes exceeds the {} bytes a ConfigMap holds, narrow it down with --namespace",
+            json.len(),
+            CONFIGMAP_MAX_BYTES
+        ))
+        .into());
+    }
+
+    let configmap = json!({
+        "apiVersion": "v1",
+        "kind": "ConfigMap",
+        "metadata": {
+            "name": name,
+            "namespace": namespace,
+            "labels": { "app.kubernetes.io/managed-by": EVENT_COMPONENT },
+            "annotations": {
+                format!("{}recommended-at", ANNOTATION_PREFIX): output.metadata.timestamp,
+            },
+        },
+        "data": { CONFIGMAP_OUTPUT_KEY: json },
+    });
+    Api::<ConfigMap>::namespaced(client, namespace)
+        .patch(
+            name,
+            &PatchParams::apply(EVENT_COMPONENT).force(),
+            &Patch::Apply(&configmap),
+        )
+        .await
+        .map_err(ApiError)?;
+
+    info!(
+        "Published {} recommendations to ConfigMap {}",
+        output.recommendations.len(),
+        reference
+    );
+    Ok(())
+}
+
+/// Container a recommendation is for, with its day profile if it has one
+fn container_label(rec: &ResourceRecommendation) -> String {
+    match rec.day_profile {
+        Some(profile) => format!("{}/{}", rec.container, profile),
+        None => rec.container.clone(),
+    }
+}
+
+/// Annotations summarizing the recommendations of one Deployment
+///
+/// A single recommendation gives plain values like `250m`, several give
+/// `container=value` pairs like `app=250m,sidecar=50m`.
+pub fn recommendation_annotations(
+    recommendations: &[&ResourceRecommendation],
+    recommended_at: &str,
+) -> BTreeMap<String, String> {
+    let values = |value: fn(&ResourceRecommendation) -> Quantity| {
+        if let [rec] = recommendations {
+            return value(rec).to_string();
+        }
+        recommendations
+            .iter()
+            .map(|rec| format!("{}={}", container_label(rec), value(rec)))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    BTreeMap::from([
+        (
+            format!("{}recommended-cpu-request", ANNOTATION_PREFIX),
+            values(|rec| rec.recommended_cpu_request),
+        ),
+        (
+            format!("{}recommended-cpu-limit", ANNOTATION_PREFIX),
+            values(|rec| rec.recommended_cpu_limit),
+        ),
+        (
+            format!("{}recommended-memory-request", ANNOTATION_PREFIX),
+            values(|rec| rec.recommended_memory_request),
+        ),
+        (
+            format!("{}recommended-memory-limit", ANNOTATION_PREFIX),
+            values(|rec| rec.recommended_memory_limit),
+        ),
+        (
+            format!("{}recommended-at", ANNOTATION_PREFIX),
+            recommended_at.to_string(),
+        ),
+    ])
+}
+
+/// One line per container with the current and recommended requests
+pub fn event_message(recommendations: &[&ResourceRecommendation]) -> String {
+    let mut message = recommendations
+        .iter()
+        .map(|rec| {
+            format!(
+                "{}: CPU request {} -> {}, memory request {} -> {} (confidence {:.0}%)",
+                container_label(rec),
+                display_optional(rec.current_cpu_request),
+                rec.recommended_cpu_request,
+                display_optional(rec.current_memory_request),
+                rec.recommended_memory_request,
+                rec.confidence * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    if message.len() > EVENT_MESSAGE_MAX {
+        let mut end = EVENT_MESSAGE_MAX - 3;
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        message.truncate(end);
+        message.push_str("...");
+    }
+    message
+}
@@ -0,0 +1,251 @@
+//! Recommendation metrics pushed to a Prometheus Pushgateway
+//!
+//! Every run replaces the metrics of its `job` and `cluster` group, so workloads
+//! that went away stop being reported. Dashboards can then graph recommended
+//! against requested resources, and the waste between them, over time.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use chrono::{DateTime, Utc};
+use log::info;
+use reqwest::Client;
+use reqwest::header::CONTENT_TYPE;
+use url::Url;
+
+use crate::error::{RecommenderError, Result};
+use crate::http::shared_client;
+use crate::quantity::Quantity;
+use crate::recommender::ResourceRecommendation;
+use crate::redact::redact_url;
+
+/// Content type of the text exposition format
+const EXPOSITION_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Gauges per container: name, help text and value, None to leave the series out
+type Gauge = (
+    &'static str,
+    &'static str,
+    fn(&ResourceRecommendation) -> Option<f64>,
+);
+
+const GAUGES: [Gauge; 12] = [
+    (
+        "rightsizing_recommended_cpu_request_cores",
+        "Recommended CPU request",
+        |rec| Some(rec.recommended_cpu_request.value()),
+    ),
+    (
+        "rightsizing_recommended_cpu_limit_cores",
+        "Recommended CPU limit",
+        |rec| Some(rec.recommended_cpu_limit.value()),
+    ),
+    (
+        "rightsizing_recommended_memory_request_bytes",
+        "Recommended memory request",
+        |rec| Some(rec.recommended_memory_request.value()),
+    ),
+    (
+        "rightsizing_recommended_memory_limit_bytes",
+        "Recommended memory limit",
+        |rec| Some(rec.recommended_memory_limit.value()),
+    ),
+    (
+        "rightsizing_current_cpu_request_cores",
+        "CPU request at the time of the run, unset requests are left out",
+        |rec| rec.current_cpu_request.map(|q| q.value()),
+    ),
+    (
+        "rightsizing_current_memory_request_bytes",
+        "Memory request at the time of the run, unset requests are left out",
+        |rec| rec.current_memory_request.map(|q| q.value()),
+    ),
+    (
+        "rightsizing_cpu_usage_p95_cores",
+        "95th percentile of the CPU usage the recommendation was sized from",
+        |rec| Some(rec.cpu_usage_stats.p95),
+    ),
+    (
+        "rightsizing_memory_usage_p95_bytes",
+        "95th percentile of the memory usage the recommendation was sized from",
+        |rec| Some(rec.memory_usage_stats.p95),
+    ),
+    (
+        "rightsizing_waste_cpu_cores",
+        "CPU requested beyond the recommendation, 0 when under-provisioned",
+        |rec| {
+            rec.current_cpu_request
+                .map(|q| waste(q, rec.recommended_cpu_request))
+        },
+    ),
+    (
+        "rightsizing_waste_bytes",
+        "Memory requested beyond the recommendation, 0 when under-provisioned",
+        |rec| {
+            rec.current_memory_request
+                .map(|q| waste(q, rec.recommended_memory_request))
+        },
+    ),
+    (
+        "rightsizing_confidence",
+        "Share of the lookback with usage data, 0 to 1",
+        |rec| Some(rec.confidence),
+    ),
+    (
+        "rightsizing_estimated_monthly_savings_dollars",
+        "Estimated monthly savings of the recommended requests across the replicas",
+        |rec| rec.estimated_monthly_savings,
+    ),
+];
+
+/// Recommendations in the Prometheus text exposition format
+///
+/// Series are labeled with `namespace`, `deployment` and `container`, plus
+/// `day_profile` for `--day-split both`. `rightsizing_last_run_timestamp_seconds`
+/// tells when the group was last pushed.
+pub fn exposition(recommendations: &[ResourceRecommendation], timestamp: DateTime<Utc>) -> String {
+    let mut body = String::new();
+    for (name, help, gauge) in GAUGES {
+        let series: Vec<(String, f64)> = recommendations
+            .iter()
+            .filter_map(|rec| Some((labels(rec), gauge(rec)?)))
+            .filter(|(_, value)| value.is_finite())
+            .collect();
+        if series.is_empty() {
+            continue;
+        }
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} gauge", name);
+        for (labels, value) in series {
+            let _ = writeln!(body, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+
+    let name = "rightsizing_last_run_timestamp_seconds";
+    let _ = writeln!(
+        body,
+        "# HELP {} When the recommendations were computed",
+        name
+    );
+    let _ = writeln!(body, "# TYPE {} gauge", name);
+    let _ = writeln!(body, "{} {}", name, timestamp.timestamp());
+    body
+}
+
+/// Pushes [`exposition`]s to one Pushgateway group
+pub struct PushgatewayExporter {
+    client: Client,
+    url: Url,
+    job: String,
+    grouping: BTreeMap<String, String>,
+}
+
+impl PushgatewayExporter {
+    /// Push to the Pushgateway at `url` under `job`
+    ///
+    /// Credentials in the URL are sent as basic auth.
+    pub fn new(url: Url, job: impl Into<String>) -> Self {
+        Self {
+            client: shared_client(),
+            url,
+            job: job.into(),
+            grouping: BTreeMap::new(),
+        }
+    }
+
+    /// Add a grouping label, e.g. `cluster`, so clusters don't replace each other's metrics
+    pub fn with_grouping(mut self, label: impl Into<String>, value: impl Into<String>) -> Self {
+        self.grouping.insert(label.into(), value.into());
+        self
+    }
+
+    /// Send requests through `client` instead of the shared one
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// `<url>/metrics/job/<job>/<label>/<value>...`
+    pub fn group_url(&self) -> Url {
+        let mut url = self.url.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments
+                .pop_if_empty()
+                .extend(["metrics", "job", self.job.as_str()]);
+            for (label, value) in &self.grouping {
+                segments.extend([label, value]);
+            }
+        }
+        url
+    }
+
+    /// Replace the group's metrics with those of `recommendations`
+    pub async fn push(
+        &self,
+        recommendations: &[ResourceRecommendation],
+        timestamp: DateTime<Utc>,
+    ) -> Result<()> {
+        let url = self.group_url();
+        let response = self
+            .client
+            .put(url.clone())
+            .header(CONTENT_TYPE, EXPOSITION_CONTENT_TYPE)
+            .body(exposition(recommendations, timestamp))
+            .send()
+            .await
+            .map_err(|e| {
+                RecommenderError::Network(format!(
+                    "Failed to push metrics to {}: {}",
+                    redact_url(&url),
+                    e.without_url()
+                ))
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(RecommenderError::Network(format!(
+                "Pushgateway {} returned {}: {}",
+                redact_url(&url),
+                status,
+                body.trim()
+            )));
+        }
+        info!(
+            "Pushed metrics of {} recommendations to {}",
+            recommendations.len(),
+            redact_url(&url)
+        );
+        Ok(())
+    }
+}
+
+/// Label set of a recommendation's series, values escaped
+fn labels(rec: &ResourceRecommendation) -> String {
+    let mut labels = vec![
+        ("namespace", rec.namespace.as_str()),
+        ("deployment", rec.deployment.as_str()),
+        ("container", rec.container.as_str()),
+    ];
+    let day_profile = rec.day_profile.map(|days| days.to_string());
+    if let Some(days) = &day_profile {
+        labels.push(("day_profile", days.as_str()));
+    }
+    labels
+        .into_iter()
+        .map(|(label, value)| format!("{}=\"{}\"", label, escape_label(value)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// `value` with backslashes, quotes and newlines escaped for a label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+/// Requested beyond the recommendation, never negative
+fn waste(current: Quantity, recommended: Quantity) -> f64 {
+    (current.value() - recommended.value()).max(0.0)
+}
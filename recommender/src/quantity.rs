@@ -0,0 +1,484 @@
+//! Kubernetes resource quantities such as `250m` or `512Mi`
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+use crate::error::{RecommenderError, Result};
+
+const KIB: f64 = 1024.0;
+const MIB: f64 = KIB * 1024.0;
+const GIB: f64 = MIB * 1024.0;
+const TIB: f64 = GIB * 1024.0;
+const PIB: f64 = TIB * 1024.0;
+const EIB: f64 = PIB * 1024.0;
+
+/// Largest first, so formatting picks the biggest suffix that divides evenly
+const BINARY_SUFFIXES: [(&str, f64); 6] = [
+    ("Ei", EIB),
+    ("Pi", PIB),
+    ("Ti", TIB),
+    ("Gi", GIB),
+    ("Mi", MIB),
+    ("Ki", KIB),
+];
+const DECIMAL_SUFFIXES: [(&str, f64); 6] = [
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+];
+/// Powers of ten of all decimal suffixes accepted when parsing
+const DECIMAL_EXPONENTS: [(&str, i32); 9] = [
+    ("E", 18),
+    ("P", 15),
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("m", -3),
+    ("u", -6),
+    ("n", -9),
+];
+
+/// Placeholder used in output for values missing from the manifest
+pub const NOT_SET: &str = "not set";
+
+/// How a quantity is written back out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuantityFormat {
+    /// Plain numbers, millis and powers of ten (`250m`, `2`, `1G`)
+    #[default]
+    DecimalSi,
+    /// Powers of two (`128Ki`, `512Mi`)
+    BinarySi,
+}
+
+/// A resource quantity, stored in base units (cores for CPU, bytes for memory)
+///
+/// Quantities compare by value, so `1Gi` equals `1024Mi`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quantity {
+    value: f64,
+    format: QuantityFormat,
+}
+
+impl Quantity {
+    pub fn new(value: f64, format: QuantityFormat) -> Self {
+        Self { value, format }
+    }
+
+    /// CPU quantity from cores
+    pub fn from_cores(cores: f64) -> Self {
+        Self::new(cores, QuantityFormat::DecimalSi)
+    }
+
+    /// Memory quantity from bytes
+    pub fn from_bytes(bytes: f64) -> Self {
+        Self::new(bytes, QuantityFormat::BinarySi)
+    }
+
+    /// Value in base units
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn format(&self) -> QuantityFormat {
+        self.format
+    }
+
+    /// Parse a quantity like `250m`, `1.5`, `128Mi`, `2G` or `1e3`
+    ///
+    /// Accepts the full Kubernetes grammar: an optionally signed decimal number
+    /// followed by a binary suffix (`Ki` to `Ei`), a decimal suffix (`n` to `E`)
+    /// or an exponent (`e3`, `E-2`).
+    pub fn parse(value: &str) -> Result<Self> {
+        let trimmed = value.trim();
+        let invalid = || RecommenderError::Parse(format!("Invalid quantity: '{}'", value));
+
+        let split = trimmed
+            .char_indices()
+            .skip_while(|&(i, c)| i == 0 && (c == '+' || c == '-'))
+            .find(|&(_, c)| !(c.is_ascii_digit() || c == '.'))
+            .map_or(trimmed.len(), |(i, _)| i);
+        let (number, suffix) = trimmed.split_at(split);
+
+        let digits = number.trim_start_matches(['+', '-']);
+        if digits.is_empty() || digits == "." || digits.matches('.').count() > 1 {
+            return Err(invalid());
+        }
+        let number = number.parse::<f64>().map_err(|_| invalid())?;
+
+        let quantity = if suffix.is_empty() {
+            Self::new(number, QuantityFormat::DecimalSi)
+        } else if let Some((_, multiplier)) = BINARY_SUFFIXES.iter().find(|(s, _)| *s == suffix) {
+            Self::new(number * multiplier, QuantityFormat::BinarySi)
+        } else if let Some((_, exponent)) = DECIMAL_EXPONENTS.iter().find(|(s, _)| *s == suffix) {
+            Self::new(scale(number, *exponent), QuantityFormat::DecimalSi)
+        } else if let Some(exponent) = suffix.strip_prefix(['e', 'E']) {
+            let exponent = exponent
+                .strip_prefix('+')
+                .unwrap_or(exponent)
+                .parse::<i32>()
+                .map_err(|_| invalid())?;
+            Self::new(scale(number, exponent), QuantityFormat::DecimalSi)
+        } else {
+            return Err(invalid());
+        };
+
+        if !quantity.value.is_finite() {
+            return Err(invalid());
+        }
+        Ok(quantity)
+    }
+}
+
+/// `number * 10^exponent`, dividing for negative exponents so `250m` stays exact
+fn scale(number: f64, exponent: i32) -> f64 {
+    if exponent < 0 {
+        number / 10f64.powi(-exponent)
+    } else {
+        number * 10f64.powi(exponent)
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = RecommenderError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.format {
+            QuantityFormat::BinarySi => {
+                let bytes = self.value.round();
+                for (suffix, multiplier) in BINARY_SUFFIXES {
+                    if bytes >= multiplier && bytes % multiplier == 0.0 {
+                        return write!(f, "{}{}", bytes / multiplier, suffix);
+                    }
+                }
+                write!(f, "{}", bytes)
+            }
+            QuantityFormat::DecimalSi => {
+                let millis = (self.value * 1000.0).round();
+                if millis == 0.0 && self.value != 0.0 {
+                    // Below a millicore, e.g. `100n` from the API. Like the API,
+                    // anything smaller than a nanocore is rounded up to `1n`.
+                    let nanos = (self.value * 1e9).round();
+                    let nanos = if nanos == 0.0 {
+                        self.value.signum()
+                    } else {
+                        nanos
+                    };
+                    return write!(f, "{}n", nanos);
+                }
+                if millis % 1000.0 != 0.0 {
+                    return write!(f, "{}m", millis);
+                }
+
+                let whole = millis / 1000.0;
+                for (suffix, multiplier) in DECIMAL_SUFFIXES {
+                    if whole >= multiplier && whole % multiplier == 0.0 {
+                        return write!(f, "{}{}", whole / multiplier, suffix);
+                    }
+                }
+                write!(f, "{}", whole)
+            }
+        }
+    }
+}
+
+impl PartialEq for Quantity {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl PartialOrd for Quantity {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl Add for Quantity {
+    type Output = Quantity;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.value + rhs.value, self.format)
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Quantity;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.value - rhs.value, self.format)
+    }
+}
+
+impl Mul<f64> for Quantity {
+    type Output = Quantity;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self::new(self.value * rhs, self.format)
+    }
+}
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct QuantityVisitor;
+
+        impl de::Visitor<'_> for QuantityVisitor {
+            type Value = Quantity;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a Kubernetes quantity")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Quantity, E> {
+                Quantity::parse(v).map_err(E::custom)
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<Quantity, E> {
+                Ok(Quantity::new(v, QuantityFormat::DecimalSi))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Quantity, E> {
+                self.visit_f64(v as f64)
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Quantity, E> {
+                self.visit_f64(v as f64)
+            }
+        }
+
+        deserializer.deserialize_any(QuantityVisitor)
+    }
+}
+
+/// Serde adapter writing missing quantities as [`NOT_SET`]
+pub mod optional {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{NOT_SET, Quantity};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Quantity>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(quantity) => serializer.collect_str(quantity),
+            None => serializer.serialize_str(NOT_SET),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Quantity>, D::Error> {
+        match Option::<serde_json::Value>::deserialize(deserializer)? {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(serde_json::Value::String(s)) if s == NOT_SET => Ok(None),
+            Some(value) => Quantity::deserialize(value)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Render an optional quantity, using [`NOT_SET`] when missing
+pub fn display_optional(value: Option<Quantity>) -> String {
+    value.map_or_else(|| NOT_SET.to_string(), |q| q.to_string())
+}
+
+/// Round cores up to whole millicores, never below `1m`
+pub fn round_cpu(cores: f64) -> Quantity {
+    Quantity::from_cores((cores * 1000.0).ceil().max(1.0) / 1000.0)
+}
+
+/// Round bytes up to whole mebibytes, never below `1Mi`
+pub fn round_memory(bytes: f64) -> Quantity {
+    Quantity::from_bytes((bytes / MIB).ceil().max(1.0) * MIB)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(quantity: &str) -> f64 {
+        Quantity::parse(quantity).unwrap().value()
+    }
+
+    #[test]
+    fn parses_binary_suffixes() {
+        assert_eq!(value("128Ki"), 128.0 * KIB);
+        assert_eq!(value("512Mi"), 512.0 * MIB);
+        assert_eq!(value("1.5Gi"), 1.5 * GIB);
+        assert_eq!(value("2Ti"), 2.0 * TIB);
+        assert_eq!(value("1Ei"), EIB);
+        assert_eq!(
+            Quantity::parse("1Gi").unwrap(),
+            Quantity::parse("1024Mi").unwrap()
+        );
+        assert_eq!(
+            Quantity::parse("1Mi").unwrap().format(),
+            QuantityFormat::BinarySi
+        );
+    }
+
+    #[test]
+    fn parses_decimal_suffixes() {
+        assert_eq!(value("2"), 2.0);
+        assert_eq!(value("1k"), 1e3);
+        assert_eq!(value("3M"), 3e6);
+        assert_eq!(value("2G"), 2e9);
+        assert_eq!(value("1E"), 1e18);
+        assert_eq!(value("250m"), 0.25);
+        assert_eq!(value("100u"), 1e-4);
+        assert_eq!(value("100n"), 1e-7);
+        assert_eq!(value(" +1 "), 1.0);
+        assert_eq!(value("-500m"), -0.5);
+        assert_eq!(
+            Quantity::parse("1G").unwrap().format(),
+            QuantityFormat::DecimalSi
+        );
+    }
+
+    #[test]
+    fn parses_exponents() {
+        assert_eq!(value("1e3"), 1e3);
+        assert_eq!(value("5E+2"), 500.0);
+        assert_eq!(value("1e-2"), 0.01);
+        assert_eq!(value("2.5e0"), 2.5);
+    }
+
+    #[test]
+    fn parses_fractional_millicores() {
+        assert_eq!(value("0.5m"), 0.0005);
+        assert_eq!(value("1.5m"), 0.0015);
+        assert_eq!(value(".5"), 0.5);
+        assert_eq!(value("1."), 1.0);
+    }
+
+    #[test]
+    fn rejects_invalid_quantities() {
+        for invalid in [
+            "", " ", "abc", "Mi", ".", "+", "-.", "1.2.3", "1Xi", "1mi", "1K", "1e", "1e3.5",
+            "1ee3", "1 Mi", "--1", "1e400", "0x10",
+        ] {
+            assert!(
+                Quantity::parse(invalid).is_err(),
+                "{:?} should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn display_round_trips_canonical_quantities() {
+        for canonical in [
+            "0", "1", "2", "250m", "1500m", "100n", "1k", "2G", "1E", "128Ki", "512Mi", "1Gi",
+            "1536Mi", "3Ti", "-500m",
+        ] {
+            let quantity = Quantity::parse(canonical).unwrap();
+            assert_eq!(quantity.to_string(), canonical);
+            assert_eq!(Quantity::parse(&quantity.to_string()).unwrap(), quantity);
+        }
+    }
+
+    #[test]
+    fn display_canonicalizes_other_spellings() {
+        for (input, canonical) in [
+            ("1e3", "1k"),
+            ("0.5", "500m"),
+            ("1000m", "1"),
+            ("1024Mi", "1Gi"),
+            ("1000Ki", "1000Ki"),
+            ("1.5Ki", "1536"),
+            ("0.0001", "100000n"),
+        ] {
+            let quantity = Quantity::parse(input).unwrap();
+            assert_eq!(quantity.to_string(), canonical, "display of {}", input);
+            assert_eq!(Quantity::parse(canonical).unwrap(), quantity);
+        }
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Spec {
+        #[serde(with = "optional")]
+        cpu: Option<Quantity>,
+        #[serde(with = "optional")]
+        memory: Option<Quantity>,
+    }
+
+    #[test]
+    fn optional_writes_missing_quantities_as_not_set() {
+        let spec = Spec {
+            cpu: Some(Quantity::parse("250m").unwrap()),
+            memory: None,
+        };
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(json, r#"{"cpu":"250m","memory":"not set"}"#);
+        assert_eq!(serde_json::from_str::<Spec>(&json).unwrap(), spec);
+
+        let spec = Spec {
+            cpu: None,
+            memory: Some(Quantity::parse("512Mi").unwrap()),
+        };
+        let yaml = serde_yaml::to_string(&spec).unwrap();
+        assert_eq!(serde_yaml::from_str::<Spec>(&yaml).unwrap(), spec);
+    }
+
+    #[test]
+    fn optional_reads_null_and_numbers() {
+        let spec: Spec = serde_json::from_str(r#"{"cpu":null,"memory":1024}"#).unwrap();
+        assert_eq!(spec.cpu, None);
+        assert_eq!(spec.memory, Some(Quantity::from_bytes(1024.0)));
+        assert!(serde_json::from_str::<Spec>(r#"{"cpu":"lots","memory":null}"#).is_err());
+    }
+
+    #[test]
+    fn round_cpu_rounds_up_to_whole_millicores() {
+        assert_eq!(round_cpu(0.0).to_string(), "1m");
+        assert_eq!(round_cpu(1e-9).to_string(), "1m");
+        assert_eq!(round_cpu(0.001).to_string(), "1m");
+        assert_eq!(round_cpu(0.0011).to_string(), "2m");
+        assert_eq!(round_cpu(0.25).to_string(), "250m");
+        assert_eq!(round_cpu(0.2501).to_string(), "251m");
+        assert_eq!(round_cpu(1.0).to_string(), "1");
+        assert_eq!(round_cpu(-1.0).to_string(), "1m");
+    }
+
+    #[test]
+    fn round_memory_rounds_up_to_whole_mebibytes() {
+        assert_eq!(round_memory(0.0).to_string(), "1Mi");
+        assert_eq!(round_memory(1.0).to_string(), "1Mi");
+        assert_eq!(round_memory(MIB).to_string(), "1Mi");
+        assert_eq!(round_memory(MIB + 1.0).to_string(), "2Mi");
+        assert_eq!(round_memory(1.5 * GIB).to_string(), "1536Mi");
+        assert_eq!(round_memory(GIB).to_string(), "1Gi");
+        assert_eq!(round_memory(GIB - 1.0).to_string(), "1Gi");
+    }
+
+    #[test]
+    fn display_rounds_values_below_a_nanocore_up() {
+        assert_eq!(Quantity::from_cores(1e-10).to_string(), "1n");
+        assert_eq!(Quantity::from_cores(4e-10).to_string(), "1n");
+        assert_eq!(Quantity::from_cores(-1e-10).to_string(), "-1n");
+        assert_eq!(Quantity::from_cores(1.4e-9).to_string(), "1n");
+        assert_eq!(Quantity::from_cores(0.0).to_string(), "0");
+    }
+}
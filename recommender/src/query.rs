@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use crate::kubernetes::{ContainerResources, DeploymentResources, WorkloadKind};
+
+/// cAdvisor counter of CPU seconds used per container
+pub(crate) const CPU_USAGE_METRIC: &str = "container_cpu_usage_seconds_total";
+
+/// cAdvisor gauge of the memory the kernel can't reclaim, what OOM kills act on
+pub(crate) const MEMORY_USAGE_METRIC: &str = "container_memory_working_set_bytes";
+
+/// kube-state-metrics gauge of the pods a Deployment currently runs
+pub(crate) const REPLICAS_METRIC: &str = "kube_deployment_status_replicas";
+
+/// PromQL regex of the pods of a Deployment, `<name>-<pod-template-hash>-<suffix>`
+pub(crate) fn pod_pattern(deployment: &str) -> String {
+    format!("{}-[a-z0-9]+-[a-z0-9]+", regex_literal(deployment))
+}
+
+/// PromQL regex of exactly the pods of `deployment`
+///
+/// With the owning ReplicaSets known only their pods match, `<replica-set>-<suffix>`,
+/// so `api` leaves out the pods of an `api-gateway` whose hash-like segments would
+/// fit [`pod_pattern`]. That matters most for aggregated queries, whose series
+/// can't be told apart once summed. Job pods are `<job>-<suffix>`, CronJob pods
+/// fit [`pod_pattern`] with the scheduled time as the hash.
+pub(crate) fn deployment_pod_pattern(deployment: &DeploymentResources) -> String {
+    if deployment.kind == WorkloadKind::Job {
+        return format!("{}-[a-z0-9]+", regex_literal(&deployment.name));
+    }
+    if deployment.replica_sets.is_empty() {
+        return pod_pattern(&deployment.name);
+    }
+    let replica_sets: Vec<String> = deployment
+        .replica_sets
+        .iter()
+        .map(|replica_set| regex_literal(replica_set))
+        .collect();
+    format!("({})-[a-z0-9]+", replica_sets.join("|"))
+}
+
+/// `name` matched literally inside a PromQL regex string, names only contain `.`
+/// as a special character
+fn regex_literal(name: &str) -> String {
+    name.replace('.', r"\\.")
+}
+
+/// What a single series returned by a usage query stands for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregation {
+    /// One series per pod, like the raw cAdvisor metrics
+    #[default]
+    PerPod,
+    /// Usage summed over all pods of the deployment, e.g. `sum()` or a recording rule
+    ///
+    /// Samples are divided by the replica count at the same step to get per-pod usage.
+    Deployment,
+}
+
+/// PromQL usage query with placeholders filled in per container
+///
+/// `$namespace`, `$deployment`, `$container`, `$pod` (the regex matching the
+/// deployment's pods), `$rate_window` (a range like `5m`) and `$step` (the query
+/// step) are replaced before the query is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTemplate {
+    pub template: String,
+    pub aggregation: Aggregation,
+}
+
+impl QueryTemplate {
+    /// Template returning one series per pod
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            aggregation: Aggregation::PerPod,
+        }
+    }
+
+    /// Template returning usage summed over the deployment's pods
+    pub fn aggregated(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+            aggregation: Aggregation::Deployment,
+        }
+    }
+
+    /// Per-pod CPU cores from cAdvisor, the default CPU query
+    pub fn cpu_usage() -> Self {
+        Self::new(format!(
+            r#"rate({}{{namespace="$namespace",pod=~"$pod",container="$container"}}[$rate_window])"#,
+            CPU_USAGE_METRIC
+        ))
+    }
+
+    /// Per-pod peak CPU cores of a short rate within each step, for sizing limits
+    ///
+    /// `$rate_window` is the burst window here, e.g. `1m`, so each step keeps the
+    /// highest short-window rate instead of the average a 5m rate smooths it into.
+    pub fn cpu_burst() -> Self {
+        Self::new(format!(
+            r#"max_over_time(rate({}{{namespace="$namespace",pod=~"$pod",container="$container"}}[$rate_window])[$step:$rate_window])"#,
+            CPU_USAGE_METRIC
+        ))
+    }
+
+    /// Per-pod working set bytes from cAdvisor, the default memory query
+    pub fn memory_usage() -> Self {
+        Self::new(format!(
+            r#"{}{{namespace="$namespace",pod=~"$pod",container="$container"}}"#,
+            MEMORY_USAGE_METRIC
+        ))
+    }
+
+    pub fn is_aggregated(&self) -> bool {
+        self.aggregation == Aggregation::Deployment
+    }
+
+    /// Whether the query depends on the rate window, and so can fall back to a wider one
+    pub fn uses_rate_window(&self) -> bool {
+        self.template.contains("$rate_window")
+    }
+
+    /// Query for one container of `deployment`
+    pub fn render(
+        &self,
+        deployment: &DeploymentResources,
+        container: &ContainerResources,
+        rate_window: Duration,
+        step: Duration,
+    ) -> String {
+        self.template
+            .replace("$rate_window", &promql_duration(rate_window))
+            .replace("$step", &promql_duration(step))
+            .replace("$namespace", &deployment.namespace)
+            .replace("$deployment", &deployment.name)
+            .replace("$container", &container.name)
+            .replace("$pod", &deployment_pod_pattern(deployment))
+    }
+}
+
+/// PromQL range of `duration`, in the largest whole unit, e.g. `5m` or `90s`
+pub(crate) fn promql_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds > 0 && seconds.is_multiple_of(3600) {
+        format!("{}h", seconds / 3600)
+    } else if seconds > 0 && seconds.is_multiple_of(60) {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Replica count of `deployment` over time, the divisor of aggregated queries
+pub(crate) fn replicas_query(deployment: &DeploymentResources) -> String {
+    format!(
+        r#"{}{{namespace="{}",deployment="{}"}}"#,
+        REPLICAS_METRIC, deployment.namespace, deployment.name
+    )
+}
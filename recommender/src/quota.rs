@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use k8s_openapi::api::core::v1::{ResourceQuota, ResourceQuotaSpec};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity as K8sQuantity;
+use kube::api::ObjectMeta;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+use crate::kubernetes::DeploymentResources;
+use crate::quantity::Quantity;
+use crate::recommender::ResourceRecommendation;
+
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Float error allowed when rounding summed quantities up to whole units
+const ROUNDING_TOLERANCE: f64 = 1e-6;
+
+/// Name of the ResourceQuota objects written by [`quota_manifests`]
+const QUOTA_NAME: &str = "compute-resources";
+
+/// Suggested ResourceQuota of a namespace, summed over its recommended workloads
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamespaceQuota {
+    pub namespace: String,
+    /// Deployments with at least one recommended container
+    pub deployments: usize,
+    /// Pods the totals are sized for, the sum of desired replicas
+    pub pods: u64,
+    /// Multiplier on the recommended totals, e.g. 1.25 for rolling-update surge pods
+    pub headroom: f64,
+    /// Current requests and limits of the same containers, unset values count as 0
+    pub current: QuotaTotals,
+    pub recommended: QuotaTotals,
+}
+
+/// `requests.*` and `limits.*` of a ResourceQuota
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct QuotaTotals {
+    pub requests_cpu: Quantity,
+    pub requests_memory: Quantity,
+    pub limits_cpu: Quantity,
+    pub limits_memory: Quantity,
+}
+
+impl QuotaTotals {
+    fn add_scaled(&mut self, other: QuotaTotals, factor: f64) {
+        self.requests_cpu = self.requests_cpu + other.requests_cpu * factor;
+        self.requests_memory = self.requests_memory + other.requests_memory * factor;
+        self.limits_cpu = self.limits_cpu + other.limits_cpu * factor;
+        self.limits_memory = self.limits_memory + other.limits_memory * factor;
+    }
+
+    /// Round up to whole millicores and mebibytes, keeping zero totals at zero
+    ///
+    /// Sums like 3 x 100m come out a hair above 300m, which mustn't round up to 301m.
+    fn rounded(self) -> Self {
+        let ceil = |units: f64| {
+            if units <= ROUNDING_TOLERANCE {
+                0.0
+            } else {
+                (units - ROUNDING_TOLERANCE).ceil()
+            }
+        };
+        let cpu = |q: Quantity| Quantity::from_cores(ceil(q.value() * 1000.0) / 1000.0);
+        let memory = |q: Quantity| Quantity::from_bytes(ceil(q.value() / MIB) * MIB);
+        Self {
+            requests_cpu: cpu(self.requests_cpu),
+            requests_memory: memory(self.requests_memory),
+            limits_cpu: cpu(self.limits_cpu),
+            limits_memory: memory(self.limits_memory),
+        }
+    }
+}
+
+/// Roll container recommendations up into one suggested quota per namespace
+///
+/// Each container counts once per desired replica of its deployment, taken from
+/// `deployments` (1 when unknown). The recommended totals are multiplied by
+/// `headroom` so rollouts and scale-ups still fit.
+pub fn namespace_quotas(
+    recommendations: &[ResourceRecommendation],
+    deployments: &[DeploymentResources],
+    headroom: f64,
+) -> Vec<NamespaceQuota> {
+    let replicas: HashMap<(&str, &str), u64> = deployments
+        .iter()
+        .map(|d| {
+            let replicas = d.replicas.map_or(1, |r| r.max(0) as u64);
+            ((d.namespace.as_str(), d.name.as_str()), replicas)
+        })
+        .collect();
+
+    let mut quotas: BTreeMap<&str, (NamespaceQuota, Vec<&str>)> = BTreeMap::new();
+    for rec in recommendations {
+        let count = replicas
+            .get(&(rec.namespace.as_str(), rec.deployment.as_str()))
+            .copied()
+            .unwrap_or(1);
+        let (quota, seen) = quotas.entry(rec.namespace.as_str()).or_insert_with(|| {
+            (
+                NamespaceQuota {
+                    namespace: rec.namespace.clone(),
+                    deployments: 0,
+                    pods: 0,
+                    headroom,
+                    current: QuotaTotals::default(),
+                    recommended: QuotaTotals::default(),
+                },
+                Vec::new(),
+            )
+        });
+
+        // Pods are counted once per deployment, not once per container
+        if !seen.contains(&rec.deployment.as_str()) {
+            seen.push(rec.deployment.as_str());
+            quota.deployments += 1;
+            quota.pods += count;
+        }
+
+        let current = QuotaTotals {
+            requests_cpu: rec.current_cpu_request.unwrap_or_default(),
+            requests_memory: rec.current_memory_request.unwrap_or_default(),
+            limits_cpu: rec.current_cpu_limit.unwrap_or_default(),
+            limits_memory: rec.current_memory_limit.unwrap_or_default(),
+        };
+        let recommended = QuotaTotals {
+            requests_cpu: rec.recommended_cpu_request,
+            requests_memory: rec.recommended_memory_request,
+            limits_cpu: rec.recommended_cpu_limit,
+            limits_memory: rec.recommended_memory_limit,
+        };
+        quota.current.add_scaled(current, count as f64);
+        quota
+            .recommended
+            .add_scaled(recommended, count as f64 * headroom);
+    }
+
+    quotas
+        .into_values()
+        .map(|(mut quota, _)| {
+            quota.current = quota.current.rounded();
+            quota.recommended = quota.recommended.rounded();
+            quota
+        })
+        .collect()
+}
+
+/// ResourceQuota object setting the recommended totals of `quota`
+pub fn resource_quota(quota: &NamespaceQuota) -> ResourceQuota {
+    let hard = [
+        ("requests.cpu", quota.recommended.requests_cpu),
+        ("requests.memory", quota.recommended.requests_memory),
+        ("limits.cpu", quota.recommended.limits_cpu),
+        ("limits.memory", quota.recommended.limits_memory),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), K8sQuantity(value.to_string())))
+    .collect();
+
+    ResourceQuota {
+        metadata: ObjectMeta {
+            name: Some(QUOTA_NAME.to_string()),
+            namespace: Some(quota.namespace.clone()),
+            ..ObjectMeta::default()
+        },
+        spec: Some(ResourceQuotaSpec {
+            hard: Some(hard),
+            ..ResourceQuotaSpec::default()
+        }),
+        status: None,
+    }
+}
+
+/// Multi-document YAML with one ResourceQuota per namespace, ready for `kubectl apply`
+pub fn quota_manifests(quotas: &[NamespaceQuota]) -> Result<String> {
+    let documents = quotas
+        .iter()
+        .map(|quota| serde_yaml::to_string(&resource_quota(quota)))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(documents.join("---\n"))
+}
+
+/// Write [`quota_manifests`] of `quotas` to `path`
+pub fn write_quota_manifests(quotas: &[NamespaceQuota], path: &Path) -> Result<()> {
+    std::fs::write(path, quota_manifests(quotas)?)?;
+    Ok(())
+}
@@ -0,0 +1,269 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Lowest rate [`RateLimiter::slow_down`] goes to, so a limiter never stalls a run
+const MIN_REQUESTS_PER_SECOND: f64 = 0.1;
+
+/// Client-side token-bucket limiter shared by all clones
+///
+/// Up to `burst` requests pass immediately, after that requests are spaced at
+/// `requests_per_second`. Waiting callers reserve their slot up front, so they
+/// are served in the order they called [`RateLimiter::acquire`].
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    requests_per_second: f64,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+    stats: RateLimiterStats,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.requests_per_second).min(self.burst);
+        self.last_refill = now;
+    }
+}
+
+/// Queue-delay counters of a [`RateLimiter`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RateLimiterStats {
+    /// Requests that passed the limiter
+    pub requests: u64,
+    /// Requests that had to wait for a token
+    pub delayed: u64,
+    /// Sum of the time requests spent waiting
+    pub total_delay: Duration,
+    /// Longest single wait
+    pub max_delay: Duration,
+    /// Times the server throttled us and the rate was lowered
+    pub throttled: u64,
+}
+
+impl RateLimiter {
+    /// Limit to `requests_per_second`, allowing bursts of `burst` requests
+    ///
+    /// Both values are clamped to sane minimums so a limiter never blocks forever.
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        let requests_per_second = requests_per_second.max(f64::MIN_POSITIVE);
+        let burst = f64::from(burst.max(1));
+
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                requests_per_second,
+                burst,
+                tokens: burst,
+                last_refill: Instant::now(),
+                stats: RateLimiterStats::default(),
+            })),
+        }
+    }
+
+    /// Limit to `requests_per_second` with a burst of one second worth of requests
+    pub fn per_second(requests_per_second: f64) -> Self {
+        Self::new(requests_per_second, requests_per_second.ceil() as u32)
+    }
+
+    /// Current rate, lower than configured after [`RateLimiter::slow_down`]
+    pub fn requests_per_second(&self) -> f64 {
+        self.bucket
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .requests_per_second
+    }
+
+    /// Wait until a request may be sent
+    pub async fn acquire(&self) {
+        let delay = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            bucket.refill(Instant::now());
+
+            // Going negative reserves a future token for this caller
+            bucket.tokens -= 1.0;
+            let delay = if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / bucket.requests_per_second)
+            } else {
+                Duration::ZERO
+            };
+
+            let stats = &mut bucket.stats;
+            stats.requests += 1;
+            if !delay.is_zero() {
+                stats.delayed += 1;
+                stats.total_delay += delay;
+                stats.max_delay = stats.max_delay.max(delay);
+            }
+            delay
+        };
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Multiply the rate by `factor` for all further requests, e.g. 0.5 after a 429
+    ///
+    /// The burst shrinks along with the rate so the next requests don't all go out at once.
+    pub fn slow_down(&self, factor: f64) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.refill(Instant::now());
+
+        bucket.requests_per_second =
+            (bucket.requests_per_second * factor.clamp(0.0, 1.0)).max(MIN_REQUESTS_PER_SECOND);
+        bucket.burst = bucket.burst.min(bucket.requests_per_second.ceil()).max(1.0);
+        bucket.tokens = bucket.tokens.min(bucket.burst);
+        bucket.stats.throttled += 1;
+    }
+
+    /// Hold back every request not yet started for at least `delay`
+    ///
+    /// Used to honor a server's `Retry-After`.
+    pub fn pause(&self, delay: Duration) {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        bucket.refill(Instant::now());
+
+        let paused = bucket.tokens.min(0.0) - delay.as_secs_f64() * bucket.requests_per_second;
+        bucket.tokens = bucket.tokens.min(paused);
+    }
+
+    /// Snapshot of the queue-delay counters
+    pub fn stats(&self) -> RateLimiterStats {
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner()).stats
+    }
+}
+
+impl fmt::Display for RateLimiterStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests, {} delayed, {:.1}s total queue delay (max {:.1}s)",
+            self.requests,
+            self.delayed,
+            self.total_delay.as_secs_f64(),
+            self.max_delay.as_secs_f64()
+        )?;
+        if self.throttled > 0 {
+            write!(f, ", throttled {} times", self.throttled)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_passes_then_requests_are_spaced() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        let stats = limiter.stats();
+        assert_eq!(stats.requests, 5);
+        assert_eq!(stats.delayed, 2);
+        assert_eq!(stats.total_delay, Duration::from_secs(1));
+        assert_eq!(stats.max_delay, Duration::from_millis(500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_callers_are_served_in_call_order() {
+        let limiter = RateLimiter::new(1.0, 1);
+        limiter.acquire().await;
+
+        let served = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for caller in 0..4 {
+            let limiter = limiter.clone();
+            let served = served.clone();
+            tasks.push(tokio::spawn(async move {
+                limiter.acquire().await;
+                served.lock().unwrap().push((caller, Instant::now()));
+            }));
+            // Let this caller reserve its token before the next one calls
+            tokio::task::yield_now().await;
+        }
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let served = served.lock().unwrap();
+        let order: Vec<_> = served.iter().map(|(caller, _)| *caller).collect();
+        assert_eq!(order, [0, 1, 2, 3]);
+        for pair in served.windows(2) {
+            assert_eq!(pair[1].1 - pair[0].1, Duration::from_secs(1));
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_time_refills_up_to_burst() {
+        let limiter = RateLimiter::new(1.0, 2);
+        limiter.acquire().await;
+        limiter.acquire().await;
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert_eq!(limiter.stats().delayed, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_down_lowers_rate_and_burst() {
+        let limiter = RateLimiter::new(4.0, 4);
+        limiter.slow_down(0.5);
+        assert_eq!(limiter.requests_per_second(), 2.0);
+        assert_eq!(limiter.stats().throttled, 1);
+
+        // The burst shrank to two, further requests are spaced at 500ms
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn slow_down_stops_at_minimum_rate() {
+        let limiter = RateLimiter::new(1.0, 1);
+        for _ in 0..10 {
+            limiter.slow_down(0.1);
+        }
+        assert_eq!(limiter.requests_per_second(), MIN_REQUESTS_PER_SECOND);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_holds_back_pending_requests() {
+        let limiter = RateLimiter::new(1.0, 3);
+        let start = Instant::now();
+        limiter.pause(Duration::from_secs(5));
+
+        // Even a full bucket waits out the pause
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(6));
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(7));
+    }
+}
@@ -0,0 +1,2172 @@
+use crate::clock::{SharedClock, SystemClock, Timezone};
+use crate::config::{DaySplit, ForecastConfig, OutlierAction, RecommenderConfig, ResourceBounds};
+use crate::error::{RecommenderError, Result};
+use crate::forecast::{Forecast, forecast};
+use crate::hpa::HpaTarget;
+use crate::kubernetes::{ContainerResources, DeploymentResources, WorkloadKind};
+use crate::metrics::to_unix_seconds;
+use crate::metrics::{MetricsProvider, Series};
+use crate::outliers::{SpikeScan, find_spikes};
+use crate::output::{PercentileConfig, SkipReason, SkippedWorkload};
+use crate::pdb::DisruptionRisk;
+use crate::provenance::Drift;
+use crate::quantity::{Quantity, round_cpu, round_memory};
+use crate::query::{QueryTemplate, REPLICAS_METRIC, promql_duration, replicas_query};
+use crate::seasonality::Seasonality;
+use crate::stats::{Interpolation, StreamingStats, UsageStats, percentile};
+use crate::vpa::VpaTarget;
+use async_stream::stream;
+use chrono::{DateTime, Datelike, Weekday};
+use futures::{Stream, StreamExt};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+
+/// Time range and resolution queried for the containers of a deployment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct QueryWindow {
+    start: SystemTime,
+    end: SystemTime,
+    step: Duration,
+    /// Part of the week samples are kept from, all of it if None
+    days: Option<DayProfile>,
+    /// Zone whose calendar `days` are told apart in
+    timezone: Timezone,
+}
+
+impl QueryWindow {
+    fn lookback(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+
+    /// Windows the samples of the range are summarized for, both parts of the
+    /// week when split by them
+    fn profiles(&self) -> Vec<QueryWindow> {
+        match self.days {
+            Some(_) => vec![
+                self.with_days(DayProfile::Weekday),
+                self.with_days(DayProfile::Weekend),
+            ],
+            None => vec![*self],
+        }
+    }
+
+    /// The same window restricted to part of the week
+    fn with_days(self, days: DayProfile) -> Self {
+        Self {
+            days: Some(days),
+            ..self
+        }
+    }
+
+    /// The range queried, which doesn't depend on the days kept
+    fn range(&self) -> Self {
+        Self {
+            days: None,
+            ..*self
+        }
+    }
+
+    /// Whether the sample at Unix `timestamp` is on one of the window's days
+    fn includes(&self, timestamp: f64) -> bool {
+        self.days
+            .is_none_or(|days| days.includes(timestamp, self.timezone))
+    }
+
+    fn confidence(&self, observed_steps: usize) -> f64 {
+        let Some(days) = self.days else {
+            return calculate_confidence(self.lookback(), self.step, observed_steps);
+        };
+        let start = to_unix_seconds(self.start);
+        let step = self.step.as_secs_f64();
+        let steps = (self.lookback().as_secs_f64() / step).floor() as usize;
+        let expected_steps = (1..=steps)
+            .filter(|i| days.includes(start + *i as f64 * step, self.timezone))
+            .count()
+            .max(1);
+        (observed_steps as f64 / expected_steps as f64).min(1.0)
+    }
+}
+
+/// Share of the lookback the rate window must cover before a wider one is tried
+const MIN_RATE_WINDOW_COVERAGE: f64 = 0.5;
+
+/// Spike windows listed per resource in the recommendation reason
+const MAX_LISTED_SPIKES: usize = 3;
+
+/// Completed runs a CronJob or Job recommendation is fully confident with
+const CONFIDENT_RUNS: usize = 5;
+
+/// Summary of the samples one usage query returned
+#[derive(Debug, Clone)]
+struct QueryStats {
+    stats: UsageStats,
+    /// Distinct query steps observed
+    steps: usize,
+    /// Spikes left out of or capped in `stats`
+    spikes: SpikeScan,
+    /// Projected usage, when forecasting is enabled
+    forecast: Option<Forecast>,
+    /// Periodic pattern `stats` were restricted to the peak hours of
+    seasonality: Option<Seasonality>,
+    /// Peaks of the runs of a CronJob or Job
+    runs: Option<RunPeaks>,
+    /// Sketch behind `stats`, with [`RecommenderConfig::retain_usage`]
+    usage: Option<StreamingStats>,
+}
+
+/// Peak usage of the runs of a CronJob or Job, one run per pod
+#[derive(Debug, Clone, Copy, Default)]
+struct RunPeaks {
+    /// Pods that stopped reporting before the end of the window
+    completed: usize,
+    /// Highest usage of a completed run, of any run when none completed yet
+    peak: f64,
+}
+
+/// PromQL query, range queried and whether it is split by part of the week
+type QueryKey = (String, QueryWindow, bool);
+
+/// Summaries of a query, one per part of the week it is split into
+type DayStats = Arc<[(Option<DayProfile>, QueryStats)]>;
+
+/// Replica count per query step
+type ReplicaCounts = Arc<HashMap<i64, f64>>;
+
+/// Result of a query, computed by the first container awaiting it
+type QueryCell<T> = Arc<OnceCell<T>>;
+
+/// Results shared between all containers of a run
+///
+/// Containers are analyzed concurrently, so each map is only locked to get the
+/// cell of a query. The first container awaiting a cell runs the query, the
+/// others wait for its result instead of sending the same one again. Only the
+/// summaries are kept, never the samples.
+#[derive(Default)]
+struct QueryMemo {
+    /// Usage stats per PromQL query and range
+    stats: Mutex<HashMap<QueryKey, QueryCell<DayStats>>>,
+    /// Replica count per step of the deployments queried with an aggregated template
+    replicas: Mutex<HashMap<String, QueryCell<ReplicaCounts>>>,
+}
+
+/// Lock a memo map, recovering it from a panicked holder
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Distinct query steps seen in a window, one bit per step
+struct StepSet {
+    start: f64,
+    step: f64,
+    seen: Vec<bool>,
+    len: usize,
+}
+
+impl StepSet {
+    fn new(window: QueryWindow) -> Self {
+        let span = window.end.duration_since(window.start).unwrap_or_default();
+        let step = window.step.as_secs_f64();
+        let steps = (span.as_secs_f64() / step).ceil() as usize + 1;
+        Self {
+            start: to_unix_seconds(window.start),
+            step,
+            seen: vec![false; steps],
+            len: 0,
+        }
+    }
+
+    fn insert(&mut self, timestamp: f64) {
+        let step = step_index(self.start, self.step, timestamp);
+        if step < 0 {
+            return;
+        }
+        if let Some(seen) = self.seen.get_mut(step as usize)
+            && !*seen
+        {
+            *seen = true;
+            self.len += 1;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Index of the query step a sample at `timestamp` belongs to
+fn step_index(start: f64, step: f64, timestamp: f64) -> i64 {
+    ((timestamp - start) / step).round() as i64
+}
+
+/// Part of the week a recommendation is sized for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DayProfile {
+    /// Monday to Friday
+    Weekday,
+    /// Saturday and Sunday
+    Weekend,
+}
+
+impl DayProfile {
+    /// Whether Unix `timestamp` falls on this part of the week in `timezone`
+    pub fn includes(&self, timestamp: f64, timezone: Timezone) -> bool {
+        let Some(time) = DateTime::from_timestamp(timestamp.floor() as i64, 0) else {
+            return false;
+        };
+        let weekend = matches!(
+            timezone.convert(time).weekday(),
+            Weekday::Sat | Weekday::Sun
+        );
+        weekend == (*self == DayProfile::Weekend)
+    }
+}
+
+impl fmt::Display for DayProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DayProfile::Weekday => "weekday",
+            DayProfile::Weekend => "weekend",
+        })
+    }
+}
+
+/// Recommendation for a container's resource sizing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceRecommendation {
+    pub deployment: String,
+    pub container: String,
+    pub namespace: String,
+    /// Kind of the workload named by `deployment`
+    #[serde(default, skip_serializing_if = "WorkloadKind::is_deployment")]
+    pub workload_kind: WorkloadKind,
+    #[serde(with = "crate::quantity::optional")]
+    pub current_cpu_request: Option<Quantity>,
+    #[serde(with = "crate::quantity::optional")]
+    pub current_cpu_limit: Option<Quantity>,
+    #[serde(with = "crate::quantity::optional")]
+    pub current_memory_request: Option<Quantity>,
+    #[serde(with = "crate::quantity::optional")]
+    pub current_memory_limit: Option<Quantity>,
+    pub recommended_cpu_request: Quantity,
+    pub recommended_cpu_limit: Quantity,
+    pub recommended_memory_request: Quantity,
+    pub recommended_memory_limit: Quantity,
+    pub cpu_usage_stats: UsageStats,
+    pub memory_usage_stats: UsageStats,
+    pub recommendation_reason: String,
+    /// Fraction of the lookback window covered by samples (0.0 - 1.0)
+    #[serde(default)]
+    pub confidence: f64,
+    /// Problems found along the way, e.g. a current limit below its request
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Range of `rate()` the CPU usage was computed with, e.g. `5m`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_rate_window: Option<String>,
+    /// Burst window the CPU limit was sized from, None when sized from the usage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_burst_window: Option<String>,
+    /// Part of the week the recommendation is for, None for the whole week
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub day_profile: Option<DayProfile>,
+    /// VerticalPodAutoscaler of the workload and its target for the container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vpa: Option<VpaTarget>,
+    /// HorizontalPodAutoscaler of the workload and its suggested CPU utilization target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hpa: Option<HpaTarget>,
+    /// PodDisruptionBudgets covering the workload's pods
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pdbs: Vec<String>,
+    /// PodDisruptionBudget the rollout of the change would breach
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disruption_risk: Option<DisruptionRisk>,
+    /// Sized from a short metrics-server sample because Prometheus had no usage
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub from_metrics_server: bool,
+    /// Whether the resources moved by hand or the usage since the last apply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drift: Option<Drift>,
+    /// Replicas of the workload when it was analyzed, None when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replicas: Option<u64>,
+    /// Dollars a month the recommended requests save across the replicas,
+    /// negative when they cost more, see [`crate::cost::ResourcePrices`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_monthly_savings: Option<f64>,
+    /// Usage the recommendation was sized from, kept with
+    /// [`RecommenderConfig::retain_usage`]
+    #[serde(skip)]
+    pub usage: Option<Arc<RetainedUsage>>,
+}
+
+/// Sketches of the usage behind a recommendation, enough to size it again for
+/// other percentiles
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetainedUsage {
+    pub cpu: StreamingStats,
+    pub memory: StreamingStats,
+    /// Peak CPU the limit was sized from, None when sized from `cpu`
+    pub cpu_burst: Option<StreamingStats>,
+    /// Factor CPU requests and limits were scaled by for the forecast, 1.0 without growth
+    pub cpu_growth: f64,
+    /// Factor memory requests and limits were scaled by for the forecast
+    pub memory_growth: f64,
+    /// Weekend usage when sized for the busier part of the week, the rest being
+    /// the weekdays'
+    pub weekend: Option<Arc<RetainedUsage>>,
+}
+
+impl RetainedUsage {
+    /// Size `recommendation` for `percentiles` of this usage, as the run did
+    fn size(&self, recommendation: &mut ResourceRecommendation, percentiles: &PercentileConfig) {
+        let cpu = UsageSource::Sketch(&self.cpu);
+        let cpu_limit = UsageSource::Sketch(self.cpu_burst.as_ref().unwrap_or(&self.cpu));
+        [
+            recommendation.recommended_cpu_request,
+            recommendation.recommended_cpu_limit,
+            recommendation.recommended_memory_request,
+            recommendation.recommended_memory_limit,
+        ] = size_for_percentiles(
+            percentiles,
+            cpu,
+            cpu_limit,
+            UsageSource::Sketch(&self.memory),
+        );
+        recommendation.enforce_limits_above_requests();
+        scale_for_growth(recommendation, self.cpu_growth, self.memory_growth);
+    }
+}
+
+impl ResourceRecommendation {
+    /// The recommendation sized again for `percentiles` from its retained usage
+    ///
+    /// Percentiles are read from the usage sketches directly, so any value sizes
+    /// differently, not just p50, p95 and p99. Sizing is shared with the run, so the
+    /// run's own percentiles give back its values: the forecast growth is applied
+    /// again, seasonal usage was only retained for its peak hours and a merged day
+    /// split is sized for the busier part of the week again. None without retained
+    /// usage, e.g. for CronJobs and Jobs.
+    pub fn retune(&self, percentiles: &PercentileConfig) -> Option<Self> {
+        let usage = self.usage.as_ref()?;
+        let mut retuned = self.clone();
+        usage.size(&mut retuned, percentiles);
+        if let Some(weekend) = &usage.weekend {
+            let mut busier = self.clone();
+            weekend.size(&mut busier, percentiles);
+            for (value, weekend) in [
+                (
+                    &mut retuned.recommended_cpu_request,
+                    busier.recommended_cpu_request,
+                ),
+                (
+                    &mut retuned.recommended_cpu_limit,
+                    busier.recommended_cpu_limit,
+                ),
+                (
+                    &mut retuned.recommended_memory_request,
+                    busier.recommended_memory_request,
+                ),
+                (
+                    &mut retuned.recommended_memory_limit,
+                    busier.recommended_memory_limit,
+                ),
+            ] {
+                if weekend > *value {
+                    *value = weekend;
+                }
+            }
+        }
+        retuned.recommendation_reason.push_str(&format!(
+            "; retuned to p{} / p{} CPU, p{} / p{} memory with a {:.2}x margin",
+            percentiles.cpu_request,
+            percentiles.cpu_limit,
+            percentiles.memory_request,
+            percentiles.memory_limit,
+            percentiles.safety_margin
+        ));
+        retuned.enforce_limits_above_requests();
+        Some(retuned)
+    }
+
+    /// Largest change of the CPU and memory requests in percent of the current ones
+    ///
+    /// Positive when the container is under-provisioned, negative when it is
+    /// over-provisioned. None when it has no current request to compare with.
+    pub fn request_change_percent(&self) -> Option<f64> {
+        [
+            (self.current_cpu_request, self.recommended_cpu_request),
+            (self.current_memory_request, self.recommended_memory_request),
+        ]
+        .into_iter()
+        .filter_map(|(current, recommended)| {
+            let current = current.filter(|current| current.value() > 0.0)?.value();
+            Some((recommended.value() - current) / current * 100.0)
+        })
+        .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+    }
+
+    /// Raise recommended limits that ended up below their request
+    ///
+    /// Requests and limits use different percentiles and are rounded and adjusted
+    /// by policies independently, so nothing else guarantees `limit >= request`.
+    /// Returns whether a limit was raised.
+    pub fn enforce_limits_above_requests(&mut self) -> bool {
+        let mut raised = false;
+        for (resource, request, limit) in [
+            (
+                "CPU",
+                self.recommended_cpu_request,
+                &mut self.recommended_cpu_limit,
+            ),
+            (
+                "memory",
+                self.recommended_memory_request,
+                &mut self.recommended_memory_limit,
+            ),
+        ] {
+            if *limit < request {
+                debug!(
+                    "Raising {} limit of {}/{}/{} from {} to its request {}",
+                    resource, self.namespace, self.deployment, self.container, limit, request
+                );
+                *limit = Quantity::new(request.value(), limit.format());
+                raised = true;
+            }
+        }
+        raised
+    }
+}
+
+/// Enforce `limit >= request` on every recommendation, see
+/// [`ResourceRecommendation::enforce_limits_above_requests`]
+///
+/// Returns the number of recommendations that had a limit raised.
+pub fn enforce_limits_above_requests(recommendations: &mut [ResourceRecommendation]) -> usize {
+    let raised = recommendations
+        .iter_mut()
+        .filter_map(|rec| rec.enforce_limits_above_requests().then_some(()))
+        .count();
+    if raised > 0 {
+        info!(
+            "Raised limits below their request in {} recommendations",
+            raised
+        );
+    }
+    raised
+}
+
+/// Clamp every recommendation to `bounds`, see [`ResourceBounds::clamp`]
+///
+/// Returns the number of recommendations that had a value changed.
+pub fn clamp_to_bounds(
+    recommendations: &mut [ResourceRecommendation],
+    bounds: &ResourceBounds,
+) -> usize {
+    if bounds.is_empty() {
+        return 0;
+    }
+    let clamped = recommendations
+        .iter_mut()
+        .filter_map(|rec| bounds.clamp(rec).then_some(()))
+        .count();
+    if clamped > 0 {
+        info!(
+            "Clamped {} recommendations to the configured bounds",
+            clamped
+        );
+    }
+    clamped
+}
+
+/// Progress of a recommendation run, reported after each container
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Containers analyzed so far, including failed ones
+    pub completed: usize,
+    /// Containers to analyze in total
+    pub total: usize,
+    /// Workload that was just analyzed, as `namespace/deployment`
+    pub workload: String,
+}
+
+/// Callback invoked with progress updates
+pub type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+pub struct Recommender {
+    metrics: Arc<dyn MetricsProvider>,
+    config: RecommenderConfig,
+    on_progress: Option<ProgressCallback>,
+    cancel: CancellationToken,
+    clock: SharedClock,
+}
+
+impl Recommender {
+    pub fn new(metrics: impl MetricsProvider + 'static, config: RecommenderConfig) -> Self {
+        Self::with_provider(Arc::new(metrics), config)
+    }
+
+    /// Create a Recommender from an already shared metrics backend
+    pub fn with_provider(metrics: Arc<dyn MetricsProvider>, config: RecommenderConfig) -> Self {
+        Self {
+            metrics,
+            config,
+            on_progress: None,
+            cancel: CancellationToken::new(),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// End the lookback window at `clock`'s time instead of the system time
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Stop analyzing, and abort in-flight queries, once `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Report progress to `callback` while recommendations are generated
+    ///
+    /// The callback runs on the task driving the run, so keep it cheap; use it
+    /// to feed a channel when rendering happens elsewhere.
+    pub fn on_progress(mut self, callback: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Generate recommendations for all deployments
+    ///
+    /// Containers whose analysis fails are left out, see [`Recommender::analyze`]
+    /// to get them along with the reason.
+    pub async fn generate_recommendations(
+        &self,
+        deployments: Vec<DeploymentResources>,
+    ) -> Result<Vec<ResourceRecommendation>> {
+        let (recommendations, _) = self.analyze(deployments).await?;
+        Ok(recommendations)
+    }
+
+    /// Generate recommendations for all deployments, collecting the containers that failed
+    pub async fn analyze(
+        &self,
+        deployments: Vec<DeploymentResources>,
+    ) -> Result<(Vec<ResourceRecommendation>, Vec<SkippedWorkload>)> {
+        let mut recommendations = Vec::new();
+        let mut skipped = Vec::new();
+
+        let mut outcomes = std::pin::pin!(self.stream_analysis(deployments));
+        while let Some(outcome) = outcomes.next().await {
+            match outcome {
+                Ok(rec) => recommendations.push(rec),
+                Err(skip) => skipped.push(skip),
+            }
+        }
+
+        if self.cancel.is_cancelled() {
+            return Err(RecommenderError::Cancelled);
+        }
+        Ok((recommendations, skipped))
+    }
+
+    /// Stream recommendations as soon as each container has been analyzed
+    ///
+    /// Containers whose recommendation fails are logged and left out, use
+    /// [`Recommender::stream_analysis`] to get them. The stream ends early on cancellation.
+    pub fn stream_recommendations(
+        &self,
+        deployments: Vec<DeploymentResources>,
+    ) -> impl Stream<Item = ResourceRecommendation> + Send + '_ {
+        self.stream_analysis(deployments)
+            .filter_map(|outcome| std::future::ready(outcome.ok()))
+    }
+
+    /// Stream the outcome of each container as soon as it has been analyzed
+    ///
+    /// Up to [`RecommenderConfig::max_concurrent_queries`] containers are analyzed
+    /// at a time, their outcomes are yielded in the order of `deployments`. Failed
+    /// containers are yielded as [`SkippedWorkload`] with the reason. The stream
+    /// ends early on cancellation.
+    pub fn stream_analysis(
+        &self,
+        deployments: Vec<DeploymentResources>,
+    ) -> impl Stream<Item = std::result::Result<ResourceRecommendation, SkippedWorkload>> + Send + '_
+    {
+        let total = deployments.iter().map(|d| d.containers.len()).sum();
+
+        // Windows all end together, so identical queries share their results
+        let end_time = self.clock.now();
+        let memo = Arc::new(QueryMemo::default());
+
+        let containers = deployments.into_iter().flat_map(move |deployment| {
+            info!(
+                "Analyzing deployment {}/{} with {} containers",
+                deployment.namespace,
+                deployment.name,
+                deployment.containers.len()
+            );
+            let lookback_hours = self.config.lookback_hours_for(&deployment);
+            if lookback_hours != self.config.lookback_hours {
+                info!(
+                    "Using a {}h lookback for {}/{}",
+                    lookback_hours, deployment.namespace, deployment.name
+                );
+            }
+            let window = QueryWindow {
+                start: end_time - Duration::from_secs_f64(lookback_hours * 3600.0),
+                end: end_time,
+                step: self.config.query_step_for(lookback_hours),
+                days: None,
+                timezone: self.config.timezone,
+            };
+            let deployment = Arc::new(deployment);
+            let memo = memo.clone();
+            (0..deployment.containers.len())
+                .map(move |index| (deployment.clone(), index, window, memo.clone()))
+        });
+
+        let outcomes = futures::stream::iter(containers)
+            .map(move |(deployment, index, window, memo)| async move {
+                let container = &deployment.containers[index];
+                let result = tokio::select! {
+                    _ = self.cancel.cancelled() => None,
+                    result = self.analyze_container(&deployment, container, window, &memo) => Some(result),
+                };
+                (deployment, index, result)
+            })
+            .buffered(self.config.max_concurrent_queries.max(1));
+
+        stream! {
+            let mut completed = 0;
+            let mut outcomes = std::pin::pin!(outcomes);
+            while let Some((deployment, index, result)) = outcomes.next().await {
+                let Some(result) = result else {
+                    info!("Cancelled, stopping analysis");
+                    break;
+                };
+                let container = &deployment.containers[index];
+
+                completed += 1;
+                if let Some(callback) = &self.on_progress {
+                    callback(&Progress {
+                        completed,
+                        total,
+                        workload: format!("{}/{}", deployment.namespace, deployment.name),
+                    });
+                }
+
+                match result {
+                    Ok(recs) => {
+                        for rec in recs {
+                            yield Ok(rec);
+                        }
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to generate recommendation for {}/{}/{}: {}",
+                            deployment.namespace, deployment.name, container.name, e
+                        );
+                        yield Err(SkippedWorkload {
+                            namespace: deployment.namespace.clone(),
+                            deployment: deployment.name.clone(),
+                            container: Some(container.name.clone()),
+                            reason: SkipReason::from_error(&e),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recommendations for a container, one per part of the week with a `Both` day split
+    ///
+    /// When only one part of the week can be sized, its recommendation is used
+    /// alone with a warning about the other.
+    async fn analyze_container(
+        &self,
+        deployment: &DeploymentResources,
+        container: &ContainerResources,
+        window: QueryWindow,
+        memo: &QueryMemo,
+    ) -> Result<Vec<ResourceRecommendation>> {
+        let Some(split) = self.config.day_split else {
+            let recommendation = self
+                .generate_container_recommendation(deployment, container, window, memo)
+                .await?;
+            return Ok(vec![recommendation]);
+        };
+
+        let mut sized = Vec::new();
+        let mut failed = Vec::new();
+        for days in [DayProfile::Weekday, DayProfile::Weekend] {
+            match self
+                .generate_container_recommendation(
+                    deployment,
+                    container,
+                    window.with_days(days),
+                    memo,
+                )
+                .await
+            {
+                Ok(mut recommendation) => {
+                    recommendation.day_profile = Some(days);
+                    sized.push(recommendation);
+                }
+                Err(RecommenderError::Cancelled) => return Err(RecommenderError::Cancelled),
+                Err(e) => failed.push((days, e)),
+            }
+        }
+
+        if sized.is_empty() {
+            let (_, e) = failed.remove(0);
+            return Err(e);
+        }
+        for (days, e) in &failed {
+            warn!(
+                "No {} recommendation for {}/{}/{}: {}",
+                days, deployment.namespace, deployment.name, container.name, e
+            );
+            for recommendation in &mut sized {
+                recommendation
+                    .warnings
+                    .push(format!("No {} usage to size from: {}", days, e));
+            }
+        }
+
+        match (split, <[_; 2]>::try_from(sized)) {
+            (DaySplit::Max, Ok([weekday, weekend])) => {
+                Ok(vec![merge_day_profiles(weekday, weekend)])
+            }
+            (_, Ok(both)) => Ok(both.into()),
+            (_, Err(one)) => Ok(one),
+        }
+    }
+
+    /// Generate recommendation for a single container
+    async fn generate_container_recommendation(
+        &self,
+        deployment: &DeploymentResources,
+        container: &ContainerResources,
+        window: QueryWindow,
+        memo: &QueryMemo,
+    ) -> Result<ResourceRecommendation> {
+        debug!(
+            "Generating recommendation for container: {}/{}/{}",
+            deployment.namespace, deployment.name, container.name
+        );
+
+        // Query CPU usage (in cores)
+        let cpu_query = &self.config.cpu_query;
+        let rate_window = self.config.rate_window;
+        let mut cpu = self
+            .query_metrics(cpu_query, rate_window, deployment, container, window, memo)
+            .await?;
+        let mut cpu_rate_window = cpu_query.uses_rate_window().then_some(rate_window);
+        let mut rate_window_warning = None;
+
+        // Scrape gaps and counter resets leave too few points in short windows
+        let coverage = window.confidence(cpu.steps);
+        // Jobs only report while running, low coverage is expected
+        if let Some(fallback) = self.config.fallback_rate_window
+            && cpu_query.uses_rate_window()
+            && coverage < MIN_RATE_WINDOW_COVERAGE
+            && !deployment.kind.is_batch()
+        {
+            let wider = self
+                .query_metrics(cpu_query, fallback, deployment, container, window, memo)
+                .await?;
+            if wider.steps > cpu.steps {
+                info!(
+                    "Using a {} rate window for {}/{}/{}, the {} window covered {:.0}% of the lookback",
+                    promql_duration(fallback),
+                    deployment.namespace,
+                    deployment.name,
+                    container.name,
+                    promql_duration(rate_window),
+                    coverage * 100.0
+                );
+                rate_window_warning = Some(format!(
+                    "CPU usage from a {} rate window, the {} window covered only {:.0}% of the lookback",
+                    promql_duration(fallback),
+                    promql_duration(rate_window),
+                    coverage * 100.0
+                ));
+                cpu = wider;
+                cpu_rate_window = Some(fallback);
+            }
+        }
+
+        // Query memory usage (in bytes)
+        let memory = self
+            .query_metrics(
+                &self.config.memory_query,
+                rate_window,
+                deployment,
+                container,
+                window,
+                memo,
+            )
+            .await?;
+
+        // Sizing from zero samples would recommend the 1m/1Mi floor
+        for (resource, query) in [("CPU", &cpu), ("memory", &memory)] {
+            if query.stats.samples == 0 {
+                return Err(RecommenderError::InsufficientData(format!(
+                    "no {} samples for {}/{}/{}",
+                    resource, deployment.namespace, deployment.name, container.name
+                )));
+            }
+        }
+
+        // Peak CPU of short windows, which the smoothed usage averages out
+        let mut burst = None;
+        let mut burst_warning = None;
+        if let Some(limit_query) = &self.config.cpu_limit_query {
+            let burst_window = self.config.burst_window;
+            let peaks = self
+                .query_metrics(
+                    limit_query,
+                    burst_window,
+                    deployment,
+                    container,
+                    window,
+                    memo,
+                )
+                .await?;
+            if peaks.stats.samples > 0 {
+                burst = Some(peaks);
+            } else {
+                warn!(
+                    "No {} burst samples for {}/{}/{}, sizing its CPU limit from usage",
+                    promql_duration(burst_window),
+                    deployment.namespace,
+                    deployment.name,
+                    container.name
+                );
+                burst_warning = Some(format!(
+                    "CPU limit sized from usage, the {} burst query returned no samples (scrape interval longer than half the window?)",
+                    promql_duration(burst_window)
+                ));
+            }
+        }
+
+        let mut recommendation = build_recommendation(
+            deployment,
+            container,
+            cpu.stats,
+            memory.stats,
+            window.confidence(cpu.steps.min(memory.steps)),
+            &self.config,
+        );
+        if let (Some(cpu_runs), Some(memory_runs)) = (cpu.runs, memory.runs) {
+            size_for_runs(
+                &mut recommendation,
+                &self.config,
+                deployment.kind,
+                cpu_runs,
+                memory_runs,
+            );
+        }
+        let lookback_hours = window.lookback().as_secs_f64() / 3600.0;
+        if lookback_hours != self.config.lookback_hours {
+            recommendation
+                .recommendation_reason
+                .push_str(&format!("; {}h lookback for this workload", lookback_hours));
+        }
+        if let Some(burst) = &burst {
+            [_, recommendation.recommended_cpu_limit, _, _] = size_for_percentiles(
+                &self.config.percentiles(),
+                UsageSource::Stats(&recommendation.cpu_usage_stats),
+                UsageSource::Stats(&burst.stats),
+                UsageSource::Stats(&recommendation.memory_usage_stats),
+            );
+            recommendation.enforce_limits_above_requests();
+            recommendation.cpu_burst_window = Some(promql_duration(self.config.burst_window));
+            recommendation.recommendation_reason.push_str(&format!(
+                "; CPU limit from {} bursts, p99: {:.3} cores",
+                promql_duration(self.config.burst_window),
+                burst.stats.p99
+            ));
+        }
+        if let Some(filter) = &self.config.outlier_filter {
+            let burst_spikes = burst.as_ref().map(|burst| ("CPU burst", &burst.spikes));
+            for (resource, spikes) in [("CPU", &cpu.spikes), ("memory", &memory.spikes)]
+                .into_iter()
+                .chain(burst_spikes)
+            {
+                if let Some(text) = describe_spikes(resource, spikes, filter.action, window) {
+                    recommendation.recommendation_reason.push_str("; ");
+                    recommendation.recommendation_reason.push_str(&text);
+                }
+            }
+        }
+        for (resource, seasonality) in [("CPU", &cpu.seasonality), ("memory", &memory.seasonality)]
+        {
+            if let Some(seasonality) = seasonality {
+                recommendation.recommendation_reason.push_str(&format!(
+                    "; {} usage strongly {} ({:.0}% of variance by hour), sized for peak hours {}",
+                    resource,
+                    seasonality.period,
+                    seasonality.strength * 100.0,
+                    seasonality.describe_peaks()
+                ));
+            }
+        }
+        if let Some(config) = &self.config.forecast {
+            apply_forecast(&mut recommendation, config, cpu.forecast, memory.forecast);
+        }
+        if !deployment.kind.is_batch()
+            && let (Some(cpu_usage), Some(memory_usage)) = (cpu.usage, memory.usage)
+        {
+            let growth = |forecast: Option<Forecast>| forecast.map_or(1.0, |f| f.growth_factor());
+            recommendation.usage = Some(Arc::new(RetainedUsage {
+                cpu: cpu_usage,
+                memory: memory_usage,
+                cpu_burst: burst.and_then(|burst| burst.usage),
+                cpu_growth: growth(cpu.forecast),
+                memory_growth: growth(memory.forecast),
+                weekend: None,
+            }));
+        }
+        recommendation.cpu_rate_window = cpu_rate_window.map(promql_duration);
+        recommendation.warnings.extend(rate_window_warning);
+        recommendation.warnings.extend(burst_warning);
+        Ok(recommendation)
+    }
+
+    /// Query the metrics backend and summarize the values in a single pass
+    ///
+    /// Returns the stats along with the number of distinct query steps observed.
+    /// With an outlier filter, short spikes are dropped or capped first.
+    /// Results are memoized per query for the whole run, e.g. for a workload listed
+    /// twice, and both parts of the week are summarized from one fetch.
+    /// Series of pods not owned by `deployment` are dropped with a warning. Samples
+    /// of aggregated templates are divided by the replica count at their step, steps
+    /// without a known replica count are left out.
+    async fn query_metrics(
+        &self,
+        template: &QueryTemplate,
+        rate_window: Duration,
+        deployment: &DeploymentResources,
+        container: &ContainerResources,
+        window: QueryWindow,
+        memo: &QueryMemo,
+    ) -> Result<QueryStats> {
+        let query = template.render(deployment, container, rate_window, window.step);
+        let key = (query.clone(), window.range(), window.days.is_some());
+        let cell = lock(&memo.stats).entry(key).or_default().clone();
+        if cell.initialized() {
+            debug!("Reusing results of identical query: {}", query);
+        }
+        let results = cell
+            .get_or_try_init(|| async {
+                let replicas = if template.is_aggregated() {
+                    Some(self.replica_counts(deployment, window, memo).await?)
+                } else {
+                    None
+                };
+                self.summarize_query(&query, deployment, window, replicas.as_deref())
+                    .await
+            })
+            .await?;
+        Ok(results
+            .iter()
+            .find(|(days, _)| *days == window.days)
+            .map(|(_, stats)| stats.clone())
+            .expect("every part of the week of a window is summarized"))
+    }
+
+    /// Stream the samples of `query` into a summary per part of the week of `window`
+    async fn summarize_query(
+        &self,
+        query: &str,
+        deployment: &DeploymentResources,
+        window: QueryWindow,
+        replicas: Option<&HashMap<i64, f64>>,
+    ) -> Result<DayStats> {
+        let mut usage: Vec<UsageReducer> = window
+            .profiles()
+            .into_iter()
+            .map(|window| {
+                UsageReducer::new(window, replicas, &self.config, deployment.kind.is_batch())
+            })
+            .collect();
+        let mut foreign = BTreeSet::new();
+        self.metrics
+            .stream_range(
+                query,
+                window.start,
+                window.end,
+                window.step,
+                &mut |series: Series| match series.labels.get("pod") {
+                    Some(pod) if !deployment.owns_pod(pod) => {
+                        foreign.insert(pod.clone());
+                    }
+                    _ => usage.iter_mut().for_each(|usage| usage.push(&series)),
+                },
+            )
+            .await?;
+        warn_foreign_pods(&foreign, deployment);
+
+        Ok(usage
+            .into_iter()
+            .map(|usage| (usage.window.days, usage.finish(query, deployment)))
+            .collect())
+    }
+
+    /// Replica count of `deployment` per query step, queried once per run
+    ///
+    /// kube-state-metrics may be scraped by several jobs, so duplicate series are
+    /// merged by taking the highest count of a step.
+    async fn replica_counts(
+        &self,
+        deployment: &DeploymentResources,
+        window: QueryWindow,
+        memo: &QueryMemo,
+    ) -> Result<ReplicaCounts> {
+        let query = replicas_query(deployment);
+        let cell = lock(&memo.replicas)
+            .entry(query.clone())
+            .or_default()
+            .clone();
+        let counts = cell
+            .get_or_try_init(|| async {
+                let start = to_unix_seconds(window.start);
+                let step = window.step.as_secs_f64();
+                let mut counts: HashMap<i64, f64> = HashMap::new();
+                self.metrics
+                    .stream_range(
+                        &query,
+                        window.start,
+                        window.end,
+                        window.step,
+                        &mut |series: Series| {
+                            for sample in &series.samples {
+                                let count = counts
+                                    .entry(step_index(start, step, sample.timestamp))
+                                    .or_default();
+                                *count = count.max(sample.value);
+                            }
+                        },
+                    )
+                    .await?;
+                if counts.is_empty() {
+                    warn!(
+                        "No {} samples for {}/{}, aggregated usage can't be split per pod",
+                        REPLICAS_METRIC, deployment.namespace, deployment.name
+                    );
+                }
+                Ok::<_, RecommenderError>(Arc::new(counts))
+            })
+            .await?;
+        Ok(counts.clone())
+    }
+}
+
+/// Warn about series of pods not owned by `deployment`, which were left out
+///
+/// Without known ReplicaSets the pod matcher is name-based, so another workload
+/// following the same naming scheme (or an unrelated Job) could otherwise leak
+/// into the samples.
+fn warn_foreign_pods(pods: &BTreeSet<String>, deployment: &DeploymentResources) {
+    if pods.is_empty() {
+        return;
+    }
+    let pods: Vec<&str> = pods.iter().map(String::as_str).collect();
+    warn!(
+        "Ignoring series of {} pods not owned by deployment {}/{}: {}",
+        pods.len(),
+        deployment.namespace,
+        deployment.name,
+        pods.join(", ")
+    );
+}
+
+/// Summary of one usage query built up while its series are decoded
+///
+/// Values go straight into the stats, so memory doesn't grow with the lookback.
+struct UsageReducer<'a> {
+    window: QueryWindow,
+    /// Replica count per step, for aggregated templates
+    replicas: Option<&'a HashMap<i64, f64>>,
+    config: &'a RecommenderConfig,
+    steps: StepSet,
+    /// Values per step, when a later pass over them is needed
+    values: Option<Vec<(i64, f64)>>,
+    stats: StreamingStats,
+    /// Sum and count of the values per step, for forecasting
+    step_totals: BTreeMap<i64, (f64, usize)>,
+    /// Peak and last timestamp per pod of a CronJob or Job
+    runs: Option<HashMap<String, (f64, f64)>>,
+    unknown_replicas: usize,
+}
+
+impl<'a> UsageReducer<'a> {
+    fn new(
+        window: QueryWindow,
+        replicas: Option<&'a HashMap<i64, f64>>,
+        config: &'a RecommenderConfig,
+        batch: bool,
+    ) -> Self {
+        let buffered = config.outlier_filter.is_some() || config.seasonality.is_some();
+        Self {
+            window,
+            replicas,
+            config,
+            steps: StepSet::new(window),
+            values: buffered.then(Vec::new),
+            stats: StreamingStats::new(),
+            step_totals: BTreeMap::new(),
+            runs: batch.then(HashMap::new),
+            unknown_replicas: 0,
+        }
+    }
+
+    /// Add the samples of a series, or of one chunk of it
+    ///
+    /// Samples of aggregated templates are divided by the replica count at their
+    /// step, steps without a known replica count are left out.
+    fn push(&mut self, series: &Series) {
+        if let Some(runs) = &mut self.runs {
+            let pod = series.labels.get("pod").cloned().unwrap_or_default();
+            for sample in &series.samples {
+                if !self.window.includes(sample.timestamp) || !sample.value.is_finite() {
+                    continue;
+                }
+                let (peak, last) = runs.entry(pod.clone()).or_insert((0.0, f64::MIN));
+                *peak = peak.max(sample.value);
+                *last = last.max(sample.timestamp);
+            }
+        }
+
+        for sample in &series.samples {
+            if !self.window.includes(sample.timestamp) {
+                continue;
+            }
+            let step = step_index(self.steps.start, self.steps.step, sample.timestamp);
+            let value = match self.replicas {
+                Some(replicas) => match replicas.get(&step) {
+                    Some(&count) if count > 0.0 => sample.value / count,
+                    _ => {
+                        self.unknown_replicas += 1;
+                        continue;
+                    }
+                },
+                None => sample.value,
+            };
+            if !value.is_finite() || value < 0.0 {
+                continue;
+            }
+            self.steps.insert(sample.timestamp);
+            match &mut self.values {
+                Some(values) => values.push((step, value)),
+                None => self.add(step, value, true),
+            }
+        }
+    }
+
+    /// Count a value left after the spike and seasonality passes
+    fn add(&mut self, step: i64, value: f64, peak_hour: bool) {
+        // Strongly periodic usage is sized for its peak hours, forecast from all of them
+        if peak_hour {
+            self.stats.push(value);
+        }
+        if self.config.forecast.is_some() {
+            let (sum, count) = self.step_totals.entry(step).or_default();
+            *sum += value;
+            *count += 1;
+        }
+    }
+
+    /// Drop or cap spikes, restrict periodic usage to its peak hours and summarize
+    fn finish(mut self, query: &str, deployment: &DeploymentResources) -> QueryStats {
+        if self.unknown_replicas > 0 {
+            debug!(
+                "Dropped {} aggregated samples of {}/{} without a replica count",
+                self.unknown_replicas, deployment.namespace, deployment.name
+            );
+        }
+
+        let (start, step_secs) = (self.steps.start, self.steps.step);
+        let timestamp = move |step: i64| start + step as f64 * step_secs;
+        let mut spikes = SpikeScan::default();
+        let mut seasonality = None;
+        if let Some(values) = self.values.take() {
+            if let Some(filter) = &self.config.outlier_filter {
+                let mut profile: BTreeMap<i64, f64> = BTreeMap::new();
+                for &(step, value) in &values {
+                    let peak = profile.entry(step).or_default();
+                    *peak = peak.max(value);
+                }
+                let max_steps =
+                    (filter.max_duration.as_secs_f64() / self.window.step.as_secs_f64()) as usize;
+                spikes = find_spikes(&profile, filter.threshold, max_steps);
+            }
+
+            let values: Vec<(i64, f64)> = values
+                .into_iter()
+                .filter_map(|(step, value)| {
+                    match (spikes.spike_at(step), &self.config.outlier_filter) {
+                        (Some(_), Some(filter)) if filter.action == OutlierAction::Exclude => None,
+                        (Some(_), _) => Some((step, value.min(spikes.threshold))),
+                        (None, _) => Some((step, value)),
+                    }
+                })
+                .collect();
+            seasonality = self.config.seasonality.and_then(|config| {
+                let samples: Vec<(f64, f64)> = values
+                    .iter()
+                    .map(|&(step, value)| (timestamp(step), value))
+                    .collect();
+                Seasonality::detect(&samples, self.window.timezone)
+                    .filter(|seasonality| seasonality.strength >= config.min_strength)
+            });
+
+            for (step, value) in values {
+                let peak_hour = seasonality
+                    .as_ref()
+                    .is_none_or(|seasonality| seasonality.is_peak(timestamp(step)));
+                self.add(step, value, peak_hour);
+            }
+        }
+
+        let forecast = self.config.forecast.and_then(|config| {
+            let profile = std::mem::take(&mut self.step_totals)
+                .into_iter()
+                .map(|(step, (sum, count))| (step, sum / count as f64))
+                .collect();
+            forecast(&profile, self.window.step, config.method, config.horizon)
+        });
+
+        debug!(
+            "Collected {} data points for query: {}",
+            self.stats.count(),
+            query
+        );
+        if !spikes.spikes.is_empty() {
+            debug!(
+                "Found {} usage spikes above {:.3} for query: {}",
+                spikes.spikes.len(),
+                spikes.threshold,
+                query
+            );
+        }
+        QueryStats {
+            stats: self.stats.finish(),
+            steps: self.steps.len(),
+            spikes,
+            forecast,
+            seasonality,
+            runs: self.runs.map(|pods| run_peaks(&pods, self.window)),
+            usage: self.config.retain_usage.then_some(self.stats),
+        }
+    }
+}
+
+/// Peak usage of the runs of a CronJob or Job from the peak and last sample of each pod
+///
+/// A pod still reporting at the end of the window may not have peaked yet, so
+/// only pods gone by then count as completed runs.
+fn run_peaks(pods: &HashMap<String, (f64, f64)>, window: QueryWindow) -> RunPeaks {
+    let end = to_unix_seconds(window.end) - window.step.as_secs_f64();
+    let completed: Vec<f64> = pods
+        .values()
+        .filter(|(_, last)| *last < end)
+        .map(|(peak, _)| *peak)
+        .collect();
+    let peaks: Vec<f64> = if completed.is_empty() {
+        pods.values().map(|(peak, _)| *peak).collect()
+    } else {
+        completed.clone()
+    };
+    RunPeaks {
+        completed: completed.len(),
+        peak: peaks.into_iter().fold(0.0, f64::max),
+    }
+}
+
+/// Size a CronJob's or Job's requests and limits for the peak of its runs
+///
+/// Its pods are idle or gone most of the lookback and start slowly, so low
+/// percentiles of all samples would starve the runs themselves.
+fn size_for_runs(
+    recommendation: &mut ResourceRecommendation,
+    config: &RecommenderConfig,
+    kind: WorkloadKind,
+    cpu: RunPeaks,
+    memory: RunPeaks,
+) {
+    let cpu_peak = round_cpu(cpu.peak * config.safety_margin);
+    let memory_peak = round_memory(memory.peak * config.safety_margin);
+    recommendation.recommended_cpu_request = cpu_peak;
+    recommendation.recommended_cpu_limit = cpu_peak;
+    recommendation.recommended_memory_request = memory_peak;
+    recommendation.recommended_memory_limit = memory_peak;
+
+    let completed = cpu.completed.min(memory.completed);
+    recommendation.confidence = (completed as f64 / CONFIDENT_RUNS as f64).min(1.0);
+    if completed == 0 {
+        recommendation.recommendation_reason.push_str(&format!(
+            "; {} sized for the peak of its running pods: {:.3} cores, {:.0}Mi",
+            kind,
+            cpu.peak,
+            memory.peak / (1024.0 * 1024.0)
+        ));
+        recommendation
+            .warnings
+            .push(format!("No {} run completed within the lookback", kind));
+    } else {
+        recommendation.recommendation_reason.push_str(&format!(
+            "; {} sized for the peak of {} completed runs: {:.3} cores, {:.0}Mi",
+            kind,
+            completed,
+            cpu.peak,
+            memory.peak / (1024.0 * 1024.0)
+        ));
+    }
+}
+
+/// Compute a recommendation from raw samples, without any metrics or Kubernetes client
+///
+/// `cpu_samples` are in cores and `memory_samples` in bytes. Each sample is assumed to
+/// cover one query step (see [`RecommenderConfig::effective_query_step`]) of the lookback window when computing the confidence.
+pub fn recommend_from_samples(
+    deployment: &DeploymentResources,
+    container: &ContainerResources,
+    cpu_samples: &[f64],
+    memory_samples: &[f64],
+    config: &RecommenderConfig,
+) -> ResourceRecommendation {
+    build_recommendation(
+        deployment,
+        container,
+        UsageStats::from_values(cpu_samples),
+        UsageStats::from_values(memory_samples),
+        calculate_confidence(
+            Duration::from_secs_f64(config.lookback_hours * 3600.0),
+            config.effective_query_step(),
+            cpu_samples.len().min(memory_samples.len()),
+        ),
+        config,
+    )
+}
+
+/// Turn usage statistics into a recommendation for one container
+fn build_recommendation(
+    deployment: &DeploymentResources,
+    container: &ContainerResources,
+    cpu_stats: UsageStats,
+    memory_stats: UsageStats,
+    confidence: f64,
+    config: &RecommenderConfig,
+) -> ResourceRecommendation {
+    let [
+        recommended_cpu_request,
+        recommended_cpu_limit,
+        recommended_memory_request,
+        recommended_memory_limit,
+    ] = size_for_percentiles(
+        &config.percentiles(),
+        UsageSource::Stats(&cpu_stats),
+        UsageSource::Stats(&cpu_stats),
+        UsageSource::Stats(&memory_stats),
+    );
+
+    let recommendation_reason = generate_reason(
+        container,
+        &cpu_stats,
+        &memory_stats,
+        recommended_cpu_request,
+        recommended_memory_request,
+    );
+
+    let mut recommendation = ResourceRecommendation {
+        deployment: deployment.name.clone(),
+        container: container.name.clone(),
+        namespace: deployment.namespace.clone(),
+        workload_kind: deployment.kind,
+        current_cpu_request: container.cpu_request,
+        current_cpu_limit: container.cpu_limit,
+        current_memory_request: container.memory_request,
+        current_memory_limit: container.memory_limit,
+        recommended_cpu_request,
+        recommended_cpu_limit,
+        recommended_memory_request,
+        recommended_memory_limit,
+        cpu_usage_stats: cpu_stats,
+        memory_usage_stats: memory_stats,
+        recommendation_reason,
+        confidence,
+        warnings: current_limit_warnings(container),
+        cpu_rate_window: None,
+        cpu_burst_window: None,
+        day_profile: None,
+        vpa: None,
+        hpa: None,
+        pdbs: Vec::new(),
+        disruption_risk: None,
+        from_metrics_server: false,
+        drift: None,
+        replicas: deployment.replicas.map(|replicas| replicas.max(0) as u64),
+        estimated_monthly_savings: None,
+        usage: None,
+    };
+    recommendation.enforce_limits_above_requests();
+    recommendation
+}
+
+/// Scale requests and limits by the projected growth of their usage, noting it in
+/// the reason and warning about workloads growing faster than the threshold
+fn apply_forecast(
+    recommendation: &mut ResourceRecommendation,
+    config: &ForecastConfig,
+    cpu: Option<Forecast>,
+    memory: Option<Forecast>,
+) {
+    let growth = |forecast: Option<Forecast>| forecast.map_or(1.0, |f| f.growth_factor());
+    scale_for_growth(recommendation, growth(cpu), growth(memory));
+
+    let weeks = config.horizon.as_secs_f64() / (7.0 * 24.0 * 3600.0);
+    for (resource, forecast) in [("CPU", cpu), ("Memory", memory)] {
+        let Some(forecast) = forecast else { continue };
+        let factor = forecast.growth_factor();
+        if factor > 1.0 {
+            recommendation.recommendation_reason.push_str(&format!(
+                "; {} usage growing {:.1}%/week, sized for the {:.0}-week forecast (x{:.2})",
+                resource,
+                forecast.weekly_growth * 100.0,
+                weeks,
+                factor
+            ));
+        }
+        if forecast.weekly_growth > config.growth_warning {
+            recommendation.warnings.push(format!(
+                "{} usage growing {:.1}%/week, faster than the {:.0}% threshold",
+                resource,
+                forecast.weekly_growth * 100.0,
+                config.growth_warning * 100.0
+            ));
+        }
+    }
+}
+
+/// Scale CPU and memory requests and limits by the growth factors of their forecasts
+fn scale_for_growth(recommendation: &mut ResourceRecommendation, cpu: f64, memory: f64) {
+    let resources = [
+        (
+            cpu,
+            round_cpu as fn(f64) -> Quantity,
+            [
+                &mut recommendation.recommended_cpu_request,
+                &mut recommendation.recommended_cpu_limit,
+            ],
+        ),
+        (
+            memory,
+            round_memory,
+            [
+                &mut recommendation.recommended_memory_request,
+                &mut recommendation.recommended_memory_limit,
+            ],
+        ),
+    ];
+    for (factor, round, quantities) in resources {
+        if factor > 1.0 {
+            for quantity in quantities {
+                *quantity = round(quantity.value() * factor);
+            }
+        }
+    }
+    recommendation.enforce_limits_above_requests();
+}
+
+/// Reason text listing the spikes of a resource, None when there were none
+fn describe_spikes(
+    resource: &str,
+    scan: &SpikeScan,
+    action: OutlierAction,
+    window: QueryWindow,
+) -> Option<String> {
+    if scan.spikes.is_empty() {
+        return None;
+    }
+    let time = |step: i64| {
+        let time = window.start + window.step.mul_f64(step.max(0) as f64);
+        window.timezone.convert(time.into())
+    };
+    let mut windows: Vec<String> = scan
+        .spikes
+        .iter()
+        .take(MAX_LISTED_SPIKES)
+        .map(|spike| {
+            let (start, end) = (time(spike.first_step), time(spike.last_step + 1));
+            let end_format = if start.date_naive() == end.date_naive() {
+                "%H:%M"
+            } else {
+                "%Y-%m-%d %H:%M"
+            };
+            let peak = if scan.median > 0.0 {
+                format!("peak {:.1}x median", spike.peak / scan.median)
+            } else {
+                format!("peak {:.3}", spike.peak)
+            };
+            let zone = match window.timezone {
+                Timezone::Utc => "UTC".to_string(),
+                _ => start.format("UTC%:z").to_string(),
+            };
+            format!(
+                "{}-{} {} ({})",
+                start.format("%Y-%m-%d %H:%M"),
+                end.format(end_format),
+                zone,
+                peak
+            )
+        })
+        .collect();
+    if scan.spikes.len() > MAX_LISTED_SPIKES {
+        windows.push(format!(
+            "and {} more",
+            scan.spikes.len() - MAX_LISTED_SPIKES
+        ));
+    }
+    let verb = match action {
+        OutlierAction::Exclude => "excluded",
+        OutlierAction::Cap => "capped",
+    };
+    Some(format!(
+        "{} spikes {}: {}",
+        resource,
+        verb,
+        windows.join(", ")
+    ))
+}
+
+/// One recommendation sized for the busier part of the week, value by value
+fn merge_day_profiles(
+    weekday: ResourceRecommendation,
+    weekend: ResourceRecommendation,
+) -> ResourceRecommendation {
+    let pick = |weekday: Quantity, weekend: Quantity| {
+        if weekend > weekday {
+            (weekend, DayProfile::Weekend)
+        } else {
+            (weekday, DayProfile::Weekday)
+        }
+    };
+    let (cpu_request, cpu_request_days) = pick(
+        weekday.recommended_cpu_request,
+        weekend.recommended_cpu_request,
+    );
+    let (cpu_limit, _) = pick(weekday.recommended_cpu_limit, weekend.recommended_cpu_limit);
+    let (memory_request, memory_request_days) = pick(
+        weekday.recommended_memory_request,
+        weekend.recommended_memory_request,
+    );
+    let (memory_limit, _) = pick(
+        weekday.recommended_memory_limit,
+        weekend.recommended_memory_limit,
+    );
+
+    let mut warnings = weekday.warnings.clone();
+    for warning in &weekend.warnings {
+        if !warnings.contains(warning) {
+            warnings.push(warning.clone());
+        }
+    }
+    let stats_from = |days: DayProfile| match days {
+        DayProfile::Weekday => &weekday,
+        DayProfile::Weekend => &weekend,
+    };
+
+    let mut merged = ResourceRecommendation {
+        recommended_cpu_request: cpu_request,
+        recommended_cpu_limit: cpu_limit,
+        recommended_memory_request: memory_request,
+        recommended_memory_limit: memory_limit,
+        cpu_usage_stats: stats_from(cpu_request_days).cpu_usage_stats.clone(),
+        memory_usage_stats: stats_from(memory_request_days).memory_usage_stats.clone(),
+        recommendation_reason: format!(
+            "CPU sized for {}s, memory for {}s. Weekdays: {}. Weekends: {}",
+            cpu_request_days,
+            memory_request_days,
+            weekday.recommendation_reason,
+            weekend.recommendation_reason
+        ),
+        confidence: weekday.confidence.min(weekend.confidence),
+        warnings,
+        day_profile: None,
+        usage: weekday
+            .usage
+            .as_ref()
+            .zip(weekend.usage.clone())
+            .map(|(weekday, weekend)| {
+                Arc::new(RetainedUsage {
+                    weekend: Some(weekend),
+                    ..RetainedUsage::clone(weekday)
+                })
+            }),
+        ..weekday.clone()
+    };
+    merged.enforce_limits_above_requests();
+    merged
+}
+
+/// Flag current specs whose limit is below the request, which the API server rejects
+fn current_limit_warnings(container: &ContainerResources) -> Vec<String> {
+    [
+        ("CPU", container.cpu_request, container.cpu_limit),
+        ("memory", container.memory_request, container.memory_limit),
+    ]
+    .into_iter()
+    .filter_map(|(resource, request, limit)| match (request, limit) {
+        (Some(request), Some(limit)) if limit < request => Some(format!(
+            "Current {} limit {} is below its request {}",
+            resource, limit, request
+        )),
+        _ => None,
+    })
+    .collect()
+}
+
+/// Confidence is the share of expected query steps that returned data
+fn calculate_confidence(lookback: Duration, step: Duration, observed_steps: usize) -> f64 {
+    let expected_steps = (lookback.as_secs_f64() / step.as_secs_f64())
+        .floor()
+        .max(1.0);
+    (observed_steps as f64 / expected_steps).min(1.0)
+}
+
+/// Usage a request or limit is sized from
+#[derive(Debug, Clone, Copy)]
+enum UsageSource<'a> {
+    /// Summary of a run: requests from its p50 or p95, limits from its p95 or p99
+    Stats(&'a UsageStats),
+    /// Retained sketch, read at the percentile itself
+    Sketch(&'a StreamingStats),
+}
+
+impl UsageSource<'_> {
+    fn request(self, pct: f64) -> f64 {
+        match self {
+            UsageSource::Stats(stats) => {
+                percentile(&[stats.p50, stats.p95], pct, Interpolation::Higher)
+            }
+            UsageSource::Sketch(sketch) => sketch.quantile(pct / 100.0),
+        }
+    }
+
+    fn limit(self, pct: f64) -> f64 {
+        match self {
+            UsageSource::Stats(stats) => {
+                percentile(&[stats.p95, stats.p99], pct, Interpolation::Higher)
+            }
+            UsageSource::Sketch(sketch) => sketch.quantile(pct / 100.0),
+        }
+    }
+}
+
+/// CPU request and limit, memory request and limit for `percentiles` of the usage
+///
+/// The CPU limit follows `cpu_limit`, the bursts when a limit query ran. Shared by
+/// the run and [`ResourceRecommendation::retune`], so both size alike.
+fn size_for_percentiles(
+    percentiles: &PercentileConfig,
+    cpu: UsageSource,
+    cpu_limit: UsageSource,
+    memory: UsageSource,
+) -> [Quantity; 4] {
+    let margin = percentiles.safety_margin;
+    [
+        round_cpu(cpu.request(percentiles.cpu_request) * margin),
+        round_cpu(cpu_limit.limit(percentiles.cpu_limit) * margin),
+        round_memory(memory.request(percentiles.memory_request) * margin),
+        round_memory(memory.limit(percentiles.memory_limit) * margin),
+    ]
+}
+
+/// Generate human-readable reason for the recommendation
+fn generate_reason(
+    container: &ContainerResources,
+    cpu_stats: &UsageStats,
+    memory_stats: &UsageStats,
+    recommended_cpu: Quantity,
+    recommended_memory: Quantity,
+) -> String {
+    let mut reasons = Vec::new();
+
+    // CPU analysis
+    if container.cpu_request.is_none() {
+        reasons.push(format!(
+            "No CPU request set, recommend {} based on p95 usage",
+            recommended_cpu
+        ));
+    } else if cpu_stats.p95 > 0.0 {
+        reasons.push(format!(
+            "CPU p95 usage: {:.3} cores, avg: {:.3} cores",
+            cpu_stats.p95, cpu_stats.avg
+        ));
+    }
+
+    // Memory analysis
+    if container.memory_request.is_none() {
+        reasons.push(format!(
+            "No memory request set, recommend {} based on p95 usage",
+            recommended_memory
+        ));
+    } else if memory_stats.p95 > 0.0 {
+        let mem_mib = memory_stats.p95 / (1024.0 * 1024.0);
+        reasons.push(format!(
+            "Memory p95 usage: {:.0}Mi, avg: {:.0}Mi",
+            mem_mib,
+            memory_stats.avg / (1024.0 * 1024.0)
+        ));
+    }
+
+    if reasons.is_empty() {
+        "Based on observed usage patterns".to_string()
+    } else {
+        reasons.join("; ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use crate::config::ForecastMethod;
+    use crate::metrics::{Sample, StaticMetricsProvider};
+    use crate::outliers::Spike;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Tuesday evening UTC, so the last hour is on weekdays only
+    const NOW: u64 = 1_700_000_000;
+
+    fn cpu_query(container: &str) -> String {
+        format!(
+            r#"rate(container_cpu_usage_seconds_total{{namespace="default",pod=~"web-[a-z0-9]+-[a-z0-9]+",container="{}"}}[5m])"#,
+            container
+        )
+    }
+
+    fn memory_query(container: &str) -> String {
+        format!(
+            r#"container_memory_working_set_bytes{{namespace="default",pod=~"web-[a-z0-9]+-[a-z0-9]+",container="{}"}}"#,
+            container
+        )
+    }
+
+    /// One sample a minute over the last hour, scaled by `usage` from the newest one back
+    fn series(pod: &str, usage: impl Fn(usize) -> f64) -> Series {
+        Series {
+            labels: HashMap::from([("pod".to_string(), pod.to_string())]),
+            samples: (0..60)
+                .map(|i| Sample {
+                    timestamp: (NOW - i as u64 * 60) as f64,
+                    value: usage(i),
+                })
+                .collect(),
+        }
+    }
+
+    fn recommender(metrics: Arc<dyn MetricsProvider>, config: RecommenderConfig) -> Recommender {
+        Recommender::with_provider(metrics, config).with_clock(Arc::new(FixedClock::new(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(NOW),
+        )))
+    }
+
+    fn quantity(value: &str) -> Quantity {
+        Quantity::parse(value).unwrap()
+    }
+
+    fn deployment(containers: &[&str]) -> DeploymentResources {
+        let containers: Vec<_> = containers
+            .iter()
+            .map(|container| {
+                json!({
+                    "name": container,
+                    "cpu_request": "100m",
+                    "cpu_limit": null,
+                    "memory_request": "128Mi",
+                    "memory_limit": null,
+                })
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "name": "web",
+            "namespace": "default",
+            "containers": containers,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sizes_requests_and_limits_from_usage() {
+        let metrics = StaticMetricsProvider::new()
+            .with_series(cpu_query("app"), vec![series("web-5d9f8-abcde", |_| 0.25)])
+            .with_series(
+                memory_query("app"),
+                vec![series("web-5d9f8-abcde", |_| 64.0 * 1024.0 * 1024.0)],
+            );
+        let config = RecommenderConfig::builder()
+            .lookback_hours(1.0)
+            .safety_margin(2.0)
+            .build();
+        let recommender = recommender(Arc::new(metrics), config);
+
+        let recommendations = recommender
+            .generate_recommendations(vec![deployment(&["app"])])
+            .await
+            .unwrap();
+
+        let [rec] = recommendations.as_slice() else {
+            panic!("expected one recommendation, got {}", recommendations.len());
+        };
+        assert_eq!(rec.current_cpu_request, Some(quantity("100m")));
+        assert_eq!(rec.current_cpu_limit, None);
+        assert_eq!(rec.recommended_cpu_request, quantity("500m"));
+        assert_eq!(rec.recommended_cpu_limit, quantity("500m"));
+        assert_eq!(rec.recommended_memory_request, quantity("128Mi"));
+        assert_eq!(rec.recommended_memory_limit, quantity("128Mi"));
+        assert_eq!(rec.cpu_usage_stats.samples, 60);
+        assert_eq!(rec.confidence, 1.0);
+    }
+
+    #[tokio::test]
+    async fn limits_follow_higher_percentile_than_requests() {
+        // Two of 60 samples at twice the usage, above p95 but not p99
+        let metrics = StaticMetricsProvider::new()
+            .with_series(
+                cpu_query("app"),
+                vec![series("web-5d9f8-abcde", |i| {
+                    if i == 10 || i == 40 { 1.0 } else { 0.5 }
+                })],
+            )
+            .with_series(
+                memory_query("app"),
+                vec![series("web-5d9f8-abcde", |_| 1e8)],
+            );
+        let config = RecommenderConfig::builder()
+            .lookback_hours(1.0)
+            .safety_margin(1.0)
+            .build();
+        let recommender = recommender(Arc::new(metrics), config);
+
+        let recommendations = recommender
+            .generate_recommendations(vec![deployment(&["app"])])
+            .await
+            .unwrap();
+
+        let rec = &recommendations[0];
+        assert_eq!(rec.cpu_usage_stats.max, 1.0);
+        // Quantiles come from the sketch, within 1% of the samples
+        assert!((rec.cpu_usage_stats.p95 - 0.5).abs() <= 0.005);
+        assert!(rec.recommended_cpu_request <= quantity("505m"));
+        assert!(rec.recommended_cpu_limit >= quantity("990m"));
+    }
+
+    #[tokio::test]
+    async fn skips_containers_without_samples() {
+        let metrics = StaticMetricsProvider::new()
+            .with_series(cpu_query("app"), vec![series("web-5d9f8-abcde", |_| 0.25)])
+            .with_series(
+                cpu_query("idle"),
+                vec![series("web-5d9f8-abcde", |_| f64::NAN)],
+            )
+            .with_series(
+                memory_query("idle"),
+                vec![series("web-5d9f8-abcde", |_| 1e8)],
+            );
+        let config = RecommenderConfig::builder().lookback_hours(1.0).build();
+        let recommender = recommender(Arc::new(metrics), config);
+
+        let (recommendations, skipped) = recommender
+            .analyze(vec![deployment(&["app", "idle"])])
+            .await
+            .unwrap();
+
+        assert!(recommendations.is_empty());
+        assert_eq!(skipped.len(), 2);
+        for skip in &skipped {
+            assert_eq!(skip.reason, SkipReason::NoData);
+        }
+        // No memory series at all, and only NaN CPU samples
+        assert_eq!(skipped[0].container.as_deref(), Some("app"));
+        assert_eq!(skipped[1].container.as_deref(), Some("idle"));
+    }
+
+    /// Static backend counting the range queries sent to it
+    struct CountingProvider {
+        inner: StaticMetricsProvider,
+        queries: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl MetricsProvider for CountingProvider {
+        async fn query_range(
+            &self,
+            query: &str,
+            start: SystemTime,
+            end: SystemTime,
+            step: Duration,
+        ) -> Result<Vec<Series>> {
+            self.queries.fetch_add(1, Ordering::SeqCst);
+            // Let concurrent containers reach the memo while this query is in flight
+            tokio::task::yield_now().await;
+            self.inner.query_range(query, start, end, step).await
+        }
+    }
+
+    fn counting_provider() -> Arc<CountingProvider> {
+        Arc::new(CountingProvider {
+            inner: StaticMetricsProvider::new()
+                .with_series(cpu_query("app"), vec![series("web-5d9f8-abcde", |_| 0.2)])
+                .with_series(
+                    memory_query("app"),
+                    vec![series("web-5d9f8-abcde", |_| 1e8)],
+                ),
+            queries: AtomicUsize::new(0),
+        })
+    }
+
+    #[tokio::test]
+    async fn identical_queries_are_sent_once_per_run() {
+        let metrics = counting_provider();
+        let config = RecommenderConfig::builder()
+            .lookback_hours(1.0)
+            .max_concurrent_queries(4)
+            .build();
+        let recommender = recommender(metrics.clone(), config);
+
+        // The same workload listed twice, with its container repeated in the first
+        let deployments = vec![deployment(&["app", "app"]), deployment(&["app"])];
+        let recommendations = recommender
+            .generate_recommendations(deployments)
+            .await
+            .unwrap();
+
+        assert_eq!(recommendations.len(), 3);
+        assert_eq!(metrics.queries.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn day_profiles_share_one_query() {
+        let metrics = counting_provider();
+        let config = RecommenderConfig::builder()
+            .lookback_hours(1.0)
+            .day_split(Some(DaySplit::Both))
+            // A part of the week without samples would retry with the wider window
+            .fallback_rate_window(None)
+            .build();
+        let recommender = recommender(metrics.clone(), config);
+
+        let (recommendations, _) = recommender
+            .analyze(vec![deployment(&["app"])])
+            .await
+            .unwrap();
+
+        // The last hour is on one part of the week only, the other has no usage
+        assert_eq!(recommendations.len(), 1);
+        assert_eq!(metrics.queries.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retune_to_the_run_percentiles_gives_back_its_values() {
+        // A growing week of hourly usage, busier and more variable on the weekend
+        let hourly = |usage: &dyn Fn(u64, bool) -> f64| Series {
+            labels: HashMap::from([("pod".to_string(), "web-5d9f8-abcde".to_string())]),
+            samples: (0..7 * 24)
+                .map(|i| {
+                    let timestamp = NOW - i * 3_600;
+                    let weekday = DateTime::from_timestamp(timestamp as i64, 0)
+                        .unwrap()
+                        .weekday();
+                    let weekend = matches!(weekday, Weekday::Sat | Weekday::Sun);
+                    Sample {
+                        timestamp: timestamp as f64,
+                        value: usage(7 * 24 - i, weekend),
+                    }
+                })
+                .collect(),
+        };
+        let cpu = hourly(&|hour, weekend| {
+            let base = if weekend {
+                0.3 + (hour % 10) as f64 * 0.02
+            } else {
+                0.2
+            };
+            base + hour as f64 * 0.001
+        });
+        let memory = hourly(&|hour, _| 1e8 + hour as f64 * 1e5);
+        let metrics = StaticMetricsProvider::new()
+            .with_series(cpu_query("app"), vec![cpu])
+            .with_series(memory_query("app"), vec![memory]);
+        let config = RecommenderConfig::builder()
+            .lookback_hours(7.0 * 24.0)
+            .query_step(Duration::from_secs(3_600))
+            .fallback_rate_window(None)
+            .safety_margin(1.2)
+            .day_split(Some(DaySplit::Max))
+            .forecast(Some(ForecastConfig::new(
+                ForecastMethod::Linear,
+                Duration::from_secs(4 * 7 * 24 * 3_600),
+            )))
+            .retain_usage(true)
+            .build();
+        let percentiles = config.percentiles();
+        let recommender = recommender(Arc::new(metrics), config);
+
+        let recommendations = recommender
+            .generate_recommendations(vec![deployment(&["app"])])
+            .await
+            .unwrap();
+
+        let [rec] = recommendations.as_slice() else {
+            panic!("expected one recommendation, got {}", recommendations.len());
+        };
+        let usage = rec.usage.as_ref().unwrap();
+        assert!(usage.weekend.is_some());
+        assert!(usage.cpu_growth > 1.0);
+        let retuned = rec.retune(&percentiles).unwrap();
+        assert_eq!(retuned.recommended_cpu_request, rec.recommended_cpu_request);
+        assert_eq!(retuned.recommended_cpu_limit, rec.recommended_cpu_limit);
+        assert_eq!(
+            retuned.recommended_memory_request,
+            rec.recommended_memory_request
+        );
+        assert_eq!(
+            retuned.recommended_memory_limit,
+            rec.recommended_memory_limit
+        );
+
+        let median = PercentileConfig {
+            cpu_request: 50.0,
+            ..percentiles
+        };
+        let retuned = rec.retune(&median).unwrap();
+        assert!(retuned.recommended_cpu_request < rec.recommended_cpu_request);
+    }
+
+    fn window(start: u64, end: u64, step: u64) -> QueryWindow {
+        QueryWindow {
+            start: SystemTime::UNIX_EPOCH + Duration::from_secs(start),
+            end: SystemTime::UNIX_EPOCH + Duration::from_secs(end),
+            step: Duration::from_secs(step),
+            days: None,
+            timezone: Timezone::Utc,
+        }
+    }
+
+    #[test]
+    fn describes_spikes_in_the_report_timezone() {
+        let scan = SpikeScan {
+            median: 0.5,
+            threshold: 1.0,
+            spikes: vec![Spike {
+                first_step: 0,
+                last_step: 9,
+                peak: 2.0,
+            }],
+        };
+        let describe = |timezone| {
+            let window = QueryWindow {
+                timezone,
+                ..window(NOW, NOW + 3_600, 60)
+            };
+            describe_spikes("CPU", &scan, OutlierAction::Cap, window).unwrap()
+        };
+
+        assert_eq!(
+            describe(Timezone::Utc),
+            "CPU spikes capped: 2023-11-14 22:13-22:23 UTC (peak 4.0x median)"
+        );
+        assert_eq!(
+            describe("+08:00".parse().unwrap()),
+            "CPU spikes capped: 2023-11-15 06:13-06:23 UTC+08:00 (peak 4.0x median)"
+        );
+    }
+
+    #[test]
+    fn step_set_counts_distinct_steps_at_window_edges() {
+        let mut steps = StepSet::new(window(1_000, 4_600, 300));
+        // Both edges are steps of their own
+        steps.insert(1_000.0);
+        steps.insert(4_600.0);
+        assert_eq!(steps.len(), 2);
+
+        // Jitter around an edge rounds to the same step
+        steps.insert(1_000.0 - 100.0);
+        steps.insert(1_000.0 + 149.0);
+        steps.insert(4_600.0 + 100.0);
+        assert_eq!(steps.len(), 2);
+
+        // Whole steps outside the window are ignored
+        steps.insert(1_000.0 - 300.0);
+        steps.insert(4_600.0 + 300.0);
+        assert_eq!(steps.len(), 2);
+
+        steps.insert(1_300.0);
+        steps.insert(4_300.0);
+        assert_eq!(steps.len(), 4);
+    }
+
+    #[test]
+    fn step_set_covers_uneven_spans() {
+        // 1000s of 300s steps: 0, 300, 600, 900 and the partial one at the end
+        let mut steps = StepSet::new(window(0, 1_000, 300));
+        for timestamp in (0..=1_200).step_by(300) {
+            steps.insert(timestamp as f64);
+        }
+        assert_eq!(steps.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn drops_invalid_and_foreign_samples() {
+        let usage = |i: usize| match i % 3 {
+            0 => f64::NAN,
+            1 => -1.0,
+            _ => 0.25,
+        };
+        let metrics = StaticMetricsProvider::new()
+            .with_series(
+                cpu_query("app"),
+                vec![
+                    series("web-5d9f8-abcde", usage),
+                    // Another workload whose pods match the name-based pattern
+                    series("web-api-5d9f8-abcde", |_| 10.0),
+                ],
+            )
+            .with_series(
+                memory_query("app"),
+                vec![series("web-5d9f8-abcde", |i| {
+                    if i == 30 { f64::INFINITY } else { 1e8 }
+                })],
+            );
+        let config = RecommenderConfig::builder().lookback_hours(1.0).build();
+        let recommender = recommender(Arc::new(metrics), config);
+
+        let recommendations = recommender
+            .generate_recommendations(vec![deployment(&["app"])])
+            .await
+            .unwrap();
+
+        let rec = &recommendations[0];
+        assert_eq!(rec.cpu_usage_stats.samples, 20);
+        assert_eq!(rec.cpu_usage_stats.min, 0.25);
+        assert_eq!(rec.cpu_usage_stats.max, 0.25);
+        assert_eq!(rec.memory_usage_stats.samples, 59);
+        assert_eq!(rec.memory_usage_stats.max, 1e8);
+    }
+}
@@ -0,0 +1,202 @@
+//! Uploading run artifacts to S3 for central reporting
+//!
+//! Objects are written with SigV4-signed `PutObject` requests through the shared
+//! HTTP client, using the same credentials as the AMP queries.
+
+use std::time::SystemTime;
+
+use aws_credential_types::Credentials;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{
+    PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest, SigningSettings,
+    UriPathNormalizationMode,
+};
+use aws_sigv4::sign::v4;
+use aws_smithy_runtime_api::client::identity::Identity;
+use chrono::{DateTime, Utc};
+use log::info;
+use reqwest::header::{CONTENT_TYPE, HeaderName, HeaderValue};
+use reqwest::{Client, Method, Request};
+use url::Url;
+
+use crate::aws_region::AwsRegion;
+use crate::config::S3Location;
+use crate::error::{AwsError, Result};
+use crate::http::shared_client;
+
+/// Writes the artifacts of one run under `<prefix><cluster>/<timestamp>/`
+pub struct S3Uploader {
+    client: Client,
+    location: S3Location,
+    region: AwsRegion,
+    credentials: Credentials,
+    /// Key prefix of the run, e.g. `reports/prod-eu/20261015T120000Z/`
+    run_prefix: String,
+}
+
+impl S3Uploader {
+    /// Upload with credentials already loaded, e.g. for the AMP queries
+    pub fn new(
+        location: S3Location,
+        region: AwsRegion,
+        credentials: Credentials,
+        cluster: &str,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let run_prefix = format!(
+            "{}{}/{}/",
+            location.prefix,
+            key_segment(cluster),
+            timestamp.format("%Y%m%dT%H%M%SZ")
+        );
+        Self {
+            client: shared_client(),
+            location,
+            region,
+            credentials,
+            run_prefix,
+        }
+    }
+
+    /// Load credentials from the default provider chain, like [`crate::PrometheusClient::new`]
+    pub async fn load_credentials() -> Result<Credentials> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        config
+            .credentials_provider()
+            .ok_or_else(|| AwsError::AuthenticationFailed("no credentials provider".to_string()))?
+            .provide_credentials()
+            .await
+            .map_err(|e| AwsError::AuthenticationFailed(e.to_string()).into())
+    }
+
+    /// Send requests through `client` instead of the shared one
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Full key of the artifact `name` of this run
+    pub fn key(&self, name: &str) -> String {
+        format!("{}{}", self.run_prefix, name)
+    }
+
+    /// Write `body` to the run's artifact `name`, replacing any existing object
+    pub async fn upload(&self, name: &str, body: Vec<u8>, content_type: &str) -> Result<()> {
+        let key = self.key(name);
+        let request = self.sign(&key, body, content_type)?;
+        let response = self
+            .client
+            .execute(request)
+            .await
+            .map_err(|e| AwsError::ServiceError(format!("PutObject {}: {}", key, e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = format!(
+                "PutObject s3://{}/{} returned HTTP {}: {}",
+                self.location.bucket,
+                key,
+                status.as_u16(),
+                body
+            );
+            return Err(if status.as_u16() == 403 {
+                AwsError::PermissionDenied(message)
+            } else {
+                AwsError::ServiceError(message)
+            }
+            .into());
+        }
+
+        info!("Uploaded s3://{}/{}", self.location.bucket, key);
+        Ok(())
+    }
+
+    /// Virtual-hosted URL of `key`, each path segment percent-encoded
+    fn object_url(&self, key: &str) -> Result<Url> {
+        let domain = if self.region.as_str().starts_with("cn-") {
+            "amazonaws.com.cn"
+        } else {
+            "amazonaws.com"
+        };
+        let mut url = Url::parse(&format!(
+            "https://{}.s3.{}.{}/",
+            self.location.bucket,
+            self.region.as_str(),
+            domain
+        ))
+        .map_err(|e| AwsError::ServiceError(format!("Invalid bucket URL: {}", e)))?;
+        url.path_segments_mut()
+            .map_err(|_| AwsError::ServiceError("Invalid bucket URL".to_string()))?
+            .clear()
+            .extend(key.split('/'));
+        Ok(url)
+    }
+
+    /// Build a `PutObject` request signed with AWS SigV4
+    fn sign(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<Request> {
+        let url = self.object_url(key)?;
+
+        let signable_request = SignableRequest::new(
+            Method::PUT.as_str(),
+            url.as_str(),
+            std::iter::empty(),
+            SignableBody::Bytes(&body),
+        )
+        .map_err(|e| AwsError::ServiceError(e.to_string()))?;
+
+        // S3 signs the payload hash and expects the path encoded only once
+        let mut settings = SigningSettings::default();
+        settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+        settings.percent_encoding_mode = PercentEncodingMode::Single;
+        settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
+
+        let identity: Identity = self.credentials.clone().into();
+        let signing_params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(self.region.as_str())
+            .name("s3")
+            .time(SystemTime::now())
+            .settings(settings)
+            .build()
+            .map_err(|e| AwsError::ServiceError(e.to_string()))?
+            .into();
+
+        let (signing_instructions, _) =
+            aws_sigv4::http_request::sign(signable_request, &signing_params)
+                .map_err(|e| AwsError::ServiceError(e.to_string()))?
+                .into_parts();
+
+        let mut request = Request::new(Method::PUT, url);
+        for (name, value) in signing_instructions.headers() {
+            let header_name: HeaderName = name.parse().unwrap();
+            let header_value: HeaderValue = value.parse().unwrap();
+            request.headers_mut().insert(header_name, header_value);
+        }
+        if let Ok(value) = HeaderValue::from_str(content_type) {
+            request.headers_mut().insert(CONTENT_TYPE, value);
+        }
+        *request.body_mut() = Some(body.into());
+
+        Ok(request)
+    }
+}
+
+/// `value` usable as a single key segment, anything unusual replaced by `_`
+fn key_segment(value: &str) -> String {
+    let segment: String = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if segment.is_empty() {
+        "default".to_string()
+    } else {
+        segment
+    }
+}
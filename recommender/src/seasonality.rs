@@ -0,0 +1,168 @@
+//! Daily and weekly usage patterns, for sizing to the busy hours
+//!
+//! Samples are bucketed by hour of day and by hour of week in the report's
+//! timezone. The share of the usage variance the bucket means explain tells how
+//! strongly periodic a container is. The weekly pattern is only used when every
+//! hour of the week was observed and it explains clearly more than the daily one.
+//! Peak hours are the buckets whose mean usage is above the overall mean.
+
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Timelike};
+
+use crate::clock::Timezone;
+
+const HOURS_PER_WEEK: usize = 7 * 24;
+
+/// How much more variance the weekly pattern has to explain to be preferred
+const WEEKLY_GAIN: f64 = 1.25;
+
+/// Fewer samples can't tell a pattern from noise
+const MIN_SAMPLES: usize = 48;
+
+/// Days of the week as listed in reasons, Monday first like [`Datelike::weekday`]
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Peak windows listed in the recommendation reason
+const MAX_LISTED_DAYS: usize = 3;
+
+/// Length of the cycle a usage pattern repeats over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeasonalPeriod {
+    Daily,
+    Weekly,
+}
+
+impl fmt::Display for SeasonalPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SeasonalPeriod::Daily => "daily",
+            SeasonalPeriod::Weekly => "weekly",
+        })
+    }
+}
+
+/// Usage pattern of one query and its peak hours
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seasonality {
+    pub period: SeasonalPeriod,
+    /// Share of the usage variance explained by the hour of the period, 0 to 1
+    pub strength: f64,
+    /// Whether each hour of the period is a peak hour, 24 or 168 entries,
+    /// Monday 00:00 first for weekly patterns
+    peak_hours: Vec<bool>,
+    timezone: Timezone,
+}
+
+impl Seasonality {
+    /// Pattern of `samples`, `(Unix timestamp, value)` pairs, None when there are
+    /// too few or they don't vary
+    pub fn detect(samples: &[(f64, f64)], timezone: Timezone) -> Option<Self> {
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        let hours: Vec<(usize, f64)> = samples
+            .iter()
+            .filter_map(|&(timestamp, value)| Some((hour_of_week(timestamp, timezone)?, value)))
+            .collect();
+        let mean = hours.iter().map(|(_, v)| v).sum::<f64>() / hours.len() as f64;
+        let variance: f64 = hours.iter().map(|(_, v)| (v - mean).powi(2)).sum();
+        if variance <= 0.0 {
+            return None;
+        }
+
+        let daily = bucket_means(hours.iter().map(|&(h, v)| (h % 24, v)), 24);
+        let daily_strength = explained(&daily, mean, variance);
+        let weekly = bucket_means(hours.iter().copied(), HOURS_PER_WEEK);
+        let weekly_strength = explained(&weekly, mean, variance);
+
+        let all_observed = weekly.iter().all(|(count, _)| *count > 0);
+        let (period, strength, buckets) =
+            if all_observed && weekly_strength > daily_strength * WEEKLY_GAIN {
+                (SeasonalPeriod::Weekly, weekly_strength, weekly)
+            } else {
+                (SeasonalPeriod::Daily, daily_strength, daily)
+            };
+        Some(Self {
+            period,
+            strength,
+            peak_hours: buckets
+                .iter()
+                .map(|&(count, bucket_mean)| count > 0 && bucket_mean > mean)
+                .collect(),
+            timezone,
+        })
+    }
+
+    /// Whether the sample at Unix `timestamp` falls in a peak hour
+    pub fn is_peak(&self, timestamp: f64) -> bool {
+        hour_of_week(timestamp, self.timezone)
+            .is_some_and(|hour| self.peak_hours[hour % self.peak_hours.len()])
+    }
+
+    /// Peak hours as ranges, e.g. `09:00-17:00` or `Mon 09:00-17:00, Tue ...`
+    pub fn describe_peaks(&self) -> String {
+        match self.period {
+            SeasonalPeriod::Daily => hour_ranges(&self.peak_hours),
+            SeasonalPeriod::Weekly => {
+                let mut days: Vec<String> = self
+                    .peak_hours
+                    .chunks(24)
+                    .zip(DAY_NAMES)
+                    .filter(|(hours, _)| hours.iter().any(|peak| *peak))
+                    .map(|(hours, day)| format!("{} {}", day, hour_ranges(hours)))
+                    .collect();
+                if days.len() > MAX_LISTED_DAYS {
+                    let more = days.len() - MAX_LISTED_DAYS;
+                    days.truncate(MAX_LISTED_DAYS);
+                    days.push(format!("and {} more days", more));
+                }
+                days.join(", ")
+            }
+        }
+    }
+}
+
+/// Hour of the week of Unix `timestamp` in `timezone`, 0 for Monday 00:00
+fn hour_of_week(timestamp: f64, timezone: Timezone) -> Option<usize> {
+    let time = timezone.convert(DateTime::from_timestamp(timestamp.floor() as i64, 0)?);
+    Some(time.weekday().num_days_from_monday() as usize * 24 + time.hour() as usize)
+}
+
+/// Sample count and mean of each of `buckets` buckets
+fn bucket_means(values: impl Iterator<Item = (usize, f64)>, buckets: usize) -> Vec<(usize, f64)> {
+    let mut sums = vec![(0usize, 0.0); buckets];
+    for (bucket, value) in values {
+        sums[bucket].0 += 1;
+        sums[bucket].1 += value;
+    }
+    sums.into_iter()
+        .map(|(count, sum)| (count, if count > 0 { sum / count as f64 } else { 0.0 }))
+        .collect()
+}
+
+/// Share of `variance`, the total squared deviation from `mean`, explained by the bucket means
+fn explained(buckets: &[(usize, f64)], mean: f64, variance: f64) -> f64 {
+    let between: f64 = buckets
+        .iter()
+        .map(|&(count, bucket_mean)| count as f64 * (bucket_mean - mean).powi(2))
+        .sum();
+    (between / variance).clamp(0.0, 1.0)
+}
+
+/// Runs of peak hours in a day as `HH:00-HH:00`, comma-separated
+fn hour_ranges(hours: &[bool]) -> String {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (hour, peak) in hours.iter().chain([&false]).enumerate() {
+        match (start, peak) {
+            (None, true) => start = Some(hour),
+            (Some(first), false) => {
+                ranges.push(format!("{:02}:00-{:02}:00", first, hour));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    ranges.join(", ")
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::to_unix_seconds;
+use crate::{
+    DeploymentResources, MetricsProvider, RecommenderError, Result, Series, WorkloadSource,
+};
+
+/// Workloads and every series queried during a run, saved for offline replay
+///
+/// Replaying a snapshot recomputes recommendations with different percentiles
+/// or margins without querying AMP again.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    /// Unix timestamp in seconds at which the queries were run
+    pub captured_at: f64,
+    /// Lookback window the series were queried for
+    pub lookback_hours: f64,
+    pub workloads: Vec<DeploymentResources>,
+    /// Series returned per PromQL query
+    pub queries: BTreeMap<String, Vec<Series>>,
+}
+
+impl MetricsSnapshot {
+    /// Read a snapshot written by [`MetricsSnapshot::save`]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            RecommenderError::InvalidInput(format!(
+                "Failed to read metrics snapshot {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        serde_json::from_str(&content).map_err(|e| {
+            RecommenderError::Parse(format!(
+                "Invalid metrics snapshot {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the snapshot as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(|e| {
+            RecommenderError::Other(format!("Failed to serialize metrics snapshot: {}", e))
+        })?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MetricsProvider for MetricsSnapshot {
+    /// Answer from the recorded series
+    ///
+    /// Windows ending after `captured_at`, e.g. at the current time, are anchored
+    /// at `captured_at` instead, so a shorter lookback replays the most recent part
+    /// of the snapshot. Earlier windows are answered as requested, e.g. to backtest.
+    async fn query_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        _step: Duration,
+    ) -> Result<Vec<Series>> {
+        let (start, end) = if to_unix_seconds(end) > self.captured_at {
+            let window = end.duration_since(start).unwrap_or_default().as_secs_f64();
+            (self.captured_at - window, self.captured_at)
+        } else {
+            (to_unix_seconds(start), to_unix_seconds(end))
+        };
+
+        Ok(self
+            .queries
+            .get(query)
+            .map(|series| {
+                series
+                    .iter()
+                    .map(|s| Series {
+                        labels: s.labels.clone(),
+                        samples: s
+                            .samples
+                            .iter()
+                            .filter(|sample| sample.timestamp >= start && sample.timestamp <= end)
+                            .copied()
+                            .collect(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl WorkloadSource for MetricsSnapshot {
+    async fn list_workloads(&self) -> Result<Vec<DeploymentResources>> {
+        Ok(self.workloads.clone())
+    }
+}
+
+/// Metrics backend wrapper remembering the series of every query it forwards
+///
+/// Instant queries aren't forwarded as such: the default [`MetricsProvider::query`]
+/// answers them through `query_range`, so they are recorded like any other window
+/// and a replayed [`MetricsSnapshot`] returns the same latest sample.
+pub struct RecordingMetricsProvider {
+    inner: Arc<dyn MetricsProvider>,
+    recorded: Mutex<Recorded>,
+}
+
+#[derive(Default)]
+struct Recorded {
+    /// Latest end of any queried window
+    captured_at: f64,
+    queries: BTreeMap<String, Vec<Series>>,
+}
+
+impl RecordingMetricsProvider {
+    pub fn new(inner: Arc<dyn MetricsProvider>) -> Self {
+        Self {
+            inner,
+            recorded: Mutex::default(),
+        }
+    }
+
+    /// Bundle the recorded queries with the analyzed workloads
+    pub fn snapshot(
+        &self,
+        workloads: Vec<DeploymentResources>,
+        lookback_hours: f64,
+    ) -> MetricsSnapshot {
+        let recorded = self.recorded.lock().unwrap_or_else(|e| e.into_inner());
+        MetricsSnapshot {
+            captured_at: recorded.captured_at,
+            lookback_hours,
+            workloads,
+            queries: recorded.queries.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl MetricsProvider for RecordingMetricsProvider {
+    async fn query_range(
+        &self,
+        query: &str,
+        start: SystemTime,
+        end: SystemTime,
+        step: Duration,
+    ) -> Result<Vec<Series>> {
+        let series = self.inner.query_range(query, start, end, step).await?;
+
+        let mut recorded = self.recorded.lock().unwrap_or_else(|e| e.into_inner());
+        recorded.captured_at = recorded.captured_at.max(to_unix_seconds(end));
+        recorded.queries.insert(query.to_string(), series.clone());
+        Ok(series)
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::debug;
+use serde_yaml::Value;
+
+use crate::error::{RecommenderError, Result};
+
+/// Default `sops` executable, looked up in `PATH`
+const DEFAULT_BINARY: &str = "sops";
+
+/// Environment variable sops reads age identities from
+const AGE_KEY_FILE_ENV: &str = "SOPS_AGE_KEY_FILE";
+
+/// How to decrypt and re-encrypt SOPS-encrypted manifests
+///
+/// Files are decrypted with `sops --decrypt` and edited values are written back
+/// with `sops set`, which re-encrypts them with the file's own keys, so neither
+/// plaintext nor a `.sops.yaml` is needed on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SopsConfig {
+    pub binary: PathBuf,
+    /// age identities passed to sops as `SOPS_AGE_KEY_FILE`
+    pub age_key_file: Option<PathBuf>,
+}
+
+impl Default for SopsConfig {
+    fn default() -> Self {
+        Self {
+            binary: PathBuf::from(DEFAULT_BINARY),
+            age_key_file: None,
+        }
+    }
+}
+
+impl SopsConfig {
+    /// Run `binary` instead of the `sops` found in `PATH`
+    pub fn with_binary(mut self, binary: impl Into<PathBuf>) -> Self {
+        self.binary = binary.into();
+        self
+    }
+
+    /// Decrypt age recipients with the identities in `path`
+    pub fn with_age_key_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.age_key_file = Some(path.into());
+        self
+    }
+
+    /// Plaintext YAML of an encrypted file
+    pub fn decrypt(&self, path: &Path) -> Result<String> {
+        let mut command = self.command();
+        command
+            .args(["--decrypt", "--input-type", "yaml", "--output-type", "yaml"])
+            .arg(path);
+        self.run(command, "decrypt", path)
+    }
+
+    /// Encrypt `value` into the file at `key`, a path like `["spec"]["replicas"]`
+    pub fn set(&self, path: &Path, key: &str, value: &serde_json::Value) -> Result<()> {
+        debug!("sops set {} {} {}", path.display(), key, value);
+        let mut command = self.command();
+        command.arg("set").arg(path).arg(key).arg(value.to_string());
+        self.run(command, "update", path).map(|_| ())
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.binary);
+        if let Some(key_file) = &self.age_key_file {
+            command.env(AGE_KEY_FILE_ENV, key_file);
+        }
+        command
+    }
+
+    fn run(&self, mut command: Command, action: &str, path: &Path) -> Result<String> {
+        let output = command.output().map_err(|e| {
+            RecommenderError::ApplyError(format!("Failed to run {}: {}", self.binary.display(), e))
+        })?;
+        if !output.status.success() {
+            return Err(RecommenderError::ApplyError(format!(
+                "sops failed to {} {}: {}",
+                action,
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        String::from_utf8(output.stdout).map_err(|e| {
+            RecommenderError::Parse(format!("sops output of {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// Whether a YAML document carries SOPS metadata
+pub fn is_encrypted(doc: &Value) -> bool {
+    doc.get("sops")
+        .is_some_and(|sops| sops.get("mac").is_some())
+}
+
+/// `sops set` keys and values of everything `modified` changes or adds
+///
+/// Removed entries are not reported, manifest edits never remove anything.
+pub fn changed_values(original: &Value, modified: &Value) -> Vec<(String, serde_json::Value)> {
+    let mut changes = Vec::new();
+    collect_changes(original, modified, &mut String::new(), &mut changes);
+    changes
+}
+
+fn collect_changes(
+    original: &Value,
+    modified: &Value,
+    key: &mut String,
+    changes: &mut Vec<(String, serde_json::Value)>,
+) {
+    match (original, modified) {
+        (Value::Mapping(before), Value::Mapping(after)) => {
+            for (name, value) in after {
+                let Some(name) = name.as_str() else {
+                    continue;
+                };
+                let len = key.len();
+                key.push_str(&format!("[{}]", serde_json::Value::from(name)));
+                match before.get(name) {
+                    Some(previous) => collect_changes(previous, value, key, changes),
+                    None => changes.push((key.clone(), to_json(value))),
+                }
+                key.truncate(len);
+            }
+        }
+        (Value::Sequence(before), Value::Sequence(after)) if before.len() == after.len() => {
+            for (index, (previous, value)) in before.iter().zip(after).enumerate() {
+                let len = key.len();
+                key.push_str(&format!("[{}]", index));
+                collect_changes(previous, value, key, changes);
+                key.truncate(len);
+            }
+        }
+        _ if original != modified => changes.push((key.clone(), to_json(modified))),
+        _ => {}
+    }
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or(serde_json::Value::Null)
+}
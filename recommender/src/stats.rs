@@ -0,0 +1,434 @@
+//! Summary statistics and quantiles over usage samples
+//!
+//! These are the numbers the Recommender bases its recommendations on, exposed so
+//! callers can compute them over their own sample sets.
+
+use serde::{Deserialize, Serialize};
+
+/// Summary of a set of usage samples
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageStats {
+    #[serde(default)]
+    pub samples: usize,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+impl UsageStats {
+    /// Summarize unsorted values, all fields are zero when `values` is empty
+    pub fn from_values(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        Self {
+            samples: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            avg: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(&sorted, 50.0, Interpolation::Higher),
+            p95: percentile(&sorted, 95.0, Interpolation::Higher),
+            p99: percentile(&sorted, 99.0, Interpolation::Higher),
+        }
+    }
+}
+
+/// Single-pass accumulator for [`UsageStats`]
+///
+/// Mean, variance and extremes are tracked exactly (Welford's algorithm), while
+/// quantiles come from a logarithmic bucket sketch with a bounded relative error,
+/// so memory only depends on the value range rather than the number of samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamingStats {
+    count: usize,
+    mean: f64,
+    /// Sum of squared differences from the mean
+    m2: f64,
+    min: f64,
+    max: f64,
+    sketch: QuantileSketch,
+}
+
+impl Default for StreamingStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamingStats {
+    /// Quantiles within 1% of the exact value
+    pub fn new() -> Self {
+        Self::with_relative_accuracy(0.01)
+    }
+
+    /// Quantiles within `accuracy` (e.g. 0.01 = 1%) of the exact value
+    pub fn with_relative_accuracy(accuracy: f64) -> Self {
+        Self {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sketch: QuantileSketch::new(accuracy),
+        }
+    }
+
+    /// Add one sample; negative and non-finite values are ignored
+    pub fn push(&mut self, value: f64) {
+        if !value.is_finite() || value < 0.0 {
+            return;
+        }
+
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sketch.insert(value);
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Population variance of the samples, 0.0 when there were none
+    pub fn variance(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.m2 / self.count as f64
+    }
+
+    /// Approximate quantile (0.0-1.0), never below the true one by more than the accuracy
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        // Same rank as `Interpolation::Higher`, clamped to the exact extremes
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).ceil() as u64;
+        self.sketch.value_at_rank(rank).clamp(self.min, self.max)
+    }
+
+    /// Share of the samples above `value` (0.0-1.0), within the quantile accuracy
+    pub fn fraction_above(&self, value: f64) -> f64 {
+        if self.count == 0 || value >= self.max {
+            return 0.0;
+        }
+        if value < self.min {
+            return 1.0;
+        }
+        self.sketch.count_above(value) as f64 / self.count as f64
+    }
+
+    /// Summarize the samples seen so far, all fields are zero when there were none
+    pub fn finish(&self) -> UsageStats {
+        if self.count == 0 {
+            return UsageStats::default();
+        }
+
+        UsageStats {
+            samples: self.count,
+            min: self.min,
+            max: self.max,
+            avg: self.mean,
+            p50: self.quantile(0.50),
+            p95: self.quantile(0.95),
+            p99: self.quantile(0.99),
+        }
+    }
+}
+
+impl Extend<f64> for StreamingStats {
+    fn extend<I: IntoIterator<Item = f64>>(&mut self, values: I) {
+        for value in values {
+            self.push(value);
+        }
+    }
+}
+
+/// Histogram over logarithmically sized buckets (DDSketch)
+///
+/// Bucket `i` covers `(gamma^(i-1), gamma^i]`; values too small for any bucket are
+/// counted separately as zero.
+#[derive(Debug, Clone, PartialEq)]
+struct QuantileSketch {
+    gamma_ln: f64,
+    zero_count: u64,
+    buckets: std::collections::BTreeMap<i32, u64>,
+}
+
+impl QuantileSketch {
+    /// Below this a sample counts as zero usage
+    const MIN_VALUE: f64 = 1e-9;
+
+    fn new(accuracy: f64) -> Self {
+        let accuracy = accuracy.clamp(1e-6, 0.5);
+        let gamma = (1.0 + accuracy) / (1.0 - accuracy);
+        Self {
+            gamma_ln: gamma.ln(),
+            zero_count: 0,
+            buckets: Default::default(),
+        }
+    }
+
+    fn insert(&mut self, value: f64) {
+        if value < Self::MIN_VALUE {
+            self.zero_count += 1;
+            return;
+        }
+        let index = (value.ln() / self.gamma_ln).ceil() as i32;
+        *self.buckets.entry(index).or_insert(0) += 1;
+    }
+
+    /// Representative value of the sample with 0-based `rank`
+    fn value_at_rank(&self, rank: u64) -> f64 {
+        if rank < self.zero_count {
+            return 0.0;
+        }
+
+        let mut seen = self.zero_count;
+        for (&index, &count) in &self.buckets {
+            seen += count;
+            if seen > rank {
+                // Midpoint of the bucket in relative terms, 2 * gamma^i / (gamma + 1)
+                let upper = (f64::from(index) * self.gamma_ln).exp();
+                let gamma = self.gamma_ln.exp();
+                return 2.0 * upper / (gamma + 1.0);
+            }
+        }
+        self.buckets
+            .last_key_value()
+            .map_or(0.0, |(&index, _)| (f64::from(index) * self.gamma_ln).exp())
+    }
+
+    /// Number of samples whose representative value is above `value`
+    fn count_above(&self, value: f64) -> u64 {
+        let gamma = self.gamma_ln.exp();
+        self.buckets
+            .iter()
+            .filter(|&(&index, _)| {
+                2.0 * (f64::from(index) * self.gamma_ln).exp() / (gamma + 1.0) > value
+            })
+            .map(|(_, &count)| count)
+            .sum()
+    }
+}
+
+/// How to pick a value when a percentile falls between two samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// The sample above, never underestimates (used for recommendations)
+    #[default]
+    Higher,
+    /// The sample below
+    Lower,
+    /// Whichever sample is closer
+    Nearest,
+    /// Linear interpolation between both samples
+    Linear,
+}
+
+/// Percentile (0-100) of values sorted in ascending order
+///
+/// Returns 0.0 for an empty slice.
+pub fn percentile(sorted: &[f64], percentile: f64, interpolation: Interpolation) -> f64 {
+    quantile(sorted, percentile / 100.0, interpolation)
+}
+
+/// Quantile (0.0-1.0) of values sorted in ascending order
+///
+/// Returns 0.0 for an empty slice.
+pub fn quantile(sorted: &[f64], q: f64, interpolation: Interpolation) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let last = sorted.len() - 1;
+    let rank = q.clamp(0.0, 1.0) * last as f64;
+    let lower = rank.floor() as usize;
+    let upper = (rank.ceil() as usize).min(last);
+
+    match interpolation {
+        Interpolation::Higher => sorted[upper],
+        Interpolation::Lower => sorted[lower],
+        Interpolation::Nearest => sorted[(rank.round() as usize).min(last)],
+        Interpolation::Linear => {
+            let fraction = rank - lower as f64;
+            sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+        }
+    }
+}
+
+/// Percentile (0-100) of `(value, weight)` pairs in any order
+///
+/// Returns the smallest value whose cumulative weight reaches the requested share
+/// of the total, e.g. to weight samples by the number of replicas reporting them.
+/// Pairs with non-positive weights are ignored; returns 0.0 when none remain.
+pub fn weighted_percentile(samples: &[(f64, f64)], percentile: f64) -> f64 {
+    let mut weighted: Vec<(f64, f64)> = samples
+        .iter()
+        .copied()
+        .filter(|(_, weight)| *weight > 0.0)
+        .collect();
+    if weighted.is_empty() {
+        return 0.0;
+    }
+    weighted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let total: f64 = weighted.iter().map(|(_, weight)| weight).sum();
+    let target = total * (percentile / 100.0).clamp(0.0, 1.0);
+
+    let mut cumulative = 0.0;
+    for (value, weight) in &weighted {
+        cumulative += weight;
+        if cumulative >= target {
+            return *value;
+        }
+    }
+    weighted[weighted.len() - 1].0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODES: [Interpolation; 4] = [
+        Interpolation::Higher,
+        Interpolation::Lower,
+        Interpolation::Nearest,
+        Interpolation::Linear,
+    ];
+
+    #[test]
+    fn percentile_of_empty_and_single_values() {
+        for mode in MODES {
+            assert_eq!(percentile(&[], 95.0, mode), 0.0);
+            for p in [0.0, 50.0, 99.0, 100.0] {
+                assert_eq!(percentile(&[5.0], p, mode), 5.0);
+            }
+        }
+    }
+
+    #[test]
+    fn percentile_extremes_are_min_and_max() {
+        let sorted = [1.0, 2.0, 4.0, 8.0];
+        for mode in MODES {
+            assert_eq!(percentile(&sorted, 0.0, mode), 1.0);
+            assert_eq!(percentile(&sorted, 100.0, mode), 8.0);
+            // Out of range percentiles are clamped
+            assert_eq!(percentile(&sorted, -5.0, mode), 1.0);
+            assert_eq!(percentile(&sorted, 150.0, mode), 8.0);
+        }
+    }
+
+    #[test]
+    fn percentile_at_exact_rank_ignores_interpolation() {
+        let sorted = [10.0, 20.0, 30.0, 40.0, 50.0];
+        for mode in MODES {
+            assert_eq!(percentile(&sorted, 25.0, mode), 20.0);
+            assert_eq!(percentile(&sorted, 75.0, mode), 40.0);
+        }
+    }
+
+    #[test]
+    fn percentile_between_ranks_follows_interpolation() {
+        // p30 of 5 values is rank 1.2, between 20 and 30
+        let sorted = [10.0, 20.0, 30.0, 40.0, 50.0];
+        assert_eq!(percentile(&sorted, 30.0, Interpolation::Higher), 30.0);
+        assert_eq!(percentile(&sorted, 30.0, Interpolation::Lower), 20.0);
+        assert_eq!(percentile(&sorted, 30.0, Interpolation::Nearest), 20.0);
+        assert!((percentile(&sorted, 30.0, Interpolation::Linear) - 22.0).abs() < 1e-9);
+        // Rank 1.6 is nearer to 30
+        assert_eq!(percentile(&sorted, 40.0, Interpolation::Nearest), 30.0);
+    }
+
+    #[test]
+    fn weighted_percentile_ignores_non_positive_weights() {
+        assert_eq!(weighted_percentile(&[], 50.0), 0.0);
+        assert_eq!(weighted_percentile(&[(3.0, 0.0), (4.0, -1.0)], 50.0), 0.0);
+        let samples = [(100.0, 0.0), (1.0, 1.0), (2.0, 1.0), (50.0, -2.0)];
+        assert_eq!(weighted_percentile(&samples, 100.0), 2.0);
+    }
+
+    #[test]
+    fn weighted_percentile_with_unequal_weights() {
+        // Cumulative weights 1, 2 and 10 out of 10
+        let samples = [(3.0, 8.0), (1.0, 1.0), (2.0, 1.0)];
+        assert_eq!(weighted_percentile(&samples, 0.0), 1.0);
+        assert_eq!(weighted_percentile(&samples, 10.0), 1.0);
+        assert_eq!(weighted_percentile(&samples, 20.0), 2.0);
+        assert_eq!(weighted_percentile(&samples, 21.0), 3.0);
+        assert_eq!(weighted_percentile(&samples, 50.0), 3.0);
+        assert_eq!(weighted_percentile(&samples, 100.0), 3.0);
+    }
+
+    /// Values spread over several orders of magnitude, in no particular order
+    fn spread_values() -> Vec<f64> {
+        (1..=5_000)
+            .map(|i| ((i * 7_919) % 5_000 + 1) as f64)
+            .map(|v| v * v / 1_000.0)
+            .collect()
+    }
+
+    #[test]
+    fn streaming_mean_and_variance_match_exact_values() {
+        let values = spread_values();
+        let mut stats = StreamingStats::new();
+        stats.extend(values.iter().copied());
+
+        let exact = UsageStats::from_values(&values);
+        let streamed = stats.finish();
+        assert_eq!(streamed.samples, exact.samples);
+        assert_eq!(streamed.min, exact.min);
+        assert_eq!(streamed.max, exact.max);
+        assert!((streamed.avg - exact.avg).abs() <= exact.avg * 1e-12);
+
+        let variance =
+            values.iter().map(|v| (v - exact.avg).powi(2)).sum::<f64>() / values.len() as f64;
+        assert!((stats.variance() - variance).abs() <= variance * 1e-9);
+    }
+
+    #[test]
+    fn streaming_quantiles_are_within_sketch_accuracy() {
+        let mut values = spread_values();
+        values.extend([0.0; 10]);
+        for accuracy in [0.01, 0.05] {
+            let mut stats = StreamingStats::with_relative_accuracy(accuracy);
+            stats.extend(values.iter().copied());
+
+            let mut sorted = values.clone();
+            sorted.sort_by(f64::total_cmp);
+            for q in [0.0, 0.001, 0.25, 0.5, 0.9, 0.95, 0.99, 1.0] {
+                let exact = quantile(&sorted, q, Interpolation::Higher);
+                let approx = stats.quantile(q);
+                assert!(
+                    (approx - exact).abs() <= exact * accuracy + 1e-12,
+                    "q{} is {} for {} at {} accuracy",
+                    q,
+                    approx,
+                    exact,
+                    accuracy
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn streaming_stats_ignore_invalid_samples() {
+        let mut stats = StreamingStats::new();
+        stats.extend([f64::NAN, -1.0, f64::INFINITY, 2.0]);
+        assert_eq!(stats.count(), 1);
+        assert_eq!(stats.finish().p99, 2.0);
+        assert_eq!(StreamingStats::new().finish(), UsageStats::default());
+        assert_eq!(StreamingStats::new().variance(), 0.0);
+    }
+}
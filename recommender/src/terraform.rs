@@ -0,0 +1,1111 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{debug, info, warn};
+
+use crate::error::Result;
+use crate::manifest::ManifestWorkload;
+use crate::recommender::ResourceRecommendation;
+
+/// Resource types declaring a Deployment with nested blocks
+const DEPLOYMENT_RESOURCES: [&str; 2] = ["kubernetes_deployment", "kubernetes_deployment_v1"];
+
+/// Resource type declaring any object through a `manifest` attribute
+const MANIFEST_RESOURCE: &str = "kubernetes_manifest";
+
+/// Namespace the provider uses when `kubernetes_deployment` leaves it out
+const DEFAULT_NAMESPACE: &str = "default";
+
+/// Edits Deployments declared in Terraform files under a directory tree
+///
+/// Handles `kubernetes_deployment`, `kubernetes_deployment_v1` and `kubernetes_manifest`
+/// resources whose name and namespace are literals. Only the `cpu` and `memory` values are
+/// rewritten in place, so comments and formatting survive; values set from variables or
+/// functions are left alone with a warning.
+pub struct TerraformEditor {
+    root: PathBuf,
+}
+
+impl TerraformEditor {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Find all `.tf` files below the root
+    pub fn find_terraform_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        find_tf_files_recursive(&self.root, &mut files)?;
+
+        debug!("Found {} Terraform files to scan", files.len());
+        Ok(files)
+    }
+
+    /// List the Deployments declared below the root
+    pub fn list_deployments(&self) -> Result<Vec<ManifestWorkload>> {
+        let mut workloads = Vec::new();
+        for path in self.find_terraform_files()? {
+            let Some(file) = TerraformFile::parse(path)? else {
+                continue;
+            };
+            workloads.extend(file.deployments().into_iter().map(|d| d.workload));
+        }
+        Ok(workloads)
+    }
+
+    /// Apply all recommendations
+    ///
+    /// Returns the number of updated containers keyed by `namespace/deployment`.
+    pub fn apply_recommendations(
+        &self,
+        recommendations: &[ResourceRecommendation],
+    ) -> Result<HashMap<String, usize>> {
+        let mut updates = HashMap::new();
+
+        for path in self.find_terraform_files()? {
+            let Some(file) = TerraformFile::parse(path)? else {
+                continue;
+            };
+            let deployments = file.deployments();
+            if deployments.is_empty() {
+                continue;
+            }
+
+            let mut edits = Vec::new();
+            for recommendation in recommendations {
+                // Never write a limit below its request, whatever the caller passed in
+                let mut recommendation = recommendation.clone();
+                recommendation.enforce_limits_above_requests();
+
+                for deployment in deployments.iter().filter(|d| {
+                    d.workload
+                        .matches(&recommendation.namespace, &recommendation.deployment)
+                }) {
+                    let Some(container) = deployment
+                        .containers
+                        .iter()
+                        .find(|(name, _)| *name == recommendation.container)
+                    else {
+                        continue;
+                    };
+
+                    debug!("Found matching deployment in: {}", file.path.display());
+                    if update_container(
+                        &file.source,
+                        container.1,
+                        deployment.style,
+                        &recommendation,
+                        &mut edits,
+                    ) {
+                        let key =
+                            format!("{}/{}", recommendation.namespace, recommendation.deployment);
+                        *updates.entry(key).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            if !edits.is_empty() {
+                fs::write(&file.path, apply_edits(&file.source, edits))?;
+                info!("Updated file: {}", file.path.display());
+            }
+        }
+
+        Ok(updates)
+    }
+}
+
+/// Source and syntax tree of one `.tf` file
+struct TerraformFile {
+    path: PathBuf,
+    source: String,
+    body: Body,
+}
+
+impl TerraformFile {
+    /// Parse `path`, None when it isn't HCL this parser understands
+    fn parse(path: PathBuf) -> Result<Option<Self>> {
+        let source = fs::read_to_string(&path)?;
+        match Parser::new(&source).parse_file() {
+            Ok(body) => Ok(Some(Self { path, source, body })),
+            Err(e) => {
+                warn!("Skipping {}: {}", path.display(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    fn deployments(&self) -> Vec<TerraformDeployment<'_>> {
+        self.body
+            .blocks("resource")
+            .filter_map(|block| {
+                let resource_type = block.labels.first()?;
+                let body = Node::Body(&block.body);
+                if DEPLOYMENT_RESOURCES.contains(&resource_type.as_str()) {
+                    deployment_resource(body)
+                } else if resource_type == MANIFEST_RESOURCE {
+                    manifest_resource(body)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// A Deployment resource and its containers by name
+struct TerraformDeployment<'a> {
+    workload: ManifestWorkload,
+    style: Style,
+    containers: Vec<(&'a str, Node<'a>)>,
+}
+
+/// How a resource nests its values, which decides how missing entries are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Style {
+    /// `kubernetes_deployment`: `resources { ... }` block holding `limits = { ... }` maps
+    Blocks,
+    /// `kubernetes_manifest`: object expressions all the way down
+    Objects,
+}
+
+fn deployment_resource(resource: Node<'_>) -> Option<TerraformDeployment<'_>> {
+    let metadata = resource.get("metadata")?;
+    let name = metadata.get("name")?.literal()?.to_string();
+    let namespace = match metadata.get("namespace") {
+        None => Some(DEFAULT_NAMESPACE.to_string()),
+        Some(namespace) => namespace.literal().map(str::to_string),
+    };
+
+    let pod_spec = resource.get("spec")?.get("template")?.get("spec")?;
+    let containers = match pod_spec {
+        Node::Body(body) => body
+            .blocks("container")
+            .filter_map(|c| {
+                let container = Node::Body(&c.body);
+                Some((container.get("name")?.literal()?, container))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Some(TerraformDeployment {
+        workload: ManifestWorkload { namespace, name },
+        style: Style::Blocks,
+        containers,
+    })
+}
+
+fn manifest_resource(resource: Node<'_>) -> Option<TerraformDeployment<'_>> {
+    let manifest = resource.get("manifest")?;
+    if manifest.get("kind")?.literal()? != "Deployment" {
+        return None;
+    }
+    let metadata = manifest.get("metadata")?;
+    let name = metadata.get("name")?.literal()?.to_string();
+    let namespace = metadata
+        .get("namespace")
+        .and_then(|n| n.literal())
+        .map(str::to_string);
+
+    let containers = match manifest
+        .get("spec")?
+        .get("template")?
+        .get("spec")?
+        .get("containers")?
+    {
+        Node::Value(Expr {
+            kind: ExprKind::Tuple(items),
+            ..
+        }) => items
+            .iter()
+            .filter_map(|item| {
+                let container = Node::from_expr(item);
+                Some((container.get("name")?.literal()?, container))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Some(TerraformDeployment {
+        workload: ManifestWorkload { namespace, name },
+        style: Style::Objects,
+        containers,
+    })
+}
+
+/// Values written under `resources`, in the order missing entries are added
+enum Tree {
+    Leaf(String),
+    Map(Vec<(&'static str, Tree)>),
+}
+
+/// Queue edits setting the requests and limits of `container`
+///
+/// Returns whether any value was written.
+fn update_container(
+    source: &str,
+    container: Node<'_>,
+    style: Style,
+    recommendation: &ResourceRecommendation,
+    edits: &mut Vec<Edit>,
+) -> bool {
+    let values = |cpu: String, memory: String| {
+        Tree::Map(vec![
+            ("cpu", Tree::Leaf(cpu)),
+            ("memory", Tree::Leaf(memory)),
+        ])
+    };
+    let resources = Tree::Map(vec![
+        (
+            "requests",
+            values(
+                recommendation.recommended_cpu_request.to_string(),
+                recommendation.recommended_memory_request.to_string(),
+            ),
+        ),
+        (
+            "limits",
+            values(
+                recommendation.recommended_cpu_limit.to_string(),
+                recommendation.recommended_memory_limit.to_string(),
+            ),
+        ),
+    ]);
+
+    let context = format!(
+        "{}/{} container {}",
+        recommendation.namespace, recommendation.deployment, recommendation.container
+    );
+    let block = style == Style::Blocks;
+    set_entry(
+        source,
+        container,
+        "resources",
+        &resources,
+        block,
+        &context,
+        edits,
+    )
+}
+
+/// Set `key` of `node` to `value`, descending into existing maps and adding missing entries
+fn set_entry(
+    source: &str,
+    node: Node<'_>,
+    key: &str,
+    value: &Tree,
+    block: bool,
+    context: &str,
+    edits: &mut Vec<Edit>,
+) -> bool {
+    match (node.get(key), value) {
+        (None, _) => {
+            let Some(close) = node.close() else {
+                return false;
+            };
+            edits.push(insert_entries(source, close, &[(key, value)], block));
+            true
+        }
+        (Some(Node::Value(expr)), Tree::Leaf(quantity)) if expr.is_literal() => {
+            edits.push(Edit {
+                start: expr.start,
+                end: expr.end,
+                text: format!("\"{}\"", quantity),
+            });
+            true
+        }
+        (Some(child @ (Node::Body(_) | Node::Object(..))), Tree::Map(entries)) => {
+            let mut updated = false;
+            let mut missing = Vec::new();
+            for (entry_key, entry_value) in entries {
+                if child.get(entry_key).is_none() {
+                    missing.push((*entry_key, entry_value));
+                } else {
+                    updated |=
+                        set_entry(source, child, entry_key, entry_value, false, context, edits);
+                }
+            }
+            if !missing.is_empty()
+                && let Some(close) = child.close()
+            {
+                edits.push(insert_entries(source, close, &missing, false));
+                updated = true;
+            }
+            updated
+        }
+        _ => {
+            warn!(
+                "Not updating {} of {}: its value isn't a literal",
+                key, context
+            );
+            false
+        }
+    }
+}
+
+/// Edit adding `entries` before the closing brace at `close`
+fn insert_entries(source: &str, close: usize, entries: &[(&str, &Tree)], block: bool) -> Edit {
+    let line_start = source[..close].rfind('\n').map_or(0, |i| i + 1);
+    let before = &source[line_start..close];
+    let indent: String = before
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect();
+
+    let mut lines = Vec::new();
+    for (key, value) in entries {
+        render_entry(key, value, block, &format!("{}  ", indent), &mut lines);
+    }
+    let rendered = lines.join("\n");
+
+    if before.trim().is_empty() {
+        // The brace is on its own line, add the entries above it
+        Edit {
+            start: line_start,
+            end: line_start,
+            text: format!("{}\n", rendered),
+        }
+    } else {
+        // Single-line map, break it open and put the brace on its own line
+        Edit {
+            start: line_start + before.trim_end().len(),
+            end: close,
+            text: format!("\n{}\n{}", rendered, indent),
+        }
+    }
+}
+
+fn render_entry(key: &str, value: &Tree, block: bool, indent: &str, lines: &mut Vec<String>) {
+    match value {
+        Tree::Leaf(quantity) => lines.push(format!("{}{} = \"{}\"", indent, key, quantity)),
+        Tree::Map(entries) => {
+            let assign = if block { "" } else { " =" };
+            lines.push(format!("{}{}{} {{", indent, key, assign));
+            for (child_key, child_value) in entries {
+                render_entry(
+                    child_key,
+                    child_value,
+                    false,
+                    &format!("{}  ", indent),
+                    lines,
+                );
+            }
+            lines.push(format!("{}}}", indent));
+        }
+    }
+}
+
+/// Replacement of `source[start..end]`
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+fn apply_edits(source: &str, mut edits: Vec<Edit>) -> String {
+    // Recommendations repeated for a container edit the same spans
+    edits.sort_by_key(|e| std::cmp::Reverse((e.start, e.end)));
+    edits.dedup_by_key(|e| (e.start, e.end));
+    let mut output = source.to_string();
+    for edit in edits {
+        output.replace_range(edit.start..edit.end, &edit.text);
+    }
+    output
+}
+
+/// Recursively find Terraform files, skipping provider caches
+fn find_tf_files_recursive(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if matches!(
+            path.file_name().and_then(|n| n.to_str()),
+            Some(".git" | ".terraform")
+        ) {
+            continue;
+        }
+
+        if path.is_dir() {
+            find_tf_files_recursive(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "tf") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Attributes and blocks of an HCL body, with offsets into the source
+#[derive(Debug)]
+struct Body {
+    items: Vec<Item>,
+    /// Offset of the closing brace, None for the top level of a file
+    close: Option<usize>,
+}
+
+impl Body {
+    fn blocks<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Block> {
+        self.items.iter().filter_map(move |item| match item {
+            Item::Block(block) if block.kind == kind => Some(block),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Item {
+    Attribute { name: String, value: Expr },
+    Block(Block),
+}
+
+#[derive(Debug)]
+struct Block {
+    kind: String,
+    labels: Vec<String>,
+    body: Body,
+}
+
+/// An expression spanning `source[start..end]`
+#[derive(Debug)]
+struct Expr {
+    start: usize,
+    end: usize,
+    kind: ExprKind,
+}
+
+impl Expr {
+    fn is_literal(&self) -> bool {
+        matches!(self.kind, ExprKind::Literal(_))
+    }
+}
+
+#[derive(Debug)]
+enum ExprKind {
+    /// String without interpolation, or a number
+    Literal(String),
+    Object {
+        entries: Vec<(String, Expr)>,
+        close: usize,
+    },
+    Tuple(Vec<Expr>),
+    /// References, function calls, templates and everything else
+    Other,
+}
+
+/// A block body or object expression, the two ways HCL nests keys, or a plain value
+#[derive(Debug, Clone, Copy)]
+enum Node<'a> {
+    Body(&'a Body),
+    Object(&'a [(String, Expr)], usize),
+    Value(&'a Expr),
+}
+
+impl<'a> Node<'a> {
+    fn from_expr(expr: &'a Expr) -> Self {
+        match &expr.kind {
+            ExprKind::Object { entries, close } => Node::Object(entries, *close),
+            _ => Node::Value(expr),
+        }
+    }
+
+    /// Attribute, nested block or object entry named `key`
+    fn get(self, key: &str) -> Option<Node<'a>> {
+        match self {
+            Node::Body(body) => body.items.iter().find_map(|item| match item {
+                Item::Attribute { name, value } if name == key => Some(Node::from_expr(value)),
+                Item::Block(block) if block.kind == key && block.labels.is_empty() => {
+                    Some(Node::Body(&block.body))
+                }
+                _ => None,
+            }),
+            Node::Object(entries, _) => entries
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| Node::from_expr(value)),
+            Node::Value(_) => None,
+        }
+    }
+
+    fn literal(self) -> Option<&'a str> {
+        match self {
+            Node::Value(Expr {
+                kind: ExprKind::Literal(value),
+                ..
+            }) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn close(self) -> Option<usize> {
+        match self {
+            Node::Body(body) => body.close,
+            Node::Object(_, close) => Some(close),
+            Node::Value(_) => None,
+        }
+    }
+}
+
+/// Just enough of an HCL parser to find resources and the offsets of their values
+///
+/// Expressions other than literals, objects and tuples are skipped by balancing
+/// brackets, strings, heredocs and comments.
+struct Parser<'a> {
+    source: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            bytes: source.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn parse_file(&mut self) -> std::result::Result<Body, String> {
+        self.parse_body(false)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = self.source[..self.pos.min(self.source.len())]
+            .matches('\n')
+            .count()
+            + 1;
+        format!("{} on line {}", message, line)
+    }
+
+    /// Skip spaces and comments, stopping at a newline
+    fn skip_inline(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                b' ' | b'\t' | b'\r' => self.pos += 1,
+                b'#' => self.skip_line_comment(),
+                b'/' if self.peek_at(1) == Some(b'/') => self.skip_line_comment(),
+                b'/' if self.peek_at(1) == Some(b'*') => self.skip_block_comment(),
+                _ => break,
+            }
+        }
+    }
+
+    /// Skip spaces, comments and newlines
+    fn skip_all(&mut self) {
+        loop {
+            self.skip_inline();
+            if self.peek() == Some(b'\n') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_line_comment(&mut self) {
+        while self.peek().is_some_and(|c| c != b'\n') {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_block_comment(&mut self) {
+        match self.source[self.pos + 2..].find("*/") {
+            Some(end) => self.pos += end + 4,
+            None => self.pos = self.bytes.len(),
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let start = self.pos;
+        if !self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+        {
+            return None;
+        }
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_' || c == b'-')
+        {
+            self.pos += 1;
+        }
+        Some(self.source[start..self.pos].to_string())
+    }
+
+    fn parse_body(&mut self, nested: bool) -> std::result::Result<Body, String> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_all();
+            match self.peek() {
+                None if nested => return Err(self.error("Unclosed block")),
+                None => return Ok(Body { items, close: None }),
+                Some(b'}') if nested => {
+                    let close = self.pos;
+                    self.pos += 1;
+                    return Ok(Body {
+                        items,
+                        close: Some(close),
+                    });
+                }
+                _ => {}
+            }
+
+            let name = self
+                .identifier()
+                .ok_or_else(|| self.error("Expected an attribute or block"))?;
+            self.skip_inline();
+            if self.peek() == Some(b'=') && self.peek_at(1) != Some(b'=') {
+                self.pos += 1;
+                let value = self.parse_expr()?;
+                items.push(Item::Attribute { name, value });
+                continue;
+            }
+
+            let mut labels = Vec::new();
+            loop {
+                self.skip_inline();
+                match self.peek() {
+                    Some(b'{') => break,
+                    Some(b'"') => match self.parse_string()? {
+                        Some(label) => labels.push(label),
+                        None => return Err(self.error("Interpolation in a block label")),
+                    },
+                    _ => labels.push(
+                        self.identifier()
+                            .ok_or_else(|| self.error("Expected a block label or '{'"))?,
+                    ),
+                }
+            }
+            self.pos += 1;
+            let body = self.parse_body(true)?;
+            items.push(Item::Block(Block {
+                kind: name,
+                labels,
+                body,
+            }));
+        }
+    }
+
+    fn parse_expr(&mut self) -> std::result::Result<Expr, String> {
+        self.skip_inline();
+        let start = self.pos;
+
+        let kind = match self.peek() {
+            Some(b'{') if !self.starts_for_expr() => Some(self.parse_object()?),
+            Some(b'[') if !self.starts_for_expr() => Some(self.parse_tuple()?),
+            Some(b'"') => Some(
+                self.parse_string()?
+                    .map_or(ExprKind::Other, ExprKind::Literal),
+            ),
+            Some(c) if c.is_ascii_digit() => {
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'.')
+                {
+                    self.pos += 1;
+                }
+                Some(ExprKind::Literal(self.source[start..self.pos].to_string()))
+            }
+            _ => None,
+        };
+
+        // Anything after the first term, like an operator, makes it an opaque expression
+        if let Some(kind) = kind {
+            let end = self.pos;
+            self.skip_inline();
+            if self
+                .peek()
+                .is_none_or(|c| matches!(c, b'\n' | b',' | b'}' | b']' | b')'))
+            {
+                return Ok(Expr { start, end, kind });
+            }
+        }
+
+        self.pos = start;
+        let end = self.skip_expr()?;
+        if end == start {
+            return Err(self.error("Expected an expression"));
+        }
+        Ok(Expr {
+            start,
+            end,
+            kind: ExprKind::Other,
+        })
+    }
+
+    /// Whether the bracket at the current position opens a `for` expression
+    fn starts_for_expr(&self) -> bool {
+        let rest = self.source[self.pos + 1..].trim_start();
+        rest.strip_prefix("for")
+            .is_some_and(|r| r.starts_with(|c: char| c.is_whitespace()))
+    }
+
+    fn parse_object(&mut self) -> std::result::Result<ExprKind, String> {
+        self.pos += 1;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_all();
+            match self.peek() {
+                None => return Err(self.error("Unclosed object")),
+                Some(b'}') => {
+                    let close = self.pos;
+                    self.pos += 1;
+                    return Ok(ExprKind::Object { entries, close });
+                }
+                _ => {}
+            }
+
+            let key = match self.peek() {
+                Some(b'"') => self.parse_string()?.unwrap_or_default(),
+                Some(b'(') => {
+                    self.skip_expr()?;
+                    String::new()
+                }
+                _ => self
+                    .identifier()
+                    .ok_or_else(|| self.error("Expected an object key"))?,
+            };
+            self.skip_inline();
+            if !matches!(self.peek(), Some(b'=' | b':')) {
+                return Err(self.error("Expected '=' after an object key"));
+            }
+            self.pos += 1;
+            let value = self.parse_expr()?;
+            entries.push((key, value));
+
+            self.skip_inline();
+            if self.peek() == Some(b',') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn parse_tuple(&mut self) -> std::result::Result<ExprKind, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+            self.skip_all();
+            match self.peek() {
+                None => return Err(self.error("Unclosed tuple")),
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(ExprKind::Tuple(items));
+                }
+                _ => {}
+            }
+            items.push(self.parse_expr()?);
+            self.skip_all();
+            if self.peek() == Some(b',') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Parse a quoted string, None when it contains interpolation or directives
+    fn parse_string(&mut self) -> std::result::Result<Option<String>, String> {
+        self.pos += 1;
+        let mut value = String::new();
+        let mut literal = true;
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error("Unterminated string"));
+            };
+            match c {
+                b'"' => {
+                    self.pos += 1;
+                    return Ok(literal.then_some(value));
+                }
+                b'\n' => return Err(self.error("Unterminated string")),
+                b'\\' => {
+                    match self.peek_at(1) {
+                        Some(b'n') => value.push('\n'),
+                        Some(b't') => value.push('\t'),
+                        Some(b'r') => value.push('\r'),
+                        Some(b'"') => value.push('"'),
+                        Some(b'\\') => value.push('\\'),
+                        // Unicode escapes are rare in names, treat them as templates
+                        _ => literal = false,
+                    }
+                    self.pos += 2;
+                }
+                b'$' | b'%' if self.peek_at(1) == Some(c) && self.peek_at(2) == Some(b'{') => {
+                    value.push(c as char);
+                    value.push('{');
+                    self.pos += 3;
+                }
+                b'$' | b'%' if self.peek_at(1) == Some(b'{') => {
+                    literal = false;
+                    self.pos += 2;
+                    self.skip_until_close(b'}')?;
+                }
+                _ => {
+                    let ch = self.source[self.pos..]
+                        .chars()
+                        .next()
+                        .expect("position is on a char boundary");
+                    value.push(ch);
+                    self.pos += ch.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// Skip past the bracket closing an already opened one
+    fn skip_until_close(&mut self, close: u8) -> std::result::Result<(), String> {
+        loop {
+            self.skip_expr()?;
+            match self.peek() {
+                Some(c) if c == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'\n' | b',') => self.pos += 1,
+                _ => return Err(self.error("Unbalanced brackets")),
+            }
+        }
+    }
+
+    /// Skip an expression up to the newline, comma or bracket ending it
+    ///
+    /// Returns the end of its last significant character.
+    fn skip_expr(&mut self) -> std::result::Result<usize, String> {
+        let mut depth = 0usize;
+        let mut end = self.pos;
+        while let Some(c) = self.peek() {
+            match c {
+                b'\n' | b',' if depth == 0 => break,
+                b')' | b']' | b'}' if depth == 0 => break,
+                b' ' | b'\t' | b'\r' | b'\n' => {
+                    self.pos += 1;
+                    continue;
+                }
+                b'#' => {
+                    self.skip_line_comment();
+                    continue;
+                }
+                b'/' if self.peek_at(1) == Some(b'/') => {
+                    self.skip_line_comment();
+                    continue;
+                }
+                b'/' if self.peek_at(1) == Some(b'*') => {
+                    self.skip_block_comment();
+                    continue;
+                }
+                b'"' => {
+                    self.parse_string()?;
+                }
+                b'<' if self.peek_at(1) == Some(b'<') => self.skip_heredoc()?,
+                b'(' | b'[' | b'{' => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                b')' | b']' | b'}' => {
+                    depth -= 1;
+                    self.pos += 1;
+                }
+                _ => self.pos += 1,
+            }
+            end = self.pos;
+        }
+        Ok(end)
+    }
+
+    /// Skip a `<<EOT` or `<<-EOT` heredoc, or the `<` operator when it isn't one
+    fn skip_heredoc(&mut self) -> std::result::Result<(), String> {
+        let start = self.pos;
+        self.pos += 2;
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        let Some(marker) = self.identifier() else {
+            self.pos = start + 1;
+            return Ok(());
+        };
+        let Some(newline) = self.source[self.pos..].find('\n') else {
+            return Err(self.error("Unterminated heredoc"));
+        };
+        self.pos += newline + 1;
+
+        while self.pos < self.bytes.len() {
+            let line_end = self.source[self.pos..]
+                .find('\n')
+                .map_or(self.bytes.len(), |i| self.pos + i);
+            let line = &self.source[self.pos..line_end];
+            if line.trim() == marker {
+                self.pos = line_end;
+                return Ok(());
+            }
+            self.pos = (line_end + 1).min(self.bytes.len());
+        }
+        Err(self.error("Unterminated heredoc"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::UsageStats;
+    use tempfile::TempDir;
+
+    fn fixture(dir: &str, name: &str) -> String {
+        let path = format!(
+            "{}/fixtures/terraform/{}/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            dir,
+            name
+        );
+        fs::read_to_string(path).unwrap()
+    }
+
+    /// Copy the `name` fixture to a fresh root and apply `recommendations` to it
+    fn rewrite(
+        name: &str,
+        recommendations: &[ResourceRecommendation],
+    ) -> (String, HashMap<String, usize>) {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("modules/shop").join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, fixture("input", name)).unwrap();
+
+        let updates = TerraformEditor::new(root.path())
+            .apply_recommendations(recommendations)
+            .unwrap();
+        (fs::read_to_string(&path).unwrap(), updates)
+    }
+
+    /// Recommendation of `[cpu request, cpu limit, memory request, memory limit]`
+    fn recommendation(
+        namespace: &str,
+        deployment: &str,
+        container: &str,
+        [cpu_request, cpu_limit, memory_request, memory_limit]: [&str; 4],
+    ) -> ResourceRecommendation {
+        serde_json::from_value(serde_json::json!({
+            "namespace": namespace,
+            "deployment": deployment,
+            "container": container,
+            "current_cpu_request": null,
+            "current_cpu_limit": null,
+            "current_memory_request": null,
+            "current_memory_limit": null,
+            "recommended_cpu_request": cpu_request,
+            "recommended_cpu_limit": cpu_limit,
+            "recommended_memory_request": memory_request,
+            "recommended_memory_limit": memory_limit,
+            "cpu_usage_stats": UsageStats::default(),
+            "memory_usage_stats": UsageStats::default(),
+            "recommendation_reason": "test",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn rewrites_deployment_blocks() {
+        let recommendations = [
+            recommendation("shop", "web", "nginx", ["300m", "600m", "384Mi", "768Mi"]),
+            recommendation(
+                "shop",
+                "web",
+                "log-shipper",
+                ["50m", "200m", "64Mi", "96Mi"],
+            ),
+            recommendation("shop", "web", "exporter", ["20m", "100m", "32Mi", "64Mi"]),
+            // Same container twice: counted twice, but its edits collapse into one
+            recommendation("shop", "web", "nginx", ["300m", "600m", "384Mi", "768Mi"]),
+        ];
+        let (rewritten, updates) = rewrite("deployment.tf", &recommendations);
+
+        // Comments, alignment, the heredoc and the variable are left as they were
+        assert_eq!(rewritten, fixture("expected", "deployment.tf"));
+        assert_eq!(updates, HashMap::from([("shop/web".to_string(), 4)]));
+    }
+
+    #[test]
+    fn rewrites_manifest_objects() {
+        // The manifest leaves the namespace out, so it matches any
+        let recommendations = [recommendation(
+            "batch",
+            "worker",
+            "worker",
+            ["750m", "1500m", "768Mi", "1Gi"],
+        )];
+        let (rewritten, updates) = rewrite("manifest.tf", &recommendations);
+
+        assert_eq!(rewritten, fixture("expected", "manifest.tf"));
+        assert_eq!(updates, HashMap::from([("batch/worker".to_string(), 1)]));
+    }
+
+    #[test]
+    fn leaves_unmatched_file_untouched() {
+        let recommendations = [
+            recommendation("shop", "web", "nginx", ["300m", "600m", "384Mi", "768Mi"]),
+            // Right name, but `kubernetes_deployment` defaults to the default namespace
+            recommendation("shop", "api", "nginx", ["300m", "600m", "384Mi", "768Mi"]),
+            recommendation(
+                "default",
+                "api",
+                "envoy",
+                ["300m", "600m", "384Mi", "768Mi"],
+            ),
+        ];
+        let (rewritten, updates) = rewrite("unrelated.tf", &recommendations);
+
+        assert_eq!(rewritten, fixture("input", "unrelated.tf"));
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn lists_deployments_of_fixtures() {
+        let root = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/terraform/input");
+        let mut workloads = TerraformEditor::new(root).list_deployments().unwrap();
+        workloads.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let workload = |namespace: Option<&str>, name: &str| ManifestWorkload {
+            namespace: namespace.map(str::to_string),
+            name: name.to_string(),
+        };
+        assert_eq!(
+            workloads,
+            [
+                workload(Some("default"), "api"),
+                workload(Some("shop"), "web"),
+                workload(None, "worker"),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_unparsable_file() {
+        let root = TempDir::new().unwrap();
+        let path = root.path().join("broken.tf");
+        let source = "resource \"kubernetes_deployment\" \"web\" {\n  metadata {\n";
+        fs::write(&path, source).unwrap();
+
+        let editor = TerraformEditor::new(root.path());
+        assert!(editor.list_deployments().unwrap().is_empty());
+        let recommendations = [recommendation(
+            "default",
+            "web",
+            "nginx",
+            ["1", "1", "1Gi", "1Gi"],
+        )];
+        assert!(
+            editor
+                .apply_recommendations(&recommendations)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), source);
+    }
+}
@@ -0,0 +1,1352 @@
+use crossterm::{
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use log::warn;
+use ratatui::{
+    Terminal,
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Clear, Gauge, Paragraph, Row, Table, TableState, Wrap},
+};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::apply::{ApplyTarget, GitPrTarget};
+use crate::clock::Timezone;
+use crate::config::{GitProvider, ResourceBounds, UpdaterConfig};
+use crate::cost::{ResourcePrices, format_dollars, total_monthly_savings};
+use crate::credentials;
+use crate::error::Result as RecommenderResult;
+use crate::output::{PercentileConfig, RecommenderOutput};
+use crate::policy::PolicySet;
+use crate::quantity::{Quantity, display_optional};
+use crate::recommender::{ResourceRecommendation, clamp_to_bounds, enforce_limits_above_requests};
+use crate::redact::redact;
+
+/// Progress update message from worker thread
+#[derive(Debug, Clone)]
+enum ProgressUpdate {
+    Stage {
+        progress: u16,
+        message: String,
+    },
+    Complete {
+        pr_url: Option<String>,
+        message: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Application mode
+#[derive(Debug, Clone, PartialEq)]
+enum AppMode {
+    BrowsingTable,
+    Tuning,
+    EditValues,
+    ConfirmApply,
+    InputUrl,
+    InputToken,
+    InputUsername,
+    InputBranch,
+    Applying { progress: u16, stage: String },
+    ShowResult(String, Option<String>), // (message, pr_url)
+}
+
+/// Application state
+struct AppState {
+    table_state: TableState,
+    selected_indices: HashSet<usize>,
+    mode: AppMode,
+    input_buffer: String,
+    error_message: Option<String>,
+    // Store collected values during input flow
+    collected_url: Option<Url>,
+    collected_token: Option<String>,
+    collected_username: Option<String>,
+    // Token the keyring holds for the collected URL's host
+    keyring_token: Option<String>,
+    // Newly entered token, saved to the keyring once the apply succeeds
+    token_to_store: Option<(Url, String)>,
+    // Channel receiver for progress updates
+    progress_rx: Option<Receiver<ProgressUpdate>>,
+    // Apply worker, joined on exit so it can stop cleanly
+    worker: Option<JoinHandle<()>>,
+    cancel: CancellationToken,
+    // Percentiles and margin of the what-if panel, and the row selected in it
+    tuning: PercentileConfig,
+    tuning_field: usize,
+    // Values typed into the edit dialog and the field being edited
+    edit_fields: [String; 4],
+    edit_field: usize,
+    // Recommended values edited by hand per row, kept through what-if tuning
+    edits: HashMap<usize, [Quantity; 4]>,
+}
+
+impl AppState {
+    fn new(total_items: usize, tuning: PercentileConfig, cancel: CancellationToken) -> Self {
+        let mut table_state = TableState::default();
+        table_state.select(Some(0));
+
+        Self {
+            table_state,
+            selected_indices: (0..total_items).collect(), // Select all by default
+            mode: AppMode::BrowsingTable,
+            input_buffer: String::new(),
+            error_message: None,
+            collected_url: None,
+            collected_token: None,
+            collected_username: None,
+            keyring_token: None,
+            token_to_store: None,
+            progress_rx: None,
+            worker: None,
+            cancel,
+            tuning,
+            tuning_field: 0,
+            edit_fields: Default::default(),
+            edit_field: 0,
+            edits: HashMap::new(),
+        }
+    }
+}
+
+/// Display recommendations in an interactive table
+///
+/// When `apply_target` is given, confirmed selections are applied to it directly;
+/// otherwise the user is prompted for git settings and a pull request is opened.
+/// Ctrl-C cancels `cancel` and waits for a running apply to stop.
+///
+/// The what-if panel sizes recommendations that kept their usage (see
+/// [`RecommenderConfig::retain_usage`](crate::config::RecommenderConfig::retain_usage))
+/// again for other percentiles, with `policies` enforced on the result.
+#[allow(clippy::too_many_arguments)]
+pub fn display_recommendations_table(
+    output: RecommenderOutput,
+    policies: Arc<PolicySet>,
+    manifest_url: Option<Url>,
+    git_branch: String,
+    git_username: Option<String>,
+    git_token: Option<String>,
+    git_provider: Option<GitProvider>,
+    apply_target: Option<Box<dyn ApplyTarget>>,
+    cancel: CancellationToken,
+) -> io::Result<()> {
+    // Setup terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    // Run the app
+    let res = run_recommendations_app(
+        &mut terminal,
+        output,
+        policies,
+        manifest_url,
+        git_branch,
+        git_username,
+        git_token,
+        git_provider,
+        apply_target,
+        cancel,
+    );
+
+    // Restore terminal
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+
+    if let Err(err) = res {
+        println!("Error: {:?}", err);
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_recommendations_app(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    mut output: RecommenderOutput,
+    policies: Arc<PolicySet>,
+    manifest_url: Option<Url>,
+    git_branch: String,
+    git_username: Option<String>,
+    git_token: Option<String>,
+    git_provider: Option<GitProvider>,
+    mut apply_target: Option<Box<dyn ApplyTarget>>,
+    cancel: CancellationToken,
+) -> io::Result<()> {
+    let total_items = output.recommendations.len();
+    let mut state = AppState::new(
+        total_items,
+        output.metadata.percentiles_used.clone(),
+        cancel,
+    );
+
+    // What-if tuning starts over from these on every change
+    let original = output.recommendations.clone();
+    let retunable = original.iter().filter(|rec| rec.usage.is_some()).count();
+
+    // Cell texts only change with the output, so format them once per change
+    let mut rows = table_rows(&output.recommendations);
+
+    loop {
+        terminal.draw(|f| {
+            let area = f.area();
+            if state.mode == AppMode::Tuning {
+                let [table_area, panel_area] =
+                    Layout::vertical([Constraint::Min(5), Constraint::Length(TUNING_PANEL_HEIGHT)])
+                        .areas(area);
+                render_table(f, table_area, &output, &rows, &mut state);
+                render_tuning_panel(
+                    f,
+                    panel_area,
+                    &state.tuning,
+                    state.tuning_field,
+                    retunable,
+                    total_items,
+                );
+            } else {
+                render_table(f, area, &output, &rows, &mut state);
+            }
+
+            match &state.mode {
+                AppMode::BrowsingTable | AppMode::Tuning => {}
+                AppMode::EditValues => {
+                    let selected = state.table_state.selected().unwrap_or(0);
+                    if let Some(rec) = output.recommendations.get(selected) {
+                        render_edit_dialog(
+                            f,
+                            area,
+                            rec,
+                            &state.edit_fields,
+                            state.edit_field,
+                            state.error_message.as_deref(),
+                        );
+                    }
+                }
+                AppMode::ConfirmApply => {
+                    render_confirm_dialog(f, area, state.selected_indices.len());
+                }
+                AppMode::InputUrl => {
+                    render_input_dialog(
+                        f,
+                        area,
+                        "Enter Git Repository URL",
+                        &state.input_buffer,
+                        state.error_message.as_deref(),
+                    );
+                }
+                AppMode::InputToken => {
+                    let masked = "*".repeat(state.input_buffer.len());
+                    let title = if state.keyring_token.is_some() {
+                        "Enter Git Token (saved in keyring, Enter to reuse)"
+                    } else {
+                        "Enter Git Token (optional)"
+                    };
+                    render_input_dialog(f, area, title, &masked, state.error_message.as_deref());
+                }
+                AppMode::InputUsername => {
+                    render_input_dialog(
+                        f,
+                        area,
+                        "Enter Git Username (optional)",
+                        &state.input_buffer,
+                        state.error_message.as_deref(),
+                    );
+                }
+                AppMode::InputBranch => {
+                    render_input_dialog(
+                        f,
+                        area,
+                        "Enter Branch Name",
+                        &state.input_buffer,
+                        state.error_message.as_deref(),
+                    );
+                }
+                AppMode::Applying { progress, stage } => {
+                    render_progress_dialog(f, area, *progress, stage);
+                }
+                AppMode::ShowResult(message, pr_url) => {
+                    render_result_dialog(f, area, message, pr_url.as_deref());
+                }
+            }
+        })?;
+
+        // Check for progress updates from worker thread (non-blocking)
+        if let Some(rx) = &state.progress_rx
+            && let Ok(update) = rx.try_recv()
+        {
+            match update {
+                ProgressUpdate::Stage { progress, message } => {
+                    state.mode = AppMode::Applying {
+                        progress,
+                        stage: message,
+                    };
+                }
+                ProgressUpdate::Complete { pr_url, message } => {
+                    if let Some((url, token)) = state.token_to_store.take()
+                        && let Err(e) = credentials::store_token(&url, &token)
+                    {
+                        warn!("{}", e);
+                    }
+                    state.mode = AppMode::ShowResult(message, pr_url);
+                    state.progress_rx = None; // Clean up channel
+                }
+                ProgressUpdate::Error { message } => {
+                    state.mode = AppMode::ShowResult(message, None);
+                    state.progress_rx = None; // Clean up channel
+                }
+            }
+        }
+
+        // Handle input based on mode
+        if event::poll(std::time::Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+        {
+            // Raw mode turns Ctrl-C into a key press instead of a signal
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                state.cancel.cancel();
+                if let Some(worker) = state.worker.take() {
+                    let _ = worker.join();
+                }
+                return Ok(());
+            }
+
+            match &state.mode {
+                AppMode::BrowsingTable => {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char(' ') => {
+                            if let Some(i) = state.table_state.selected()
+                                && !state.selected_indices.remove(&i)
+                            {
+                                state.selected_indices.insert(i);
+                            }
+                        }
+                        KeyCode::Char('a') => {
+                            // Select all
+                            state.selected_indices = (0..total_items).collect();
+                        }
+                        KeyCode::Char('n') => {
+                            // Deselect all
+                            state.selected_indices.clear();
+                        }
+                        KeyCode::Enter if !state.selected_indices.is_empty() => {
+                            state.mode = AppMode::ConfirmApply;
+                        }
+                        KeyCode::Char('t') => {
+                            state.mode = AppMode::Tuning;
+                        }
+                        KeyCode::Char('e') => {
+                            if let Some(rec) = state
+                                .table_state
+                                .selected()
+                                .and_then(|i| output.recommendations.get(i))
+                            {
+                                state.edit_fields =
+                                    recommended_values(rec).map(|value| value.to_string());
+                                state.edit_field = 0;
+                                state.error_message = None;
+                                state.mode = AppMode::EditValues;
+                            }
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            let i = match state.table_state.selected() {
+                                Some(i) => {
+                                    if i >= total_items - 1 {
+                                        0
+                                    } else {
+                                        i + 1
+                                    }
+                                }
+                                None => 0,
+                            };
+                            state.table_state.select(Some(i));
+                        }
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            let i = match state.table_state.selected() {
+                                Some(i) => {
+                                    if i == 0 {
+                                        total_items - 1
+                                    } else {
+                                        i - 1
+                                    }
+                                }
+                                None => 0,
+                            };
+                            state.table_state.select(Some(i));
+                        }
+                        _ => {}
+                    }
+                }
+                AppMode::Tuning => {
+                    let step = match key.code {
+                        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('t') => {
+                            state.mode = AppMode::BrowsingTable;
+                            continue;
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            state.tuning_field = (state.tuning_field + 1) % TUNING_FIELDS.len();
+                            continue;
+                        }
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            state.tuning_field = (state.tuning_field + TUNING_FIELDS.len() - 1)
+                                % TUNING_FIELDS.len();
+                            continue;
+                        }
+                        KeyCode::Char('r') => {
+                            state.tuning = output.metadata.percentiles_used.clone();
+                            output.recommendations = original.clone();
+                            apply_edits(
+                                &mut output.recommendations,
+                                &state.edits,
+                                output.metadata.prices.as_ref(),
+                            );
+                            rows = table_rows(&output.recommendations);
+                            continue;
+                        }
+                        KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Right => 1.0,
+                        KeyCode::Char('-') | KeyCode::Left => -1.0,
+                        _ => continue,
+                    };
+
+                    adjust_tuning(&mut state.tuning, state.tuning_field, step);
+                    output.recommendations = retune_all(
+                        &original,
+                        &state.tuning,
+                        &policies,
+                        &output.metadata.bounds,
+                        output.metadata.prices.as_ref(),
+                    );
+                    apply_edits(
+                        &mut output.recommendations,
+                        &state.edits,
+                        output.metadata.prices.as_ref(),
+                    );
+                    rows = table_rows(&output.recommendations);
+                }
+                AppMode::EditValues => match key.code {
+                    KeyCode::Enter => match parse_edit_fields(&state.edit_fields) {
+                        Ok(values) => {
+                            if let Some(i) = state.table_state.selected() {
+                                state.edits.insert(i, values);
+                                apply_edits(
+                                    &mut output.recommendations,
+                                    &state.edits,
+                                    output.metadata.prices.as_ref(),
+                                );
+                                rows = table_rows(&output.recommendations);
+                            }
+                            state.mode = AppMode::BrowsingTable;
+                        }
+                        Err(message) => state.error_message = Some(message),
+                    },
+                    KeyCode::Esc => {
+                        state.mode = AppMode::BrowsingTable;
+                        state.error_message = None;
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        state.edit_field = (state.edit_field + 1) % EDIT_FIELDS.len();
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        state.edit_field =
+                            (state.edit_field + EDIT_FIELDS.len() - 1) % EDIT_FIELDS.len();
+                    }
+                    KeyCode::Char(c) => {
+                        state.edit_fields[state.edit_field].push(c);
+                        state.error_message = None;
+                    }
+                    KeyCode::Backspace => {
+                        state.edit_fields[state.edit_field].pop();
+                        state.error_message = None;
+                    }
+                    _ => {}
+                },
+                AppMode::ConfirmApply => {
+                    match key.code {
+                        KeyCode::Char('y') | KeyCode::Char('Y') => {
+                            // Pre-configured targets need no further input
+                            if let Some(target) = apply_target.take() {
+                                let recommendations = selected_recommendations(&state, &output);
+                                let (rx, worker) =
+                                    spawn_apply_worker(move || Ok(target), recommendations);
+                                state.progress_rx = Some(rx);
+                                state.worker = Some(worker);
+                                state.mode = AppMode::Applying {
+                                    progress: 0,
+                                    stage: "Initializing...".to_string(),
+                                };
+                                continue;
+                            }
+
+                            // Git flow starts with URL input, pre-filled if provided
+                            state.mode = AppMode::InputUrl;
+                            state.input_buffer = manifest_url
+                                .as_ref()
+                                .map(|u| u.to_string())
+                                .unwrap_or_default();
+                            state.error_message = None;
+                        }
+                        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                            state.mode = AppMode::BrowsingTable;
+                        }
+                        _ => {}
+                    }
+                }
+                AppMode::InputUrl
+                | AppMode::InputToken
+                | AppMode::InputUsername
+                | AppMode::InputBranch => match key.code {
+                    KeyCode::Enter => {
+                        handle_input_submit(
+                            &mut state,
+                            &output,
+                            &manifest_url,
+                            &git_token,
+                            &git_username,
+                            &git_branch,
+                            git_provider,
+                        );
+                    }
+                    KeyCode::Esc => {
+                        state.mode = AppMode::BrowsingTable;
+                        state.input_buffer.clear();
+                        state.error_message = None;
+                    }
+                    KeyCode::Char(c) => {
+                        state.input_buffer.push(c);
+                        state.error_message = None;
+                    }
+                    KeyCode::Backspace => {
+                        state.input_buffer.pop();
+                        state.error_message = None;
+                    }
+                    _ => {}
+                },
+                AppMode::ShowResult(_, _) => {
+                    // Any key returns to browsing
+                    return Ok(());
+                }
+                AppMode::Applying { .. } => {
+                    // No input during applying
+                }
+            }
+        }
+    }
+}
+
+fn handle_input_submit(
+    state: &mut AppState,
+    output: &RecommenderOutput,
+    _manifest_url: &Option<Url>,
+    git_token: &Option<String>,
+    git_username: &Option<String>,
+    git_branch: &str,
+    git_provider: Option<GitProvider>,
+) {
+    match &state.mode {
+        AppMode::InputUrl => {
+            // Validate URL
+            match Url::parse(&state.input_buffer) {
+                Ok(url) => {
+                    // A token given on the command line wins over the keyring
+                    state.keyring_token = match git_token {
+                        Some(_) => None,
+                        None => credentials::stored_token(&url),
+                    };
+                    state.collected_url = Some(url);
+                    state.mode = AppMode::InputToken;
+                    // Pre-fill with the token from the CLI or the keyring
+                    state.input_buffer = git_token
+                        .clone()
+                        .or_else(|| state.keyring_token.clone())
+                        .unwrap_or_default();
+                    state.error_message = None;
+                }
+                Err(e) => {
+                    state.error_message = Some(format!("Invalid URL: {}", e));
+                }
+            }
+        }
+        AppMode::InputToken => {
+            // Token is optional, store it
+            state.collected_token = if state.input_buffer.is_empty() {
+                None
+            } else {
+                Some(state.input_buffer.clone())
+            };
+            // Remember tokens typed in here, not ones passed with --git-token
+            state.token_to_store = match (&state.collected_url, &state.collected_token) {
+                (Some(url), Some(token))
+                    if git_token.is_none() && state.keyring_token.as_ref() != Some(token) =>
+                {
+                    Some((url.clone(), token.clone()))
+                }
+                _ => None,
+            };
+            // Move to username input
+            state.mode = AppMode::InputUsername;
+            state.input_buffer = git_username.clone().unwrap_or_default();
+            state.error_message = None;
+        }
+        AppMode::InputUsername => {
+            // Username is optional, store it
+            state.collected_username = if state.input_buffer.is_empty() {
+                None
+            } else {
+                Some(state.input_buffer.clone())
+            };
+            // Move to branch input
+            state.mode = AppMode::InputBranch;
+            state.input_buffer = git_branch.to_string();
+            state.error_message = None;
+        }
+        AppMode::InputBranch => {
+            // All inputs collected, spawn worker thread
+            let branch = state.input_buffer.clone();
+
+            if let Some(url) = state.collected_url.clone() {
+                let selected_recommendations = selected_recommendations(state, output);
+                let token = state.collected_token.clone();
+                let username = state.collected_username.clone();
+
+                // Spawn worker thread with apply task
+                let cancel = state.cancel.clone();
+                let timezone = output_timezone(output);
+                let (rx, worker) = spawn_apply_worker(
+                    move || {
+                        let config = UpdaterConfig::new(url, token, username)?
+                            .with_provider_override(git_provider);
+                        let target = GitPrTarget::new(config, branch)?
+                            .with_cancellation(cancel)
+                            .with_timezone(timezone);
+                        Ok(Box::new(target) as Box<dyn ApplyTarget>)
+                    },
+                    selected_recommendations,
+                );
+
+                // Store receiver and transition to Applying mode
+                state.progress_rx = Some(rx);
+                state.worker = Some(worker);
+                state.mode = AppMode::Applying {
+                    progress: 0,
+                    stage: "Initializing...".to_string(),
+                };
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Recommendations currently ticked in the table
+fn selected_recommendations(
+    state: &AppState,
+    output: &RecommenderOutput,
+) -> Vec<ResourceRecommendation> {
+    state
+        .selected_indices
+        .iter()
+        .filter_map(|&i| output.recommendations.get(i).cloned())
+        .collect()
+}
+
+/// Spawn a worker thread that performs the apply operation
+fn spawn_apply_worker<F>(
+    make_target: F,
+    recommendations: Vec<ResourceRecommendation>,
+) -> (Receiver<ProgressUpdate>, JoinHandle<()>)
+where
+    F: FnOnce() -> RecommenderResult<Box<dyn ApplyTarget>> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+
+    let worker = thread::spawn(move || {
+        // Create tokio runtime in worker thread
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => {
+                let _ = tx.send(ProgressUpdate::Error {
+                    message: format!("Failed to create async runtime: {}", e),
+                });
+                return;
+            }
+        };
+
+        // Run async apply operation
+        rt.block_on(async {
+            // Send initial progress
+            let _ = tx.send(ProgressUpdate::Stage {
+                progress: 10,
+                message: "Initializing apply target...".to_string(),
+            });
+
+            let mut target = match make_target() {
+                Ok(t) => t,
+                Err(e) => {
+                    let _ = tx.send(ProgressUpdate::Error {
+                        message: format!("Failed to create apply target: {}", e),
+                    });
+                    return;
+                }
+            };
+
+            let _ = tx.send(ProgressUpdate::Stage {
+                progress: 30,
+                message: format!("Applying to {}...", target.describe()),
+            });
+
+            match target.apply(&recommendations).await {
+                Ok(outcome) => {
+                    let _ = tx.send(ProgressUpdate::Stage {
+                        progress: 90,
+                        message: "Finalizing...".to_string(),
+                    });
+
+                    let _ = tx.send(ProgressUpdate::Complete {
+                        message: outcome.summary(),
+                        pr_url: outcome.pr_url,
+                    });
+                }
+                Err(e) => {
+                    let _ = tx.send(ProgressUpdate::Error {
+                        message: redact(&format!("Failed to apply recommendations: {}", e))
+                            .into_owned(),
+                    });
+                }
+            }
+        });
+    });
+
+    (rx, worker)
+}
+
+/// Pre-formatted "current → recommended" cells of one table row
+struct TableRow {
+    changes: [(String, Style); 4],
+    savings: (String, Style),
+}
+
+fn table_rows(recommendations: &[ResourceRecommendation]) -> Vec<TableRow> {
+    let change = |current: Option<Quantity>, recommended: Quantity| {
+        (
+            format!("{} → {}", display_optional(current), recommended),
+            get_change_indicator(current, recommended),
+        )
+    };
+
+    recommendations
+        .iter()
+        .map(|rec| TableRow {
+            changes: [
+                change(rec.current_cpu_request, rec.recommended_cpu_request),
+                change(rec.current_cpu_limit, rec.recommended_cpu_limit),
+                change(rec.current_memory_request, rec.recommended_memory_request),
+                change(rec.current_memory_limit, rec.recommended_memory_limit),
+            ],
+            savings: savings_cell(rec.estimated_monthly_savings),
+        })
+        .collect()
+}
+
+/// Dollars a month, green when saved and red when it costs more
+fn savings_cell(savings: Option<f64>) -> (String, Style) {
+    match savings {
+        Some(savings) => {
+            let color = if savings > 0.005 {
+                Color::Green
+            } else if savings < -0.005 {
+                Color::Red
+            } else {
+                Color::Gray
+            };
+            (format_dollars(savings), Style::default().fg(color))
+        }
+        None => ("-".to_string(), Style::default().fg(Color::Gray)),
+    }
+}
+
+fn render_table(
+    f: &mut ratatui::Frame,
+    area: Rect,
+    output: &RecommenderOutput,
+    rows: &[TableRow],
+    state: &mut AppState,
+) {
+    // Create the table header
+    let header_cells = [
+        "✓",
+        "Namespace",
+        "Deployment",
+        "Container",
+        "CPU Req (Current → Rec)",
+        "CPU Lim (Current → Rec)",
+        "Mem Req (Current → Rec)",
+        "Mem Lim (Current → Rec)",
+        "Savings/mo",
+    ]
+    .iter()
+    .map(|h| {
+        Cell::from(*h).style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )
+    });
+    let header = Row::new(header_cells)
+        .style(Style::default().bg(Color::DarkGray))
+        .height(1);
+
+    // Containers of both day profiles count once, see `total_monthly_savings`
+    let total = total_monthly_savings(&output.recommendations);
+
+    // Only build widgets for the rows that fit: borders and header take 3 lines,
+    // the savings total another
+    let reserved = if total.is_some() { 4 } else { 3 };
+    let visible = usize::from(area.height.saturating_sub(reserved)).max(1);
+    let selected = state.table_state.selected().unwrap_or(0);
+    let offset = scroll_offset(state.table_state.offset(), selected, visible);
+    *state.table_state.offset_mut() = offset;
+
+    // Create table rows with selection indicators
+    let rows = output
+        .recommendations
+        .iter()
+        .zip(rows)
+        .enumerate()
+        .skip(offset)
+        .take(visible)
+        .map(|(idx, (rec, row))| {
+            // Rows edited by hand are starred
+            let selected_mark = match (
+                state.selected_indices.contains(&idx),
+                state.edits.contains_key(&idx),
+            ) {
+                (true, true) => "✓*",
+                (true, false) => "✓",
+                (false, true) => " *",
+                (false, false) => " ",
+            };
+
+            let cells = [
+                Cell::from(selected_mark).style(Style::default().fg(Color::Green)),
+                Cell::from(rec.namespace.as_str()),
+                Cell::from(rec.deployment.as_str()),
+                Cell::from(match rec.day_profile {
+                    Some(days) => format!("{} ({})", rec.container, days),
+                    None => rec.container.clone(),
+                }),
+            ]
+            .into_iter()
+            .chain(
+                row.changes
+                    .iter()
+                    .chain(std::iter::once(&row.savings))
+                    .map(|(text, style)| Cell::from(text.as_str()).style(*style)),
+            );
+            Row::new(cells).height(1)
+        });
+
+    let skipped = if output.skipped.is_empty() {
+        String::new()
+    } else {
+        format!(" | Skipped: {} (see log)", output.skipped.len())
+    };
+    let title = format!(
+        " Resource Recommendations | Selected: {}/{}{} | Space: Toggle | a: All | n: None | t: Tune | e: Edit | Enter: Apply | q: Quit ",
+        state.selected_indices.len(),
+        output.recommendations.len(),
+        skipped
+    );
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(3),
+            Constraint::Percentage(9),
+            Constraint::Percentage(11),
+            Constraint::Percentage(9),
+            Constraint::Percentage(16),
+            Constraint::Percentage(14),
+            Constraint::Percentage(16),
+            Constraint::Percentage(14),
+            Constraint::Percentage(9),
+        ],
+    )
+    .header(header)
+    .block(Block::default().borders(Borders::ALL).title(title))
+    .row_highlight_style(Style::default().bg(Color::DarkGray))
+    .highlight_symbol(">> ");
+    let table = match total {
+        Some(total) => {
+            let (text, style) = savings_cell(Some(total));
+            let mut cells = vec![Cell::from(""); 8];
+            cells[1] = Cell::from("Total").style(Style::default().add_modifier(Modifier::BOLD));
+            cells.push(Cell::from(text).style(style.add_modifier(Modifier::BOLD)));
+            table.footer(Row::new(cells).style(Style::default().bg(Color::DarkGray)))
+        }
+        None => table,
+    };
+
+    // The table only holds the visible window, so select relative to it
+    let mut window_state = TableState::default().with_selected(selected.checked_sub(offset));
+    f.render_stateful_widget(table, area, &mut window_state);
+}
+
+/// Lines of the what-if panel: borders, one per field and the key help
+const TUNING_PANEL_HEIGHT: u16 = TUNING_FIELDS.len() as u16 + 3;
+
+/// Fields of the what-if panel: label, step, lowest and highest value
+const TUNING_FIELDS: [(&str, f64, f64, f64); 5] = [
+    ("CPU request percentile", 1.0, 1.0, 100.0),
+    ("CPU limit percentile", 1.0, 1.0, 100.0),
+    ("Memory request percentile", 1.0, 1.0, 100.0),
+    ("Memory limit percentile", 1.0, 1.0, 100.0),
+    ("Safety margin", 0.05, 0.5, 3.0),
+];
+
+/// Value behind field `field` of [`TUNING_FIELDS`]
+fn tuning_value(tuning: &mut PercentileConfig, field: usize) -> &mut f64 {
+    match field {
+        0 => &mut tuning.cpu_request,
+        1 => &mut tuning.cpu_limit,
+        2 => &mut tuning.memory_request,
+        3 => &mut tuning.memory_limit,
+        _ => &mut tuning.safety_margin,
+    }
+}
+
+/// Move a field of the what-if panel `steps` steps, within its bounds
+fn adjust_tuning(tuning: &mut PercentileConfig, field: usize, steps: f64) {
+    let (_, step, min, max) = TUNING_FIELDS[field];
+    let value = tuning_value(tuning, field);
+    // Snap to the step so repeated float additions don't drift
+    *value = (((*value + steps * step) / step).round() * step).clamp(min, max);
+}
+
+/// Size `original` again for `tuning`, keeping those without retained usage as they are
+///
+/// Policies and `bounds` apply as in the run. Savings are estimated again at
+/// `prices`, the ones the output was estimated at.
+fn retune_all(
+    original: &[ResourceRecommendation],
+    tuning: &PercentileConfig,
+    policies: &PolicySet,
+    bounds: &ResourceBounds,
+    prices: Option<&ResourcePrices>,
+) -> Vec<ResourceRecommendation> {
+    let mut recommendations: Vec<_> = original
+        .iter()
+        .map(|rec| rec.retune(tuning).unwrap_or_else(|| rec.clone()))
+        .collect();
+    policies.enforce(&mut recommendations);
+    clamp_to_bounds(&mut recommendations, bounds);
+    enforce_limits_above_requests(&mut recommendations);
+    if let Some(prices) = prices {
+        prices.estimate(&mut recommendations);
+    }
+    recommendations
+}
+
+/// Fields of the edit dialog, in the order of the table's columns
+const EDIT_FIELDS: [&str; 4] = ["CPU request", "CPU limit", "Memory request", "Memory limit"];
+
+/// Recommended values of `rec` in the order of [`EDIT_FIELDS`]
+fn recommended_values(rec: &ResourceRecommendation) -> [Quantity; 4] {
+    [
+        rec.recommended_cpu_request,
+        rec.recommended_cpu_limit,
+        rec.recommended_memory_request,
+        rec.recommended_memory_limit,
+    ]
+}
+
+/// Quantities typed into the edit dialog, each limit at least its request
+fn parse_edit_fields(fields: &[String; 4]) -> Result<[Quantity; 4], String> {
+    let mut values = [Quantity::default(); 4];
+    for (value, (field, label)) in values.iter_mut().zip(fields.iter().zip(EDIT_FIELDS)) {
+        *value = Quantity::parse(field).map_err(|_| {
+            format!(
+                "{}: '{}' is not a Kubernetes quantity like 250m, 1.5 or 512Mi",
+                label,
+                field.trim()
+            )
+        })?;
+        if value.value() <= 0.0 {
+            return Err(format!("{} must be greater than 0", label));
+        }
+    }
+
+    let [cpu_request, cpu_limit, memory_request, memory_limit] = values;
+    if cpu_limit < cpu_request {
+        return Err(format!(
+            "CPU limit {} is below the request {}",
+            cpu_limit, cpu_request
+        ));
+    }
+    if memory_limit < memory_request {
+        return Err(format!(
+            "Memory limit {} is below the request {}",
+            memory_limit, memory_request
+        ));
+    }
+    Ok(values)
+}
+
+/// Put the values edited by hand back on `recommendations`, estimating their
+/// savings again at `prices`
+fn apply_edits(
+    recommendations: &mut [ResourceRecommendation],
+    edits: &HashMap<usize, [Quantity; 4]>,
+    prices: Option<&ResourcePrices>,
+) {
+    for (&i, &[cpu_request, cpu_limit, memory_request, memory_limit]) in edits {
+        let Some(rec) = recommendations.get_mut(i) else {
+            continue;
+        };
+        rec.recommended_cpu_request = cpu_request;
+        rec.recommended_cpu_limit = cpu_limit;
+        rec.recommended_memory_request = memory_request;
+        rec.recommended_memory_limit = memory_limit;
+        if let Some(prices) = prices {
+            rec.estimated_monthly_savings = prices.monthly_savings(rec);
+        }
+    }
+}
+
+fn render_tuning_panel(
+    f: &mut ratatui::Frame,
+    area: Rect,
+    tuning: &PercentileConfig,
+    selected: usize,
+    retunable: usize,
+    total: usize,
+) {
+    let mut tuning = tuning.clone();
+    let mut lines: Vec<Line> = TUNING_FIELDS
+        .iter()
+        .enumerate()
+        .map(|(field, (label, _, _, _))| {
+            let value = *tuning_value(&mut tuning, field);
+            let value = if field == TUNING_FIELDS.len() - 1 {
+                format!("{:.2}x", value)
+            } else {
+                format!("p{}", value)
+            };
+            let (marker, style) = if field == selected {
+                (
+                    ">> ",
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                )
+            } else {
+                ("   ", Style::default())
+            };
+            Line::from(Span::styled(
+                format!("{}{:<28}{}", marker, label, value),
+                style,
+            ))
+        })
+        .collect();
+    lines.push(Line::from(Span::styled(
+        format!(
+            "↑/↓: Select | +/-: Adjust | r: Reset | Esc: Done | {} of {} recommendations kept their samples",
+            retunable, total
+        ),
+        Style::default().fg(Color::Gray),
+    )));
+
+    let block = Block::default()
+        .title(" What-if Tuning ")
+        .borders(Borders::ALL);
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Zone of the report timestamp, so branches and commits match the report
+fn output_timezone(output: &RecommenderOutput) -> Timezone {
+    chrono::DateTime::parse_from_rfc3339(&output.metadata.timestamp)
+        .map(|time| Timezone::Fixed(*time.offset()))
+        .unwrap_or_default()
+}
+
+/// First visible row, scrolled just enough to keep `selected` in view
+fn scroll_offset(offset: usize, selected: usize, visible: usize) -> usize {
+    if selected < offset {
+        selected
+    } else if selected >= offset + visible {
+        selected + 1 - visible
+    } else {
+        offset
+    }
+}
+
+fn render_edit_dialog(
+    f: &mut ratatui::Frame,
+    area: Rect,
+    rec: &ResourceRecommendation,
+    fields: &[String; 4],
+    selected: usize,
+    error: Option<&str>,
+) {
+    let dialog_area = centered_rect(60, 40, area);
+
+    let block = Block::default()
+        .title(" Edit Recommended Values ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!(
+                "{}/{} container {}",
+                rec.namespace, rec.deployment, rec.container
+            ),
+            Style::default().fg(Color::Yellow),
+        )),
+        Line::from(""),
+    ];
+    for (field, (label, value)) in EDIT_FIELDS.iter().zip(fields).enumerate() {
+        let (marker, style) = if field == selected {
+            (
+                ">> ",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            ("   ", Style::default())
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{}{:<16}{}", marker, label, value),
+            style,
+        )));
+    }
+    lines.push(Line::from(""));
+
+    if let Some(err) = error {
+        lines.push(Line::from(Span::styled(
+            err,
+            Style::default().fg(Color::Red),
+        )));
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(Span::styled(
+        "Tab/↑/↓: Field | Enter: Save | Esc: Cancel",
+        Style::default().fg(Color::Gray),
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(paragraph, dialog_area);
+}
+
+fn render_confirm_dialog(f: &mut ratatui::Frame, area: Rect, selected_count: usize) {
+    let dialog_area = centered_rect(60, 20, area);
+
+    let block = Block::default()
+        .title(" Confirm Apply ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let text = vec![
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("Apply changes to {} selected containers?", selected_count),
+            Style::default().fg(Color::Yellow),
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Press 'y' to confirm, 'n' to cancel",
+            Style::default().fg(Color::Gray),
+        )),
+    ];
+
+    let paragraph = Paragraph::new(text)
+        .block(block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(paragraph, dialog_area);
+}
+
+fn render_input_dialog(
+    f: &mut ratatui::Frame,
+    area: Rect,
+    title: &str,
+    input: &str,
+    error: Option<&str>,
+) {
+    let dialog_area = centered_rect(70, 25, area);
+
+    let block = Block::default()
+        .title(format!(" {} ", title))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(input, Style::default().fg(Color::Cyan))),
+        Line::from(""),
+    ];
+
+    if let Some(err) = error {
+        lines.push(Line::from(Span::styled(
+            err,
+            Style::default().fg(Color::Red),
+        )));
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(Span::styled(
+        "Press Enter to confirm, Esc to cancel",
+        Style::default().fg(Color::Gray),
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(paragraph, dialog_area);
+}
+
+fn render_progress_dialog(f: &mut ratatui::Frame, area: Rect, progress: u16, stage: &str) {
+    let dialog_area = centered_rect(60, 20, area);
+
+    // Split the dialog area into sections
+    let chunks = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Title
+            Constraint::Length(3), // Gauge
+            Constraint::Length(2), // Stage message
+            Constraint::Min(0),    // Padding
+        ])
+        .split(dialog_area);
+
+    // Clear background
+    f.render_widget(Clear, dialog_area);
+
+    // Render title block
+    let title_block = Block::default()
+        .title(" Applying Changes ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    f.render_widget(title_block, dialog_area);
+
+    // Render progress gauge
+    let gauge = Gauge::default()
+        .block(Block::default())
+        .gauge_style(
+            Style::default()
+                .fg(Color::Green)
+                .bg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        )
+        .percent(progress)
+        .label(format!("{}%", progress));
+
+    f.render_widget(gauge, chunks[1]);
+
+    // Render stage message
+    let stage_text = vec![
+        Line::from(""),
+        Line::from(Span::styled(stage, Style::default().fg(Color::Yellow))),
+    ];
+    let stage_paragraph = Paragraph::new(stage_text).alignment(Alignment::Center);
+    f.render_widget(stage_paragraph, chunks[2]);
+}
+
+fn render_result_dialog(f: &mut ratatui::Frame, area: Rect, message: &str, pr_url: Option<&str>) {
+    let dialog_area = centered_rect(70, 25, area);
+
+    let block = Block::default()
+        .title(" Result ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let mut lines = vec![
+        Line::from(""),
+        Line::from(Span::styled(message, Style::default().fg(Color::Green))),
+        Line::from(""),
+    ];
+
+    if let Some(url) = pr_url {
+        lines.push(Line::from(Span::styled(
+            "Pull Request:",
+            Style::default().fg(Color::Yellow),
+        )));
+        lines.push(Line::from(Span::styled(
+            url,
+            Style::default().fg(Color::Cyan),
+        )));
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(Span::styled(
+        "Press any key to exit",
+        Style::default().fg(Color::Gray),
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+
+    f.render_widget(Clear, dialog_area);
+    f.render_widget(paragraph, dialog_area);
+}
+
+/// Helper function to create a centered rectangle
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(ratatui::layout::Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(ratatui::layout::Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+/// Get change indicator and style based on comparison
+fn get_change_indicator(current: Option<Quantity>, recommended: Quantity) -> Style {
+    match current {
+        Some(current) if recommended > current => Style::default().fg(Color::Green),
+        Some(current) if recommended < current => Style::default().fg(Color::Red),
+        _ => Style::default().fg(Color::White),
+    }
+}
@@ -0,0 +1,1180 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use git2::{Cred, FetchOptions, PushOptions, RemoteCallbacks, Repository};
+use log::{debug, info, warn};
+use serde_json::json;
+use tempfile::TempDir;
+use tokio_util::sync::CancellationToken;
+
+use crate::chargeback::format_bytes;
+use crate::clock::{SharedClock, SystemClock, Timezone};
+use crate::config::{GitConnectionType, GitProvider, MergeMethod, UpdaterConfig};
+use crate::error::{RecommenderError, Result};
+use crate::http::shared_client;
+use crate::manifest::{ManifestEditor, ManifestWorkload};
+use crate::overlays::{EnvironmentChanges, merge_updates};
+use crate::pr_template::{DescriptionSection, fill_template, find_template};
+use crate::quantity::Quantity;
+use crate::recommender::ResourceRecommendation;
+use crate::redact::{redact, redact_url};
+
+/// Name of the commit status posted on the recommendation commit
+const STATUS_CONTEXT: &str = "autorightsizing";
+
+/// GitHub rejects longer commit status descriptions
+const STATUS_DESCRIPTION_MAX: usize = 140;
+
+/// A PR/MR opened through the provider API
+struct PullRequest {
+    url: String,
+    /// Number (GitHub, Gitea, Bitbucket) or iid (GitLab) within the repository
+    number: Option<u64>,
+    /// GraphQL node ID, GitHub only
+    node_id: Option<String>,
+}
+
+pub struct ManifestUpdater {
+    config: UpdaterConfig,
+    temp_dir: TempDir,
+    repo: Option<Repository>,
+    cancel: CancellationToken,
+    clock: SharedClock,
+    timezone: Timezone,
+    http: reqwest::Client,
+    /// Workloads updated per environment overlay by the last apply
+    environments: Vec<EnvironmentChanges>,
+    /// PodDisruptionBudget warnings keyed by `namespace/deployment` for the last apply
+    disruption_risks: BTreeMap<String, String>,
+}
+
+impl ManifestUpdater {
+    /// Create a new ManifestUpdater
+    pub fn new(config: UpdaterConfig) -> Result<Self> {
+        let temp_dir = TempDir::new()?;
+        info!("Created temporary directory: {}", temp_dir.path().display());
+
+        Ok(Self {
+            config,
+            temp_dir,
+            repo: None,
+            cancel: CancellationToken::new(),
+            clock: SystemClock::shared(),
+            timezone: Timezone::Utc,
+            http: shared_client(),
+            environments: Vec::new(),
+            disruption_risks: BTreeMap::new(),
+        })
+    }
+
+    /// Derive branch names and PR titles from `clock` instead of the system time
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Write branch names, commit messages and PR titles in `timezone`
+    pub fn with_timezone(mut self, timezone: Timezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Stop the PR workflow at the next stage once `token` is cancelled
+    ///
+    /// Nothing is pushed after cancellation; the temporary clone is removed on drop.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Fail with [`RecommenderError::Cancelled`] if the run was cancelled
+    fn check_cancelled(&self, stage: &str) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            warn!("Cancelled before {}", stage);
+            return Err(RecommenderError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Wait for the configured provider API rate limit, if any
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.config.rate_limiter {
+            limiter.acquire().await;
+        }
+    }
+
+    /// Clone the repository
+    pub fn clone_repo(&mut self, branch: &str) -> Result<()> {
+        info!("Cloning base branch: {}", branch);
+        info!("Cloning repository: {}", redact_url(&self.config.git_url));
+
+        let mut callbacks = RemoteCallbacks::new();
+
+        // Setup credentials based on connection type
+        match &self.config.connection_type {
+            GitConnectionType::Ssh => {
+                callbacks.credentials(|_url, username_from_url, _allowed_types| {
+                    if let Some(username) = username_from_url
+                        && let Ok(cred) = Cred::ssh_key_from_agent(username)
+                    {
+                        return Ok(cred);
+                    }
+                    Cred::default()
+                });
+            }
+            GitConnectionType::Https => {
+                let token = self.config.auth_token.clone();
+                let username = self.config.auth_username.clone();
+
+                callbacks.credentials(move |url_str, username_from_url, allowed_types| {
+                    // Only whether credentials are set is logged, never their values
+                    debug!(
+                        "Git credential callback for {} (allowed: {:?}, token: {}, username: {})",
+                        redact(url_str),
+                        allowed_types,
+                        if token.is_some() { "set" } else { "not set" },
+                        if username.is_some() || username_from_url.is_some() {
+                            "set"
+                        } else {
+                            "not set"
+                        },
+                    );
+
+                    if let Some(ref token) = token {
+                        // Priority: 1) CLI provided username, 2) URL username, 3) default to "git"
+                        let user = username.as_deref().or(username_from_url).unwrap_or("git");
+                        return Cred::userpass_plaintext(user, token);
+                    }
+
+                    debug!("Falling back to default credentials");
+                    Cred::default()
+                });
+            }
+        }
+
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(callbacks);
+
+        let mut builder = git2::build::RepoBuilder::new();
+        builder.fetch_options(fetch_options);
+        builder.branch(branch);
+
+        let repo = builder.clone(self.config.git_url.as_str(), self.temp_dir.path())?;
+        info!("Repository cloned successfully");
+
+        self.repo = Some(repo);
+        Ok(())
+    }
+
+    /// Deployments declared in the repository, cloning `branch` if needed
+    ///
+    /// The clone is reused by the following PR workflow.
+    pub fn list_deployments(&mut self, branch: &str) -> Result<Vec<ManifestWorkload>> {
+        if self.repo.is_none() {
+            self.clone_repo(branch)?;
+        }
+        self.editor().list_deployments()
+    }
+
+    /// Find all deployment YAML files in the repository
+    pub fn find_deployment_files(&self) -> Result<Vec<PathBuf>> {
+        self.editor().find_deployment_files()
+    }
+
+    /// Apply all recommendations
+    pub fn apply_recommendations(
+        &self,
+        recommendations: &[ResourceRecommendation],
+    ) -> Result<HashMap<String, usize>> {
+        self.editor().apply_recommendations(recommendations)
+    }
+
+    fn editor(&self) -> ManifestEditor {
+        let mut editor = ManifestEditor::new(self.temp_dir.path());
+        if let Some(sops) = &self.config.sops {
+            editor = editor.with_sops(sops.clone());
+        }
+        if let Some(overlays) = &self.config.overlays {
+            editor = editor.with_overlays(overlays.clone());
+        }
+        if let Some(helm) = &self.config.helm_values {
+            editor = editor.with_helm_values(helm.clone());
+        }
+        if self.config.hpa_targets {
+            editor = editor.with_hpa_targets();
+        }
+        editor
+    }
+
+    /// Commit changes
+    ///
+    /// Configured trailers and the sign-off are added to the last paragraph of
+    /// `message`, where a Gerrit Change-Id may already be.
+    pub fn commit_changes(&self, message: &str) -> Result<git2::Oid> {
+        let repo = self
+            .repo
+            .as_ref()
+            .ok_or_else(|| RecommenderError::ApplyError("Repository not cloned".to_string()))?;
+
+        // Add all changes
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+
+        // Create commit
+        let signature = repo.signature()?;
+        let mut trailers = self.config.trailers.clone();
+        if self.config.signoff {
+            trailers.push((
+                "Signed-off-by".to_string(),
+                format!(
+                    "{} <{}>",
+                    signature.name().unwrap_or_default(),
+                    signature.email().unwrap_or_default()
+                ),
+            ));
+        }
+        let message = append_trailers(message, &trailers);
+        let tree_id = index.write_tree()?;
+        let tree = repo.find_tree(tree_id)?;
+        let parent_commit = repo.head()?.peel_to_commit()?;
+
+        let oid = repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            &message,
+            &tree,
+            &[&parent_commit],
+        )?;
+
+        info!("Created commit: {}", oid);
+        Ok(oid)
+    }
+
+    /// Push changes to remote
+    pub fn push_changes(&self, branch: &str) -> Result<()> {
+        self.push_refspec(&format!("refs/heads/{}:refs/heads/{}", branch, branch))
+    }
+
+    /// Push `branch` to Gerrit for review against `base_branch`
+    ///
+    /// The branch name becomes the change topic, the commit needs a Change-Id trailer.
+    pub fn push_for_review(&self, branch: &str, base_branch: &str) -> Result<()> {
+        self.push_refspec(&format!(
+            "refs/heads/{}:refs/for/{}%topic={}",
+            branch, base_branch, branch
+        ))
+    }
+
+    fn push_refspec(&self, refspec: &str) -> Result<()> {
+        let repo = self
+            .repo
+            .as_ref()
+            .ok_or_else(|| RecommenderError::ApplyError("Repository not cloned".to_string()))?;
+
+        info!("Pushing changes to remote...");
+
+        let mut remote = repo.find_remote("origin")?;
+
+        let mut callbacks = RemoteCallbacks::new();
+
+        // Setup credentials based on connection type
+        match &self.config.connection_type {
+            GitConnectionType::Ssh => {
+                callbacks.credentials(|_url, username_from_url, _allowed_types| {
+                    if let Some(username) = username_from_url
+                        && let Ok(cred) = Cred::ssh_key_from_agent(username)
+                    {
+                        return Ok(cred);
+                    }
+                    Cred::default()
+                });
+            }
+            GitConnectionType::Https => {
+                let token = self.config.auth_token.clone();
+                let username = self.config.auth_username.clone();
+                callbacks.credentials(move |_url, username_from_url, _allowed_types| {
+                    if let Some(ref token) = token {
+                        // Same priority as cloning, Gerrit HTTP passwords belong to a user
+                        let user = username.as_deref().or(username_from_url).unwrap_or("git");
+                        return Cred::userpass_plaintext(user, token);
+                    }
+                    Cred::default()
+                });
+            }
+        }
+
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+
+        remote.push(&[refspec], Some(&mut push_options))?;
+
+        info!("Changes pushed successfully");
+        Ok(())
+    }
+
+    /// Create a new branch for changes
+    pub fn create_branch(&mut self, branch_name: &str) -> Result<()> {
+        let repo = self
+            .repo
+            .as_ref()
+            .ok_or_else(|| RecommenderError::ApplyError("Repository not cloned".to_string()))?;
+
+        let head = repo.head()?;
+        let commit = head.peel_to_commit()?;
+
+        repo.branch(branch_name, &commit, false)?;
+        repo.set_head(&format!("refs/heads/{}", branch_name))?;
+
+        info!("Created and switched to branch: {}", branch_name);
+
+        Ok(())
+    }
+
+    /// Get the commit SHA
+    pub fn get_commit_sha(&self) -> Result<String> {
+        let repo = self
+            .repo
+            .as_ref()
+            .ok_or_else(|| RecommenderError::ApplyError("Repository not cloned".to_string()))?;
+
+        let head = repo.head()?;
+        let oid = head
+            .target()
+            .ok_or_else(|| RecommenderError::ApplyError("Could not get HEAD target".to_string()))?;
+
+        Ok(oid.to_string())
+    }
+
+    /// Get the repository path
+    pub fn repo_path(&self) -> &Path {
+        self.temp_dir.path()
+    }
+
+    /// Complete workflow: clone, create branch, apply, commit, push, and create PR
+    /// Returns (branch_name, commit_sha, pr_url)
+    pub async fn apply_and_create_pr(
+        &mut self,
+        base_branch: &str,
+        recommendations: &[ResourceRecommendation],
+    ) -> Result<(String, String, Option<String>)> {
+        let (branch, commit_sha, pr_url, _) =
+            self.run_pr_workflow(base_branch, recommendations).await?;
+        Ok((branch, commit_sha, pr_url))
+    }
+
+    /// Same as [`ManifestUpdater::apply_and_create_pr`], also returning the updated workloads
+    pub(crate) async fn run_pr_workflow(
+        &mut self,
+        base_branch: &str,
+        recommendations: &[ResourceRecommendation],
+    ) -> Result<(String, String, Option<String>, HashMap<String, usize>)> {
+        // 1. Clone the base branch, unless already done to list deployments
+        if self.repo.is_none() {
+            info!("Cloning base branch: {}", base_branch);
+            self.clone_repo(base_branch)?;
+        }
+        self.check_cancelled("creating the branch")?;
+
+        // 2. Create new branch with timestamp
+        let timestamp = self.clock.now_in(self.timezone).format("%Y%m%d-%H%M%S");
+        let new_branch = format!("autorightsizing-{}", timestamp);
+        info!("Creating new branch: {}", new_branch);
+        self.create_branch(&new_branch)?;
+
+        // 3. Apply recommendations
+        info!("Applying recommendations...");
+        let updates = if self.config.overlays.is_some() {
+            self.environments = self.editor().apply_to_overlays(recommendations)?;
+            merge_updates(&self.environments)
+        } else {
+            self.apply_recommendations(recommendations)?
+        };
+
+        if updates.is_empty() {
+            return Err(RecommenderError::ApplyError(
+                "No matching deployments found in repository".to_string(),
+            ));
+        }
+
+        info!("Updated {} deployments", updates.len());
+        self.disruption_risks = recommendations
+            .iter()
+            .filter_map(|rec| {
+                let key = format!("{}/{}", rec.namespace, rec.deployment);
+                let risk = rec.disruption_risk.as_ref()?;
+                updates.contains_key(&key).then(|| (key, risk.to_string()))
+            })
+            .collect();
+
+        // 4. Commit changes
+        self.check_cancelled("committing")?;
+        let mut commit_message = self.generate_commit_message(&updates);
+        let change_id = match self.config.provider {
+            GitProvider::Gerrit => {
+                let change_id = self.generate_change_id(&commit_message)?;
+                commit_message.push_str(&format!("\n\nChange-Id: {}\n", change_id));
+                Some(change_id)
+            }
+            _ => None,
+        };
+        info!("Committing changes...");
+        self.commit_changes(&commit_message)?;
+
+        let commit_sha = self.get_commit_sha()?;
+        info!("Commit SHA: {}", commit_sha);
+
+        // 5. Push to remote, Gerrit creates the change from the push itself
+        self.check_cancelled("pushing")?;
+        if let Some(change_id) = change_id {
+            info!("Pushing change for review to {}...", base_branch);
+            self.push_for_review(&new_branch, base_branch)?;
+            let change_url = self.gerrit_change_url(&change_id);
+            info!("Gerrit change created: {}", change_url);
+            return Ok((new_branch, commit_sha, Some(change_url), updates));
+        }
+        info!("Pushing branch to remote...");
+        self.push_changes(&new_branch)?;
+
+        // 6. Create Pull Request
+        if self.cancel.is_cancelled() {
+            warn!(
+                "Cancelled after pushing, please create PR manually from {} to {}",
+                new_branch, base_branch
+            );
+            return Ok((new_branch, commit_sha, None, updates));
+        }
+
+        info!("Creating pull request...");
+        let pr_request = self.create_pull_request(&new_branch, base_branch, &updates);
+        let pr_result = tokio::select! {
+            _ = self.cancel.cancelled() => Err(RecommenderError::Cancelled),
+            result = pr_request => result,
+        };
+        let pr_url = match pr_result {
+            Ok(pr) => {
+                let url = pr.url.clone();
+                info!("Pull request created: {}", url);
+                if let Some(method) = self.config.auto_merge {
+                    match self.enable_auto_merge(&pr, method).await {
+                        Ok(()) => info!("Auto-merge enabled on {}", url),
+                        Err(e) => warn!("Failed to enable auto-merge on {}: {}", url, e),
+                    }
+                }
+                // The status only adds context to the merge UI, never fail the run over it
+                let description = self.status_description(&updates, recommendations);
+                if let Err(e) = self
+                    .create_commit_status(&commit_sha, &url, &description)
+                    .await
+                {
+                    warn!("Failed to post commit status: {}", e);
+                }
+                Some(url)
+            }
+            Err(e) => {
+                warn!("Failed to create pull request automatically: {}", e);
+                warn!(
+                    "Please create PR manually from {} to {}",
+                    new_branch, base_branch
+                );
+                None
+            }
+        };
+
+        Ok((new_branch, commit_sha, pr_url, updates))
+    }
+
+    /// Generate a detailed commit message
+    fn generate_commit_message(&self, updates: &HashMap<String, usize>) -> String {
+        let mut message = String::from("chore: apply resource recommendations\n\n");
+        message.push_str(&format!(
+            "Updated resource requests and limits for {} deployment(s):\n",
+            updates.len()
+        ));
+
+        if self.environments.is_empty() {
+            for deployment in updates.keys() {
+                message.push_str(&format!("  - {}\n", deployment));
+            }
+        }
+        for environment in self.environments.iter().filter(|e| !e.updated.is_empty()) {
+            message.push_str(&format!("\n{}:\n", environment.label()));
+            for deployment in environment.workloads() {
+                message.push_str(&format!("  - {}\n", deployment));
+            }
+        }
+
+        message.push_str(&format!(
+            "\nGenerated by Kubernetes Resource Recommender at {}",
+            self.clock
+                .now_in(self.timezone)
+                .format("%Y-%m-%d %H:%M:%S %:z")
+        ));
+        message
+    }
+
+    /// Gerrit Change-Id for a commit on top of HEAD, like the commit-msg hook creates
+    fn generate_change_id(&self, message: &str) -> Result<String> {
+        let repo = self
+            .repo
+            .as_ref()
+            .ok_or_else(|| RecommenderError::ApplyError("Repository not cloned".to_string()))?;
+        let parent = repo.head()?.peel_to_commit()?.id();
+
+        let seed = format!(
+            "{}\n{}\n{}",
+            parent,
+            self.clock.now_in(self.timezone).to_rfc3339(),
+            message
+        );
+        let hash = git2::Oid::hash_object(git2::ObjectType::Blob, seed.as_bytes())?;
+        Ok(format!("I{}", hash))
+    }
+
+    /// Web URL of a Gerrit change, found by its Change-Id on the repository host
+    fn gerrit_change_url(&self, change_id: &str) -> String {
+        let url = &self.config.git_url;
+        let host = url.host_str().unwrap_or_default();
+        // SSH pushes go to port 29418, the web UI is served over HTTPS
+        match url.scheme() {
+            "http" | "https" => match url.port() {
+                Some(port) => format!("{}://{}:{}/q/{}", url.scheme(), host, port, change_id),
+                None => format!("{}://{}/q/{}", url.scheme(), host, change_id),
+            },
+            _ => format!("https://{}/q/{}", host, change_id),
+        }
+    }
+
+    /// Create a Pull Request (supports multiple Git providers)
+    async fn create_pull_request(
+        &self,
+        head_branch: &str,
+        base_branch: &str,
+        updates: &HashMap<String, usize>,
+    ) -> Result<PullRequest> {
+        match &self.config.provider {
+            GitProvider::GitHub => self.create_github_pr(head_branch, base_branch, updates).await,
+            GitProvider::GitLab => self.create_gitlab_mr(head_branch, base_branch, updates).await,
+            GitProvider::Bitbucket => {
+                self.create_bitbucket_pr(head_branch, base_branch, updates)
+                    .await
+            }
+            GitProvider::Gitea => self.create_gitea_pr(head_branch, base_branch, updates).await,
+            GitProvider::Gerrit => Err(RecommenderError::ApplyError(
+                "Gerrit changes are created by pushing to refs/for, not through an API".to_string(),
+            )),
+            GitProvider::Generic => Err(RecommenderError::ApplyError(
+                "Automatic PR creation not supported for this Git provider. Please create PR manually.".to_string(),
+            )),
+        }
+    }
+
+    /// Commit status summary: updated workloads and projected request savings
+    fn status_description(
+        &self,
+        updates: &HashMap<String, usize>,
+        recommendations: &[ResourceRecommendation],
+    ) -> String {
+        let (cpu_savings, memory_savings) = recommendations
+            .iter()
+            .filter(|rec| updates.contains_key(&format!("{}/{}", rec.namespace, rec.deployment)))
+            .fold((0.0, 0.0), |(cpu, memory), rec| {
+                let savings = |current: Option<Quantity>, recommended: Quantity| {
+                    current.map_or(0.0, |c| c.value() - recommended.value())
+                };
+                (
+                    cpu + savings(rec.current_cpu_request, rec.recommended_cpu_request),
+                    memory + savings(rec.current_memory_request, rec.recommended_memory_request),
+                )
+            });
+
+        let mut description = format!(
+            "{} workload(s) changed, projected savings per replica: {:.3} CPU, {} memory",
+            updates.len(),
+            cpu_savings,
+            format_bytes(memory_savings)
+        );
+        if description.len() > STATUS_DESCRIPTION_MAX {
+            description.truncate(STATUS_DESCRIPTION_MAX);
+        }
+        description
+    }
+
+    /// Post a successful commit status linking to the PR (supports multiple Git providers)
+    async fn create_commit_status(
+        &self,
+        commit_sha: &str,
+        pr_url: &str,
+        description: &str,
+    ) -> Result<()> {
+        let (owner, repo) = self.parse_repo_owner_name()?;
+        let token = self.get_auth_token()?;
+        let api_base = self.config.provider.api_base_url(&self.config.git_url);
+
+        let request = match (&self.config.provider, api_base) {
+            // GitHub and Gitea share the commit status API
+            (GitProvider::GitHub | GitProvider::Gitea, Some(api_base)) => self
+                .http
+                .post(format!(
+                    "{}/repos/{}/{}/statuses/{}",
+                    api_base, owner, repo, commit_sha
+                ))
+                .header("Authorization", format!("token {}", token))
+                .json(&json!({
+                    "state": "success",
+                    "context": STATUS_CONTEXT,
+                    "description": description,
+                    "target_url": pr_url,
+                })),
+            (GitProvider::GitLab, Some(api_base)) => {
+                let project_path = format!("{}/{}", owner, repo);
+                self.http
+                    .post(format!(
+                        "{}/projects/{}/statuses/{}",
+                        api_base,
+                        urlencoding::encode(&project_path),
+                        commit_sha
+                    ))
+                    .header("PRIVATE-TOKEN", token)
+                    .json(&json!({
+                        "state": "success",
+                        "name": STATUS_CONTEXT,
+                        "description": description,
+                        "target_url": pr_url,
+                    }))
+            }
+            (GitProvider::Bitbucket, _) => self
+                .http
+                .post(format!(
+                    "https://api.bitbucket.org/2.0/repositories/{}/{}/commit/{}/statuses/build",
+                    owner, repo, commit_sha
+                ))
+                .header("Authorization", format!("Bearer {}", token))
+                .json(&json!({
+                    "key": STATUS_CONTEXT,
+                    "state": "SUCCESSFUL",
+                    "name": STATUS_CONTEXT,
+                    "description": description,
+                    "url": pr_url,
+                })),
+            _ => {
+                debug!("Commit statuses not supported for this Git provider");
+                return Ok(());
+            }
+        };
+
+        self.throttle().await;
+        let response = request
+            .header("User-Agent", "kubernetes-recommender")
+            .send()
+            .await
+            .map_err(|source| RecommenderError::Http {
+                context: "Failed to send commit status request".to_string(),
+                source,
+            })?;
+
+        ensure_success(response).await?;
+
+        info!("Commit status posted on {}", commit_sha);
+        Ok(())
+    }
+
+    /// Prepare PR/MR description (common across providers)
+    ///
+    /// When the repository has a PR/MR template, it is filled instead of replaced.
+    fn prepare_pr_description(&self, updates: &HashMap<String, usize>) -> String {
+        let list = |keys: Vec<&str>| {
+            keys.iter()
+                .map(|k| format!("- `{}`", k))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut changes = format!("Updated {} deployment(s):", updates.len());
+        if self.environments.is_empty() {
+            changes.push_str(&format!(
+                "\n{}",
+                list(updates.keys().map(String::as_str).collect())
+            ));
+        }
+        for environment in self.environments.iter().filter(|e| !e.updated.is_empty()) {
+            changes.push_str(&format!(
+                "\n\n#### {}\n\n{}",
+                environment.label(),
+                list(environment.workloads())
+            ));
+        }
+        if !self.disruption_risks.is_empty() {
+            changes.push_str("\n\n#### Availability\n\nThese rollouts may breach a PodDisruptionBudget, merge them when the workloads can spare the pods:\n");
+            for (workload, risk) in &self.disruption_risks {
+                changes.push_str(&format!("\n- `{}`: {}", workload, risk));
+            }
+        }
+        let intro = "This PR applies resource recommendations generated by the Kubernetes Resource Recommender.";
+        let guidelines = "- Review the resource changes for each deployment\n\
+                          - Ensure the new values are appropriate for your workload\n\
+                          - Test in a non-production environment first";
+        let footer = "---\n*Generated automatically by Kubernetes Resource Recommender*";
+
+        if let Some(template) = find_template(self.temp_dir.path(), &self.config.provider) {
+            debug!("Filling the repository's PR template");
+            let sections = [
+                DescriptionSection {
+                    title: "Automated Resource Recommendations".to_string(),
+                    body: format!("{}\n\n{}", intro, changes),
+                    keywords: &[
+                        "summary",
+                        "description",
+                        "what",
+                        "change",
+                        "overview",
+                        "context",
+                    ],
+                },
+                DescriptionSection {
+                    title: "Review Guidelines".to_string(),
+                    body: guidelines.to_string(),
+                    keywords: &["review", "test", "how", "note"],
+                },
+            ];
+            return format!("{}\n{}", fill_template(&template, &sections), footer);
+        }
+
+        format!(
+            "## Automated Resource Recommendations\n\n{}\n\n\
+             ### Changes\n\n{}\n\n\
+             ### Review Guidelines\n\n{}\n\n{}",
+            intro, changes, guidelines, footer
+        )
+    }
+
+    /// Create a GitHub Pull Request
+    async fn create_github_pr(
+        &self,
+        head_branch: &str,
+        base_branch: &str,
+        updates: &HashMap<String, usize>,
+    ) -> Result<PullRequest> {
+        let (owner, repo) = self.parse_repo_owner_name()?;
+        let token = self.get_auth_token()?;
+        let api_base = self
+            .config
+            .provider
+            .api_base_url(&self.config.git_url)
+            .ok_or_else(|| {
+                RecommenderError::ApplyError("Could not determine API base URL".to_string())
+            })?;
+
+        let client = &self.http;
+        let api_url = format!("{}/repos/{}/{}/pulls", api_base, owner, repo);
+
+        let pr_request = json!({
+            "title": format!("chore: apply resource recommendations ({})", self.clock.now_in(self.timezone).format("%Y-%m-%d")),
+            "head": head_branch,
+            "base": base_branch,
+            "body": self.prepare_pr_description(updates),
+        });
+
+        self.throttle().await;
+        let response = client
+            .post(&api_url)
+            .header("Authorization", format!("token {}", token))
+            .header("User-Agent", "kubernetes-recommender")
+            .header("Accept", "application/vnd.github.v3+json")
+            .json(&pr_request)
+            .send()
+            .await
+            .map_err(|source| RecommenderError::Http {
+                context: "Failed to send PR request".to_string(),
+                source,
+            })?;
+
+        self.handle_api_response(response, "html_url").await
+    }
+
+    /// Create a GitLab Merge Request
+    async fn create_gitlab_mr(
+        &self,
+        head_branch: &str,
+        base_branch: &str,
+        updates: &HashMap<String, usize>,
+    ) -> Result<PullRequest> {
+        let (owner, repo) = self.parse_repo_owner_name()?;
+        let token = self.get_auth_token()?;
+        let api_base = self
+            .config
+            .provider
+            .api_base_url(&self.config.git_url)
+            .ok_or_else(|| {
+                RecommenderError::ApplyError("Could not determine API base URL".to_string())
+            })?;
+
+        // GitLab uses URL-encoded project path (owner/repo -> owner%2Frepo)
+        let project_path = format!("{}/{}", owner, repo);
+        let encoded_project = urlencoding::encode(&project_path);
+
+        let client = &self.http;
+        let api_url = format!("{}/projects/{}/merge_requests", api_base, encoded_project);
+
+        let mr_request = json!({
+            "source_branch": head_branch,
+            "target_branch": base_branch,
+            "title": format!("chore: apply resource recommendations ({})", self.clock.now_in(self.timezone).format("%Y-%m-%d")),
+            "description": self.prepare_pr_description(updates),
+        });
+
+        self.throttle().await;
+        let response = client
+            .post(&api_url)
+            .header("PRIVATE-TOKEN", token)
+            .header("User-Agent", "kubernetes-recommender")
+            .json(&mr_request)
+            .send()
+            .await
+            .map_err(|source| RecommenderError::Http {
+                context: "Failed to send MR request".to_string(),
+                source,
+            })?;
+
+        self.handle_api_response(response, "web_url").await
+    }
+
+    /// Create a Bitbucket Pull Request
+    async fn create_bitbucket_pr(
+        &self,
+        head_branch: &str,
+        base_branch: &str,
+        updates: &HashMap<String, usize>,
+    ) -> Result<PullRequest> {
+        let (owner, repo) = self.parse_repo_owner_name()?;
+        let token = self.get_auth_token()?;
+
+        let client = &self.http;
+        let api_url = format!(
+            "https://api.bitbucket.org/2.0/repositories/{}/{}/pullrequests",
+            owner, repo
+        );
+
+        let pr_request = json!({
+            "title": format!("chore: apply resource recommendations ({})", self.clock.now_in(self.timezone).format("%Y-%m-%d")),
+            "source": {
+                "branch": {
+                    "name": head_branch
+                }
+            },
+            "destination": {
+                "branch": {
+                    "name": base_branch
+                }
+            },
+            "description": self.prepare_pr_description(updates),
+        });
+
+        self.throttle().await;
+        let response = client
+            .post(&api_url)
+            .header("Authorization", format!("Bearer {}", token))
+            .header("User-Agent", "kubernetes-recommender")
+            .json(&pr_request)
+            .send()
+            .await
+            .map_err(|source| RecommenderError::Http {
+                context: "Failed to send PR request".to_string(),
+                source,
+            })?;
+
+        // Bitbucket uses nested structure: links.html.href
+        let pr_response: serde_json::Value =
+            response
+                .json()
+                .await
+                .map_err(|source| RecommenderError::Http {
+                    context: "Failed to parse PR response".to_string(),
+                    source,
+                })?;
+
+        let pr_url = pr_response["links"]["html"]["href"]
+            .as_str()
+            .ok_or_else(|| RecommenderError::ApplyError("No PR URL in response".to_string()))?
+            .to_string();
+
+        Ok(PullRequest {
+            url: pr_url,
+            number: pr_response["id"].as_u64(),
+            node_id: None,
+        })
+    }
+
+    /// Create a Gitea Pull Request
+    async fn create_gitea_pr(
+        &self,
+        head_branch: &str,
+        base_branch: &str,
+        updates: &HashMap<String, usize>,
+    ) -> Result<PullRequest> {
+        let (owner, repo) = self.parse_repo_owner_name()?;
+        let token = self.get_auth_token()?;
+        let api_base = self
+            .config
+            .provider
+            .api_base_url(&self.config.git_url)
+            .ok_or_else(|| {
+                RecommenderError::ApplyError("Could not determine API base URL".to_string())
+            })?;
+
+        let client = &self.http;
+        let api_url = format!("{}/repos/{}/{}/pulls", api_base, owner, repo);
+
+        let pr_request = json!({
+            "title": format!("chore: apply resource recommendations ({})", self.clock.now_in(self.timezone).format("%Y-%m-%d")),
+            "head": head_branch,
+            "base": base_branch,
+            "body": self.prepare_pr_description(updates),
+        });
+
+        self.throttle().await;
+        let response = client
+            .post(&api_url)
+            .header("Authorization", format!("token {}", token))
+            .header("User-Agent", "kubernetes-recommender")
+            .json(&pr_request)
+            .send()
+            .await
+            .map_err(|source| RecommenderError::Http {
+                context: "Failed to send PR request".to_string(),
+                source,
+            })?;
+
+        self.handle_api_response(response, "html_url").await
+    }
+
+    /// Enable auto-merge on a freshly created PR (supports multiple Git providers)
+    async fn enable_auto_merge(&self, pr: &PullRequest, method: MergeMethod) -> Result<()> {
+        let (owner, repo) = self.parse_repo_owner_name()?;
+        let token = self.get_auth_token()?;
+        let api_base = self.config.provider.api_base_url(&self.config.git_url);
+        let missing =
+            |field: &str| RecommenderError::ApplyError(format!("No PR {} in API response", field));
+
+        let request = match (&self.config.provider, api_base) {
+            // Auto-merge is only exposed through GraphQL
+            (GitProvider::GitHub, Some(api_base)) => {
+                let node_id = pr.node_id.as_deref().ok_or_else(|| missing("node_id"))?;
+                let graphql_url = match api_base.strip_suffix("/v3") {
+                    Some(enterprise) => format!("{}/graphql", enterprise),
+                    None => format!("{}/graphql", api_base),
+                };
+                let method = match method {
+                    MergeMethod::Merge => "MERGE",
+                    MergeMethod::Squash => "SQUASH",
+                    MergeMethod::Rebase => "REBASE",
+                };
+                self.http
+                    .post(graphql_url)
+                    .header("Authorization", format!("bearer {}", token))
+                    .json(&json!({
+                        "query": "mutation($id: ID!, $method: PullRequestMergeMethod!) { \
+                                  enablePullRequestAutoMerge(input: {pullRequestId: $id, mergeMethod: $method}) \
+                                  { clientMutationId } }",
+                        "variables": { "id": node_id, "method": method },
+                    }))
+            }
+            (GitProvider::GitLab, Some(api_base)) => {
+                let iid = pr.number.ok_or_else(|| missing("iid"))?;
+                if method == MergeMethod::Rebase {
+                    warn!("GitLab rebases according to the project merge method, merging instead");
+                }
+                let project_path = format!("{}/{}", owner, repo);
+                self.http
+                    .put(format!(
+                        "{}/projects/{}/merge_requests/{}/merge",
+                        api_base,
+                        urlencoding::encode(&project_path),
+                        iid
+                    ))
+                    .header("PRIVATE-TOKEN", token)
+                    // `auto_merge` replaces `merge_when_pipeline_succeeds` on newer GitLab
+                    .json(&json!({
+                        "merge_when_pipeline_succeeds": true,
+                        "auto_merge": true,
+                        "squash": method == MergeMethod::Squash,
+                    }))
+            }
+            (GitProvider::Gitea, Some(api_base)) => {
+                let number = pr.number.ok_or_else(|| missing("number"))?;
+                let method = match method {
+                    MergeMethod::Merge => "merge",
+                    MergeMethod::Squash => "squash",
+                    MergeMethod::Rebase => "rebase",
+                };
+                self.http
+                    .post(format!(
+                        "{}/repos/{}/{}/pulls/{}/merge",
+                        api_base, owner, repo, number
+                    ))
+                    .header("Authorization", format!("token {}", token))
+                    .json(&json!({
+                        "Do": method,
+                        "merge_when_checks_succeed": true,
+                    }))
+            }
+            _ => {
+                return Err(RecommenderError::ApplyError(
+                    "Auto-merge not supported for this Git provider".to_string(),
+                ));
+            }
+        };
+
+        self.throttle().await;
+        let response = request
+            .header("User-Agent", "kubernetes-recommender")
+            .send()
+            .await
+            .map_err(|source| RecommenderError::Http {
+                context: "Failed to send auto-merge request".to_string(),
+                source,
+            })?;
+        let response = ensure_success(response).await?;
+
+        // GraphQL reports failures in the body of a successful response
+        if self.config.provider == GitProvider::GitHub {
+            let body: serde_json::Value =
+                response
+                    .json()
+                    .await
+                    .map_err(|source| RecommenderError::Http {
+                        context: "Failed to parse auto-merge response".to_string(),
+                        source,
+                    })?;
+            if let Some(message) = body["errors"][0]["message"].as_str() {
+                return Err(RecommenderError::ApplyError(message.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle API response and extract the PR
+    async fn handle_api_response(
+        &self,
+        response: reqwest::Response,
+        url_field: &str,
+    ) -> Result<PullRequest> {
+        let response = ensure_success(response).await?;
+
+        let pr_response: serde_json::Value =
+            response
+                .json()
+                .await
+                .map_err(|source| RecommenderError::Http {
+                    context: "Failed to parse API response".to_string(),
+                    source,
+                })?;
+
+        let pr_url = pr_response[url_field]
+            .as_str()
+            .ok_or_else(|| RecommenderError::ApplyError("No URL in API response".to_string()))?
+            .to_string();
+
+        Ok(PullRequest {
+            url: pr_url,
+            number: pr_response["number"]
+                .as_u64()
+                .or_else(|| pr_response["iid"].as_u64()),
+            node_id: pr_response["node_id"].as_str().map(str::to_string),
+        })
+    }
+
+    /// Get authentication token
+    fn get_auth_token(&self) -> Result<&String> {
+        self.config.auth_token.as_ref().ok_or_else(|| {
+            RecommenderError::ApplyError(
+                "Authentication token required for creating pull requests".to_string(),
+            )
+        })
+    }
+
+    /// Parse repository owner and name from git URL (generic)
+    fn parse_repo_owner_name(&self) -> Result<(String, String)> {
+        let url_str = self.config.git_url.as_str();
+
+        // Handle HTTPS URLs: https://provider.com/owner/repo.git
+        if url_str.starts_with("https://") || url_str.starts_with("http://") {
+            // Extract path after hostname
+            if let Some(host_start) = url_str.find("://") {
+                let after_protocol = &url_str[host_start + 3..];
+                if let Some(path_start) = after_protocol.find('/') {
+                    let path = &after_protocol[path_start + 1..].trim_end_matches(".git");
+
+                    let parts: Vec<&str> = path.split('/').collect();
+                    if parts.len() >= 2 {
+                        return Ok((parts[0].to_string(), parts[1].to_string()));
+                    }
+                }
+            }
+        }
+
+        // Handle SSH URLs: git@provider.com:owner/repo.git
+        if url_str.contains("git@")
+            && let Some(colon_pos) = url_str.find(':')
+        {
+            let path = &url_str[colon_pos + 1..].trim_end_matches(".git");
+
+            let parts: Vec<&str> = path.split('/').collect();
+            if parts.len() >= 2 {
+                return Ok((parts[0].to_string(), parts[1].to_string()));
+            }
+        }
+
+        Err(RecommenderError::ApplyError(format!(
+            "Could not parse owner/repo from URL: {}",
+            url_str
+        )))
+    }
+}
+
+/// Turn an unsuccessful provider API response into an error carrying its body
+async fn ensure_success(response: reqwest::Response) -> Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let error_text = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "Unknown error".to_string());
+    Err(RecommenderError::HttpStatus {
+        status: status.as_u16(),
+        message: error_text,
+    })
+}
+
+/// `message` with `trailers` added to its trailer block, skipping ones already there
+fn append_trailers(message: &str, trailers: &[(String, String)]) -> String {
+    let mut message = message.trim_end().to_string();
+    let is_trailer = |line: &str| {
+        line.split_once(": ").is_some_and(|(key, _)| {
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+    };
+    let last_paragraph = message.rsplit("\n\n").next().unwrap_or_default();
+    let mut has_block = message.contains("\n\n") && last_paragraph.lines().all(is_trailer);
+
+    for (key, value) in trailers {
+        let trailer = format!("{}: {}", key, value);
+        if has_block
+            && message
+                .rsplit("\n\n")
+                .next()
+                .unwrap_or_default()
+                .lines()
+                .any(|l| l == trailer)
+        {
+            continue;
+        }
+        message.push_str(if has_block { "\n" } else { "\n\n" });
+        message.push_str(&trailer);
+        has_block = true;
+    }
+    message.push('\n');
+    message
+}
@@ -0,0 +1,237 @@
+//! VerticalPodAutoscalers already sizing the analyzed Deployments
+//!
+//! A VPA in any update mode but `Off` rewrites pod requests itself. Changing the
+//! Deployment as well makes the two fight over the values, so such workloads are
+//! skipped, reported next to the VPA's target, or sized by bounding the VPA.
+//! `--output vpa` renders the recommendations as VPAs of their own instead.
+
+use std::collections::{BTreeMap, HashMap};
+
+use kube::Client;
+use kube::api::{Api, ApiResource, DynamicObject, GroupVersionKind, ListParams};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::KubernetesError::ApiError;
+use crate::Result;
+use crate::config::VpaUpdateMode;
+use crate::quantity::Quantity;
+use crate::recommender::{DayProfile, ResourceRecommendation};
+
+/// Update mode in which a VPA only publishes recommendations
+pub const UPDATE_MODE_OFF: &str = "Off";
+
+/// VPA targeting a Deployment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vpa {
+    pub name: String,
+    /// `spec.updatePolicy.updateMode`, `Auto` when unset
+    pub update_mode: String,
+    /// Targets in `status.recommendation`, empty before the VPA has recommended
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<VpaContainerTarget>,
+}
+
+/// Target of a VPA for one container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VpaContainerTarget {
+    pub container: String,
+    #[serde(default, with = "crate::quantity::optional")]
+    pub cpu: Option<Quantity>,
+    #[serde(default, with = "crate::quantity::optional")]
+    pub memory: Option<Quantity>,
+}
+
+/// The VPA of a recommendation's workload and its target for the container
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VpaTarget {
+    pub name: String,
+    pub update_mode: String,
+    #[serde(default, with = "crate::quantity::optional")]
+    pub cpu: Option<Quantity>,
+    #[serde(default, with = "crate::quantity::optional")]
+    pub memory: Option<Quantity>,
+}
+
+impl VpaTarget {
+    /// Whether the VPA changes pod requests rather than only recommending
+    pub fn manages(&self) -> bool {
+        self.update_mode != UPDATE_MODE_OFF
+    }
+}
+
+impl Vpa {
+    /// Whether the VPA changes pod requests rather than only recommending
+    pub fn manages(&self) -> bool {
+        self.update_mode != UPDATE_MODE_OFF
+    }
+
+    /// The VPA and its target for `container`
+    pub fn target(&self, container: &str) -> VpaTarget {
+        let target = self.targets.iter().find(|t| t.container == container);
+        VpaTarget {
+            name: self.name.clone(),
+            update_mode: self.update_mode.clone(),
+            cpu: target.and_then(|t| t.cpu),
+            memory: target.and_then(|t| t.memory),
+        }
+    }
+
+    /// Namespace, target Deployment and VPA of an `autoscaling.k8s.io` object
+    fn from_object(object: DynamicObject) -> Option<((String, String), Self)> {
+        let spec = object.data.get("spec")?;
+        let target_ref = spec.get("targetRef")?;
+        if target_ref.get("kind")?.as_str()? != "Deployment" {
+            return None;
+        }
+        let deployment = target_ref.get("name")?.as_str()?.to_string();
+        let update_mode = spec
+            .pointer("/updatePolicy/updateMode")
+            .and_then(|mode| mode.as_str())
+            .unwrap_or("Auto")
+            .to_string();
+
+        let quantity = |value: &serde_json::Value, key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .and_then(|v| Quantity::parse(v).ok())
+        };
+        let targets = object
+            .data
+            .pointer("/status/recommendation/containerRecommendations")
+            .and_then(|c| c.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|rec| {
+                let target = rec.get("target")?;
+                Some(VpaContainerTarget {
+                    container: rec.get("containerName")?.as_str()?.to_string(),
+                    cpu: quantity(target, "cpu"),
+                    memory: quantity(target, "memory"),
+                })
+            })
+            .collect();
+
+        Some((
+            (object.metadata.namespace?, deployment),
+            Self {
+                name: object.metadata.name?,
+                update_mode,
+                targets,
+            },
+        ))
+    }
+}
+
+/// API resource of `autoscaling.k8s.io/v1` VerticalPodAutoscalers
+pub fn vpa_resource() -> ApiResource {
+    ApiResource::from_gvk(&GroupVersionKind::gvk(
+        "autoscaling.k8s.io",
+        "v1",
+        "VerticalPodAutoscaler",
+    ))
+}
+
+/// VPAs keyed by the namespace and name of the Deployment they target, empty
+/// when the VPA CRDs aren't installed
+pub async fn list_vpas(
+    client: Client,
+    namespace: Option<&str>,
+) -> Result<HashMap<(String, String), Vpa>> {
+    let resource = vpa_resource();
+    let api: Api<DynamicObject> = match namespace {
+        Some(namespace) => Api::namespaced_with(client, namespace, &resource),
+        None => Api::all_with(client, &resource),
+    };
+    let vpas = match api.list(&ListParams::default()).await {
+        Ok(vpas) => vpas,
+        Err(kube::Error::Api(response)) if response.code == 404 => {
+            debug!("No VerticalPodAutoscaler resource, VPA not installed");
+            return Ok(HashMap::new());
+        }
+        Err(e) => return Err(ApiError(e).into()),
+    };
+
+    let vpas: HashMap<_, _> = vpas
+        .items
+        .into_iter()
+        .filter_map(Vpa::from_object)
+        .collect();
+    info!(
+        "Found {} VerticalPodAutoscalers targeting deployments",
+        vpas.len()
+    );
+    Ok(vpas)
+}
+
+/// Multi-document YAML with one VPA per recommended Deployment, ready for `kubectl apply`
+///
+/// Each VPA is named `<deployment>-rightsizing` and bounds its containers like
+/// `--vpa update-vpa` does: the recommended requests are `minAllowed` and the
+/// recommended limits `maxAllowed`, so the VPA's own target stays within them.
+/// With `--day-split both` only the weekday recommendations are rendered.
+pub fn vpa_manifests(
+    recommendations: &[ResourceRecommendation],
+    update_mode: VpaUpdateMode,
+) -> Result<String> {
+    let mut deployments: BTreeMap<(&str, &str), Vec<&ResourceRecommendation>> = BTreeMap::new();
+    for recommendation in recommendations {
+        if recommendation.day_profile == Some(DayProfile::Weekend) {
+            continue;
+        }
+        deployments
+            .entry((
+                recommendation.namespace.as_str(),
+                recommendation.deployment.as_str(),
+            ))
+            .or_default()
+            .push(recommendation);
+    }
+
+    let documents = deployments
+        .into_iter()
+        .map(|((namespace, deployment), containers)| {
+            let kind = containers[0].workload_kind;
+            let policies: Vec<serde_json::Value> = containers
+                .into_iter()
+                .map(|recommendation| {
+                    let mut recommendation = recommendation.clone();
+                    // maxAllowed below minAllowed is rejected by the VPA
+                    recommendation.enforce_limits_above_requests();
+                    json!({
+                        "containerName": recommendation.container,
+                        "minAllowed": {
+                            "cpu": recommendation.recommended_cpu_request.to_string(),
+                            "memory": recommendation.recommended_memory_request.to_string(),
+                        },
+                        "maxAllowed": {
+                            "cpu": recommendation.recommended_cpu_limit.to_string(),
+                            "memory": recommendation.recommended_memory_limit.to_string(),
+                        },
+                    })
+                })
+                .collect();
+            let vpa = json!({
+                "apiVersion": "autoscaling.k8s.io/v1",
+                "kind": "VerticalPodAutoscaler",
+                "metadata": {
+                    "name": format!("{}-rightsizing", deployment),
+                    "namespace": namespace,
+                },
+                "spec": {
+                    "targetRef": {
+                        "apiVersion": kind.api_version(),
+                        "kind": kind.to_string(),
+                        "name": deployment,
+                    },
+                    "updatePolicy": { "updateMode": update_mode.as_str() },
+                    "resourcePolicy": { "containerPolicies": policies },
+                },
+            });
+            serde_yaml::to_string(&vpa)
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(documents.join("---\n"))
+}
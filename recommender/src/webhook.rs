@@ -0,0 +1,510 @@
+//! Receiving deployment webhooks that trigger a re-analysis
+//!
+//! `POST /webhook` accepts ArgoCD sync notifications, GitHub `deployment_status`
+//! events and plain `{"namespace": "..."}` bodies. Each accepted event schedules
+//! an analysis of its namespace once the soak period has passed, so the new
+//! release has produced some usage data; further events for the same namespace
+//! restart the soak. `GET /healthz` answers probes.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::AUTHORIZATION;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{HeaderMap, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use log::{debug, error, info, warn};
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::{RecommenderError, Result};
+
+/// Default time between a deployment and its re-analysis
+pub const DEFAULT_SOAK: Duration = Duration::from_secs(600);
+
+/// Larger bodies are rejected, ArgoCD applications stay far below this
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Where and how webhooks are received
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub listen: SocketAddr,
+    /// GitHub webhook secret, or the bearer token other senders must present
+    pub secret: Option<String>,
+    /// Wait after a deployment before analyzing its namespace
+    pub soak: Duration,
+}
+
+impl WebhookConfig {
+    pub fn new(listen: SocketAddr) -> Self {
+        Self {
+            listen,
+            secret: None,
+            soak: DEFAULT_SOAK,
+        }
+    }
+
+    /// Only accept webhooks signed with or carrying `secret`
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Analyze a namespace `soak` after its last deployment
+    pub fn with_soak(mut self, soak: Duration) -> Self {
+        self.soak = soak;
+        self
+    }
+}
+
+/// A finished deployment reported by a webhook
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeploymentEvent {
+    pub namespace: String,
+    /// Sender, for logging: "ArgoCD", "GitHub" or "webhook"
+    pub source: &'static str,
+}
+
+/// The deployment a webhook body reports, None for events that don't trigger anything
+///
+/// `github_event` is the `X-GitHub-Event` header. GitHub deployments name their
+/// namespace in `payload.namespace`, falling back to the environment name.
+pub fn parse_event(github_event: Option<&str>, body: &[u8]) -> Result<Option<DeploymentEvent>> {
+    let payload: Value = serde_json::from_slice(body)
+        .map_err(|e| RecommenderError::Parse(format!("Webhook body: {}", e)))?;
+
+    let event = match github_event {
+        Some("deployment_status") => {
+            if payload["deployment_status"]["state"].as_str() != Some("success") {
+                return Ok(None);
+            }
+            let deployment = &payload["deployment"];
+            // The deployment payload may be sent as an object or a JSON string
+            let custom = match &deployment["payload"] {
+                Value::String(text) => serde_json::from_str(text).unwrap_or_default(),
+                value => value.clone(),
+            };
+            let namespace = custom["namespace"]
+                .as_str()
+                .or_else(|| deployment["environment"].as_str());
+            (namespace.map(str::to_string), "GitHub")
+        }
+        // ping, push and everything else
+        Some(_) => return Ok(None),
+        None => {
+            // argocd-notifications bodies either are the Application or wrap it in `app`
+            let app = payload.get("app").unwrap_or(&payload);
+            if app["kind"] == "Application" || app["spec"]["destination"].is_object() {
+                match app["status"]["operationState"]["phase"].as_str() {
+                    None | Some("Succeeded") => {}
+                    Some(_) => return Ok(None),
+                }
+                let namespace = app["spec"]["destination"]["namespace"].as_str();
+                (namespace.map(str::to_string), "ArgoCD")
+            } else {
+                (payload["namespace"].as_str().map(str::to_string), "webhook")
+            }
+        }
+    };
+
+    match event {
+        (Some(namespace), source) if is_namespace_name(&namespace) => {
+            Ok(Some(DeploymentEvent { namespace, source }))
+        }
+        (Some(namespace), source) => Err(RecommenderError::Parse(format!(
+            "{} webhook names invalid namespace {:?}",
+            source, namespace
+        ))),
+        (None, source) => Err(RecommenderError::Parse(format!(
+            "{} webhook body has no namespace",
+            source
+        ))),
+    }
+}
+
+/// Receives webhooks and runs the scheduled analyses one at a time
+pub struct WebhookServer {
+    config: WebhookConfig,
+}
+
+impl WebhookServer {
+    pub fn new(config: WebhookConfig) -> Self {
+        Self { config }
+    }
+
+    /// Serve until `cancel` fires, calling `analyze` with each due namespace
+    ///
+    /// Failed analyses are logged and the server keeps running.
+    pub async fn run<F, Fut>(self, cancel: CancellationToken, mut analyze: F) -> Result<()>
+    where
+        F: FnMut(String) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let listener = TcpListener::bind(self.config.listen).await?;
+        info!("Listening for webhooks on {}", self.config.listen);
+        if self.config.secret.is_none() {
+            warn!("No webhook secret set, anyone reaching the listener can trigger analyses");
+        }
+
+        let (events, mut received) = mpsc::unbounded_channel();
+        let state = Arc::new(ServerState {
+            secret: self.config.secret.clone(),
+            events,
+        });
+        tokio::spawn(accept_loop(listener, state, cancel.clone()));
+
+        let mut due: HashMap<String, Instant> = HashMap::new();
+        loop {
+            let next = due
+                .iter()
+                .min_by_key(|(_, at)| **at)
+                .map(|(namespace, at)| (namespace.clone(), *at));
+            let wait = async {
+                match &next {
+                    Some((_, at)) => tokio::time::sleep_until(*at).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                Some(event) = received.recv() => {
+                    info!(
+                        "{} deployment in {}, analyzing in {}s",
+                        event.source,
+                        event.namespace,
+                        self.config.soak.as_secs()
+                    );
+                    due.insert(event.namespace, Instant::now() + self.config.soak);
+                }
+                _ = wait => {
+                    let Some((namespace, _)) = next else { continue };
+                    due.remove(&namespace);
+                    info!("Re-analyzing namespace {}", namespace);
+                    match analyze(namespace.clone()).await {
+                        Ok(()) => {}
+                        Err(RecommenderError::Cancelled) => break,
+                        Err(e) => error!("Analysis of namespace {} failed: {}", namespace, e),
+                    }
+                }
+            }
+        }
+
+        if !due.is_empty() {
+            warn!("Shutting down with {} analyses still soaking", due.len());
+        }
+        Ok(())
+    }
+}
+
+struct ServerState {
+    secret: Option<String>,
+    events: mpsc::UnboundedSender<DeploymentEvent>,
+}
+
+async fn accept_loop(listener: TcpListener, state: Arc<ServerState>, cancel: CancellationToken) {
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = cancel.cancelled() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept webhook connection: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let state = state.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(request, state.clone()));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                debug!("Webhook connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle(
+    request: Request<Incoming>,
+    state: Arc<ServerState>,
+) -> std::result::Result<Response<Full<Bytes>>, Infallible> {
+    let reply = |status: StatusCode, text: &str| {
+        let mut response = Response::new(Full::new(Bytes::from(format!("{}\n", text))));
+        *response.status_mut() = status;
+        Ok(response)
+    };
+
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/healthz") => return reply(StatusCode::OK, "ok"),
+        (&Method::POST, "/webhook") => {}
+        (_, "/webhook") => return reply(StatusCode::METHOD_NOT_ALLOWED, "use POST"),
+        _ => return reply(StatusCode::NOT_FOUND, "not found"),
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = match Limited::new(body, MAX_BODY_BYTES).collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(_) => return reply(StatusCode::PAYLOAD_TOO_LARGE, "body too large"),
+    };
+
+    if let Some(secret) = &state.secret
+        && !authenticate(&parts.headers, &body, secret)
+    {
+        warn!("Rejected webhook with a missing or wrong signature");
+        return reply(StatusCode::UNAUTHORIZED, "invalid signature or token");
+    }
+
+    let github_event = parts
+        .headers
+        .get("x-github-event")
+        .and_then(|v| v.to_str().ok());
+    match parse_event(github_event, &body) {
+        Ok(Some(event)) => {
+            let namespace = event.namespace.clone();
+            if state.events.send(event).is_err() {
+                return reply(StatusCode::SERVICE_UNAVAILABLE, "shutting down");
+            }
+            reply(
+                StatusCode::ACCEPTED,
+                &format!("analysis of {} scheduled", namespace),
+            )
+        }
+        Ok(None) => reply(StatusCode::OK, "ignored"),
+        Err(e) => {
+            warn!("Rejected webhook: {}", e);
+            reply(StatusCode::BAD_REQUEST, &e.to_string())
+        }
+    }
+}
+
+/// GitHub signs the body with the secret, other senders present it as bearer token
+fn authenticate(headers: &HeaderMap, body: &[u8], secret: &str) -> bool {
+    let mac = |value: &[u8]| {
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+        mac.update(value);
+        mac
+    };
+
+    if let Some(signature) = headers.get("x-hub-signature-256") {
+        return signature
+            .to_str()
+            .ok()
+            .and_then(|s| s.strip_prefix("sha256="))
+            .and_then(|s| hex::decode(s).ok())
+            .is_some_and(|signature| mac(body).verify_slice(&signature).is_ok());
+    }
+
+    // Comparing MACs of both tokens keeps the comparison constant-time
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| {
+            mac(token.as_bytes())
+                .verify_slice(&mac(secret.as_bytes()).finalize().into_bytes())
+                .is_ok()
+        })
+}
+
+/// Kubernetes namespace names are DNS labels, anything else never reaches a query
+fn is_namespace_name(name: &str) -> bool {
+    (1..=63).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    const SECRET: &str = "s3cret";
+
+    fn signed(body: &[u8]) -> HeaderMap {
+        let mut mac = HmacSha256::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-hub-signature-256",
+            HeaderValue::from_str(&signature).unwrap(),
+        );
+        headers
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        );
+        headers
+    }
+
+    fn event(namespace: &str, source: &'static str) -> Option<DeploymentEvent> {
+        Some(DeploymentEvent {
+            namespace: namespace.to_string(),
+            source,
+        })
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let body = br#"{"namespace":"shop"}"#;
+        assert!(authenticate(&signed(body), body, SECRET));
+    }
+
+    #[test]
+    fn rejects_tampered_body() {
+        let headers = signed(br#"{"namespace":"shop"}"#);
+        assert!(!authenticate(
+            &headers,
+            br#"{"namespace":"kube-system"}"#,
+            SECRET
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_signature() {
+        let body = br#"{"namespace":"shop"}"#;
+        for signature in ["", "sha256=", "sha256=zz", "sha1=00", "deadbeef"] {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-hub-signature-256", HeaderValue::from_static(signature));
+            assert!(!authenticate(&headers, body, SECRET), "{:?}", signature);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_credentials() {
+        assert!(!authenticate(&HeaderMap::new(), b"{}", SECRET));
+    }
+
+    #[test]
+    fn checks_bearer_token() {
+        assert!(authenticate(&bearer(SECRET), b"{}", SECRET));
+        assert!(!authenticate(&bearer("wrong"), b"{}", SECRET));
+        assert!(!authenticate(&bearer(""), b"{}", SECRET));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Basic czNjcmV0"));
+        assert!(!authenticate(&headers, b"{}", SECRET));
+    }
+
+    #[test]
+    fn parses_plain_namespace() {
+        let parsed = parse_event(None, br#"{"namespace":"shop"}"#).unwrap();
+        assert_eq!(parsed, event("shop", "webhook"));
+    }
+
+    #[test]
+    fn parses_argocd_application() {
+        let app = br#"{
+            "kind": "Application",
+            "spec": {"destination": {"namespace": "shop"}},
+            "status": {"operationState": {"phase": "Succeeded"}}
+        }"#;
+        assert_eq!(parse_event(None, app).unwrap(), event("shop", "ArgoCD"));
+
+        // argocd-notifications templates wrapping it in `app`, without a phase yet
+        let wrapped = br#"{"app": {"spec": {"destination": {"namespace": "billing"}}}}"#;
+        assert_eq!(
+            parse_event(None, wrapped).unwrap(),
+            event("billing", "ArgoCD")
+        );
+
+        let failed = br#"{
+            "kind": "Application",
+            "spec": {"destination": {"namespace": "shop"}},
+            "status": {"operationState": {"phase": "Failed"}}
+        }"#;
+        assert_eq!(parse_event(None, failed).unwrap(), None);
+    }
+
+    #[test]
+    fn parses_github_deployment_status() {
+        let object = br#"{
+            "deployment_status": {"state": "success"},
+            "deployment": {"environment": "production", "payload": {"namespace": "shop"}}
+        }"#;
+        assert_eq!(
+            parse_event(Some("deployment_status"), object).unwrap(),
+            event("shop", "GitHub")
+        );
+
+        let string = br#"{
+            "deployment_status": {"state": "success"},
+            "deployment": {"environment": "production", "payload": "{\"namespace\":\"shop\"}"}
+        }"#;
+        assert_eq!(
+            parse_event(Some("deployment_status"), string).unwrap(),
+            event("shop", "GitHub")
+        );
+
+        let environment = br#"{
+            "deployment_status": {"state": "success"},
+            "deployment": {"environment": "staging", "payload": {}}
+        }"#;
+        assert_eq!(
+            parse_event(Some("deployment_status"), environment).unwrap(),
+            event("staging", "GitHub")
+        );
+
+        let pending = br#"{"deployment_status": {"state": "pending"}, "deployment": {}}"#;
+        assert_eq!(
+            parse_event(Some("deployment_status"), pending).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn ignores_other_github_events() {
+        assert_eq!(parse_event(Some("ping"), br#"{"zen":"hi"}"#).unwrap(), None);
+        assert_eq!(
+            parse_event(Some("push"), br#"{"namespace":"shop"}"#).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_bodies() {
+        assert!(parse_event(None, b"not json").is_err());
+        assert!(parse_event(None, br#"{"name":"shop"}"#).is_err());
+        for namespace in [
+            "",
+            "Shop",
+            "shop/web",
+            "-shop",
+            "kube_system",
+            &"a".repeat(64),
+        ] {
+            let body = serde_json::json!({ "namespace": namespace }).to_string();
+            assert!(
+                parse_event(None, body.as_bytes()).is_err(),
+                "{:?}",
+                namespace
+            );
+        }
+    }
+}